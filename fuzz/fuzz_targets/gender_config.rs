@@ -0,0 +1,14 @@
+//! A gender configuration file must parse or be rejected with an error,
+//! whatever it contains, and what parses must be valid.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use peoplegen::gender_config::parse_gender_config;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(entries) = parse_gender_config(data) {
+        assert!(!entries.is_empty());
+        assert_eq!(entries.iter().map(|e| e.percent as u64).sum::<u64>(), 100);
+    }
+});
@@ -0,0 +1,17 @@
+//! Fixed-width layouts, from `--layout` or `--layout-file`, must parse or be
+//! rejected with an error, whatever they contain.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use peoplegen::fixed::{parse_layout, parse_layout_file};
+
+const FIELDS: [&str; 4] = ["id", "first_name", "last_name", "salary"];
+
+fuzz_target!(|s: &str| {
+    let _ = parse_layout(s, &FIELDS);
+
+    if let Ok(layout) = parse_layout_file(s, &FIELDS) {
+        assert!(layout.iter().all(|f| f.width > 0));
+    }
+});
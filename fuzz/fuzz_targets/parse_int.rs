@@ -0,0 +1,17 @@
+//! `numlib::parse_int()` must return an error, not panic, for any string and
+//! radix.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use peoplegen::numlib::parse_int;
+
+fuzz_target!(|input: (u8, &str)| {
+    let (radix, s) = input;
+    let s = s.to_string();
+    let radix = radix as u32;
+
+    let _ = parse_int::<u8>(&s, radix);
+    let _ = parse_int::<u32>(&s, radix);
+    let _ = parse_int::<i64>(&s, radix);
+});
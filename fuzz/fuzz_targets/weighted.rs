@@ -0,0 +1,15 @@
+//! A weighted value file must parse or be rejected with an error, whatever
+//! it contains, and what parses must be valid.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use peoplegen::weighted::WeightedValues;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(values) = WeightedValues::parse(data) {
+        assert!(!values.is_empty());
+        let total: f64 = (0..values.len()).map(|i| values.probability(i)).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+    }
+});
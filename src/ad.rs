@@ -0,0 +1,156 @@
+//! Active Directory-related helpers, used by the `ad` header format preset.
+//!
+//! The preset maps the generated fields onto the usual Active Directory
+//! attribute names (`givenName`, `sn`, `employeeID`, etc.), and it adds two
+//! attributes that are derived from each person's name: a logon name
+//! (`sAMAccountName`) and a user principal name (`userPrincipalName`). Values
+//! are truncated to the limits Active Directory enforces, and logon names are
+//! guaranteed to be unique within a run.
+
+use std::collections::HashSet;
+
+/// The maximum length of an `sAMAccountName` value.
+pub const MAX_ACCOUNT_NAME: usize = 20;
+
+/// The maximum length of a `givenName` value.
+pub const MAX_GIVEN_NAME: usize = 64;
+
+/// The maximum length of a `middleName` value.
+pub const MAX_MIDDLE_NAME: usize = 64;
+
+/// The maximum length of an `sn` (surname) value.
+pub const MAX_SURNAME: usize = 64;
+
+/// The maximum length of an `employeeID` value.
+pub const MAX_EMPLOYEE_ID: usize = 16;
+
+/// The maximum length of a `userPrincipalName` value.
+pub const MAX_USER_PRINCIPAL_NAME: usize = 1024;
+
+/// The default domain used to build user principal names.
+pub const DEFAULT_DOMAIN: &str = "example.com";
+
+/**
+ * Allocates unique `sAMAccountName` values. Names are built from the first
+ * initial and the last name (e.g., "jsmith"), lowercased and stripped of
+ * characters Active Directory doesn't allow. If a name has already been
+ * handed out, a numeric suffix is appended ("jsmith2", "jsmith3", ...),
+ * truncating the base name as necessary to stay within the length limit.
+ */
+pub struct AccountNames {
+    used: HashSet<String>,
+}
+
+impl AccountNames {
+    /// Create a new, empty allocator.
+    pub fn new() -> Self {
+        Self { used: HashSet::new() }
+    }
+
+    /**
+     * Allocate a unique account name for a person.
+     *
+     * # Arguments
+     *
+     * - `first_name`: The person's first name
+     * - `last_name`: The person's last name
+     *
+     * # Returns
+     *
+     * The allocated account name, which is guaranteed not to have been
+     * returned before by this allocator.
+     */
+    pub fn allocate(&mut self, first_name: &str, last_name: &str) -> String {
+        let base = account_name_base(first_name, last_name);
+        let mut candidate = base.clone();
+        let mut n: u64 = 1;
+
+        while self.used.contains(&candidate) {
+            n += 1;
+            let suffix = n.to_string();
+            let keep = MAX_ACCOUNT_NAME - suffix.len();
+            candidate = format!("{}{}", truncate(&base, keep), suffix);
+        }
+
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+impl Default for AccountNames {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+ * Build a user principal name from an account name and a domain.
+ */
+pub fn user_principal_name(account_name: &str, domain: &str) -> String {
+    truncate(&format!("{account_name}@{domain}"), MAX_USER_PRINCIPAL_NAME)
+}
+
+/**
+ * Truncate a string to at most `max` characters (not bytes).
+ */
+pub fn truncate(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+/// Build the non-unique base of an account name.
+fn account_name_base(first_name: &str, last_name: &str) -> String {
+    let clean = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+
+    let initial: String = clean(first_name).chars().take(1).collect();
+    let base = truncate(&format!("{}{}", initial, clean(last_name)),
+                        MAX_ACCOUNT_NAME);
+
+    if base.is_empty() {
+        String::from("user")
+    }
+    else {
+        base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ad::*;
+
+    #[test]
+    fn simple_account_names() {
+        let mut names = AccountNames::new();
+        assert_eq!(names.allocate("John", "Smith"), "jsmith");
+        assert_eq!(names.allocate("Shannon", "O'Neill"), "soneill");
+    }
+
+    #[test]
+    fn duplicate_account_names() {
+        let mut names = AccountNames::new();
+        assert_eq!(names.allocate("John", "Smith"), "jsmith");
+        assert_eq!(names.allocate("Jane", "Smith"), "jsmith2");
+        assert_eq!(names.allocate("Jim", "Smith"), "jsmith3");
+    }
+
+    #[test]
+    fn long_account_names() {
+        let mut names = AccountNames::new();
+        let n1 = names.allocate("Mary", "Wolfeschlegelsteinhausen");
+        let n2 = names.allocate("Mark", "Wolfeschlegelsteinhausen");
+        assert_eq!(n1, "mwolfeschlegelsteinh");
+        assert_eq!(n2, "mwolfeschlegelstein2");
+        assert!(n1.len() <= MAX_ACCOUNT_NAME);
+        assert!(n2.len() <= MAX_ACCOUNT_NAME);
+    }
+
+    #[test]
+    fn principal_names() {
+        assert_eq!(user_principal_name("jsmith", "example.com"),
+                   "jsmith@example.com");
+    }
+}
@@ -0,0 +1,223 @@
+//! Aggregate tables: summaries of the people, computed as they're generated,
+//! and written alongside the output, so a BI demo gets both a fact table and
+//! summary tables from one run. There are two, both CSV:
+//!
+//! - `gender_by_decade.csv`: how many people of each gender were born in
+//!   each decade (`gender,birth_decade,count`)
+//! - `salary_quantiles.csv`, with `--salary`: the salary quantiles for each
+//!   gender (`gender,count,min,p10,p25,median,p75,p90,max`)
+//!
+//! The quantiles are exact: each group keeps a count per distinct salary,
+//! which stays small, since salaries are whole (and usually rounded)
+//! numbers. Like assertions, the tables see the values as the rules leave
+//! them, before formatting, hashing or encryption.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use csv::WriterBuilder;
+
+use crate::path::path_str;
+
+/// The file holding the counts by gender and birth decade.
+pub const GENDER_BY_DECADE_FILE: &str = "gender_by_decade.csv";
+
+/// The file holding the salary quantiles by gender.
+pub const SALARY_QUANTILES_FILE: &str = "salary_quantiles.csv";
+
+/// The salary quantiles, as percentiles, and their column names.
+const QUANTILES: [(u32, &str); 7] = [
+    (0, "min"),
+    (10, "p10"),
+    (25, "p25"),
+    (50, "median"),
+    (75, "p75"),
+    (90, "p90"),
+    (100, "max"),
+];
+
+/// The aggregates of the people generated so far.
+#[derive(Debug, Default)]
+pub struct Aggregates {
+    /// How many people there are of each gender and birth decade
+    by_decade: BTreeMap<(String, String), u64>,
+    /// How many people of each gender have each salary, if salaries are
+    /// being written
+    salaries: Option<BTreeMap<String, BTreeMap<u64, u64>>>,
+}
+
+impl Aggregates {
+    /**
+     * Start the aggregates, with no people yet.
+     *
+     * # Arguments
+     *
+     * - `salaries`: Whether salaries are being written, so there's a salary
+     *   table
+     */
+    pub fn new(salaries: bool) -> Aggregates {
+        Aggregates { by_decade: BTreeMap::new(), salaries: salaries.then(BTreeMap::new) }
+    }
+
+    /**
+     * Add a person to the aggregates.
+     *
+     * # Arguments
+     *
+     * - `value_of`: Gets the value of any of the person's fields
+     */
+    pub fn observe<F: Fn(&str) -> String>(&mut self, value_of: F) {
+        let gender = value_of("gender");
+        *self.by_decade.entry((gender.clone(), decade(&value_of("birth_date")))).or_default() += 1;
+
+        // A salary a rule replaced with something that isn't one is left out.
+        if let (Some(salaries), Ok(salary)) = (&mut self.salaries, value_of("salary").parse::<u64>()) {
+            *salaries.entry(gender).or_default().entry(salary).or_default() += 1;
+        }
+    }
+
+    /**
+     * Write the tables.
+     *
+     * # Arguments
+     *
+     * - `path`: Where to write each table, given its file name, e.g., where
+     *   it's staged
+     *
+     * # Returns
+     *
+     * - `Ok(count)`: How many tables were written
+     * - `Err(msg)`: A table couldn't be written; `msg` explains why.
+     */
+    pub fn write<P: FnMut(&str) -> PathBuf>(&self, mut path: P) -> Result<usize, String> {
+        let rows = self.by_decade
+            .iter()
+            .map(|((gender, decade), count)| vec![gender.clone(), decade.clone(), count.to_string()]);
+        write_table(&path(GENDER_BY_DECADE_FILE), &["gender", "birth_decade", "count"], rows)?;
+
+        let Some(salaries) = &self.salaries else {
+            return Ok(1);
+        };
+
+        let mut header = vec!["gender", "count"];
+        header.extend(QUANTILES.iter().map(|(_, name)| name));
+        let rows = salaries.iter().map(|(gender, counts)| {
+            let total: u64 = counts.values().sum();
+            let mut row = vec![gender.clone(), total.to_string()];
+            row.extend(QUANTILES.iter().map(|(p, _)| quantile(counts, total, *p).to_string()));
+            row
+        });
+        write_table(&path(SALARY_QUANTILES_FILE), &header, rows)?;
+
+        Ok(2)
+    }
+}
+
+/// Get the decade of a date, e.g., "1960" for "1966-11-17", or "" if it
+/// doesn't start with a year.
+fn decade(date: &str) -> String {
+    match date.get(..4).filter(|year| year.bytes().all(|b| b.is_ascii_digit())) {
+        Some(year) => format!("{}0", &year[..3]),
+        None => String::new(),
+    }
+}
+
+/**
+ * Get a percentile of some counted values, by the nearest-rank method: the
+ * smallest value that at least `percent`% of the values are at or below.
+ *
+ * # Arguments
+ *
+ * - `counts`: How many times each value occurs
+ * - `total`: The total of the counts, at least 1
+ * - `percent`: The percentile, from 0 (the minimum) to 100 (the maximum)
+ */
+pub fn quantile(counts: &BTreeMap<u64, u64>, total: u64, percent: u32) -> u64 {
+    // Hundreds, then the rest, so a huge total doesn't overflow.
+    let rank = (total / 100 * u64::from(percent) + (total % 100 * u64::from(percent)).div_ceil(100)).max(1);
+    let mut seen = 0;
+    for (value, count) in counts {
+        seen += count;
+        if seen >= rank {
+            return *value;
+        }
+    }
+    counts.keys().next_back().copied().unwrap_or_default()
+}
+
+/// Write a CSV table.
+fn write_table<I: Iterator<Item = Vec<String>>>(path: &Path, header: &[&str], rows: I) -> Result<(), String> {
+    let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let mut w = WriterBuilder::new().from_path(path).map_err(write_err)?;
+
+    w.write_record(header).map_err(write_err)?;
+    for row in rows {
+        w.write_record(&row).map_err(write_err)?;
+    }
+
+    w.flush().map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregates::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    /// Aggregate people given as (gender, birth date, salary), and get the
+    /// tables.
+    fn aggregate(people: &[(&str, &str, &str)], salaries: bool) -> (String, Option<String>) {
+        let mut aggregates = Aggregates::new(salaries);
+        for (gender, birth_date, salary) in people {
+            let person = HashMap::from([("gender", gender), ("birth_date", birth_date), ("salary", salary)]);
+            aggregates.observe(|key| person[key].to_string());
+        }
+
+        let dir = TempDir::new().unwrap();
+        let count = aggregates.write(|name| dir.path().join(name)).unwrap();
+        assert_eq!(count, if salaries { 2 } else { 1 });
+
+        let read = |name| std::fs::read_to_string(dir.path().join(name)).ok();
+        (read(GENDER_BY_DECADE_FILE).unwrap(), read(SALARY_QUANTILES_FILE))
+    }
+
+    #[test]
+    fn by_decade() {
+        let people = [
+            ("M", "1966-11-17", "50000"),
+            ("F", "1960-01-01", "60000"),
+            ("M", "1969-12-31", "70000"),
+            ("M", "1974-08-29", "80000"),
+            ("F", "", "90000"),
+        ];
+        let (by_decade, salaries) = aggregate(&people, false);
+
+        assert_eq!(by_decade, "gender,birth_decade,count\nF,,1\nF,1960,1\nM,1960,2\nM,1970,1\n");
+        assert_eq!(salaries, None);
+    }
+
+    #[test]
+    fn huge_quantiles() {
+        let counts = BTreeMap::from([(1, u64::MAX / 2), (2, u64::MAX / 2 + 1)]);
+        assert_eq!(quantile(&counts, u64::MAX, 0), 1);
+        assert_eq!(quantile(&counts, u64::MAX, 50), 2);
+        assert_eq!(quantile(&counts, u64::MAX, 49), 1);
+        assert_eq!(quantile(&counts, u64::MAX, 100), 2);
+    }
+
+    #[test]
+    fn salary_quantiles() {
+        let salaries: Vec<String> = (1..=20).map(|n| (n * 1000).to_string()).collect();
+        let mut people: Vec<(&str, &str, &str)> = salaries.iter().map(|s| ("F", "1980-01-01", s.as_str())).collect();
+        people.push(("M", "1980-01-01", "40000"));
+        people.push(("M", "1980-01-01", "not a salary"));
+        let (_, salaries) = aggregate(&people, true);
+
+        assert_eq!(
+            salaries.unwrap(),
+            "gender,count,min,p10,p25,median,p75,p90,max\n\
+             F,20,1000,2000,5000,10000,15000,18000,20000\n\
+             M,1,40000,40000,40000,40000,40000,40000,40000\n"
+        );
+    }
+}
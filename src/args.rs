@@ -1,45 +1,174 @@
 //! All things command-line for `peoplegen`, including the argument parser.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use clap::{Command, Arg, ArgAction};
-use chrono::{Duration, Utc, Datelike};
-use crate::path::{path_is_empty, file_extension};
+use clap::{Command, Arg, ArgAction, ArgMatches};
+use clap::parser::ValueSource;
+use thousands::Separable;
+use chrono::{Duration, NaiveDate, Utc, Datelike};
+use crate::path::{path_is_empty, path_str, file_extension};
+use crate::dirs::{config_dir, find_file};
 use crate::env::getenv;
+use crate::fetch::{default_names_dir, default_names_file, FEMALE_FIRST_NAMES_FILE,
+                   LAST_NAMES_FILE, MALE_FIRST_NAMES_FILE};
+use crate::ad;
+use crate::barcode::BarcodeSource;
+use crate::output::{is_null, is_stdout, split_compression, Compression, NULL_PATH, STDOUT_PATH};
+use crate::preview::{PreviewFormat, PREVIEW_FORMAT_NAMES};
+use crate::rollover::{parse_size, Rollover, MIN_FILE_SIZE};
+use crate::shards::{ShardBy, Shards, SHARD_BY_NAMES};
+use crate::cluster::{ClusterBy, CLUSTER_BY_NAMES};
+use crate::assertions::Assertion;
+use crate::benefits::BenefitsOptions;
+use crate::config_file::{
+    effective_config, merge_config_file, subcommand_index, ConfigDump, CONFIG_OPTION, DUMP_CONFIG_OPTION
+};
+use crate::consent::{ConsentOptions, DEFAULT_CONSENT_VERSIONS, DEFAULT_EMAIL_OPT_IN, DEFAULT_SMS_OPT_IN};
+use crate::crypt::{FieldCipher, CIPHER_NAMES};
+use crate::fixed::{parse_layout, read_layout_file, LayoutField, FILLER};
+use crate::format::{FieldFormat, FORMATTER_NAMES};
+use crate::genders::{GenderValues, GENDER_CONFIG_FILE};
+use crate::hash::{FieldHash, HASH_ALGORITHM_NAMES};
+use crate::immunizations::{default_value_set, read_value_set, ImmunizationOptions, DEFAULT_MAX_IMMUNIZATIONS};
+use crate::lifecycle::{LifecycleOptions, DEFAULT_CHURN_RATE};
+use crate::loyalty::{LoyaltyOptions, DEFAULT_LOYALTY_TIERS};
+use crate::ldif;
+use crate::logging;
+use crate::telemetry;
+use crate::numlib::parse_count;
+use crate::metadata::{parse_source_systems, MetadataOptions, DEFAULT_SOURCE_SYSTEMS};
+use crate::people::{output_fields, ssn_generator, FIELD_NAMES};
+use crate::pronouns::{parse_pronoun_mapping, PronounOptions, PRONOUNS_ASK};
+use crate::pg::{self, PostgresOptions};
+use crate::rules::{read_rules_file, Rules};
+use crate::salary::{fit_log_normal, parse_salary_mix, SalaryMix, SalaryPercentiles, SalaryRounding};
+use crate::scenario::{read_scenario, step_seed, written_twice};
+use crate::shifts::{Department, ShiftOptions, DEFAULT_DEPARTMENTS, DEPARTMENT_NAMES};
+use crate::sql::{SqlDialect, SQL_DIALECT_NAMES};
+use crate::ssn::{SsnFormat, SSN_FORMAT_NAMES};
+use crate::transactions::{TransactionOptions, DEFAULT_TRANSACTIONS_PER_PERSON};
+use crate::validate::{ValidateOptions, DEFAULT_DATE_FORMAT, DEFAULT_MAX_VIOLATIONS};
+use crate::vin::{VinOptions, DEFAULT_VIN_WMIS, FIRST_MODEL_YEAR};
+use crate::w2::{W2Options, FIRST_TAX_YEAR, LAST_TAX_YEAR};
+use crate::weighted::WeightedValues;
+use crate::xlsx;
 
-const STARTING_YEAR_DEFAULT_DELTA: u32 = 90;
-const ENDING_YEAR_DEFAULT_DELTA: u32 = 18;
-const ENV_MALE_FIRST_NAMES_FILE: &str = "PEOPLEGEN_MALE_FIRST_NAMES";
-const ENV_FEMALE_FIRST_NAMES_FILE: &str = "PEOPLEGEN_FEMALE_FIRST_NAMES";
-const ENV_LAST_NAMES_FILE: &str = "PEOPLEGEN_LAST_NAMES";
+pub const STARTING_YEAR_DEFAULT_DELTA: u32 = 90;
+pub const ENDING_YEAR_DEFAULT_DELTA: u32 = 18;
+const FAKE_SSN_PREFIXES: u32 = 101;
+const CREATED_DEFAULT_WINDOW_DAYS: i64 = 5 * 365;
+const AFTER_HELP: &str =
+"Supports CSV, JSON, JSON Lines, Avro, Arrow IPC (Feather), Excel, SQL,
+Protobuf, fixed-width, vCard, LDIF, HTML and Markdown output formats.
+The output format is determined by the output file extension (\".csv\"
+or \".tsv\", \".json\", \".jsonl\", \".avro\", \".arrow\" or \".feather\", \".xlsx\", \".sql\",
+\".pb\", \".dat\", \".vcf\", \".ldif\", \".html\", or \".md\"), unless
+--format says otherwise.
+See https://github.com/bmc/peoplegen-rust for more information.";
+const MEMBER_DEFAULT_WINDOW_DAYS: i64 = 15 * 365;
+const TRANSACTION_DEFAULT_WINDOW_DAYS: i64 = 365;
+// --vin's model years default to the last 20 years' models (and next year's).
+const MODEL_YEAR_DEFAULT_SPAN: u32 = 20;
+// The subcommands that generate people, and take generate's options.
+const GENERATING_SUBCOMMANDS: [&str; 3] = ["generate", "bench", "doctor"];
+// The options `peoplegen bench` doesn't take, by id, with how they're given.
+const BENCH_EXCLUDED_OPTIONS: [(&str, &str); 7] = [
+    ("postgres-url", "--postgres-url"),
+    ("output-sink", "--output"),
+    ("preview", "--preview"),
+    ("shards", "--shards"),
+    ("max-rows-per-file", "--max-rows-per-file"),
+    ("max-bytes-per-file", "--max-bytes-per-file"),
+    ("dry-run", "--dry-run"),
+];
+pub const ENV_MALE_FIRST_NAMES_FILE: &str = "PEOPLEGEN_MALE_FIRST_NAMES";
+pub const ENV_FEMALE_FIRST_NAMES_FILE: &str = "PEOPLEGEN_FEMALE_FIRST_NAMES";
+pub const ENV_LAST_NAMES_FILE: &str = "PEOPLEGEN_LAST_NAMES";
 
 // 2021 mean salary across all professions, per the Bureau of Labor Statistics.
 // See https://www.bls.gov/oes/current/oes_nat.htm
 //
 // These next two constants are strings, mostly to keep the argument parser
 // happy.
-const SALARY_MEAN_DEFAULT: &str = "58260";
+pub const SALARY_MEAN_DEFAULT: &str = "58260";
 
 // This is arbitrary
-const SALARY_SIGMA_DEFAULT: &str = "5000";
+pub const SALARY_SIGMA_DEFAULT: &str = "5000";
+
+// The default number of rows per COPY, with --postgres-url.
+const BATCH_SIZE_DEFAULT: &str = "10000";
 
 /// The header format to use in the output CSV file.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum HeaderFormat {
+    #[default]
     SnakeCase,
     CamelCase,
-    Pretty
+    Pretty,
+    ActiveDirectory
 }
 
 // The desired output format
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub enum OutputFormat {
     JsonPretty,
     JsonL,
-    Csv
+    #[default]
+    Csv,
+    Avro,
+    Arrow,
+    Xlsx,
+    Sql,
+    Protobuf,
+    FixedWidth,
+    VCard,
+    Ldif,
+    Html,
+    Markdown,
+    /// An aligned table, for `--preview`
+    Table,
+    Postgres
 }
 
+/// The names `--format` accepts, which are also the output file extensions
+/// that select a format.
+pub const OUTPUT_FORMAT_NAMES: [&str; 15] = [
+    "csv", "tsv", "json", "jsonl", "avro", "arrow", "feather", "xlsx", "sql", "pb", "dat", "vcf",
+    "ldif", "html", "md",
+];
+
 impl OutputFormat {
+    /**
+     * Look up an output format by name.
+     *
+     * # Arguments
+     *
+     * - `name`: The name, one of `OUTPUT_FORMAT_NAMES`, or a file extension
+     *
+     * # Returns
+     *
+     * The format, or `None` if the name isn't one. ("tsv" is CSV, with a
+     * different delimiter.)
+     */
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "csv" | "tsv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::JsonPretty),
+            "jsonl" => Some(OutputFormat::JsonL),
+            "avro" => Some(OutputFormat::Avro),
+            "arrow" | "feather" => Some(OutputFormat::Arrow),
+            "xlsx" => Some(OutputFormat::Xlsx),
+            "sql" => Some(OutputFormat::Sql),
+            "pb" => Some(OutputFormat::Protobuf),
+            "dat" => Some(OutputFormat::FixedWidth),
+            "vcf" => Some(OutputFormat::VCard),
+            "ldif" => Some(OutputFormat::Ldif),
+            "html" => Some(OutputFormat::Html),
+            "md" => Some(OutputFormat::Markdown),
+            _ => None,
+        }
+    }
+
     /**
      * Returns a `str` representation of an output format, suitable for
      * printing or formatting.
@@ -49,41 +178,208 @@ impl OutputFormat {
             OutputFormat::JsonPretty => "JSON",
             OutputFormat::JsonL => "JSON Lines",
             OutputFormat::Csv => "CSV",
+            OutputFormat::Avro => "Avro",
+            OutputFormat::Arrow => "Arrow IPC",
+            OutputFormat::Xlsx => "Excel",
+            OutputFormat::Sql => "SQL",
+            OutputFormat::Protobuf => "Protobuf",
+            OutputFormat::FixedWidth => "fixed-width",
+            OutputFormat::VCard => "vCard",
+            OutputFormat::Ldif => "LDIF",
+            OutputFormat::Html => "HTML",
+            OutputFormat::Markdown => "Markdown",
+            OutputFormat::Table => "table",
+            OutputFormat::Postgres => "Postgres",
         }
     }
 }
 
-/// Command-line arguments, as parsed.
+/// What the command line asks for.
 #[derive(Debug)]
+pub enum Action {
+    /// Generate people (the usual case)
+    Generate(Box<Arguments>),
+    /// `peoplegen fetch-names`: download the names data to `dest`
+    FetchNames { dest: PathBuf, force: bool },
+    /// `peoplegen schema ddl`: print the `CREATE TABLE` statement for the
+    /// columns `args` would generate
+    SchemaDdl { args: Box<Arguments>, dialect: SqlDialect, table: String },
+    /// `--dump-config -`: print the effective configuration, instead of
+    /// generating anything
+    DumpConfig(String),
+    /// `peoplegen validate`: check a file `peoplegen` wrote
+    Validate { path: PathBuf, options: ValidateOptions },
+    /// `peoplegen bench`: generate people into the null sink, and say how
+    /// fast
+    Bench(Box<Arguments>),
+    /// `peoplegen doctor`: check what a run needs from its environment,
+    /// before it starts
+    Doctor(Box<Arguments>),
+    /// `peoplegen scenario`: run each of `steps`, in order. `seed` is the
+    /// scenario's, which `random_seed` says was chosen at random.
+    Scenario { steps: Vec<Arguments>, seed: u64, random_seed: bool },
+}
+
+/// Command-line arguments, as parsed.
+#[derive(Debug, Default)]
 pub struct Arguments {
     pub female_percent: u32,
     pub male_percent: u32,
+    /// The percentage of nonbinary people
+    pub other_percent: u32,
+    pub no_gender: bool,
+    pub gender_config: Option<PathBuf>,
+    /// What to write for each gender, in place of its label
+    pub gender_values: Option<GenderValues>,
+    pub pronouns: Option<PronounOptions>,
     pub generate_ssns: bool,
+    pub npi: bool,
+    pub dea: bool,
+    pub vin: Option<VinOptions>,
+    pub ssn_format: SsnFormat,
+    pub ssn_prefixes: Option<usize>,
     pub generate_ids: bool,
     pub generate_salaries: bool,
     pub salary_mean: u32,
     pub salary_sigma: u32,
+    pub salary_percentiles: Option<SalaryPercentiles>,
+    pub salary_mix: Option<SalaryMix>,
+    pub salary_rounding: SalaryRounding,
     pub header_format: HeaderFormat,
+    pub sort_keys: bool,
+    /// Write a row per field of each person, instead of a row per person
+    pub long: bool,
+    /// The CSV field delimiter, if it isn't a comma
+    pub delimiter: Option<u8>,
+    /// Quote every CSV field, not just the ones that need it
+    pub quote_all: bool,
+    /// End CSV lines with CRLF, not LF
+    pub crlf: bool,
+    /// Start CSV output with a UTF-8 byte order mark
+    pub bom: bool,
+    pub field_formats: Vec<FieldFormat>,
+    pub field_ciphers: Vec<FieldCipher>,
+    pub field_hashes: Vec<FieldHash>,
+    pub ad_domain: String,
+    pub base_dn: String,
     pub year_min: u32,
     pub year_max: u32,
     pub male_first_names_file: PathBuf,
     pub female_first_names_file: PathBuf,
+    /// The nonbinary first names, if they aren't the male and female ones
+    pub other_first_names_file: Option<PathBuf>,
     pub last_names_file: PathBuf,
     pub output_file: PathBuf,
+    pub template_file: Option<PathBuf>,
+    pub documents_dir: Option<PathBuf>,
+    pub documents_as_pdf: bool,
+    pub avatars_dir: Option<PathBuf>,
+    pub avatar_base_url: Option<String>,
+    pub barcode_source: Option<BarcodeSource>,
+    pub barcodes_dir: Option<PathBuf>,
+    pub metadata: Option<MetadataOptions>,
+    pub device: bool,
+    pub consent: Option<ConsentOptions>,
+    pub lifecycle: Option<LifecycleOptions>,
+    pub loyalty: Option<LoyaltyOptions>,
+    pub ssn_collisions_file: Option<PathBuf>,
+    /// The `--dump-config` file, and the configuration to write to it
+    pub config_dump: Option<ConfigDump>,
+    pub aggregates_dir: Option<PathBuf>,
+    pub stats: bool,
+    pub shifts_file: Option<PathBuf>,
+    pub shifts: Option<ShiftOptions>,
+    pub benefits_dir: Option<PathBuf>,
+    pub benefits: Option<BenefitsOptions>,
+    pub w2_file: Option<PathBuf>,
+    pub immunizations_file: Option<PathBuf>,
+    pub immunizations: Option<ImmunizationOptions>,
+    pub w2: Option<W2Options>,
+    pub transactions_file: Option<PathBuf>,
+    pub transactions: Option<TransactionOptions>,
+    pub proto_file: Option<PathBuf>,
+    pub layout: Option<Vec<LayoutField>>,
+    pub rules: Option<Rules>,
+    pub unique_ssns: bool,
     pub output_format: OutputFormat,
+    pub compression: Option<Compression>,
+    pub sql_dialect: SqlDialect,
+    pub postgres: Option<PostgresOptions>,
+    pub seed: u64,
+    pub random_seed: bool,
+    pub json_summary: bool,
+    pub summary_json_file: Option<PathBuf>,
+    /// The OpenTelemetry collector to send the run's trace spans to (see
+    /// `telemetry`)
+    pub otlp_endpoint: Option<String>,
+    /// How many times `-v` was given (see `logging`)
+    pub verbosity: u8,
+    pub strict: bool,
+    pub quiet: bool,
+    /// Whether the people are a `--preview`, printed instead of written
+    pub preview: bool,
+    /// How many of the people to print as a `--preview` table, as well as
+    /// writing them, when there's an output file, too
+    pub preview_rows: Option<u64>,
+    /// Whether to say what would be generated, instead of generating it
+    pub dry_run: bool,
+    pub shards: Option<Shards>,
+    pub cluster_by: Option<ClusterBy>,
+    pub rollover: Option<Rollover>,
+    pub assertions: Vec<Assertion>,
+    /// The ID before the first person's: in a scenario, how many people the
+    /// steps before this one generate (see `scenario`)
+    pub id_offset: u64,
     pub total: u64
 }
 
 /**
- * Parse the command line arguments into an `Arguments` structure.
- * Returns an `Ok` with the parsed arguments, or an `Err` with a message
- * on error.
+ * Move the options given before a subcommand that generates (`generate`,
+ * `bench` or `doctor`) to just after it, where the subcommand takes them,
+ * so that `peoplegen --seed 1 generate ...` is `peoplegen generate --seed 1
+ * ...`, as it was before there were subcommands.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser
+ * - `args`: The command line, starting with the program name
+ *
+ * # Returns
+ *
+ * The command line, with the options moved, if there were any.
+ */
+fn options_after_subcommand(command: &Command, mut args: Vec<String>) -> Vec<String> {
+    if let Some(i) = subcommand_index(command, &args).filter(|i| GENERATING_SUBCOMMANDS.contains(&args[*i].as_str())) {
+        let before: Vec<String> = args.drain(1..i).collect();
+        args.splice(2..2, before);
+    }
+    args
+}
+
+/**
+ * Parse the command line arguments into an `Action`, which, unless a
+ * subcommand was given, holds an `Arguments` structure. Returns an `Ok` with
+ * the parsed action, or an `Err` with a message on error.
 */
-pub fn parse_args() -> Result<Arguments, String> {
+pub fn parse_args() -> Result<Action, String> {
+    // Paths that aren't valid Unicode are rejected later, anyway.
+    parse_command_line(std::env::args_os().map(|a| a.to_string_lossy().into_owned()).collect())
+}
+
+/**
+ * Parse a command line (the program's, or a scenario step's) into an
+ * `Action`.
+ *
+ * # Arguments
+ *
+ * - `command_line`: The command line, starting with the program name
+ */
+fn parse_command_line(command_line: Vec<String>) -> Result<Action, String> {
     let header_format_map: HashMap<&str, HeaderFormat> = HashMap::from([
         ("snake", HeaderFormat::SnakeCase),
         ("pretty", HeaderFormat::Pretty),
         ("camel", HeaderFormat::CamelCase),
+        ("ad", HeaderFormat::ActiveDirectory),
     ]);
     // See https://stackoverflow.com/a/56724224/53495
     let header_formats: Vec<&str> = header_format_map.keys().cloned().collect();
@@ -97,15 +393,31 @@ pub fn parse_args() -> Result<Arguments, String> {
 
     let default_year_min = year_before_now(STARTING_YEAR_DEFAULT_DELTA);
     let default_year_max = year_before_now(ENDING_YEAR_DEFAULT_DELTA);
-    let female_first_names_default = getenv(ENV_FEMALE_FIRST_NAMES_FILE);
-    let male_first_names_default = getenv(ENV_MALE_FIRST_NAMES_FILE);
-    let last_names_default = getenv(ENV_LAST_NAMES_FILE);
+    let female_first_names_default =
+        names_file_default(ENV_FEMALE_FIRST_NAMES_FILE, FEMALE_FIRST_NAMES_FILE);
+    let male_first_names_default =
+        names_file_default(ENV_MALE_FIRST_NAMES_FILE, MALE_FIRST_NAMES_FILE);
+    let last_names_default = names_file_default(ENV_LAST_NAMES_FILE, LAST_NAMES_FILE);
+    let names_dir_default = default_names_dir()
+        .map(|d| path_str(&d).to_string())
+        .unwrap_or_default();
 
-    let parser = Command::new("peoplegen")
-        // See https://stackoverflow.com/a/27841363/53495
-        .version(env!("CARGO_PKG_VERSION"))
-        .author("bmc@clapper.org")
-        .about("Generate fake people data in a CSV")
+    let generate = Command::new("generate")
+        .about("Generate the people. Without a subcommand, peoplegen does this.")
+        .arg(Arg::new(CONFIG_OPTION)
+                 .long(CONFIG_OPTION)
+                 .value_name("FILE")
+                 .help(
+"Read options from a TOML file: each key is an option's long name, or
+output or total. Options on the command line override the file's."))
+        .arg(Arg::new(DUMP_CONFIG_OPTION)
+                 .long(DUMP_CONFIG_OPTION)
+                 .value_name("FILE")
+                 .help(
+"Write the run's effective configuration (every option's value,
+including defaults and the seed) to FILE, as a --config file that
+reproduces the run. With -, print it and stop, without generating
+anything."))
         .arg(Arg::new("female")
                  .short('f')
                  .long("female-pct")
@@ -120,35 +432,363 @@ pub fn parse_args() -> Result<Arguments, String> {
                  .value_name("PERCENT")
                  .value_parser(clap::value_parser!(u32))
                  .help("Percentage of male names."))
+        .arg(Arg::new("other")
+                 .long("other-pct")
+                 .default_value("0")
+                 .value_name("PERCENT")
+                 .value_parser(clap::value_parser!(u32))
+                 .help(
+"Percentage of nonbinary people, whose gender is X. The female, male and
+other percentages must add up to 100."))
+        .arg(Arg::new("no-gender")
+                 .long("no-gender")
+                 .action(ArgAction::SetTrue)
+                 .conflicts_with_all(["female", "male", "other"])
+                 .help(
+"Omit the gender field, and draw first (and middle) names from the
+union of the male and female name files, so the data doesn't reveal
+anyone's gender."))
         .arg(Arg::new("female-first-names")
                  .short('F')
                  .long("female-names")
                  .value_name("<path>")
                  .help(format!(
-"Path to text file containing female first names, one per line.
-If not specified, it defaults to the value of environment variable
-{}.", ENV_FEMALE_FIRST_NAMES_FILE)))
+"Path to text file containing female first names, one per line, each
+optionally followed by a comma and a weight. If not specified, it
+defaults to the value of environment variable {}.", ENV_FEMALE_FIRST_NAMES_FILE)))
         .arg(Arg::new("male-first-names")
                  .short('M')
                  .long("male-names")
                  .value_name("<path>")
                  .help(format!(
-"Path to text file containing male first names, one per line.
-If not specified, it defaults to the value of environment variable
-{}.", ENV_MALE_FIRST_NAMES_FILE)))
+"Path to text file containing male first names, one per line, each
+optionally followed by a comma and a weight. If not specified, it
+defaults to the value of environment variable {}.", ENV_MALE_FIRST_NAMES_FILE)))
+        .arg(Arg::new("other-first-names")
+                 .long("other-names")
+                 .value_name("<path>")
+                 .requires("other")
+                 .help(
+"Path to text file containing first names for the --other-pct people, in
+the same format as the others. If not specified, their names come from
+both the male and female names files."))
+        .arg(Arg::new("gender-config")
+                 .long("gender-config")
+                 .value_name("PATH")
+                 .conflicts_with_all(["female", "male", "other", "female-first-names",
+                                      "male-first-names", "other-first-names"])
+                 .help(
+"Path to a CSV file defining the gender categories to generate, in
+place of male and female. Each line has a label (written to the gender
+field), a percentage, and the path to a first names file (relative to
+the configuration file). The first line is a header. Defaults to
+genders.csv in the config directory, if it exists, unless the genders
+are given with other options."))
+        .arg(Arg::new("gender-values")
+                 .long("gender-values")
+                 .value_name("MALE,FEMALE[,NONBINARY]")
+                 .value_parser(GenderValues::parse)
+                 .conflicts_with_all(["no-gender", "gender-config"])
+                 .help(
+"What to write in the gender field for male, female and (with
+--other-pct) nonbinary people, in place of M, F and X, e.g.,
+\"Male,Female\" or \"0,1\". Rules, assertions and --pronoun still use
+M, F and X."))
+        .arg(Arg::new("pronouns")
+                 .long("pronouns")
+                 .action(ArgAction::SetTrue)
+                 .conflicts_with("no-gender")
+                 .help(
+"Add a pronouns field, derived from the gender: \"he/him\" for M,
+\"she/her\" for F and \"they/them\" for anything else, unless changed
+with --pronoun."))
+        .arg(Arg::new("pronoun")
+                 .long("pronoun")
+                 .value_name("GENDER=PRONOUNS")
+                 .action(ArgAction::Append)
+                 .requires("pronouns")
+                 .help(
+"The pronouns to use for a gender, with --pronouns, e.g.,
+--pronoun F=she/they. May be specified multiple times."))
+        .arg(Arg::new("pronouns-ask")
+                 .long("pronouns-ask")
+                 .value_name("PERCENT")
+                 .value_parser(clap::value_parser!(u32).range(0..=100))
+                 .requires("pronouns")
+                 .help(format!(
+"The percentage of people whose pronouns are \"{}\", with --pronouns.
+Default: 0", PRONOUNS_ASK)))
              .arg(Arg::new("last-names")
                  .short('L')
                  .long("last-names")
                  .value_name("PATH")
                  .help(format!(
-"Path to text file containing last names, one per line. If not
-specified, defaults to the value of environment variable
-{}.", ENV_LAST_NAMES_FILE)))
+"Path to text file containing last names, one per line, each
+optionally followed by a comma and a weight. If not specified,
+defaults to the value of environment variable {}.", ENV_LAST_NAMES_FILE)))
         .arg(Arg::new("ssn")
                  .short('s')
                  .long("ssn")
                  .action(ArgAction::SetTrue)
                  .help("Generate fake (and invalid) Social Security numbers"))
+        .arg(Arg::new("ssn-format")
+                 .long("ssn-format")
+                 .value_name("FORMAT")
+                 .value_parser(SSN_FORMAT_NAMES)
+                 .default_value("dashed")
+                 .help(
+"How to render SSNs: dashed (900-01-6789), digits (900016789), or
+masked (***-**-6789)."))
+        .arg(Arg::new("npi")
+                 .long("npi")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Generate National Provider Identifiers (npi), with valid check
+digits, for healthcare provider data."))
+        .arg(Arg::new("dea")
+                 .long("dea")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Generate DEA registration numbers (dea_number), with valid check
+digits, whose second letter is the last name's first."))
+        .arg(Arg::new("vin")
+                 .long("vin")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Generate vehicle identification numbers (vin), with valid check
+digits and model year codes, for fleet and insurance data."))
+        .arg(Arg::new("vin-wmis")
+                 .long("vin-wmis")
+                 .value_name("WMI:WEIGHT,...")
+                 .requires("vin")
+                 .default_value(DEFAULT_VIN_WMIS)
+                 .help(
+"The World Manufacturer Identifiers VINs start with, with relative
+weights, with --vin. A WMI without a weight has weight 1."))
+        .arg(Arg::new("model-year-min")
+                 .long("model-year-min")
+                 .value_name("YEAR")
+                 .value_parser(clap::value_parser!(u32).range(i64::from(FIRST_MODEL_YEAR)..))
+                 .requires("vin")
+                 .help(format!(
+"The earliest model year, with --vin. Default: {}", year_before_now(MODEL_YEAR_DEFAULT_SPAN))))
+        .arg(Arg::new("model-year-max")
+                 .long("model-year-max")
+                 .value_name("YEAR")
+                 .value_parser(clap::value_parser!(u32).range(i64::from(FIRST_MODEL_YEAR)..))
+                 .requires("vin")
+                 .help(format!(
+"The latest model year, with --vin. Default: {} (next year's models
+are on sale this year)", year_before_now(0) + 1)))
+        .arg(Arg::new("sql-dialect")
+                 .long("sql-dialect")
+                 .value_name("DIALECT")
+                 .value_parser(SQL_DIALECT_NAMES)
+                 .default_value("postgres")
+                 .help(
+"The SQL dialect for \".sql\" output, which controls quoting and
+column types: postgres, mysql, sqlite or snowflake."))
+        .arg(Arg::new("random-ssn-prefixes")
+                 .long("random-ssn-prefixes")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32).range(1..=i64::from(FAKE_SSN_PREFIXES)))
+                 .help(format!(
+"Use N randomly chosen SSN prefixes (from the {} fake ones), in random
+order, so that separately generated data sets are unlikely to share SSNs.
+Use {} to shuffle all the prefixes.", FAKE_SSN_PREFIXES, FAKE_SSN_PREFIXES)))
+        .arg(Arg::new("ssn-collisions")
+                 .long("ssn-collisions")
+                 .value_name("PATH")
+                 .requires("ssn")
+                 .help(
+"Write a CSV report of SSNs shared by more than one person (one row
+per person, with the SSN and the person's ID) to PATH. SSNs are only
+shared when generating more people than there are fake SSNs."))
+        .arg(Arg::new("aggregates")
+                 .long("aggregates")
+                 .value_name("DIR")
+                 .conflicts_with("preview")
+                 .help(
+"Also write summary tables of the people to DIR, as CSV:
+gender_by_decade.csv (the count of each gender by birth decade), and,
+with --salary, salary_quantiles.csv (the salary quantiles by gender)."))
+        .arg(Arg::new("stats")
+                 .long("stats")
+                 .action(ArgAction::SetTrue)
+                 .conflicts_with("preview")
+                 .help(
+"Print statistics about the people after the run: the count of each
+gender, a histogram of birth years, the salaries' mean, median and
+standard deviation (with --salary), and how many people share a name."))
+        .arg(Arg::new("shifts")
+                 .long("shifts")
+                 .value_name("PATH")
+                 .conflicts_with("preview")
+                 .help(
+"Also write weekly shift schedules to PATH, as CSV: a row per shift,
+with the person's ID and department, the start and end times, and the
+hours. A person's shifts never overlap."))
+        .arg(Arg::new("shift-weeks")
+                 .long("shift-weeks")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32).range(1..=52))
+                 .default_value("1")
+                 .requires("shifts")
+                 .help("How many weeks the --shifts schedules cover."))
+        .arg(Arg::new("shift-start")
+                 .long("shift-start")
+                 .value_name("YYYY-MM-DD")
+                 .requires("shifts")
+                 .help(
+"The Monday the --shifts schedules start on. Default: this week's Monday"))
+        .arg(Arg::new("departments")
+                 .long("departments")
+                 .value_name("NAME:WEIGHT,...")
+                 .default_value(DEFAULT_DEPARTMENTS)
+                 .requires("shifts")
+                 .help(format!(
+"The departments people work in, with relative weights, with --shifts.
+Each schedules shifts its own way. The departments are {}.", DEPARTMENT_NAMES.join(", "))))
+        .arg(Arg::new("benefits")
+                 .long("benefits")
+                 .value_name("DIR")
+                 .conflicts_with("preview")
+                 .help(
+"Also write benefits enrollments to DIR, as CSV: elections.csv (a row
+per medical, dental or vision plan elected, with the hire date, coverage
+tier and effective dates) and dependents.csv (the spouses and children
+covered, linked by the person's ID)."))
+        .arg(Arg::new("w2")
+                 .long("w2")
+                 .value_name("PATH")
+                 .requires("salary")
+                 .conflicts_with("preview")
+                 .help(
+"Also write W-2 style wage and tax statements to PATH, as CSV, with
+--salary: a row per person per tax year, with the employer's (fake)
+EIN, the wages, and the federal, Social Security and Medicare boxes."))
+        .arg(Arg::new("tax-year")
+                 .long("tax-year")
+                 .value_name("YEAR")
+                 .value_parser(clap::value_parser!(i32).range(i64::from(FIRST_TAX_YEAR)..=i64::from(LAST_TAX_YEAR)))
+                 .requires("w2")
+                 .help(format!(
+"The latest tax year of the --w2 statements, the year of the salaries.
+Default: {}", default_tax_year())))
+        .arg(Arg::new("w2-years")
+                 .long("w2-years")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32).range(1..))
+                 .default_value("1")
+                 .requires("w2")
+                 .help("How many tax years, back from --tax-year, the --w2 statements cover."))
+        .arg(Arg::new("transactions")
+                 .long("transactions")
+                 .value_name("PATH")
+                 .conflicts_with("preview")
+                 .help(
+"Also write transactions to PATH, as CSV: the same number of line items
+for each person, each with a date, a merchant category code and an
+amount."))
+        .arg(Arg::new("transactions-per-person")
+                 .long("transactions-per-person")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32))
+                 .requires("transactions")
+                 .help(format!(
+"How many --transactions each person has. Default: {}", DEFAULT_TRANSACTIONS_PER_PERSON)))
+        .arg(Arg::new("transaction-min")
+                 .long("transaction-min")
+                 .value_name("YYYY-MM-DD")
+                 .requires("transactions")
+                 .help(format!(
+"The earliest transaction date, with --transactions. Default: {}",
+now_minus_days(TRANSACTION_DEFAULT_WINDOW_DAYS))))
+        .arg(Arg::new("transaction-max")
+                 .long("transaction-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("transactions")
+                 .help("The latest transaction date, with --transactions. Default: today"))
+        .arg(Arg::new("immunizations")
+                 .long("immunizations")
+                 .value_name("PATH")
+                 .conflicts_with("preview")
+                 .help(
+"Also write immunization histories to PATH: a few immunizations per
+person, with vaccine codes from a value set and dates after their birth.
+A \".ndjson\" or \".jsonl\" file gets FHIR Immunization resources, one
+per line; anything else gets CSV."))
+        .arg(Arg::new("immunization-codes")
+                 .long("immunization-codes")
+                 .value_name("FILE")
+                 .requires("immunizations")
+                 .help(
+"The value set for --immunizations, as CSV, with the columns code and
+display, and, optionally, system and weight. Default: common CVX codes"))
+        .arg(Arg::new("max-immunizations")
+                 .long("max-immunizations")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32))
+                 .requires("immunizations")
+                 .help(format!(
+"The most immunizations a person has, with --immunizations. Default: {}", DEFAULT_MAX_IMMUNIZATIONS)))
+        .arg(Arg::new("immunization-max")
+                 .long("immunization-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("immunizations")
+                 .help("The latest immunization date, with --immunizations. Default: today"))
+        .arg(Arg::new("plan-year")
+                 .long("plan-year")
+                 .value_name("YEAR")
+                 .value_parser(clap::value_parser!(i32).range(1900..=9998))
+                 .requires("benefits")
+                 .help("The plan year the --benefits elections are for. Default: this year"))
+        .arg(Arg::new("layout")
+                 .long("layout")
+                 .value_name("FIELD:WIDTH,...")
+                 .help(
+"The column layout for fixed-width (\".dat\") output, e.g.,
+id:8,last_name:25,first_name:20,filler:2,birth_date:10. Text is
+left-justified and padded with spaces (and cut off, if it's too long);
+IDs and salaries are right-justified and padded with zeros. \"filler\"
+inserts blanks."))
+        .arg(Arg::new("layout-file")
+                 .long("layout-file")
+                 .value_name("PATH")
+                 .conflicts_with("layout")
+                 .help(
+"Read the fixed-width layout from a file, with one FIELD:WIDTH entry
+per line, in place of --layout."))
+        .arg(Arg::new("rules")
+                 .long("rules")
+                 .value_name("PATH")
+                 .help(
+"Read conditional rules from a file, one per line, e.g.,
+if age < 18 then salary = 0. Each rule sets a field when its
+conditions hold."))
+        .arg(Arg::new("assert")
+                 .long("assert")
+                 .value_name("ASSERTION")
+                 .action(ArgAction::Append)
+                 .help(
+"Check a property of the generated data at the end of the run, and
+fail (with exit status 6) if it doesn't hold, e.g.,
+--assert \"mean(salary) between 55000 and 62000\" or
+--assert \"count(gender = 'F') / count(*) ~ 0.5 ± 0.02\". May be
+specified more than once. See the README for the syntax."))
+        .arg(Arg::new("emit-proto")
+                 .long("emit-proto")
+                 .value_name("PATH")
+                 .help(
+"Write the protobuf definition (a .proto file) of the messages in
+\".pb\" output to PATH. The fields match the output columns and are
+named like the headers."))
+        .arg(Arg::new("unique-ssns")
+                 .long("unique-ssns")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Fail, instead of warning, if there are too few fake SSNs for every
+person to get a unique one."))
         .arg(Arg::new("salary")
                  .short('S')
                  .long("salary")
@@ -158,29 +798,159 @@ specified, defaults to the value of environment variable
 a normal (Poisson) distribution with a mean salary of {} (the
 2021 mean salary for all professions, according to the Bureau of
 Labor Statistics) and a sigma (standard deviation) of {}. You can
-change those values with --salary-mean and --salary-sigma",
+change those values with --salary-mean and --salary-sigma, or give
+percentiles instead, with --salary-p50 and --salary-p90.",
 SALARY_MEAN_DEFAULT, SALARY_SIGMA_DEFAULT)))
         .arg(Arg::new("salary-mean")
                  .long("salary-mean")
-                 .value_parser(clap::value_parser!(u32))
+                 .value_parser(parse_count::<u32>)
                  .default_value(SALARY_MEAN_DEFAULT)
-                 .help(format!("Mean salary to use.")))
+                 .help("Mean salary to use, e.g., 58260 or 58.26k."))
         .arg(Arg::new("salary-sigma")
                  .long("salary-sigma")
-                 .value_parser(clap::value_parser!(u32))
+                 .value_parser(parse_count::<u32>)
                  .default_value(SALARY_SIGMA_DEFAULT)
-                 .help(format!("Sigma (standard deviation) for salaries.")))
+                 .help("Sigma (standard deviation) for salaries, e.g., 5000 or 5k."))
+        .arg(Arg::new("salary-p50")
+                 .long("salary-p50")
+                 .value_name("SALARY")
+                 .value_parser(clap::value_parser!(u32))
+                 .requires("salary-p90")
+                 .conflicts_with_all(["salary-mean", "salary-sigma"])
+                 .help(
+"The median salary. With --salary-p90, salaries follow the
+log-normal distribution with those percentiles, instead of
+a normal distribution."))
+        .arg(Arg::new("salary-p90")
+                 .long("salary-p90")
+                 .value_name("SALARY")
+                 .value_parser(clap::value_parser!(u32))
+                 .requires("salary-p50")
+                 .conflicts_with_all(["salary-mean", "salary-sigma"])
+                 .help("The 90th percentile salary. See --salary-p50."))
+        .arg(Arg::new("salary-mix")
+                 .long("salary-mix")
+                 .value_name("MIXTURE")
+                 .conflicts_with_all(["salary-mean", "salary-sigma", "salary-p50", "salary-p90"])
+                 .help(
+"Draw each salary from one of several distributions, with the given
+percentages, e.g., \"70% normal(50k, 8k) + 30% lognormal(120k, 250k)\".
+Distributions: normal(MEAN, SIGMA), lognormal(P50, P90) and
+uniform(MIN, MAX)."))
+        .arg(Arg::new("salary-round")
+                 .long("salary-round")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u32).range(1..))
+                 .conflicts_with("salary-precision")
+                 .help("Round salaries to the nearest multiple of N, e.g., 1000."))
+        .arg(Arg::new("salary-precision")
+                 .long("salary-precision")
+                 .value_name("DIGITS")
+                 .value_parser(clap::value_parser!(u32).range(1..=9))
+                 .help(
+"Round salaries to DIGITS significant digits, e.g., 58,260 becomes
+58,000 with --salary-precision 2."))
         .arg(Arg::new("id")
                  .short('i')
                  .long("id")
                  .action(ArgAction::SetTrue)
                  .help("Generate unique IDs for each person"))
+        .arg(Arg::new("format")
+                 .long("format")
+                 .value_name("FORMAT")
+                 .value_parser(OUTPUT_FORMAT_NAMES)
+                 .help(format!(
+"The output format, one of: {}. Default: the one the
+output file's extension names, or CSV for standard output.", OUTPUT_FORMAT_NAMES.join(", "))))
         .arg(Arg::new("header-format")
                  .short('H')
                  .long("header-format")
                  .default_value("snake")
-                 .help(format!("CSV header format, one of: {}",
-                               header_formats.join(", "))))
+                 .help(format!(
+"CSV header format, one of: {}. The \"ad\" format uses Active
+Directory attribute names, enforces Active Directory length
+limits, and adds unique sAMAccountName and userPrincipalName
+values. Default: snake, except for HTML and Markdown output, which
+get pretty headers.", header_formats.join(", "))))
+        .arg(Arg::new("delimiter")
+                 .short('d')
+                 .long("delimiter")
+                 .value_name("CHAR")
+                 .value_parser(parse_delimiter)
+                 .help(
+"The field delimiter for CSV output, e.g., \"|\". Use \"tab\" or \"\\t\"
+for a tab. Default: a comma, or a tab for --format tsv or an output
+file ending in \".tsv\"."))
+        .arg(Arg::new("quote-all")
+                 .long("quote-all")
+                 .action(ArgAction::SetTrue)
+                 .help("Quote every CSV field, instead of only the ones that need it."))
+        .arg(Arg::new("crlf")
+                 .long("crlf")
+                 .action(ArgAction::SetTrue)
+                 .help("End CSV lines with CRLF, as RFC 4180 says, instead of LF."))
+        .arg(Arg::new("bom")
+                 .long("bom")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Start CSV output with a UTF-8 byte order mark, so Excel reads it as
+UTF-8, instead of guessing."))
+        .arg(Arg::new("sort-keys")
+                 .long("sort-keys")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Sort the keys of each JSON object by name. By default, the keys are
+written in the same order as the CSV columns."))
+        .arg(Arg::new("long")
+                 .long("long")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Write the people in long (\"tall\") format, with a row per field of
+each person (person_id, field, value) instead of a row per person.
+For CSV, TSV, JSON and JSON Lines output, and --preview."))
+        .arg(Arg::new("format-field")
+                 .long("format-field")
+                 .value_name("FIELD=FORMATTER")
+                 .action(ArgAction::Append)
+                 .help(format!(
+"Control how a field is rendered in the output, e.g.,
+--format-field ssn=digits-only or
+--format-field birth_date=date:%m/%d/%Y. May be specified more
+than once; multiple formatters for the same field are applied in
+order. Formatters: {}",
+FORMATTER_NAMES.join(", "))))
+        .arg(Arg::new("encrypt-field")
+                 .long("encrypt-field")
+                 .value_name("FIELD=METHOD:KEYFILE")
+                 .action(ArgAction::Append)
+                 .help(format!(
+"Encrypt or tokenize a field, using the key in KEYFILE (raw bytes
+or hex), e.g., --encrypt-field ssn=aes-gcm:ssn.key. aes-gcm
+writes base64(nonce + ciphertext + tag) and needs a 16- or
+32-byte key; token replaces each digit and letter while
+preserving the format. May be specified more than once.
+Methods: {}", CIPHER_NAMES.join(", "))))
+        .arg(Arg::new("hash-field")
+                 .long("hash-field")
+                 .value_name("FIELD=ALGORITHM")
+                 .action(ArgAction::Append)
+                 .help(format!(
+"Add a FIELD_hash field containing the hex-encoded hash of another
+field, e.g., --hash-field ssn=sha256 adds ssn_hash. The hash is
+computed after any --format-field formatting, but before
+--encrypt-field. May be specified more than once, for different
+fields. Algorithms: {}",
+HASH_ALGORITHM_NAMES.join(", "))))
+        .arg(Arg::new("ad-domain")
+                 .long("ad-domain")
+                 .value_name("DOMAIN")
+                 .default_value(ad::DEFAULT_DOMAIN)
+                 .help("Domain to use for userPrincipalName values, with -H ad."))
+        .arg(Arg::new("base-dn")
+                 .long("base-dn")
+                 .value_name("DN")
+                 .default_value(ldif::DEFAULT_BASE_DN)
+                 .help("The DN that entries go under, in LDIF (\".ldif\") output."))
         .arg(Arg::new("year-min")
                  .short('y')
                  .long("year-min")
@@ -193,21 +963,589 @@ SALARY_MEAN_DEFAULT, SALARY_SIGMA_DEFAULT)))
                  .value_parser(clap::value_parser!(u32))
                  .help(format!("The ending year for birth dates. Default: {}",
                        default_year_max)))
+        .arg(Arg::new("template")
+                 .short('t')
+                 .long("template")
+                 .value_name("PATH")
+                 .requires("documents-dir")
+                 .help(
+"Render one document per person from this template file. Placeholders
+of the form {{field}} (e.g., {{first_name}}) are replaced with that
+person's values. Requires --documents-dir."))
+        .arg(Arg::new("documents-dir")
+                 .short('D')
+                 .long("documents-dir")
+                 .value_name("DIR")
+                 .requires("template")
+                 .help(
+"Directory in which to write the documents rendered from --template.
+Documents are spread across numbered subdirectories of 1,000 documents
+each."))
+        .arg(Arg::new("pdf")
+                 .long("pdf")
+                 .action(ArgAction::SetTrue)
+                 .requires("template")
+                 .help(
+"Render the documents from --template as simple PDFs. The rendered
+template text is laid out as-is, so use a plain text template."))
+        .arg(Arg::new("avatars")
+                 .long("avatars")
+                 .value_name("DIR")
+                 .help(
+"Generate an identicon-style PNG avatar for each person in this
+directory, and add an avatar_path field. Avatars are derived from
+the person's ID, so a given ID always gets the same image."))
+        .arg(Arg::new("avatar-base-url")
+                 .long("avatar-base-url")
+                 .value_name("URL")
+                 .help(
+"Add an avatar_url field, pointing to each person's avatar under
+this base URL (e.g., https://cdn.example.com/avatars)."))
+        .arg(Arg::new("barcode")
+                 .long("barcode")
+                 .value_name("FIELD")
+                 .value_parser(["id", "ssn"])
+                 .help(
+"Add a barcode field containing a Code 39 payload (e.g.,
+*900-01-0001*) that encodes the person's ID or Social Security
+number. One of: id, ssn"))
+        .arg(Arg::new("barcodes")
+                 .long("barcodes")
+                 .value_name("DIR")
+                 .requires("barcode")
+                 .help(
+"Also render each person's --barcode as a PNG image in this
+directory."))
+        .arg(Arg::new("metadata")
+                 .long("metadata")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Add row-level metadata fields: created_at, updated_at (never before
+created_at), source_system and record_uuid."))
+        .arg(Arg::new("created-min")
+                 .long("created-min")
+                 .value_name("YYYY-MM-DD")
+                 .requires("metadata")
+                 .help(format!(
+"The earliest created_at date, with --metadata. Default: {}",
+now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))))
+        .arg(Arg::new("created-max")
+                 .long("created-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("metadata")
+                 .help(
+"The latest created_at (and updated_at) date, with --metadata.
+Default: today"))
+        .arg(Arg::new("source-systems")
+                 .long("source-systems")
+                 .value_name("NAME:WEIGHT,...")
+                 .requires("metadata")
+                 .default_value(DEFAULT_SOURCE_SYSTEMS)
+                 .help(
+"Source system names for the source_system field, with relative
+weights, with --metadata. A name without a weight has weight 1."))
+        .arg(Arg::new("device")
+                 .long("device")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Add device fields, for web-analytics data: device_type (desktop,
+mobile or tablet), os and a matching browser user_agent."))
+        .arg(Arg::new("consent")
+                 .long("consent")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Add marketing consent fields: email_opt_in and sms_opt_in (true or
+false), consent_timestamp and consent_version."))
+        .arg(Arg::new("email-opt-in")
+                 .long("email-opt-in")
+                 .value_name("PERCENT")
+                 .value_parser(clap::value_parser!(u32).range(0..=100))
+                 .requires("consent")
+                 .help(format!(
+"The percentage of people who opt in to marketing email, with
+--consent. Default: {}", DEFAULT_EMAIL_OPT_IN)))
+        .arg(Arg::new("sms-opt-in")
+                 .long("sms-opt-in")
+                 .value_name("PERCENT")
+                 .value_parser(clap::value_parser!(u32).range(0..=100))
+                 .requires("consent")
+                 .help(format!(
+"The percentage of people who opt in to marketing text messages,
+with --consent. Default: {}", DEFAULT_SMS_OPT_IN)))
+        .arg(Arg::new("consent-min")
+                 .long("consent-min")
+                 .value_name("YYYY-MM-DD")
+                 .requires("consent")
+                 .help(format!(
+"The earliest consent_timestamp date, with --consent. Default: {}",
+now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))))
+        .arg(Arg::new("consent-max")
+                 .long("consent-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("consent")
+                 .help(
+"The latest consent_timestamp date, with --consent. Default: today"))
+        .arg(Arg::new("consent-versions")
+                 .long("consent-versions")
+                 .value_name("VERSION:WEIGHT,...")
+                 .requires("consent")
+                 .default_value(DEFAULT_CONSENT_VERSIONS)
+                 .help(
+"Versions of the consent wording for the consent_version field, with
+relative weights, with --consent. A version without a weight has
+weight 1."))
+        .arg(Arg::new("lifecycle")
+                 .long("lifecycle")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Add customer-lifecycle fields: signup_date, last_active_date,
+churned (true or false) and lifetime_value (in dollars)."))
+        .arg(Arg::new("signup-min")
+                 .long("signup-min")
+                 .value_name("YYYY-MM-DD")
+                 .requires("lifecycle")
+                 .help(format!(
+"The earliest signup_date, with --lifecycle. Default: {}",
+now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))))
+        .arg(Arg::new("signup-max")
+                 .long("signup-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("lifecycle")
+                 .help(
+"The latest signup_date, with --lifecycle, which is also the latest
+last_active_date. Default: today"))
+        .arg(Arg::new("churn-rate")
+                 .long("churn-rate")
+                 .value_name("PERCENT")
+                 .value_parser(clap::value_parser!(u32).range(0..=100))
+                 .requires("lifecycle")
+                 .help(format!(
+"The percentage of customers who've churned, with --lifecycle.
+Default: {}", DEFAULT_CHURN_RATE)))
+        .arg(Arg::new("loyalty")
+                 .long("loyalty")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Add loyalty-program fields: loyalty_tier, points_balance and
+member_since."))
+        .arg(Arg::new("loyalty-tiers")
+                 .long("loyalty-tiers")
+                 .value_name("TIER:WEIGHT,...")
+                 .requires("loyalty")
+                 .default_value(DEFAULT_LOYALTY_TIERS)
+                 .help(
+"The tiers for the loyalty_tier field, lowest first, with relative
+weights, with --loyalty. A tier without a weight has weight 1. Each
+tier has about twice the points of the one before it."))
+        .arg(Arg::new("member-min")
+                 .long("member-min")
+                 .value_name("YYYY-MM-DD")
+                 .requires("loyalty")
+                 .help(format!(
+"The earliest member_since date, with --loyalty. Default: {}",
+now_minus_days(MEMBER_DEFAULT_WINDOW_DAYS))))
+        .arg(Arg::new("member-max")
+                 .long("member-max")
+                 .value_name("YYYY-MM-DD")
+                 .requires("loyalty")
+                 .help(
+"The latest member_since date, with --loyalty. Default: today"))
+        .arg(Arg::new("seed")
+                 .long("seed")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u64))
+                 .help(
+"Seed for the random number generator. Runs with the same seed and
+the same options produce identical output. If not specified, a
+random seed is chosen and printed, so the run can be reproduced."))
+        .arg(Arg::new("json-summary")
+                 .long("json-summary")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Print the run summary (records written, the files written and their
+counts, the seed and the duration) as a single JSON object on standard
+output, instead of as messages."))
+        .arg(Arg::new("summary-json")
+                 .long("summary-json")
+                 .value_name("PATH")
+                 .help(
+"Also write a JSON report of the run to PATH, after it: the summary,
+as with --json-summary, plus each column's settings (header, type,
+formatters, encryption, hashing and rules), so CI pipelines can
+check the run."))
+        .arg(Arg::new("otlp-endpoint")
+                 .long("otlp-endpoint")
+                 .value_name("URL")
+                 .help(format!(
+"Send trace spans for the run (reading the names files, writing each
+output file, finishing the extra files) to this OpenTelemetry
+collector, as OTLP/HTTP with JSON, e.g., http://localhost:4318.
+Default: the value of environment variable {}, if it's set. A
+collector that can't be reached is a warning, not an error.", telemetry::ENV_OTLP_ENDPOINT)))
+        .arg(Arg::new("verbose")
+                 .long("verbose")
+                 .short('v')
+                 .action(ArgAction::Count)
+                 .conflicts_with("quiet")
+                 .help(format!(
+"Log how long each phase of the run (reading the names files,
+generating, writing) takes, on standard error. Give it twice (-vv)
+for more detail. Without it, the log level comes from environment
+variable {}, if it's set: off, error, warn, info, debug or trace.", logging::ENV_LOG)))
+        .arg(Arg::new("quiet")
+                 .long("quiet")
+                 .short('q')
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Don't print the run summary, the random seed or warnings. Errors
+are still printed, and so is --json-summary."))
+        .arg(Arg::new("strict")
+                 .long("strict")
+                 .action(ArgAction::SetTrue)
+                 .help(
+"Make warnings errors: more people than unique SSNs, a names file that
+lists a name more than once, or a percentage too small to give any
+people at all. A failed run leaves the old output alone."))
+        .arg(Arg::new("postgres-url")
+                 .long("postgres-url")
+                 .value_name("URL")
+                 .help(
+"Load the people into an existing Postgres table, with COPY, instead
+of writing an output file. The URL is a libpq connection string, e.g.,
+postgres://user@localhost/test. Give just the total, without an
+output file."))
+        .arg(Arg::new("table")
+                 .long("table")
+                 .value_name("[SCHEMA.]TABLE")
+                 .default_value(pg::DEFAULT_TABLE)
+                 .requires("postgres-url")
+                 .help(
+"The table to load, with --postgres-url. Its columns must be named
+like the headers (see --header-format)."))
+        .arg(Arg::new("truncate")
+                 .long("truncate")
+                 .action(ArgAction::SetTrue)
+                 .requires("postgres-url")
+                 .help("Empty the table before loading it, with --postgres-url."))
+        .arg(Arg::new("batch-size")
+                 .long("batch-size")
+                 .value_name("ROWS")
+                 .value_parser(clap::value_parser!(u64).range(1..))
+                 .default_value(BATCH_SIZE_DEFAULT)
+                 .requires("postgres-url")
+                 .help(
+"How many rows to send in each COPY, with --postgres-url. All the
+batches are loaded in a single transaction."))
+        .arg(Arg::new("shards")
+                 .long("shards")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u64).range(1..=100_000))
+                 .conflicts_with_all(["postgres-url", "output-sink", "preview"])
+                 .help(
+"Split the people across N output files, numbered from 0 before the
+extension: people-00000.csv, people-00001.csv, and so on, for an output
+file of people.csv. Each shard is a complete file, with its own header."))
+        .arg(Arg::new("shard-by")
+                 .long("shard-by")
+                 .value_name("HOW")
+                 .value_parser(SHARD_BY_NAMES)
+                 .default_value("range")
+                 .requires("shards")
+                 .help(
+"How to split the people among the --shards: range (the first shard gets
+the first people, and so on) or round-robin (one person per shard in
+turn, with every shard written at once)."))
+        .arg(Arg::new("cluster-by")
+                 .long("cluster-by")
+                 .value_name("KEY")
+                 .value_parser(CLUSTER_BY_NAMES)
+                 .help(
+"Write the people grouped by KEY, so columnar formats compress better and
+partitioned files come out smaller. The only key is birth_year: the birth
+dates are generated in order, oldest first, so each year's people are
+together."))
+        .arg(Arg::new("max-rows-per-file")
+                 .long("max-rows-per-file")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u64).range(1..))
+                 .conflicts_with_all(["postgres-url", "output-sink", "preview", "shards"])
+                 .help(
+"Start a new output file after every N people, numbering the files as
+--shards does: people-00000.csv, people-00001.csv, and so on."))
+        .arg(Arg::new("max-bytes-per-file")
+                 .long("max-bytes-per-file")
+                 .value_name("SIZE")
+                 .value_parser(parse_size)
+                 .conflicts_with_all(["postgres-url", "output-sink", "preview", "shards"])
+                 .help(
+"Start a new output file before a file gets bigger than SIZE bytes
+(e.g., 1G, 500M or 64K; at least 128K), numbering the files as
+--shards does. A file ends within 64K of SIZE. For compressed output,
+SIZE limits the uncompressed size. Not for Avro, Arrow or Excel
+output, which write in blocks."))
+        .arg(Arg::new("output-sink")
+                 .long("output")
+                 .value_name("SINK")
+                 .value_parser(["null"])
+                 .conflicts_with("postgres-url")
+                 .help(
+"Where to write the people instead of an output file. The only sink is
+null, which generates and formats the people as usual and throws them
+away, for timing a run or trying out options at full scale. Give just
+the total, with no output file. An output file of /dev/null does the
+same."))
+        .arg(Arg::new("preview")
+                 .long("preview")
+                 .value_name("N")
+                 .value_parser(clap::value_parser!(u64).range(1..))
+                 .conflicts_with_all(["postgres-url", "output-sink", "format"])
+                 .help(
+"Print N people to standard output, instead of writing an output file,
+to see what the other options produce. Give no output file or total.
+With an output file and total, print the first N people as a table, and
+write the file, too."))
+        .arg(Arg::new("preview-format")
+                 .long("preview-format")
+                 .value_name("FORMAT")
+                 .value_parser(PREVIEW_FORMAT_NAMES)
+                 .default_value("table")
+                 .requires("preview")
+                 .help(
+"How to print a --preview: table (an aligned table, with pretty
+headers), json or csv."))
+        .arg(Arg::new("dry-run")
+                 .long("dry-run")
+                 .action(ArgAction::SetTrue)
+                 .conflicts_with("preview")
+                 .help(
+"Check the options and read the names files, then say what would be
+generated (how many people of each gender, which files, which columns),
+without generating or writing anything."))
         .arg(Arg::new("output")
-                 .required(true)
+                 .required_unless_present_any(["postgres-url", "output-sink", "preview"])
                  .value_name("OUTPUT_FILE")
-                 .help("Path to output file"))
+                 .help("Path to output file, or - for standard output (as CSV, unless --format says otherwise)"))
         .arg(Arg::new("total")
-                 .required(true)
+                 .required_unless_present_any(["postgres-url", "output-sink", "preview"])
                  .value_name("TOTAL")
-                 .value_parser(clap::value_parser!(u64))
-                 .help("How many people to generate"))
-        .after_help(
-"Supports CSV, JSON, and JSON Lines output formats. The output format is
-determined by the output file extension (\".csv\", \".json\", or \".jsonl\").
-See https://github.com/bmc/peoplegen-rust for more information.");
+                 .value_parser(parse_count::<u64>)
+                 .help("How many people to generate, e.g., 1000, 1_000, 10k or 2.5M"))
+        .after_help(AFTER_HELP);
+
+    let parser = Command::new("peoplegen")
+        // See https://stackoverflow.com/a/27841363/53495
+        .version(env!("CARGO_PKG_VERSION"))
+        .author("bmc@clapper.org")
+        .about("Generate fake people data in a CSV")
+        // Without a subcommand, peoplegen generates, so generate's options
+        // work without it, too.
+        .args(generate.get_arguments().cloned())
+        .subcommand_negates_reqs(true)
+        .subcommand(generate.clone())
+        .subcommand(Command::new("schema")
+            .about("Print the schema of the generated data.")
+            .subcommand_required(true)
+            .subcommand(Command::new("ddl")
+                .about(
+"Print the CREATE TABLE statement for a table that holds the people,
+with the columns the other options select:
+
+  peoplegen schema ddl --ssn --salary --header-format pretty")
+                // The output file and total, and the Postgres sink's table,
+                // don't go with it.
+                .args(generate.get_arguments().filter(|a| !a.is_positional() && a.get_id() != "table").cloned())
+                .arg(Arg::new("dialect")
+                         .long("dialect")
+                         .value_name("DIALECT")
+                         .value_parser(SQL_DIALECT_NAMES)
+                         .default_value("postgres")
+                         .help(
+"The SQL dialect, which controls quoting and column types: postgres,
+mysql, sqlite or snowflake."))
+                .arg(Arg::new("table")
+                         .long("table")
+                         .value_name("[SCHEMA.]TABLE")
+                         .default_value(pg::DEFAULT_TABLE)
+                         .help("The table name."))))
+        .subcommand(Command::new("bench")
+            .about(
+"Generate TOTAL people into the null sink, and report how many rows a
+second were generated, and how many formatted, separately, to compare
+builds:
+
+  peoplegen bench --seed 1 --ssn --format jsonl 1000000")
+            // The people go nowhere, so there's no output file.
+            .args(generate.get_arguments().filter(|a| !a.is_positional()).cloned())
+            .arg(Arg::new("total")
+                     .required(true)
+                     .value_name("TOTAL")
+                     .value_parser(parse_count::<u64>)
+                     .help("How many people to generate, e.g., 1000, 1_000, 10k or 2.5M")))
+        .subcommand(Command::new("doctor")
+            .about(
+"Check what a run needs from its environment, before a long run starts:
+that the names files are set, readable, UTF-8 and not empty, that the
+output file's directory can be written to, and that there's room on its
+disk for the output, going by a sample of the people. Give it the run's
+options, output file and total:
+
+  peoplegen doctor --ssn people.csv.gz 10M
+
+Prints what it found, and what to do about each problem, and exits with
+status 7 if there are any.")
+            .args(generate.get_arguments().filter(|a| !a.is_positional()).cloned())
+            .arg(Arg::new("output")
+                     .value_name("OUTPUT_FILE")
+                     .help("The run's output file. Without it, only the names files are checked."))
+            .arg(Arg::new("total")
+                     .value_name("TOTAL")
+                     .value_parser(parse_count::<u64>)
+                     .help("The run's total, to estimate the output's size")))
+        .subcommand(Command::new("scenario")
+            .about(
+"Run the generation steps in a scenario file, in order, as one command.
+The steps share the seed, and their people share one ID space, so each
+step's IDs carry on from the step before's. See the README for the
+format.")
+            .arg(Arg::new("file")
+                     .required(true)
+                     .value_name("FILE")
+                     .help("The scenario file, in TOML"))
+            .arg(Arg::new("seed")
+                     .long("seed")
+                     .value_name("N")
+                     .value_parser(clap::value_parser!(u64))
+                     .help("The seed, in place of the file's. Default: the file's, or a random one")))
+        .subcommand(Command::new("fetch-names")
+            .about(
+"Download the SSA first names and Census Bureau last names data, and
+write the three names files that peoplegen uses. Files written to the
+default directory are used automatically, when the names files aren't
+specified on the command line or in the environment.")
+            .arg(Arg::new("dest")
+                     .long("dest")
+                     .value_name("DIR")
+                     .help(format!(
+"Where to write the names files. Default: {}", names_dir_default)))
+            .arg(Arg::new("force")
+                     .long("force")
+                     .action(ArgAction::SetTrue)
+                     .help("Download the data even if the names files exist.")))
+        .subcommand(Command::new("validate")
+            .about(
+"Check that a CSV, TSV, JSON or JSON Lines file of people still looks
+like one peoplegen wrote: that its columns are peoplegen's, and its
+dates, SSNs and salaries are valid. Prints each violation, and exits with
+status 7 if there are any.")
+            .arg(Arg::new("file")
+                     .required(true)
+                     .value_name("FILE")
+                     .help("The file to check"))
+            .arg(Arg::new("date-format")
+                     .long("date-format")
+                     .value_name("FORMAT")
+                     .default_value(DEFAULT_DATE_FORMAT)
+                     .help(
+"The strftime-style format of the dates, e.g., \"%m/%d/%Y\", if they were
+written with --format-field."))
+            .arg(Arg::new("salary-min")
+                     .long("salary-min")
+                     .value_name("N")
+                     .value_parser(clap::value_parser!(u64))
+                     .default_value("0")
+                     .help("The lowest valid salary."))
+            .arg(Arg::new("salary-max")
+                     .long("salary-max")
+                     .value_name("N")
+                     .value_parser(clap::value_parser!(u64))
+                     .help("The highest valid salary. Default: no limit"))
+            .arg(Arg::new("max-violations")
+                     .long("max-violations")
+                     .value_name("N")
+                     .value_parser(clap::value_parser!(usize))
+                     .help(format!(
+"How many violations to print. The rest are just counted. Default: {}", DEFAULT_MAX_VIOLATIONS))))
+        .after_help(AFTER_HELP);
 
-    let matches = parser.get_matches();
+    let command_line = options_after_subcommand(&parser, command_line);
+    let command_line = merge_config_file(&parser, command_line)?;
+    let top = parser.clone().get_matches_from(command_line);
+
+    let schema_ddl = top
+        .subcommand_matches("schema")
+        .and_then(|m| m.subcommand_matches("ddl"));
+
+    // Options before a subcommand only mean something for "schema ddl",
+    // whose options could only go there, before they could go after it, and
+    // for the subcommands that generate, which have them moved after the
+    // subcommand by now.
+    if let Some(m) = top.subcommand_matches("fetch-names") {
+        if options_given(&top, &[]) {
+            return Err(String::from("fetch-names doesn't take any options, except its own."));
+        }
+        let dest = PathBuf::from(m.get_one::<String>("dest").unwrap_or(&names_dir_default));
+        if path_is_empty(&dest) {
+            return Err(String::from(
+                "No home directory to put the names files in. Use --dest."
+            ));
+        }
+        return Ok(Action::FetchNames { dest, force: *m.get_one::<bool>("force").unwrap() });
+    }
+
+    if let Some(m) = top.subcommand_matches("validate") {
+        if options_given(&top, &[]) {
+            return Err(String::from("validate doesn't take any options, except its own."));
+        }
+        let options = ValidateOptions {
+            date_format: m.get_one::<String>("date-format").cloned().unwrap(),
+            salary_min: m.get_one::<u64>("salary-min").copied().unwrap(),
+            salary_max: m.get_one::<u64>("salary-max").copied(),
+            max_violations: m.get_one::<usize>("max-violations").copied().unwrap_or(DEFAULT_MAX_VIOLATIONS),
+        };
+        if options.salary_max.is_some_and(|max| max < options.salary_min) {
+            return Err(String::from("--salary-max can't be less than --salary-min."));
+        }
+        return Ok(Action::Validate { path: PathBuf::from(m.get_one::<String>("file").unwrap()), options });
+    }
+
+    if let Some(m) = top.subcommand_matches("scenario") {
+        if options_given(&top, &[]) {
+            return Err(String::from("scenario doesn't take any options, except its own."));
+        }
+        return parse_scenario_steps(m, &generate);
+    }
+
+    let bench = top.subcommand_matches("bench");
+    if let Some(m) = bench {
+        // These say where the people go, and they go nowhere.
+        for (id, option) in BENCH_EXCLUDED_OPTIONS {
+            if m.value_source(id) == Some(ValueSource::CommandLine) {
+                return Err(format!("bench writes to the null sink, so it doesn't take {option}."));
+            }
+        }
+    }
+
+    let doctor = top.subcommand_matches("doctor");
+
+    // The options come from the subcommand that has them, or, without one,
+    // from the top level.
+    let (command, matches) = match (top.subcommand_matches("generate").or(bench).or(doctor), schema_ddl) {
+        (Some(m), _) if bench.is_some() => {
+            (parser.find_subcommand("bench").unwrap().clone(), m.clone())
+        },
+        (Some(m), _) if doctor.is_some() => {
+            (parser.find_subcommand("doctor").unwrap().clone(), m.clone())
+        },
+        (Some(m), _) => (generate, m.clone()),
+        (_, Some(m)) if options_given(m, &["dialect", "table"]) => {
+            if options_given(&top, &[]) {
+                return Err(String::from("Options go before \"schema\" or after \"ddl\", not both."));
+            }
+            let ddl = parser.find_subcommand("schema").and_then(|c| c.find_subcommand("ddl")).unwrap();
+            (ddl.clone(), m.clone())
+        },
+        _ => (parser.clone(), top.clone()),
+    };
 
     // NOTE: It's okay to use unwrap() rather than unwrap_or() on arguments
     // with a default, because they'll never come back as None.
@@ -215,23 +1553,27 @@ See https://github.com/bmc/peoplegen-rust for more information.");
         // In this case, get_one() actually returns a reference to a u32.
         // We can use map() to dereference it.
         .get_one::<u32>("female")
-        .map(|reference| *reference)
+        .copied()
         .unwrap();
     let male_percent = matches
         .get_one::<u32>("male")
-        .map(|reference| *reference)
+        .copied()
+        .unwrap();
+    let other_percent = matches
+        .get_one::<u32>("other")
+        .copied()
         .unwrap();
     let year_min = matches
         .get_one::<u32>("year-min")
-        .map(|reference| *reference)
+        .copied()
         .unwrap_or_else(|| year_before_now(STARTING_YEAR_DEFAULT_DELTA));
     let year_max = matches
         .get_one::<u32>("year-max")
-        .map(|reference| *reference)
+        .copied()
         .unwrap_or_else(|| year_before_now(ENDING_YEAR_DEFAULT_DELTA));
     let header_format = matches
         .get_one::<String>("header-format")
-        .map(|s| parse_header_format(s))
+        .map(parse_header_format)
         .unwrap()?;
     let male_first_names_file = matches
         .get_one::<String>("male-first-names")
@@ -239,95 +1581,857 @@ See https://github.com/bmc/peoplegen-rust for more information.");
     let female_first_names_file = matches
         .get_one::<String>("female-first-names")
         .unwrap_or(&female_first_names_default);
+    let other_first_names_file = matches
+        .get_one::<String>("other-first-names")
+        .map(PathBuf::from);
     let last_names_file = matches
         .get_one::<String>("last-names")
         .unwrap_or(&last_names_default);
+    let field_formats = matches
+        .get_many::<String>("format-field")
+        .unwrap_or_default()
+        .map(|s| FieldFormat::parse(s, &FIELD_NAMES))
+        .collect::<Result<Vec<FieldFormat>, String>>()?;
+    let field_ciphers = matches
+        .get_many::<String>("encrypt-field")
+        .unwrap_or_default()
+        .map(|s| FieldCipher::parse(s, &FIELD_NAMES))
+        .collect::<Result<Vec<FieldCipher>, String>>()?;
+    let assertions = matches
+        .get_many::<String>("assert")
+        .unwrap_or_default()
+        .map(|s| Assertion::parse(s, &FIELD_NAMES))
+        .collect::<Result<Vec<Assertion>, String>>()?;
+    let field_hashes = matches
+        .get_many::<String>("hash-field")
+        .unwrap_or_default()
+        .map(|s| FieldHash::parse(s, &FIELD_NAMES))
+        .collect::<Result<Vec<FieldHash>, String>>()?;
+    let pronouns = if *matches.get_one::<bool>("pronouns").unwrap() {
+        Some(PronounOptions {
+            mappings: matches
+                .get_many::<String>("pronoun")
+                .unwrap_or_default()
+                .map(|s| parse_pronoun_mapping(s))
+                .collect::<Result<Vec<(String, String)>, String>>()?,
+            ask_percent: matches
+                .get_one::<u32>("pronouns-ask")
+                .copied()
+                .unwrap_or(0),
+        })
+    }
+    else {
+        None
+    };
+    let ad_domain = matches
+        .get_one::<String>("ad-domain")
+        .cloned()
+        .unwrap();
+    let base_dn = matches
+        .get_one::<String>("base-dn")
+        .cloned()
+        .unwrap();
     let salary_mean = matches
         .get_one::<u32>("salary-mean")
-        .map(|reference| *reference)
+        .copied()
         .unwrap();
     let salary_sigma = matches
         .get_one::<u32>("salary-sigma")
-        .map(|reference| *reference)
+        .copied()
         .unwrap();
-    let output_file = matches
-        .get_one::<String>("output")
+    let salary_rounding = match (matches.get_one::<u32>("salary-round"),
+                                 matches.get_one::<u32>("salary-precision")) {
+        (Some(n), _) => SalaryRounding::Nearest(*n),
+        (_, Some(digits)) => SalaryRounding::Significant(*digits),
+        (None, None) => SalaryRounding::None,
+    };
+    let salary_mix = matches
+        .get_one::<String>("salary-mix")
+        .map(|s| parse_salary_mix(s))
+        .transpose()?;
+    let salary_percentiles = matches.get_one::<u32>("salary-p50").map(|p50| SalaryPercentiles {
+        p50: *p50,
+        p90: *matches.get_one::<u32>("salary-p90").unwrap(),
+    });
+    let postgres = matches.get_one::<String>("postgres-url").map(|url| PostgresOptions {
+        url: url.to_string(),
+        table: matches.get_one::<String>("table").cloned().unwrap(),
+        truncate: *matches.get_one::<bool>("truncate").unwrap(),
+        batch_size: *matches.get_one::<u64>("batch-size").unwrap() as usize,
+    });
+    // With --postgres-url or --output, there's no output file, so the only
+    // positional argument (which the parser thinks is the output file) is the
+    // total. The null sink is written as NULL_PATH from here on.
+    let no_output_file = match (&postgres, matches.get_one::<String>("output-sink")) {
+        (Some(_), _) => Some((PathBuf::new(), "--postgres-url")),
+        (None, Some(_)) => Some((PathBuf::from(NULL_PATH), "--output")),
+        (None, None) => None,
+    };
+    let preview = matches.get_one::<u64>("preview").copied();
+    // With an output file, a preview is printed, and the file written, too.
+    let preview_only = preview.is_some() && matches.get_one::<String>("output").is_none();
+    if preview.is_some() && !preview_only {
+        if matches.get_one::<u64>("total").is_none() {
+            return Err(String::from("With --preview and an output file, give the total, too."));
+        }
+        if matches.value_source("preview-format") == Some(ValueSource::CommandLine) {
+            return Err(String::from("--preview-format only applies to a --preview with no output file."));
+        }
+    }
+    let rollover = match (matches.get_one::<u64>("max-rows-per-file"),
+                          matches.get_one::<u64>("max-bytes-per-file")) {
+        (None, None) => None,
+        (max_rows, max_bytes) => Some(Rollover { max_rows: max_rows.copied(), max_bytes: max_bytes.copied() }),
+    };
+    let shards = matches.get_one::<u64>("shards").map(|n| Shards {
+        count: *n as usize,
+        by: matches.get_one::<String>("shard-by")
+            .map(String::as_str)
+            .and_then(ShardBy::from_name)
+            .unwrap_or_default(),
+    });
+    let (output_file, total) = if schema_ddl.is_some() {
+        // Nothing's generated, so there's no output or total.
+        (PathBuf::new(), 0)
+    }
+    else if bench.is_some() {
+        (PathBuf::from(NULL_PATH), matches.get_one::<u64>("total").copied().unwrap())
+    }
+    else {
+        match (no_output_file, matches.get_one::<String>("output")) {
+            // A preview goes to standard output, and says how many people.
+            _ if preview_only => (PathBuf::from(STDOUT_PATH), preview.unwrap()),
+            (Some((path, _)), Some(s)) if matches.get_one::<u64>("total").is_none() => {
+                let total = s.parse::<u64>()
+                    .map_err(|_| format!("Bad total \"{s}\": expected a number"))?;
+                (path, total)
+            },
+            (Some((_, option)), _) => {
+                return Err(format!("With {option}, give only the total, not an output file."));
+            },
+            // Only doctor can go without them.
+            (None, output) => (
+                output.map(PathBuf::from).unwrap_or_default(),
+                matches.get_one::<u64>("total").copied().unwrap_or_default()
+            ),
+        }
+    };
+    // Without --gender-config, a gender configuration file in the config
+    // directory is used, unless the command line says how to do genders.
+    let genders_on_command_line = ["female", "male", "other", "female-first-names", "male-first-names",
+                                   "other-first-names"]
+        .iter()
+        .any(|a| matches.value_source(a) == Some(ValueSource::CommandLine));
+    let gender_config = matches
+        .get_one::<String>("gender-config")
         .map(PathBuf::from)
-        .unwrap();
-    let total = matches
-        .get_one::<u64>("total")
-        .map(|reference| *reference)
-        .unwrap();
-
-    let output_format = match file_extension(&output_file) {
-        Some("csv") => Ok(OutputFormat::Csv),
-        Some("json") => Ok(OutputFormat::JsonPretty),
-        Some("jsonl") => Ok(OutputFormat::JsonL),
-        Some(_) | None => Err(format!(
-            "Output file \"{}\" must end in \".csv\", \".json\" or \".jsonl\".",
+        .or_else(|| {
+            if genders_on_command_line {
+                None
+            }
+            else {
+                find_file(config_dir(), GENDER_CONFIG_FILE)
+            }
+        });
+    let template_file = matches
+        .get_one::<String>("template")
+        .map(PathBuf::from);
+    let documents_dir = matches
+        .get_one::<String>("documents-dir")
+        .map(PathBuf::from);
+    let avatars_dir = matches
+        .get_one::<String>("avatars")
+        .map(PathBuf::from);
+    let avatar_base_url = matches
+        .get_one::<String>("avatar-base-url")
+        .cloned();
+    let barcode_source = matches
+        .get_one::<String>("barcode")
+        .map(|s| {
+            if s == "ssn" { BarcodeSource::Ssn } else { BarcodeSource::Id }
+        });
+    let barcodes_dir = matches
+        .get_one::<String>("barcodes")
+        .map(PathBuf::from);
+    let parse_date = |name: &str, default: NaiveDate| {
+        matches.get_one::<String>(name).map_or(Ok(default), |s| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map_err(|_| format!("Bad --{name} date \"{s}\": expected YYYY-MM-DD"))
+        })
+    };
+    let metadata = if *matches.get_one::<bool>("metadata").unwrap() {
+        Some(MetadataOptions {
+            created_min: parse_date("created-min", now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))?,
+            created_max: parse_date("created-max", now_minus_days(0))?,
+            source_systems: parse_source_systems(
+                matches.get_one::<String>("source-systems").unwrap()
+            )?,
+        })
+    }
+    else {
+        None
+    };
+    let consent = if *matches.get_one::<bool>("consent").unwrap() {
+        Some(ConsentOptions {
+            email_percent: matches.get_one::<u32>("email-opt-in").copied().unwrap_or(DEFAULT_EMAIL_OPT_IN),
+            sms_percent: matches.get_one::<u32>("sms-opt-in").copied().unwrap_or(DEFAULT_SMS_OPT_IN),
+            recorded_min: parse_date("consent-min", now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))?,
+            recorded_max: parse_date("consent-max", now_minus_days(0))?,
+            versions: WeightedValues::parse_list(
+                matches.get_one::<String>("consent-versions").unwrap(), "consent version"
+            )?,
+        })
+    }
+    else {
+        None
+    };
+    let lifecycle = if *matches.get_one::<bool>("lifecycle").unwrap() {
+        Some(LifecycleOptions {
+            signup_min: parse_date("signup-min", now_minus_days(CREATED_DEFAULT_WINDOW_DAYS))?,
+            signup_max: parse_date("signup-max", now_minus_days(0))?,
+            churn_percent: matches.get_one::<u32>("churn-rate").copied().unwrap_or(DEFAULT_CHURN_RATE),
+        })
+    }
+    else {
+        None
+    };
+    let loyalty = if *matches.get_one::<bool>("loyalty").unwrap() {
+        Some(LoyaltyOptions {
+            tiers: WeightedValues::parse_list(matches.get_one::<String>("loyalty-tiers").unwrap(), "loyalty tier")?,
+            member_min: parse_date("member-min", now_minus_days(MEMBER_DEFAULT_WINDOW_DAYS))?,
+            member_max: parse_date("member-max", now_minus_days(0))?,
+        })
+    }
+    else {
+        None
+    };
+    let vin = if *matches.get_one::<bool>("vin").unwrap() {
+        Some(VinOptions {
+            wmis: WeightedValues::parse_list(matches.get_one::<String>("vin-wmis").unwrap(), "WMI")?,
+            model_year_min: matches.get_one::<u32>("model-year-min").copied()
+                .unwrap_or_else(|| year_before_now(MODEL_YEAR_DEFAULT_SPAN)),
+            model_year_max: matches.get_one::<u32>("model-year-max").copied()
+                .unwrap_or_else(|| year_before_now(0) + 1),
+        })
+    }
+    else {
+        None
+    };
+    let ssn_format = matches
+        .get_one::<String>("ssn-format")
+        .map(|s| SsnFormat::parse(s))
+        .unwrap()?;
+    let sql_dialect = matches
+        .get_one::<String>("sql-dialect")
+        .map(|s| SqlDialect::parse(s))
+        .unwrap()?;
+    let ssn_prefixes = matches
+        .get_one::<u32>("random-ssn-prefixes")
+        .map(|n| *n as usize);
+    let aggregates_dir = matches
+        .get_one::<String>("aggregates")
+        .map(PathBuf::from);
+    let shifts_file = matches
+        .get_one::<String>("shifts")
+        .map(PathBuf::from);
+    let shifts = match &shifts_file {
+        Some(_) => Some(ShiftOptions {
+            week_start: parse_date("shift-start", this_monday())?,
+            weeks: *matches.get_one::<u32>("shift-weeks").unwrap(),
+            departments: parse_departments(matches.get_one::<String>("departments").unwrap())?,
+        }),
+        None => None,
+    };
+    let benefits_dir = matches
+        .get_one::<String>("benefits")
+        .map(PathBuf::from);
+    let benefits = benefits_dir.as_ref().map(|_| BenefitsOptions {
+        plan_year: matches.get_one::<i32>("plan-year").copied()
+            .unwrap_or_else(|| year_before_now(0) as i32),
+    });
+    let w2_file = matches
+        .get_one::<String>("w2")
+        .map(PathBuf::from);
+    let w2 = w2_file.as_ref().map(|_| W2Options {
+        tax_year: matches.get_one::<i32>("tax-year").copied().unwrap_or_else(default_tax_year),
+        years: *matches.get_one::<u32>("w2-years").unwrap(),
+    });
+    let transactions_file = matches
+        .get_one::<String>("transactions")
+        .map(PathBuf::from);
+    let transactions = match &transactions_file {
+        Some(_) => Some(TransactionOptions {
+            per_person: matches.get_one::<u32>("transactions-per-person").copied()
+                .unwrap_or(DEFAULT_TRANSACTIONS_PER_PERSON),
+            min_date: parse_date("transaction-min", now_minus_days(TRANSACTION_DEFAULT_WINDOW_DAYS))?,
+            max_date: parse_date("transaction-max", now_minus_days(0))?,
+        }),
+        None => None,
+    };
+    let immunizations_file = matches
+        .get_one::<String>("immunizations")
+        .map(PathBuf::from);
+    let immunizations = match &immunizations_file {
+        Some(_) => Some(ImmunizationOptions {
+            value_set: match matches.get_one::<String>("immunization-codes") {
+                Some(path) => read_value_set(Path::new(path))?,
+                None => default_value_set(),
+            },
+            max_per_person: matches.get_one::<u32>("max-immunizations").copied()
+                .unwrap_or(DEFAULT_MAX_IMMUNIZATIONS),
+            max_date: parse_date("immunization-max", now_minus_days(0))?,
+        }),
+        None => None,
+    };
+    let ssn_collisions_file = matches
+        .get_one::<String>("ssn-collisions")
+        .map(PathBuf::from);
+    let layout = match (matches.get_one::<String>("layout"),
+                        matches.get_one::<String>("layout-file")) {
+        (Some(s), _) => Some(parse_layout(s, &FIELD_NAMES)?),
+        (_, Some(path)) => Some(read_layout_file(Path::new(path), &FIELD_NAMES)?),
+        (None, None) => None,
+    };
+    let rules = match matches.get_one::<String>("rules") {
+        Some(path) => Some(Rules {
+            rules: read_rules_file(Path::new(path), &FIELD_NAMES)?,
+            today: Utc::now().date_naive(),
+        }),
+        None => None,
+    };
+    let proto_file = matches
+        .get_one::<String>("emit-proto")
+        .map(PathBuf::from);
+    let seed = matches
+        .get_one::<u64>("seed")
+        .copied();
+    // "people.csv.gz" is compressed CSV.
+    let (format_file, compression) = split_compression(&output_file);
+    // --format wins over the extension, so any name will do.
+    let format_name = matches
+        .get_one::<String>("format")
+        .map(String::as_str)
+        .or_else(|| file_extension(&format_file));
+    let output_format = match format_name.and_then(OutputFormat::from_name) {
+        _ if postgres.is_some() => Ok(OutputFormat::Postgres),
+        // There's no output file; the format doesn't change the columns.
+        _ if schema_ddl.is_some() => Ok(OutputFormat::default()),
+        _ if preview_only => Ok(match matches.get_one::<String>("preview-format")
+                                            .map(String::as_str)
+                                            .and_then(PreviewFormat::from_name) {
+            Some(PreviewFormat::Json) => OutputFormat::JsonPretty,
+            Some(PreviewFormat::Csv) => OutputFormat::Csv,
+            _ => OutputFormat::Table,
+        }),
+        Some(format) => Ok(format),
+        // Standard output and nowhere have no extension to go by, and
+        // neither does doctor, without an output file.
+        None if is_stdout(&output_file) || is_null(&output_file) || path_is_empty(&output_file) => {
+            Ok(OutputFormat::default())
+        },
+        None => Err(format!(
+            "Output file \"{}\" must end in \".csv\", \".tsv\", \".json\", \".jsonl\", \".avro\", \".arrow\", \
+             \".feather\", \".xlsx\", \".sql\", \".pb\", \".dat\", \".vcf\", \".ldif\", \".html\" \
+             or \".md\", optionally followed by \".gz\", \".zst\" or \".bz2\", or --format must say \
+             what it is.",
             output_file.display()
         )),
     }?;
+    // HTML and Markdown are for people, so they get readable headers,
+    // unless asked not to.
+    let for_people = matches!(output_format, OutputFormat::Html | OutputFormat::Markdown | OutputFormat::Table);
+    let header_format = match matches.value_source("header-format") {
+        Some(ValueSource::CommandLine) => header_format,
+        _ if for_people => HeaderFormat::Pretty,
+        _ => header_format,
+    };
 
-    validate(Arguments {
+    let mut args = Arguments {
         female_percent,
         male_percent,
+        other_percent,
+        no_gender: *matches.get_one::<bool>("no-gender").unwrap(),
+        gender_values: matches.get_one::<GenderValues>("gender-values").cloned(),
+        gender_config,
+        pronouns,
         generate_ssns: *matches.get_one::<bool>("ssn").unwrap(),
+        npi: *matches.get_one::<bool>("npi").unwrap(),
+        dea: *matches.get_one::<bool>("dea").unwrap(),
+        vin,
+        ssn_format,
+        ssn_prefixes,
         generate_ids: *matches.get_one::<bool>("id").unwrap(),
         generate_salaries: *matches.get_one::<bool>("salary").unwrap(),
         salary_mean,
         salary_sigma,
+        salary_percentiles,
+        salary_mix,
+        salary_rounding,
         header_format,
+        sort_keys: *matches.get_one::<bool>("sort-keys").unwrap(),
+        long: *matches.get_one::<bool>("long").unwrap(),
+        delimiter: matches.get_one::<u8>("delimiter").copied().or_else(|| {
+            if format_name == Some("tsv") { Some(b'\t') } else { None }
+        }),
+        quote_all: *matches.get_one::<bool>("quote-all").unwrap(),
+        crlf: *matches.get_one::<bool>("crlf").unwrap(),
+        bom: *matches.get_one::<bool>("bom").unwrap(),
+        field_formats,
+        field_ciphers,
+        field_hashes,
+        ad_domain,
+        base_dn,
         year_min,
         year_max,
         male_first_names_file: PathBuf::from(male_first_names_file),
         female_first_names_file: PathBuf::from(female_first_names_file),
+        other_first_names_file,
         last_names_file: PathBuf::from(last_names_file),
         output_format,
-        output_file: output_file,
+        compression,
+        sql_dialect,
+        postgres,
+        output_file,
+        seed: seed.unwrap_or_else(rand::random),
+        random_seed: seed.is_none(),
+        json_summary: *matches.get_one::<bool>("json-summary").unwrap(),
+        summary_json_file: matches.get_one::<String>("summary-json").map(PathBuf::from),
+        otlp_endpoint: matches
+            .get_one::<String>("otlp-endpoint")
+            .cloned()
+            .or_else(|| Some(getenv(telemetry::ENV_OTLP_ENDPOINT)).filter(|s| !s.is_empty())),
+        verbosity: matches.get_count("verbose"),
+        strict: *matches.get_one::<bool>("strict").unwrap(),
+        quiet: matches.get_flag("quiet"),
+        preview: preview_only,
+        preview_rows: preview.filter(|_| !preview_only),
+        dry_run: matches.get_flag("dry-run"),
+        shards,
+        cluster_by: matches.get_one::<String>("cluster-by").map(String::as_str).and_then(ClusterBy::from_name),
+        rollover,
+        assertions,
+        template_file,
+        documents_dir,
+        documents_as_pdf: *matches.get_one::<bool>("pdf").unwrap(),
+        avatars_dir,
+        avatar_base_url,
+        barcode_source,
+        barcodes_dir,
+        metadata,
+        device: *matches.get_one::<bool>("device").unwrap(),
+        consent,
+        lifecycle,
+        loyalty,
+        ssn_collisions_file,
+        config_dump: None,
+        aggregates_dir,
+        stats: *matches.get_one::<bool>("stats").unwrap(),
+        shifts_file,
+        shifts,
+        benefits_dir,
+        benefits,
+        w2_file,
+        w2,
+        transactions_file,
+        transactions,
+        immunizations_file,
+        immunizations,
+        proto_file,
+        layout,
+        rules,
+        unique_ssns: *matches.get_one::<bool>("unique-ssns").unwrap(),
+        id_offset: 0,
         total
+    };
+
+    if let Some(path) = matches.get_one::<String>(DUMP_CONFIG_OPTION) {
+        let toml = effective_config(&command, &matches, &resolved_options(&args));
+        if path == STDOUT_PATH {
+            return validate(args).map(|_| Action::DumpConfig(toml));
+        }
+        args.config_dump = Some(ConfigDump { path: PathBuf::from(path), toml });
+    }
+
+    match schema_ddl {
+        // The DDL doesn't depend on the names files, so they needn't exist.
+        Some(m) => Ok(Action::SchemaDdl {
+            args: Box::new(args),
+            dialect: m.get_one::<String>("dialect").map(|s| SqlDialect::parse(s)).unwrap()?,
+            table: m.get_one::<String>("table").cloned().unwrap(),
+        }),
+        None if bench.is_some() => validate(args).map(|args| Action::Bench(Box::new(args))),
+        // Missing names files are what doctor is for, so it reports them,
+        // instead.
+        None if doctor.is_some() => Ok(Action::Doctor(Box::new(args))),
+        None => validate(args).map(|args| Action::Generate(Box::new(args))),
+    }
+}
+
+/**
+ * Parse the steps of `peoplegen scenario`, giving each the scenario's seed
+ * and the next of its IDs.
+ *
+ * # Arguments
+ *
+ * - `matches`: The `scenario` subcommand's part of the command line
+ * - `generate`: The `generate` subcommand, whose options the steps give
+ *
+ * # Returns
+ *
+ * - `Ok(Action::Scenario)`: The steps, ready to run
+ * - `Err(msg)`: The file or a step is invalid; `msg` explains why.
+ */
+fn parse_scenario_steps(matches: &ArgMatches, generate: &Command) -> Result<Action, String> {
+    let scenario = read_scenario(Path::new(matches.get_one::<String>("file").unwrap()), generate)?;
+    let given = matches.get_one::<u64>("seed").copied().or(scenario.seed);
+    let seed = given.unwrap_or_else(rand::random);
+    let mut steps = Vec::with_capacity(scenario.steps.len());
+    let mut id_offset = 0;
+
+    for (i, step) in scenario.steps.into_iter().enumerate() {
+        let command_line = ["peoplegen", "generate", "--seed", &step_seed(seed, i).to_string()]
+            .map(String::from)
+            .into_iter()
+            .chain(step.args)
+            .collect();
+        let mut args = match parse_command_line(command_line).map_err(|e| format!("{}: {}", step.label, e))? {
+            Action::Generate(args) => args,
+            _ => return Err(format!("{} doesn't generate anyone.", step.label)),
+        };
+        args.id_offset = id_offset;
+        id_offset += args.total;
+        // The steps share the SSNs, as well as the IDs.
+        if args.unique_ssns && id_offset > ssn_generator(&args).total() {
+            return Err(format!(
+                "{}: Can't generate {} people with unique SSNs, with the steps before: there are only {} fake SSNs.",
+                step.label, id_offset, ssn_generator(&args).total()
+            ));
+        }
+        steps.push(*args);
+    }
+
+    if let Some(path) = written_twice(&steps) {
+        return Err(format!("More than one step writes \"{}\".", path.display()));
+    }
+
+    Ok(Action::Scenario { steps, seed, random_seed: given.is_none() })
+}
+
+/**
+ * Whether a command line gives any options (as opposed to their defaults).
+ *
+ * # Arguments
+ *
+ * - `matches`: The parsed command line, or a subcommand's part of it
+ * - `except`: The ids of options that don't count
+ */
+fn options_given(matches: &ArgMatches, except: &[&str]) -> bool {
+    matches.ids().any(|id| {
+        !except.contains(&id.as_str()) && matches.value_source(id.as_str()) == Some(ValueSource::CommandLine)
     })
 }
 
+/**
+ * Get the option values a run works out for itself, for `--dump-config`:
+ * those from the environment, the date or a random number generator, and
+ * the header format, whose default depends on the output format.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * The values, by option id. `None` means the option is left out.
+ */
+fn resolved_options(args: &Arguments) -> Vec<(&'static str, Option<toml::Value>)> {
+    let string = |s: String| Some(toml::Value::String(s));
+    let path = |p: &Path| Some(path_str(p)).filter(|s| !s.is_empty()).map(|s| toml::Value::String(s.to_string()));
+    let integer = |n: u64| Some(i64::try_from(n).map_or(toml::Value::String(n.to_string()), toml::Value::Integer));
+    let header_format = match args.header_format {
+        HeaderFormat::SnakeCase => "snake",
+        HeaderFormat::CamelCase => "camel",
+        HeaderFormat::Pretty => "pretty",
+        HeaderFormat::ActiveDirectory => "ad",
+    };
+
+    let mut resolved = vec![
+        ("seed", integer(args.seed)),
+        ("year-min", integer(args.year_min.into())),
+        ("year-max", integer(args.year_max.into())),
+        ("header-format", string(header_format.to_string())),
+        ("last-names", path(&args.last_names_file)),
+    ];
+
+    // A gender configuration file replaces the percentages and first names.
+    match &args.gender_config {
+        Some(gender_config) => resolved.extend([
+            ("gender-config", path(gender_config)),
+            ("female", None),
+            ("male", None),
+            ("other", None),
+            ("female-first-names", None),
+            ("male-first-names", None),
+            ("other-first-names", None),
+        ]),
+        None => resolved.extend([
+            ("female-first-names", path(&args.female_first_names_file)),
+            ("male-first-names", path(&args.male_first_names_file)),
+        ]),
+    }
+
+    if let Some(m) = &args.metadata {
+        resolved.push(("created-min", string(m.created_min.to_string())));
+        resolved.push(("created-max", string(m.created_max.to_string())));
+    }
+
+    if let Some(c) = &args.consent {
+        resolved.push(("consent-min", string(c.recorded_min.to_string())));
+        resolved.push(("consent-max", string(c.recorded_max.to_string())));
+    }
+
+    if let Some(l) = &args.lifecycle {
+        resolved.push(("signup-min", string(l.signup_min.to_string())));
+        resolved.push(("signup-max", string(l.signup_max.to_string())));
+    }
+
+    if let Some(l) = &args.loyalty {
+        resolved.push(("member-min", string(l.member_min.to_string())));
+        resolved.push(("member-max", string(l.member_max.to_string())));
+    }
+
+    if let Some(v) = &args.vin {
+        resolved.push(("model-year-min", integer(v.model_year_min.into())));
+        resolved.push(("model-year-max", integer(v.model_year_max.into())));
+    }
+
+    if let Some(s) = &args.shifts {
+        resolved.push(("shift-start", string(s.week_start.to_string())));
+    }
+
+    if let Some(b) = &args.benefits {
+        resolved.push(("plan-year", integer(b.plan_year as u64)));
+    }
+
+    if let Some(i) = &args.immunizations {
+        resolved.push(("immunization-max", string(i.max_date.to_string())));
+    }
+
+    if let Some(w) = &args.w2 {
+        resolved.push(("tax-year", integer(w.tax_year as u64)));
+    }
+
+    if let Some(t) = &args.transactions {
+        resolved.push(("transaction-min", string(t.min_date.to_string())));
+        resolved.push(("transaction-max", string(t.max_date.to_string())));
+    }
+
+    resolved
+}
+
+/// The default for a names file: the environment variable, if it's set, or
+/// the file fetched by "peoplegen fetch-names", if there is one.
+pub fn names_file_default(env_var: &str, file_name: &str) -> String {
+    let from_env = getenv(env_var);
+    if !from_env.is_empty() {
+        return from_env;
+    }
+
+    default_names_file(file_name)
+        .map(|p| path_str(&p).to_string())
+        .unwrap_or_default()
+}
+
 /// Given the current date, return the year `years` ago.
-fn year_before_now(years: u32) -> u32 {
+pub fn year_before_now(years: u32) -> u32 {
     // There's no Duration::years(), so just use weeks and multiply.
     let y = years as i64;
     (Utc::now() - Duration::weeks(y * 52)).year() as u32
 }
 
+/// Return the date `days` days before today.
+fn now_minus_days(days: i64) -> NaiveDate {
+    (Utc::now() - Duration::days(days)).date_naive()
+}
+
+/// Return the default `--tax-year`: last year, the latest with a whole
+/// year's wages, if there's a wage base for it.
+fn default_tax_year() -> i32 {
+    (year_before_now(1) as i32).clamp(FIRST_TAX_YEAR, LAST_TAX_YEAR)
+}
+
+/// Return this week's Monday (today, if it's a Monday).
+fn this_monday() -> NaiveDate {
+    now_minus_days(Utc::now().weekday().num_days_from_monday().into())
+}
+
+/// Parse a `--delimiter` value: a single ASCII character, other than a
+/// quote or a line break, or "tab" or "\t" for a tab.
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() && !matches!(s, "\"" | "\n" | "\r") => Ok(s.as_bytes()[0]),
+        _ => Err(String::from("must be a single ASCII character, other than a quote or a line break")),
+    }
+}
+
+/// Parse a `--departments` list, checking that each is a department.
+fn parse_departments(s: &str) -> Result<WeightedValues, String> {
+    let departments = WeightedValues::parse_list(s, "department")?;
+    for name in departments.values() {
+        Department::parse(name)?;
+    }
+    Ok(departments)
+}
+
+/// Find a field in the fixed-width layout that the other options don't
+/// generate, if there is one.
+fn missing_layout_field(args: &Arguments) -> Option<&LayoutField> {
+    let fields = output_fields(args);
+    args.layout
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .find(|f| f.field != FILLER && !fields.contains(&f.field))
+}
+
+/// Find a field that's given more than one `--hash-field`, if there is one.
+/// Its hash field would have the same name each time.
+fn twice_hashed_field(args: &Arguments) -> Option<&str> {
+    args.field_hashes
+        .iter()
+        .enumerate()
+        .find(|(i, h)| args.field_hashes[..*i].iter().any(|other| other.field == h.field))
+        .map(|(_, h)| h.field.as_str())
+}
+
 /// Cross-validate the parsed arguments.
-fn validate(args: Arguments) -> Result<Arguments, String> {
-    if (args.female_percent + args.male_percent) != 100 {
+pub fn validate(args: Arguments) -> Result<Arguments, String> {
+    // A gender configuration file replaces the percentages and the male and
+    // female names files; it's checked when it's loaded.
+    let default_genders = args.gender_config.is_none();
+    let gender_percent = [args.female_percent, args.male_percent, args.other_percent].map(u64::from).iter().sum::<u64>();
+
+    if default_genders && gender_percent != 100 && args.other_percent == 0 {
         Err(String::from("Female and male percentages must add up to 100."))
     }
 
+    else if default_genders && gender_percent != 100 {
+        Err(format!("Female, male and other percentages must add up to 100, not {gender_percent}."))
+    }
+
+    else if let (Some(path), Some(_)) = (&args.gender_config, &args.gender_values) {
+        // --gender-config conflicts with it in clap; this is for the
+        // config directory's file, which is used without being asked for.
+        Err(format!(
+            "--gender-values can't be used with the gender configuration file \"{}\", which already says what to write.",
+            path.display()
+        ))
+    }
+
+    else if args.other_percent > 0 && args.gender_values.as_ref().is_some_and(|v| v.nonbinary.is_none()) {
+        Err(String::from("With --other-pct, --gender-values needs a third value, for nonbinary people."))
+    }
+
     else if args.year_min > args.year_max {
-        Err(String::from(format!(
+        Err(format!(
             "Minimum year {} exceeds maximum year {}.",
             args.year_min, args.year_max
-        )))
+        ))
+    }
+
+    else if args.unique_ssns && args.total > ssn_generator(&args).total() {
+        Err(format!(
+            "Can't generate {} people with unique SSNs: there are only {} fake SSNs.",
+            args.total.separate_with_commas(),
+            ssn_generator(&args).total().separate_with_commas()
+        ))
     }
 
-    else if path_is_empty(&args.male_first_names_file) {
-        Err(String::from(format!(
-            "Male first names file not specified, and {} not set in environment.",
+    else if default_genders && path_is_empty(&args.male_first_names_file) {
+        Err(format!(
+            "Male first names file not specified, and {} not set in environment. Run \"peoplegen fetch-names\" to download names files.",
             ENV_MALE_FIRST_NAMES_FILE
-        )))
+        ))
     }
 
-    else if path_is_empty(&args.female_first_names_file) {
-        Err(String::from(format!(
-            "Female first names file not specified, and {} not set in environment.",
+    else if default_genders && path_is_empty(&args.female_first_names_file) {
+        Err(format!(
+            "Female first names file not specified, and {} not set in environment. Run \"peoplegen fetch-names\" to download names files.",
             ENV_FEMALE_FIRST_NAMES_FILE
-        )))
+        ))
+    }
+
+    else if let Some(Err(e)) = args.salary_percentiles.as_ref().map(fit_log_normal) {
+        Err(e)
+    }
+
+    else if args.output_format == OutputFormat::Xlsx && args.total >= xlsx::MAX_ROWS {
+        Err(format!(
+            "Excel worksheets are limited to {} rows, including the header row.",
+            xlsx::MAX_ROWS.separate_with_commas()
+        ))
+    }
+
+    else if args.output_format == OutputFormat::FixedWidth && args.layout.is_none() {
+        Err(String::from("Fixed-width output needs a --layout or a --layout-file."))
+    }
+
+    else if args.output_format != OutputFormat::FixedWidth && args.layout.is_some() {
+        Err(String::from("--layout and --layout-file only apply to fixed-width (\".dat\") output."))
+    }
+
+    else if args.output_format != OutputFormat::Csv &&
+            (args.delimiter.is_some() || args.quote_all || args.crlf || args.bom) {
+        Err(String::from(
+            "--delimiter, --quote-all, --crlf and --bom only apply to CSV (\".csv\" or \".tsv\") output."
+        ))
+    }
+
+    else if args.long &&
+            !matches!(args.output_format,
+                      OutputFormat::Csv | OutputFormat::JsonL | OutputFormat::JsonPretty | OutputFormat::Table) {
+        Err(String::from("--long only applies to CSV, TSV, JSON and JSON Lines output, and --preview."))
+    }
+
+    else if args.shards.is_some() && (is_stdout(&args.output_file) || is_null(&args.output_file)) {
+        Err(String::from("--shards needs an output file, to name the shards after."))
+    }
+
+    else if args.rollover.is_some() && (is_stdout(&args.output_file) || is_null(&args.output_file)) {
+        Err(String::from(
+            "--max-rows-per-file and --max-bytes-per-file need an output file, to name the files after."
+        ))
+    }
+
+    else if args.rollover.and_then(|r| r.max_bytes).is_some_and(|max| max < MIN_FILE_SIZE) {
+        Err(format!("--max-bytes-per-file must be at least {}K.", MIN_FILE_SIZE / 1024))
+    }
+
+    else if args.rollover.and_then(|r| r.max_bytes).is_some() &&
+            matches!(args.output_format, OutputFormat::Avro | OutputFormat::Arrow | OutputFormat::Xlsx) {
+        Err(String::from(
+            "--max-bytes-per-file doesn't work with Avro, Arrow or Excel output, which write in blocks. \
+             Use --max-rows-per-file."
+        ))
+    }
+
+    else if let Some(f) = missing_layout_field(&args) {
+        Err(format!(
+            "Layout field \"{}\" isn't being generated. Check the options that add it (e.g., --ssn).",
+            f.field
+        ))
+    }
+
+    else if let Some(field) = twice_hashed_field(&args) {
+        Err(format!("--hash-field is given more than once for \"{field}\". A field can only have one hash."))
     }
 
     else if path_is_empty(&args.last_names_file) {
-        Err(String::from(format!(
-            "Last names file not specified, and {} is not set in environment.",
+        Err(format!(
+            "Last names file not specified, and {} is not set in environment. Run \"peoplegen fetch-names\" to download names files.",
             ENV_LAST_NAMES_FILE
-        )))
+        ))
     }
 
     else {
@@ -0,0 +1,421 @@
+//! A minimal writer for Arrow IPC files (also known as Feather version 2),
+//! which pyarrow, polars and friends can memory-map directly.
+//!
+//! Only what `peoplegen` needs is supported: a schema whose columns are all
+//! non-nullable UTF-8 strings, written as uncompressed record batches. The
+//! IPC metadata is encoded with a small, special-purpose FlatBuffers builder.
+//! See <https://arrow.apache.org/docs/format/Columnar.html#ipc-file-format>
+
+use std::io::{self, Write};
+
+/// The magic bytes at the start and end of every Arrow IPC file.
+const MAGIC: &[u8; 6] = b"ARROW1";
+
+/// How many rows go into each record batch.
+const ROWS_PER_BATCH: usize = 10_000;
+
+// Values from the Arrow FlatBuffers schema (Schema.fbs and Message.fbs).
+const METADATA_VERSION_V5: i16 = 4;
+const TYPE_UTF8: u8 = 5;
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+/**
+ * Writes rows to an Arrow IPC file, a record batch at a time.
+ */
+pub struct ArrowWriter<W: Write> {
+    w: W,
+    schema: Object,
+    columns: Vec<Column>,
+    rows: usize,
+    // The number of bytes written so far.
+    pos: u64,
+    // The location of each record batch, for the footer.
+    blocks: Vec<Block>,
+}
+
+/// A column of the record batch being built.
+struct Column {
+    offsets: Vec<i32>,
+    data: Vec<u8>,
+}
+
+/// The location of a message in the file: its offset, its metadata length,
+/// and its body length.
+struct Block(u64, i32, i64);
+
+impl<W: Write> ArrowWriter<W> {
+    /**
+     * Create a new writer, writing the file header and schema.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the file
+     * - `names`: The column names, in order
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The header was written.
+     * - `Err(e)`: The write failed.
+     */
+    pub fn new(w: W, names: &[&String]) -> io::Result<ArrowWriter<W>> {
+        let mut aw = ArrowWriter {
+            w,
+            schema: schema(names),
+            columns: names.iter().map(|_| Column { offsets: vec![0], data: Vec::new() }).collect(),
+            rows: 0,
+            pos: 0,
+            blocks: Vec::new(),
+        };
+
+        aw.write_bytes(MAGIC)?;
+        aw.write_bytes(&[0, 0])?;
+        let message = message(HEADER_SCHEMA, aw.schema.clone(), 0);
+        aw.write_message(&message, &[])?;
+        Ok(aw)
+    }
+
+    /**
+     * Write a row. The values must be in column order.
+     */
+    pub fn write_record(&mut self, values: &[String]) -> io::Result<()> {
+        for (col, v) in self.columns.iter_mut().zip(values) {
+            col.data.extend(v.as_bytes());
+            col.offsets.push(col.data.len() as i32);
+        }
+
+        self.rows += 1;
+        if self.rows == ROWS_PER_BATCH {
+            self.flush_batch()?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Write any buffered rows and the file footer, and flush the underlying
+     * writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The write failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_batch()?;
+
+        // End-of-stream marker
+        self.write_bytes(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0])?;
+
+        let mut blocks: Vec<u8> = Vec::new();
+        for Block(offset, meta_len, body_len) in &self.blocks {
+            blocks.extend(offset.to_le_bytes());
+            blocks.extend(meta_len.to_le_bytes());
+            blocks.extend([0; 4]);
+            blocks.extend(body_len.to_le_bytes());
+        }
+
+        let footer = finish_buffer(Object::Table(vec![
+            (0, Value::I16(METADATA_VERSION_V5)),
+            (1, Value::Offset(self.schema.clone())),
+            (3, Value::Offset(Object::Structs(blocks, self.blocks.len()))),
+        ]));
+
+        self.write_bytes(&footer)?;
+        self.write_bytes(&(footer.len() as i32).to_le_bytes())?;
+        self.write_bytes(MAGIC)?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn flush_batch(&mut self) -> io::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+
+        let mut body: Vec<u8> = Vec::new();
+        let mut nodes: Vec<u8> = Vec::new();
+        let mut buffers: Vec<u8> = Vec::new();
+        let mut add_buffer = |body: &mut Vec<u8>, bytes: &[u8]| {
+            buffers.extend((body.len() as i64).to_le_bytes());
+            buffers.extend((bytes.len() as i64).to_le_bytes());
+            body.extend(bytes);
+            pad(body, 8);
+        };
+
+        for col in &self.columns {
+            nodes.extend((self.rows as i64).to_le_bytes());
+            nodes.extend(0i64.to_le_bytes());
+            // There are no nulls, so the validity bitmap can be empty.
+            add_buffer(&mut body, &[]);
+            let offsets: Vec<u8> = col.offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+            add_buffer(&mut body, &offsets);
+            add_buffer(&mut body, &col.data);
+        }
+
+        let batch = Object::Table(vec![
+            (0, Value::I64(self.rows as i64)),
+            (1, Value::Offset(Object::Structs(nodes, self.columns.len()))),
+            (2, Value::Offset(Object::Structs(buffers, self.columns.len() * 3))),
+        ]);
+        let message = message(HEADER_RECORD_BATCH, batch, body.len() as i64);
+        let offset = self.pos;
+        let meta_len = self.write_message(&message, &body)?;
+        self.blocks.push(Block(offset, meta_len, body.len() as i64));
+
+        for col in self.columns.iter_mut() {
+            col.offsets.truncate(1);
+            col.data.clear();
+        }
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// Write an encapsulated message and its body, returning the length of
+    /// the metadata (including its prefix and padding).
+    fn write_message(&mut self, message: &[u8], body: &[u8]) -> io::Result<i32> {
+        let mut meta: Vec<u8> = message.to_vec();
+        // The prefix is 8 bytes, so padding the message keeps the body
+        // aligned.
+        pad(&mut meta, 8);
+        self.write_bytes(&[0xff, 0xff, 0xff, 0xff])?;
+        self.write_bytes(&(meta.len() as i32).to_le_bytes())?;
+        self.write_bytes(&meta)?;
+        self.write_bytes(body)?;
+        Ok(meta.len() as i32 + 8)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.w.write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Build the Schema table for a set of string columns.
+fn schema(names: &[&String]) -> Object {
+    let fields = names
+        .iter()
+        .map(|name| {
+            Object::Table(vec![
+                (0, Value::Offset(Object::Str(name.to_string()))),
+                (1, Value::Bool(false)),
+                (2, Value::U8(TYPE_UTF8)),
+                (3, Value::Offset(Object::Table(Vec::new()))),
+                (5, Value::Offset(Object::Tables(Vec::new()))),
+            ])
+        })
+        .collect();
+
+    Object::Table(vec![
+        (0, Value::I16(0)), // little-endian
+        (1, Value::Offset(Object::Tables(fields))),
+    ])
+}
+
+/// Build an encoded Message with the given header.
+fn message(header_type: u8, header: Object, body_length: i64) -> Vec<u8> {
+    finish_buffer(Object::Table(vec![
+        (0, Value::I16(METADATA_VERSION_V5)),
+        (1, Value::U8(header_type)),
+        (2, Value::Offset(header)),
+        (3, Value::I64(body_length)),
+    ]))
+}
+
+// ----------------------------------------------------------------------------
+// FlatBuffers encoding
+// ----------------------------------------------------------------------------
+
+/// A FlatBuffers object, which is referred to by offset.
+#[derive(Debug, Clone)]
+enum Object {
+    /// A table, as (field ID, value) pairs
+    Table(Vec<(u16, Value)>),
+    Str(String),
+    /// A vector of tables
+    Tables(Vec<Object>),
+    /// A vector of structs, as already-encoded bytes, and the number of
+    /// structs. (All the structs used here are 8-byte aligned.)
+    Structs(Vec<u8>, usize),
+}
+
+/// A table field value.
+#[derive(Debug, Clone)]
+enum Value {
+    Bool(bool),
+    U8(u8),
+    I16(i16),
+    I64(i64),
+    Offset(Object),
+}
+
+/**
+ * Encode a FlatBuffers buffer with the given root table.
+ *
+ * FlatBuffers are normally built back to front; this builder works front to
+ * back instead, writing each object before the objects it refers to, and
+ * patching the (forward) offsets as it goes. The result is just as valid,
+ * and it's simpler for the handful of objects needed here.
+ */
+fn finish_buffer(root: Object) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0; 4];
+    let pos = write_object(&mut buf, &root);
+    patch_offset(&mut buf, 0, pos);
+    pad(&mut buf, 8);
+    buf
+}
+
+/// Write an object, returning its position.
+fn write_object(buf: &mut Vec<u8>, obj: &Object) -> usize {
+    match obj {
+        Object::Table(fields) => write_table(buf, fields),
+        Object::Str(s) => {
+            pad(buf, 4);
+            let pos = buf.len();
+            buf.extend((s.len() as u32).to_le_bytes());
+            buf.extend(s.as_bytes());
+            buf.push(0);
+            pos
+        },
+        Object::Tables(tables) => {
+            pad(buf, 4);
+            let pos = buf.len();
+            buf.extend((tables.len() as u32).to_le_bytes());
+            let slots = buf.len();
+            buf.extend(vec![0; 4 * tables.len()]);
+            for (i, t) in tables.iter().enumerate() {
+                let t_pos = write_object(buf, t);
+                patch_offset(buf, slots + 4 * i, t_pos);
+            }
+            pos
+        },
+        Object::Structs(bytes, count) => {
+            // The structs themselves (after the length) must be 8-byte
+            // aligned.
+            while buf.len() % 8 != 4 {
+                buf.push(0);
+            }
+            let pos = buf.len();
+            buf.extend((*count as u32).to_le_bytes());
+            buf.extend(bytes);
+            pos
+        },
+    }
+}
+
+/// Write a table (preceded by its vtable), returning the table's position.
+fn write_table(buf: &mut Vec<u8>, fields: &[(u16, Value)]) -> usize {
+    let slots = fields.iter().map(|(id, _)| *id as usize + 1).max().unwrap_or(0);
+
+    pad(buf, 2);
+    let vtable_pos = buf.len();
+    buf.extend(vec![0; 4 + 2 * slots]);
+    pad(buf, 4);
+    let table_pos = buf.len();
+    buf.extend(((table_pos - vtable_pos) as i32).to_le_bytes());
+
+    let mut children: Vec<(usize, &Object)> = Vec::new();
+
+    for (id, value) in fields {
+        let (align, bytes): (usize, Vec<u8>) = match value {
+            Value::Bool(b) => (1, vec![*b as u8]),
+            Value::U8(n) => (1, vec![*n]),
+            Value::I16(n) => (2, n.to_le_bytes().to_vec()),
+            Value::I64(n) => (8, n.to_le_bytes().to_vec()),
+            Value::Offset(_) => (4, vec![0; 4]),
+        };
+        pad(buf, align);
+        let field_pos = buf.len();
+        buf.extend(bytes);
+        set_u16(buf, vtable_pos + 4 + 2 * *id as usize, (field_pos - table_pos) as u16);
+
+        if let Value::Offset(obj) = value {
+            children.push((field_pos, obj));
+        }
+    }
+
+    set_u16(buf, vtable_pos, (4 + 2 * slots) as u16);
+    let table_size = buf.len() - table_pos;
+    set_u16(buf, vtable_pos + 2, table_size as u16);
+
+    for (field_pos, obj) in children {
+        let pos = write_object(buf, obj);
+        patch_offset(buf, field_pos, pos);
+    }
+
+    table_pos
+}
+
+/// Point the offset at `at` at `target`.
+fn patch_offset(buf: &mut [u8], at: usize, target: usize) {
+    buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+}
+
+fn set_u16(buf: &mut [u8], at: usize, n: u16) {
+    buf[at..at + 2].copy_from_slice(&n.to_le_bytes());
+}
+
+/// Pad a buffer with zeros to a multiple of `align` bytes.
+fn pad(buf: &mut Vec<u8>, align: usize) {
+    while !buf.len().is_multiple_of(align) {
+        buf.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arrow::*;
+
+    fn u32_at(buf: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    fn u16_at(buf: &[u8], at: usize) -> usize {
+        u16::from_le_bytes(buf[at..at + 2].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn flatbuffer_tables() {
+        let buf = finish_buffer(Object::Table(vec![
+            (1, Value::I64(42)),
+            (2, Value::Offset(Object::Str(String::from("Moe")))),
+        ]));
+
+        // Follow the root offset to the table, and the table's soffset back
+        // to its vtable.
+        let table = u32_at(&buf, 0);
+        let vtable = table - u32_at(&buf, table);
+        assert_eq!(u16_at(&buf, vtable), 4 + 2 * 3);
+        // Field 0 is absent.
+        assert_eq!(u16_at(&buf, vtable + 4), 0);
+
+        let f1 = table + u16_at(&buf, vtable + 6);
+        assert_eq!(f1 % 8, 0);
+        assert_eq!(i64::from_le_bytes(buf[f1..f1 + 8].try_into().unwrap()), 42);
+
+        let f2 = table + u16_at(&buf, vtable + 8);
+        let s = f2 + u32_at(&buf, f2);
+        assert_eq!(u32_at(&buf, s), 3);
+        assert_eq!(&buf[s + 4..s + 8], b"Moe\0");
+    }
+
+    #[test]
+    fn file_layout() {
+        let names = [String::from("first_name"), String::from("last_name")];
+        let mut w = ArrowWriter::new(Vec::new(), &names.iter().collect::<Vec<_>>()).unwrap();
+        w.write_record(&[String::from("Moe"), String::from("Howard")]).unwrap();
+        w.write_record(&[String::from("Larry"), String::from("Fine")]).unwrap();
+        let bytes = w.finish().unwrap();
+
+        assert!(bytes.starts_with(b"ARROW1\0\0"));
+        assert!(bytes.ends_with(MAGIC));
+        let n = bytes.len();
+        let footer_len = i32::from_le_bytes(bytes[n - 10..n - 6].try_into().unwrap()) as usize;
+        assert!(footer_len < n);
+        // The first message (the schema) follows the magic.
+        assert_eq!(&bytes[8..12], &[0xff; 4]);
+        // The column data is in the body.
+        assert!(bytes.windows(8).any(|w| w == b"MoeLarry"));
+    }
+}
@@ -0,0 +1,532 @@
+//! Assertions about the generated data as a whole, checked at the end of a
+//! run, so a CI job can insist on properties of a dataset without a separate
+//! validation script. Each assertion is an expression over column statistics,
+//! and a check:
+//!
+//! ```text
+//! mean(salary) between 55000 and 62000
+//! count(gender = 'F') / count(*) ~ 0.5 ± 0.02
+//! min(salary) > 0
+//! ```
+//!
+//! The statistics are `count(*)`, `count(CONDITION [and CONDITION ...])`,
+//! and `mean`, `sum`, `min`, `max` and `stddev` (the population standard
+//! deviation) of a field. Conditions are as in `--rules` (without `age`),
+//! except that `=` also means `==` and strings can be in single quotes.
+//! Statistics of a field skip values that aren't numbers (e.g., empty ones). Statistics and numbers
+//! can be multiplied and divided, with parentheses for grouping.
+//!
+//! The checks are `between LOW and HIGH` (inclusive), `~ VALUE ± TOLERANCE`
+//! (or `+-`), and a comparison with a number (`==`, `!=`, `<`, `<=`, `>`,
+//! `>=`).
+//!
+//! Assertions see the values as the rules leave them, before formatting,
+//! hashing or encryption.
+
+use crate::rules::{Condition, Operator, Value};
+
+/// The statistics an assertion can use, besides `count`.
+const FIELD_STATISTICS: [&str; 5] = ["mean", "sum", "min", "max", "stddev"];
+
+/// A statistic of the people.
+#[derive(Debug, Clone, PartialEq)]
+enum Statistic {
+    /// How many people meet all the conditions (all of them, if there are
+    /// none)
+    Count(Vec<Condition>),
+    /// A statistic of a field's numeric values: one of `FIELD_STATISTICS`
+    Field(String, String),
+}
+
+/// An expression over statistics.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// The statistic at this index in the assertion's list
+    Statistic(usize),
+    Product(Box<Expr>, Box<Expr>),
+    Quotient(Box<Expr>, Box<Expr>),
+}
+
+/// What an expression's value has to be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Check {
+    Between(f64, f64),
+    Near(f64, f64),
+    Compare(Operator, f64),
+}
+
+/// An assertion, as given with `--assert`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assertion {
+    text: String,
+    statistics: Vec<Statistic>,
+    expr: Expr,
+    check: Check,
+}
+
+/// The running totals of a statistic.
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    // Welford's running mean and sum of squared differences, for stddev
+    mean: f64,
+    m2: f64,
+}
+
+/// The statistics of the people written so far, for checking assertions.
+#[derive(Debug)]
+pub struct Tally<'a> {
+    assertions: &'a [Assertion],
+    /// The totals of each assertion's statistics
+    totals: Vec<Vec<Totals>>,
+}
+
+impl Assertion {
+    /**
+     * Parse an assertion.
+     *
+     * # Arguments
+     *
+     * - `text`: The assertion, such as "mean(salary) between 55000 and 62000"
+     * - `fields`: The valid field names
+     *
+     * # Returns
+     *
+     * - `Ok(assertion)`: The assertion
+     * - `Err(msg)`: The assertion is invalid; `msg` explains why.
+     */
+    pub fn parse(text: &str, fields: &[&str]) -> Result<Assertion, String> {
+        let err = |msg: String| format!("Bad assertion \"{text}\": {msg}");
+        let tokens = tokenize(text).map_err(err)?;
+        let mut parser = Parser { tokens, next: 0, fields, statistics: Vec::new() };
+
+        let expr = parser.expr().map_err(err)?;
+        let check = parser.check().map_err(err)?;
+        if parser.next < parser.tokens.len() {
+            return Err(err(String::from("unexpected text after the check.")));
+        }
+
+        Ok(Assertion { text: text.to_string(), statistics: parser.statistics, expr, check })
+    }
+}
+
+impl<'a> Tally<'a> {
+    /// Start a tally, with no people yet.
+    pub fn new(assertions: &'a [Assertion]) -> Tally<'a> {
+        let totals = assertions
+            .iter()
+            .map(|a| vec![Totals { min: f64::INFINITY, max: f64::NEG_INFINITY, ..Totals::default() };
+                          a.statistics.len()])
+            .collect();
+        Tally { assertions, totals }
+    }
+
+    /**
+     * Add a person to the tally.
+     *
+     * # Arguments
+     *
+     * - `value_of`: Gets the value of any of the person's fields
+     */
+    pub fn observe<F: Fn(&str) -> String>(&mut self, value_of: F) {
+        for (assertion, totals) in self.assertions.iter().zip(self.totals.iter_mut()) {
+            for (statistic, t) in assertion.statistics.iter().zip(totals.iter_mut()) {
+                match statistic {
+                    Statistic::Count(conditions) => {
+                        if conditions.iter().all(|c| c.holds(&value_of(&c.field))) {
+                            t.count += 1;
+                        }
+                    },
+                    Statistic::Field(_, field) => {
+                        if let Ok(n) = value_of(field).trim().parse::<f64>() {
+                            t.add(n);
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    /**
+     * Check the assertions.
+     *
+     * # Returns
+     *
+     * - `Ok(())`: They all hold.
+     * - `Err(msg)`: Some don't; `msg` has a line for each, with the value
+     *   that failed.
+     */
+    pub fn check(&self) -> Result<(), String> {
+        let failures: Vec<String> = self.assertions
+            .iter()
+            .zip(&self.totals)
+            .filter_map(|(assertion, totals)| {
+                let values: Vec<f64> = assertion.statistics
+                    .iter()
+                    .zip(totals)
+                    .map(|(statistic, t)| t.value(statistic))
+                    .collect();
+                let value = assertion.expr.eval(&values);
+
+                if assertion.check.holds(value) {
+                    None
+                }
+                else {
+                    Some(format!("Assertion \"{}\" failed: the value is {}.", assertion.text, show(value)))
+                }
+            })
+            .collect();
+
+        if failures.is_empty() { Ok(()) } else { Err(failures.join("\n")) }
+    }
+}
+
+impl Totals {
+    fn add(&mut self, n: f64) {
+        self.count += 1;
+        self.sum += n;
+        self.min = self.min.min(n);
+        self.max = self.max.max(n);
+        let delta = n - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (n - self.mean);
+    }
+
+    /// The value of a statistic. Statistics of no values aren't numbers,
+    /// except for counts and sums.
+    fn value(&self, statistic: &Statistic) -> f64 {
+        let some = |v: f64| if self.count == 0 { f64::NAN } else { v };
+        match statistic {
+            Statistic::Count(_) => self.count as f64,
+            Statistic::Field(name, _) => match name.as_str() {
+                "sum" => self.sum,
+                "mean" => some(self.mean),
+                "min" => some(self.min),
+                "max" => some(self.max),
+                _ => some((self.m2 / self.count as f64).sqrt()),
+            },
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, statistics: &[f64]) -> f64 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Statistic(i) => statistics[*i],
+            Expr::Product(a, b) => a.eval(statistics) * b.eval(statistics),
+            Expr::Quotient(a, b) => a.eval(statistics) / b.eval(statistics),
+        }
+    }
+}
+
+impl Check {
+    /// Whether a value passes. A value that isn't a number never does.
+    fn holds(&self, value: f64) -> bool {
+        match *self {
+            _ if value.is_nan() => false,
+            Check::Between(low, high) => low <= value && value <= high,
+            Check::Near(target, tolerance) => (value - target).abs() <= tolerance,
+            Check::Compare(op, n) => match op {
+                Operator::Eq => value == n,
+                Operator::Ne => value != n,
+                Operator::Lt => value < n,
+                Operator::Le => value <= n,
+                Operator::Gt => value > n,
+                Operator::Ge => value >= n,
+            },
+        }
+    }
+}
+
+/// A value, for a message: whole numbers as they are, others to four
+/// decimal places.
+fn show(value: f64) -> String {
+    if value.is_nan() {
+        String::from("not a number (there were no values)")
+    }
+    else if value.fract() == 0.0 {
+        format!("{value:.0}")
+    }
+    else {
+        let s = format!("{value:.4}");
+        s.trim_end_matches('0').to_string()
+    }
+}
+
+/// A token in an assertion.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Text(String),
+    Op(Operator),
+    Open,
+    Close,
+    Star,
+    Slash,
+    Tilde,
+    PlusMinus,
+}
+
+/// A recursive-descent parser for an assertion.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    next: usize,
+    fields: &'a [&'a str],
+    statistics: Vec<Statistic>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        if self.take() == Some(token) { Ok(()) } else { Err(format!("expected {what}.")) }
+    }
+
+    /// `FACTOR (("*" | "/") FACTOR)*`
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.factor()?;
+        loop {
+            match self.peek() {
+                Some(Token::Star) => {
+                    self.next += 1;
+                    expr = Expr::Product(Box::new(expr), Box::new(self.factor()?));
+                },
+                Some(Token::Slash) => {
+                    self.next += 1;
+                    expr = Expr::Quotient(Box::new(expr), Box::new(self.factor()?));
+                },
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// `NUMBER | "(" EXPR ")" | STATISTIC "(" ... ")"`
+    fn factor(&mut self) -> Result<Expr, String> {
+        match self.take() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Open) => {
+                let expr = self.expr()?;
+                self.expect(Token::Close, "\")\"")?;
+                Ok(expr)
+            },
+            Some(Token::Word(name)) if name == "count" => {
+                self.expect(Token::Open, "\"(\" after \"count\"")?;
+                let conditions = if self.peek() == Some(&Token::Star) {
+                    self.next += 1;
+                    Vec::new()
+                }
+                else {
+                    self.conditions()?
+                };
+                self.expect(Token::Close, "\")\" after the conditions")?;
+                Ok(self.statistic(Statistic::Count(conditions)))
+            },
+            Some(Token::Word(name)) if FIELD_STATISTICS.contains(&name.as_str()) => {
+                self.expect(Token::Open, &format!("\"(\" after \"{name}\""))?;
+                let field = self.field()?;
+                self.expect(Token::Close, &format!("\")\" after \"{field}\""))?;
+                Ok(self.statistic(Statistic::Field(name, field)))
+            },
+            Some(Token::Word(name)) => Err(format!(
+                "unknown statistic \"{}\". Statistics: count, {}", name, FIELD_STATISTICS.join(", ")
+            )),
+            _ => Err(String::from("expected a statistic, a number or \"(\".")),
+        }
+    }
+
+    fn statistic(&mut self, statistic: Statistic) -> Expr {
+        self.statistics.push(statistic);
+        Expr::Statistic(self.statistics.len() - 1)
+    }
+
+    fn field(&mut self) -> Result<String, String> {
+        match self.take() {
+            Some(Token::Word(w)) if self.fields.contains(&w.as_str()) => Ok(w),
+            Some(Token::Word(w)) => Err(format!("unknown field \"{w}\".")),
+            _ => Err(String::from("expected a field name.")),
+        }
+    }
+
+    /// `FIELD OPERATOR VALUE ("and" FIELD OPERATOR VALUE)*`
+    fn conditions(&mut self) -> Result<Vec<Condition>, String> {
+        let mut conditions = Vec::new();
+        loop {
+            let field = self.field()?;
+            let op = match self.take() {
+                Some(Token::Op(op)) => op,
+                _ => return Err(format!("expected a comparison after \"{field}\".")),
+            };
+            let value = match self.take() {
+                Some(Token::Number(n)) => Value::Number(n),
+                Some(Token::Text(s)) => Value::Text(s),
+                Some(Token::Word(w)) if w == "null" => Value::Null,
+                _ => return Err(String::from("expected a number, a quoted string or null.")),
+            };
+            conditions.push(Condition { field, op, value });
+
+            match self.peek() {
+                Some(Token::Word(w)) if w == "and" => self.next += 1,
+                _ => return Ok(conditions),
+            }
+        }
+    }
+
+    /// `"between" NUMBER "and" NUMBER | "~" NUMBER "±" NUMBER | OPERATOR NUMBER`
+    fn check(&mut self) -> Result<Check, String> {
+        let check = match self.take() {
+            Some(Token::Word(w)) if w == "between" => {
+                let low = self.number()?;
+                self.expect(Token::Word(String::from("and")), "\"and\" after the low end")?;
+                Check::Between(low, self.number()?)
+            },
+            Some(Token::Tilde) => {
+                let target = self.number()?;
+                self.expect(Token::PlusMinus, "\"±\" or \"+-\" after the value")?;
+                Check::Near(target, self.number()?)
+            },
+            Some(Token::Op(op)) => Check::Compare(op, self.number()?),
+            _ => return Err(String::from("expected \"between\", \"~\" or a comparison.")),
+        };
+        Ok(check)
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        match self.take() {
+            Some(Token::Number(n)) => Ok(n),
+            _ => Err(String::from("expected a number.")),
+        }
+    }
+}
+
+/// Split an assertion into tokens.
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let starts_number = c.is_ascii_digit() ||
+            ((c == '-' || c == '.') && chars.peek().is_some_and(|d| d.is_ascii_digit()));
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word)
+            },
+            _ if starts_number => {
+                let mut number = String::from(c);
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                Token::Number(number.parse::<f64>().map_err(|_| format!("bad number \"{number}\"."))?)
+            },
+            '"' | '\'' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => s.push(ch),
+                        None => return Err(String::from("unterminated string.")),
+                    }
+                }
+                Token::Text(s)
+            },
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '~' => Token::Tilde,
+            '±' => Token::PlusMinus,
+            '+' if chars.next_if_eq(&'-').is_some() => Token::PlusMinus,
+            '=' | '!' | '<' | '>' => {
+                let equals = chars.next_if_eq(&'=').is_some();
+                Token::Op(match (c, equals) {
+                    ('=', _) => Operator::Eq,
+                    ('!', true) => Operator::Ne,
+                    ('<', true) => Operator::Le,
+                    ('>', true) => Operator::Ge,
+                    ('<', false) => Operator::Lt,
+                    ('>', false) => Operator::Gt,
+                    _ => return Err(String::from("unexpected \"!\".")),
+                })
+            },
+            _ => return Err(format!("unexpected \"{c}\".")),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertions::*;
+
+    const FIELDS: [&str; 3] = ["gender", "salary", "ssn"];
+
+    /// Tally people with the given genders and salaries, and check an
+    /// assertion.
+    fn check(assertion: &str, people: &[(&str, &str)]) -> Result<(), String> {
+        let assertions = [Assertion::parse(assertion, &FIELDS).unwrap()];
+        let mut tally = Tally::new(&assertions);
+        for (gender, salary) in people {
+            tally.observe(|field| String::from(if field == "gender" { *gender } else { *salary }));
+        }
+        tally.check()
+    }
+
+    const PEOPLE: [(&str, &str); 4] = [("F", "50000"), ("M", "60000"), ("F", "70000"), ("M", "")];
+
+    #[test]
+    fn statistics() {
+        assert_eq!(check("mean(salary) between 55000 and 65000", &PEOPLE), Ok(()));
+        assert_eq!(check("count(gender='F') / count(*) ~ 0.5 ± 0.01", &PEOPLE), Ok(()));
+        assert_eq!(check("count(gender == \"M\" and salary > 0) == 1", &PEOPLE), Ok(()));
+        assert_eq!(check("min(salary) >= 50000", &PEOPLE), Ok(()));
+        assert_eq!(check("(max(salary) / 1000) * 2 == 140", &PEOPLE), Ok(()));
+        assert_eq!(check("sum(salary) ~ 180000 +- 0", &PEOPLE), Ok(()));
+        assert_eq!(check("stddev(salary) ~ 8164.97 +- 0.01", &PEOPLE), Ok(()));
+
+        assert_eq!(
+            check("mean(salary) < 60000", &PEOPLE),
+            Err(String::from("Assertion \"mean(salary) < 60000\" failed: the value is 60000."))
+        );
+        assert_eq!(
+            check("count(gender = 'F') / count(*) > 0.6", &PEOPLE),
+            Err(String::from("Assertion \"count(gender = 'F') / count(*) > 0.6\" failed: the value is 0.5."))
+        );
+        assert!(check("mean(salary) > 0", &[]).unwrap_err().contains("not a number"));
+    }
+
+    #[test]
+    fn parsing() {
+        let bad = |s: &str| Assertion::parse(s, &FIELDS).unwrap_err();
+        assert!(bad("median(salary) > 1").contains("unknown statistic \"median\""));
+        assert!(bad("mean(age) > 1").contains("unknown field \"age\""));
+        assert!(bad("mean(salary)").contains("expected \"between\""));
+        assert!(bad("mean(salary) between 1").contains("\"and\""));
+        assert!(bad("mean(salary) ~ 1").contains("\"±\""));
+        assert!(bad("count(gender) > 1").contains("comparison after \"gender\""));
+        assert!(bad("count(gender = 'F) > 1").contains("unterminated"));
+        assert!(bad("mean(salary) > 1 1").contains("unexpected text"));
+        assert!(bad("mean(salary) - 1 > 1").contains("unexpected \"-\""));
+    }
+}
@@ -0,0 +1,83 @@
+//! Identicon-style avatar images.
+//!
+//! Each avatar is a 5x5 grid of cells, mirrored left to right, in one
+//! foreground color on a light background. Both the pattern and the color
+//! are derived from a seed (normally the person's ID), so a given seed
+//! always produces the same image.
+
+use crate::png::encode_two_color;
+
+const GRID: u32 = 5;
+const CELL: u32 = 20;
+const BORDER: u32 = 10;
+const BACKGROUND: [u8; 3] = [0xf0, 0xf0, 0xf0];
+
+/// The width and height of a generated avatar, in pixels.
+pub const AVATAR_SIZE: u32 = GRID * CELL + 2 * BORDER;
+
+/**
+ * Generate an avatar as a PNG image.
+ *
+ * # Arguments
+ *
+ * - `seed`: The seed from which the image is derived
+ *
+ * # Returns
+ *
+ * The bytes of the PNG image.
+ */
+pub fn identicon_png(seed: u64) -> Vec<u8> {
+    let hash = mix(seed);
+    let color = color_from_hash(hash >> 32);
+
+    // Only the left three columns are random; the right two mirror them.
+    let cell_on = |col: u32, row: u32| {
+        let col = if col >= GRID - GRID / 2 { GRID - 1 - col } else { col };
+        let bit = row * (GRID - GRID / 2) + col;
+        (hash >> bit) & 1 == 1
+    };
+
+    encode_two_color(AVATAR_SIZE, AVATAR_SIZE, [BACKGROUND, color], |x, y| {
+        let inside = |v: u32| (BORDER..BORDER + GRID * CELL).contains(&v);
+        inside(x) && inside(y) && cell_on((x - BORDER) / CELL, (y - BORDER) / CELL)
+    })
+}
+
+/// Scramble a seed (the SplitMix64 finalizer), so that consecutive seeds
+/// produce unrelated images.
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Pick a reasonably saturated, medium-dark color from some hash bits.
+fn color_from_hash(bits: u64) -> [u8; 3] {
+    let hue = (bits % 360) as f32;
+    let (s, l) = (0.55_f32, 0.45_f32);
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to_byte = |v: f32| ((v + m) * 255.0).round() as u8;
+    [to_byte(r), to_byte(g), to_byte(b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::avatar::identicon_png;
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(identicon_png(42), identicon_png(42));
+        assert_ne!(identicon_png(42), identicon_png(43));
+    }
+}
@@ -0,0 +1,223 @@
+//! A minimal writer for Avro object container files.
+//!
+//! Only what `peoplegen` needs is supported: a single record schema whose
+//! fields are all strings, written uncompressed (the `null` codec). See
+//! <https://avro.apache.org/docs/1.11.1/specification/#object-container-files>
+
+use json::JsonValue;
+use std::io::{self, Write};
+
+/// The magic bytes at the start of every Avro object container file.
+const MAGIC: &[u8; 4] = b"Obj\x01";
+
+/// How many records go into each data block.
+const RECORDS_PER_BLOCK: u64 = 1000;
+
+/**
+ * Writes records to an Avro object container file, a block at a time.
+ */
+pub struct AvroWriter<W: Write> {
+    w: W,
+    sync: [u8; 16],
+    block: Vec<u8>,
+    count: u64,
+}
+
+impl<W: Write> AvroWriter<W> {
+    /**
+     * Create a new writer, writing the file header.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the file
+     * - `schema`: The schema, as JSON, from `record_schema()`
+     * - `sync`: The sync marker that separates the data blocks
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The header was written.
+     * - `Err(e)`: The write failed.
+     */
+    pub fn new(mut w: W, schema: &str, sync: [u8; 16]) -> io::Result<AvroWriter<W>> {
+        let mut header: Vec<u8> = MAGIC.to_vec();
+
+        // The file metadata is a map with a single block of two entries.
+        write_long(&mut header, 2);
+        write_bytes(&mut header, b"avro.schema");
+        write_bytes(&mut header, schema.as_bytes());
+        write_bytes(&mut header, b"avro.codec");
+        write_bytes(&mut header, b"null");
+        write_long(&mut header, 0);
+        header.extend(sync);
+
+        w.write_all(&header)?;
+        Ok(AvroWriter { w, sync, block: Vec::new(), count: 0 })
+    }
+
+    /**
+     * Write a record. The values must be in schema order.
+     */
+    pub fn write_record(&mut self, values: &[String]) -> io::Result<()> {
+        for v in values {
+            write_bytes(&mut self.block, v.as_bytes());
+        }
+
+        self.count += 1;
+        if self.count == RECORDS_PER_BLOCK {
+            self.flush_block()?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Write any buffered records, and flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The write failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+
+        let mut prefix: Vec<u8> = Vec::new();
+        write_long(&mut prefix, self.count as i64);
+        write_long(&mut prefix, self.block.len() as i64);
+        self.w.write_all(&prefix)?;
+        self.w.write_all(&self.block)?;
+        self.w.write_all(&self.sync)?;
+        self.block.clear();
+        self.count = 0;
+        Ok(())
+    }
+}
+
+/**
+ * Generate the schema for a record whose fields are all strings.
+ *
+ * # Arguments
+ *
+ * - `name`: The record name
+ * - `fields`: The field names, in order. They're converted to valid Avro
+ *   names with `avro_name()`.
+ *
+ * # Returns
+ *
+ * The schema, as JSON.
+ */
+pub fn record_schema(name: &str, fields: &[&String]) -> String {
+    let mut jfields = JsonValue::new_array();
+
+    for f in fields {
+        let mut jf = JsonValue::new_object();
+        jf["name"] = avro_name(f).into();
+        jf["type"] = "string".into();
+        // Pushing onto an array can't fail.
+        jfields.push(jf).unwrap();
+    }
+
+    let mut schema = JsonValue::new_object();
+    schema["type"] = "record".into();
+    schema["name"] = name.into();
+    schema["fields"] = jfields;
+    schema.dump()
+}
+
+/**
+ * Convert a string into a valid Avro name, which must start with a letter
+ * or underscore, and contain only letters, digits and underscores. Anything
+ * else (e.g., the spaces in "pretty" headers) becomes an underscore.
+ */
+pub fn avro_name(s: &str) -> String {
+    let name: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("_{name}"),
+    }
+}
+
+/// Write a long, zig-zag and variable-length encoded.
+fn write_long(buf: &mut Vec<u8>, n: i64) {
+    let mut z = ((n << 1) ^ (n >> 63)) as u64;
+
+    while z >= 0x80 {
+        buf.push((z as u8 & 0x7f) | 0x80);
+        z >>= 7;
+    }
+
+    buf.push(z as u8);
+}
+
+/// Write a length-prefixed byte string (which is also how strings are
+/// encoded).
+fn write_bytes(buf: &mut Vec<u8>, b: &[u8]) {
+    write_long(buf, b.len() as i64);
+    buf.extend(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::avro::*;
+
+    fn long(n: i64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_long(&mut buf, n);
+        buf
+    }
+
+    #[test]
+    fn longs() {
+        assert_eq!(long(0), vec![0x00]);
+        assert_eq!(long(-1), vec![0x01]);
+        assert_eq!(long(1), vec![0x02]);
+        assert_eq!(long(-64), vec![0x7f]);
+        assert_eq!(long(64), vec![0x80, 0x01]);
+        assert_eq!(long(1000), vec![0xd0, 0x0f]);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(avro_name("first_name"), "first_name");
+        assert_eq!(avro_name("First Name"), "First_Name");
+        assert_eq!(avro_name("2fa"), "_2fa");
+        assert_eq!(avro_name(""), "_");
+    }
+
+    #[test]
+    fn container() {
+        let fields = [String::from("first_name"), String::from("Last Name")];
+        let schema = record_schema("Person", &fields.iter().collect::<Vec<_>>());
+        assert_eq!(
+            schema,
+            "{\"type\":\"record\",\"name\":\"Person\",\"fields\":[\
+             {\"name\":\"first_name\",\"type\":\"string\"},\
+             {\"name\":\"Last_Name\",\"type\":\"string\"}]}"
+        );
+
+        let sync = [7u8; 16];
+        let mut w = AvroWriter::new(Vec::new(), &schema, sync).unwrap();
+        w.write_record(&[String::from("Moe"), String::from("Howard")]).unwrap();
+        let bytes = w.finish().unwrap();
+
+        assert!(bytes.starts_with(MAGIC));
+        // One block: 1 record, 11 bytes ("Moe" and "Howard", each with a
+        // length byte), then the sync marker.
+        let block = [&[0x02, 0x16, 0x06][..], b"Moe", &[0x0c], b"Howard", &sync].concat();
+        assert!(bytes.ends_with(&block));
+        // The header also ends with the sync marker.
+        assert_eq!(bytes.windows(16).filter(|w| *w == sync).count(), 2);
+    }
+}
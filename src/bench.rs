@@ -0,0 +1,88 @@
+//! `peoplegen bench`: how fast a build generates and formats people. The
+//! people go to the null sink, so the disk doesn't come into it, and the
+//! time spent generating them is kept apart from the time spent formatting
+//! them, so a slowdown between versions can be pinned on one or the other.
+
+use std::time::Duration;
+
+use thousands::Separable;
+
+use crate::logging::secs;
+
+/// The results of a benchmark run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// How many people were generated
+    pub records: u64,
+    /// The output format's name, e.g., "CSV"
+    pub format: String,
+    pub seed: u64,
+    /// The time spent generating the people
+    pub generating: Duration,
+    /// The time spent formatting them
+    pub formatting: Duration,
+}
+
+impl BenchReport {
+    /**
+     * Get the report as messages, one per line: the version and the run,
+     * then the time and rate for each part of it, and the total.
+     */
+    pub fn to_messages(&self) -> Vec<String> {
+        let total = self.generating + self.formatting;
+        let line = |what: &str, duration: Duration| {
+            format!("{:<12}{:>10}  {:>12} rows/s", what, secs(duration), self.rate(duration).separate_with_commas())
+        };
+
+        vec![
+            format!("peoplegen {}: {} people, as {}, with seed {}.",
+                    env!("CARGO_PKG_VERSION"), self.records.separate_with_commas(), self.format, self.seed),
+            line("Generation", self.generating),
+            line("Formatting", self.formatting),
+            line("Total", total),
+        ]
+    }
+
+    /// The rows per second, for a part of the run that took `duration`.
+    fn rate(&self, duration: Duration) -> u64 {
+        match duration.as_secs_f64() {
+            s if s > 0.0 => (self.records as f64 / s).round() as u64,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::*;
+
+    #[test]
+    fn messages() {
+        let report = BenchReport {
+            records: 1_000_000,
+            format: String::from("CSV"),
+            seed: 42,
+            generating: Duration::from_millis(1_250),
+            formatting: Duration::from_millis(500),
+        };
+
+        assert_eq!(report.to_messages()[1..], [
+            "Generation      1.250s       800,000 rows/s",
+            "Formatting      0.500s     2,000,000 rows/s",
+            "Total           1.750s       571,429 rows/s",
+        ]);
+        assert!(report.to_messages()[0].ends_with(": 1,000,000 people, as CSV, with seed 42."));
+    }
+
+    #[test]
+    fn no_time() {
+        let report = BenchReport {
+            records: 0,
+            format: String::from("CSV"),
+            seed: 42,
+            generating: Duration::ZERO,
+            formatting: Duration::ZERO,
+        };
+        assert!(report.to_messages()[3].ends_with(" 0 rows/s"));
+    }
+}
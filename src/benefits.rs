@@ -0,0 +1,380 @@
+//! Benefits enrollments, for testing open-enrollment systems. Each person
+//! gets a hire date, a coverage tier and, for each kind of plan they enroll
+//! in, an election. They may have a spouse and children, whose coverage goes
+//! with theirs.
+//!
+//! The elections are for one plan year. An election takes effect on the
+//! first of the month after the hire date, or at the start of the plan year
+//! for people hired before it, and runs to the end of the plan year. The
+//! tier always matches the dependents: `employee_spouse` means a spouse and
+//! no children, and so on. Children are under 26, as dependent children
+//! usually must be, and born when their parent was 20 to 45.
+//!
+//! The enrollments come from their own random number generator, derived
+//! from the run's seed, so turning them on or off doesn't change any of the
+//! other generated values.
+
+use std::fs::File;
+use std::path::Path;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use csv::{Writer, WriterBuilder};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::path::path_str;
+
+/// Mixed into the run's seed to seed the enrollments' random number
+/// generator.
+const BENEFITS_SEED_SALT: u64 = 0x6265_6e65_6669_7473;
+
+/// The benefits files, in the `--benefits` directory.
+pub const ELECTIONS_FILE: &str = "elections.csv";
+pub const DEPENDENTS_FILE: &str = "dependents.csv";
+
+const ELECTIONS_HEADER: [&str; 8] =
+    ["id", "hire_date", "plan_type", "plan", "coverage_tier", "effective_date", "end_date", "dependents"];
+const DEPENDENTS_HEADER: [&str; 4] = ["dependent_id", "id", "relationship", "birth_date"];
+
+/// The kinds of plans, with their plans and the percentage of people who
+/// enroll in one.
+const PLAN_TYPES: [(&str, &[&str], u32); 3] = [
+    ("medical", &["medical_ppo", "medical_hmo", "medical_hdhp"], 85),
+    ("dental", &["dental_basic", "dental_plus"], 75),
+    ("vision", &["vision"], 60),
+];
+
+/// How old people are when they're hired, at the youngest.
+const MIN_HIRE_AGE: i32 = 18;
+
+/// How many years before the plan year hire dates go back, at most.
+const MAX_TENURE_YEARS: i32 = 30;
+
+/// The percentage of people with a spouse.
+const SPOUSE_PERCENT: u32 = 50;
+
+/// The most children anyone has.
+const MAX_CHILDREN: u32 = 4;
+
+/// Children stop being dependents at this age.
+const CHILD_AGE_LIMIT: i32 = 26;
+
+/// The settings for benefits enrollments, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenefitsOptions {
+    /// The plan year the elections are for
+    pub plan_year: i32,
+}
+
+/// A covered spouse or child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependent {
+    /// `spouse` or `child`
+    pub relationship: &'static str,
+    pub birth_date: NaiveDate,
+}
+
+/// An election of one kind of plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Election {
+    /// `medical`, `dental` or `vision`
+    pub plan_type: &'static str,
+    pub plan: &'static str,
+    pub effective_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+/// A person's benefits enrollment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enrollment {
+    pub hire_date: NaiveDate,
+    /// `employee`, `employee_spouse`, `employee_children` or `family`
+    pub coverage_tier: &'static str,
+    pub elections: Vec<Election>,
+    pub dependents: Vec<Dependent>,
+}
+
+/// Generates benefits enrollments.
+pub struct BenefitsGenerator {
+    rng: ChaCha8Rng,
+    plan_year: i32,
+}
+
+impl BenefitsGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The benefits settings
+     * - `seed`: The run's random number generator seed
+     */
+    pub fn new(options: &BenefitsOptions, seed: u64) -> BenefitsGenerator {
+        BenefitsGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ BENEFITS_SEED_SALT),
+            plan_year: options.plan_year,
+        }
+    }
+
+    /**
+     * Generate the next person's enrollment.
+     *
+     * # Arguments
+     *
+     * - `birth_date`: The person's birth date
+     *
+     * # Returns
+     *
+     * The enrollment, or `None` if the person is too young to have been
+     * hired in time for the plan year.
+     */
+    pub fn next_enrollment(&mut self, birth_date: NaiveDate) -> Option<Enrollment> {
+        let year_start = NaiveDate::from_ymd(self.plan_year, 1, 1);
+        let year_end = NaiveDate::from_ymd(self.plan_year, 12, 31);
+        // Hired by the end of November, so coverage starts by December.
+        let earliest = add_years(birth_date, MIN_HIRE_AGE).max(add_years(year_start, -MAX_TENURE_YEARS));
+        let hire_date = self.date_between(earliest, NaiveDate::from_ymd(self.plan_year, 11, 30))?;
+        let effective_date = first_of_next_month(hire_date).max(year_start);
+
+        let mut dependents = Vec::new();
+        if self.rng.gen_range(0..100) < SPOUSE_PERCENT {
+            let offset = Duration::days(self.rng.gen_range(-5 * 365..=5 * 365));
+            dependents.push(Dependent { relationship: "spouse", birth_date: birth_date + offset });
+        }
+        // Children are born to parents aged 20 to 45, and only those under
+        // the age limit, and born by the end of the plan year, are covered.
+        let first_child = add_years(birth_date, 20).max(add_years(year_end, -CHILD_AGE_LIMIT) + Duration::days(1));
+        let last_child = add_years(birth_date, 45).min(year_end);
+        for _ in 0..self.rng.gen_range(0..=MAX_CHILDREN) {
+            if let Some(birth_date) = self.date_between(first_child, last_child) {
+                dependents.push(Dependent { relationship: "child", birth_date });
+            }
+        }
+        dependents.sort_by_key(|d| (d.relationship != "spouse", d.birth_date));
+
+        let spouse = dependents.iter().any(|d| d.relationship == "spouse");
+        let children = dependents.iter().any(|d| d.relationship == "child");
+        let coverage_tier = match (spouse, children) {
+            (false, false) => "employee",
+            (true, false) => "employee_spouse",
+            (false, true) => "employee_children",
+            (true, true) => "family",
+        };
+
+        let mut elections = Vec::new();
+        for (plan_type, plans, percent) in PLAN_TYPES {
+            if self.rng.gen_range(0..100) < percent {
+                elections.push(Election {
+                    plan_type,
+                    plan: plans[self.rng.gen_range(0..plans.len())],
+                    effective_date,
+                    end_date: year_end,
+                });
+            }
+        }
+
+        Some(Enrollment { hire_date, coverage_tier, elections, dependents })
+    }
+
+    /// Pick a date at random in a range, or `None` if the range is empty.
+    fn date_between(&mut self, first: NaiveDate, last: NaiveDate) -> Option<NaiveDate> {
+        let days = (last - first).num_days();
+        (days >= 0).then(|| first + Duration::days(self.rng.gen_range(0..=days)))
+    }
+}
+
+/// Writes people's benefits enrollments to two CSV files: the elections,
+/// one row per plan, and the dependents, one row per dependent. Dependents
+/// are linked to people by the people's IDs.
+pub struct BenefitsWriter {
+    generator: BenefitsGenerator,
+    elections: Writer<File>,
+    dependents: Writer<File>,
+    elections_path: String,
+    dependents_path: String,
+    /// How many elections have been written
+    pub total: u64,
+}
+
+impl BenefitsWriter {
+    /**
+     * Start the benefits files.
+     *
+     * # Arguments
+     *
+     * - `options`: The benefits settings
+     * - `elections_path`: Where to write the elections
+     * - `dependents_path`: Where to write the dependents
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, with the headers written
+     * - `Err(msg)`: The files can't be written.
+     */
+    pub fn new(
+        options: &BenefitsOptions,
+        elections_path: &Path,
+        dependents_path: &Path,
+        seed: u64,
+    ) -> Result<BenefitsWriter, String> {
+        let start = |path: &Path, header: &[&str]| {
+            let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+            let mut w = WriterBuilder::new().from_path(path).map_err(write_err)?;
+            w.write_record(header).map_err(write_err)?;
+            Ok::<_, String>(w)
+        };
+
+        Ok(BenefitsWriter {
+            generator: BenefitsGenerator::new(options, seed),
+            elections: start(elections_path, &ELECTIONS_HEADER)?,
+            dependents: start(dependents_path, &DEPENDENTS_HEADER)?,
+            elections_path: path_str(elections_path).to_string(),
+            dependents_path: path_str(dependents_path).to_string(),
+            total: 0,
+        })
+    }
+
+    /**
+     * Generate and write a person's enrollment.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     * - `birth_date`: The person's birth date
+     */
+    pub fn write(&mut self, id: u64, birth_date: NaiveDate) -> Result<(), String> {
+        let enrollment = match self.generator.next_enrollment(birth_date) {
+            Some(enrollment) => enrollment,
+            None => return Ok(()),
+        };
+
+        for e in &enrollment.elections {
+            self.elections
+                .write_record([
+                    id.to_string(),
+                    enrollment.hire_date.to_string(),
+                    e.plan_type.to_string(),
+                    e.plan.to_string(),
+                    enrollment.coverage_tier.to_string(),
+                    e.effective_date.to_string(),
+                    e.end_date.to_string(),
+                    enrollment.dependents.len().to_string(),
+                ])
+                .map_err(|err| format!("Can't write to \"{}\": {}", self.elections_path, err))?;
+        }
+
+        // Dependents are only worth listing if something covers them.
+        if !enrollment.elections.is_empty() {
+            for (i, d) in enrollment.dependents.iter().enumerate() {
+                self.dependents
+                    .write_record([
+                        format!("{}-{}", id, i + 1),
+                        id.to_string(),
+                        d.relationship.to_string(),
+                        d.birth_date.to_string(),
+                    ])
+                    .map_err(|err| format!("Can't write to \"{}\": {}", self.dependents_path, err))?;
+            }
+        }
+
+        self.total += enrollment.elections.len() as u64;
+        Ok(())
+    }
+
+    /// Finish the files, and return how many elections there are.
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.elections
+            .flush()
+            .map_err(|e| format!("Can't write to \"{}\": {}", self.elections_path, e))?;
+        self.dependents
+            .flush()
+            .map_err(|e| format!("Can't write to \"{}\": {}", self.dependents_path, e))?;
+        Ok(self.total)
+    }
+}
+
+/// Add years to a date, making February 29 into February 28 if need be.
+fn add_years(date: NaiveDate, years: i32) -> NaiveDate {
+    let year = date.year() + years;
+    NaiveDate::from_ymd_opt(year, date.month(), date.day())
+        .unwrap_or_else(|| NaiveDate::from_ymd(year, date.month(), 28))
+}
+
+/// Get the first of the month after a date.
+fn first_of_next_month(date: NaiveDate) -> NaiveDate {
+    match date.month() {
+        12 => NaiveDate::from_ymd(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd(date.year(), month + 1, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::benefits::*;
+
+    #[test]
+    fn enrollments() {
+        let mut g = BenefitsGenerator::new(&BenefitsOptions { plan_year: 2025 }, 42);
+        let year_start = NaiveDate::from_ymd(2025, 1, 1);
+        let year_end = NaiveDate::from_ymd(2025, 12, 31);
+        let mut tiers = std::collections::HashSet::new();
+
+        for i in 0..5000 {
+            let birth_date = NaiveDate::from_ymd(1940 + i % 66, 1 + (i % 12) as u32, 1 + (i % 28) as u32);
+            let e = match g.next_enrollment(birth_date) {
+                Some(e) => e,
+                None => {
+                    assert!(add_years(birth_date, MIN_HIRE_AGE) > NaiveDate::from_ymd(2025, 11, 30));
+                    continue;
+                },
+            };
+            assert!(e.hire_date >= add_years(birth_date, MIN_HIRE_AGE), "{e:?}");
+            assert!(e.hire_date >= add_years(year_start, -MAX_TENURE_YEARS), "{e:?}");
+
+            for election in &e.elections {
+                assert!(election.effective_date > e.hire_date, "{e:?}");
+                assert!(election.effective_date >= year_start, "{e:?}");
+                assert_eq!(election.effective_date.day(), 1);
+                assert!(election.effective_date <= year_end);
+                assert_eq!(election.end_date, year_end);
+            }
+
+            let spouses = e.dependents.iter().filter(|d| d.relationship == "spouse").count();
+            let children: Vec<&Dependent> = e.dependents.iter().filter(|d| d.relationship == "child").collect();
+            assert!(spouses <= 1);
+            for child in &children {
+                assert!(child.birth_date >= add_years(birth_date, 20), "{e:?}");
+                assert!(child.birth_date <= year_end, "{e:?}");
+                assert!(add_years(child.birth_date, CHILD_AGE_LIMIT) > year_end, "{e:?}");
+            }
+            let tier = match (spouses, children.len()) {
+                (0, 0) => "employee",
+                (1, 0) => "employee_spouse",
+                (0, _) => "employee_children",
+                _ => "family",
+            };
+            assert_eq!(e.coverage_tier, tier);
+            tiers.insert(tier);
+        }
+        assert_eq!(tiers.len(), 4);
+    }
+
+    #[test]
+    fn seeds() {
+        let enrollments = |seed| {
+            let mut g = BenefitsGenerator::new(&BenefitsOptions { plan_year: 2025 }, seed);
+            (0..20).map(|_| g.next_enrollment(NaiveDate::from_ymd(1980, 6, 15))).collect::<Vec<_>>()
+        };
+        assert_eq!(enrollments(42), enrollments(42));
+        assert_ne!(enrollments(42), enrollments(43));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(add_years(NaiveDate::from_ymd(2000, 2, 29), 18), NaiveDate::from_ymd(2018, 2, 28));
+        assert_eq!(add_years(NaiveDate::from_ymd(2000, 3, 1), -1), NaiveDate::from_ymd(1999, 3, 1));
+        assert_eq!(first_of_next_month(NaiveDate::from_ymd(2024, 12, 31)), NaiveDate::from_ymd(2025, 1, 1));
+        assert_eq!(first_of_next_month(NaiveDate::from_ymd(2024, 1, 1)), NaiveDate::from_ymd(2024, 2, 1));
+    }
+}
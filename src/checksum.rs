@@ -0,0 +1,393 @@
+//! Check digits for generated identifiers, so that they pass the validation
+//! real systems apply to them. Generators use these, rather than working out
+//! the math themselves:
+//!
+//! - Luhn (mod 10), for NPIs and payment card numbers
+//! - ISO 7064 MOD 97-10, for IBANs
+//! - ISO 7064 MOD 11-2, for ISNIs and ORCID iDs
+//! - Verhoeff, which also catches every transposition of adjacent digits
+//! - the DEA registration number checksum
+//! - the vehicle identification number (VIN) check digit
+//!
+//! Each check digit function takes the identifier without its check digit(s)
+//! and returns them (except for VINs, whose check digit is in the middle);
+//! each `_valid` function takes the whole identifier.
+
+/**
+ * Compute the Luhn check digit for a number.
+ *
+ * # Arguments
+ *
+ * - `digits`: The number, without its check digit. Anything that isn't a
+ *   digit is ignored.
+ *
+ * # Returns
+ *
+ * The check digit, which makes the number valid when appended to it.
+ */
+pub fn luhn_check_digit(digits: &str) -> u32 {
+    // The check digit goes on the right, so the rightmost payload digit is
+    // the first one doubled.
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { luhn_double(d) } else { d })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/**
+ * Determine whether a number, including its check digit, passes the Luhn
+ * check.
+ *
+ * # Arguments
+ *
+ * - `number`: The number. Anything that isn't a digit is ignored.
+ */
+pub fn luhn_valid(number: &str) -> bool {
+    let mut digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    match digits.pop().and_then(|c| c.to_digit(10)) {
+        Some(check) => check == luhn_check_digit(&digits),
+        None => false,
+    }
+}
+
+/// Double a digit, the Luhn way: digits of the result are added together.
+fn luhn_double(d: u32) -> u32 {
+    let doubled = d * 2;
+    if doubled > 9 { doubled - 9 } else { doubled }
+}
+
+/**
+ * Compute the ISO 7064 MOD 97-10 check digits for a string of digits and
+ * letters. Letters count as two-digit numbers, `A` as 10 through `Z` as 35,
+ * as in IBANs.
+ *
+ * # Arguments
+ *
+ * - `s`: The string, without its check digits. Anything that isn't an ASCII
+ *   letter or digit is ignored.
+ *
+ * # Returns
+ *
+ * The check digits, from 2 to 98, which go on the end, as two digits.
+ */
+pub fn mod97_check_digits(s: &str) -> u32 {
+    98 - mod97(&format!("{s}00"))
+}
+
+/**
+ * Determine whether a string, including its two check digits at the end,
+ * passes the ISO 7064 MOD 97-10 check.
+ *
+ * # Arguments
+ *
+ * - `s`: The string. Anything that isn't an ASCII letter or digit is
+ *   ignored.
+ */
+pub fn mod97_valid(s: &str) -> bool {
+    s.chars().filter(char::is_ascii_alphanumeric).count() > 2 && mod97(s) == 1
+}
+
+/// Get the remainder of a string of digits and letters (`A` is 10, and so
+/// on) divided by 97.
+fn mod97(s: &str) -> u32 {
+    s.chars()
+        .filter_map(|c| c.to_digit(36))
+        .fold(0, |r, d| if d < 10 { (r * 10 + d) % 97 } else { (r * 100 + d) % 97 })
+}
+
+/**
+ * Compute the check digits for an IBAN: ISO 7064 MOD 97-10, over the
+ * account number (BBAN) followed by the country code.
+ *
+ * # Arguments
+ *
+ * - `country`: The two-letter country code, e.g., "GB"
+ * - `bban`: The basic bank account number
+ *
+ * # Returns
+ *
+ * The check digits, which go between the country code and the BBAN, as two
+ * digits.
+ */
+pub fn iban_check_digits(country: &str, bban: &str) -> u32 {
+    mod97_check_digits(&format!("{bban}{}", country.to_ascii_uppercase()))
+}
+
+/**
+ * Determine whether an IBAN's check digits are right.
+ *
+ * # Arguments
+ *
+ * - `iban`: The IBAN, with or without spaces
+ */
+pub fn iban_valid(iban: &str) -> bool {
+    let iban: String = iban.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase();
+    match (iban.get(..4), iban.get(4..)) {
+        (Some(start), Some(bban)) if !bban.is_empty() => mod97_valid(&format!("{bban}{start}")),
+        _ => false,
+    }
+}
+
+/**
+ * Compute the ISO 7064 MOD 11-2 check character for a string of digits.
+ *
+ * # Arguments
+ *
+ * - `digits`: The digits, without the check character. Anything that isn't
+ *   a digit is ignored.
+ *
+ * # Returns
+ *
+ * The check character: a digit, or `X` for 10.
+ */
+pub fn mod11_2_check_char(digits: &str) -> char {
+    let total = digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |total, d| (total + d) * 2 % 11);
+    match (12 - total % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10).unwrap(),
+    }
+}
+
+/**
+ * Determine whether a string of digits, including its check character at
+ * the end, passes the ISO 7064 MOD 11-2 check.
+ *
+ * # Arguments
+ *
+ * - `s`: The string. Anything but digits and a final `X` is ignored.
+ */
+pub fn mod11_2_valid(s: &str) -> bool {
+    let mut chars: String = s.chars().filter(|c| c.is_ascii_digit() || *c == 'X' || *c == 'x').collect();
+    match chars.pop() {
+        Some(check) if !chars.contains(['X', 'x']) => check.to_ascii_uppercase() == mod11_2_check_char(&chars),
+        _ => false,
+    }
+}
+
+/// The Verhoeff multiplication table: the dihedral group D5.
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+/// The Verhoeff permutation table: each row applies the permutation once
+/// more than the row before.
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
+/// The inverses in `VERHOEFF_D`.
+const VERHOEFF_INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+/**
+ * Compute the Verhoeff check digit for a number.
+ *
+ * # Arguments
+ *
+ * - `digits`: The number, without its check digit. Anything that isn't a
+ *   digit is ignored.
+ */
+pub fn verhoeff_check_digit(digits: &str) -> u32 {
+    // Positions count from the right, starting at 1 for the rightmost
+    // payload digit, since the check digit will be position 0.
+    let c = verhoeff(digits, 1);
+    u32::from(VERHOEFF_INV[c as usize])
+}
+
+/**
+ * Determine whether a number, including its check digit, passes the
+ * Verhoeff check.
+ *
+ * # Arguments
+ *
+ * - `number`: The number. Anything that isn't a digit is ignored.
+ */
+pub fn verhoeff_valid(number: &str) -> bool {
+    number.chars().any(|c| c.is_ascii_digit()) && verhoeff(number, 0) == 0
+}
+
+/// Run the Verhoeff algorithm over some digits, with the rightmost at
+/// position `first`.
+fn verhoeff(digits: &str, first: usize) -> u8 {
+    digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .fold(0, |c, (i, d)| VERHOEFF_D[c as usize][VERHOEFF_P[(i + first) % 8][d as usize] as usize])
+}
+
+/**
+ * Compute the check digit for the six digits of a DEA registration number:
+ * the last digit of the sum of the first, third and fifth digits, plus
+ * twice the sum of the second, fourth and sixth.
+ *
+ * # Arguments
+ *
+ * - `digits`: The six digits
+ */
+pub fn dea_check_digit(digits: [u32; 6]) -> u32 {
+    let odd = digits[0] + digits[2] + digits[4];
+    let even = digits[1] + digits[3] + digits[5];
+    (odd + 2 * even) % 10
+}
+
+/// The characters VINs are made of: digits and capital letters, except `I`,
+/// `O` and `Q`, which look too much like `1` and `0`.
+pub const VIN_CHARS: &str = "0123456789ABCDEFGHJKLMNPRSTUVWXYZ";
+
+/// How much each position of a VIN counts towards the check digit. The check
+/// digit's own position counts for nothing.
+const VIN_WEIGHTS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+
+/**
+ * Compute the check digit for a vehicle identification number: the sum of
+ * each character's value times its position's weight, mod 11, with `X` for
+ * 10. Digits are worth themselves, and letters 1 to 9, in order, starting
+ * again at `J` and `S` (so `A` and `J` are 1, and `S` is 2).
+ *
+ * # Arguments
+ *
+ * - `vin`: The VIN, all 17 characters. The ninth, where the check digit
+ *   goes, is ignored, so it can be anything.
+ *
+ * # Returns
+ *
+ * The check digit (or `X`), or `None` if `vin` isn't 17 VIN characters.
+ */
+pub fn vin_check_char(vin: &str) -> Option<char> {
+    let values: Vec<u32> = vin.chars().map(vin_value).collect::<Option<_>>()?;
+    if values.len() != VIN_WEIGHTS.len() {
+        return None;
+    }
+
+    let sum: u32 = values.iter().zip(VIN_WEIGHTS).map(|(v, w)| v * w).sum();
+    match sum % 11 {
+        10 => Some('X'),
+        check => char::from_digit(check, 10),
+    }
+}
+
+/**
+ * Determine whether a vehicle identification number's check digit is
+ * right. (VINs from outside North America don't always have one.)
+ *
+ * # Arguments
+ *
+ * - `vin`: The VIN
+ */
+pub fn vin_valid(vin: &str) -> bool {
+    vin.chars().nth(8).is_some_and(|c| vin_check_char(vin) == Some(c))
+}
+
+/// What a character is worth in a VIN check digit, or `None` if it can't be
+/// in a VIN.
+fn vin_value(c: char) -> Option<u32> {
+    const LETTER_VALUES: [u32; 23] = [1, 2, 3, 4, 5, 6, 7, 8, 1, 2, 3, 4, 5, 7, 9, 2, 3, 4, 5, 6, 7, 8, 9];
+    match VIN_CHARS.find(c)? {
+        i @ 0..=9 => Some(i as u32),
+        i => Some(LETTER_VALUES[i - 10]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::*;
+
+    #[test]
+    fn luhn() {
+        // The usual test card number, and CMS's example NPI (with the 80840
+        // prefix NPIs are checked with).
+        assert_eq!(luhn_check_digit("7992739871"), 3);
+        assert!(luhn_valid("79927398713"));
+        assert!(!luhn_valid("79927398710"));
+        assert!(luhn_valid("4111 1111 1111 1111"));
+        assert_eq!(luhn_check_digit("80840123456789"), 3);
+        assert!(!luhn_valid(""));
+        assert!(luhn_valid("0"));
+    }
+
+    #[test]
+    fn iso7064_mod97_10() {
+        // The example IBANs in the standard.
+        assert_eq!(iban_check_digits("GB", "WEST12345698765432"), 82);
+        assert_eq!(iban_check_digits("de", "370400440532013000"), 89);
+        assert!(iban_valid("GB82 WEST 1234 5698 7654 32"));
+        assert!(iban_valid("DE89370400440532013000"));
+        assert!(!iban_valid("GB82 WEST 1234 5698 7654 33"));
+        assert!(!iban_valid("GB82"));
+
+        assert_eq!(mod97_check_digits("123456"), 76);
+        assert!(mod97_valid("12345676"));
+        assert!(!mod97_valid("01"));
+    }
+
+    #[test]
+    fn iso7064_mod11_2() {
+        // ORCID iDs.
+        assert_eq!(mod11_2_check_char("000000021825009"), '7');
+        assert_eq!(mod11_2_check_char("0000-0001-5109-370"), '0');
+        assert_eq!(mod11_2_check_char("000000021694233"), 'X');
+        assert!(mod11_2_valid("0000-0002-1694-233X"));
+        assert!(mod11_2_valid("0000-0002-1825-0097"));
+        assert!(!mod11_2_valid("0000-0002-1825-0098"));
+        assert!(!mod11_2_valid("X0"));
+        assert!(!mod11_2_valid(""));
+    }
+
+    #[test]
+    fn verhoeff_digits() {
+        assert_eq!(verhoeff_check_digit("236"), 3);
+        assert_eq!(verhoeff_check_digit("75872"), 2);
+        assert_eq!(verhoeff_check_digit("12345"), 1);
+        assert!(verhoeff_valid("2363"));
+        assert!(verhoeff_valid("758722"));
+        assert!(!verhoeff_valid("758727"));
+        // Swapping adjacent digits is always caught.
+        assert!(!verhoeff_valid("578722"));
+        assert!(!verhoeff_valid(""));
+    }
+
+    #[test]
+    fn vins() {
+        // NHTSA's example, and a real one.
+        assert_eq!(vin_check_char("1M8GDM9A_KP042788"), None);
+        assert_eq!(vin_check_char("1M8GDM9A0KP042788"), Some('X'));
+        assert!(vin_valid("1M8GDM9AXKP042788"));
+        assert!(vin_valid("1HGCM82633A004352"));
+        assert!(vin_valid("11111111111111111"));
+        assert!(!vin_valid("1HGCM82643A004352"));
+        // I, O and Q aren't allowed, and it has to be 17 characters.
+        assert!(!vin_valid("1HGCM8263IA004352"));
+        assert!(!vin_valid("1HGCM82633A00435"));
+        assert!(!vin_valid("1hgcm82633a004352"));
+    }
+
+    #[test]
+    fn dea() {
+        assert_eq!(dea_check_digit([1, 2, 3, 4, 5, 6]), 3);
+        assert_eq!(dea_check_digit([0, 0, 0, 0, 0, 0]), 0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Clustered output: the people written grouped by a key, so the values that
+//! compress well together sit together, and files partitioned on the key
+//! come out smaller. The only key is the birth year.
+//!
+//! Sorting would mean holding every person in memory, so, instead, the birth
+//! dates themselves are generated in order, one at a time: each is the
+//! smallest of the uniformly random dates still to come, drawn from its
+//! distribution directly. The dates are distributed just as they are
+//! unclustered; they just come out sorted, birth years and all.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Mixed into the run's seed to seed the birth dates' random number
+/// generator.
+const CLUSTER_SEED_SALT: u64 = 0x636c_7573_7465_7273;
+
+/// The names `--cluster-by` accepts.
+pub const CLUSTER_BY_NAMES: [&str; 1] = ["birth_year"];
+
+/// The key to group the people by.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClusterBy {
+    /// People born in the same year are written together, youngest last.
+    BirthYear,
+}
+
+impl ClusterBy {
+    /**
+     * Look up a clustering key by name.
+     *
+     * # Arguments
+     *
+     * - `name`: The name, one of `CLUSTER_BY_NAMES`
+     *
+     * # Returns
+     *
+     * The key, or `None` if the name isn't one.
+     */
+    pub fn from_name(name: &str) -> Option<ClusterBy> {
+        match name {
+            "birth_year" => Some(ClusterBy::BirthYear),
+            _ => None,
+        }
+    }
+
+    /// The key's name, as `--cluster-by` takes it.
+    pub fn name(&self) -> &str {
+        match self {
+            ClusterBy::BirthYear => "birth_year",
+        }
+    }
+}
+
+/// Generates uniformly random birth dates, in order.
+pub struct SortedBirthDates {
+    rng: ChaCha8Rng,
+    start: i64,
+    end: i64,
+    /// How many dates are still to come
+    left: u64,
+    /// How far from the end of the range the last date was, from 1 (the
+    /// start of the range) to 0 (its end)
+    above: f64,
+}
+
+impl SortedBirthDates {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `start`: The earliest birth time, as a Unix timestamp
+     * - `end`: The latest birth time, as a Unix timestamp
+     * - `total`: How many dates to generate
+     * - `seed`: The run's random number generator seed
+     */
+    pub fn new(start: i64, end: i64, total: u64, seed: u64) -> SortedBirthDates {
+        SortedBirthDates {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ CLUSTER_SEED_SALT),
+            start,
+            end,
+            left: total,
+            above: 1.0,
+        }
+    }
+}
+
+impl Iterator for SortedBirthDates {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+
+        // The largest of n uniform values is distributed as U^(1/n), so the
+        // distance above the smallest of the dates still to come is, too.
+        self.above *= self.rng.gen::<f64>().powf(1.0 / self.left as f64);
+        self.left -= 1;
+
+        let span = (self.end - self.start + 1) as f64;
+        let epoch_birth = (self.start + ((1.0 - self.above) * span) as i64).min(self.end);
+        Some(NaiveDateTime::from_timestamp(epoch_birth, 0).date())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cluster::*;
+    use chrono::Datelike;
+
+    fn timestamp(year: i32, month: u32, day: u32) -> i64 {
+        NaiveDate::from_ymd(year, month, day).and_hms(0, 0, 0).timestamp()
+    }
+
+    #[test]
+    fn sorted() {
+        let dates: Vec<NaiveDate> = SortedBirthDates::new(timestamp(1950, 1, 1), timestamp(2000, 12, 31), 10_000, 42)
+            .collect();
+
+        assert_eq!(dates.len(), 10_000);
+        assert!(dates.windows(2).all(|d| d[0] <= d[1]));
+        assert!(dates[0].year() >= 1950 && dates[9_999].year() <= 2000);
+
+        // Uniform, so the first 25 of the 51 years get about 25/51 of the
+        // people (4,902).
+        let first_years = dates.iter().filter(|d| d.year() < 1975).count();
+        assert!((4_750..5_050).contains(&first_years), "{first_years}");
+    }
+
+    #[test]
+    fn seeds() {
+        let dates = |seed| SortedBirthDates::new(timestamp(1950, 1, 1), timestamp(1950, 12, 31), 100, seed)
+            .collect::<Vec<_>>();
+        assert_eq!(dates(42), dates(42));
+        assert_ne!(dates(42), dates(43));
+        assert!(dates(42).iter().all(|d| d.year() == 1950));
+    }
+
+    #[test]
+    fn names() {
+        for name in CLUSTER_BY_NAMES {
+            assert_eq!(ClusterBy::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(ClusterBy::from_name("ssn"), None);
+    }
+}
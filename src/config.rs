@@ -0,0 +1,392 @@
+//! Configuration for generating people from other programs, without the
+//! command line. A `GenerationConfig` holds the settings the command line
+//! would: start with `GenerationConfig::builder()`, which has the command
+//! line's defaults, override whatever you need, and `build()` it, which
+//! checks the settings as the command line does.
+//!
+//! ```no_run
+//! use peoplegen::GenerationConfig;
+//!
+//! let config = GenerationConfig::builder()
+//!     .last_names_file("names/last.csv")
+//!     .total(1000)
+//!     .female_percent(60)
+//!     .year_range(1950..=2000)
+//!     .seed(42)
+//!     .build()?;
+//! let names = config.read_names()?;
+//!
+//! for person in config.people(&names)? {
+//!     println!("{} {}", person.first_name, person.last_name);
+//! }
+//! # Ok::<(), peoplegen::PeoplegenError>(())
+//! ```
+
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::error::PeoplegenError;
+use crate::args::{names_file_default, validate, year_before_now, Arguments, ENDING_YEAR_DEFAULT_DELTA,
+                  ENV_FEMALE_FIRST_NAMES_FILE, ENV_LAST_NAMES_FILE, ENV_MALE_FIRST_NAMES_FILE,
+                  SALARY_MEAN_DEFAULT, SALARY_SIGMA_DEFAULT, STARTING_YEAR_DEFAULT_DELTA};
+use crate::fetch::{FEMALE_FIRST_NAMES_FILE, LAST_NAMES_FILE, MALE_FIRST_NAMES_FILE};
+use crate::genders::{gender_categories, GenderCategory};
+use crate::people::{make_people, read_names_file, PersonGenerator};
+use crate::weighted::WeightedValues;
+
+/// The settings for generating people, from `GenerationConfigBuilder`.
+#[derive(Debug)]
+pub struct GenerationConfig {
+    args: Arguments,
+}
+
+/// The names to generate people from, read by `GenerationConfig::read_names()`.
+#[derive(Debug, Clone)]
+pub struct Names {
+    /// The gender categories, with their first names
+    pub categories: Vec<GenderCategory>,
+    pub last_names: WeightedValues,
+}
+
+/**
+ * Builds a `GenerationConfig`. Start with `GenerationConfig::builder()`,
+ * which uses the command line's defaults, then override whatever you need.
+ */
+#[derive(Debug, Clone)]
+pub struct GenerationConfigBuilder {
+    male_first_names_file: PathBuf,
+    female_first_names_file: PathBuf,
+    other_first_names_file: Option<PathBuf>,
+    last_names_file: PathBuf,
+    total: u64,
+    female_percent: u32,
+    male_percent: Option<u32>,
+    other_percent: u32,
+    years: RangeInclusive<u32>,
+    salary_mean: u32,
+    salary_sigma: u32,
+    seed: Option<u64>,
+    strict: bool,
+}
+
+impl GenerationConfigBuilder {
+    /**
+     * Set the male first names file. The default is the same as the
+     * command line's: `$PEOPLEGEN_MALE_FIRST_NAMES`, or the file
+     * `peoplegen fetch-names` wrote.
+     */
+    pub fn male_first_names_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.male_first_names_file = path.into();
+        self
+    }
+
+    /**
+     * Set the female first names file. The default is the same as the
+     * command line's: `$PEOPLEGEN_FEMALE_FIRST_NAMES`, or the file
+     * `peoplegen fetch-names` wrote.
+     */
+    pub fn female_first_names_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.female_first_names_file = path.into();
+        self
+    }
+
+    /**
+     * Set the first names file for nonbinary people. By default, their
+     * names come from both the male and female first names files.
+     */
+    pub fn other_first_names_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.other_first_names_file = Some(path.into());
+        self
+    }
+
+    /**
+     * Set the last names file. The default is the same as the command
+     * line's: `$PEOPLEGEN_LAST_NAMES`, or the file `peoplegen fetch-names`
+     * wrote.
+     */
+    pub fn last_names_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.last_names_file = path.into();
+        self
+    }
+
+    /**
+     * Set how many people to generate. The default is 0.
+     */
+    pub fn total(mut self, total: u64) -> Self {
+        self.total = total;
+        self
+    }
+
+    /**
+     * Set the percentage of women. The default is 50. Unless
+     * `male_percent()` says otherwise, the rest (after any nonbinary people)
+     * are men.
+     */
+    pub fn female_percent(mut self, percent: u32) -> Self {
+        self.female_percent = percent;
+        self
+    }
+
+    /**
+     * Set the percentage of men. The default is whatever `female_percent()`
+     * and `other_percent()` leave. The three must add up to 100.
+     */
+    pub fn male_percent(mut self, percent: u32) -> Self {
+        self.male_percent = Some(percent);
+        self
+    }
+
+    /**
+     * Set the percentage of nonbinary people, whose gender is
+     * `Gender::NonBinary`. The default is 0.
+     */
+    pub fn other_percent(mut self, percent: u32) -> Self {
+        self.other_percent = percent;
+        self
+    }
+
+    /**
+     * Set the range of birth years. The default is the same as the command
+     * line's: from 90 years ago to 18 years ago.
+     */
+    pub fn year_range(mut self, years: RangeInclusive<u32>) -> Self {
+        self.years = years;
+        self
+    }
+
+    /**
+     * Set the mean of the (normal) salary distribution. The default is the
+     * command line's, 58,260.
+     */
+    pub fn salary_mean(mut self, mean: u32) -> Self {
+        self.salary_mean = mean;
+        self
+    }
+
+    /**
+     * Set the standard deviation of the salary distribution. The default is
+     * the command line's, 5,000.
+     */
+    pub fn salary_sigma(mut self, sigma: u32) -> Self {
+        self.salary_sigma = sigma;
+        self
+    }
+
+    /**
+     * Set the random number generator seed, so the same settings always
+     * generate the same people. By default, a seed is chosen at random;
+     * `GenerationConfig::seed()` says what it was.
+     */
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /**
+     * Make warnings errors, as the command line's `--strict` does. The
+     * default is to log them and carry on.
+     */
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /**
+     * Build the configuration, checking the settings as the command line
+     * does.
+     *
+     * # Returns
+     *
+     * - `Ok(config)`: The configuration
+     * - `Err(PeoplegenError::Validation(msg))`: The settings are invalid;
+     *   `msg` explains why.
+     */
+    pub fn build(self) -> Result<GenerationConfig, PeoplegenError> {
+        let (year_min, year_max) = self.years.into_inner();
+        let args = Arguments {
+            male_first_names_file: self.male_first_names_file,
+            female_first_names_file: self.female_first_names_file,
+            other_first_names_file: self.other_first_names_file,
+            last_names_file: self.last_names_file,
+            total: self.total,
+            female_percent: self.female_percent,
+            male_percent: self.male_percent
+                .unwrap_or(100u32.saturating_sub(self.female_percent).saturating_sub(self.other_percent)),
+            other_percent: self.other_percent,
+            year_min,
+            year_max,
+            salary_mean: self.salary_mean,
+            salary_sigma: self.salary_sigma,
+            seed: self.seed.unwrap_or_else(rand::random),
+            random_seed: self.seed.is_none(),
+            strict: self.strict,
+            ..Default::default()
+        };
+
+        validate(args).map(|args| GenerationConfig { args }).map_err(PeoplegenError::Validation)
+    }
+}
+
+impl GenerationConfig {
+    /**
+     * Get a builder, initialized with the command line's defaults.
+     */
+    pub fn builder() -> GenerationConfigBuilder {
+        GenerationConfigBuilder {
+            male_first_names_file: names_file_default(ENV_MALE_FIRST_NAMES_FILE, MALE_FIRST_NAMES_FILE).into(),
+            female_first_names_file: names_file_default(ENV_FEMALE_FIRST_NAMES_FILE, FEMALE_FIRST_NAMES_FILE).into(),
+            other_first_names_file: None,
+            last_names_file: names_file_default(ENV_LAST_NAMES_FILE, LAST_NAMES_FILE).into(),
+            total: 0,
+            female_percent: 50,
+            male_percent: None,
+            other_percent: 0,
+            years: year_before_now(STARTING_YEAR_DEFAULT_DELTA)..=year_before_now(ENDING_YEAR_DEFAULT_DELTA),
+            // The defaults are numbers.
+            salary_mean: SALARY_MEAN_DEFAULT.parse().unwrap(),
+            salary_sigma: SALARY_SIGMA_DEFAULT.parse().unwrap(),
+            seed: None,
+            strict: false,
+        }
+    }
+
+    /**
+     * Get the seed: the one given to the builder, or the one chosen at
+     * random.
+     */
+    pub fn seed(&self) -> u64 {
+        self.args.seed
+    }
+
+    /**
+     * Get the settings as command-line arguments, for the rest of the
+     * library (e.g., `people::write_people()`), which takes them.
+     */
+    pub fn arguments(&self) -> &Arguments {
+        &self.args
+    }
+
+    /**
+     * Read the names files.
+     *
+     * # Returns
+     *
+     * - `Ok(names)`: The names
+     * - `Err(e)`: A file couldn't be read (`PeoplegenError::Io`) or is
+     *   invalid (`PeoplegenError::Parse`).
+     */
+    pub fn read_names(&self) -> Result<Names, PeoplegenError> {
+        Ok(Names {
+            categories: gender_categories(&self.args)?,
+            last_names: read_names_file(&self.args.last_names_file, self.args.strict)?,
+        })
+    }
+
+    /**
+     * Start generating the people.
+     *
+     * # Arguments
+     *
+     * - `names`: The names to generate them from, from `read_names()`
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: A `PersonGenerator` that yields the people
+     * - `Err(PeoplegenError::Validation(msg))`: The settings are invalid;
+     *   `msg` explains why.
+     */
+    pub fn people<'a>(&self, names: &'a Names) -> Result<PersonGenerator<'a>, PeoplegenError> {
+        make_people(&self.args, &names.categories, &names.last_names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::*;
+    use crate::people::Gender;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A builder that uses names files in a directory, with one name each.
+    fn builder(dir: &TempDir) -> GenerationConfigBuilder {
+        let file = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+
+        GenerationConfig::builder()
+            .male_first_names_file(file("male.txt", "Moe\n"))
+            .female_first_names_file(file("female.txt", "Shemp\n"))
+            .last_names_file(file("last.txt", "Howard\n"))
+    }
+
+    #[test]
+    fn people() {
+        let dir = TempDir::new().unwrap();
+        let config = builder(&dir).total(100).female_percent(30).year_range(1950..=1959).seed(42).build().unwrap();
+        let names = config.read_names().unwrap();
+        let people: Vec<_> = config.people(&names).unwrap().collect();
+
+        assert_eq!(config.seed(), 42);
+        assert_eq!(people.len(), 100);
+        assert_eq!(people.iter().filter(|p| p.gender == Gender::Female).count(), 30);
+        assert!(people.iter().all(|p| &*p.last_name == "Howard"));
+        assert!(people.iter().all(|p| (1950..=1959).contains(&p.birth_date.format("%Y").to_string().parse().unwrap())));
+
+        // The same seed gets the same people.
+        let again: Vec<_> = config.people(&names).unwrap().map(|p| p.birth_date).collect();
+        assert_eq!(again, people.iter().map(|p| p.birth_date).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn nonbinary() {
+        let dir = TempDir::new().unwrap();
+        let config = builder(&dir).total(100).female_percent(40).other_percent(20).seed(42).build().unwrap();
+        let names = config.read_names().unwrap();
+        let people: Vec<_> = config.people(&names).unwrap().collect();
+        let count = |gender: Gender| people.iter().filter(|p| p.gender == gender).count();
+
+        assert_eq!((count(Gender::Male), count(Gender::Female), count(Gender::NonBinary)), (40, 40, 20));
+        // Without their own names, nonbinary people get names from both lists.
+        let names: Vec<&str> = people.iter().filter(|p| p.gender == Gender::NonBinary).map(|p| &*p.first_name).collect();
+        assert!(names.contains(&"Moe") && names.contains(&"Shemp"), "{names:?}");
+
+        let other = dir.path().join("other.txt");
+        fs::write(&other, "Curly\n").unwrap();
+        let config = builder(&dir).total(10).female_percent(0).other_percent(100).other_first_names_file(other).build().unwrap();
+        let names = config.read_names().unwrap();
+        assert!(config.people(&names).unwrap().all(|p| p.gender == Gender::NonBinary && &*p.first_name == "Curly"));
+    }
+
+    #[test]
+    fn validation() {
+        let dir = TempDir::new().unwrap();
+
+        let e = builder(&dir).female_percent(60).male_percent(60).build().unwrap_err();
+        assert!(matches!(e, PeoplegenError::Validation(_)));
+        assert_eq!(e.to_string(), "Female and male percentages must add up to 100.");
+
+        let e = builder(&dir).female_percent(60).male_percent(30).other_percent(20).build().unwrap_err();
+        assert_eq!(e.to_string(), "Female, male and other percentages must add up to 100, not 110.");
+
+        let e = builder(&dir).year_range(RangeInclusive::new(2000, 1990)).build().unwrap_err();
+        assert_eq!(e.to_string(), "Minimum year 2000 exceeds maximum year 1990.");
+
+        let e = builder(&dir).last_names_file("").build().unwrap_err();
+        assert!(e.to_string().starts_with("Last names file not specified"), "{e}");
+    }
+
+    #[test]
+    fn read_errors() {
+        let dir = TempDir::new().unwrap();
+
+        let e = builder(&dir).last_names_file(dir.path().join("missing.txt")).build().unwrap().read_names().unwrap_err();
+        assert!(matches!(&e, PeoplegenError::Io { source, .. } if source.kind() == std::io::ErrorKind::NotFound));
+        assert!(e.to_string().contains("missing.txt"), "{e}");
+
+        fs::write(dir.path().join("bad.txt"), "Howard,-1\n").unwrap();
+        let e = builder(&dir).last_names_file(dir.path().join("bad.txt")).build().unwrap().read_names().unwrap_err();
+        assert!(matches!(e, PeoplegenError::Parse(_)), "{e}");
+    }
+}
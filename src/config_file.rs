@@ -0,0 +1,557 @@
+//! Configuration files: `--config FILE` reads command-line options from a TOML
+//! file, so a run's settings can be kept in one place, instead of on a long
+//! command line. Each key is an option's long name (with dashes or
+//! underscores), and each value is the option's value:
+//!
+//! ```toml
+//! female-pct = 60
+//! male-pct = 40
+//! ssn = true
+//! salary_mean = 65000
+//! format-field = ["birth_date=date:%m/%d/%Y", "ssn=digits-only"]
+//! output = "people.csv"
+//! total = 10000
+//! ```
+//!
+//! A flag is `true` (on) or `false` (off). An option that can be given more
+//! than once takes an array. `output` and `total` are the output file and
+//! the total.
+//!
+//! The file's options go in front of the command line's, where they have the
+//! same effect, and an option given on the command line replaces the file's,
+//! so a shared file can be adjusted for a run.
+//!
+//! `--dump-config` goes the other way: it writes a run's effective
+//! configuration as a configuration file, with every option's value, whether
+//! it came from the command line, a configuration file, the environment or a
+//! default, and the seed, even if it was chosen at random. Running with that
+//! file reproduces the run.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use toml::{Table, Value};
+
+use crate::path::path_str;
+
+/// The id (and long name) of the `--config` option.
+pub const CONFIG_OPTION: &str = "config";
+
+/// The id (and long name) of the `--dump-config` option.
+pub const DUMP_CONFIG_OPTION: &str = "dump-config";
+
+/// Where `--dump-config` writes the effective configuration, and what it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDump {
+    pub path: std::path::PathBuf,
+    pub toml: String,
+}
+
+/// What a command line gives, as far as merging in a configuration file is
+/// concerned.
+#[derive(Debug, Default, PartialEq)]
+struct Given {
+    /// The ids of the options it gives
+    options: HashSet<String>,
+    /// How many positional arguments it gives
+    positionals: usize,
+    /// Where its subcommand is, if it has one
+    subcommand: Option<usize>,
+    /// The `--config` file, if any
+    config: Option<String>,
+}
+
+/**
+ * Merge the options in the command line's `--config` file, if there is one,
+ * into the command line. The file's options go with the command or
+ * subcommand whose `--config` it is.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser, which says what the options are
+ * - `args`: The command line, starting with the program name
+ *
+ * # Returns
+ *
+ * - `Ok(args)`: The command line, with the file's options added, or
+ *   unchanged if there's no `--config`
+ * - `Err(msg)`: The file couldn't be read or is invalid; `msg` explains why.
+ */
+pub fn merge_config_file(command: &Command, args: Vec<String>) -> Result<Vec<String>, String> {
+    // Where the (sub)command's own arguments start.
+    let mut start = 1;
+    let mut command = command;
+    let (given, file) = loop {
+        let given = scan(command, args.get(start..).unwrap_or_default());
+        if let Some(file) = given.config.clone() {
+            break (given, file);
+        }
+        match given.subcommand {
+            Some(i) => {
+                command = command.find_subcommand(&args[start + i]).unwrap();
+                start += i + 1;
+            },
+            None => return Ok(args),
+        }
+    };
+
+    let path = Path::new(&file);
+    let contents = fs::read_to_string(path).map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
+    let table: Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| format!("\"{}\": {}", path_str(path), e.message()))?;
+    let (options, positionals) = config_args(command, &table, &given)
+        .map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
+
+    let mut merged = Vec::with_capacity(args.len() + options.len() + positionals.len());
+    merged.extend(args[..start].iter().cloned());
+    merged.extend(options);
+    merged.extend(args.into_iter().skip(start));
+    // A subcommand after the options doesn't take the output file and total.
+    if given.subcommand.is_none() {
+        merged.extend(positionals.into_iter().skip(given.positionals));
+    }
+
+    Ok(merged)
+}
+
+/**
+ * Find a command line's subcommand, if it has one.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser, which says what the options are
+ * - `args`: The command line, starting with the program name
+ *
+ * # Returns
+ *
+ * The subcommand's index in `args`, or `None` if there isn't one.
+ */
+pub fn subcommand_index(command: &Command, args: &[String]) -> Option<usize> {
+    scan(command, args.get(1..).unwrap_or_default()).subcommand.map(|i| i + 1)
+}
+
+/**
+ * Turn a table of options, as in a configuration file, into command-line
+ * arguments, all of them: the options, then the output file and total, if it
+ * gives them.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser
+ * - `table`: The options
+ *
+ * # Returns
+ *
+ * - `Ok(args)`: The arguments, without the program name
+ * - `Err(msg)`: The table is invalid; `msg` explains why.
+ */
+pub fn table_args(command: &Command, table: &Table) -> Result<Vec<String>, String> {
+    let (options, positionals) = config_args(command, table, &Given::default())?;
+    Ok(options.into_iter().chain(positionals).collect())
+}
+
+/**
+ * Turn a configuration file into command-line arguments.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser
+ * - `table`: The file's contents
+ * - `given`: What the command line gives. Options it gives are left out.
+ *
+ * # Returns
+ *
+ * - `Ok((options, positionals))`: The options, and the positional arguments
+ *   (the output file, then the total), as far as the file gives them
+ * - `Err(msg)`: The file is invalid; `msg` explains why.
+ */
+fn config_args(command: &Command, table: &Table, given: &Given) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut options = Vec::new();
+    let mut positionals: Vec<(usize, String)> = Vec::new();
+
+    for (key, value) in table {
+        let name = key.replace('_', "-");
+        // A subcommand that doesn't generate has no output file or total,
+        // and one that doesn't write has no output file.
+        let positional = command.get_positionals().any(|a| a.get_id() == name.as_str());
+        if ["output", "total"].contains(&name.as_str()) && !positional {
+            continue;
+        }
+        // "output" is the output file, not `--output`.
+        let arg = command
+            .get_positionals()
+            .find(|a| a.get_id() == name.as_str())
+            .or_else(|| command.get_arguments().find(|a| a.get_long() == Some(name.as_str())))
+            .filter(|a| a.get_id() != CONFIG_OPTION)
+            .ok_or_else(|| format!("Unknown option \"{key}\"."))?;
+
+        if arg.is_positional() {
+            let index = command.get_positionals().position(|a| a.get_id() == arg.get_id()).unwrap();
+            positionals.push((index, scalar(key, value)?));
+            continue;
+        }
+
+        if given.options.contains(arg.get_id().as_str()) {
+            continue;
+        }
+
+        let option = format!("--{}", arg.get_long().unwrap());
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, Value::Boolean(true)) => options.push(option),
+            (ArgAction::SetTrue, Value::Boolean(false)) => (),
+            (ArgAction::SetTrue, _) => return Err(format!("\"{key}\" is a flag: true or false.")),
+            (ArgAction::Count, Value::Integer(n)) if (0..=u8::MAX.into()).contains(n) => {
+                options.extend((0..*n).map(|_| option.clone()));
+            },
+            (ArgAction::Count, _) => return Err(format!("\"{key}\" is a count: 0, 1, 2 and so on.")),
+            (ArgAction::Append, Value::Array(values)) => {
+                for v in values {
+                    options.push(option.clone());
+                    options.push(scalar(key, v)?);
+                }
+            },
+            (_, Value::Array(_)) => return Err(format!("\"{key}\" takes a single value, not an array.")),
+            (_, value) if takes_value(arg) => {
+                options.push(option);
+                options.push(scalar(key, value)?);
+            },
+            _ => return Err(format!("\"{key}\" can't be set in a configuration file.")),
+        }
+    }
+
+    // The positionals only make sense in order, so stop at the first gap.
+    positionals.sort();
+    let positionals = positionals
+        .into_iter()
+        .enumerate()
+        .take_while(|(i, (index, _))| i == index)
+        .map(|(_, (_, value))| value)
+        .collect();
+
+    Ok((options, positionals))
+}
+
+/**
+ * Get a run's effective configuration, as a configuration file.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser
+ * - `matches`: What it parsed, from the command line and any `--config`
+ *   file
+ * - `resolved`: The values the program works out for itself, by option id,
+ *   which replace whatever was parsed: the seed, defaults that come from the
+ *   environment or the date, and so on. `None` leaves an option out.
+ *
+ * # Returns
+ *
+ * The configuration file. Defaults that can't be combined with the rest of
+ * the options (because they only go with an option that isn't on, say) are
+ * left out, since they have no effect.
+ */
+pub fn effective_config(command: &Command, matches: &ArgMatches, resolved: &[(&str, Option<Value>)]) -> String {
+    let mut table = Table::new();
+    let mut defaults = Vec::new();
+
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if [CONFIG_OPTION, DUMP_CONFIG_OPTION, "help", "version"].contains(&id) {
+            continue;
+        }
+
+        let key = arg.get_long().filter(|_| !arg.is_positional()).unwrap_or(id).to_string();
+        let value = match resolved.iter().find(|(r, _)| *r == id) {
+            Some((_, value)) => value.clone(),
+            None => parsed_value(arg, matches),
+        };
+
+        match (value, matches.value_source(id)) {
+            (None, _) => (),
+            (Some(value), Some(ValueSource::DefaultValue)) => defaults.push((key, value)),
+            (Some(value), _) => {
+                table.insert(key, value);
+            },
+        }
+    }
+
+    // A default that doesn't go with the other options isn't in effect.
+    for (key, value) in defaults {
+        let mut with_default = table.clone();
+        with_default.insert(key.clone(), value.clone());
+        if parses(command, &with_default) {
+            table.insert(key, value);
+        }
+    }
+
+    format!("# The effective configuration of a peoplegen run. Use it with --config.\n{table}")
+}
+
+/// Get an option's parsed value as a configuration file value, or `None`
+/// if it has none.
+fn parsed_value(arg: &Arg, matches: &ArgMatches) -> Option<Value> {
+    let id = arg.get_id().as_str();
+    if let ArgAction::SetTrue = arg.get_action() {
+        return Some(Value::Boolean(matches.get_flag(id)));
+    }
+    // Not counting at all is the default, and goes without saying.
+    if let ArgAction::Count = arg.get_action() {
+        return Some(matches.get_count(id)).filter(|n| *n > 0).map(|n| Value::Integer(n.into()));
+    }
+
+    let values: Vec<Value> = matches
+        .get_raw(id)?
+        .map(|v| typed(&v.to_string_lossy()))
+        .collect();
+    match arg.get_action() {
+        ArgAction::Append => Some(Value::Array(values)),
+        _ => values.into_iter().next(),
+    }
+}
+
+/// Get a value as a number, if it is one (and reads back the same), or a
+/// string.
+fn typed(s: &str) -> Value {
+    match s.parse::<i64>() {
+        Ok(n) if n.to_string() == s => Value::Integer(n),
+        _ => Value::String(s.to_string()),
+    }
+}
+
+/// Whether a configuration file's options are acceptable to the parser.
+fn parses(command: &Command, table: &Table) -> bool {
+    match config_args(command, table, &Given::default()) {
+        Ok((options, positionals)) => {
+            let args = std::iter::once(String::from("peoplegen")).chain(options).chain(positionals);
+            command.clone().try_get_matches_from(args).is_ok()
+        },
+        Err(_) => false,
+    }
+}
+
+/// Get a configuration file value as a command-line argument.
+fn scalar(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(n) => Ok(n.to_string()),
+        Value::Float(x) => Ok(x.to_string()),
+        Value::Datetime(d) => Ok(d.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Array(_) | Value::Table(_) => Err(format!("Bad value for \"{key}\": expected a string or a number.")),
+    }
+}
+
+/// Whether an option takes a value.
+fn takes_value(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Set | ArgAction::Append)
+}
+
+/**
+ * Find what a command line gives: which options, how many positional
+ * arguments, and where the subcommand is, if there is one. This is only as
+ * much of parsing as merging needs; the parser does the rest.
+ *
+ * # Arguments
+ *
+ * - `command`: The command-line parser
+ * - `args`: The command line, without the program name
+ */
+fn scan(command: &Command, args: &[String]) -> Given {
+    let mut given = Given::default();
+    let mut args = args.iter().enumerate();
+
+    while let Some((i, token)) = args.next() {
+        // The value, if the option has one and it's in the same token.
+        let (arg, inline) = if token == "--" {
+            given.positionals += args.len();
+            break;
+        }
+        else if let Some(long) = token.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            (command.get_arguments().find(|a| a.get_long() == Some(name)), value.is_some())
+        }
+        else if let Some(shorts) = token.strip_prefix('-').filter(|s| !s.is_empty()) {
+            let mut chars = shorts.chars();
+            let short = chars.next();
+            (command.get_arguments().find(|a| a.get_short() == short), chars.next().is_some())
+        }
+        else if command.get_subcommands().any(|c| c.get_name() == token) {
+            given.subcommand = Some(i);
+            break;
+        }
+        else {
+            given.positionals += 1;
+            continue;
+        };
+
+        let Some(arg) = arg else {
+            continue;
+        };
+        given.options.insert(arg.get_id().to_string());
+        if takes_value(arg) && !inline {
+            let value = args.next();
+            if arg.get_id() == CONFIG_OPTION {
+                given.config = value.map(|(_, v)| v.clone());
+            }
+        }
+        else if arg.get_id() == CONFIG_OPTION {
+            given.config = token.split_once('=').map(|(_, v)| v.to_string());
+        }
+    }
+
+    given
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_file::*;
+    use tempfile::TempDir;
+
+    fn command() -> Command {
+        let generate = Command::new("generate")
+            .arg(Arg::new("config").long("config"))
+            .arg(Arg::new("female").short('f').long("female-pct"))
+            .arg(Arg::new("ssn").long("ssn").action(ArgAction::SetTrue))
+            .arg(Arg::new("salary").long("salary").action(ArgAction::SetTrue))
+            .arg(Arg::new("verbose").short('v').long("verbose").action(ArgAction::Count))
+            .arg(Arg::new("format-field").long("format-field").action(ArgAction::Append))
+            .arg(Arg::new("output-sink").long("output"))
+            .arg(Arg::new("output"))
+            .arg(Arg::new("total"));
+        Command::new("test")
+            .args(generate.get_arguments().cloned())
+            .subcommand(generate)
+            .subcommand(Command::new("fetch-names"))
+    }
+
+    /// Merge a configuration file into a command line.
+    fn merge(config: &str, args: &[&str]) -> Result<Vec<String>, String> {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("peoplegen.toml");
+        fs::write(&path, config).unwrap();
+
+        let mut command_line = vec![String::from("test"), String::from("--config"), path_str(&path).to_string()];
+        command_line.extend(args.iter().map(|s| s.to_string()));
+        merge_config_file(&command(), command_line).map(|merged| {
+            let config = path_str(&path).to_string();
+            merged.into_iter().skip(1).filter(|s| *s != "--config" && *s != config).collect()
+        })
+    }
+
+    #[test]
+    fn options() {
+        let config = "female_pct = 60\nssn = true\nsalary = false\n\
+                      format-field = [\"ssn=x\", \"salary=y\"]\noutput = \"people.csv\"\ntotal = 100\n";
+        assert_eq!(
+            merge(config, &[]).unwrap(),
+            ["--female-pct", "60", "--format-field", "ssn=x", "--format-field", "salary=y", "--ssn",
+             "people.csv", "100"]
+        );
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let config = "female-pct = 60\nformat-field = [\"ssn=x\"]\noutput = \"people.csv\"\ntotal = 100\n";
+        assert_eq!(merge(config, &["-f", "70", "other.csv"]).unwrap(), ["--format-field", "ssn=x", "-f", "70", "other.csv", "100"]);
+        assert_eq!(merge(config, &["--female-pct=70", "--format-field", "salary=y", "a.csv", "5"]).unwrap(),
+                   ["--female-pct=70", "--format-field", "salary=y", "a.csv", "5"]);
+        // A subcommand doesn't get the output file and total.
+        assert_eq!(merge(config, &["fetch-names"]).unwrap(), ["--female-pct", "60", "--format-field", "ssn=x", "fetch-names"]);
+    }
+
+    #[test]
+    fn subcommand() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("peoplegen.toml");
+        fs::write(&path, "ssn = true\noutput = \"b.csv\"\ntotal = 100\n").unwrap();
+        let config = path_str(&path).to_string();
+
+        // The file's options go with the subcommand whose --config it is.
+        let args = ["test", "generate", "-f", "70", "--config", &config, "a.csv"].map(String::from).to_vec();
+        assert_eq!(
+            merge_config_file(&command(), args).unwrap(),
+            ["test", "generate", "--ssn", "-f", "70", "--config", &config, "a.csv", "100"]
+        );
+
+        // A subcommand without them ignores the output file and total.
+        let ddl = Command::new("ddl")
+            .arg(Arg::new("config").long("config"))
+            .arg(Arg::new("ssn").long("ssn").action(ArgAction::SetTrue));
+        let args = ["test", "ddl", "--config", &config].map(String::from).to_vec();
+        assert_eq!(merge_config_file(&command().subcommand(ddl), args).unwrap(), ["test", "ddl", "--ssn", "--config", &config]);
+    }
+
+    #[test]
+    fn subcommand_indexes() {
+        let index = |args: &[&str]| subcommand_index(&command(), &args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(index(&["test", "-f", "70", "--ssn", "generate", "a.csv"]), Some(4));
+        // An option's value isn't a subcommand, even if it's named like one.
+        assert_eq!(index(&["test", "--config", "generate", "generate"]), Some(3));
+        assert_eq!(index(&["test", "--ssn", "a.csv", "100"]), None);
+        assert_eq!(index(&["test"]), None);
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(merge("verbose = 2\n", &[]).unwrap(), ["--verbose", "--verbose"]);
+        assert_eq!(merge("verbose = 2\n", &["-v"]).unwrap(), ["-v"]);
+        let e = merge("verbose = true\n", &[]).unwrap_err();
+        assert!(e.ends_with("\"verbose\" is a count: 0, 1, 2 and so on."), "{e}");
+
+        let matches = command().get_matches_from(["test", "-vv"]);
+        assert!(effective_config(&command(), &matches, &[]).contains("verbose = 2\n"));
+    }
+
+    #[test]
+    fn bad_files() {
+        let e = merge("nope = 1\n", &[]).unwrap_err();
+        assert!(e.ends_with("Unknown option \"nope\"."), "{e}");
+        let e = merge("ssn = \"yes\"\n", &[]).unwrap_err();
+        assert!(e.ends_with("\"ssn\" is a flag: true or false."), "{e}");
+        let e = merge("female-pct = [1, 2]\n", &[]).unwrap_err();
+        assert!(e.ends_with("\"female-pct\" takes a single value, not an array."), "{e}");
+        let e = merge("config = \"other.toml\"\n", &[]).unwrap_err();
+        assert!(e.ends_with("Unknown option \"config\"."), "{e}");
+        assert!(merge("ssn = \n", &[]).is_err());
+
+        let missing = merge_config_file(&command(), vec![String::from("test"), String::from("--config=/nonexistent.toml")]);
+        assert!(missing.unwrap_err().starts_with("\"/nonexistent.toml\": "));
+    }
+
+    #[test]
+    fn dump() {
+        let command = command()
+            .arg(Arg::new("source-systems").long("source-systems").default_value("crm").requires("ssn"))
+            .arg(Arg::new("seed").long("seed"));
+        let matches = command.clone().get_matches_from(["test", "-f", "60", "--format-field", "ssn=x", "a.csv"]);
+        let resolved = [("seed", Some(Value::Integer(42))), ("total", Some(Value::Integer(10)))];
+
+        assert_eq!(
+            effective_config(&command, &matches, &resolved),
+            "# The effective configuration of a peoplegen run. Use it with --config.\n\
+             female-pct = 60\n\
+             format-field = [\"ssn=x\"]\n\
+             output = \"a.csv\"\n\
+             salary = false\n\
+             seed = 42\n\
+             ssn = false\n\
+             total = 10\n"
+        );
+
+        // With --ssn, --source-systems' default is in effect.
+        let matches = command.clone().get_matches_from(["test", "--ssn"]);
+        assert!(effective_config(&command, &matches, &[]).contains("source-systems = \"crm\"\n"));
+    }
+
+    #[test]
+    fn no_config() {
+        let args = vec![String::from("test"), String::from("--ssn")];
+        assert_eq!(merge_config_file(&command(), args.clone()).unwrap(), args);
+    }
+}
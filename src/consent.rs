@@ -0,0 +1,157 @@
+//! The optional marketing-consent fields, for testing consent-management
+//! and suppression logic: whether each person opted in to marketing email
+//! and to marketing text messages, when they made that choice, and which
+//! version of the consent wording they saw.
+//!
+//! The email and SMS choices are independent, each an opt-in with its own
+//! probability. The timestamp falls in a date window, and the version is
+//! chosen from a weighted list.
+//!
+//! Consent comes from its own random number generator, derived from the
+//! run's seed, so turning it on or off doesn't change any of the other
+//! generated values.
+
+use crate::weighted::WeightedValues;
+use chrono::naive::{NaiveDate, NaiveDateTime};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Mixed into the run's seed to seed the consent random number generator.
+const CONSENT_SEED_SALT: u64 = 0x636f_6e73_656e_7421;
+
+/// The default `--email-opt-in` percentage.
+pub const DEFAULT_EMAIL_OPT_IN: u32 = 60;
+
+/// The default `--sms-opt-in` percentage.
+pub const DEFAULT_SMS_OPT_IN: u32 = 25;
+
+/// The default `--consent-versions` list.
+pub const DEFAULT_CONSENT_VERSIONS: &str = "v1:1,v2:2,v3:5";
+
+/// The settings for consent generation, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsentOptions {
+    /// The percentage of people who opt in to marketing email
+    pub email_percent: u32,
+    /// The percentage of people who opt in to marketing text messages
+    pub sms_percent: u32,
+    /// The first day on which consent can have been recorded
+    pub recorded_min: NaiveDate,
+    /// The last day on which consent can have been recorded
+    pub recorded_max: NaiveDate,
+    /// The consent versions, with their relative weights
+    pub versions: WeightedValues,
+}
+
+/// A person's marketing consent. With serde, its fields are named as with
+/// the snake case header format, and any other header format's names are
+/// accepted, too (see `Person`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Consent {
+    #[serde(alias = "emailOptIn", alias = "Email Opt In")]
+    pub email_opt_in: bool,
+    #[serde(alias = "smsOptIn", alias = "SMS Opt In")]
+    pub sms_opt_in: bool,
+    #[serde(with = "crate::metadata::timestamp", alias = "consentTimestamp", alias = "Consent Timestamp")]
+    pub consent_timestamp: NaiveDateTime,
+    #[serde(alias = "consentVersion", alias = "Consent Version")]
+    pub consent_version: String,
+}
+
+/// Generates a `Consent` for each person.
+pub struct ConsentGenerator {
+    rng: ChaCha8Rng,
+    email_percent: u32,
+    sms_percent: u32,
+    window_start: i64,
+    window_end: i64,
+    versions: WeightedValues,
+}
+
+impl ConsentGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The consent settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &ConsentOptions, seed: u64) -> Result<ConsentGenerator, String> {
+        if options.recorded_min > options.recorded_max {
+            return Err(format!(
+                "Minimum consent date {} is after maximum consent date {}.",
+                options.recorded_min, options.recorded_max
+            ));
+        }
+
+        Ok(ConsentGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ CONSENT_SEED_SALT),
+            email_percent: options.email_percent,
+            sms_percent: options.sms_percent,
+            window_start: options.recorded_min.and_hms(0, 0, 0).timestamp(),
+            window_end: options.recorded_max.and_hms(23, 59, 59).timestamp(),
+            versions: options.versions.clone(),
+        })
+    }
+
+    /// Generate the consent for the next person.
+    pub fn next_consent(&mut self) -> Consent {
+        let email_opt_in = self.rng.gen_range(0..100) < self.email_percent;
+        let sms_opt_in = self.rng.gen_range(0..100) < self.sms_percent;
+        let recorded = self.rng.gen_range(self.window_start..=self.window_end);
+        let version = self.versions.choose(&mut self.rng);
+
+        Consent {
+            email_opt_in,
+            sms_opt_in,
+            consent_timestamp: NaiveDateTime::from_timestamp(recorded, 0),
+            consent_version: version.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consent::*;
+    use chrono::Datelike;
+
+    fn options(email_percent: u32, sms_percent: u32) -> ConsentOptions {
+        ConsentOptions {
+            email_percent,
+            sms_percent,
+            recorded_min: NaiveDate::from_ymd(2022, 1, 1),
+            recorded_max: NaiveDate::from_ymd(2022, 12, 31),
+            versions: WeightedValues::parse_list("v1:0,v2:1", "consent version").unwrap(),
+        }
+    }
+
+    #[test]
+    fn consent() {
+        let mut g = ConsentGenerator::new(&options(70, 0), 42).unwrap();
+        let consents: Vec<Consent> = (0..10000).map(|_| g.next_consent()).collect();
+
+        let email = consents.iter().filter(|c| c.email_opt_in).count();
+        assert!((6700..7300).contains(&email), "{email}");
+        assert!(consents.iter().all(|c| !c.sms_opt_in));
+        assert!(consents.iter().all(|c| c.consent_timestamp.date().year() == 2022));
+        // Zero-weight versions are never chosen.
+        assert!(consents.iter().all(|c| c.consent_version == "v2"));
+
+        let mut g = ConsentGenerator::new(&options(100, 100), 42).unwrap();
+        assert!((0..100).map(|_| g.next_consent()).all(|c| c.email_opt_in && c.sms_opt_in));
+    }
+
+    #[test]
+    fn bad_options() {
+        let mut o = options(50, 50);
+        o.recorded_min = NaiveDate::from_ymd(2023, 1, 1);
+        assert!(ConsentGenerator::new(&o, 42).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Field encryption and tokenization, so that generated sensitive-looking
+//! fields (e.g., Social Security numbers) can also exercise downstream
+//! decryption and detokenization code.
+//!
+//! Two methods are supported, specified on the command line as
+//! `field=method:keyfile`:
+//!
+//! - `aes-gcm`: AES-GCM encryption (AES-128 or AES-256, depending on the key
+//!   length). The output is the base64-encoded (standard alphabet, with
+//!   padding) concatenation of the 12-byte nonce, the ciphertext and the
+//!   16-byte authentication tag. Each nonce is derived from the run's seed,
+//!   the record ID and the field name, so output is reproducible with
+//!   `--seed`. (This is fine for test data, but don't reuse the key for
+//!   anything real.)
+//! - `token`: deterministic, format-preserving tokenization. Each digit is
+//!   replaced with another digit, and each ASCII letter with another letter
+//!   of the same case, using a keystream derived from the key and the field
+//!   name with HMAC-SHA256; everything else is left alone. The same value
+//!   always gets the same token, and `detokenize()` reverses it. This is
+//!   *not* cryptographically strong format-preserving encryption; it's meant
+//!   for testing.
+//!
+//! Key files contain either raw key bytes or the key in hex. AES-GCM keys
+//! must be 16 or 32 bytes long; tokenization keys can be any length.
+
+use crate::path::path_str;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// The methods that can be used to protect a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cipher {
+    AesGcm(Vec<u8>),
+    Token(Vec<u8>),
+}
+
+/// The names of the available methods, for help and error messages.
+pub const CIPHER_NAMES: [&str; 2] = ["aes-gcm", "token"];
+
+/// A cipher bound to a field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldCipher {
+    pub field: String,
+    pub cipher: Cipher,
+}
+
+impl FieldCipher {
+    /**
+     * Parse a `field=method:keyfile` specification, reading the key file.
+     *
+     * # Arguments
+     *
+     * - `s`: The specification
+     * - `fields`: The valid field names
+     *
+     * # Returns
+     *
+     * - `Ok(field_cipher)`: The parsed specification
+     * - `Err(msg)`: The specification or the key file is invalid; `msg`
+     *   explains why.
+     */
+    pub fn parse(s: &str, fields: &[&str]) -> Result<FieldCipher, String> {
+        let bad = || format!("Bad field encryption \"{s}\": expected FIELD=METHOD:KEYFILE");
+        let (field, rest) = s.split_once('=').ok_or_else(bad)?;
+        let (method, key_file) = rest.split_once(':').ok_or_else(bad)?;
+
+        if !fields.contains(&field) {
+            return Err(format!(
+                "Unknown field \"{}\" in \"{}\". Valid fields: {}",
+                field, s, fields.join(", ")
+            ));
+        }
+
+        let key = read_key(Path::new(key_file))?;
+        let cipher = match method {
+            "aes-gcm" if key.len() == 16 || key.len() == 32 => Ok(Cipher::AesGcm(key)),
+            "aes-gcm" => Err(format!(
+                "\"{}\": AES-GCM keys must be 16 or 32 bytes long, not {}.",
+                key_file, key.len()
+            )),
+            "token" => Ok(Cipher::Token(key)),
+            _ => Err(format!(
+                "Unknown encryption method \"{}\". Valid methods: {}",
+                method, CIPHER_NAMES.join(", ")
+            )),
+        }?;
+
+        Ok(FieldCipher { field: field.to_string(), cipher })
+    }
+}
+
+impl Cipher {
+    /**
+     * Get the method's name, as in a `--encrypt-field` specification.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cipher::AesGcm(_) => "aes-gcm",
+            Cipher::Token(_) => "token",
+        }
+    }
+
+    /**
+     * Encrypt or tokenize a field value.
+     *
+     * # Arguments
+     *
+     * - `field`: The field name
+     * - `id`: The ID of the record (used, with `seed`, to derive nonces)
+     * - `seed`: The run's random number generator seed
+     * - `value`: The value to protect
+     *
+     * # Returns
+     *
+     * The encrypted or tokenized value.
+     */
+    pub fn apply(&self, field: &str, id: u64, seed: u64, value: &str) -> String {
+        match self {
+            Cipher::AesGcm(key) => {
+                let digest = Sha256::new()
+                    .chain_update(seed.to_be_bytes())
+                    .chain_update(id.to_be_bytes())
+                    .chain_update(field.as_bytes())
+                    .finalize();
+                let nonce = Nonce::from_slice(&digest[..12]);
+                // Encryption can only fail for absurdly long plaintexts, and
+                // the keys were checked when they were loaded.
+                let ciphertext = if key.len() == 16 {
+                    Aes128Gcm::new_from_slice(key).unwrap()
+                        .encrypt(nonce, value.as_bytes())
+                }
+                else {
+                    Aes256Gcm::new_from_slice(key).unwrap()
+                        .encrypt(nonce, value.as_bytes())
+                }.expect("AES-GCM encryption failed");
+
+                let mut buf = nonce.to_vec();
+                buf.extend(ciphertext);
+                BASE64.encode(buf)
+            },
+            Cipher::Token(key) => shift(key, field, value, true),
+        }
+    }
+}
+
+/**
+ * Apply all the ciphers for a field to a value.
+ *
+ * # Arguments
+ *
+ * - `ciphers`: The field ciphers
+ * - `field`: The field name
+ * - `id`: The ID of the record
+ * - `seed`: The run's random number generator seed
+ * - `value`: The value to protect
+ *
+ * # Returns
+ *
+ * The protected value, or the original value if there are no ciphers for
+ * the field.
+ */
+pub fn protect_field(ciphers: &[FieldCipher],
+                     field: &str,
+                     id: u64,
+                     seed: u64,
+                     value: String) -> String {
+    ciphers
+        .iter()
+        .filter(|c| c.field == field)
+        .fold(value, |v, c| c.cipher.apply(field, id, seed, &v))
+}
+
+/**
+ * Reverse the tokenization done by the `token` method.
+ *
+ * # Arguments
+ *
+ * - `key`: The tokenization key
+ * - `field`: The field name
+ * - `token`: The tokenized value
+ *
+ * # Returns
+ *
+ * The original value.
+ */
+pub fn detokenize(key: &[u8], field: &str, token: &str) -> String {
+    shift(key, field, token, false)
+}
+
+/// Shift each digit and letter by the keystream, forward or backward.
+fn shift(key: &[u8], field: &str, value: &str, forward: bool) -> String {
+    let stream = keystream(key, field, value.len());
+
+    value.chars().zip(stream).map(|(c, k)| {
+        let (base, modulus) = match c {
+            '0'..='9' => (b'0', 10),
+            'a'..='z' => (b'a', 26),
+            'A'..='Z' => (b'A', 26),
+            _ => return c,
+        };
+        let offset = k % modulus;
+        let offset = if forward { offset } else { modulus - offset };
+        (base + (c as u8 - base + offset) % modulus) as char
+    }).collect()
+}
+
+/// Generate `len` bytes of HMAC-SHA256 keystream for a field.
+fn keystream(key: &[u8], field: &str, len: usize) -> Vec<u8> {
+    let mut stream: Vec<u8> = Vec::new();
+    let mut block: u32 = 0;
+
+    while stream.len() < len {
+        // HMAC accepts keys of any length.
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).unwrap();
+        mac.update(field.as_bytes());
+        mac.update(&block.to_be_bytes());
+        stream.extend(mac.finalize().into_bytes());
+        block += 1;
+    }
+
+    stream.truncate(len);
+    stream
+}
+
+/// Read a key file, which contains either raw bytes or hex.
+fn read_key(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let trimmed = text.trim();
+
+    let key = if !trimmed.is_empty() &&
+                 trimmed.len().is_multiple_of(2) &&
+                 trimmed.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..trimmed.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&trimmed[i..i + 2], 16).unwrap())
+            .collect()
+    }
+    else {
+        bytes
+    };
+
+    if key.is_empty() {
+        Err(format!("\"{}\": Key file is empty.", path_str(path)))
+    }
+    else {
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypt::*;
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+
+    #[test]
+    fn tokens_preserve_format() {
+        let key = b"secret".to_vec();
+        let token = Cipher::Token(key.clone()).apply("ssn", 1, 0, "900-01-0001");
+        assert_eq!(token.len(), 11);
+        assert_eq!(&token[3..4], "-");
+        assert_eq!(&token[6..7], "-");
+        assert!(token.chars().filter(|c| *c != '-').all(|c| c.is_ascii_digit()));
+        assert_ne!(token, "900-01-0001");
+    }
+
+    #[test]
+    fn tokens_are_deterministic_and_reversible() {
+        let key = b"secret".to_vec();
+        let cipher = Cipher::Token(key.clone());
+        let token = cipher.apply("last_name", 1, 0, "O'Neill");
+        assert_eq!(token, cipher.apply("last_name", 2, 99, "O'Neill"));
+        assert_eq!(detokenize(&key, "last_name", &token), "O'Neill");
+    }
+
+    #[test]
+    fn aes_gcm_round_trip() {
+        let key = vec![7u8; 32];
+        let encrypted = Cipher::AesGcm(key.clone()).apply("ssn", 1, 42, "900-01-0001");
+        let bytes = BASE64.decode(encrypted).unwrap();
+        let (nonce, ciphertext) = bytes.split_at(12);
+        let decrypted = Aes256Gcm::new_from_slice(&key).unwrap()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .unwrap();
+        assert_eq!(decrypted, b"900-01-0001");
+    }
+
+    #[test]
+    fn aes_gcm_nonces_vary() {
+        let cipher = Cipher::AesGcm(vec![7u8; 16]);
+        assert_ne!(cipher.apply("ssn", 1, 42, "x"), cipher.apply("ssn", 2, 42, "x"));
+        assert_eq!(cipher.apply("ssn", 1, 42, "x"), cipher.apply("ssn", 1, 42, "x"));
+    }
+}
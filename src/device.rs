@@ -0,0 +1,235 @@
+//! The optional device fields, for web-analytics test data: the kind of
+//! device each person uses (`desktop`, `mobile` or `tablet`), its operating
+//! system, and a browser user-agent string to match.
+//!
+//! Each is drawn from a weighted list, roughly in line with published
+//! browser market share figures: first the device type, then an operating
+//! system for it, then a browser for that, whose user agent gets a recent
+//! version number. So a `mobile` person never has `Windows`, and an `iOS`
+//! person's user agent is always an iPhone's.
+//!
+//! Devices come from their own random number generator, derived from the
+//! run's seed, so turning them on or off doesn't change any of the other
+//! generated values.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Mixed into the run's seed to seed the device random number generator.
+const DEVICE_SEED_SALT: u64 = 0x6465_7669_6365_7321;
+
+/// An operating system, how common it is on its device type, and its
+/// browsers' user agents, with how common each is. In a user agent,
+/// `{chrome}` and `{firefox}` are replaced with a major version number,
+/// `{safari}` with a Safari version (e.g., "17.5") and `{ios}` with the
+/// matching iOS version (e.g., "17_5").
+struct Os {
+    name: &'static str,
+    weight: u32,
+    user_agents: &'static [(&'static str, u32)],
+}
+
+const DESKTOP: [Os; 4] = [
+    Os {
+        name: "Windows",
+        weight: 72,
+        user_agents: &[
+            ("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36", 65),
+            ("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36 Edg/{chrome}.0.0.0", 20),
+            ("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:{firefox}.0) Gecko/20100101 Firefox/{firefox}.0", 15),
+        ],
+    },
+    Os {
+        name: "macOS",
+        weight: 16,
+        user_agents: &[
+            ("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) \
+              Version/{safari} Safari/605.1.15", 55),
+            ("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36", 40),
+            ("Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:{firefox}.0) Gecko/20100101 Firefox/{firefox}.0", 5),
+        ],
+    },
+    Os {
+        name: "Linux",
+        weight: 4,
+        user_agents: &[
+            ("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36", 60),
+            ("Mozilla/5.0 (X11; Linux x86_64; rv:{firefox}.0) Gecko/20100101 Firefox/{firefox}.0", 40),
+        ],
+    },
+    Os {
+        name: "ChromeOS",
+        weight: 2,
+        user_agents: &[
+            ("Mozilla/5.0 (X11; CrOS x86_64 14541.0.0) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36", 1),
+        ],
+    },
+];
+
+const MOBILE: [Os; 2] = [
+    Os {
+        name: "Android",
+        weight: 71,
+        user_agents: &[
+            ("Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Mobile Safari/537.36", 80),
+            ("Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) \
+              SamsungBrowser/25.0 Chrome/{chrome}.0.0.0 Mobile Safari/537.36", 20),
+        ],
+    },
+    Os {
+        name: "iOS",
+        weight: 29,
+        user_agents: &[
+            ("Mozilla/5.0 (iPhone; CPU iPhone OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) \
+              Version/{safari} Mobile/15E148 Safari/604.1", 85),
+            ("Mozilla/5.0 (iPhone; CPU iPhone OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) \
+              CriOS/{chrome}.0.0.0 Mobile/15E148 Safari/604.1", 15),
+        ],
+    },
+];
+
+const TABLET: [Os; 2] = [
+    Os {
+        name: "iPadOS",
+        weight: 55,
+        user_agents: &[
+            ("Mozilla/5.0 (iPad; CPU OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) \
+              Version/{safari} Mobile/15E148 Safari/604.1", 1),
+        ],
+    },
+    Os {
+        name: "Android",
+        weight: 45,
+        user_agents: &[
+            ("Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) \
+              Chrome/{chrome}.0.0.0 Safari/537.36", 1),
+        ],
+    },
+];
+
+/// The device types, how common each is, and their operating systems.
+const DEVICE_TYPES: [(&str, u32, &[Os]); 3] = [
+    ("mobile", 58, &MOBILE),
+    ("desktop", 40, &DESKTOP),
+    ("tablet", 2, &TABLET),
+];
+
+/// The Chrome and Firefox major versions user agents get.
+const CHROME_VERSIONS: std::ops::RangeInclusive<u32> = 120..=131;
+const FIREFOX_VERSIONS: std::ops::RangeInclusive<u32> = 121..=133;
+
+/// The Safari versions user agents get, which are also the iOS versions.
+const SAFARI_VERSIONS: [&str; 6] = ["16.6", "17.4", "17.5", "17.6", "18.0", "18.1"];
+
+/// A person's device. With serde, its fields are named as with the snake
+/// case header format, and any other header format's names are accepted,
+/// too (see `Person`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    /// "desktop", "mobile" or "tablet"
+    #[serde(alias = "deviceType", alias = "Device Type")]
+    pub device_type: String,
+    #[serde(alias = "OS", alias = "operatingSystem")]
+    pub os: String,
+    #[serde(alias = "userAgent", alias = "User Agent")]
+    pub user_agent: String,
+}
+
+/// Generates a `Device` for each person.
+pub struct DeviceGenerator {
+    rng: ChaCha8Rng,
+}
+
+impl DeviceGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `seed`: The run's random number generator seed
+     */
+    pub fn new(seed: u64) -> DeviceGenerator {
+        DeviceGenerator { rng: ChaCha8Rng::seed_from_u64(seed ^ DEVICE_SEED_SALT) }
+    }
+
+    /// Generate the device for the next person.
+    pub fn next_device(&mut self) -> Device {
+        let (device_type, _, systems) = pick(&mut self.rng, &DEVICE_TYPES, |d| d.1);
+        let os = pick(&mut self.rng, systems, |os| os.weight);
+        let (user_agent, _) = pick(&mut self.rng, os.user_agents, |ua| ua.1);
+        let safari = SAFARI_VERSIONS[self.rng.gen_range(0..SAFARI_VERSIONS.len())];
+
+        Device {
+            device_type: device_type.to_string(),
+            os: os.name.to_string(),
+            user_agent: user_agent
+                .replace("{chrome}", &self.rng.gen_range(CHROME_VERSIONS).to_string())
+                .replace("{firefox}", &self.rng.gen_range(FIREFOX_VERSIONS).to_string())
+                .replace("{safari}", safari)
+                .replace("{ios}", &safari.replace('.', "_")),
+        }
+    }
+}
+
+/// Choose one of some items at random, in proportion to their weights.
+fn pick<'a, T, W: Fn(&T) -> u32>(rng: &mut ChaCha8Rng, items: &'a [T], weight: W) -> &'a T {
+    let total: u32 = items.iter().map(&weight).sum();
+    let mut r = rng.gen_range(0..total);
+    for item in items {
+        if r < weight(item) {
+            return item;
+        }
+        r -= weight(item);
+    }
+    &items[items.len() - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn devices_are_consistent() {
+        let mut g = DeviceGenerator::new(42);
+        let mut types: HashMap<String, usize> = HashMap::new();
+        for _ in 0..10000 {
+            let d = g.next_device();
+            let systems: &[Os] = match d.device_type.as_str() {
+                "desktop" => &DESKTOP,
+                "mobile" => &MOBILE,
+                "tablet" => &TABLET,
+                other => panic!("Unexpected device type {other}"),
+            };
+            assert!(systems.iter().any(|os| os.name == d.os), "{d:?}");
+            assert!(d.user_agent.starts_with("Mozilla/5.0 ("), "{d:?}");
+            assert!(!d.user_agent.contains('{'), "{d:?}");
+            if d.os == "iOS" {
+                assert!(d.user_agent.contains("iPhone"), "{d:?}");
+            }
+            *types.entry(d.device_type).or_default() += 1;
+        }
+
+        // Roughly in proportion to the weights.
+        assert!((5500..6100).contains(&types["mobile"]), "{types:?}");
+        assert!((3700..4300).contains(&types["desktop"]), "{types:?}");
+        assert!((100..300).contains(&types["tablet"]), "{types:?}");
+    }
+
+    #[test]
+    fn same_seed_same_devices() {
+        let devices = |seed| {
+            let mut g = DeviceGenerator::new(seed);
+            (0..10).map(|_| g.next_device()).collect::<Vec<Device>>()
+        };
+        assert_eq!(devices(42), devices(42));
+        assert_ne!(devices(42), devices(43));
+    }
+}
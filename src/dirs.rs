@@ -0,0 +1,73 @@
+//! The standard places `peoplegen` looks for its files, when they aren't
+//! given on the command line or in the environment.
+//!
+//! On Unix-like systems, these follow the XDG Base Directory specification:
+//! configuration files go in `$XDG_CONFIG_HOME/peoplegen` (by default,
+//! `~/.config/peoplegen`), and data files, such as names files, in
+//! `$XDG_DATA_HOME/peoplegen` (by default, `~/.local/share/peoplegen`). On
+//! Windows, both go in `%APPDATA%\peoplegen`.
+
+use crate::env::{getenv, home_dir};
+use std::path::PathBuf;
+
+/// The name of `peoplegen`'s directory within each base directory.
+const APP_DIR: &str = "peoplegen";
+
+/**
+ * Get the directory for `peoplegen`'s configuration files.
+ *
+ * # Returns
+ *
+ * The directory (which may not exist), or `None` if there's no home
+ * directory to put it in.
+ */
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", &[".config"]).map(|d| d.join(APP_DIR))
+}
+
+/**
+ * Get the directory for `peoplegen`'s data files.
+ *
+ * # Returns
+ *
+ * The directory (which may not exist), or `None` if there's no home
+ * directory to put it in.
+ */
+pub fn data_dir() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", &[".local", "share"]).map(|d| d.join(APP_DIR))
+}
+
+/**
+ * Find a file in one of the standard directories.
+ *
+ * # Arguments
+ *
+ * - `dir`: The directory, e.g., from `config_dir()`
+ * - `name`: The file name
+ *
+ * # Returns
+ *
+ * The path to the file, or `None` if it doesn't exist.
+ */
+pub fn find_file(dir: Option<PathBuf>, name: &str) -> Option<PathBuf> {
+    dir.map(|d| d.join(name)).filter(|path| path.is_file())
+}
+
+/// Get a base directory: from the XDG environment variable, if it's set to
+/// an absolute path (as the specification requires), or the default, under
+/// the home directory.
+fn base_dir(xdg_var: &str, default: &[&str]) -> Option<PathBuf> {
+    if cfg!(windows) {
+        let appdata = getenv("APPDATA");
+        if !appdata.is_empty() {
+            return Some(PathBuf::from(appdata));
+        }
+    }
+
+    let from_env = PathBuf::from(getenv(xdg_var));
+    if from_env.is_absolute() {
+        return Some(from_env);
+    }
+
+    home_dir().map(|home| default.iter().fold(home, |dir, d| dir.join(d)))
+}
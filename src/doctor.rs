@@ -0,0 +1,459 @@
+//! `peoplegen doctor`: check that what a run needs from its environment is
+//! there, before a long run starts, and say what to do about anything that
+//! isn't. The checks are of the names files (they're set, readable, UTF-8 and
+//! not empty), the output file's directory (it can be written to), and the
+//! free space on its disk (enough for the output, going by a sample of the
+//! people).
+//!
+//! A run's options can be given to `doctor` as they are. They're parsed, but
+//! not checked against each other, as they are for a run, since a names file
+//! that isn't set is one of the things `doctor` is for.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::args::{Arguments, ENV_FEMALE_FIRST_NAMES_FILE, ENV_LAST_NAMES_FILE, ENV_MALE_FIRST_NAMES_FILE};
+use crate::gender_config::parse_gender_config;
+use crate::genders::gender_categories;
+use crate::output::{is_null, is_stdout, split_compression};
+use crate::path::{path_is_empty, path_str};
+use crate::people::{make_people, read_names_file, write_people};
+use crate::weighted::WeightedValues;
+
+/// How many people are written to estimate the output's size.
+const SAMPLE_SIZE: u64 = 1_000;
+
+/// How a check went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    /// Something the run can live with, but might not be what was meant
+    Warning,
+    /// Something that would stop the run
+    Problem,
+}
+
+/// The outcome of a check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub status: Status,
+    /// What was found, and, for a problem, what to do about it
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn ok(message: String) -> Diagnostic {
+        Diagnostic { status: Status::Ok, message }
+    }
+
+    fn warning(message: String) -> Diagnostic {
+        Diagnostic { status: Status::Warning, message }
+    }
+
+    fn problem(message: String) -> Diagnostic {
+        Diagnostic { status: Status::Problem, message }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Status::Ok => write!(f, "OK: {}", self.message),
+            Status::Warning => write!(f, "Warning: {}", self.message),
+            Status::Problem => write!(f, "Problem: {}", self.message),
+        }
+    }
+}
+
+/// A names file a run reads: what it's for, where it is, and what to do if
+/// it isn't there.
+struct NamesFile {
+    what: String,
+    path: PathBuf,
+    fix: String,
+}
+
+/**
+ * Check a run's environment.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments, for the run
+ *
+ * # Returns
+ *
+ * The outcome of each check, in order. The output's size is only estimated
+ * once the names files and the output file's directory check out.
+ */
+pub fn diagnose(args: &Arguments) -> Vec<Diagnostic> {
+    let mut diagnostics = check_names_files(args);
+    let names_ok = diagnostics.iter().all(|d| d.status != Status::Problem);
+
+    if let Some(dir) = output_dir(args) {
+        let dir_check = check_output_dir(&dir);
+        let dir_ok = dir_check.status == Status::Ok;
+        diagnostics.push(dir_check);
+        if names_ok && dir_ok && args.total > 0 {
+            diagnostics.push(check_free_space(args, &dir));
+        }
+    }
+    diagnostics
+}
+
+/// Check the names files: those the gender configuration file names, if
+/// there is one, or the first names files for each gender, and the last
+/// names file.
+fn check_names_files(args: &Arguments) -> Vec<Diagnostic> {
+    let fetch = "run \"peoplegen fetch-names\" to download the names files";
+    let mut diagnostics = Vec::new();
+    let mut files = Vec::new();
+
+    match &args.gender_config {
+        Some(config) => match read_text("gender configuration", config, "Check the path") {
+            Ok(text) => match parse_gender_config(text.as_bytes()) {
+                Ok(entries) => {
+                    diagnostics.push(Diagnostic::ok(format!(
+                        "\"{}\" (gender configuration): {} categories.", path_str(config), entries.len()
+                    )));
+                    let dir = config.parent().unwrap_or_else(|| Path::new(""));
+                    files.extend(entries.into_iter().map(|entry| NamesFile {
+                        what: format!("first names for \"{}\"", entry.label),
+                        path: dir.join(entry.names_file),
+                        fix: format!("Check its path in \"{}\"", path_str(config)),
+                    }));
+                },
+                Err(e) => diagnostics.push(Diagnostic::problem(format!(
+                    "\"{}\" (gender configuration): {}", path_str(config), e
+                ))),
+            },
+            Err(problem) => diagnostics.push(problem),
+        },
+        None => {
+            files.push(NamesFile {
+                what: String::from("male first names"),
+                path: args.male_first_names_file.clone(),
+                fix: format!("Give it with --male-names, set {ENV_MALE_FIRST_NAMES_FILE}, or {fetch}"),
+            });
+            files.push(NamesFile {
+                what: String::from("female first names"),
+                path: args.female_first_names_file.clone(),
+                fix: format!("Give it with --female-names, set {ENV_FEMALE_FIRST_NAMES_FILE}, or {fetch}"),
+            });
+            if let Some(path) = args.other_first_names_file.as_ref().filter(|_| args.other_percent > 0) {
+                files.push(NamesFile {
+                    what: String::from("other first names"),
+                    path: path.clone(),
+                    fix: String::from("Check the path given with --other-names"),
+                });
+            }
+        },
+    }
+    files.push(NamesFile {
+        what: String::from("last names"),
+        path: args.last_names_file.clone(),
+        fix: format!("Give it with --last-names, set {ENV_LAST_NAMES_FILE}, or {fetch}"),
+    });
+
+    diagnostics.extend(files.iter().map(|file| check_names_file(file, args.strict)));
+    diagnostics
+}
+
+/// Check that a names file can be read, and has names in it.
+fn check_names_file(file: &NamesFile, strict: bool) -> Diagnostic {
+    let text = match read_text(&file.what, &file.path, &file.fix) {
+        Ok(text) => text,
+        Err(problem) => return problem,
+    };
+    let name = path_str(&file.path);
+
+    if text.lines().map(str::trim).all(|line| line.is_empty() || line.starts_with('#')) {
+        return Diagnostic::problem(format!(
+            "\"{}\" ({}) has no names in it. {}.", name, file.what, file.fix
+        ));
+    }
+    match WeightedValues::parse(text.as_bytes()) {
+        Err(e) => Diagnostic::problem(format!("\"{}\" ({}): {} Fix the line.", name, file.what, e)),
+        Ok(names) => {
+            let duplicates = names.duplicates();
+            match duplicates.first() {
+                // Without --strict, the run goes ahead, so this is only a
+                // warning.
+                Some(first) => {
+                    let message = format!(
+                        "\"{}\" ({}): {} name(s) are listed more than once, e.g., \"{}\". Each gets the total \
+                         of its weights.", name, file.what, duplicates.len(), first
+                    );
+                    if strict { Diagnostic::problem(message) } else { Diagnostic::warning(message) }
+                },
+                None => Diagnostic::ok(format!("\"{}\" ({}): {} name(s).", name, file.what, names.len())),
+            }
+        },
+    }
+}
+
+/**
+ * Read a text file that a run needs.
+ *
+ * # Arguments
+ *
+ * - `what`: What the file is for, e.g., "last names"
+ * - `path`: Where it is, or empty, if it isn't set
+ * - `fix`: What to do if it isn't set, or isn't there
+ *
+ * # Returns
+ *
+ * - `Ok(text)`: The file's contents
+ * - `Err(problem)`: It couldn't be read, or isn't UTF-8.
+ */
+fn read_text(what: &str, path: &Path, fix: &str) -> Result<String, Diagnostic> {
+    if path_is_empty(path) {
+        return Err(Diagnostic::problem(format!("The {what} file isn't set. {fix}.")));
+    }
+
+    let name = path_str(path);
+    let bytes = fs::read(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => Diagnostic::problem(format!("\"{name}\" ({what}) doesn't exist. {fix}.")),
+        _ => Diagnostic::problem(format!("Can't read \"{name}\" ({what}): {e}. Check its permissions.")),
+    })?;
+    String::from_utf8(bytes).map_err(|e| {
+        Diagnostic::problem(format!(
+            "\"{}\" ({}) isn't UTF-8: byte {} isn't valid. Convert it, e.g., with \"iconv -f latin1 -t utf-8\".",
+            name, what, e.utf8_error().valid_up_to()
+        ))
+    })
+}
+
+/// Get the directory the output goes to, if it goes to a file.
+fn output_dir(args: &Arguments) -> Option<PathBuf> {
+    let path = &args.output_file;
+    if args.postgres.is_some() || path_is_empty(path) || is_stdout(path) || is_null(path) {
+        return None;
+    }
+
+    match path.parent() {
+        Some(dir) if !path_is_empty(dir) => Some(dir.to_path_buf()),
+        _ => Some(PathBuf::from(".")),
+    }
+}
+
+/// Check that files can be written in the output file's directory, by
+/// writing one.
+fn check_output_dir(dir: &Path) -> Diagnostic {
+    let name = path_str(dir);
+    match fs::metadata(dir) {
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Diagnostic::problem(format!(
+                "\"{name}\", the output file's directory, doesn't exist. Create it, or write somewhere else."
+            ));
+        },
+        Ok(metadata) if !metadata.is_dir() => {
+            return Diagnostic::problem(format!(
+                "\"{name}\", the output file's directory, isn't a directory. Write somewhere else."
+            ));
+        },
+        _ => (),
+    }
+
+    let probe = dir.join(format!(".peoplegen-doctor-{}", process::id()));
+    match File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Diagnostic::ok(format!("\"{name}\", the output file's directory, can be written to."))
+        },
+        Err(e) => Diagnostic::problem(format!(
+            "Can't write to \"{name}\", the output file's directory: {e}. Check its permissions, or write \
+             somewhere else."
+        )),
+    }
+}
+
+/// Check that there's room for the output on its disk.
+fn check_free_space(args: &Arguments, dir: &Path) -> Diagnostic {
+    let name = path_str(dir);
+    let size = match estimate_size(args, dir) {
+        Ok(size) => size,
+        Err(e) => return Diagnostic::warning(format!("Can't estimate the output's size: {e}")),
+    };
+
+    match fs4::available_space(dir) {
+        Ok(free) if free < size => {
+            let compress = match split_compression(&args.output_file) {
+                (_, Some(_)) => "",
+                (_, None) => ", or compress the output (e.g., with a \".gz\" extension)",
+            };
+            Diagnostic::problem(format!(
+                "The output will take about {}, but there's only {} free for \"{}\". Free some space, write \
+                 somewhere else{}.", size_str(size), size_str(free), name, compress
+            ))
+        },
+        Ok(free) => Diagnostic::ok(format!(
+            "The output will take about {}, and there's {} free for \"{}\".", size_str(size), size_str(free), name
+        )),
+        Err(e) => Diagnostic::warning(format!("Can't tell how much space is free for \"{name}\": {e}.")),
+    }
+}
+
+/**
+ * Estimate how big the output will be, by writing a sample of the people,
+ * as the run would, to a file in the output file's directory, and scaling
+ * its size up. The extra files (`--w2` and so on) aren't included.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `dir`: The output file's directory
+ *
+ * # Returns
+ *
+ * - `Ok(bytes)`: About how many bytes the output will take
+ * - `Err(msg)`: The sample couldn't be written; `msg` says why.
+ */
+fn estimate_size(args: &Arguments, dir: &Path) -> Result<u64, String> {
+    let categories = gender_categories(args).map_err(|e| e.to_string())?;
+    let last_names = read_names_file(&args.last_names_file, args.strict).map_err(|e| e.to_string())?;
+    let people = make_people(args, &categories, &last_names).map_err(|e| e.to_string())?;
+
+    // The sample's name ends with the output file's, so it's written in the
+    // same format, and compressed the same way.
+    let file_name = args.output_file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let sample = dir.join(format!(".peoplegen-doctor-{}-{}", process::id(), file_name));
+    let written = write_people(args, &sample, people.results().take(SAMPLE_SIZE as usize));
+    let size = fs::metadata(&sample).map(|m| m.len());
+    let _ = fs::remove_file(&sample);
+
+    match (written.map_err(|e| e.to_string())?, size.map_err(|e| e.to_string())?) {
+        (0, _) => Ok(0),
+        (n, size) => Ok((u128::from(size) * u128::from(args.total) / u128::from(n)).try_into().unwrap_or(u64::MAX)),
+    }
+}
+
+/// Format a number of bytes for people, in powers of 1024, e.g., "1.5 GiB".
+fn size_str(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doctor::*;
+    use tempfile::TempDir;
+
+    /// Write the fixtures' names files to a directory, and get arguments
+    /// that read them, and write `people.csv` there.
+    fn fixtures(dir: &Path) -> Arguments {
+        let write = |name: &str, names: &str| {
+            let path = dir.join(name);
+            fs::write(&path, names).unwrap();
+            path
+        };
+        Arguments {
+            male_first_names_file: write("male.txt", "Moe\nLarry\n"),
+            female_first_names_file: write("female.txt", "# Nobody else\nShemp\n"),
+            last_names_file: write("last.txt", "Howard\nFine\n"),
+            female_percent: 50,
+            male_percent: 50,
+            year_min: 1950,
+            year_max: 2000,
+            output_file: dir.join("people.csv"),
+            total: 10_000,
+            ..Default::default()
+        }
+    }
+
+    fn statuses(diagnostics: &[Diagnostic]) -> Vec<Status> {
+        diagnostics.iter().map(|d| d.status).collect()
+    }
+
+    #[test]
+    fn healthy() {
+        let dir = TempDir::new().unwrap();
+        let diagnostics = diagnose(&fixtures(dir.path()));
+
+        assert_eq!(statuses(&diagnostics), [Status::Ok; 5], "{diagnostics:?}");
+        assert!(diagnostics[0].to_string().ends_with("(male first names): 2 name(s)."), "{}", diagnostics[0]);
+        assert!(diagnostics[1].message.ends_with("(female first names): 1 name(s)."));
+        assert!(diagnostics[3].message.ends_with(", the output file's directory, can be written to."));
+        assert!(diagnostics[4].message.starts_with("The output will take about "));
+        // Nothing's left behind.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn names_problems() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("latin1.txt"), b"Jos\xe9\n").unwrap();
+        fs::write(dir.path().join("empty.txt"), "# No names\n\n").unwrap();
+        let args = Arguments {
+            male_first_names_file: PathBuf::new(),
+            female_first_names_file: dir.path().join("latin1.txt"),
+            last_names_file: dir.path().join("empty.txt"),
+            ..fixtures(dir.path())
+        };
+        let diagnostics = diagnose(&args);
+
+        // Without names, there's no estimate.
+        assert_eq!(statuses(&diagnostics), [Status::Problem, Status::Problem, Status::Problem, Status::Ok]);
+        assert!(diagnostics[0].message.starts_with("The male first names file isn't set. Give it with --male-names"));
+        assert!(diagnostics[1].message.contains("(female first names) isn't UTF-8: byte 3 isn't valid."));
+        assert!(diagnostics[2].message.contains("(last names) has no names in it."));
+
+        let args = Arguments { last_names_file: dir.path().join("missing.txt"), ..fixtures(dir.path()) };
+        let diagnostics = diagnose(&args);
+        assert!(diagnostics[2].message.contains("(last names) doesn't exist. Give it with --last-names"));
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("twice.txt"), "Moe\nMoe\n").unwrap();
+        let args = Arguments { male_first_names_file: dir.path().join("twice.txt"), ..fixtures(dir.path()) };
+        assert_eq!(diagnose(&args)[0].status, Status::Warning);
+
+        let args = Arguments { strict: true, ..args };
+        assert_eq!(diagnose(&args)[0].status, Status::Problem);
+    }
+
+    #[test]
+    fn output_dirs() {
+        let dir = TempDir::new().unwrap();
+        let args = Arguments { output_file: dir.path().join("missing").join("people.csv"), ..fixtures(dir.path()) };
+        let diagnostics = diagnose(&args);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics[3].message.ends_with(", the output file's directory, doesn't exist. Create it, or \
+                                                  write somewhere else."));
+
+        // Standard output has nothing to check.
+        let args = Arguments { output_file: PathBuf::from("-"), ..fixtures(dir.path()) };
+        assert_eq!(diagnose(&args).len(), 3);
+    }
+
+    #[test]
+    fn estimates() {
+        let dir = TempDir::new().unwrap();
+        let args = fixtures(dir.path());
+        let small = estimate_size(&Arguments { total: 1_000, ..fixtures(dir.path()) }, dir.path()).unwrap();
+        let large = estimate_size(&args, dir.path()).unwrap();
+        assert!(small > 0);
+        assert!(large > small * 9 && large < small * 11, "{small}, {large}");
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(size_str(1023), "1023 bytes");
+        assert_eq!(size_str(1536), "1.5 KiB");
+        assert_eq!(size_str(3 << 30), "3.0 GiB");
+    }
+}
@@ -1,6 +1,7 @@
 //! Environment variable-related helpers.
 
 use std::env;
+use std::path::PathBuf;
 
 /**
  * Get the value of an environment variable as a `String`, returning `""`
@@ -14,5 +15,17 @@ pub fn getenv(s: &str) -> String {
 
     env::var_os(s)
         .map(|v| v.into_string().unwrap())
-        .unwrap_or_else(|| String::new())
+        .unwrap_or_default()
+}
+
+/**
+ * Get the user's home directory, from `HOME` (or, on Windows, where `HOME`
+ * usually isn't set, `USERPROFILE`).
+ */
+pub fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .iter()
+        .map(|v| getenv(v))
+        .find(|dir| !dir.is_empty())
+        .map(PathBuf::from)
 }
@@ -0,0 +1,69 @@
+//! The errors the library's entry points return, so a program using the
+//! library can tell the kinds of failure apart. Each one's message is the
+//! one the command line prints.
+//!
+//! Inside the library, errors are still mostly messages (`String`s); they
+//! get their kind where they come out.
+
+use std::io;
+use std::path::PathBuf;
+
+use log::warn;
+use thiserror::Error;
+
+use crate::path::path_str;
+
+/// A failure, and what kind it was.
+#[derive(Debug, Error)]
+pub enum PeoplegenError {
+    /// A file couldn't be opened or read.
+    #[error("\"{}\": {source}", path_str(path))]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A file or a value is invalid, e.g., a names file with a bad weight.
+    #[error("{0}")]
+    Parse(String),
+
+    /// The settings are invalid, or don't make sense together.
+    #[error("{0}")]
+    Validation(String),
+
+    /// Generating the people failed part way through, e.g., because the
+    /// salary distribution produced a negative salary.
+    #[error("{0}")]
+    Generation(String),
+
+    /// The people couldn't be serialized to the output: encoded and written
+    /// to a file, or loaded into a database.
+    #[error("{0}")]
+    Serialization(String),
+}
+
+/// A `Result` with a `PeoplegenError`.
+pub type Result<T> = std::result::Result<T, PeoplegenError>;
+
+/**
+ * Report something that doesn't stop a run, but may not be what was meant,
+ * e.g., a names file that lists a name twice.
+ *
+ * # Arguments
+ *
+ * - `strict`: Whether warnings are errors (`--strict`)
+ * - `message`: What's wrong
+ *
+ * # Returns
+ *
+ * - `Ok(())`: It's a warning, and it's been logged.
+ * - `Err(PeoplegenError::Validation(message))`: It's an error.
+ */
+pub fn warning(strict: bool, message: String) -> Result<()> {
+    if strict {
+        return Err(PeoplegenError::Validation(message));
+    }
+    warn!("{}", message);
+    Ok(())
+}
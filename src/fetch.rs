@@ -0,0 +1,292 @@
+//! `peoplegen fetch-names`: download the public name data and turn it into
+//! the three names files `peoplegen` uses, so new users don't have to find
+//! or build them by hand.
+//!
+//! First names come from the Social Security Administration's national baby
+//! names data (every name given to at least five babies of a sex in a year,
+//! since 1880). Last names come from the Census Bureau's 2010 surnames file.
+//! Both are zip files.
+//!
+//! The files are written to the data directory (see `dirs`) by default, where
+//! `peoplegen` finds them automatically, if the names files aren't specified
+//! on the command line or in the environment.
+
+use crate::dirs::{data_dir, find_file};
+use crate::path::path_str;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Where the SSA national baby names data lives.
+pub const SSA_NAMES_URL: &str = "https://www.ssa.gov/oact/babynames/names.zip";
+
+/// Where the Census Bureau 2010 surnames data lives.
+pub const CENSUS_SURNAMES_URL: &str =
+    "https://www2.census.gov/topics/genealogy/2010surnames/names.zip";
+
+// The names of the files written.
+pub const MALE_FIRST_NAMES_FILE: &str = "male_first_names.txt";
+pub const FEMALE_FIRST_NAMES_FILE: &str = "female_first_names.txt";
+pub const LAST_NAMES_FILE: &str = "last_names.txt";
+
+// The SSA data has over 100,000 distinct names, most of them very rare. A
+// first name has to have been given to at least this many babies (of a
+// sex), over all the years, to make the cut.
+const MIN_BIRTHS: u64 = 1000;
+
+// The Census Bureau lumps the rarest surnames together under this name.
+const ALL_OTHER_NAMES: &str = "ALL OTHER NAMES";
+
+// The downloads are about 7 MB and 10 MB; anything much bigger is suspect.
+const MAX_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
+/**
+ * Get the default directory for names files, the data directory.
+ *
+ * # Returns
+ *
+ * The directory, or `None` if the home directory isn't known.
+ */
+pub fn default_names_dir() -> Option<PathBuf> {
+    data_dir()
+}
+
+/**
+ * Get the default path of a names file: the file in the data directory, if
+ * it exists.
+ *
+ * # Arguments
+ *
+ * - `name`: The file name, e.g., `MALE_FIRST_NAMES_FILE`
+ *
+ * # Returns
+ *
+ * The path, or `None` if there's no such file.
+ */
+pub fn default_names_file(name: &str) -> Option<PathBuf> {
+    find_file(default_names_dir(), name)
+}
+
+/**
+ * Download the name data and write the names files, unless they're already
+ * there.
+ *
+ * # Arguments
+ *
+ * - `dest`: The directory to write the names files to. It's created, if
+ *   necessary.
+ * - `force`: Whether to download the data even if the names files exist
+ *
+ * # Returns
+ *
+ * - `Ok(files)`: The paths of the names files, with the number of names in
+ *   each, or `None` if the file was already there.
+ * - `Err(msg)`: Something failed, and `msg` explains the error.
+ */
+pub fn fetch_names(dest: &Path, force: bool) -> Result<Vec<(PathBuf, Option<usize>)>, String> {
+    let paths: Vec<PathBuf> = [MALE_FIRST_NAMES_FILE, FEMALE_FIRST_NAMES_FILE, LAST_NAMES_FILE]
+        .iter()
+        .map(|name| dest.join(name))
+        .collect();
+
+    if !force && paths.iter().all(|p| p.is_file()) {
+        return Ok(paths.into_iter().map(|p| (p, None)).collect());
+    }
+
+    fs::create_dir_all(dest)
+        .map_err(|e| format!("Can't create \"{}\": {}", path_str(dest), e))?;
+
+    println!("Downloading {} ...", SSA_NAMES_URL);
+    let (male, female) = first_names_from_ssa(&download(SSA_NAMES_URL)?)?;
+    println!("Downloading {} ...", CENSUS_SURNAMES_URL);
+    let last = last_names_from_census(&download(CENSUS_SURNAMES_URL)?)?;
+
+    paths
+        .into_iter()
+        .zip([male, female, last])
+        .map(|(path, names)| {
+            let mut contents = names.join("\n");
+            contents.push('\n');
+            fs::write(&path, contents)
+                .map_err(|e| format!("Can't write to \"{}\": {}", path_str(&path), e))?;
+            Ok((path, Some(names.len())))
+        })
+        .collect()
+}
+
+/**
+ * Extract the male and female first names from the SSA national baby names
+ * zip file, which holds one `yobYYYY.txt` file per year, with lines of the
+ * form `Mary,F,7065`.
+ *
+ * # Arguments
+ *
+ * - `zip_bytes`: The zip file
+ *
+ * # Returns
+ *
+ * - `Ok((male, female))`: The names, sorted
+ * - `Err(msg)`: The zip file isn't what was expected; `msg` explains why.
+ */
+pub fn first_names_from_ssa(zip_bytes: &[u8]) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut archive = open_zip(zip_bytes)?;
+    let mut births: HashMap<(String, String), u64> = HashMap::new();
+    let mut years = 0;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| format!("Bad SSA zip file: {e}"))?;
+        let name = file.name().to_string();
+        if !(name.starts_with("yob") && name.ends_with(".txt")) {
+            continue;
+        }
+
+        let mut text = String::new();
+        file.read_to_string(&mut text).map_err(|e| format!("Bad SSA file {name}: {e}"))?;
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let bad = || format!("Bad line in SSA file {}: \"{}\"", name, line);
+            let mut parts = line.trim().split(',');
+            let (first, sex, count) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(first), Some(sex), Some(count)) => (first, sex, count),
+                _ => return Err(bad()),
+            };
+            let count = count.parse::<u64>().map_err(|_| bad())?;
+            *births.entry((first.to_string(), sex.to_string())).or_insert(0) += count;
+        }
+        years += 1;
+    }
+
+    if years == 0 {
+        return Err(String::from("No yobYYYY.txt files in the SSA zip file."));
+    }
+
+    let names_for = |wanted: &str| -> Vec<String> {
+        births
+            .iter()
+            .filter(|((_, sex), count)| sex == wanted && **count >= MIN_BIRTHS)
+            .map(|((first, _), _)| first.clone())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect()
+    };
+
+    Ok((names_for("M"), names_for("F")))
+}
+
+/**
+ * Extract the last names from the Census Bureau 2010 surnames zip file, whose
+ * CSV file has the (upper case) surname in the first column.
+ *
+ * # Arguments
+ *
+ * - `zip_bytes`: The zip file
+ *
+ * # Returns
+ *
+ * - `Ok(names)`: The names, capitalized, and sorted
+ * - `Err(msg)`: The zip file isn't what was expected; `msg` explains why.
+ */
+pub fn last_names_from_census(zip_bytes: &[u8]) -> Result<Vec<String>, String> {
+    let mut archive = open_zip(zip_bytes)?;
+    let csv_name = archive
+        .file_names()
+        .find(|n| n.to_lowercase().ends_with(".csv"))
+        .map(|n| n.to_string())
+        .ok_or_else(|| String::from("No CSV file in the Census zip file."))?;
+    let file = archive.by_name(&csv_name).map_err(|e| format!("Bad Census zip file: {e}"))?;
+    let mut reader = csv::Reader::from_reader(file);
+    let mut names: BTreeSet<String> = BTreeSet::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Bad Census file {csv_name}: {e}"))?;
+        match record.get(0) {
+            Some(name) if !name.is_empty() && name != ALL_OTHER_NAMES => {
+                names.insert(capitalize(name));
+            },
+            _ => (),
+        }
+    }
+
+    Ok(names.into_iter().collect())
+}
+
+/// Download a file, returning its contents.
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    // ureq's errors already include the URL.
+    let response = ureq::get(url).call().map_err(|e| format!("Can't download {e}"))?;
+    let mut buf: Vec<u8> = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES)
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Can't download {url}: {e}"))?;
+    Ok(buf)
+}
+
+fn open_zip(zip_bytes: &[u8]) -> Result<ZipArchive<Cursor<&[u8]>>, String> {
+    ZipArchive::new(Cursor::new(zip_bytes)).map_err(|e| format!("Bad zip file: {e}"))
+}
+
+/// Capitalize an upper case name: "SMITH" becomes "Smith", and "SMITH-JONES"
+/// becomes "Smith-Jones".
+fn capitalize(name: &str) -> String {
+    let mut after_letter = false;
+
+    name.chars()
+        .map(|c| {
+            let c = if after_letter { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() };
+            after_letter = c.is_alphabetic();
+            c
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fetch::*;
+    use std::io::Write;
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    fn zip_of(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut w = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            w.start_file(*name, SimpleFileOptions::default()).unwrap();
+            w.write_all(contents.as_bytes()).unwrap();
+        }
+        w.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn ssa_names() {
+        let zip = zip_of(&[
+            ("NationalReadMe.pdf", "not names"),
+            ("yob1950.txt", "Mary,F,900\nJames,M,2000\nMary,M,10\n"),
+            ("yob1951.txt", "Mary,F,900\nZelda,F,5\n"),
+        ]);
+        let (male, female) = first_names_from_ssa(&zip).unwrap();
+        assert_eq!(male, vec![String::from("James")]);
+        assert_eq!(female, vec![String::from("Mary")]);
+
+        assert!(first_names_from_ssa(&zip_of(&[("yob1950.txt", "Mary,F")])).is_err());
+        assert!(first_names_from_ssa(&zip_of(&[("x.txt", "")])).is_err());
+    }
+
+    #[test]
+    fn census_names() {
+        let zip = zip_of(&[(
+            "Names_2010Census.csv",
+            "name,rank,count\nSMITH,1,2442977\nSMITH-JONES,2,100\nALL OTHER NAMES,0,29312001\n",
+        )]);
+        assert_eq!(
+            last_names_from_census(&zip).unwrap(),
+            vec![String::from("Smith"), String::from("Smith-Jones")]
+        );
+    }
+
+    #[test]
+    fn capitalization() {
+        assert_eq!(capitalize("MCDONALD"), "Mcdonald");
+        assert_eq!(capitalize("O'NEILL"), "O'Neill");
+    }
+}
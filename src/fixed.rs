@@ -0,0 +1,229 @@
+//! Fixed-width (mainframe-style) text output: one record per line, with each
+//! field padded to the width the layout gives it, and no separators or
+//! header, so the file can be read with a COBOL copybook.
+//!
+//! A layout is a list of `field:width` entries, given on the command line
+//! (`--layout first_name:20,last_name:25,birth_date:10`) or in a layout file,
+//! with one entry per line (`#` starts a comment). `filler:width` inserts
+//! blanks. Text is left-justified and padded with spaces, and cut off if it's
+//! too long; numbers (IDs and salaries) are right-justified and padded with
+//! zeros, like `PIC 9` fields, and a number that doesn't fit is an error.
+//! Widths count characters.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The layout field name that inserts blanks.
+pub const FILLER: &str = "filler";
+
+/// A field in a layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutField {
+    /// The field name, or `FILLER`
+    pub field: String,
+    pub width: usize,
+}
+
+/// How a field is justified.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Justify {
+    /// Left-justified and padded with spaces, like `PIC X`
+    Text,
+    /// Right-justified and padded with zeros, like `PIC 9`
+    Number,
+}
+
+/**
+ * Parse a layout from the command line.
+ *
+ * # Arguments
+ *
+ * - `s`: The layout, as comma-separated `field:width` entries
+ * - `fields`: The valid field names (besides `FILLER`)
+ *
+ * # Returns
+ *
+ * - `Ok(layout)`: The layout
+ * - `Err(msg)`: The layout is invalid; `msg` explains why.
+ */
+pub fn parse_layout(s: &str, fields: &[&str]) -> Result<Vec<LayoutField>, String> {
+    let layout = s
+        .split(',')
+        .map(|entry| parse_layout_field(entry.trim(), fields))
+        .collect::<Result<Vec<LayoutField>, String>>()?;
+    check_layout(layout)
+}
+
+/**
+ * Read a layout file, which has one `field:width` entry per line. Blank lines
+ * and lines starting with `#` are ignored.
+ *
+ * # Arguments
+ *
+ * - `path`: The layout file
+ * - `fields`: The valid field names (besides `FILLER`)
+ *
+ * # Returns
+ *
+ * - `Ok(layout)`: The layout
+ * - `Err(msg)`: The file can't be read or is invalid; `msg` explains why.
+ */
+pub fn read_layout_file(path: &Path, fields: &[&str]) -> Result<Vec<LayoutField>, String> {
+    let err = |msg: String| format!("\"{}\": {}", path.display(), msg);
+    let text = fs::read_to_string(path).map_err(|e| err(e.to_string()))?;
+    parse_layout_file(&text, fields).map_err(err)
+}
+
+/**
+ * Parse the contents of a layout file. See `read_layout_file()`.
+ *
+ * # Arguments
+ *
+ * - `text`: The contents of the file
+ * - `fields`: The valid field names (besides `FILLER`)
+ *
+ * # Returns
+ *
+ * - `Ok(layout)`: The layout
+ * - `Err(msg)`: The layout is invalid; `msg` explains why.
+ */
+pub fn parse_layout_file(text: &str, fields: &[&str]) -> Result<Vec<LayoutField>, String> {
+    let layout = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| parse_layout_field(line, fields).map_err(|e| format!("Line {n}: {e}")))
+        .collect::<Result<Vec<LayoutField>, String>>()?;
+    check_layout(layout)
+}
+
+fn parse_layout_field(entry: &str, fields: &[&str]) -> Result<LayoutField, String> {
+    let (field, width) = entry.split_once(':').ok_or_else(|| {
+        format!("Bad layout entry \"{entry}\": expected FIELD:WIDTH")
+    })?;
+
+    if field != FILLER && !fields.contains(&field) {
+        return Err(format!(
+            "Unknown field \"{}\" in layout. Valid fields: {}, {}",
+            field, fields.join(", "), FILLER
+        ));
+    }
+
+    match width.parse::<usize>() {
+        Ok(width) if width > 0 => Ok(LayoutField { field: field.to_string(), width }),
+        _ => Err(format!("Bad width \"{width}\" for layout field \"{field}\".")),
+    }
+}
+
+fn check_layout(layout: Vec<LayoutField>) -> Result<Vec<LayoutField>, String> {
+    if layout.iter().all(|f| f.field == FILLER) {
+        Err(String::from("The layout has no fields."))
+    }
+    else {
+        Ok(layout)
+    }
+}
+
+/**
+ * Writes fixed-width records.
+ */
+pub struct FixedWidthWriter<W: Write> {
+    w: W,
+    columns: Vec<(usize, Justify)>,
+    line: String,
+}
+
+impl<W: Write> FixedWidthWriter<W> {
+    /**
+     * Create a new writer.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the records
+     * - `columns`: The width and justification of each column, in order
+     */
+    pub fn new(w: W, columns: Vec<(usize, Justify)>) -> FixedWidthWriter<W> {
+        FixedWidthWriter { w, columns, line: String::new() }
+    }
+
+    /**
+     * Write a record. The values must be in column order.
+     */
+    pub fn write_record(&mut self, values: &[String]) -> io::Result<()> {
+        self.line.clear();
+
+        for (v, (width, justify)) in values.iter().zip(&self.columns) {
+            let len = v.chars().count();
+            match justify {
+                Justify::Text => {
+                    self.line.extend(v.chars().take(*width));
+                    self.line.extend(std::iter::repeat_n(' ', width.saturating_sub(len)));
+                },
+                Justify::Number if len > *width => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("\"{v}\" doesn't fit in {width} digit(s)")
+                    ));
+                },
+                Justify::Number => {
+                    self.line.extend(std::iter::repeat_n('0', width - len));
+                    self.line.push_str(v);
+                },
+            }
+        }
+
+        self.line.push('\n');
+        self.w.write_all(self.line.as_bytes())
+    }
+
+    /**
+     * Flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The flush failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixed::*;
+
+    const FIELDS: [&str; 3] = ["id", "first_name", "last_name"];
+
+    #[test]
+    fn layouts() {
+        assert_eq!(parse_layout("id:6, filler:2,last_name:10", &FIELDS).unwrap(), vec![
+            LayoutField { field: String::from("id"), width: 6 },
+            LayoutField { field: String::from(FILLER), width: 2 },
+            LayoutField { field: String::from("last_name"), width: 10 },
+        ]);
+
+        assert!(parse_layout("id", &FIELDS).unwrap_err().contains("FIELD:WIDTH"));
+        assert!(parse_layout("ssn:11", &FIELDS).unwrap_err().contains("Unknown field"));
+        assert!(parse_layout("id:0", &FIELDS).unwrap_err().contains("Bad width"));
+        assert!(parse_layout("filler:3", &FIELDS).unwrap_err().contains("no fields"));
+    }
+
+    #[test]
+    fn records() {
+        let columns = vec![(4, Justify::Number), (2, Justify::Text), (6, Justify::Text)];
+        let mut w = FixedWidthWriter::new(Vec::new(), columns);
+        let row = |a: &str, b: &str, c: &str| [a.to_string(), b.to_string(), c.to_string()];
+
+        w.write_record(&row("42", "", "Moe")).unwrap();
+        w.write_record(&row("7", "", "Fitzgerald")).unwrap();
+        assert!(w.write_record(&row("12345", "", "")).is_err());
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "0042  Moe   \n0007  Fitzge\n"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-field value formatters, which control how a field is rendered in the
+//! output, independently of how it's represented internally.
+//!
+//! Formatters are specified on the command line as `field=formatter`, e.g.,
+//! `--format-field ssn=digits-only` or `--format-field birth_date=date:%m/%d/%Y`.
+
+use chrono::NaiveDate;
+
+/// A formatter that can be applied to a field value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Formatter {
+    /// Remove everything but the digits (e.g., "900-01-0001" -> "900010001").
+    DigitsOnly,
+    /// Convert to upper case.
+    Upper,
+    /// Convert to lower case.
+    Lower,
+    /// Reformat an ISO 8601 (YYYY-MM-DD) date with a `strftime`-style
+    /// format string.
+    Date(String),
+}
+
+/// The names of the available formatters, for help and error messages.
+pub const FORMATTER_NAMES: [&str; 4] = ["digits-only", "upper", "lower", "date:<format>"];
+
+/// A formatter bound to a field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFormat {
+    pub field: String,
+    pub formatter: Formatter,
+}
+
+impl Formatter {
+    /**
+     * Parse a formatter name.
+     *
+     * # Arguments
+     *
+     * - `s`: The formatter name, e.g., "digits-only" or "date:%d/%m/%Y"
+     *
+     * # Returns
+     *
+     * - `Ok(formatter)`: The parsed formatter
+     * - `Err(msg)`: Not a valid formatter; `msg` explains why.
+     */
+    pub fn parse(s: &str) -> Result<Formatter, String> {
+        match s {
+            "digits-only" => Ok(Formatter::DigitsOnly),
+            "upper" => Ok(Formatter::Upper),
+            "lower" => Ok(Formatter::Lower),
+            _ if s.starts_with("date:") && s.len() > 5 => {
+                let pattern = &s[5..];
+                // chrono doesn't validate a format string until it's used,
+                // so try it out on a known date.
+                let d = NaiveDate::from_ymd(2000, 1, 1);
+                let mut out = String::new();
+                std::fmt::write(&mut out, format_args!("{}", d.format(pattern)))
+                    .map_err(|_| format!("Bad date format: \"{pattern}\""))?;
+                Ok(Formatter::Date(pattern.to_string()))
+            },
+            _ => Err(format!(
+                "Unknown formatter \"{}\". Valid formatters: {}",
+                s, FORMATTER_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /**
+     * Get the formatter's name, as `parse()` takes it, e.g., "date:%d/%m/%Y".
+     */
+    pub fn name(&self) -> String {
+        match self {
+            Formatter::DigitsOnly => String::from("digits-only"),
+            Formatter::Upper => String::from("upper"),
+            Formatter::Lower => String::from("lower"),
+            Formatter::Date(pattern) => format!("date:{pattern}"),
+        }
+    }
+
+    /**
+     * Apply the formatter to a value.
+     *
+     * # Arguments
+     *
+     * - `value`: The value to format
+     *
+     * # Returns
+     *
+     * The formatted value. Values a formatter doesn't apply to (e.g., a
+     * date formatter on something that isn't a date) are returned
+     * unchanged.
+     */
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Formatter::DigitsOnly => {
+                value.chars().filter(|c| c.is_ascii_digit()).collect()
+            },
+            Formatter::Upper => value.to_uppercase(),
+            Formatter::Lower => value.to_lowercase(),
+            Formatter::Date(pattern) => {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|d| d.format(pattern).to_string())
+                    .unwrap_or_else(|_| value.to_string())
+            },
+        }
+    }
+}
+
+impl FieldFormat {
+    /**
+     * Parse a `field=formatter` specification.
+     *
+     * # Arguments
+     *
+     * - `s`: The specification
+     * - `fields`: The valid field names
+     *
+     * # Returns
+     *
+     * - `Ok(field_format)`: The parsed specification
+     * - `Err(msg)`: The specification is invalid; `msg` explains why.
+     */
+    pub fn parse(s: &str, fields: &[&str]) -> Result<FieldFormat, String> {
+        let (field, formatter) = s.split_once('=').ok_or_else(|| {
+            format!("Bad field format \"{s}\": expected FIELD=FORMATTER")
+        })?;
+
+        if !fields.contains(&field) {
+            return Err(format!(
+                "Unknown field \"{}\" in \"{}\". Valid fields: {}",
+                field, s, fields.join(", ")
+            ));
+        }
+
+        Ok(FieldFormat {
+            field: field.to_string(),
+            formatter: Formatter::parse(formatter)?,
+        })
+    }
+}
+
+/**
+ * Apply all the formatters for a field to a value, in order.
+ *
+ * # Arguments
+ *
+ * - `formats`: The field formatters
+ * - `field`: The field name
+ * - `value`: The value to format
+ *
+ * # Returns
+ *
+ * The formatted value.
+ */
+pub fn format_field(formats: &[FieldFormat], field: &str, value: String) -> String {
+    formats
+        .iter()
+        .filter(|f| f.field == field)
+        .fold(value, |v, f| f.formatter.apply(&v))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::*;
+
+    #[test]
+    fn formatters() {
+        assert_eq!(Formatter::DigitsOnly.apply("900-01-0001"), "900010001");
+        assert_eq!(Formatter::Upper.apply("Moe"), "MOE");
+        assert_eq!(Formatter::Lower.apply("Moe"), "moe");
+        assert_eq!(Formatter::Date(String::from("%m/%d/%Y")).apply("1897-06-19"),
+                   "06/19/1897");
+        assert_eq!(Formatter::Date(String::from("%m/%d/%Y")).apply("Moe"), "Moe");
+    }
+
+    #[test]
+    fn parse_field_formats() {
+        let fields = ["ssn", "birth_date"];
+        assert_eq!(
+            FieldFormat::parse("ssn=digits-only", &fields),
+            Ok(FieldFormat {
+                field: String::from("ssn"),
+                formatter: Formatter::DigitsOnly
+            })
+        );
+        assert!(FieldFormat::parse("birth_date=date:%Y%m%d", &fields).is_ok());
+        assert!(FieldFormat::parse("ssn", &fields).is_err());
+        assert!(FieldFormat::parse("nope=upper", &fields).is_err());
+        assert!(FieldFormat::parse("ssn=sideways", &fields).is_err());
+
+        for name in ["digits-only", "upper", "lower", "date:%d/%m/%Y"] {
+            assert_eq!(Formatter::parse(name).unwrap().name(), name);
+        }
+    }
+
+    #[test]
+    fn formatters_in_order() {
+        let fields = ["last_name"];
+        let formats = vec![
+            FieldFormat::parse("last_name=upper", &fields).unwrap(),
+            FieldFormat::parse("last_name=lower", &fields).unwrap(),
+        ];
+        assert_eq!(format_field(&formats, "last_name", String::from("Howard")),
+                   "howard");
+        assert_eq!(format_field(&formats, "first_name", String::from("Moe")),
+                   "Moe");
+    }
+}
@@ -0,0 +1,114 @@
+//! Parsing gender configuration files, which define the gender categories
+//! `peoplegen` generates. See the `peoplegen` README for the format: a CSV
+//! file with a header line and three columns, the label written to the
+//! gender field, the percentage of people in the category, and the path to
+//! the category's first names file. Lines starting with `#` are ignored.
+
+use csv::ReaderBuilder;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// A line from a gender configuration file, before the names are read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenderConfigEntry {
+    pub label: String,
+    pub percent: u32,
+    pub names_file: PathBuf,
+}
+
+/**
+ * Parse the contents of a gender configuration file.
+ *
+ * # Arguments
+ *
+ * - `r`: Where to read the configuration from
+ *
+ * # Returns
+ *
+ * - `Ok(entries)`: The categories, in file order. Names file paths are as
+ *   they appear in the file.
+ * - `Err(msg)`: The configuration is invalid; `msg` explains why.
+ */
+pub fn parse_gender_config<R: Read>(r: R) -> Result<Vec<GenderConfigEntry>, String> {
+    let mut reader = ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(r);
+    let mut entries: Vec<GenderConfigEntry> = Vec::new();
+    let mut labels: HashSet<String> = HashSet::new();
+
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("{}", e))?;
+        let line = i + 2;
+        if record.len() != 3 {
+            return Err(format!(
+                "Line {}: expected 3 columns (label, percent, names), not {}.",
+                line, record.len()
+            ));
+        }
+
+        let label = record[0].to_string();
+        if label.is_empty() {
+            return Err(format!("Line {line}: empty label."));
+        }
+        if !labels.insert(label.clone()) {
+            return Err(format!("Line {}: duplicate label \"{}\".", line, label));
+        }
+
+        let percent = record[1].parse::<u32>()
+            .map_err(|_| format!("Line {}: bad percentage \"{}\".", line, &record[1]))?;
+
+        entries.push(GenderConfigEntry {
+            label,
+            percent,
+            names_file: PathBuf::from(&record[2]),
+        });
+    }
+
+    let total: u64 = entries.iter().map(|e| e.percent as u64).sum();
+    if entries.is_empty() {
+        Err(String::from("No gender categories defined."))
+    }
+    else if total != 100 {
+        Err(format!("Gender percentages must add up to 100, not {total}."))
+    }
+    else {
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gender_config::*;
+
+    #[test]
+    fn parse_config() {
+        let config = "label,percent,names\n\
+                      # A comment\n\
+                      woman, 45, women.txt\n\
+                      man,45,men.txt\n\
+                      nonbinary,10,/data/all.txt\n";
+        let entries = parse_gender_config(config.as_bytes()).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], GenderConfigEntry {
+            label: String::from("woman"),
+            percent: 45,
+            names_file: PathBuf::from("women.txt"),
+        });
+        assert_eq!(entries[2].names_file, PathBuf::from("/data/all.txt"));
+    }
+
+    #[test]
+    fn bad_configs() {
+        let bad = |s: &str| parse_gender_config(s.as_bytes()).unwrap_err();
+
+        assert!(bad("label,percent,names\n").contains("No gender"));
+        assert!(bad("label,percent,names\na,50,x\nb,40,y\n").contains("not 90"));
+        assert!(bad("label,percent,names\na,50,x\na,50,y\n").contains("duplicate"));
+        assert!(bad("label,percent,names\na,lots,x\n").contains("bad percentage"));
+        assert!(bad("label,percent,names\n,100,x\n").contains("empty label"));
+        assert!(bad("label,percent,names\na,4294967295,x\nb,1,y\n").contains("not 4294967296"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Gender categories. By default, `peoplegen` generates the two categories in
+//! the 2010 Census Bureau data, male ("M") and female ("F"), with the
+//! percentages from `--male-pct` and `--female-pct`. `--other-pct` adds a
+//! third, nonbinary ("X"), whose first names come from `--other-names`, or,
+//! without it, from both the male and female names. `--gender-config`
+//! replaces them all with whatever categories a gender configuration file
+//! defines.
+//!
+//! `--gender-values` changes what's written for male, female and nonbinary
+//! people, for systems that expect "Male" and "Female", say, or "0" and "1".
+//!
+//! A gender configuration file is a CSV file with a header line and three
+//! columns: the label written to the gender field, the percentage of people
+//! in the category, and the path to the category's first names file. Relative
+//! paths are relative to the directory containing the configuration file.
+//! Lines starting with `#` are ignored. For example:
+//!
+//! ```text
+//! label,percent,names
+//! # Labels can be anything, as long as they're unique.
+//! woman,40,female_first_names.txt
+//! man,40,male_first_names.txt
+//! nonbinary,10,all_first_names.txt
+//! other,5,all_first_names.txt
+//! unknown,5,all_first_names.txt
+//! ```
+
+use crate::args::Arguments;
+use crate::error::PeoplegenError;
+use crate::path::path_str;
+use crate::people::{read_names_file, Gender};
+use crate::gender_config::parse_gender_config;
+use crate::weighted::WeightedValues;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// The name of the gender configuration file that's used, if it's in the
+/// config directory (see `dirs`) and `--gender-config` isn't given.
+pub const GENDER_CONFIG_FILE: &str = "genders.csv";
+
+/// A gender category: its label, how many people get it, and where their
+/// first names come from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenderCategory {
+    pub gender: Gender,
+    pub percent: u32,
+    pub first_names: WeightedValues,
+}
+
+/// The values written for each gender, with `--gender-values`, in place of
+/// "M", "F" and "X".
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenderValues {
+    pub male: String,
+    pub female: String,
+    /// The value for nonbinary people, if there's a third value
+    pub nonbinary: Option<String>,
+}
+
+impl GenderValues {
+    /**
+     * Parse the values, as `--gender-values` takes them: the male and female
+     * values, and, optionally, the nonbinary value, separated by commas.
+     *
+     * # Arguments
+     *
+     * - `s`: The values, e.g., "Male,Female" or "0,1,2"
+     *
+     * # Returns
+     *
+     * - `Ok(values)`: The values
+     * - `Err(msg)`: They aren't valid; `msg` explains why.
+     */
+    pub fn parse(s: &str) -> Result<GenderValues, String> {
+        let values: Vec<&str> = s.split(',').map(str::trim).collect();
+        let (male, female, nonbinary) = match values[..] {
+            [male, female] => (male, female, None),
+            [male, female, nonbinary] => (male, female, Some(nonbinary)),
+            _ => return Err(format!(
+                "\"{s}\" isn't two or three values: male, female and, optionally, nonbinary, \
+                 separated by commas, e.g., \"Male,Female\"."
+            )),
+        };
+
+        if values.iter().any(|v| v.is_empty()) {
+            Err(format!("\"{s}\" has an empty value."))
+        }
+        // Otherwise, nobody could tell the genders apart.
+        else if values.iter().enumerate().any(|(i, v)| values[..i].contains(v)) {
+            Err(format!("\"{s}\" has the same value twice."))
+        }
+        else {
+            Ok(GenderValues {
+                male: male.to_string(),
+                female: female.to_string(),
+                nonbinary: nonbinary.map(str::to_string),
+            })
+        }
+    }
+
+    /**
+     * Get the value to write for a gender. Nonbinary people without a value
+     * of their own, and custom categories, get their labels.
+     */
+    pub fn value<'a>(&'a self, gender: &'a Gender) -> &'a str {
+        match gender {
+            Gender::Male => &self.male,
+            Gender::Female => &self.female,
+            Gender::NonBinary => self.nonbinary.as_deref().unwrap_or(gender.to_str()),
+            Gender::Custom(label) => label,
+        }
+    }
+
+    /**
+     * Get the value to write for a gender field's label, which may be one
+     * that `--rules` put there. Anything that isn't one of the genders'
+     * labels is written as it is.
+     */
+    pub fn relabel(&self, label: String) -> String {
+        match Gender::from_label(&label) {
+            Gender::Custom(_) => label,
+            gender => self.value(&gender).to_string(),
+        }
+    }
+}
+
+/**
+ * Load the gender categories for a run: those in the `--gender-config` file,
+ * if there is one, or the default male and female categories, otherwise.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * - `Ok(categories)`: The categories, with their first names
+ * - `Err(e)`: A file couldn't be read or is invalid.
+ */
+pub fn gender_categories(args: &Arguments) -> Result<Vec<GenderCategory>, PeoplegenError> {
+    match &args.gender_config {
+        Some(path) => read_gender_config(path, args.strict),
+        None => {
+            let mut categories = vec![
+                GenderCategory {
+                    gender: Gender::Male,
+                    percent: args.male_percent,
+                    first_names: read_names_file(&args.male_first_names_file, args.strict)?,
+                },
+                GenderCategory {
+                    gender: Gender::Female,
+                    percent: args.female_percent,
+                    first_names: read_names_file(&args.female_first_names_file, args.strict)?,
+                },
+            ];
+            // Without other people, there's no third category, so the
+            // people are just what they were before there could be.
+            if args.other_percent > 0 {
+                let first_names = match &args.other_first_names_file {
+                    Some(path) => read_names_file(path, args.strict)?,
+                    None => all_first_names(&categories).map_err(PeoplegenError::Validation)?,
+                };
+                categories.push(GenderCategory { gender: Gender::NonBinary, percent: args.other_percent, first_names });
+            }
+            Ok(categories)
+        },
+    }
+}
+
+/**
+ * Get the first names of all the categories, together. A name in more than
+ * one category gets its largest weight.
+ *
+ * # Arguments
+ *
+ * - `categories`: The gender categories
+ *
+ * # Returns
+ *
+ * - `Ok(names)`: The names
+ * - `Err(msg)`: There are no names.
+ */
+pub fn all_first_names(categories: &[GenderCategory]) -> Result<WeightedValues, String> {
+    let mut union: Vec<(String, f64)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for names in categories.iter().map(|c| &c.first_names) {
+        for (i, name) in names.values().iter().enumerate() {
+            match index.get(&**name) {
+                Some(&j) => union[j].1 = union[j].1.max(names.weight(i)),
+                None => {
+                    index.insert(name, union.len());
+                    union.push((name.to_string(), names.weight(i)));
+                },
+            }
+        }
+    }
+    WeightedValues::new(union)
+}
+
+/**
+ * Read a gender configuration file, and the first names files it refers to.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the configuration file
+ * - `strict`: Whether a names file listing a name twice is an error
+ *
+ * # Returns
+ *
+ * - `Ok(categories)`: The categories, with their first names
+ * - `Err(e)`: A file couldn't be read or is invalid.
+ */
+pub fn read_gender_config(path: &Path, strict: bool) -> Result<Vec<GenderCategory>, PeoplegenError> {
+    let file = File::open(path).map_err(|source| PeoplegenError::Io { path: path.to_path_buf(), source })?;
+    let entries = parse_gender_config(file)
+        .map_err(|e| PeoplegenError::Parse(format!("\"{}\": {}", path_str(path), e)))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let names_file = dir.join(&entry.names_file);
+            let first_names = read_names_file(&names_file, strict)?;
+
+            Ok(GenderCategory {
+                gender: Gender::Custom(entry.label),
+                percent: entry.percent,
+                first_names,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genders::*;
+
+    #[test]
+    fn gender_values() {
+        let values = GenderValues::parse("Male, Female").unwrap();
+        assert_eq!(values.value(&Gender::Male), "Male");
+        assert_eq!(values.value(&Gender::Female), "Female");
+        assert_eq!(values.value(&Gender::NonBinary), "X");
+        assert_eq!(values.value(&Gender::Custom(String::from("unknown"))), "unknown");
+        assert_eq!(values.relabel(String::from("F")), "Female");
+        assert_eq!(values.relabel(String::from("Shemp")), "Shemp");
+
+        let values = GenderValues::parse("0,1,2").unwrap();
+        assert_eq!(values.value(&Gender::NonBinary), "2");
+        assert_eq!(values.relabel(String::from("X")), "2");
+        assert_eq!(values.relabel(String::new()), "");
+    }
+
+    #[test]
+    fn bad_gender_values() {
+        for s in ["Male", "M,F,X,U", "M,,X", "M,F,", "0,0", "M,F,M"] {
+            assert!(GenderValues::parse(s).is_err(), "{s}");
+        }
+        assert!(GenderValues::parse("Male").unwrap_err().contains("e.g., \"Male,Female\""));
+    }
+}
@@ -0,0 +1,121 @@
+//! Hash companion fields. `--hash-field ssn=sha256` adds an `ssn_hash` field
+//! containing the hex-encoded hash of each person's (formatted) SSN, which is
+//! useful for testing pseudonymized-join pipelines, where raw values and
+//! hashes have to correspond.
+
+use sha2::{Digest, Sha256, Sha512};
+
+/// The supported hash algorithms.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+/// The names of the supported algorithms, for help and error messages.
+pub const HASH_ALGORITHM_NAMES: [&str; 2] = ["sha256", "sha512"];
+
+/// The suffix added to a field name to name its hash field.
+pub const HASH_FIELD_SUFFIX: &str = "_hash";
+
+/// A hash companion for a field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldHash {
+    pub field: String,
+    pub algorithm: HashAlgorithm,
+}
+
+impl HashAlgorithm {
+    /**
+     * Get the algorithm's name, as in a `--hash-field` specification.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /**
+     * Hash a value, returning the hash as lower case hex.
+     */
+    pub fn hash(&self, value: &str) -> String {
+        let digest = match self {
+            HashAlgorithm::Sha256 => Sha256::digest(value.as_bytes()).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(value.as_bytes()).to_vec(),
+        };
+
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl FieldHash {
+    /**
+     * Parse a `field=algorithm` specification.
+     *
+     * # Arguments
+     *
+     * - `s`: The specification
+     * - `fields`: The valid field names
+     *
+     * # Returns
+     *
+     * - `Ok(field_hash)`: The parsed specification
+     * - `Err(msg)`: The specification is invalid; `msg` explains why.
+     */
+    pub fn parse(s: &str, fields: &[&str]) -> Result<FieldHash, String> {
+        let (field, algorithm) = s.split_once('=').ok_or_else(|| {
+            format!("Bad field hash \"{s}\": expected FIELD=ALGORITHM")
+        })?;
+
+        if !fields.contains(&field) {
+            return Err(format!(
+                "Unknown field \"{}\" in \"{}\". Valid fields: {}",
+                field, s, fields.join(", ")
+            ));
+        }
+
+        let algorithm = match algorithm {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            _ => Err(format!(
+                "Unknown hash algorithm \"{}\". Valid algorithms: {}",
+                algorithm, HASH_ALGORITHM_NAMES.join(", ")
+            )),
+        }?;
+
+        Ok(FieldHash { field: field.to_string(), algorithm })
+    }
+
+    /**
+     * The name of the hash field, e.g., `ssn_hash`.
+     */
+    pub fn key(&self) -> String {
+        format!("{}{}", self.field, HASH_FIELD_SUFFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::*;
+
+    #[test]
+    fn hashes() {
+        assert_eq!(
+            HashAlgorithm::Sha256.hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(HashAlgorithm::Sha512.hash("abc").len(), 128);
+    }
+
+    #[test]
+    fn parse_field_hashes() {
+        let fields = ["ssn"];
+        let h = FieldHash::parse("ssn=sha256", &fields).unwrap();
+        assert_eq!(h.algorithm, HashAlgorithm::Sha256);
+        assert_eq!(h.key(), "ssn_hash");
+        assert!(FieldHash::parse("ssn=md5", &fields).is_err());
+        assert!(FieldHash::parse("nope=sha256", &fields).is_err());
+        assert!(FieldHash::parse("ssn", &fields).is_err());
+    }
+}
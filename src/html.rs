@@ -0,0 +1,166 @@
+//! HTML output: a standalone page with one table, for sharing sample data
+//! with people who'd rather not open a CSV file. The page has no external
+//! dependencies. Clicking a column header sorts the table by that column
+//! (clicking again reverses the order), with a few lines of inline
+//! JavaScript; without JavaScript, it's just a table.
+
+use std::io::{self, Write};
+
+const HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="generator" content="peoplegen">
+<title>{title}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+th { background: #f0f0f0; cursor: pointer; user-select: none; position: sticky; top: 0; }
+th[aria-sort="ascending"]::after { content: " \25B2"; }
+th[aria-sort="descending"]::after { content: " \25BC"; }
+td.number { text-align: right; }
+tbody tr:nth-child(even) { background: #fafafa; }
+</style>
+</head>
+<body>
+<table>
+"#;
+
+const TAIL: &str = r#"</tbody>
+</table>
+<script>
+document.querySelectorAll("th").forEach((th, column) => {
+  th.addEventListener("click", () => {
+    const tbody = th.closest("table").tBodies[0];
+    const ascending = th.getAttribute("aria-sort") !== "ascending";
+    const numeric = th.dataset.type === "number";
+    const value = row => row.cells[column].textContent;
+    const compare = numeric
+      ? (a, b) => Number(value(a)) - Number(value(b))
+      : (a, b) => value(a).localeCompare(value(b));
+    const rows = Array.from(tbody.rows).sort(ascending ? compare : (a, b) => compare(b, a));
+    th.parentElement.querySelectorAll("th").forEach(h => h.removeAttribute("aria-sort"));
+    th.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    tbody.append(...rows);
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+/// A table column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub header: String,
+    /// Whether the column is numeric, so it's right-aligned, and sorted by
+    /// value instead of alphabetically
+    pub numeric: bool,
+}
+
+/**
+ * Writes an HTML page with a table, one row at a time.
+ */
+pub struct HtmlWriter<W: Write> {
+    w: W,
+    numeric: Vec<bool>,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    /**
+     * Create a new writer, and write the start of the page, through the
+     * table header.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the page
+     * - `title`: The page title
+     * - `columns`: The table columns, in order
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, ready for rows
+     * - `Err(e)`: The start of the page couldn't be written.
+     */
+    pub fn new(mut w: W, title: &str, columns: &[Column]) -> io::Result<HtmlWriter<W>> {
+        w.write_all(HEAD.replace("{title}", &escape(title)).as_bytes())?;
+        w.write_all(b"<thead>\n<tr>")?;
+        for c in columns {
+            let attr = if c.numeric { " data-type=\"number\"" } else { "" };
+            write!(w, "<th{}>{}</th>", attr, escape(&c.header))?;
+        }
+        w.write_all(b"</tr>\n</thead>\n<tbody>\n")?;
+
+        Ok(HtmlWriter { w, numeric: columns.iter().map(|c| c.numeric).collect() })
+    }
+
+    /**
+     * Write a row. The values must be in column order.
+     */
+    pub fn write_row(&mut self, values: &[String]) -> io::Result<()> {
+        self.w.write_all(b"<tr>")?;
+        for (v, numeric) in values.iter().zip(&self.numeric) {
+            if *numeric {
+                write!(self.w, "<td class=\"number\">{}</td>", escape(v))?;
+            }
+            else {
+                write!(self.w, "<td>{}</td>", escape(v))?;
+            }
+        }
+        self.w.write_all(b"</tr>\n")
+    }
+
+    /**
+     * Write the end of the page, and flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The page couldn't be finished.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.w.write_all(TAIL.as_bytes())?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Escape text for HTML element content and attribute values.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html::*;
+
+    #[test]
+    fn table() {
+        let columns = [
+            Column { header: String::from("ID"), numeric: true },
+            Column { header: String::from("Last Name"), numeric: false },
+        ];
+        let mut w = HtmlWriter::new(Vec::new(), "<People>", &columns).unwrap();
+        w.write_row(&[String::from("1"), String::from("O'Brien & Sons")]).unwrap();
+        let page = String::from_utf8(w.finish().unwrap()).unwrap();
+
+        assert!(page.contains("<title>&lt;People&gt;</title>"));
+        assert!(page.contains("<tr><th data-type=\"number\">ID</th><th>Last Name</th></tr>"));
+        assert!(page.contains("<tr><td class=\"number\">1</td><td>O&#39;Brien &amp; Sons</td></tr>"));
+        assert!(page.ends_with("</html>\n"));
+    }
+}
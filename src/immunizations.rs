@@ -0,0 +1,442 @@
+//! Immunization histories, for healthcare datasets: each person gets a few
+//! immunizations, each with a vaccine code from a value set and a date
+//! between their birth and the latest date allowed, in order. The records
+//! go to a related table, as CSV, or, for a ".ndjson" or ".jsonl" file, to
+//! FHIR resources, one per line, as in FHIR bulk data: `Immunization`
+//! resources for CVX codes, and `MedicationStatement` resources for any
+//! other codes, so a value set of, say, RxNorm codes makes medication
+//! histories instead.
+//!
+//! The value set is the one built in, `DEFAULT_VALUE_SET` (common CVX
+//! vaccine codes), or a CSV file, with the columns `code` and `display` and,
+//! optionally, `system` (the code system's URI, CVX's by default) and
+//! `weight` (how likely the code is, relative to the others, 1 by default).
+//!
+//! The histories come from their own random number generator, derived from
+//! the run's seed, so turning them on or off doesn't change any of the
+//! other generated values.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+use csv::{ReaderBuilder, Writer, WriterBuilder};
+use json::JsonValue;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::path::{file_extension, path_str};
+
+/// Mixed into the run's seed to seed the histories' random number
+/// generator.
+const IMMUNIZATIONS_SEED_SALT: u64 = 0x696d_6d75_6e69_7a65;
+
+/// The CVX code system's URI.
+pub const CVX_SYSTEM: &str = "http://hl7.org/fhir/sid/cvx";
+
+/// The built-in value set: common CVX vaccine codes, as (code, display,
+/// weight).
+pub const DEFAULT_VALUE_SET: [(&str, &str, u32); 8] = [
+    ("88", "influenza, unspecified formulation", 30),
+    ("208", "COVID-19, mRNA, LNP-S, PF, 30 mcg/0.3 mL dose", 15),
+    ("115", "Tdap", 12),
+    ("08", "Hep B, adolescent or pediatric", 10),
+    ("20", "DTaP", 10),
+    ("10", "IPV", 8),
+    ("03", "MMR", 8),
+    ("21", "varicella", 7),
+];
+
+/// The default `--max-immunizations`.
+pub const DEFAULT_MAX_IMMUNIZATIONS: u32 = 5;
+
+const HEADER: [&str; 6] = ["immunization_id", "id", "system", "code", "display", "date"];
+
+/// A code in a value set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+    pub system: String,
+    pub code: String,
+    pub display: String,
+}
+
+/// The settings for immunization histories, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImmunizationOptions {
+    /// The value set, with the codes' weights
+    pub value_set: Vec<(Code, u32)>,
+    /// The most immunizations anyone has
+    pub max_per_person: u32,
+    /// The latest date an immunization can be on
+    pub max_date: NaiveDate,
+}
+
+/// One immunization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Immunization {
+    /// The index of the code in the value set
+    pub code: usize,
+    pub date: NaiveDate,
+}
+
+/**
+ * Get the built-in value set.
+ */
+pub fn default_value_set() -> Vec<(Code, u32)> {
+    DEFAULT_VALUE_SET
+        .iter()
+        .map(|(code, display, weight)| {
+            let code = Code { system: CVX_SYSTEM.to_string(), code: code.to_string(), display: display.to_string() };
+            (code, *weight)
+        })
+        .collect()
+}
+
+/**
+ * Parse a value set file.
+ *
+ * # Arguments
+ *
+ * - `r`: Where to read the value set from
+ *
+ * # Returns
+ *
+ * - `Ok(value_set)`: The codes, with their weights
+ * - `Err(msg)`: The value set is invalid; `msg` explains why.
+ */
+pub fn parse_value_set<R: Read>(r: R) -> Result<Vec<(Code, u32)>, String> {
+    let mut reader = ReaderBuilder::new().comment(Some(b'#')).trim(csv::Trim::All).from_reader(r);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(code), Some(display)) = (column("code"), column("display")) else {
+        return Err(String::from("The value set needs \"code\" and \"display\" columns."));
+    };
+    let (system, weight) = (column("system"), column("weight"));
+    let mut value_set = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).filter(|s| !s.is_empty());
+        let code = Code {
+            system: field(system).unwrap_or(CVX_SYSTEM).to_string(),
+            code: field(Some(code)).ok_or_else(|| format!("Line {line}: empty code."))?.to_string(),
+            display: field(Some(display)).unwrap_or_default().to_string(),
+        };
+        let weight = match field(weight) {
+            Some(w) => w.parse::<u32>().map_err(|_| format!("Line {line}: bad weight \"{w}\"."))?,
+            None => 1,
+        };
+        value_set.push((code, weight));
+    }
+
+    if value_set.is_empty() {
+        return Err(String::from("The value set has no codes."));
+    }
+    Ok(value_set)
+}
+
+/**
+ * Read a value set file.
+ *
+ * # Arguments
+ *
+ * - `path`: The file
+ *
+ * # Returns
+ *
+ * - `Ok(value_set)`: The codes, with their weights
+ * - `Err(msg)`: The file couldn't be read or is invalid; `msg` explains
+ *   why, and names the file.
+ */
+pub fn read_value_set(path: &Path) -> Result<Vec<(Code, u32)>, String> {
+    let err = |msg: String| format!("\"{}\": {}", path_str(path), msg);
+    let file = File::open(path).map_err(|e| err(e.to_string()))?;
+    parse_value_set(file).map_err(err)
+}
+
+/// Generates immunization histories.
+pub struct ImmunizationGenerator {
+    rng: ChaCha8Rng,
+    weights: WeightedIndex<u32>,
+    max_per_person: u32,
+    max_date: NaiveDate,
+}
+
+impl ImmunizationGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The immunization settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The weights are invalid; `msg` explains why.
+     */
+    pub fn new(options: &ImmunizationOptions, seed: u64) -> Result<ImmunizationGenerator, String> {
+        let weights = WeightedIndex::new(options.value_set.iter().map(|(_, w)| *w))
+            .map_err(|e| format!("Bad value set weights: {}", e))?;
+
+        Ok(ImmunizationGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ IMMUNIZATIONS_SEED_SALT),
+            weights,
+            max_per_person: options.max_per_person,
+            max_date: options.max_date,
+        })
+    }
+
+    /**
+     * Generate the next person's immunizations.
+     *
+     * # Arguments
+     *
+     * - `birth_date`: The person's birth date
+     *
+     * # Returns
+     *
+     * The immunizations, in date order. They're all after the birth date,
+     * so there are none for anyone born on or after the latest date.
+     */
+    pub fn next_history(&mut self, birth_date: NaiveDate) -> Vec<Immunization> {
+        let days = (self.max_date - birth_date).num_days();
+        let count = self.rng.gen_range(0..=self.max_per_person);
+        if days < 1 {
+            return Vec::new();
+        }
+
+        let mut history: Vec<Immunization> = (0..count)
+            .map(|_| Immunization {
+                code: self.weights.sample(&mut self.rng),
+                date: birth_date + Duration::days(self.rng.gen_range(1..=days)),
+            })
+            .collect();
+        history.sort_by_key(|i| (i.date, i.code));
+        history
+    }
+}
+
+/// Where the records go.
+enum Output {
+    Csv(Box<Writer<File>>),
+    Fhir(BufWriter<File>),
+}
+
+/// Writes people's immunizations, as CSV or FHIR resources.
+pub struct ImmunizationWriter {
+    generator: ImmunizationGenerator,
+    codes: Vec<Code>,
+    output: Output,
+    path: String,
+    /// How many immunizations have been written
+    pub total: u64,
+}
+
+impl ImmunizationWriter {
+    /**
+     * Start an immunizations file.
+     *
+     * # Arguments
+     *
+     * - `options`: The immunization settings
+     * - `path`: Where to write the immunizations
+     * - `fhir`: Whether to write FHIR resources, rather than CSV
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, with any header written
+     * - `Err(msg)`: The settings are invalid, or the file can't be written.
+     */
+    pub fn new(options: &ImmunizationOptions, path: &Path, fhir: bool, seed: u64) -> Result<ImmunizationWriter, String> {
+        let generator = ImmunizationGenerator::new(options, seed)?;
+        let path = path_str(path).to_string();
+        let output = if fhir {
+            let file = File::create(&path).map_err(|e| format!("Can't write to \"{}\": {}", path, e))?;
+            Output::Fhir(BufWriter::new(file))
+        }
+        else {
+            let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path, e);
+            let mut w = WriterBuilder::new().from_path(&path).map_err(write_err)?;
+            w.write_record(HEADER).map_err(write_err)?;
+            Output::Csv(Box::new(w))
+        };
+
+        Ok(ImmunizationWriter {
+            generator,
+            codes: options.value_set.iter().map(|(code, _)| code.clone()).collect(),
+            output,
+            path,
+            total: 0,
+        })
+    }
+
+    /**
+     * Generate and write a person's immunizations.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     * - `birth_date`: The person's birth date
+     */
+    pub fn write(&mut self, id: u64, birth_date: NaiveDate) -> Result<(), String> {
+        let history = self.generator.next_history(birth_date);
+        let write_err = |e: String| format!("Can't write to \"{}\": {}", self.path, e);
+
+        for (i, immunization) in history.iter().enumerate() {
+            let code = &self.codes[immunization.code];
+            let immunization_id = format!("{}-{}", id, i + 1);
+            match &mut self.output {
+                Output::Csv(w) => w
+                    .write_record([
+                        immunization_id.as_str(),
+                        &id.to_string(),
+                        &code.system,
+                        &code.code,
+                        &code.display,
+                        &immunization.date.to_string(),
+                    ])
+                    .map_err(|e| write_err(e.to_string()))?,
+                Output::Fhir(w) => {
+                    let resource = fhir_resource(&immunization_id, id, code, immunization.date);
+                    writeln!(w, "{}", resource.dump()).map_err(|e| write_err(e.to_string()))?
+                },
+            }
+        }
+
+        self.total += history.len() as u64;
+        Ok(())
+    }
+
+    /// Finish the file, and return how many immunizations are in it.
+    pub fn finish(mut self) -> Result<u64, String> {
+        let flushed = match &mut self.output {
+            Output::Csv(w) => w.flush(),
+            Output::Fhir(w) => w.flush(),
+        };
+        flushed.map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        Ok(self.total)
+    }
+}
+
+/// Whether an immunizations file is written as FHIR resources: if it's
+/// ".ndjson" or ".jsonl".
+pub fn is_fhir_path(path: &Path) -> bool {
+    matches!(file_extension(path), Some("ndjson" | "jsonl"))
+}
+
+/// Make a FHIR resource: an `Immunization` for a CVX code, otherwise a
+/// `MedicationStatement`.
+fn fhir_resource(immunization_id: &str, id: u64, code: &Code, date: NaiveDate) -> JsonValue {
+    let mut coding = JsonValue::new_object();
+    coding["system"] = code.system.as_str().into();
+    coding["code"] = code.code.as_str().into();
+    if !code.display.is_empty() {
+        coding["display"] = code.display.as_str().into();
+    }
+    let mut concept = JsonValue::new_object();
+    concept["coding"] = JsonValue::Array(vec![coding]);
+    let mut patient = JsonValue::new_object();
+    patient["reference"] = format!("Patient/{id}").into();
+
+    let mut resource = JsonValue::new_object();
+    if code.system == CVX_SYSTEM {
+        resource["resourceType"] = "Immunization".into();
+        resource["id"] = immunization_id.into();
+        resource["status"] = "completed".into();
+        resource["vaccineCode"] = concept;
+        resource["patient"] = patient;
+        resource["occurrenceDateTime"] = date.to_string().into();
+    }
+    else {
+        resource["resourceType"] = "MedicationStatement".into();
+        resource["id"] = immunization_id.into();
+        resource["status"] = "completed".into();
+        resource["medicationCodeableConcept"] = concept;
+        resource["subject"] = patient;
+        resource["effectiveDateTime"] = date.to_string().into();
+    }
+    resource
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::immunizations::*;
+
+    fn options(max_per_person: u32) -> ImmunizationOptions {
+        ImmunizationOptions {
+            value_set: default_value_set(),
+            max_per_person,
+            max_date: NaiveDate::from_ymd(2024, 6, 30),
+        }
+    }
+
+    #[test]
+    fn histories() {
+        let mut g = ImmunizationGenerator::new(&options(5), 42).unwrap();
+        let mut counts = std::collections::HashSet::new();
+
+        for i in 0..2000 {
+            let birth_date = NaiveDate::from_ymd(1950 + i % 75, 1 + (i % 12) as u32, 1 + (i % 28) as u32);
+            let history = g.next_history(birth_date);
+            assert!(history.len() <= 5);
+            counts.insert(history.len());
+            for immunization in &history {
+                assert!(immunization.date > birth_date, "{immunization:?}");
+                assert!(immunization.date <= NaiveDate::from_ymd(2024, 6, 30), "{immunization:?}");
+                assert!(immunization.code < DEFAULT_VALUE_SET.len());
+            }
+            assert!(history.windows(2).all(|w| w[0].date <= w[1].date));
+        }
+        assert_eq!(counts.len(), 6);
+
+        // No one is immunized before they're born.
+        assert!(g.next_history(NaiveDate::from_ymd(2024, 6, 30)).is_empty());
+        assert!(ImmunizationGenerator::new(&options(0), 42).unwrap().next_history(NaiveDate::from_ymd(1980, 1, 1)).is_empty());
+    }
+
+    #[test]
+    fn value_sets() {
+        let value_set = parse_value_set("code,display,weight\n# Flu\n141,\"Influenza, seasonal, injectable\",3\n140,,\n".as_bytes()).unwrap();
+        assert_eq!(value_set.len(), 2);
+        assert_eq!(value_set[0].0.display, "Influenza, seasonal, injectable");
+        assert_eq!(value_set[0].0.system, CVX_SYSTEM);
+        assert_eq!(value_set[0].1, 3);
+        assert_eq!(value_set[1].1, 1);
+
+        let with_system = parse_value_set("system,code,display\nhttp://www.nlm.nih.gov/research/umls/rxnorm,197361,Amlodipine\n".as_bytes()).unwrap();
+        assert_eq!(with_system[0].0.system, "http://www.nlm.nih.gov/research/umls/rxnorm");
+
+        let bad = |s: &str| parse_value_set(s.as_bytes()).unwrap_err();
+        assert!(bad("code,name\n1,x\n").contains("\"display\""));
+        assert!(bad("code,display\n").contains("no codes"));
+        assert!(bad("code,display\n,x\n").contains("Line 2: empty code"));
+        assert!(bad("code,display,weight\n1,x,heavy\n").contains("bad weight"));
+    }
+
+    #[test]
+    fn fhir() {
+        let code = &default_value_set()[0].0;
+        let resource = fhir_resource("7-1", 7, code, NaiveDate::from_ymd(2020, 10, 1));
+        assert_eq!(resource["resourceType"], "Immunization");
+        assert_eq!(resource["patient"]["reference"], "Patient/7");
+        assert_eq!(resource["vaccineCode"]["coding"][0]["code"], "88");
+        assert_eq!(resource["vaccineCode"]["coding"][0]["system"], CVX_SYSTEM);
+        assert_eq!(resource["occurrenceDateTime"], "2020-10-01");
+
+        let code = Code { system: String::from("http://www.nlm.nih.gov/research/umls/rxnorm"), code: String::from("197361"), display: String::new() };
+        let resource = fhir_resource("7-2", 7, &code, NaiveDate::from_ymd(2020, 10, 1));
+        assert_eq!(resource["resourceType"], "MedicationStatement");
+        assert_eq!(resource["subject"]["reference"], "Patient/7");
+        assert_eq!(resource["medicationCodeableConcept"]["coding"][0]["code"], "197361");
+        assert!(resource["medicationCodeableConcept"]["coding"][0]["display"].is_null());
+        assert_eq!(resource["effectiveDateTime"], "2020-10-01");
+        assert!(is_fhir_path(Path::new("shots.ndjson")));
+        assert!(!is_fhir_path(Path::new("shots.csv")));
+    }
+}
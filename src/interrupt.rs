@@ -0,0 +1,123 @@
+//! Graceful handling of SIGINT (Ctrl-C) and SIGTERM.
+//!
+//! A signal doesn't kill `peoplegen` outright. Instead, it stops the stream
+//! of people, so the writer finishes the output normally (closing the JSON
+//! array, writing the last Avro block, and so on). Output on standard output
+//! holds every person generated up to that point, and staged files are
+//! thrown away, rather than moved into place. A second signal exits at once.
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when a signal arrives.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set once the handler is installed.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// The message for a stream that was cut short.
+pub const INTERRUPTED_MESSAGE: &str = "Interrupted.";
+
+/**
+ * Install the signal handler. Installing it again (for a scenario's next
+ * step, say) does nothing.
+ *
+ * # Arguments
+ *
+ * - `exit_code`: What to exit with, on a second signal
+ *
+ * # Returns
+ *
+ * - `Ok(())`: The handler was installed, or already was.
+ * - `Err(msg)`: It couldn't be; `msg` explains why.
+ */
+pub fn install_handler(exit_code: i32) -> Result<(), String> {
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    ctrlc::set_handler(move || {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(exit_code);
+        }
+    })
+    .map_err(|e| format!("Can't install signal handler: {e}"))
+}
+
+/// Whether a signal has arrived.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/**
+ * Stop a stream of results when a signal arrives.
+ *
+ * # Arguments
+ *
+ * - `iter`: The stream
+ * - `fail`: If `true`, the stream ends with an `INTERRUPTED_MESSAGE` error,
+ *   so the writer gives up (e.g., rolls back a database load), instead of
+ *   finishing normally.
+ *
+ * # Returns
+ *
+ * The stream, which stops at the first result after the signal.
+ */
+pub fn until_interrupted<I, T>(iter: I, fail: bool) -> UntilInterrupted<'static, I>
+where
+    I: Iterator<Item = Result<T, String>>,
+{
+    UntilInterrupted { iter, flag: &INTERRUPTED, fail, done: false }
+}
+
+/// A stream of results that stops when a flag is set. See
+/// `until_interrupted()`.
+pub struct UntilInterrupted<'a, I> {
+    iter: I,
+    flag: &'a AtomicBool,
+    fail: bool,
+    done: bool,
+}
+
+impl<I, T> Iterator for UntilInterrupted<'_, I>
+where
+    I: Iterator<Item = Result<T, String>>,
+{
+    type Item = Result<T, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            None
+        }
+        else if self.flag.load(Ordering::SeqCst) {
+            self.done = true;
+            if self.fail { Some(Err(String::from(INTERRUPTED_MESSAGE))) } else { None }
+        }
+        else {
+            self.iter.next()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interrupt::*;
+
+    #[test]
+    fn stops_when_flagged() {
+        for fail in [false, true] {
+            let flag = AtomicBool::new(false);
+            let mut s = UntilInterrupted {
+                iter: (1..).map(Ok::<i32, String>),
+                flag: &flag,
+                fail,
+                done: false,
+            };
+
+            assert_eq!(s.next(), Some(Ok(1)));
+            flag.store(true, Ordering::SeqCst);
+            let expected = if fail { Some(Err(String::from(INTERRUPTED_MESSAGE))) } else { None };
+            assert_eq!(s.next(), expected);
+            assert_eq!(s.next(), None);
+        }
+    }
+}
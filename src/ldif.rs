@@ -0,0 +1,209 @@
+//! LDIF output, for bulk-loading test users into an LDAP directory (OpenLDAP,
+//! 389 Directory Server, Active Directory) with `ldapadd` or `ldifde`.
+//!
+//! Each person is an `inetOrgPerson` entry, named by its `uid`, under a base
+//! DN. Values that LDIF can't hold as plain text (e.g., names with accented
+//! letters) are base64-encoded, and long lines are folded. See RFC 2849.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::io::{self, Write};
+
+/// The default base DN for the entries.
+pub const DEFAULT_BASE_DN: &str = "ou=people,dc=example,dc=com";
+
+/// The object classes of every entry.
+pub const OBJECT_CLASSES: [&str; 4] = ["top", "person", "organizationalPerson", "inetOrgPerson"];
+
+/// Lines longer than this many bytes are folded.
+const MAX_LINE_BYTES: usize = 76;
+
+/**
+ * Writes LDIF entries.
+ */
+pub struct LdifWriter<W: Write> {
+    w: W,
+    started: bool,
+}
+
+impl<W: Write> LdifWriter<W> {
+    /**
+     * Create a new writer.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the entries
+     */
+    pub fn new(w: W) -> LdifWriter<W> {
+        LdifWriter { w, started: false }
+    }
+
+    /**
+     * Write an entry.
+     *
+     * # Arguments
+     *
+     * - `dn`: The entry's distinguished name, e.g., from `dn()`
+     * - `attributes`: The attribute names and values, in order. Empty values
+     *   are left out.
+     */
+    pub fn write_entry(&mut self, dn: &str, attributes: &[(&str, String)]) -> io::Result<()> {
+        if !self.started {
+            self.w.write_all(b"version: 1\n")?;
+            self.started = true;
+        }
+
+        self.w.write_all(b"\n")?;
+        self.write_attribute("dn", dn)?;
+        for class in OBJECT_CLASSES {
+            self.write_attribute("objectClass", class)?;
+        }
+        for (name, value) in attributes.iter().filter(|(_, v)| !v.is_empty()) {
+            self.write_attribute(name, value)?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The flush failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.started {
+            self.w.write_all(b"version: 1\n")?;
+        }
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn write_attribute(&mut self, name: &str, value: &str) -> io::Result<()> {
+        let line = if is_safe(value) {
+            format!("{name}: {value}")
+        }
+        else {
+            format!("{name}:: {}", BASE64.encode(value))
+        };
+
+        self.w.write_all(fold(&line).as_bytes())?;
+        self.w.write_all(b"\n")
+    }
+}
+
+/**
+ * Build the distinguished name of an entry, escaping the RDN value as
+ * RFC 4514 requires.
+ *
+ * # Arguments
+ *
+ * - `uid`: The entry's `uid`
+ * - `base_dn`: The DN the entry goes under
+ *
+ * # Returns
+ *
+ * The DN, e.g., "uid=jsmith,ou=people,dc=example,dc=com".
+ */
+pub fn dn(uid: &str, base_dn: &str) -> String {
+    let mut escaped = String::with_capacity(uid.len());
+
+    for (i, c) in uid.chars().enumerate() {
+        let special = matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=') ||
+                      (i == 0 && (c == '#' || c == ' ')) ||
+                      (c == ' ' && i == uid.chars().count() - 1);
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    if base_dn.is_empty() { format!("uid={escaped}") } else { format!("uid={escaped},{base_dn}") }
+}
+
+/// Whether a value can be written as is (an LDIF SAFE-STRING): printable
+/// ASCII, not starting with a space, colon or "<", and not ending with a
+/// space.
+fn is_safe(value: &str) -> bool {
+    !value.starts_with([' ', ':', '<']) &&
+    !value.ends_with(' ') &&
+    value.bytes().all(|b| (0x20..0x7f).contains(&b))
+}
+
+/// Fold a line, so no line is longer than `MAX_LINE_BYTES`: continuation
+/// lines start with a space. Folded lines are ASCII (anything else is
+/// base64-encoded), so they can be split anywhere.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut max = MAX_LINE_BYTES;
+
+    while rest.len() > max {
+        let (head, tail) = rest.split_at(max);
+        folded.push_str(head);
+        folded.push_str("\n ");
+        rest = tail;
+        max = MAX_LINE_BYTES - 1;
+    }
+
+    folded.push_str(rest);
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ldif::*;
+
+    #[test]
+    fn entries() {
+        let mut w = LdifWriter::new(Vec::new());
+        w.write_entry(&dn("jbrel", DEFAULT_BASE_DN), &[
+            ("cn", String::from("Jacques Brel")),
+            ("sn", String::from("Brel")),
+            ("givenName", String::from("Jacques")),
+            ("initials", String::new()),
+            ("uid", String::from("jbrel")),
+        ]).unwrap();
+        w.write_entry("uid=zoe,dc=example", &[("sn", String::from("Zoë"))]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "version: 1\n\
+             \n\
+             dn: uid=jbrel,ou=people,dc=example,dc=com\n\
+             objectClass: top\n\
+             objectClass: person\n\
+             objectClass: organizationalPerson\n\
+             objectClass: inetOrgPerson\n\
+             cn: Jacques Brel\n\
+             sn: Brel\n\
+             givenName: Jacques\n\
+             uid: jbrel\n\
+             \n\
+             dn: uid=zoe,dc=example\n\
+             objectClass: top\n\
+             objectClass: person\n\
+             objectClass: organizationalPerson\n\
+             objectClass: inetOrgPerson\n\
+             sn:: Wm/Dqw==\n"
+        );
+    }
+
+    #[test]
+    fn dns() {
+        assert_eq!(dn("a,b+c", "dc=example"), "uid=a\\,b\\+c,dc=example");
+        assert_eq!(dn("#x ", ""), "uid=\\#x\\ ");
+    }
+
+    #[test]
+    fn folding() {
+        let line = "x".repeat(200);
+        let folded = fold(&line);
+
+        assert!(folded.lines().all(|l| l.len() <= MAX_LINE_BYTES));
+        assert_eq!(folded.replace("\n ", ""), line);
+        assert!(!is_safe(":x") && !is_safe("x ") && !is_safe("é") && is_safe("O'Brien"));
+    }
+}
@@ -0,0 +1,117 @@
+//! `peoplegen` as a library: everything the `peoplegen` program does, for
+//! other Rust programs that want fake people without shelling out to it.
+//! The binary (`main.rs`) is just the command line on top of this.
+//!
+//! The places to start are the re-exports here: `PersonGenerator`, an
+//! iterator over randomly generated `Person`s, and `GenerationConfig`, which
+//! builds the settings for one (see `config`). The generator comes from
+//! `people::make_people()`, which takes the settings as the command line's
+//! `Arguments`:
+//!
+//! ```
+//! use peoplegen::args::Arguments;
+//! use peoplegen::genders::GenderCategory;
+//! use peoplegen::people::make_people;
+//! use peoplegen::weighted::WeightedValues;
+//! use peoplegen::{Gender, Person};
+//!
+//! let names = |names: &[&str]| WeightedValues::uniform(names.iter().map(|n| n.to_string()).collect());
+//! let categories = [
+//!     GenderCategory { gender: Gender::Male, percent: 50, first_names: names(&["Moe", "Larry"]) },
+//!     GenderCategory { gender: Gender::Female, percent: 50, first_names: names(&["Shemp"]) },
+//! ];
+//! let last_names = names(&["Howard", "Fine"]);
+//! let args = Arguments { total: 10, year_min: 1950, year_max: 2000, seed: 42, ..Default::default() };
+//!
+//! let people: Vec<Person> = make_people(&args, &categories, &last_names).unwrap().collect();
+//! assert_eq!(people.len(), 10);
+//! assert_eq!(people.iter().filter(|p| p.gender == Gender::Female).count(), 5);
+//! assert!(people.iter().all(|p| &*p.last_name == "Howard" || &*p.last_name == "Fine"));
+//! ```
+//!
+//! The entry points fail with a `PeoplegenError`, which says what kind of
+//! failure it was (see `error`).
+//!
+//! Some of the modules are useful on their own, too:
+//!
+//! - `ssn`: an iterator over guaranteed-fake U.S. Social Security numbers
+//! - `fixed`: fixed-width layouts, and a writer for fixed-width records
+//! - `gender_config`: a parser for gender configuration files
+//! - `numlib`: numeric parsing helpers
+//! - `telemetry`: a `tracing` subscriber that exports spans to OpenTelemetry
+//! - `weighted`: weighted value files, for choosing values at random
+//!
+//! The parsers are here, too, so the fuzz targets in `fuzz/` can reach them.
+
+pub mod ad;
+pub mod aggregates;
+pub mod args;
+pub mod arrow;
+pub mod assertions;
+pub mod avatar;
+pub mod avro;
+pub mod barcode;
+pub mod bench;
+pub mod benefits;
+pub mod checksum;
+pub mod cluster;
+pub mod config;
+pub mod config_file;
+pub mod consent;
+pub mod crypt;
+pub mod device;
+pub mod dirs;
+pub mod doctor;
+pub mod env;
+pub mod error;
+pub mod fetch;
+pub mod fixed;
+pub mod format;
+pub mod gender_config;
+pub mod genders;
+pub mod hash;
+pub mod html;
+pub mod immunizations;
+pub mod interrupt;
+pub mod ldif;
+pub mod lifecycle;
+pub mod logging;
+pub mod loyalty;
+pub mod markdown;
+pub mod metadata;
+pub mod numlib;
+pub mod output;
+pub mod path;
+pub mod pdf;
+pub mod people;
+pub mod pg;
+pub mod plan;
+pub mod png;
+pub mod preview;
+pub mod pronouns;
+pub mod protobuf;
+pub mod provider;
+pub mod rollover;
+pub mod rules;
+pub mod salary;
+pub mod scenario;
+pub mod shards;
+pub mod shifts;
+pub mod sql;
+pub mod ssn;
+pub mod staging;
+pub mod stats;
+pub mod summary;
+pub mod telemetry;
+pub mod template;
+pub mod transactions;
+pub mod validate;
+pub mod vcard;
+pub mod vin;
+pub mod w2;
+pub mod weighted;
+pub mod xlsx;
+
+pub use config::GenerationConfig;
+pub use error::PeoplegenError;
+pub use people::{Gender, Person, PersonGenerator};
@@ -0,0 +1,235 @@
+//! The optional customer-lifecycle fields, which make the people into
+//! customers, for product-analytics demos: when each one signed up, when they
+//! were last active, whether they've churned, and what they've spent.
+//!
+//! The fields hang together the way real customer data does:
+//!
+//! - Nobody signs up before their 18th birthday.
+//! - `last_active_date` is never before `signup_date`. Active customers were
+//!   last active within `ACTIVE_DAYS` of the end of the signup window;
+//!   churned ones have been gone at least `CHURNED_DAYS`, unless they only
+//!   just signed up.
+//! - `lifetime_value` grows with how long the customer was active, and with
+//!   their salary, so long-standing, well-paid customers are worth the most.
+//!
+//! Lifecycles come from their own random number generator, derived from the
+//! run's seed, so turning them on or off doesn't change any of the other
+//! generated values.
+
+use chrono::naive::NaiveDate;
+use chrono::{Datelike, Duration};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, LogNormal};
+use serde::{Deserialize, Serialize};
+
+/// Mixed into the run's seed to seed the lifecycle random number generator.
+const LIFECYCLE_SEED_SALT: u64 = 0x6c69_6665_6379_636c;
+
+/// The default `--churn-rate` percentage.
+pub const DEFAULT_CHURN_RATE: u32 = 25;
+
+/// How recently active customers were last active, at most, in days.
+const ACTIVE_DAYS: i64 = 30;
+
+/// How long churned customers have been inactive, at least, in days.
+const CHURNED_DAYS: i64 = 90;
+
+/// The median yearly spend, for a customer with the default mean salary.
+const MEDIAN_YEARLY_SPEND: f64 = 300.0;
+
+/// The salary at which customers spend the median.
+const SPEND_SALARY: f64 = 58260.0;
+
+/// The settings for lifecycle generation, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifecycleOptions {
+    /// The first day on which customers can have signed up
+    pub signup_min: NaiveDate,
+    /// The last day on which customers can have signed up, which is also
+    /// the end of the data: nobody's active after it
+    pub signup_max: NaiveDate,
+    /// The percentage of customers who've churned
+    pub churn_percent: u32,
+}
+
+/// A customer's lifecycle. With serde, its fields are named as with the snake
+/// case header format, and any other header format's names are accepted,
+/// too (see `Person`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lifecycle {
+    #[serde(alias = "signupDate", alias = "Signup Date")]
+    pub signup_date: NaiveDate,
+    #[serde(alias = "lastActiveDate", alias = "Last Active Date")]
+    pub last_active_date: NaiveDate,
+    #[serde(alias = "Churned")]
+    pub churned: bool,
+    /// What the customer has spent, in dollars and cents
+    #[serde(alias = "lifetimeValue", alias = "Lifetime Value")]
+    pub lifetime_value: f64,
+}
+
+/// Generates a `Lifecycle` for each person.
+pub struct LifecycleGenerator {
+    rng: ChaCha8Rng,
+    signup_min: NaiveDate,
+    signup_max: NaiveDate,
+    churn_percent: u32,
+    yearly_spend: LogNormal<f64>,
+}
+
+impl LifecycleGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The lifecycle settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &LifecycleOptions, seed: u64) -> Result<LifecycleGenerator, String> {
+        if options.signup_min > options.signup_max {
+            return Err(format!(
+                "Minimum signup date {} is after maximum signup date {}.",
+                options.signup_min, options.signup_max
+            ));
+        }
+
+        Ok(LifecycleGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ LIFECYCLE_SEED_SALT),
+            signup_min: options.signup_min,
+            signup_max: options.signup_max,
+            churn_percent: options.churn_percent,
+            // The parameters are constants, and valid.
+            yearly_spend: LogNormal::new(MEDIAN_YEARLY_SPEND.ln(), 0.75).unwrap(),
+        })
+    }
+
+    /**
+     * Generate the lifecycle for the next person.
+     *
+     * # Arguments
+     *
+     * - `birth_date`: The person's birth date
+     * - `salary`: The person's salary
+     */
+    pub fn next_lifecycle(&mut self, birth_date: NaiveDate, salary: u32) -> Lifecycle {
+        // Draw everything, whatever's used, so the sequence doesn't depend on
+        // the people.
+        let churned = self.rng.gen_range(0..100) < self.churn_percent;
+        let signup_at: f64 = self.rng.gen();
+        let active_at: f64 = self.rng.gen();
+        let yearly_spend = self.yearly_spend.sample(&mut self.rng);
+
+        let adult = add_years(birth_date, 18);
+        let first = self.signup_min.max(adult).min(self.signup_max);
+        let signup_date = between(first, self.signup_max, signup_at);
+
+        let last_active_date = if churned {
+            let latest = (self.signup_max - Duration::days(CHURNED_DAYS)).max(signup_date);
+            between(signup_date, latest, active_at)
+        }
+        else {
+            let earliest = (self.signup_max - Duration::days(ACTIVE_DAYS)).max(signup_date);
+            between(earliest, self.signup_max, active_at)
+        };
+
+        let years = ((last_active_date - signup_date).num_days() + 1) as f64 / 365.25;
+        let salary_factor = (f64::from(salary) / SPEND_SALARY).clamp(0.25, 4.0);
+        let lifetime_value = (yearly_spend * salary_factor * years * 100.0).round() / 100.0;
+
+        Lifecycle { signup_date, last_active_date, churned, lifetime_value }
+    }
+}
+
+/// Get the date `fraction` of the way from `first` to `last`, inclusive.
+pub fn between(first: NaiveDate, last: NaiveDate, fraction: f64) -> NaiveDate {
+    let days = (last - first).num_days();
+    first + Duration::days(((days + 1) as f64 * fraction).floor().min(days as f64) as i64)
+}
+
+/// Add years to a date, moving February 29 to March 1 in years that don't
+/// have one.
+pub fn add_years(date: NaiveDate, years: i32) -> NaiveDate {
+    let year = date.year() + years;
+    NaiveDate::from_ymd_opt(year, date.month(), date.day())
+        .unwrap_or_else(|| NaiveDate::from_ymd(year, 3, 1))
+}
+
+/// Format a lifetime value, in dollars and cents.
+pub fn money_str(value: f64) -> String {
+    format!("{value:.2}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lifecycle::*;
+
+    fn options(churn_percent: u32) -> LifecycleOptions {
+        LifecycleOptions {
+            signup_min: NaiveDate::from_ymd(2015, 1, 1),
+            signup_max: NaiveDate::from_ymd(2024, 12, 31),
+            churn_percent,
+        }
+    }
+
+    #[test]
+    fn lifecycles_are_consistent() {
+        let mut g = LifecycleGenerator::new(&options(30), 42).unwrap();
+        let end = NaiveDate::from_ymd(2024, 12, 31);
+        let mut churned = 0;
+
+        for i in 0..10000 {
+            let birth_date = NaiveDate::from_ymd(1950 + i % 55, 2, 28);
+            let l = g.next_lifecycle(birth_date, 58260);
+            assert!(l.signup_date >= NaiveDate::from_ymd(2015, 1, 1), "{l:?}");
+            assert!(l.signup_date >= add_years(birth_date, 18) || l.signup_date == end, "{l:?}");
+            assert!(l.last_active_date >= l.signup_date && l.last_active_date <= end, "{l:?}");
+            assert!(l.lifetime_value > 0.0, "{l:?}");
+            if l.churned {
+                churned += 1;
+                assert!(l.last_active_date <= end - Duration::days(CHURNED_DAYS) || l.last_active_date == l.signup_date,
+                        "{l:?}");
+            }
+            else {
+                assert!(l.last_active_date >= end - Duration::days(ACTIVE_DAYS), "{l:?}");
+            }
+        }
+        assert!((2700..3300).contains(&churned), "{churned}");
+    }
+
+    #[test]
+    fn value_follows_tenure_and_salary() {
+        let average = |salary, signup_min| {
+            let o = LifecycleOptions { signup_min, churn_percent: 0, ..options(0) };
+            let mut g = LifecycleGenerator::new(&o, 42).unwrap();
+            (0..2000).map(|_| g.next_lifecycle(NaiveDate::from_ymd(1970, 1, 1), salary).lifetime_value).sum::<f64>()
+        };
+
+        let recent = NaiveDate::from_ymd(2024, 1, 1);
+        let long_ago = NaiveDate::from_ymd(2000, 1, 1);
+        assert!(average(58260, long_ago) > 5.0 * average(58260, recent));
+        assert!(average(116520, recent) > 1.5 * average(58260, recent));
+    }
+
+    #[test]
+    fn bad_window() {
+        let o = LifecycleOptions { signup_min: NaiveDate::from_ymd(2025, 1, 1), ..options(0) };
+        assert!(LifecycleGenerator::new(&o, 42).is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(add_years(NaiveDate::from_ymd(2000, 2, 29), 18), NaiveDate::from_ymd(2018, 3, 1));
+        let (first, last) = (NaiveDate::from_ymd(2020, 1, 1), NaiveDate::from_ymd(2020, 1, 10));
+        assert_eq!(between(first, last, 0.0), first);
+        assert_eq!(between(first, last, 0.999), last);
+        assert_eq!(between(last, last, 0.5), last);
+        assert_eq!(money_str(12.5), "12.50");
+    }
+}
@@ -0,0 +1,141 @@
+//! The program's log messages, on standard error: warnings by default, how
+//! long each phase of the run took with `-v`, and more detail with `-vv`.
+//! `--quiet` leaves only errors.
+//!
+//! This is a backend for the `log` crate, in the style of `env_logger`, but
+//! with just what the program needs: one level for the whole run, from the
+//! command line or, failing that, the `PEOPLEGEN_LOG` environment variable
+//! (`off`, `error`, `warn`, `info`, `debug` or `trace`). The library logs
+//! through the `log` macros, too, so programs using it can install a logger
+//! of their own.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The environment variable that sets the log level, when the command line
+/// doesn't.
+pub const ENV_LOG: &str = "PEOPLEGEN_LOG";
+
+/// When the logger was installed, which log messages' times count from.
+static START: OnceLock<Instant> = OnceLock::new();
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Writes log messages to standard error.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Other crates' informational messages are none of the user's
+        // business, but their warnings may be.
+        metadata.level() <= log::max_level()
+            && (metadata.level() <= Level::Warn || metadata.target().starts_with("peoplegen"))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let elapsed = START.get().map_or(Duration::ZERO, Instant::elapsed);
+            let line = format_message(record.level(), &record.args().to_string(), elapsed);
+            // There's nowhere to report a failure to write to standard error.
+            let _ = writeln!(io::stderr(), "{line}");
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/**
+ * Install the logger, if there isn't one already.
+ *
+ * # Arguments
+ *
+ * - `level`: The most detailed level to log
+ */
+pub fn init(level: LevelFilter) {
+    START.get_or_init(Instant::now);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/**
+ * Work out the log level for a run.
+ *
+ * # Arguments
+ *
+ * - `verbosity`: How many times `-v` was given
+ * - `quiet`: Whether `--quiet` was given
+ * - `env_level`: The value of `ENV_LOG`, or `""` if it isn't set. A value
+ *   that isn't a level is ignored.
+ *
+ * # Returns
+ *
+ * The level: from the options if either was given, otherwise from the
+ * environment, otherwise warnings.
+ */
+pub fn log_level(verbosity: u8, quiet: bool, env_level: &str) -> LevelFilter {
+    match (verbosity, quiet) {
+        (_, true) => LevelFilter::Error,
+        (0, false) => LevelFilter::from_str(env_level.trim()).unwrap_or(LevelFilter::Warn),
+        (1, false) => LevelFilter::Info,
+        (2, false) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/**
+ * Format a log message.
+ *
+ * # Arguments
+ *
+ * - `level`: The message's level
+ * - `message`: The message
+ * - `elapsed`: How far into the run it is
+ *
+ * # Returns
+ *
+ * Errors and warnings read as they always have, as "Error: ..." and
+ * "Warning: ...". The rest start with the time into the run and the level,
+ * e.g., "[0.012s INFO] ...".
+ */
+pub fn format_message(level: Level, message: &str, elapsed: Duration) -> String {
+    match level {
+        Level::Error => format!("Error: {message}"),
+        Level::Warn => format!("Warning: {message}"),
+        _ => format!("[{} {level}] {message}", secs(elapsed)),
+    }
+}
+
+/// Format a duration in seconds, to the millisecond, for log messages.
+pub fn secs(duration: Duration) -> String {
+    format!("{:.3}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logging::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(log_level(0, false, ""), LevelFilter::Warn);
+        assert_eq!(log_level(1, false, ""), LevelFilter::Info);
+        assert_eq!(log_level(2, false, "off"), LevelFilter::Debug);
+        assert_eq!(log_level(5, false, ""), LevelFilter::Trace);
+        assert_eq!(log_level(0, true, "debug"), LevelFilter::Error);
+        assert_eq!(log_level(0, false, "DEBUG"), LevelFilter::Debug);
+        assert_eq!(log_level(0, false, "off"), LevelFilter::Off);
+        assert_eq!(log_level(0, false, "loud"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn messages() {
+        let elapsed = Duration::from_millis(1234);
+        assert_eq!(format_message(Level::Warn, "Careful.", elapsed), "Warning: Careful.");
+        assert_eq!(format_message(Level::Error, "Oops.", elapsed), "Error: Oops.");
+        assert_eq!(format_message(Level::Info, "Wrote 5.", elapsed), "[1.234s INFO] Wrote 5.");
+        assert_eq!(format_message(Level::Debug, "Hm.", Duration::ZERO), "[0.000s DEBUG] Hm.");
+    }
+}
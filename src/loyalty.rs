@@ -0,0 +1,160 @@
+//! The optional loyalty-program fields, for retail and travel datasets: each
+//! person's membership tier, their points balance, and when they joined.
+//!
+//! The tier is chosen from a weighted list, lowest tier first, and the
+//! balance depends on it: each tier up has about twice the points of the one
+//! below. Nobody joins before their 18th birthday.
+//!
+//! Loyalty comes from its own random number generator, derived from the run's
+//! seed, so turning it on or off doesn't change any of the other generated
+//! values.
+
+use crate::lifecycle::{add_years, between};
+use crate::weighted::WeightedValues;
+use chrono::naive::NaiveDate;
+use rand::distributions::Distribution;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::LogNormal;
+use serde::{Deserialize, Serialize};
+
+/// Mixed into the run's seed to seed the loyalty random number generator.
+const LOYALTY_SEED_SALT: u64 = 0x6c6f_7961_6c74_7921;
+
+/// The default `--loyalty-tiers` list, lowest tier first.
+pub const DEFAULT_LOYALTY_TIERS: &str = "Bronze:55,Silver:28,Gold:13,Platinum:4";
+
+/// The median points balance in the lowest tier.
+const MEDIAN_POINTS: f64 = 1500.0;
+
+/// The settings for loyalty generation, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoyaltyOptions {
+    /// The tiers, lowest first, with their relative weights
+    pub tiers: WeightedValues,
+    /// The first day on which people can have joined
+    pub member_min: NaiveDate,
+    /// The last day on which people can have joined
+    pub member_max: NaiveDate,
+}
+
+/// A person's loyalty-program membership. With serde, its fields are named
+/// as with the snake case header format, and any other header format's names
+/// are accepted, too (see `Person`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loyalty {
+    #[serde(alias = "loyaltyTier", alias = "Loyalty Tier")]
+    pub loyalty_tier: String,
+    #[serde(alias = "pointsBalance", alias = "Points Balance")]
+    pub points_balance: u64,
+    #[serde(alias = "memberSince", alias = "Member Since")]
+    pub member_since: NaiveDate,
+}
+
+/// Generates a `Loyalty` for each person.
+pub struct LoyaltyGenerator {
+    rng: ChaCha8Rng,
+    tiers: WeightedValues,
+    member_min: NaiveDate,
+    member_max: NaiveDate,
+    points: LogNormal<f64>,
+}
+
+impl LoyaltyGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The loyalty settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &LoyaltyOptions, seed: u64) -> Result<LoyaltyGenerator, String> {
+        if options.member_min > options.member_max {
+            return Err(format!(
+                "Minimum membership date {} is after maximum membership date {}.",
+                options.member_min, options.member_max
+            ));
+        }
+
+        Ok(LoyaltyGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ LOYALTY_SEED_SALT),
+            tiers: options.tiers.clone(),
+            member_min: options.member_min,
+            member_max: options.member_max,
+            // The parameters are constants, and valid.
+            points: LogNormal::new(MEDIAN_POINTS.ln(), 0.6).unwrap(),
+        })
+    }
+
+    /**
+     * Generate the loyalty membership for the next person.
+     *
+     * # Arguments
+     *
+     * - `birth_date`: The person's birth date
+     */
+    pub fn next_loyalty(&mut self, birth_date: NaiveDate) -> Loyalty {
+        let rank = self.tiers.choose_index(&mut self.rng);
+        let points = self.points.sample(&mut self.rng) * 2f64.powi(rank as i32);
+        let joined_at: f64 = self.rng.gen();
+
+        let first = self.member_min.max(add_years(birth_date, 18)).min(self.member_max);
+
+        Loyalty {
+            loyalty_tier: self.tiers.values()[rank].to_string(),
+            points_balance: points.round() as u64,
+            member_since: between(first, self.member_max, joined_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loyalty::*;
+
+    fn options(tiers: &str) -> LoyaltyOptions {
+        LoyaltyOptions {
+            tiers: WeightedValues::parse_list(tiers, "loyalty tier").unwrap(),
+            member_min: NaiveDate::from_ymd(2010, 1, 1),
+            member_max: NaiveDate::from_ymd(2024, 12, 31),
+        }
+    }
+
+    #[test]
+    fn loyalty() {
+        let mut g = LoyaltyGenerator::new(&options(DEFAULT_LOYALTY_TIERS), 42).unwrap();
+        let mut points: Vec<Vec<u64>> = vec![Vec::new(); 4];
+
+        for i in 0..10000 {
+            let birth_date = NaiveDate::from_ymd(1960 + i % 45, 2, 28);
+            let l = g.next_loyalty(birth_date);
+            assert!(l.member_since >= NaiveDate::from_ymd(2010, 1, 1), "{l:?}");
+            assert!(l.member_since <= NaiveDate::from_ymd(2024, 12, 31), "{l:?}");
+            assert!(l.member_since >= add_years(birth_date, 18) || l.member_since == g.member_max, "{l:?}");
+            let rank = g.tiers.values().iter().position(|t| **t == l.loyalty_tier).unwrap();
+            points[rank].push(l.points_balance);
+        }
+
+        // Roughly in proportion to the weights, with more points in the higher
+        // tiers.
+        assert!((5200..5800).contains(&points[0].len()), "{}", points[0].len());
+        assert!((250..550).contains(&points[3].len()), "{}", points[3].len());
+        let mean = |p: &Vec<u64>| p.iter().sum::<u64>() as f64 / p.len() as f64;
+        for rank in 1..4 {
+            assert!(mean(&points[rank]) > 1.5 * mean(&points[rank - 1]));
+        }
+    }
+
+    #[test]
+    fn bad_options() {
+        let mut o = options("Basic");
+        o.member_min = NaiveDate::from_ymd(2025, 1, 1);
+        assert!(LoyaltyGenerator::new(&o, 42).is_err());
+    }
+}
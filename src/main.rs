@@ -1,34 +1,109 @@
-//! Main program (and the crate root).
+//! Main program: the command line, on top of the library in `lib.rs`.
 //!
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process;
-use crate::args::{Arguments, parse_args};
-use crate::people::{read_names_file, make_people, write_people};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use peoplegen::aggregates::Aggregates;
+use peoplegen::bench::BenchReport;
+use peoplegen::benefits::{BenefitsWriter, DEPENDENTS_FILE, ELECTIONS_FILE};
+use peoplegen::args::{Action, Arguments, parse_args, ENV_FEMALE_FIRST_NAMES_FILE,
+                      ENV_LAST_NAMES_FILE, ENV_MALE_FIRST_NAMES_FILE};
+use peoplegen::doctor::{diagnose, Status};
+use peoplegen::env::getenv;
+use peoplegen::fetch::{default_names_dir, fetch_names};
+use peoplegen::genders::{gender_categories, GenderCategory};
+use peoplegen::immunizations::{is_fhir_path, ImmunizationWriter};
+use peoplegen::interrupt::{self, until_interrupted};
+use peoplegen::logging::{self, secs};
+use peoplegen::assertions::Tally;
+use peoplegen::people::{column_settings, read_names_file, make_people, ruled_value, table_ddl,
+                        write_people, write_preview, write_proto_definition, write_ssn_collisions,
+                        ExtraFiles, Person};
+use peoplegen::output::{is_null, is_stdout};
+use peoplegen::plan::plan;
+use peoplegen::rollover::write_rolling;
+use peoplegen::shards::{shard_paths, write_shards};
+use peoplegen::shifts::ShiftWriter;
+use peoplegen::staging::Staging;
+use peoplegen::stats::Stats;
+use peoplegen::summary::{Destination, ExtraOutput, RunSummary};
+use peoplegen::telemetry;
+use peoplegen::transactions::TransactionWriter;
+use peoplegen::validate::{validate_file, ValidateOptions};
+use peoplegen::w2::W2Writer;
+use peoplegen::weighted::WeightedValues;
+use tracing::span::EnteredSpan;
+use tracing::{field, info_span};
 
 #[macro_use]
 extern crate comp;
 
-pub mod numlib;
-pub mod args;
-pub mod people;
-pub mod path;
-pub mod env;
-pub mod ssn;
+// Exit codes, so wrapper scripts can tell failures apart. 2 is also what the
+// command-line parser exits with when it rejects the arguments.
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_INPUT: i32 = 3;
+pub const EXIT_GENERATION: i32 = 4;
+pub const EXIT_OUTPUT: i32 = 5;
+pub const EXIT_ASSERTION: i32 = 6;
+pub const EXIT_INVALID: i32 = 7;
+// 128 + SIGINT, as shells report a process killed by Ctrl-C.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// A failed run: the message to print, and the code to exit with.
+#[derive(Debug)]
+struct Failure {
+    code: i32,
+    msg: String,
+}
+
+/// Make a function that turns an error (or its message) into a `Failure`,
+/// for `map_err()`.
+fn fail<E: ToString>(code: i32) -> impl Fn(E) -> Failure {
+    move |e| Failure { code, msg: e.to_string() }
+}
 
 /**
  * Main program.
  */
 fn main() {
     let res = result! {
-        let args <- parse_args();
-        let res <- run(args);
+        let action <- parse_args().map_err(fail(EXIT_USAGE));
+        let res <- match action {
+            Action::Generate(args) if args.dry_run => dry_run(*args),
+            Action::Generate(args) => run(*args),
+            Action::FetchNames { dest, force } => fetch(&dest, force).map_err(fail(EXIT_OUTPUT)),
+            Action::SchemaDdl { args, dialect, table } => {
+                println!("{}", table_ddl(&args, dialect, &table));
+                Ok(())
+            },
+            Action::DumpConfig(toml) => {
+                print!("{}", toml);
+                Ok(())
+            },
+            Action::Validate { path, options } => validate(&path, &options),
+            Action::Bench(args) => bench(*args),
+            Action::Doctor(args) => doctor(&args),
+            Action::Scenario { steps, seed, random_seed } => scenario(steps, seed, random_seed),
+        };
         res
     };
 
+    // Whether or not the run worked, its spans say where the time went.
+    match telemetry::export() {
+        Ok(0) => (),
+        Ok(n) => debug!("Exported {} trace span(s).", n),
+        Err(msg) => warn!("{}", msg),
+    }
+
     match res {
         Ok(_) => process::exit(0),
-        Err(msg) => {
-            eprintln!{"{}", msg};
-            process::exit(1);
+        Err(failure) => {
+            eprintln!{"{}", failure.msg};
+            process::exit(failure.code);
         }
     }
 }
@@ -44,35 +119,716 @@ fn main() {
  * # Returns
  *
  * - `Ok(())`: Everything worked. No result.
- * - `Err(msg)`: Something failed, and `msg` explains the error.
+ * - `Err(failure)`: Something failed, and `failure` explains the error.
  */
-fn run(args: Arguments) -> Result<(), String> {
+fn run(args: Arguments) -> Result<(), Failure> {
+    let mut staging = Staging::new();
+    let finished = stage_run(args, &mut staging)?;
+    commit(staging)?;
+    finished.report();
+    Ok(())
+}
+
+/// A run whose files are staged, and what to say about it once they're in
+/// place.
+struct Finished {
+    args: Arguments,
+    summary: RunSummary,
+    stats: Option<Stats>,
+}
+
+impl Finished {
+    /// Say what the run wrote.
+    fn report(&self) {
+        let Finished { args, summary, stats } = self;
+        if args.json_summary {
+            inform(args, &summary.to_json().dump());
+        }
+        // A preview speaks for itself.
+        else if !args.preview && !args.quiet {
+            for message in summary.to_messages() {
+                inform(args, &message);
+            }
+        }
+        if let Some(stats) = stats {
+            // The JSON summary is all there is on standard output.
+            if args.json_summary {
+                eprint!("{}", stats.report());
+            }
+            else {
+                inform(args, stats.report().trim_end());
+            }
+        }
+    }
+}
+
+/**
+ * Do everything a run does except moving its files into place and saying
+ * what it wrote: generate the people, writing the files to `staging`.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `staging`: Where the run's files are staged
+ *
+ * # Returns
+ *
+ * - `Ok(finished)`: Everything worked, and the files are ready to commit.
+ * - `Err(failure)`: Something failed, or the run was interrupted, and
+ *   `failure` explains the error. The staged files shouldn't be committed.
+ */
+fn stage_run(args: Arguments, staging: &mut Staging) -> Result<Finished, Failure> {
+    let start = Instant::now();
+    logging::init(logging::log_level(args.verbosity, args.quiet, &getenv(logging::ENV_LOG)));
+    let _span = trace_run(&args, "generate");
+
     result! {
         // The macro requires <- for "assignments" that return Result.
-        let male_first_names <- read_names_file(&args.male_first_names_file);
-        let female_first_names <- read_names_file(&args.female_first_names_file);
-        let last_names <- read_names_file(&args.last_names_file);
-        let people <- make_people(
-            &args,
-            &male_first_names,
-            &female_first_names,
-            &last_names
-        );
+        let _ <- report_seed(&args).map_err(fail(EXIT_OUTPUT));
+        let _ <- dump_config(&args).map_err(fail(EXIT_OUTPUT));
+        let _ <- interrupt::install_handler(EXIT_INTERRUPTED).map_err(fail(EXIT_OUTPUT));
+        let (categories, last_names) <- read_names(&args);
+        let Generated { total, split_files, extras, stats, .. } <- generate(&args, &categories, &last_names, staging);
+        // The files are cut short, so they're thrown away.
+        let _ <- if interrupt::interrupted() {
+            Err(Failure {
+                code: EXIT_INTERRUPTED,
+                msg: format!("Interrupted after {} of {} record(s). No files were written.",
+                             total, args.total),
+            })
+        }
+        else {
+            Ok(())
+        };
+
+        let summary = RunSummary {
+            records: total,
+            format: args.output_format.to_str().to_string(),
+            destination: match &args.postgres {
+                Some(pg) => Destination::Table(pg.table.clone()),
+                None if is_stdout(&args.output_file) => Destination::Stdout,
+                None if is_null(&args.output_file) => Destination::Null,
+                None if !split_files.is_empty() => Destination::Shards(split_files),
+                None => Destination::File(args.output_file.clone()),
+            },
+            extras,
+            seed: args.seed,
+            random_seed: args.random_seed,
+            duration: start.elapsed(),
+        };
+
+        let _ <- write_summary_json(&args, &summary, staging).map_err(fail(EXIT_OUTPUT));
+        Finished { args, summary, stats }
+    }
+}
+
+/**
+ * Move a run's staged files into place.
+ *
+ * # Arguments
+ *
+ * - `staging`: The run's staged files
+ *
+ * # Returns
+ *
+ * - `Ok(())`: The files are in place.
+ * - `Err(failure)`: They couldn't all be moved, so none were, and `failure`
+ *   explains why.
+ */
+fn commit(staging: Staging) -> Result<(), Failure> {
+    let committing = Instant::now();
+    let _span = info_span!("commit").entered();
+    staging.commit().map_err(fail(EXIT_OUTPUT))?;
+    debug!("Moved the output into place in {}.", secs(committing.elapsed()));
+    Ok(())
+}
+
+/**
+ * `dry_run` implements `--dry-run`: check everything a run would check
+ * before generating anyone, and say what it would generate.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * - `Ok(())`: Everything checked out. No result.
+ * - `Err(failure)`: Something failed, and `failure` explains the error.
+ */
+fn dry_run(args: Arguments) -> Result<(), Failure> {
+    logging::init(logging::log_level(args.verbosity, args.quiet, &getenv(logging::ENV_LOG)));
+
+    let (categories, last_names) = read_names(&args)?;
+    ExtraFiles::new(&args, Path::to_path_buf).map_err(fail(EXIT_INPUT))?;
+    make_people(&args, &categories, &last_names).map_err(fail(EXIT_GENERATION))?;
+    for message in plan(&args, &categories, &last_names) {
+        inform(&args, &message);
+    }
+
+    Ok(())
+}
+
+/**
+ * `bench` implements `peoplegen bench`: generate the people into the null
+ * sink, and report how fast they were generated, and formatted.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * - `Ok(())`: Everything worked. No result.
+ * - `Err(failure)`: Something failed, and `failure` explains the error.
+ */
+fn bench(args: Arguments) -> Result<(), Failure> {
+    logging::init(logging::log_level(args.verbosity, args.quiet, &getenv(logging::ENV_LOG)));
+    let _span = trace_run(&args, "bench");
+
+    let (categories, last_names) = read_names(&args)?;
+    let mut staging = Staging::new();
+    let generated = generate(&args, &categories, &last_names, &mut staging)?;
+    commit(staging)?;
+    let report = BenchReport {
+        records: generated.total,
+        format: args.output_format.to_str().to_string(),
+        seed: args.seed,
+        generating: generated.generating,
+        formatting: generated.writing,
+    };
+    for message in report.to_messages() {
+        println!("{}", message);
+    }
+
+    Ok(())
+}
+
+/**
+ * `scenario` implements `peoplegen scenario`: run each step, in order, as
+ * `peoplegen generate` would, staging all their files, and only move them
+ * into place once the last step has worked. A step that fails stops the
+ * scenario, and none of its files are written.
+ *
+ * # Arguments
+ *
+ * - `steps`: The steps' parsed arguments
+ * - `seed`: The scenario's seed, which the steps' seeds are derived from
+ * - `random_seed`: Whether the seed was chosen at random
+ *
+ * # Returns
+ *
+ * - `Ok(())`: Every step worked. No result.
+ * - `Err(failure)`: A step failed, and `failure` explains the error.
+ */
+fn scenario(steps: Vec<Arguments>, seed: u64, random_seed: bool) -> Result<(), Failure> {
+    // There's always a step; the file has to have one.
+    let first = &steps[0];
+    if random_seed && !first.json_summary && !first.quiet {
+        inform(first, &format!("Using random seed {}. Use --seed {} to reproduce this scenario.", seed, seed));
+    }
+
+    let mut staging = Staging::new();
+    let mut finished = Vec::new();
+    for args in steps {
+        if args.dry_run {
+            dry_run(args)?;
+        }
+        else {
+            finished.push(stage_run(args, &mut staging)?);
+        }
+    }
+
+    commit(staging)?;
+    for step in &finished {
+        step.report();
+    }
+
+    Ok(())
+}
+
+/**
+ * Install the `--otlp-endpoint` collector, if there is one, and start the
+ * run's span, which the rest of its spans belong to. The span ends when
+ * it's dropped.
+ */
+fn trace_run(args: &Arguments, command: &'static str) -> EnteredSpan {
+    if let Some(endpoint) = &args.otlp_endpoint {
+        telemetry::init(endpoint);
+    }
+    info_span!("peoplegen", command, format = args.output_format.to_str(), total = args.total, seed = args.seed)
+        .entered()
+}
+
+/**
+ * Read the first names, for each gender category, and the last names.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * - `Ok((categories, last_names))`: The gender categories, with their first
+ *   names, and the last names
+ * - `Err(failure)`: A file couldn't be read, and `failure` explains why.
+ */
+fn read_names(args: &Arguments) -> Result<(Vec<GenderCategory>, WeightedValues), Failure> {
+    let start = Instant::now();
+    let span = info_span!("read_names", first_names = field::Empty, last_names = field::Empty).entered();
+    let categories = gender_categories(args).map_err(fail(EXIT_INPUT))?;
+    let last_names = read_names_file(&args.last_names_file, args.strict).map_err(fail(EXIT_INPUT))?;
+    let first_names: usize = categories.iter().map(|c| c.first_names.len()).sum();
+    span.record("first_names", first_names).record("last_names", last_names.len());
+    info!("Read {} first name(s) and {} last name(s) in {}.",
+          first_names, last_names.len(), secs(start.elapsed()));
+    Ok((categories, last_names))
+}
+
+/**
+ * `fetch` implements `peoplegen fetch-names`: download the names data, write
+ * the names files, and explain how to use them.
+ *
+ * # Arguments
+ *
+ * - `dest`: The directory to write the names files to
+ * - `force`: Whether to download the data even if the files are already there
+ *
+ * # Returns
+ *
+ * - `Ok(())`: Everything worked. No result.
+ * - `Err(msg)`: Something failed, and `msg` explains the error.
+ */
+fn fetch(dest: &Path, force: bool) -> Result<(), String> {
+    let files = fetch_names(dest, force)?;
 
-        let total <- write_people(
-            &args.output_file,
-            args.output_format,
-            args.header_format,
-            args.generate_ids,
-            args.generate_ssns,
-            args.generate_salaries,
-            people
-        );
+    for (path, count) in &files {
+        match count {
+            Some(n) => println!("Wrote {} name(s) to \"{}\".", n, path.display()),
+            None => println!("\"{}\" already exists. Use --force to replace it.", path.display()),
+        }
+    }
 
-        println!("Wrote {} records(s) to {} file \"{}\".",
-                 total, args.output_format.to_str(), args.output_file.display());
-        ()
+    if default_names_dir().as_deref() == Some(dest) {
+        println!("peoplegen will use these files when no names files are specified.");
+    }
+    else {
+        println!("Set these environment variables to use these files by default:");
+        for (var, (path, _)) in [ENV_MALE_FIRST_NAMES_FILE,
+                                 ENV_FEMALE_FIRST_NAMES_FILE,
+                                 ENV_LAST_NAMES_FILE].iter().zip(&files) {
+            println!("export {}=\"{}\"", var, path.display());
+        }
     }
+
+    Ok(())
 }
 
+/**
+ * `validate` implements `peoplegen validate`: check a file, and print what's
+ * wrong with it.
+ *
+ * # Arguments
+ *
+ * - `path`: The file to check
+ * - `options`: What to check it against
+ *
+ * # Returns
+ *
+ * - `Ok(())`: The file is valid.
+ * - `Err(failure)`: The file couldn't be read, or isn't valid, and
+ *   `failure` says how many violations there were.
+ */
+fn validate(path: &Path, options: &ValidateOptions) -> Result<(), Failure> {
+    let validation = validate_file(path, options).map_err(fail(EXIT_INPUT))?;
+
+    for violation in &validation.violations {
+        println!("{}", violation);
+    }
+    let shown = validation.violations.len() as u64;
+    if validation.total_violations > shown {
+        println!("... and {} more.", validation.total_violations - shown);
+    }
+
+    if validation.is_valid() {
+        println!("\"{}\" is valid: {} record(s) checked.", path.display(), validation.records);
+        Ok(())
+    }
+    else {
+        Err(Failure {
+            code: EXIT_INVALID,
+            msg: format!("\"{}\" has {} violation(s) in {} record(s).",
+                         path.display(), validation.total_violations, validation.records),
+        })
+    }
+}
 
+/**
+ * `doctor` implements `peoplegen doctor`: check what a run needs from its
+ * environment, and print what was found.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments, for the run
+ *
+ * # Returns
+ *
+ * - `Ok(())`: Everything checked out, though there may have been warnings.
+ * - `Err(failure)`: There were problems, and `failure` says how many.
+ */
+fn doctor(args: &Arguments) -> Result<(), Failure> {
+    let diagnostics = diagnose(args);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    match diagnostics.iter().filter(|d| d.status == Status::Problem).count() {
+        0 => {
+            println!("Ready to run.");
+            Ok(())
+        },
+        problems => Err(Failure {
+            code: EXIT_INVALID,
+            msg: format!("Found {} problem(s). Fix them before the run.", problems),
+        }),
+    }
+}
+
+/// What `generate()` wrote.
+struct Generated {
+    /// How many people were written
+    total: u64,
+    /// The numbered files, if the output was split (`--shards`,
+    /// `--max-rows-per-file`, `--max-bytes-per-file`)
+    split_files: Vec<PathBuf>,
+    /// The extra files
+    extras: Vec<ExtraOutput>,
+    /// The `--stats` statistics
+    stats: Option<Stats>,
+    /// The time spent generating the people
+    generating: Duration,
+    /// The time spent writing them
+    writing: Duration,
+}
+
+/**
+ * Generate the people and write them out, along with any extra per-person
+ * files (rendered documents, avatars, barcodes) requested on the command
+ * line. Everything is written in a single pass, as the people are generated.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `categories`: The gender categories, with their first names
+ * - `last_names`: The list of last names
+ * - `staging`: Where the files are staged, to be moved into place once the
+ *   run is over
+ *
+ * # Returns
+ *
+ * - `Ok(generated)`: Everything worked, and `generated` says what was
+ *   written.
+ * - `Err(failure)`: Something failed, and `failure` explains the error.
+ */
+fn generate(
+    args: &Arguments,
+    categories: &[GenderCategory],
+    last_names: &WeightedValues,
+    staging: &mut Staging,
+) -> Result<Generated, Failure> {
+    let span = info_span!("generate", records = field::Empty, generating_secs = field::Empty,
+                          writing_secs = field::Empty).entered();
+    // The files only replace the old ones once everything's written.
+    let mut extras = ExtraFiles::new(args, |dir| staging.stage_dir(dir)).map_err(fail(EXIT_INPUT))?;
+    let output_files: Vec<PathBuf> = match (&args.postgres, &args.shards) {
+        (None, Some(shards)) => shard_paths(&args.output_file, shards.count)
+            .iter()
+            .map(|path| staging.stage(path))
+            .collect(),
+        // The files are staged as they're started.
+        _ if args.rollover.is_some() => Vec::new(),
+        (None, None) if !is_stdout(&args.output_file) && !is_null(&args.output_file) => {
+            vec![staging.stage(&args.output_file)]
+        },
+        _ => vec![args.output_file.clone()],
+    };
+    // The writers stop at the first error, whether it came from generating a
+    // person or from writing one, so note which it was.
+    let failed = Cell::new(EXIT_OUTPUT);
+    let mut tally = Tally::new(&args.assertions);
+    let mut aggregates = args.aggregates_dir.as_ref().map(|_| Aggregates::new(args.generate_salaries));
+    let mut stats = args.stats.then(|| Stats::new(args.generate_salaries));
+    let mut shifts = match (&args.shifts_file, &args.shifts) {
+        (Some(path), Some(options)) => Some(
+            ShiftWriter::new(options, &staging.stage(path), args.seed).map_err(fail(EXIT_OUTPUT))?
+        ),
+        _ => None,
+    };
+    let mut benefits = match (&args.benefits_dir, &args.benefits) {
+        (Some(dir), Some(options)) => {
+            fs::create_dir_all(dir).map_err(|e| Failure {
+                code: EXIT_OUTPUT,
+                msg: format!("Can't create \"{}\": {}", dir.display(), e),
+            })?;
+            let elections = staging.stage(&dir.join(ELECTIONS_FILE));
+            let dependents = staging.stage(&dir.join(DEPENDENTS_FILE));
+            Some(BenefitsWriter::new(options, &elections, &dependents, args.seed).map_err(fail(EXIT_OUTPUT))?)
+        },
+        _ => None,
+    };
+    let mut w2s = match (&args.w2_file, &args.w2) {
+        (Some(path), Some(options)) => Some(
+            W2Writer::new(options, &staging.stage(path), args.seed).map_err(fail(EXIT_OUTPUT))?
+        ),
+        _ => None,
+    };
+    let mut transactions = match (&args.transactions_file, &args.transactions) {
+        (Some(path), Some(options)) => Some(
+            TransactionWriter::new(options, &staging.stage(path), args.seed).map_err(fail(EXIT_OUTPUT))?
+        ),
+        _ => None,
+    };
+    let mut immunizations = match (&args.immunizations_file, &args.immunizations) {
+        (Some(path), Some(options)) => Some(
+            ImmunizationWriter::new(options, &staging.stage(path), is_fhir_path(path), args.seed)
+                .map_err(fail(EXIT_OUTPUT))?
+        ),
+        _ => None,
+    };
+    // With an output file, a --preview is printed as soon as there are enough
+    // people for it, while the rest are written.
+    let mut preview = args.preview_rows.map(|n| Vec::with_capacity(n as usize));
+    // Generating and writing take turns, person by person, so the time spent
+    // generating is added up as it goes, and the rest is writing.
+    let start = Instant::now();
+    let generating = Cell::new(Duration::ZERO);
+    let mut generated = make_people(args, categories, last_names)
+        .map_err(fail(EXIT_GENERATION))?
+        .results();
+    let people = std::iter::from_fn(|| {
+            let started = Instant::now();
+            let p = generated.next();
+            generating.set(generating.get() + started.elapsed());
+            p
+        })
+        .map(|p| {
+            let p = p.inspect_err(|_| failed.set(EXIT_GENERATION))?;
+            extras.write(&p, p.id)?;
+            tally.observe(|key| ruled_value(&p, p.id, args, key));
+            if let Some(aggregates) = &mut aggregates {
+                aggregates.observe(|key| ruled_value(&p, p.id, args, key));
+            }
+            if let Some(stats) = &mut stats {
+                stats.observe(|key| ruled_value(&p, p.id, args, key));
+            }
+            if let Some(shifts) = &mut shifts {
+                shifts.write(p.id)?;
+            }
+            // A salary a rule replaced with something that isn't one gets no
+            // statements.
+            if let (Some(w2s), Ok(salary)) = (&mut w2s, ruled_value(&p, p.id, args, "salary").parse::<u64>()) {
+                w2s.write(p.id, salary)?;
+            }
+            if let Some(transactions) = &mut transactions {
+                transactions.write(p.id)?;
+            }
+            if let Some(immunizations) = &mut immunizations {
+                immunizations.write(p.id, p.birth_date)?;
+            }
+            if let Some(benefits) = &mut benefits {
+                benefits.write(p.id, p.birth_date)?;
+            }
+            if let Some(rows) = &mut preview {
+                rows.push(p.clone());
+                if Some(rows.len() as u64) == args.preview_rows {
+                    print_preview(args, &preview.take().unwrap_or_default())?;
+                }
+            }
+            Ok(p)
+        });
+    // On a signal, files are finished normally, with the people so far (and
+    // then thrown away, unless they're on standard output), but a database
+    // load is rolled back.
+    let people = until_interrupted(people, args.postgres.is_some());
+
+    // The numbered files, when the output is split.
+    let mut split_files = Vec::new();
+    let written = match (&args.shards, &args.rollover) {
+        (Some(shards), _) => {
+            split_files = shard_paths(&args.output_file, shards.count);
+            write_shards(shards, &output_files, args.total, people, |path, people| {
+                write_people(args, path, people).map_err(|e| e.to_string())
+            })
+        },
+        (_, Some(rollover)) => write_rolling(rollover, &args.output_file, people, |path, people| {
+            write_people(args, &staging.stage(path), people).map_err(|e| e.to_string())
+        }).map(|(total, paths)| {
+            split_files = paths;
+            total
+        }),
+        (None, None) => write_people(args, &output_files[0], people).map_err(|e| e.to_string()),
+    };
+    let total = written.map_err(|msg| match &args.postgres {
+        Some(pg) if interrupt::interrupted() => Failure {
+            code: EXIT_INTERRUPTED,
+            msg: format!("Interrupted. The load was rolled back, so table \"{}\" is unchanged.",
+                         pg.table),
+        },
+        _ => Failure { code: failed.get(), msg },
+    })?;
+    // There were fewer people than the preview asked for.
+    if let Some(rows) = preview.filter(|rows| !rows.is_empty()) {
+        print_preview(args, &rows).map_err(fail(EXIT_OUTPUT))?;
+    }
+    let writing = start.elapsed().saturating_sub(generating.get());
+    info!("Generated {} people in {}.", total, secs(generating.get()));
+    info!("Wrote {} records(s) in {}.", total, secs(writing));
+    span.record("records", total)
+        .record("generating_secs", generating.get().as_secs_f64())
+        .record("writing_secs", writing.as_secs_f64());
+    let finishing = info_span!("finish_extras").entered();
+    let mut outputs: Vec<ExtraOutput> = Vec::new();
+    let extra = |kind, dir: &Path| ExtraOutput {
+        kind,
+        path: dir.to_path_buf(),
+        count: extras.total,
+    };
+
+    if let (Some(_), Some(dir)) = (&args.template_file, &args.documents_dir) {
+        outputs.push(extra("documents", dir));
+    }
+
+    if let Some(dir) = &args.avatars_dir {
+        outputs.push(extra("avatars", dir));
+    }
+
+    if let Some(dir) = &args.barcodes_dir {
+        outputs.push(extra("barcodes", dir));
+    }
+
+    if let (Some(aggregates), Some(dir)) = (&aggregates, &args.aggregates_dir) {
+        fs::create_dir_all(dir).map_err(|e| Failure {
+            code: EXIT_OUTPUT,
+            msg: format!("Can't create \"{}\": {}", dir.display(), e),
+        })?;
+        let tables = aggregates.write(|name| staging.stage(&dir.join(name))).map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "aggregates", path: dir.clone(), count: tables as u64 });
+    }
+
+    if let (Some(shifts), Some(path)) = (shifts, &args.shifts_file) {
+        let count = shifts.finish().map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "shifts", path: path.clone(), count });
+    }
+
+    if let (Some(benefits), Some(dir)) = (benefits, &args.benefits_dir) {
+        let count = benefits.finish().map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "benefits", path: dir.clone(), count });
+    }
+
+    if let (Some(immunizations), Some(path)) = (immunizations, &args.immunizations_file) {
+        let count = immunizations.finish().map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "immunizations", path: path.clone(), count });
+    }
+
+    if let (Some(w2s), Some(path)) = (w2s, &args.w2_file) {
+        let count = w2s.finish().map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "w2", path: path.clone(), count });
+    }
+
+    if let (Some(transactions), Some(path)) = (transactions, &args.transactions_file) {
+        let count = transactions.finish().map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "transactions", path: path.clone(), count });
+    }
+
+    // The SSN report covers the whole run, so it'd be wrong, now.
+    if let Some(path) = args.ssn_collisions_file.as_ref().filter(|_| !interrupt::interrupted()) {
+        let shared = write_ssn_collisions(args, &staging.stage(path)).map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "ssn_collisions", path: path.clone(), count: shared });
+    }
+
+    if let Some(path) = &args.proto_file {
+        write_proto_definition(args, &staging.stage(path)).map_err(fail(EXIT_OUTPUT))?;
+        outputs.push(ExtraOutput { kind: "proto", path: path.clone(), count: 1 });
+    }
+
+    drop(finishing);
+
+    // The assertions are about the whole run, so a partial one can't fail
+    // them. Failing them leaves the old files alone, as any failure does.
+    if !interrupt::interrupted() {
+        tally.check().map_err(fail(EXIT_ASSERTION))?;
+    }
+
+    Ok(Generated { total, split_files, extras: outputs, stats, generating: generating.get(), writing })
+}
+
+/**
+ * If the random number generator seed was chosen at random, print it, so
+ * the run can be reproduced. (With `--json-summary`, it's in the summary.)
+ * It's logged, too, with `-vv`, so a log always says how to reproduce it.
+ */
+fn report_seed(args: &Arguments) -> Result<(), String> {
+    debug!("Seed {}.", args.seed);
+    if args.random_seed && !args.json_summary && !args.quiet {
+        inform(args, &format!("Using random seed {}. Use --seed {} to reproduce this run.",
+                              args.seed, args.seed));
+    }
+
+    Ok(())
+}
+
+/**
+ * With `--dump-config`, write the effective configuration, before anything
+ * else, so even a failed run can be reproduced.
+ */
+fn dump_config(args: &Arguments) -> Result<(), String> {
+    match &args.config_dump {
+        Some(dump) => fs::write(&dump.path, &dump.toml)
+            .map_err(|e| format!("Can't write to \"{}\": {}", dump.path.display(), e)),
+        None => Ok(()),
+    }
+}
+
+/**
+ * With `--summary-json`, write the run's summary, and the settings of each
+ * column, as JSON, to `staging`.
+ */
+fn write_summary_json(args: &Arguments, summary: &RunSummary, staging: &mut Staging) -> Result<(), String> {
+    match &args.summary_json_file {
+        Some(path) => {
+            let mut report = summary.to_json();
+            report["columns"] = column_settings(args);
+            let staged = staging.stage(path);
+            fs::write(&staged, report.pretty(2) + "\n")
+                .map_err(|e| format!("Can't write to \"{}\": {}", staged.display(), e))
+        },
+        None => Ok(()),
+    }
+}
+
+/**
+ * Print the `--preview` of a run that writes a file, too: on standard output,
+ * unless the people are being written there, as with `inform()`.
+ */
+fn print_preview(args: &Arguments, people: &[Person]) -> Result<(), String> {
+    if is_stdout(&args.output_file) {
+        write_preview(io::stderr(), args, people)
+    }
+    else {
+        write_preview(io::stdout(), args, people)
+    }
+}
+
+/**
+ * Print a message about the run: on standard output, unless the people are
+ * being written there, in which case it goes to standard error, so it
+ * doesn't end up in the data.
+ */
+fn inform(args: &Arguments, message: &str) {
+    if is_stdout(&args.output_file) {
+        eprintln!("{}", message);
+    }
+    else {
+        println!("{}", message);
+    }
+}
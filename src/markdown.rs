@@ -0,0 +1,100 @@
+//! Markdown output: a GitHub-flavored Markdown table, for pasting samples
+//! into design docs, issues and pull requests. Numeric columns are
+//! right-aligned. The table is written one row at a time, so the columns
+//! aren't padded to line up in the source; they line up once the Markdown is
+//! rendered.
+
+use std::io::{self, Write};
+
+/**
+ * Writes a Markdown table.
+ */
+pub struct MarkdownWriter<W: Write> {
+    w: W,
+    line: String,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    /**
+     * Create a new writer, and write the header row.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the table
+     * - `headers`: The column headers, in order
+     * - `numeric`: Whether each column is numeric, and so right-aligned
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, ready for rows
+     * - `Err(e)`: The header couldn't be written.
+     */
+    pub fn new(w: W, headers: &[&String], numeric: &[bool]) -> io::Result<MarkdownWriter<W>> {
+        let mut mw = MarkdownWriter { w, line: String::new() };
+        mw.write_row(headers)?;
+
+        let separator: Vec<&str> = numeric.iter().map(|n| if *n { "---:" } else { "---" }).collect();
+        writeln!(mw.w, "| {} |", separator.join(" | "))?;
+        Ok(mw)
+    }
+
+    /**
+     * Write a row. The values must be in column order.
+     */
+    pub fn write_row<S: AsRef<str>>(&mut self, values: &[S]) -> io::Result<()> {
+        self.line.clear();
+        self.line.push('|');
+        for v in values {
+            self.line.push(' ');
+            escape_into(&mut self.line, v.as_ref());
+            self.line.push_str(" |");
+        }
+        self.line.push('\n');
+        self.w.write_all(self.line.as_bytes())
+    }
+
+    /**
+     * Flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The flush failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Append a cell value: pipes and backslashes are escaped, and line breaks
+/// become `<br>`, since a cell can't span lines.
+fn escape_into(line: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '|' => line.push_str("\\|"),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("<br>"),
+            '\r' => (),
+            _ => line.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::*;
+
+    #[test]
+    fn table() {
+        let headers = [String::from("ID"), String::from("Last Name")];
+        let mut w = MarkdownWriter::new(Vec::new(), &[&headers[0], &headers[1]], &[true, false]).unwrap();
+        w.write_row(&["1", "Howard"]).unwrap();
+        w.write_row(&["2", "a|b\nc"]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "| ID | Last Name |\n| ---: | --- |\n| 1 | Howard |\n| 2 | a\\|b<br>c |\n"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Row-level metadata ("system") columns, of the sort found in real
+//! operational extracts: when each record was created and last updated, which
+//! system it came from, and a unique record identifier.
+//!
+//! Metadata is generated from its own random number generator, derived from
+//! the run's seed, so turning metadata on or off doesn't change any of the
+//! other generated values.
+
+use chrono::naive::{NaiveDate, NaiveDateTime};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Mixed into the run's seed to seed the metadata random number generator.
+const METADATA_SEED_SALT: u64 = 0x6d65_7461_6461_7461;
+
+/// The default `--source-systems` list.
+pub const DEFAULT_SOURCE_SYSTEMS: &str = "crm:5,erp:3,web:2";
+
+/// The settings for metadata generation, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataOptions {
+    /// The first day on which records can have been created
+    pub created_min: NaiveDate,
+    /// The last day on which records can have been created (or updated)
+    pub created_max: NaiveDate,
+    /// The source system names, with their relative weights
+    pub source_systems: Vec<(String, u32)>,
+}
+
+/// The metadata for a single record. With serde, its fields are named as with
+/// the snake case header format, and any other header format's names are
+/// accepted, too (see `Person`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordMetadata {
+    #[serde(with = "timestamp", alias = "createdAt", alias = "Created At", alias = "whenCreated")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "timestamp", alias = "updatedAt", alias = "Updated At", alias = "whenChanged")]
+    pub updated_at: NaiveDateTime,
+    #[serde(alias = "sourceSystem", alias = "Source System")]
+    pub source_system: String,
+    #[serde(alias = "recordUuid", alias = "Record UUID", alias = "recordUUID")]
+    pub record_uuid: String,
+}
+
+/// Generates `RecordMetadata` values.
+pub struct MetadataGenerator {
+    rng: ChaCha8Rng,
+    window_start: i64,
+    window_end: i64,
+    source_systems: Vec<String>,
+    weights: WeightedIndex<u32>,
+}
+
+impl MetadataGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The metadata settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &MetadataOptions, seed: u64) -> Result<MetadataGenerator, String> {
+        if options.created_min > options.created_max {
+            return Err(format!(
+                "Minimum creation date {} is after maximum creation date {}.",
+                options.created_min, options.created_max
+            ));
+        }
+
+        let weights = WeightedIndex::new(options.source_systems.iter().map(|(_, w)| *w))
+            .map_err(|e| format!("Bad source system weights: {}", e))?;
+
+        Ok(MetadataGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ METADATA_SEED_SALT),
+            window_start: options.created_min.and_hms(0, 0, 0).timestamp(),
+            window_end: options.created_max.and_hms(23, 59, 59).timestamp(),
+            source_systems: options.source_systems.iter().map(|(s, _)| s.clone()).collect(),
+            weights,
+        })
+    }
+
+    /// Generate the metadata for the next record.
+    pub fn next_metadata(&mut self) -> RecordMetadata {
+        let created = self.rng.gen_range(self.window_start..=self.window_end);
+        let updated = self.rng.gen_range(created..=self.window_end);
+        let source = &self.source_systems[self.weights.sample(&mut self.rng)];
+        let uuid: [u8; 16] = self.rng.gen();
+
+        RecordMetadata {
+            created_at: NaiveDateTime::from_timestamp(created, 0),
+            updated_at: NaiveDateTime::from_timestamp(updated, 0),
+            source_system: source.clone(),
+            record_uuid: uuid_v4(uuid),
+        }
+    }
+}
+
+/**
+ * Parse a `--source-systems` list, of the form `name:weight,name:weight,...`.
+ * A name without a weight gets a weight of 1.
+ *
+ * # Arguments
+ *
+ * - `s`: The list
+ *
+ * # Returns
+ *
+ * - `Ok(systems)`: The names and weights
+ * - `Err(msg)`: The list is invalid; `msg` explains why.
+ */
+pub fn parse_source_systems(s: &str) -> Result<Vec<(String, u32)>, String> {
+    s.split(',')
+        .map(|entry| {
+            let (name, weight) = match entry.split_once(':') {
+                Some((name, w)) => {
+                    let weight = w.trim().parse::<u32>().map_err(|_| {
+                        format!("Bad weight \"{}\" for source system \"{}\".", w, name)
+                    })?;
+                    (name.trim(), weight)
+                },
+                None => (entry.trim(), 1),
+            };
+
+            if name.is_empty() {
+                Err(format!("Empty source system name in \"{s}\"."))
+            }
+            else {
+                Ok((name.to_string(), weight))
+            }
+        })
+        .collect()
+}
+
+/// Format a timestamp the way metadata columns are written (ISO 8601, UTC).
+pub fn timestamp_str(t: &NaiveDateTime) -> String {
+    t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Timestamps for serde, as `timestamp_str()` writes them, for
+/// `#[serde(with = "crate::metadata::timestamp")]`.
+pub mod timestamp {
+    use chrono::naive::NaiveDateTime;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(t: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::timestamp_str(t))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%SZ").map_err(de::Error::custom)
+    }
+}
+
+/// Turn 16 random bytes into a version 4 (random) UUID string.
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}",
+            &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::*;
+    use chrono::Datelike;
+
+    fn options() -> MetadataOptions {
+        MetadataOptions {
+            created_min: NaiveDate::from_ymd(2020, 1, 1),
+            created_max: NaiveDate::from_ymd(2020, 12, 31),
+            source_systems: vec![(String::from("crm"), 1), (String::from("erp"), 0)],
+        }
+    }
+
+    #[test]
+    fn metadata_is_consistent() {
+        let mut g = MetadataGenerator::new(&options(), 42).unwrap();
+        for _ in 0..100 {
+            let m = g.next_metadata();
+            assert!(m.updated_at >= m.created_at);
+            assert_eq!(m.created_at.date().year(), 2020);
+            assert_eq!(m.updated_at.date().year(), 2020);
+            // Zero-weight systems are never chosen.
+            assert_eq!(m.source_system, "crm");
+            assert_eq!(m.record_uuid.len(), 36);
+            assert_eq!(&m.record_uuid[14..15], "4");
+        }
+    }
+
+    #[test]
+    fn bad_window() {
+        let mut o = options();
+        o.created_min = NaiveDate::from_ymd(2021, 1, 1);
+        assert!(MetadataGenerator::new(&o, 42).is_err());
+    }
+
+    #[test]
+    fn source_systems() {
+        assert_eq!(
+            parse_source_systems("crm:5, erp"),
+            Ok(vec![(String::from("crm"), 5), (String::from("erp"), 1)])
+        );
+        assert!(parse_source_systems("crm:x").is_err());
+        assert!(parse_source_systems("crm:1,").is_err());
+    }
+
+    #[test]
+    fn uuids() {
+        assert_eq!(uuid_v4([0xff; 16]), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+        assert_eq!(uuid_v4([0; 16]), "00000000-0000-4000-8000-000000000000");
+    }
+}
@@ -19,11 +19,17 @@ use funty::Integral;
  * # Returns
  *
  * `Ok(n)` - the parsed integral result (`n`)
- * `Err(msg)` - not a valid number, with an appropriate error message.
+ * `Err(msg)` - not a valid number (or radix), with an appropriate error
+ * message.
  */
 pub fn parse_int<T>(s: &String, radix: u32) -> Result<T, String>
     where T: Integral
 {
+    // from_str_radix() panics on a radix it doesn't support.
+    if !(2..=36).contains(&radix) {
+        return Err(format!("{radix} is not a valid radix (must be 2-36)"));
+    }
+
     T::from_str_radix(s, radix)
         .map_err(|_| format!("\"{}\" is not a valid base-{} number for this type",
                  s, radix))
@@ -31,9 +37,58 @@ pub fn parse_int<T>(s: &String, radix: u32) -> Result<T, String>
     //s.parse::<T>().map_err(|_| format!("\"{s}\" is an invalid number"))
 }
 
+/**
+ * Parse a count, such as a number of people or a salary, into an integer.
+ * Counts are decimal, and can be written more readably than `parse_int()`
+ * allows: with underscores between the digits (1_000_000), and with a "k"
+ * (thousands), "M" (millions) or "B" (billions) suffix, in either case. A
+ * suffixed count can have a fraction (2.5M), as long as it comes out whole.
+ *
+ * # Arguments
+ *
+ * `s` - the string to convert
+ *
+ * # Returns
+ *
+ * `Ok(n)` - the parsed integral result (`n`)
+ * `Err(msg)` - not a valid count, or too big for the type, with an
+ * appropriate error message.
+ */
+pub fn parse_count<T>(s: &str) -> Result<T, String>
+    where T: Integral
+{
+    let err = || format!("\"{s}\" is not a valid count (e.g., 1000, 1_000, 10k, 2.5M or 1B)");
+    let number = s.trim();
+    let (number, zeros) = match number.chars().last() {
+        Some('k' | 'K') => (&number[..number.len() - 1], 3),
+        Some('m' | 'M') => (&number[..number.len() - 1], 6),
+        Some('b' | 'B') => (&number[..number.len() - 1], 9),
+        _ => (number, 0),
+    };
+    // Underscores go between digits, not at either end or two together.
+    let digits = |part: &str| match part.split('_').all(|d| !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit())) {
+        true => Ok(part.replace('_', "")),
+        false => Err(err()),
+    };
+    let (whole, fraction) = match number.split_once('.') {
+        Some((whole, fraction)) => (digits(whole)?, digits(fraction)?),
+        None => (digits(number)?, String::new()),
+    };
+    if fraction.len() > zeros {
+        return Err(err());
+    }
+
+    let n = format!("{whole}{fraction}")
+        .parse::<u128>()
+        .ok()
+        .and_then(|n| n.checked_mul(10u128.pow((zeros - fraction.len()) as u32)))
+        .ok_or_else(err)?;
+    T::try_from(n).map_err(|_| format!("\"{s}\" is too large a number for this type"))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::numlib::parse_int;
+    use crate::numlib::{parse_count, parse_int};
 
     #[test]
     fn parse_u32() {
@@ -65,4 +120,33 @@ mod tests {
         assert_eq!(parse_int::<u32>(&String::from("00a0"), 16), Ok(160));
         assert!(parse_int::<u32>(&String::from("x00a0"), 16).is_err());
     }
+
+    #[test]
+    fn bad_radix() {
+        assert!(parse_int::<u32>(&String::from("10"), 1).is_err());
+        assert!(parse_int::<u32>(&String::from("10"), 37).is_err());
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(parse_count::<u64>("1000"), Ok(1000));
+        assert_eq!(parse_count::<u64>("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse_count::<u64>("10k"), Ok(10_000));
+        assert_eq!(parse_count::<u64>("10M"), Ok(10_000_000));
+        assert_eq!(parse_count::<u64>("2.5M"), Ok(2_500_000));
+        assert_eq!(parse_count::<u64>("1B"), Ok(1_000_000_000));
+        assert_eq!(parse_count::<u32>("58.26K"), Ok(58_260));
+        assert_eq!(parse_count::<u64>("1_500.250_5m"), Ok(1_500_250_500));
+        assert_eq!(parse_count::<u64>("0"), Ok(0));
+    }
+
+    #[test]
+    fn bad_counts() {
+        for s in ["", "k", "-1", "1.5", "1.2345k", "1__000", "_1000", "1000_", "1_.5k", "1.k", "10X", "1e6"] {
+            assert!(parse_count::<u64>(s).is_err(), "{s}");
+        }
+        assert!(parse_count::<u32>("5B").is_err());
+        assert!(parse_count::<u64>("100000000000B").is_err());
+        assert_eq!(parse_count::<u64>("18446744073709551615"), Ok(u64::MAX));
+    }
 }
@@ -0,0 +1,239 @@
+//! Output files. When the output file's name ends in `.gz`, `.zst` or
+//! `.bz2` (e.g., `people.jsonl.gz`), the output is compressed as it's
+//! written, and the rest of the name picks the format, as usual. An output
+//! file named `-` is standard output, and `/dev/null` (or `--output null`) is
+//! nowhere: the people are formatted as usual, and thrown away.
+
+use bzip2::write::BzEncoder;
+use flate2::write::GzEncoder;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The output file name that means standard output.
+pub const STDOUT_PATH: &str = "-";
+
+/// The output file name that means nowhere. It's never opened, so it means
+/// the same thing on every platform.
+pub const NULL_PATH: &str = "/dev/null";
+
+thread_local! {
+    /// How many bytes have been written to output files on this thread,
+    /// before compression. Writers run on one thread each, so this is how
+    /// `--max-bytes-per-file` keeps track, without every writer having to.
+    static BYTES_WRITTEN: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The compression methods, and the extensions that select them.
+pub const COMPRESSION_EXTENSIONS: [&str; 3] = ["gz", "zst", "bz2"];
+
+/// How to compress an output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /**
+     * Get the compression method for a file extension.
+     *
+     * # Arguments
+     *
+     * - `extension`: The extension, without the dot
+     *
+     * # Returns
+     *
+     * - `Some(compression)`: The method
+     * - `None`: The extension isn't a compressed file's.
+     */
+    pub fn from_extension(extension: &str) -> Option<Compression> {
+        match extension {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Split the compression extension off of an output file name.
+ *
+ * # Arguments
+ *
+ * - `path`: The output file
+ *
+ * # Returns
+ *
+ * The file name without the compression extension, whose extension picks
+ * the format (e.g., "people.csv" for "people.csv.gz"), and the compression
+ * method, if any.
+ */
+pub fn split_compression(path: &Path) -> (PathBuf, Option<Compression>) {
+    match path.extension().and_then(|e| e.to_str()).and_then(Compression::from_extension) {
+        Some(compression) => (path.with_extension(""), Some(compression)),
+        None => (path.to_path_buf(), None),
+    }
+}
+
+/// Whether an output file name means standard output.
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT_PATH)
+}
+
+/// Whether an output file name means nowhere.
+pub fn is_null(path: &Path) -> bool {
+    path == Path::new(NULL_PATH)
+}
+
+/// How many bytes have been written to output files on this thread, before
+/// compression. Writers buffer what they write, so the last few kilobytes of
+/// a file may not have been counted yet.
+pub fn bytes_written() -> u64 {
+    BYTES_WRITTEN.with(Cell::get)
+}
+
+/**
+ * An output file, which compresses what's written to it, if asked to.
+ * `finish()` must be called once everything's written, to write the end of
+ * the compressed stream.
+ */
+pub enum OutputFile {
+    Stdout(io::Stdout),
+    Null(io::Sink),
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+    Bzip2(BzEncoder<File>),
+}
+
+impl OutputFile {
+    /**
+     * Create (or overwrite) an output file.
+     *
+     * # Arguments
+     *
+     * - `path`: The file, `STDOUT_PATH` or `NULL_PATH`
+     * - `compression`: How to compress it, if at all
+     *
+     * # Returns
+     *
+     * - `Ok(file)`: The file, ready for writing
+     * - `Err(e)`: The file couldn't be created.
+     */
+    pub fn create(path: &Path, compression: Option<Compression>) -> io::Result<OutputFile> {
+        if is_stdout(path) {
+            return Ok(OutputFile::Stdout(io::stdout()));
+        }
+        if is_null(path) {
+            return Ok(OutputFile::Null(io::sink()));
+        }
+        let file = File::create(path)?;
+
+        Ok(match compression {
+            None => OutputFile::Plain(file),
+            Some(Compression::Gzip) => OutputFile::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Some(Compression::Zstd) => OutputFile::Zstd(zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)?),
+            Some(Compression::Bzip2) => OutputFile::Bzip2(BzEncoder::new(file, bzip2::Compression::default())),
+        })
+    }
+
+    /**
+     * Finish the compressed stream, if any, and flush the file.
+     */
+    pub fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            OutputFile::Stdout(mut w) => return w.flush(),
+            OutputFile::Null(_) => return Ok(()),
+            OutputFile::Plain(file) => file,
+            OutputFile::Gzip(w) => w.finish()?,
+            OutputFile::Zstd(w) => w.finish()?,
+            OutputFile::Bzip2(w) => w.finish()?,
+        };
+        file.flush()
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match self {
+            OutputFile::Stdout(w) => w.write(buf).map_err(stop_if_closed),
+            OutputFile::Null(w) => w.write(buf),
+            OutputFile::Plain(w) => w.write(buf),
+            OutputFile::Gzip(w) => w.write(buf),
+            OutputFile::Zstd(w) => w.write(buf),
+            OutputFile::Bzip2(w) => w.write(buf),
+        }?;
+        BYTES_WRITTEN.with(|b| b.set(b.get() + n as u64));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Stdout(w) => w.flush().map_err(stop_if_closed),
+            OutputFile::Null(w) => w.flush(),
+            OutputFile::Plain(w) => w.flush(),
+            OutputFile::Gzip(w) => w.flush(),
+            OutputFile::Zstd(w) => w.flush(),
+            OutputFile::Bzip2(w) => w.flush(),
+        }
+    }
+}
+
+/// When whatever's reading standard output stops (e.g., `head` has all the
+/// lines it wants), there's nobody left to write to, or to tell. Stop
+/// quietly, as a program killed by `SIGPIPE` would; Rust ignores the signal,
+/// so writes fail instead.
+fn stop_if_closed(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        process::exit(0);
+    }
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::*;
+    use std::fs;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn splitting() {
+        assert_eq!(
+            split_compression(Path::new("out/people.jsonl.zst")),
+            (PathBuf::from("out/people.jsonl"), Some(Compression::Zstd))
+        );
+        assert_eq!(split_compression(Path::new("people.csv")), (PathBuf::from("people.csv"), None));
+        assert_eq!(split_compression(Path::new("people.gz")), (PathBuf::from("people"), Some(Compression::Gzip)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new().unwrap();
+        let text = "first_name,last_name\nMoe,Howard\n".repeat(100);
+
+        for extension in COMPRESSION_EXTENSIONS {
+            let path = dir.path().join(format!("people.csv.{extension}"));
+            let compression = Compression::from_extension(extension);
+            let mut w = OutputFile::create(&path, compression).unwrap();
+            w.write_all(text.as_bytes()).unwrap();
+            w.finish().unwrap();
+
+            let compressed = fs::read(&path).unwrap();
+            assert!(compressed.len() < text.len());
+
+            let mut decompressed = String::new();
+            match compression.unwrap() {
+                Compression::Gzip => flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut decompressed),
+                Compression::Zstd => zstd::Decoder::new(&compressed[..]).unwrap().read_to_string(&mut decompressed),
+                Compression::Bzip2 => bzip2::read::BzDecoder::new(&compressed[..]).read_to_string(&mut decompressed),
+            }.unwrap();
+            assert_eq!(decompressed, text);
+        }
+    }
+}
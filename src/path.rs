@@ -1,35 +1,35 @@
 //! Some path-related helpers.
 
-use std::path::PathBuf;
+use std::path::Path;
 use std::ffi::OsStr;
 
 /**
- * Simple utility function to take a reference to a `PathBuf` and return
+ * Simple utility function to take a reference to a `Path` and return
  * the pathname as a normal string (i.e., not an `OsString` or an `OsStr`),
  * usually for printing.
  *
  * # Arguments:
  *
- * - `path`: The `PathBuf`
+ * - `path`: The `Path`
  *
  * # Returns
  *
  * The pathname as a string, or "" if the pathname could not be decoded (which,
  * frankly, should rarely, if ever, happen)
 */
-pub fn path_str(path: &PathBuf) -> &str {
+pub fn path_str(path: &Path) -> &str {
     path.as_os_str().to_str().unwrap_or("")
 }
 
 /**
  * Convenience function that determines whether a path is empty.
  */
-pub fn path_is_empty(p: &PathBuf) -> bool {
-    p.as_path().as_os_str() == ""
+pub fn path_is_empty(p: &Path) -> bool {
+    p.as_os_str() == ""
 }
 
 /**
- * Convenience function to get a file extension. Unlike `PathBuf::extension()`,
+ * Convenience function to get a file extension. Unlike `Path::extension()`,
  * this function returns a `&str` (rather than an `OsStr`).
  *
  * # Arguments
@@ -41,7 +41,7 @@ pub fn path_is_empty(p: &PathBuf) -> bool {
  * - `Some(extension)` if there's a file extension
  * - `None` if there's no file extension
  */
-pub fn file_extension(path: &PathBuf) -> Option<&str> {
+pub fn file_extension(path: &Path) -> Option<&str> {
     path.extension().and_then(OsStr::to_str)
 }
 
@@ -0,0 +1,183 @@
+//! A minimal PDF writer, used to render per-person documents as PDFs.
+//!
+//! The layout is deliberately simple: US Letter pages with one-inch margins,
+//! with the text set in 11-point Helvetica, one text line per output line.
+//! Long lines are wrapped at word boundaries, and text that doesn't fit on
+//! one page flows onto the next.
+
+const PAGE_WIDTH: u32 = 612;
+const PAGE_HEIGHT: u32 = 792;
+const MARGIN: u32 = 72;
+const FONT_SIZE: u32 = 11;
+const LEADING: u32 = 14;
+
+// Helvetica averages a bit over half an em per character, so this is a
+// conservative estimate of how many characters fit between the margins.
+const MAX_LINE_CHARS: usize = 85;
+
+/**
+ * Render text as a PDF document.
+ *
+ * # Arguments
+ *
+ * - `text`: The text to render. Characters outside of Latin-1 are replaced
+ *   with `?`, since the document uses the standard (non-embedded) Helvetica
+ *   font.
+ *
+ * # Returns
+ *
+ * The bytes of the PDF document.
+ */
+pub fn text_to_pdf(text: &str) -> Vec<u8> {
+    let lines = wrap_lines(text);
+    let lines_per_page = ((PAGE_HEIGHT - 2 * MARGIN) / LEADING) as usize;
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&lines[..]]
+    }
+    else {
+        lines.chunks(lines_per_page).collect()
+    };
+
+    // Object numbering: 1 is the catalog, 2 is the page tree, 3 is the font,
+    // and each page then gets two objects: the page and its content stream.
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 4 + i * 2))
+        .collect();
+
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>",
+                         kids.join(" "), pages.len()).into_bytes());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica \
+                   /Encoding /WinAnsiEncoding >>".to_vec());
+
+    for (i, page_lines) in pages.iter().enumerate() {
+        let content = page_content(page_lines);
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, 5 + i * 2
+        ).into_bytes());
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len())
+            .into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut buf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+    let mut offsets: Vec<usize> = Vec::new();
+
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(buf.len());
+        buf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        buf.extend(obj);
+        buf.extend(b"\nendobj\n");
+    }
+
+    let xref_offset = buf.len();
+    buf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1)
+        .into_bytes());
+    for offset in offsets {
+        buf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    buf.extend(format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1, xref_offset
+    ).into_bytes());
+
+    buf
+}
+
+/// Build the content stream for one page.
+fn page_content(lines: &[String]) -> Vec<u8> {
+    let mut buf: Vec<u8> = format!(
+        "BT\n/F1 {} Tf\n{} TL\n{} {} Td\n",
+        FONT_SIZE, LEADING, MARGIN, PAGE_HEIGHT - MARGIN - FONT_SIZE
+    ).into_bytes();
+
+    for line in lines {
+        buf.push(b'(');
+        buf.extend(pdf_string(line));
+        buf.extend(b") Tj T*\n");
+    }
+
+    buf.extend(b"ET");
+    buf
+}
+
+/// Encode a string as the contents of a PDF literal string.
+fn pdf_string(s: &str) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+
+    for c in s.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                buf.push(b'\\');
+                buf.push(c as u8);
+            },
+            '\t' => buf.extend(b"    "),
+            c if (c as u32) < 0x20 => (),
+            c if (c as u32) < 0x100 => buf.push(c as u32 as u8),
+            _ => buf.push(b'?'),
+        }
+    }
+
+    buf
+}
+
+/// Split text into lines, wrapping long lines at word boundaries.
+fn wrap_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in text.lines() {
+        let mut current = String::new();
+
+        for word in line.split(' ') {
+            let len = current.chars().count();
+            if len > 0 && len + 1 + word.chars().count() > MAX_LINE_CHARS {
+                lines.push(current);
+                current = String::new();
+            }
+
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+
+        lines.push(current);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::{text_to_pdf, wrap_lines};
+
+    #[test]
+    fn pdf_structure() {
+        let pdf = String::from_utf8_lossy(&text_to_pdf("Hello (world)")).to_string();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("(Hello \\(world\\)) Tj"));
+        assert!(pdf.contains("/Count 1"));
+    }
+
+    #[test]
+    fn multiple_pages() {
+        let text = vec!["line"; 100].join("\n");
+        let pdf = String::from_utf8_lossy(&text_to_pdf(&text)).to_string();
+        assert!(pdf.contains("/Count 3"));
+    }
+
+    #[test]
+    fn wrapping() {
+        let text = vec!["word"; 30].join(" ");
+        let lines = wrap_lines(&text);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() <= 85));
+    }
+}
@@ -3,32 +3,79 @@
 //!
 //! - read people-related data from files
 //! - randomly generate `Person` objects
-//! - serialize generated data to CSV
-
+//! - serialize generated data to CSV or JSON
+//! - write per-person files (documents, avatars and barcodes)
+
+use crate::ad::{self, AccountNames};
+use crate::arrow::ArrowWriter;
+use crate::avatar::identicon_png;
+use crate::avro::{record_schema, AvroWriter};
+use crate::output::{split_compression, OutputFile};
+use crate::barcode::{self, BarcodeSource};
+use crate::cluster::{ClusterBy, SortedBirthDates};
+use crate::consent::{Consent, ConsentGenerator};
+use crate::crypt::protect_field;
+use crate::device::{Device, DeviceGenerator};
+use crate::error::{warning, PeoplegenError};
+use crate::format::format_field;
+use crate::hash::HASH_FIELD_SUFFIX;
+use crate::genders::{all_first_names, GenderCategory};
+use crate::lifecycle::{money_str, Lifecycle, LifecycleGenerator};
+use crate::loyalty::{Loyalty, LoyaltyGenerator};
+use crate::metadata::{timestamp_str, MetadataGenerator, RecordMetadata};
 use crate::args::{Arguments, HeaderFormat, OutputFormat};
 use crate::path::path_str;
-use crate::ssn::SsnGenerator;
+use crate::pronouns::PronounGenerator;
+use crate::provider::ProviderIdGenerator;
+use crate::salary::{SalaryDistribution, SalaryRounding};
+use crate::pdf::text_to_pdf;
+use crate::pg;
+use crate::fixed::{FixedWidthWriter, Justify, FILLER};
+use crate::protobuf::{proto_definition, FieldType, ProtobufWriter};
+use crate::vcard::{Contact, VCardWriter};
+use crate::ldif::{self, LdifWriter};
+use crate::html::{Column, HtmlWriter};
+use crate::markdown::MarkdownWriter;
+use crate::preview::TableWriter;
+use crate::sql::{create_table, ColumnType, SqlDialect, SqlWriter};
+use crate::ssn::{self, SsnFormat, SsnGenerator};
+use crate::template::Template;
+use crate::vin::VinGenerator;
+use crate::weighted::WeightedValues;
+use crate::xlsx::{Cell, XlsxWriter};
 use chrono::naive::{NaiveDate, NaiveDateTime};
-use csv::WriterBuilder;
+use chrono::Datelike;
+use csv::{QuoteStyle, Terminator, WriterBuilder};
 use json::JsonValue;
-use rand::seq::SliceRandom;
-use rand::Rng;
-use rand_distr::{Normal, Distribution};
+use log::debug;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::LineWriter;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
 use std::io::{self, prelude::*};
-use std::path::PathBuf;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thousands::Separable;
+use tracing::{field, info_span};
 
 /**
- * Abstract representation of gender. Too restrictive currently, but it
- * matches the gender definitions in the 2010 Census Bureau data.
+ * Abstract representation of gender. `Male` and `Female` match the gender
+ * definitions in the 2010 Census Bureau data, and are the defaults.
+ * `NonBinary` is the third category `--other-pct` adds. `Custom` categories
+ * come from a gender configuration file (see `genders`).
 */
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Gender {
     Male,
     Female,
+    /// Nonbinary, from `--other-pct`
+    NonBinary,
+    Custom(String),
 }
 
 impl Gender {
@@ -37,19 +84,44 @@ impl Gender {
      * writing to a CSV file.
      */
     pub fn to_str(&self) -> &str {
-        if *self == Gender::Male {
-            "M"
-        } else {
-            "F"
+        match self {
+            Gender::Male => "M",
+            Gender::Female => "F",
+            Gender::NonBinary => "X",
+            Gender::Custom(label) => label,
         }
     }
 
     /**
-     * Converts a `Gender` value to a string suitable for display or for
-     * writing to a CSV file.
+     * Converts a label, as returned by `to_str()`, back to a `Gender`.
+     * Anything other than "M", "F" and "X" is a custom category.
      */
-    pub fn to_string(&self) -> String {
-        String::from(self.to_str())
+    pub fn from_label(label: &str) -> Gender {
+        match label {
+            "M" => Gender::Male,
+            "F" => Gender::Female,
+            "X" => Gender::NonBinary,
+            _ => Gender::Custom(label.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+// A gender is serialized as its label, as it's written to a CSV file.
+impl Serialize for Gender {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Gender {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Gender, D::Error> {
+        String::deserialize(deserializer).map(|label| Gender::from_label(&label))
     }
 }
 
@@ -61,18 +133,154 @@ impl Gender {
  * - `first_name`: The person's first name (gender-specific)
  * - `middle_name`: The person's middle name (gender-specific)
  * - `last_name`: The person's last name
+ *
+ *   The names are shared with the names lists they came from, so the same
+ *   name, chosen for a million people, is stored once, not a million times.
  * - `gender`: The gender
  * - `birth_date`: The person's birth date
  * - `ssn`: The person's (fake) U.S. Social Security Number
+ * - `npi`: The person's National Provider Identifier, if requested
+ *   (otherwise empty, and left out when serializing)
+ * - `dea_number`: The person's DEA registration number, if requested
+ *   (otherwise empty, and left out when serializing)
+ * - `salary`: The person's salary
+ * - `vin`: A vehicle identification number for the person's vehicle, if
+ *   requested (otherwise empty, and left out when serializing)
+ * - `account_name`: The person's unique Active Directory logon name. Only
+ *   assigned when the Active Directory header format is in use; empty
+ *   otherwise.
+ * - `metadata`: The record's metadata columns, if requested
+ * - `pronouns`: The person's pronouns, if requested (otherwise empty)
+ * - `device`: The person's device fields, if requested
+ * - `consent`: The person's marketing consent fields, if requested
+ * - `lifecycle`: The person's customer-lifecycle fields, if requested
+ * - `loyalty`: The person's loyalty-program fields, if requested
+ *
+ * With serde, a person is a flat record, with the metadata, device, consent,
+ * lifecycle and loyalty fields (if there are any) alongside the rest, named
+ * as with the snake case header format.
+ * Any other header format's names are accepted, too, when deserializing.
+ * `with_headers()` serializes the fields with a header format's names.
 */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Person {
-    pub first_name: String,
-    pub middle_name: String,
-    pub last_name: String,
+    /// The person's number in the run, from 1, which is also their ID
+    #[serde(alias = "ID", alias = "employeeID")]
+    pub id: u64,
+    #[serde(alias = "firstName", alias = "First Name", alias = "givenName")]
+    pub first_name: Arc<str>,
+    #[serde(alias = "middleName", alias = "Middle Name")]
+    pub middle_name: Arc<str>,
+    #[serde(alias = "lastName", alias = "Last Name", alias = "sn")]
+    pub last_name: Arc<str>,
+    #[serde(alias = "Gender")]
     pub gender: Gender,
+    #[serde(alias = "birthDate", alias = "Birth Date")]
     pub birth_date: NaiveDate,
+    #[serde(alias = "SSN")]
     pub ssn: String,
-    pub salary: u32
+    #[serde(default, skip_serializing_if = "String::is_empty", alias = "NPI")]
+    pub npi: String,
+    #[serde(default, skip_serializing_if = "String::is_empty", alias = "deaNumber", alias = "DEA Number")]
+    pub dea_number: String,
+    #[serde(alias = "Salary")]
+    pub salary: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty", alias = "VIN")]
+    pub vin: String,
+    #[serde(default, alias = "accountName", alias = "Account Name", alias = "sAMAccountName")]
+    pub account_name: String,
+    #[serde(flatten)]
+    pub metadata: Option<RecordMetadata>,
+    #[serde(default, alias = "Pronouns")]
+    pub pronouns: String,
+    #[serde(flatten)]
+    pub device: Option<Device>,
+    #[serde(flatten)]
+    pub consent: Option<Consent>,
+    #[serde(flatten)]
+    pub lifecycle: Option<Lifecycle>,
+    #[serde(flatten)]
+    pub loyalty: Option<Loyalty>,
+}
+
+impl Person {
+    /**
+     * Get the person, for serializing with a header format's field names:
+     * as a flat record of the fields a `Person` holds, in output order,
+     * named as in the header row of a CSV file.
+     *
+     * # Arguments
+     *
+     * - `header_format`: The header format
+     */
+    pub fn with_headers(&self, header_format: HeaderFormat) -> HeadedPerson<'_> {
+        HeadedPerson { person: self, header_format }
+    }
+}
+
+/// A `Person`, serialized with a header format's field names. See
+/// `Person::with_headers()`.
+pub struct HeadedPerson<'a> {
+    person: &'a Person,
+    header_format: HeaderFormat,
+}
+
+impl Serialize for HeadedPerson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let headers = get_headers(self.header_format);
+        let p = self.person;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry(&headers[HEADER_ID_KEY], &p.id)?;
+        map.serialize_entry(&headers[HEADER_FIRST_NAME_KEY], &*p.first_name)?;
+        map.serialize_entry(&headers[HEADER_MIDDLE_NAME_KEY], &*p.middle_name)?;
+        map.serialize_entry(&headers[HEADER_LAST_NAME_KEY], &*p.last_name)?;
+        map.serialize_entry(&headers[HEADER_GENDER_KEY], &p.gender)?;
+        map.serialize_entry(&headers[HEADER_BIRTH_DATE_KEY], &p.birth_date)?;
+        map.serialize_entry(&headers[HEADER_SSN_KEY], &p.ssn)?;
+        if !p.npi.is_empty() {
+            map.serialize_entry(&headers[HEADER_NPI_KEY], &p.npi)?;
+        }
+        if !p.dea_number.is_empty() {
+            map.serialize_entry(&headers[HEADER_DEA_NUMBER_KEY], &p.dea_number)?;
+        }
+        map.serialize_entry(&headers[HEADER_SALARY_KEY], &p.salary)?;
+        if !p.vin.is_empty() {
+            map.serialize_entry(&headers[HEADER_VIN_KEY], &p.vin)?;
+        }
+        map.serialize_entry(&headers[HEADER_ACCOUNT_NAME_KEY], &p.account_name)?;
+        if let Some(m) = &p.metadata {
+            map.serialize_entry(&headers[HEADER_CREATED_AT_KEY], &timestamp_str(&m.created_at))?;
+            map.serialize_entry(&headers[HEADER_UPDATED_AT_KEY], &timestamp_str(&m.updated_at))?;
+            map.serialize_entry(&headers[HEADER_SOURCE_SYSTEM_KEY], &m.source_system)?;
+            map.serialize_entry(&headers[HEADER_RECORD_UUID_KEY], &m.record_uuid)?;
+        }
+        map.serialize_entry(&headers[HEADER_PRONOUNS_KEY], &p.pronouns)?;
+        if let Some(d) = &p.device {
+            map.serialize_entry(&headers[HEADER_DEVICE_TYPE_KEY], &d.device_type)?;
+            map.serialize_entry(&headers[HEADER_OS_KEY], &d.os)?;
+            map.serialize_entry(&headers[HEADER_USER_AGENT_KEY], &d.user_agent)?;
+        }
+        if let Some(c) = &p.consent {
+            map.serialize_entry(&headers[HEADER_EMAIL_OPT_IN_KEY], &c.email_opt_in)?;
+            map.serialize_entry(&headers[HEADER_SMS_OPT_IN_KEY], &c.sms_opt_in)?;
+            map.serialize_entry(&headers[HEADER_CONSENT_TIMESTAMP_KEY], &timestamp_str(&c.consent_timestamp))?;
+            map.serialize_entry(&headers[HEADER_CONSENT_VERSION_KEY], &c.consent_version)?;
+        }
+        if let Some(l) = &p.lifecycle {
+            map.serialize_entry(&headers[HEADER_SIGNUP_DATE_KEY], &l.signup_date)?;
+            map.serialize_entry(&headers[HEADER_LAST_ACTIVE_DATE_KEY], &l.last_active_date)?;
+            map.serialize_entry(&headers[HEADER_CHURNED_KEY], &l.churned)?;
+            map.serialize_entry(&headers[HEADER_LIFETIME_VALUE_KEY], &l.lifetime_value)?;
+        }
+        if let Some(l) = &p.loyalty {
+            map.serialize_entry(&headers[HEADER_LOYALTY_TIER_KEY], &l.loyalty_tier)?;
+            map.serialize_entry(&headers[HEADER_POINTS_BALANCE_KEY], &l.points_balance)?;
+            map.serialize_entry(&headers[HEADER_MEMBER_SINCE_KEY], &l.member_since)?;
+        }
+
+        map.end()
+    }
 }
 
 const HEADER_ID_KEY: &str = "id";
@@ -82,7 +290,67 @@ const HEADER_MIDDLE_NAME_KEY: &str = "middle_name";
 const HEADER_GENDER_KEY: &str = "gender";
 const HEADER_BIRTH_DATE_KEY: &str = "birth_date";
 const HEADER_SSN_KEY: &str = "ssn";
+const HEADER_NPI_KEY: &str = "npi";
+const HEADER_DEA_NUMBER_KEY: &str = "dea_number";
 const HEADER_SALARY_KEY: &str = "salary";
+const HEADER_VIN_KEY: &str = "vin";
+const HEADER_ACCOUNT_NAME_KEY: &str = "account_name";
+const HEADER_PRINCIPAL_NAME_KEY: &str = "principal_name";
+const HEADER_AVATAR_PATH_KEY: &str = "avatar_path";
+const HEADER_AVATAR_URL_KEY: &str = "avatar_url";
+const HEADER_BARCODE_KEY: &str = "barcode";
+const HEADER_CREATED_AT_KEY: &str = "created_at";
+const HEADER_UPDATED_AT_KEY: &str = "updated_at";
+const HEADER_SOURCE_SYSTEM_KEY: &str = "source_system";
+const HEADER_RECORD_UUID_KEY: &str = "record_uuid";
+const HEADER_PRONOUNS_KEY: &str = "pronouns";
+const HEADER_DEVICE_TYPE_KEY: &str = "device_type";
+const HEADER_OS_KEY: &str = "os";
+const HEADER_USER_AGENT_KEY: &str = "user_agent";
+const HEADER_EMAIL_OPT_IN_KEY: &str = "email_opt_in";
+const HEADER_SMS_OPT_IN_KEY: &str = "sms_opt_in";
+const HEADER_CONSENT_TIMESTAMP_KEY: &str = "consent_timestamp";
+const HEADER_CONSENT_VERSION_KEY: &str = "consent_version";
+const HEADER_SIGNUP_DATE_KEY: &str = "signup_date";
+const HEADER_LAST_ACTIVE_DATE_KEY: &str = "last_active_date";
+const HEADER_CHURNED_KEY: &str = "churned";
+const HEADER_LIFETIME_VALUE_KEY: &str = "lifetime_value";
+const HEADER_LOYALTY_TIER_KEY: &str = "loyalty_tier";
+const HEADER_POINTS_BALANCE_KEY: &str = "points_balance";
+const HEADER_MEMBER_SINCE_KEY: &str = "member_since";
+
+const METADATA_HEADERS: [&str; 4] = [
+    HEADER_CREATED_AT_KEY,
+    HEADER_UPDATED_AT_KEY,
+    HEADER_SOURCE_SYSTEM_KEY,
+    HEADER_RECORD_UUID_KEY,
+];
+
+const DEVICE_HEADERS: [&str; 3] = [
+    HEADER_DEVICE_TYPE_KEY,
+    HEADER_OS_KEY,
+    HEADER_USER_AGENT_KEY,
+];
+
+const CONSENT_HEADERS: [&str; 4] = [
+    HEADER_EMAIL_OPT_IN_KEY,
+    HEADER_SMS_OPT_IN_KEY,
+    HEADER_CONSENT_TIMESTAMP_KEY,
+    HEADER_CONSENT_VERSION_KEY,
+];
+
+const LIFECYCLE_HEADERS: [&str; 4] = [
+    HEADER_SIGNUP_DATE_KEY,
+    HEADER_LAST_ACTIVE_DATE_KEY,
+    HEADER_CHURNED_KEY,
+    HEADER_LIFETIME_VALUE_KEY,
+];
+
+const LOYALTY_HEADERS: [&str; 3] = [
+    HEADER_LOYALTY_TIER_KEY,
+    HEADER_POINTS_BALANCE_KEY,
+    HEADER_MEMBER_SINCE_KEY,
+];
 
 const REQUIRED_HEADERS: [&str; 5] = [
     HEADER_FIRST_NAME_KEY,
@@ -92,29 +360,243 @@ const REQUIRED_HEADERS: [&str; 5] = [
     HEADER_BIRTH_DATE_KEY,
 ];
 
+/// The names of all the fields that can be generated. These are the names
+/// used to refer to fields in templates, formatters, and so on.
+pub const FIELD_NAMES: [&str; 35] = [
+    HEADER_ID_KEY,
+    HEADER_FIRST_NAME_KEY,
+    HEADER_MIDDLE_NAME_KEY,
+    HEADER_LAST_NAME_KEY,
+    HEADER_GENDER_KEY,
+    HEADER_BIRTH_DATE_KEY,
+    HEADER_SSN_KEY,
+    HEADER_SALARY_KEY,
+    HEADER_ACCOUNT_NAME_KEY,
+    HEADER_PRINCIPAL_NAME_KEY,
+    HEADER_AVATAR_PATH_KEY,
+    HEADER_AVATAR_URL_KEY,
+    HEADER_BARCODE_KEY,
+    HEADER_CREATED_AT_KEY,
+    HEADER_UPDATED_AT_KEY,
+    HEADER_SOURCE_SYSTEM_KEY,
+    HEADER_RECORD_UUID_KEY,
+    HEADER_PRONOUNS_KEY,
+    HEADER_DEVICE_TYPE_KEY,
+    HEADER_OS_KEY,
+    HEADER_USER_AGENT_KEY,
+    HEADER_EMAIL_OPT_IN_KEY,
+    HEADER_SMS_OPT_IN_KEY,
+    HEADER_CONSENT_TIMESTAMP_KEY,
+    HEADER_CONSENT_VERSION_KEY,
+    HEADER_SIGNUP_DATE_KEY,
+    HEADER_LAST_ACTIVE_DATE_KEY,
+    HEADER_CHURNED_KEY,
+    HEADER_LIFETIME_VALUE_KEY,
+    HEADER_LOYALTY_TIER_KEY,
+    HEADER_POINTS_BALANCE_KEY,
+    HEADER_MEMBER_SINCE_KEY,
+    HEADER_NPI_KEY,
+    HEADER_DEA_NUMBER_KEY,
+    HEADER_VIN_KEY,
+];
+
+// How many generated files (rendered documents, avatars) go into each
+// subdirectory of an output directory. Keeps directories to a manageable size.
+const FILES_PER_DIRECTORY: u64 = 1000;
+
+// Mixed into the run's seed to seed the random number generator that picks
+// SSN prefixes.
+const SSN_PREFIX_SEED_SALT: u64 = 0x7373_6e70_7265_6678;
+
+// The name of the table in SQL output.
+const SQL_TABLE: &str = "people";
+
+const AVATAR_EXTENSION: &str = "png";
+const BARCODE_EXTENSION: &str = "png";
+
 /**
- * Read a file of names into a vector of strings.
+ * Read a names file: a weighted value file (see `peoplegen::weighted`), so
+ * it can be a plain list of names, or give each name a weight.
  *
  * # Arguments
  *
  * - path: The path to the file to be read
+ * - strict: Whether a name listed more than once is an error, rather than
+ *   a warning
  *
  * # Returns
  *
- * - `Ok(v)`: The file was successfully read into vector `v`
- * - `Err(msg)`: The file could not be read, and `msg` explains why
+ * - `Ok(names)`: The names, and how likely each one is
+ * - `Err(e)`: The file could not be read (`PeoplegenError::Io`) or is
+ *   invalid (`PeoplegenError::Parse`), or, with `strict`, has duplicates
+ *   (`PeoplegenError::Validation`)
 */
-pub fn read_names_file(path: &PathBuf) -> Result<Vec<String>, String> {
-    let file = File::open(path).map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
-    let reader = io::BufReader::new(file);
-    let mut buf: Vec<String> = Vec::new();
+pub fn read_names_file(path: &Path, strict: bool) -> Result<WeightedValues, PeoplegenError> {
+    let file = File::open(path).map_err(|source| PeoplegenError::Io { path: path.to_path_buf(), source })?;
+    let names = WeightedValues::parse(io::BufReader::new(file))
+        .map_err(|e| PeoplegenError::Parse(format!("\"{}\": {}", path_str(path), e)))?;
+    debug!("Read {} name(s) from \"{}\".", names.len(), path_str(path));
+    let duplicates = names.duplicates();
+    if let Some(first) = duplicates.first() {
+        warning(strict, format!(
+            "\"{}\": {} name(s) are listed more than once, e.g., \"{}\". Each gets the total of its weights.",
+            path_str(path), duplicates.len(), first
+        ))?;
+    }
+    Ok(names)
+}
+
+/**
+ * A stream of randomly generated `Person` objects, returned by
+ * `make_people()`. People are generated one at a time, as the stream is
+ * consumed, so memory use doesn't depend on the number of people generated.
+ * (The one exception is Active Directory account names, which have to be
+ * unique, so every name handed out is remembered.)
+ *
+ * Generation can fail part way through; for instance, a badly chosen
+ * salary distribution can produce a negative salary. The stream ends there,
+ * and `error()` says why. `results()` turns the generator into a stream of
+ * `Result`s instead, ending with the error, for when a failure has to stop
+ * whatever's consuming the people.
+ */
+pub struct PersonGenerator<'a> {
+    rng: ChaCha8Rng,
+    ssns: SsnGenerator,
+    salaries: SalaryDistribution,
+    salary_rounding: SalaryRounding,
+    birth_range: RangeInclusive<i64>,
+    // With --cluster-by birth_year, the birth dates come from here, in order.
+    sorted_birth_dates: Option<SortedBirthDates>,
+    categories: &'a [GenderCategory],
+    last_names: &'a WeightedValues,
+    // With --no-gender, first names come from this list, instead.
+    all_first_names: Option<WeightedValues>,
+    // How many people of each category are still to be generated.
+    left: Vec<u64>,
+    account_names: Option<AccountNames>,
+    metadata: Option<MetadataGenerator>,
+    pronouns: Option<PronounGenerator>,
+    devices: Option<DeviceGenerator>,
+    consents: Option<ConsentGenerator>,
+    lifecycles: Option<LifecycleGenerator>,
+    loyalties: Option<LoyaltyGenerator>,
+    provider_ids: Option<ProviderIdGenerator>,
+    vins: Option<VinGenerator>,
+    // The ID before the first person's.
+    id_offset: u64,
+    // How many people have been generated so far.
+    generated: u64,
+    // Why generation stopped early, if it did.
+    error: Option<String>,
+}
+
+impl<'a> PersonGenerator<'a> {
+    /**
+     * Get the reason the stream of people ended early, if it did.
+     *
+     * # Returns
+     *
+     * The error message, or `None` if every person has been generated (or
+     * hasn't been yet).
+     */
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
 
-    for line_res in reader.lines() {
-        let line = line_res.map_err(|e| format!("{}", e))?;
-        buf.push(line);
+    /**
+     * Turn the generator into a stream of `Result`s: a person for each
+     * person generated, then, if generation failed, the error.
+     */
+    pub fn results(mut self) -> impl Iterator<Item = Result<Person, String>> + 'a {
+        let mut ended = false;
+        std::iter::from_fn(move || {
+            if ended {
+                return None;
+            }
+            match self.next() {
+                Some(p) => Some(Ok(p)),
+                None => {
+                    ended = true;
+                    self.error.clone().map(Err)
+                },
+            }
+        })
     }
+}
+
+impl<'a> Iterator for PersonGenerator<'a> {
+    type Item = Person;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining: u64 = self.left.iter().sum();
+        if remaining == 0 || self.error.is_some() {
+            return None;
+        }
+
+        // Choosing each gender in proportion to the number of people of that
+        // gender still to be generated yields exactly the requested totals,
+        // in a uniformly random order, without having to shuffle.
+        let mut r = self.rng.gen_range(0..remaining);
+        let mut chosen = 0;
+        while r >= self.left[chosen] {
+            r -= self.left[chosen];
+            chosen += 1;
+        }
+        self.left[chosen] -= 1;
+        let category = &self.categories[chosen];
+
+        let salary = self.salaries.sample(&mut self.rng);
+        if salary < 0.0 {
+            self.error = Some(format!("Generated negative salary ({salary})"));
+            return None;
+        }
+
+        let first_names = self.all_first_names.as_ref().unwrap_or(&category.first_names);
+        let ssn = self.ssns.next().unwrap();
+        let mut p = make_person(
+            &mut self.rng,
+            first_names,
+            self.last_names,
+            category.gender.clone(),
+            self.salary_rounding.apply(salary) as u32,
+            &self.birth_range,
+            ssn,
+        );
+        if let Some(dates) = &mut self.sorted_birth_dates {
+            // There are as many dates as people.
+            p.birth_date = dates.next().unwrap();
+        }
+        self.generated += 1;
+        p.id = self.id_offset + self.generated;
 
-    Ok(buf)
+        // The first person with a given name gets the unadorned account
+        // name.
+        if let Some(account_names) = &mut self.account_names {
+            p.account_name = account_names.allocate(&p.first_name, &p.last_name);
+        }
+
+        p.metadata = self.metadata.as_mut().map(|m| m.next_metadata());
+
+        if let Some(pronouns) = &mut self.pronouns {
+            p.pronouns = pronouns.next_pronouns(&p.gender);
+        }
+
+        p.device = self.devices.as_mut().map(|d| d.next_device());
+        p.consent = self.consents.as_mut().map(|c| c.next_consent());
+        p.lifecycle = self.lifecycles.as_mut().map(|l| l.next_lifecycle(p.birth_date, p.salary));
+        p.loyalty = self.loyalties.as_mut().map(|l| l.next_loyalty(p.birth_date));
+
+        if let Some(ids) = &mut self.provider_ids {
+            p.npi = ids.npi(p.id);
+            p.dea_number = ids.dea_number(p.id, &p.last_name);
+        }
+
+        if let Some(vins) = &mut self.vins {
+            p.vin = vins.vin(p.id);
+        }
+
+        Some(p)
+    }
 }
 
 /**
@@ -123,140 +605,544 @@ pub fn read_names_file(path: &PathBuf) -> Result<Vec<String>, String> {
  * setting of `args.generate_ssns`. They should be suppressed at write-time,
  * if desired.
  *
+ * All randomness comes from a single random number generator seeded with
+ * `args.seed`, so the same arguments always produce the same people.
+ *
  * # Arguments
  *
  * - `args`: The parsed command-line arguments. The number of people generated
- * is taken from `args.total`.
- * - `male_first_names`: The list of male first names
- * - `female_first_names`: The list of female first names
+ *   is taken from `args.total`.
+ * - `categories`: The gender categories, with their first names, from
+ *   `genders::gender_categories()`
  * - `last_names`: The list of last names
  *
  * # Returns
  *
- * A vector containing the randomly generated `Person` objects.
+ * - `Ok(generator)`: A `PersonGenerator` that yields the people
+ * - `Err(PeoplegenError::Validation(msg))`: The settings are invalid; `msg`
+ *   explains why.
  */
-pub fn make_people(
+pub fn make_people<'a>(
     args: &Arguments,
-    male_first_names: &Vec<String>,
-    female_first_names: &Vec<String>,
-    last_names: &Vec<String>,
-) -> Result<Vec<Person>, String> {
+    categories: &'a [GenderCategory],
+    last_names: &'a WeightedValues,
+) -> Result<PersonGenerator<'a>, PeoplegenError> {
     let epoch_start = NaiveDate::from_ymd(args.year_min as i32, 1, 1)
         .and_hms(0, 0, 0)
         .timestamp();
     let epoch_end = NaiveDate::from_ymd(args.year_max as i32, 12, 31)
         .and_hms(23, 59, 59)
         .timestamp();
-    let male_percent = args.male_percent as u64;
-    let female_percent = args.female_percent as u64;
-    let total_males: u64 = (args.total * male_percent) / 100;
-    let w = (args.total * female_percent) / 100;
-    let total_females = w + (args.total - total_males - w);
-    let mut rng = rand::thread_rng();
-    let mut ssns = SsnGenerator::new_auto_reset();
+    let left = category_counts(args.total, categories);
+    // With no more people than categories, some categories have to go
+    // without.
+    let short = args.total > categories.len() as u64;
+    if let Some((c, _)) = categories.iter().zip(&left).find(|(c, n)| short && c.percent > 0 && **n == 0) {
+        warning(args.strict, format!(
+            "{}% of {} people rounds down to none, so there will be no \"{}\" people.",
+            c.percent, args.total, c.gender
+        ))?;
+    }
+    let ssns = ssn_generator(args);
 
     if args.total > ssns.total() {
-        println!(
-"Warning: There are {} total unique SSNs.
+        warning(args.strict, format!(
+"There are {} total unique SSNs.
 You're generating {} people.
 There will be some repeated SSNs.",
 ssns.total().separate_with_commas(),
-args.total.separate_with_commas())
+args.total.separate_with_commas()))?;
     }
 
-    let normal_dist =
-        Normal::new(args.salary_mean as f32, args.salary_sigma as f32)
-              .map_err(|e| format!("{}", e))?;
-    let mut get_salary = || {
-        let s = normal_dist.sample(&mut rng);
-        if s < 0.0 {
-            Err(format!("Generated negative salary ({s})"))
-        }
-        else {
-            Ok(s as u32)
-        }
+    let salaries = match &args.salary_mix {
+        Some(mix) => SalaryDistribution::mixture(mix).map_err(PeoplegenError::Validation)?,
+        None => SalaryDistribution::new(
+            args.salary_mean, args.salary_sigma, args.salary_percentiles.as_ref()
+        ).map_err(PeoplegenError::Validation)?,
     };
 
-    let mut buf: Vec<Person> = Vec::new();
-
-    for _ in 0..total_males {
-        let salary = get_salary()?;
-        let ssn = ssns.next().unwrap();
-        let p = make_person(
-            male_first_names,
-            last_names,
-            Gender::Male,
-            salary,
-            epoch_start,
-            epoch_end,
-            ssn,
-        );
-        buf.push(p)
-    }
-
-    for _ in 0..total_females {
-        let salary = get_salary()?;
-        let ssn = ssns.next().unwrap();
-        let p = make_person(
-            female_first_names,
-            last_names,
-            Gender::Female,
-            salary,
-            epoch_start,
-            epoch_end,
-            ssn,
-        );
-        buf.push(p)
+    // LDIF entries are named by their account names, too.
+    let account_names = match (args.header_format, args.output_format) {
+        (HeaderFormat::ActiveDirectory, _) | (_, OutputFormat::Ldif) => Some(AccountNames::new()),
+        _ => None,
+    };
+
+    // For gender-free data, first names are drawn from the union of the
+    // name lists, so they don't give away anyone's gender, either.
+    let all_first_names = if args.no_gender {
+        Some(all_first_names(categories).map_err(PeoplegenError::Validation)?)
     }
+    else {
+        None
+    };
+
+    let metadata = match &args.metadata {
+        Some(options) => Some(MetadataGenerator::new(options, args.seed).map_err(PeoplegenError::Validation)?),
+        None => None,
+    };
+
+    let consents = match &args.consent {
+        Some(options) => Some(ConsentGenerator::new(options, args.seed).map_err(PeoplegenError::Validation)?),
+        None => None,
+    };
+
+    let lifecycles = match &args.lifecycle {
+        Some(options) => Some(LifecycleGenerator::new(options, args.seed).map_err(PeoplegenError::Validation)?),
+        None => None,
+    };
+
+    let loyalties = match &args.loyalty {
+        Some(options) => Some(LoyaltyGenerator::new(options, args.seed).map_err(PeoplegenError::Validation)?),
+        None => None,
+    };
+
+    let vins = match &args.vin {
+        Some(options) => Some(VinGenerator::new(options, args.seed).map_err(PeoplegenError::Validation)?),
+        None => None,
+    };
 
-    buf.shuffle(&mut rng);
-    Ok(buf)
+    Ok(PersonGenerator {
+        rng: ChaCha8Rng::seed_from_u64(args.seed),
+        ssns,
+        salaries,
+        salary_rounding: args.salary_rounding,
+        birth_range: epoch_start..=epoch_end,
+        sorted_birth_dates: args.cluster_by.map(|ClusterBy::BirthYear| {
+            SortedBirthDates::new(epoch_start, epoch_end, args.total, args.seed)
+        }),
+        categories,
+        last_names,
+        all_first_names,
+        left,
+        account_names,
+        metadata,
+        pronouns: args.pronouns.as_ref().map(|o| PronounGenerator::new(o, args.seed)),
+        devices: args.device.then(|| DeviceGenerator::new(args.seed)),
+        consents,
+        lifecycles,
+        loyalties,
+        provider_ids: (args.npi || args.dea).then(|| ProviderIdGenerator::new(args.seed)),
+        vins,
+        id_offset: args.id_offset,
+        generated: 0,
+        error: None,
+    })
 }
 
 /**
- * Creates a CSV or JSON file from a vector of randomly generated `Person`
- * objects.
+ * Work out how many people each gender category gets: its percentage of the
+ * total, rounded down, except the last, which gets whatever's left over.
  *
  * # Arguments
  *
- * - `path`: The path to the CSV file to create or overwrite
- * - `output_format`: The output format of the file
- * - `header_format`: What style of CSV header names or JSON keys to use.
- * - `generate_ids`: Whether or not to generate and save unique numeric IDs
- *                   for each person
- * - `save_ssns`: Whether or not to save the fake Social Security numbers
- * - `save_salaries`: Whether or not to save generated salary data
- * - `people`: The list of randomly generated people to save. Note that this
- *             parameter isn't a reference and is, therefore, consumed by this
- *             function.
+ * - `total`: How many people there are
+ * - `categories`: The gender categories
+ *
+ * # Returns
+ *
+ * The number of people in each category, in the categories' order.
+ */
+pub fn category_counts(total: u64, categories: &[GenderCategory]) -> Vec<u64> {
+    let mut counts: Vec<u64> = categories
+        .iter()
+        // Hundreds, then the rest, so a total near u64::MAX doesn't overflow.
+        .map(|c| total / 100 * u64::from(c.percent) + total % 100 * u64::from(c.percent) / 100)
+        .collect();
+    if let Some(n) = counts.len().checked_sub(1) {
+        counts[n] = total - counts[..n].iter().sum::<u64>();
+    }
+    counts
+}
+
+/**
+ * Creates a CSV or JSON file from a stream of randomly generated `Person`
+ * objects. Records are written as they arrive, so the people don't have to
+ * fit in memory.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments, which supply the output
+ *   format, the header format, and which optional fields (IDs, Social
+ *   Security numbers, salaries) to save.
+ * - `path`: The file to create or overwrite: `args.output_file`, or where
+ *   it's staged
+ * - `people`: The randomly generated people to save, e.g., from
+ *   `make_people()`
  *
  * # Returns
  *
  * - `Ok(total)`: The save was successful, and `total` people were written
- * - `Err(msg)`: Unable to write the CSV file; `msg` explains why.
+ * - `Err(e)`: Writing stopped. It's a `PeoplegenError::Generation` if
+ *   `people` yielded an error (which is `e`'s message), and a
+ *   `PeoplegenError::Serialization` if the people couldn't be written.
  */
-pub fn write_people(
-    path: &PathBuf,
-    output_format: OutputFormat,
-    header_format: HeaderFormat,
-    generate_ids: bool,
-    save_ssns: bool,
-    save_salaries: bool,
-    people: Vec<Person>,
-) -> Result<usize, String> {
-    match output_format {
+pub fn write_people<I>(args: &Arguments, path: &Path, people: I) -> Result<u64, PeoplegenError>
+where
+    I: Iterator<Item = Result<Person, String>>,
+{
+    // Pulling the people through the writer generates them, so the span
+    // covers both.
+    let span = info_span!("write_people", path = %path_str(path), format = args.output_format.to_str(),
+                          records = field::Empty).entered();
+    let fields = output_fields(args);
+    // The writers stop at the first error, whichever side it came from.
+    let generation_failed = std::cell::Cell::new(false);
+    let people = people.inspect(|p| if p.is_err() { generation_failed.set(true) });
+
+    let written = match args.output_format {
+        _ if args.long => {
+            write_long(path, args, &fields, people)
+        },
         OutputFormat::Csv => {
-            write_csv(path, header_format, generate_ids, save_ssns,
-                      save_salaries, people)
+            write_csv(path, args, &fields, people)
         },
         OutputFormat::JsonL => {
-            write_jsonl(path, header_format, generate_ids, save_ssns,
-                        save_salaries, people)
+            write_jsonl(path, args, &json_key_order(args, fields), people)
         },
         OutputFormat::JsonPretty => {
-            write_json(path, header_format, generate_ids, save_ssns,
-                       save_salaries, people)
+            write_json(path, args, &json_key_order(args, fields), people)
+        },
+        OutputFormat::Avro => {
+            write_avro(path, args, &fields, people)
+        },
+        OutputFormat::Arrow => {
+            write_arrow(path, args, &fields, people)
+        },
+        OutputFormat::Xlsx => {
+            write_xlsx(path, args, &fields, people)
+        },
+        OutputFormat::Sql => {
+            write_sql(path, args, &fields, people)
+        },
+        OutputFormat::Protobuf => {
+            write_protobuf(path, args, &fields, people)
+        },
+        OutputFormat::FixedWidth => {
+            write_fixed_width(path, args, &fields, people)
+        },
+        OutputFormat::VCard => {
+            write_vcard(path, args, &fields, people)
+        },
+        OutputFormat::Ldif => {
+            write_ldif(path, args, &fields, people)
+        },
+        OutputFormat::Html => {
+            write_html(path, args, &fields, people)
+        },
+        OutputFormat::Table => {
+            write_table(path, args, &fields, people)
+        },
+        OutputFormat::Markdown => {
+            write_markdown(path, args, &fields, people)
+        },
+        OutputFormat::Postgres => {
+            write_postgres(args, &fields, people)
+        }
+    };
+
+    if let Ok(n) = written {
+        span.record("records", n);
+    }
+    written.map_err(|msg| match generation_failed.get() {
+        true => PeoplegenError::Generation(msg),
+        false => PeoplegenError::Serialization(msg),
+    })
+}
+
+/**
+ * Flush a buffered output file, and finish it, so a compressed file gets
+ * the end of its stream.
+ */
+fn finish_output(w: BufWriter<OutputFile>) -> io::Result<()> {
+    w.into_inner().map_err(|e| e.into_error())?.finish()
+}
+
+/**
+ * Create the (auto-resetting) Social Security number generator for a run.
+ * With `--random-ssn-prefixes`, the prefixes are chosen with a random number
+ * generator derived from the seed, so the choice is reproducible, but it
+ * doesn't change any of the other generated values. In a scenario, it
+ * starts where the steps before left off, so the SSNs go with the IDs.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * The generator.
+ */
+pub fn ssn_generator(args: &Arguments) -> SsnGenerator {
+    let builder = SsnGenerator::builder().auto_reset(true);
+    let builder = match args.ssn_prefixes {
+        Some(count) => builder
+            .shuffle_prefixes(args.seed ^ SSN_PREFIX_SEED_SALT)
+            .max_prefixes(count),
+        None => builder,
+    };
+
+    // --random-ssn-prefixes is limited to 1 to 101, so this can't fail.
+    let mut ssns = builder.build().unwrap();
+    ssns.advance(args.id_offset);
+    ssns
+}
+
+/**
+ * Writes a CSV report of the Social Security numbers shared by more than
+ * one person, which happens when more people are generated than there are
+ * fake SSNs. There's one row per person with a shared SSN, giving the SSN (as
+ * written to the main output file, in the `--ssn-format` and with any
+ * `--format-field` formatting, but not encrypted) and the person's ID. The
+ * report is written even if there are no shared SSNs, in which case it only
+ * contains the header. In a scenario, a step's report has the SSNs its people
+ * share, with each other or with the steps before's, and all of their IDs.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `path`: The path to the CSV file to create or overwrite
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The report was written, and `total` SSNs are shared.
+ * - `Err(msg)`: Unable to write the report; `msg` explains why.
+ */
+pub fn write_ssn_collisions(args: &Arguments, path: &Path) -> Result<u64, String> {
+    let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let mut w = WriterBuilder::new().from_path(path).map_err(write_err)?;
+    let headers = headers_for(args);
+    let mut total = 0;
+
+    w.write_record([&headers[HEADER_SSN_KEY], &headers[HEADER_ID_KEY]])
+        .map_err(write_err)?;
+
+    // The IDs are the positions in the whole sequence, from its start.
+    let mut ssns = ssn_generator(args);
+    ssns.reset();
+    for (ssn, ids) in ssn::collisions(ssns, args.id_offset, args.total) {
+        let ssn = format_field(&args.field_formats, HEADER_SSN_KEY, args.ssn_format.apply(&ssn));
+        for id in ids {
+            w.write_record([&ssn, &id.to_string()]).map_err(write_err)?;
+        }
+        total += 1;
+    }
+
+    w.flush().map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+    Ok(total)
+}
+
+/**
+ * Build the `CREATE TABLE` statement for a table that holds the people, with
+ * the columns the command-line settings select.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `dialect`: The SQL dialect
+ * - `table`: The table name
+ *
+ * # Returns
+ *
+ * The statement.
+ */
+pub fn table_ddl(args: &Arguments, dialect: SqlDialect, table: &str) -> String {
+    let headers = headers_for(args);
+    create_table(dialect, table, &sql_columns(args, &output_fields(args), &headers))
+}
+
+/**
+ * Describe the columns the command-line settings select, for
+ * `--summary-json`: for each one, in output order, an object with its field
+ * name, its header, its type (as in SQL output), and what's done to it on
+ * the way out, e.g.:
+ *
+ * ```text
+ * {"field":"ssn","header":"ssn","type":"text","formats":["digits-only"],
+ *  "encryption":"token","hash":null,"rules":1}
+ * ```
+ *
+ * `formats` lists the `--format-field` formatters, in the order they're
+ * applied, `encryption` is the `--encrypt-field` method, `hash` is the
+ * algorithm for a `--hash-field` column, and `rules` is how many `--rules`
+ * rules set the field.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ */
+pub fn column_settings(args: &Arguments) -> JsonValue {
+    let headers = headers_for(args);
+    let fields = output_fields(args);
+    let mut columns = JsonValue::new_array();
+
+    for (key, (header, t)) in fields.iter().zip(sql_columns(args, &fields, &headers)) {
+        let mut column = JsonValue::new_object();
+        column["field"] = key.as_str().into();
+        column["header"] = header.as_str().into();
+        column["type"] = t.name().into();
+        column["formats"] = args.field_formats
+            .iter()
+            .filter(|f| &f.field == key)
+            .map(|f| f.formatter.name())
+            .collect::<Vec<_>>()
+            .into();
+        column["encryption"] = args.field_ciphers
+            .iter()
+            .find(|c| &c.field == key)
+            .map(|c| c.cipher.name())
+            .into();
+        column["hash"] = args.field_hashes
+            .iter()
+            .find(|h| &h.key() == key)
+            .map(|h| h.algorithm.name())
+            .into();
+        column["rules"] = args.rules
+            .as_ref()
+            .map_or(0, |r| r.rules.iter().filter(|rule| &rule.field == key).count())
+            .into();
+        // Pushing onto an array can't fail.
+        let _ = columns.push(column);
+    }
+
+    columns
+}
+
+/**
+ * Write the protobuf definition of the messages in Protobuf output, with the
+ * fields the command-line settings select.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `path`: The `.proto` file to create or overwrite
+ *
+ * # Returns
+ *
+ * - `Ok(())`: The file was written.
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+pub fn write_proto_definition(args: &Arguments, path: &Path) -> Result<(), String> {
+    let headers = headers_for(args);
+    let fields = proto_fields(args, &output_fields(args), &headers);
+
+    fs::write(path, proto_definition(&fields))
+        .map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))
+}
+
+/**
+ * Writes the extra per-person files requested on the command line (rendered
+ * documents, avatars and barcodes), one person at a time, as the people are
+ * generated.
+ *
+ * Files are spread across numbered subdirectories (`0000`, `0001`, ...) of
+ * at most 1,000 files each, and each file is named after the person's ID:
+ * e.g., the first person's avatar is `0000/000001.png`.
+ *
+ * Documents are rendered from a template and take their extension from the
+ * template file. For instance, with a template called `letter.html`, the
+ * first person's document is `0000/000001.html`. If `args.documents_as_pdf`
+ * is set, each rendered document is converted to a simple PDF, instead, and
+ * gets a `.pdf` extension.
+ *
+ * Avatars are identicon-style PNG images, derived from each person's ID (so
+ * the same ID always gets the same image). Barcodes are Code 39 PNG images,
+ * encoding the field selected by `args.barcode_source`.
+ */
+pub struct ExtraFiles<'a> {
+    args: &'a Arguments,
+    template: Option<(Template, String)>,
+    /// Where the documents, avatars and barcodes are written, e.g., where
+    /// their directories are staged
+    documents_dir: Option<PathBuf>,
+    avatars_dir: Option<PathBuf>,
+    barcodes_dir: Option<PathBuf>,
+    /// The number of people for whom files have been written.
+    pub total: u64,
+}
+
+impl<'a> ExtraFiles<'a> {
+    /**
+     * Prepare to write the extra files, loading the document template, if
+     * there is one.
+     *
+     * # Arguments
+     *
+     * - `args`: The parsed command-line arguments, which supply the template
+     *   file and the output directories.
+     * - `dir`: Where to write the files of each output directory, e.g., where
+     *   it's staged. Directories are created as files are written to them.
+     *
+     * # Returns
+     *
+     * - `Ok(extra_files)`: Ready to write
+     * - `Err(msg)`: Unable to load the template; `msg` explains why.
+     */
+    pub fn new<D: FnMut(&Path) -> PathBuf>(args: &'a Arguments, mut dir: D) -> Result<ExtraFiles<'a>, String> {
+        let template = match &args.template_file {
+            Some(path) if args.documents_dir.is_some() => {
+                let extension = if args.documents_as_pdf {
+                    "pdf"
+                }
+                else {
+                    path.extension().and_then(|e| e.to_str()).unwrap_or("txt")
+                };
+                Some((Template::from_file(path, &FIELD_NAMES)?, extension.to_string()))
+            },
+            _ => None,
+        };
+
+        Ok(ExtraFiles {
+            args,
+            template,
+            documents_dir: args.documents_dir.as_deref().map(&mut dir),
+            avatars_dir: args.avatars_dir.as_deref().map(&mut dir),
+            barcodes_dir: args.barcodes_dir.as_deref().map(&mut dir),
+            total: 0,
+        })
+    }
+
+    /**
+     * Write the extra files for one person.
+     *
+     * # Arguments
+     *
+     * - `person`: The person
+     * - `id`: The person's ID, starting at 1
+     *
+     * # Returns
+     *
+     * - `Ok(())`: The files were written, or none were requested.
+     * - `Err(msg)`: Unable to write a file; `msg` explains why.
+     */
+    pub fn write(&mut self, person: &Person, id: u64) -> Result<(), String> {
+        let args = self.args;
+
+        if let (Some((template, extension)), Some(dir)) =
+            (&self.template, &self.documents_dir) {
+            let text = template.render(|key| field_value(person, id, args, key));
+            let doc = if args.documents_as_pdf {
+                text_to_pdf(&text)
+            }
+            else {
+                text.into_bytes()
+            };
+
+            write_numbered_file(&dir.join(numbered_file(id, args.id_offset + args.total, extension)), &doc)?;
+        }
+
+        if let Some(dir) = &self.avatars_dir {
+            let image_path = dir.join(numbered_file(id, args.id_offset + args.total, AVATAR_EXTENSION));
+            write_numbered_file(&image_path, &identicon_png(id))?;
+        }
+
+        if let Some(dir) = &self.barcodes_dir {
+            let image_path = dir.join(numbered_file(id, args.id_offset + args.total, BARCODE_EXTENSION));
+            let png = barcode::code39_png(&barcode_data(person, id, args))?;
+            write_numbered_file(&image_path, &png)?;
         }
+
+        self.total += 1;
+        Ok(())
     }
 }
 
@@ -265,12 +1151,12 @@ pub fn write_people(
 // ----------------------------------------------------------------------------
 
 /**
- * Creates a JSON Lines file from a vector of randomly generated `Person`
+ * Creates a JSON Lines file from a stream of randomly generated `Person`
  * objects. JSON Lines is a line-by-line JSON format, where each object
  * occupies its own text line, and there's no enclosing object or array. For
  * instance:
  *
- * ```
+ * ```text
  * { "first_name": "Moe", ... },
  * { "first_name": "Larry", ... },
  * { "first_name": "Curly", ... },
@@ -284,52 +1170,46 @@ pub fn write_people(
  * # Arguments
  *
  * - `path`: The path to the JSON file to create or overwrite
- * - `header_format`: What style of JSON keys to use.
- * - `generate_ids`: Whether or not to generate and save unique numeric IDs
- *                   for each person
- * - `save_ssns`: Whether or not to save the fake Social Security numbers
- * - `save_salaries`: Whether or not to save generated salary data
- * - `people`: The list of randomly generated people to save. Note that this
- *             parameter isn't a reference and is, therefore, consumed by this
- *             function.
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
  *
  * # Returns
  *
  * - `Ok(total)`: The save was successful, and `total` people were written
- * - `Err(msg)`: Unable to write the CSV file; `msg` explains why.
+ * - `Err(msg)`: Unable to write the JSON file; `msg` explains why.
  */
-
 fn write_jsonl(
-    path: &PathBuf,
-    header_format: HeaderFormat,
-    generate_ids: bool,
-    save_ssns: bool,
-    save_salaries: bool,
-    people: Vec<Person>,
-) -> Result<usize, String> {
-    let file = File::create(path).map_err(|e| format!("{}", e))?;
-    let mut w = LineWriter::new(file);
-    let headers = get_headers(header_format);
-
-    for (i, p) in people.iter().enumerate() {
-        let id = if generate_ids { Some(i + 1) } else {None};
-
-        let jv = person_to_json_object(p, &headers, id, save_ssns, save_salaries)?;
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let file = OutputFile::create(path, args.compression).map_err(|e| format!("{}", e))?;
+    let mut w = BufWriter::new(file);
+    let headers = headers_for(args);
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let jv = person_to_json_object(&p, p.id, args, fields, &headers)?;
 
         let json_line = jv.dump();
 
         w.write_fmt(format_args!("{}\n", json_line))
             .map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+        total += 1;
     }
 
-    Ok(people.len())
+    finish_output(w).map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+    Ok(total)
 }
 
 /**
- * Creates a JSON document from a vector of randomly generated `Person` objects.
- * The JSON output is of this form (though _not_ pretty-printed):
+ * Creates a JSON document from a stream of randomly generated `Person`
+ * objects. The JSON output is of this form (though _not_ pretty-printed):
  *
- * ```
+ * ```text
  * {"people": [
  *   { "first_name": "Moe", ... },
  *   { "first_name": "Larry", ... },
@@ -338,67 +1218,63 @@ fn write_jsonl(
  * ]}
  * ```
  *
+ * The enclosing object is written by hand, around the individual people, so
+ * that the whole document never has to be held in memory.
+ *
  * # Arguments
  *
  * - `path`: The path to the JSON file to create or overwrite
- * - `header_format`: What style of JSON keys to use.
- * - `generate_ids`: Whether or not to generate and save unique numeric IDs
- *                   for each person
- * - `save_ssns`: Whether or not to save the fake Social Security numbers
- * - `save_salaries`: Whether or not to save generated salary data
- * - `people`: The list of randomly generated people to save. Note that this
- *             parameter isn't a reference and is, therefore, consumed by this
- *             function.
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
  *
  * # Returns
  *
  * - `Ok(total)`: The save was successful, and `total` people were written
- * - `Err(msg)`: Unable to write the CSV file; `msg` explains why.
+ * - `Err(msg)`: Unable to write the JSON file; `msg` explains why.
  */
 fn write_json(
-    path: &PathBuf,
-    header_format: HeaderFormat,
-    generate_ids: bool,
-    save_ssns: bool,
-    save_salaries: bool,
-    people: Vec<Person>,
-) -> Result<usize, String> {
-
-    let file = File::create(path).map_err(|e| format!("{}", e))?;
-    let mut w = LineWriter::new(file);
-    let headers = get_headers(header_format);
-    let mut jo = JsonValue::new_object();
-    let mut ja = JsonValue::new_array();
-
-    for (i, p) in people.iter().enumerate() {
-        let id = if generate_ids { Some(i + 1) } else {None};
-
-        let jv = person_to_json_object(p, &headers, id, save_ssns, save_salaries)?;
-        ja.push(jv).map_err(|e| format!("{}", e))?;
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+
+    let file = OutputFile::create(path, args.compression).map_err(|e| format!("{}", e))?;
+    let mut w = BufWriter::new(file);
+    let headers = headers_for(args);
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let mut total = 0;
+
+    w.write_all(b"{\"people\":[").map_err(write_err)?;
+
+    for p in people {
+        let p = p?;
+        let jv = person_to_json_object(&p, p.id, args, fields, &headers)?;
+        if total > 0 {
+            w.write_all(b",").map_err(write_err)?;
+        }
+        w.write_all(jv.dump().as_bytes()).map_err(write_err)?;
+        total += 1;
     }
 
-    jo.insert("people", ja).map_err(|e| format!("{}", e))?;
+    w.write_all(b"]}\n").map_err(write_err)?;
+    finish_output(w).map_err(write_err)?;
 
-    let json_str = jo.dump();
-    w.write_fmt(format_args!("{}\n", json_str))
-        .map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
-
-    Ok(people.len())
+    Ok(total)
 }
 
 /**
- * Creates a CSV from a vector of randomly generated `Person` objects.
+ * Creates a CSV from a stream of randomly generated `Person` objects. The
+ * fields are separated by `args.delimiter`, or commas. `args.quote_all`,
+ * `args.crlf` and `args.bom` pick the rest of the dialect.
  *
  * # Arguments
  *
  * - `path`: The path to the CSV file to create or overwrite
- * - `header_format`: What style of CSV header names or JSON keys to use.
- * - `generate_ids`: Whether or not to generate and save unique numeric IDs
- *                   for each person
- * - `save_ssns`: Whether or not to save the fake Social Security numbers
- * - `people`: The list of randomly generated people to save. Note that this
- *             parameter isn't a reference and is, therefore, consumed by this
- *             function.
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
  *
  * # Returns
  *
@@ -406,73 +1282,1016 @@ fn write_json(
  * - `Err(msg)`: Unable to write the CSV file; `msg` explains why.
  */
 fn write_csv(
-    path: &PathBuf,
-    header_format: HeaderFormat,
-    generate_ids: bool,
-    save_ssns: bool,
-    save_salaries: bool,
-    people: Vec<Person>,
-) -> Result<usize, String> {
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let mut file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    if args.bom {
+        file.write_all("\u{feff}".as_bytes()).map_err(write_err)?;
+    }
 
-    let mut w = WriterBuilder::new()
-        .from_path(path)
-        .map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+    let mut w = csv_builder(args).from_writer(file);
 
-    let headers = get_headers(header_format);
+    let headers = headers_for(args);
 
-    let mut header_rec: Vec<&String> = Vec::new();
+    let header_rec: Vec<&String> = fields
+        .iter()
+        .map(|key| headers.get(key).unwrap())
+        .collect();
 
-    if generate_ids {
-        header_rec.push(headers.get(HEADER_ID_KEY).unwrap())
-    }
+    w.write_record(&header_rec).map_err(|e| format!("{}", e))?;
 
-    for h in REQUIRED_HEADERS {
-        header_rec.push(headers.get(h).unwrap())
+    let mut total = 0;
+
+    match csv_fields(args, fields) {
+        // The usual case: the fields as generated, written straight from
+        // the person, without building a record.
+        Some(csv_fields) => {
+            let mut buffers = CsvBuffers::default();
+            for p in people {
+                let p = p?;
+                for field in &csv_fields {
+                    write_csv_field(&mut w, &p, args, field, &mut buffers).map_err(|e| format!("{}", e))?;
+                }
+                w.write_record(None::<&[u8]>).map_err(|e| format!("{}", e))?;
+                total += 1;
+            }
+        },
+        None => {
+            for p in people {
+                let p = p?;
+                let rec: Vec<String> = fields
+                    .iter()
+                    .map(|key| field_value(&p, p.id, args, key))
+                    .collect();
+
+                w.write_record(&rec).map_err(|e| format!("{}", e))?;
+                total += 1;
+            }
+        },
     }
 
-    if save_ssns {
-        header_rec.push(headers.get(HEADER_SSN_KEY).unwrap());
-    }
+    w.flush().map_err(write_err)?;
+    let file = w.into_inner().map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+    file.finish().map_err(write_err)?;
+    Ok(total)
+}
 
-    if save_salaries {
-        header_rec.push(headers.get(HEADER_SALARY_KEY).unwrap());
-    }
+/// A CSV field, as the fast path in `write_csv()` writes it.
+#[derive(Debug, Clone, PartialEq)]
+enum CsvField<'a> {
+    Id,
+    FirstName,
+    MiddleName,
+    LastName,
+    Gender,
+    BirthDate,
+    Ssn,
+    Salary,
+    /// Any other field, which is written as `generated_value()` gets it
+    Other(&'a str),
+}
 
-    w.write_record(&header_rec).map_err(|e| format!("{}", e))?;
+/// The buffers the CSV fast path formats numbers and dates in, reused from
+/// row to row.
+#[derive(Default)]
+struct CsvBuffers {
+    number: itoa::Buffer,
+    date: [u8; 10],
+}
 
-    for (i, p) in people.iter().enumerate() {
-        let id = i + 1;
-        let id_str = id.to_string();
-        let mut rec: Vec<&String> = Vec::new();
-        let salary = p.salary.to_string();
+/**
+ * Get the fields for the CSV fast path, which writes the people's values
+ * straight from each `Person`. That's only possible when the values are
+ * written as generated: no `--rules`, formatting, hashing or encryption, and
+ * no Active Directory length limits.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ *
+ * # Returns
+ *
+ * The fields, or `None` if the values have to be worked out as strings.
+ */
+fn csv_fields<'a>(args: &Arguments, fields: &'a [String]) -> Option<Vec<CsvField<'a>>> {
+    if args.rules.is_some()
+        || !args.field_formats.is_empty()
+        || !args.field_hashes.is_empty()
+        || !args.field_ciphers.is_empty()
+        || matches!(args.header_format, HeaderFormat::ActiveDirectory) {
+        return None;
+    }
+
+    let field = |key: &'a String| match key.as_str() {
+        HEADER_ID_KEY => CsvField::Id,
+        HEADER_FIRST_NAME_KEY => CsvField::FirstName,
+        HEADER_MIDDLE_NAME_KEY => CsvField::MiddleName,
+        HEADER_LAST_NAME_KEY => CsvField::LastName,
+        HEADER_GENDER_KEY => CsvField::Gender,
+        HEADER_BIRTH_DATE_KEY => CsvField::BirthDate,
+        HEADER_SSN_KEY if matches!(args.ssn_format, SsnFormat::Dashed) => CsvField::Ssn,
+        HEADER_SALARY_KEY => CsvField::Salary,
+        other => CsvField::Other(other),
+    };
+    Some(fields.iter().map(field).collect())
+}
 
-        if generate_ids {
-            rec.push(&id_str);
+/**
+ * Write one field of a person, for the CSV fast path.
+ *
+ * # Arguments
+ *
+ * - `w`: The CSV writer
+ * - `person`: The person
+ * - `args`: The parsed command-line arguments
+ * - `field`: The field, from `csv_fields()`
+ * - `buffers`: Where to format numbers and dates
+ */
+fn write_csv_field<W: Write>(
+    w: &mut csv::Writer<W>,
+    person: &Person,
+    args: &Arguments,
+    field: &CsvField,
+    buffers: &mut CsvBuffers,
+) -> csv::Result<()> {
+    match field {
+        CsvField::Id => w.write_field(buffers.number.format(person.id)),
+        CsvField::FirstName => w.write_field(&*person.first_name),
+        CsvField::MiddleName => w.write_field(&*person.middle_name),
+        CsvField::LastName => w.write_field(&*person.last_name),
+        CsvField::Gender if args.no_gender => w.write_field(""),
+        CsvField::Gender => match &args.gender_values {
+            Some(values) => w.write_field(values.value(&person.gender)),
+            None => w.write_field(person.gender.to_str()),
+        },
+        CsvField::BirthDate => match date_bytes(&person.birth_date, &mut buffers.date) {
+            Some(date) => w.write_field(date),
+            None => w.write_field(date_str(&person.birth_date)),
+        },
+        CsvField::Ssn => w.write_field(&person.ssn),
+        CsvField::Salary => w.write_field(buffers.number.format(person.salary)),
+        CsvField::Other(key) => w.write_field(generated_value(person, person.id, args, key)),
+    }
+}
+
+/**
+ * Format a date as `date_str()` does, "YYYY-MM-DD", without allocating.
+ *
+ * # Arguments
+ *
+ * - `d`: The date
+ * - `buf`: Where to format it
+ *
+ * # Returns
+ *
+ * The formatted date, or `None` if the year isn't from 0 to 9999, which
+ * `date_str()` formats differently.
+ */
+fn date_bytes<'a>(d: &NaiveDate, buf: &'a mut [u8; 10]) -> Option<&'a [u8]> {
+    let year = u32::try_from(d.year()).ok().filter(|y| *y <= 9999)?;
+    let digits = |buf: &mut [u8], mut n: u32| {
+        for b in buf.iter_mut().rev() {
+            *b = b'0' + (n % 10) as u8;
+            n /= 10;
         }
+    };
 
-        let birth_str = date_str(&p.birth_date);
-        let gender_str = p.gender.to_str().to_string();
+    digits(&mut buf[0..4], year);
+    buf[4] = b'-';
+    digits(&mut buf[5..7], d.month());
+    buf[7] = b'-';
+    digits(&mut buf[8..10], d.day());
+    Some(&buf[..])
+}
 
-        rec.extend([
-            &p.first_name,
-            &p.middle_name,
-            &p.last_name,
-            &gender_str,
-            &birth_str,
-        ]);
+/// Get a CSV writer builder for the CSV dialect the options pick.
+fn csv_builder(args: &Arguments) -> WriterBuilder {
+    let mut builder = WriterBuilder::new();
+    builder
+        .delimiter(args.delimiter.unwrap_or(b','))
+        .quote_style(if args.quote_all { QuoteStyle::Always } else { QuoteStyle::Necessary })
+        .terminator(if args.crlf { Terminator::CRLF } else { Terminator::Any(b'\n') });
+    builder
+}
 
-        if save_ssns {
-            rec.push(&p.ssn);
-        }
+/**
+ * Creates an Avro object container file from a stream of randomly generated
+ * `Person` objects. The file embeds a schema for a `Person` record, with one
+ * string field per output field, named according to the header format.
+ * Avro field names can only contain letters, digits and underscores, so
+ * anything else is replaced with an underscore (e.g., "First Name" becomes
+ * "First_Name").
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the Avro file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the Avro file; `msg` explains why.
+ */
+fn write_avro(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let names: Vec<&String> = fields.iter().map(|key| &headers[key]).collect();
+
+    // The sync marker is arbitrary, but deriving it from the seed keeps the
+    // output reproducible.
+    let sync: [u8; 16] = ChaCha8Rng::seed_from_u64(args.seed).gen();
+    let mut w = AvroWriter::new(BufWriter::new(file), &record_schema("Person", &names), sync)
+        .map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_record(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates an Arrow IPC file (also known as a Feather version 2 file) from a
+ * stream of randomly generated `Person` objects. There's one UTF-8 string
+ * column per output field, named according to the header format, and the
+ * rows are written in record batches, so the people don't have to fit in
+ * memory.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the Arrow file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the Arrow file; `msg` explains why.
+ */
+fn write_arrow(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let names: Vec<&String> = fields.iter().map(|key| &headers[key]).collect();
+    let mut w = ArrowWriter::new(BufWriter::new(file), &names).map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_record(&rec).map_err(write_err)?;
+        total += 1;
+    }
 
-        if save_salaries {
-            rec.push(&salary);
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Loads a stream of randomly generated `Person` objects into an existing
+ * Postgres table, as described by `args.postgres`. The table's columns are
+ * named according to the header format, and the values are sent as text,
+ * for Postgres to convert to the column types.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to load, from `output_fields()`
+ * - `people`: The randomly generated people to load
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The load was successful, and `total` people were loaded
+ * - `Err(msg)`: Unable to load the people; `msg` explains why.
+ */
+fn write_postgres(
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    // The arguments parser only picks the Postgres output format when
+    // there are Postgres options.
+    let options = args.postgres.as_ref().unwrap();
+    let headers = headers_for(args);
+    let columns: Vec<&String> = fields.iter().map(|key| &headers[key]).collect();
+    let rows = people.map(|p| {
+        let p = p?;
+        Ok(fields.iter().map(|key| field_value(&p, p.id, args, key)).collect())
+    });
+
+    pg::copy_rows(options, &columns, rows)
+}
+
+/**
+ * Creates a SQL script from a stream of randomly generated `Person` objects:
+ * a `CREATE TABLE people` statement, with one column per output field, named
+ * according to the header format, followed by batched `INSERT` statements.
+ * IDs and salaries are integer columns and birth dates are date columns,
+ * unless `--format-field` or `--encrypt-field` changes them into something
+ * else, in which case they're text, like everything else.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the script to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the script; `msg` explains why.
+ */
+fn write_sql(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let columns = sql_columns(args, fields, &headers);
+    let mut w = SqlWriter::new(BufWriter::new(file), args.sql_dialect, SQL_TABLE, &columns)
+        .map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_row(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates a file of length-delimited protobuf `Person` messages from a
+ * stream of randomly generated `Person` objects. IDs and salaries are
+ * `int64` fields, unless formatting or encryption has made them into
+ * something else; everything else is a string. `--emit-proto` writes the
+ * matching `.proto` definition.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_protobuf(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let types = proto_fields(args, fields, &headers).into_iter().map(|(_, t)| t).collect();
+    let mut w = ProtobufWriter::new(BufWriter::new(file), types);
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_record(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates a fixed-width text file from a stream of randomly generated
+ * `Person` objects, with the columns in `args.layout`. There's no header.
+ * IDs and salaries are zero-padded numbers, unless formatting or encryption
+ * has made them into something else; everything else is space-padded text.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields being generated, from `output_fields()`.
+ *   Every field in the layout has to be one of them (see `validate()`).
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_fixed_width(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let layout = args.layout.as_deref().unwrap_or_default();
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let types: HashMap<&String, ColumnType> = fields
+        .iter()
+        .zip(sql_columns(args, fields, &headers))
+        .map(|(key, (_, t))| (key, t))
+        .collect();
+    let columns = layout
+        .iter()
+        .map(|f| match types.get(&f.field) {
+            Some(ColumnType::Integer) => (f.width, Justify::Number),
+            _ => (f.width, Justify::Text),
+        })
+        .collect();
+    let mut w = FixedWidthWriter::new(BufWriter::new(file), columns);
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = layout
+            .iter()
+            .map(|f| {
+                if f.field == FILLER { String::new() } else { field_value(&p, p.id, args, &f.field) }
+            })
+            .collect();
+
+        w.write_record(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates a vCard file from a stream of randomly generated `Person` objects,
+ * with one card per person. A card holds the name (`N` and `FN`), the birth
+ * date (`BDAY`) and, with `--id`, the ID (`UID`); vCards have no place for
+ * the other fields. Formatting and encryption apply, as in every other
+ * format, so `--format-field birth_date=...` changes the `BDAY` values.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields being generated, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_vcard(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let has_id = fields.iter().any(|f| f == HEADER_ID_KEY);
+    let mut w = VCardWriter::new(BufWriter::new(file));
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let value = |key: &str| field_value(&p, p.id, args, key);
+        let contact = Contact {
+            first_name: value(HEADER_FIRST_NAME_KEY),
+            middle_name: value(HEADER_MIDDLE_NAME_KEY),
+            last_name: value(HEADER_LAST_NAME_KEY),
+            birthday: Some(value(HEADER_BIRTH_DATE_KEY)),
+            uid: if has_id { Some(value(HEADER_ID_KEY)) } else { None },
+        };
+
+        w.write_contact(&contact).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates an LDIF file from a stream of randomly generated `Person` objects,
+ * with one `inetOrgPerson` entry per person, under `args.base_dn`. Each
+ * entry is named by its `uid`, which is a unique account name, as with
+ * `-H ad`. It has the full name (`cn`), the last name (`sn`), the first name
+ * (`givenName`) and, with `--id`, the ID (`employeeNumber`).
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields being generated, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_ldif(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let has_id = fields.iter().any(|f| f == HEADER_ID_KEY);
+    let mut w = LdifWriter::new(BufWriter::new(file));
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let value = |key: &str| field_value(&p, p.id, args, key);
+        let (first_name, last_name) = (value(HEADER_FIRST_NAME_KEY), value(HEADER_LAST_NAME_KEY));
+        let attributes = [
+            ("cn", format!("{first_name} {last_name}")),
+            ("sn", last_name),
+            ("givenName", first_name),
+            ("uid", p.account_name.clone()),
+            ("employeeNumber", if has_id { value(HEADER_ID_KEY) } else { String::new() }),
+        ];
+
+        w.write_entry(&ldif::dn(&p.account_name, &args.base_dn), &attributes).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates an HTML page from a stream of randomly generated `Person`
+ * objects, with a table that can be sorted by clicking the column headers.
+ * IDs and salaries are sorted as numbers, unless formatting or encryption
+ * has made them into something else. The page title is the file name,
+ * without the extension.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_html(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let columns: Vec<Column> = sql_columns(args, fields, &headers)
+        .into_iter()
+        .map(|(header, t)| Column { header: header.to_string(), numeric: t == ColumnType::Integer })
+        .collect();
+    // The output file's real name, not where it's staged, or its
+    // compression extension.
+    let (html_file, _) = split_compression(&args.output_file);
+    let title = html_file.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let mut w = HtmlWriter::new(BufWriter::new(file), &title, &columns).map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_row(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates a GitHub-flavored Markdown table from a stream of randomly
+ * generated `Person` objects. IDs and salaries are right-aligned, unless
+ * formatting or encryption has made them into something else.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_markdown(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let (header_rec, numeric): (Vec<&String>, Vec<bool>) = sql_columns(args, fields, &headers)
+        .into_iter()
+        .map(|(header, t)| (header, t == ColumnType::Integer))
+        .unzip();
+    let mut w = MarkdownWriter::new(BufWriter::new(file), &header_rec, &numeric).map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_row(&rec).map_err(write_err)?;
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Creates an aligned table, for `--preview`, from a stream of randomly
+ * generated `Person` objects. IDs and salaries are right-aligned, as in
+ * Markdown.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to write to (standard output, for a preview)
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the table; `msg` explains why.
+ */
+fn write_table(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let (header_rec, numeric) = table_columns(args, fields, &headers);
+    let mut w = TableWriter::new(BufWriter::new(file), &header_rec, &numeric);
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(&p, p.id, args, key))
+            .collect();
+
+        w.write_row(&rec);
+        total += 1;
+    }
+
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
+}
+
+/**
+ * Print the first few people of a run that's writing a file, too, as an
+ * aligned table (as for `--preview`), with the file's columns and headers.
+ *
+ * # Arguments
+ *
+ * - `w`: Where to print the table
+ * - `args`: The parsed command-line arguments
+ * - `people`: The people to print
+ *
+ * # Returns
+ *
+ * - `Ok(())`: The table was printed.
+ * - `Err(msg)`: Unable to print the table; `msg` explains why.
+ */
+pub fn write_preview<W: Write>(w: W, args: &Arguments, people: &[Person]) -> Result<(), String> {
+    let fields = output_fields(args);
+    let headers = headers_for(args);
+    let (header_rec, numeric) = table_columns(args, &fields, &headers);
+    let mut w = TableWriter::new(w, &header_rec, &numeric);
+
+    for p in people {
+        let rec: Vec<String> = fields
+            .iter()
+            .map(|key| field_value(p, p.id, args, key))
+            .collect();
+        w.write_row(&rec);
+    }
+
+    w.finish().and_then(|mut w| w.flush()).map_err(|e| format!("Can't print the preview: {}", e))
+}
+
+/// The headers of a table's columns, and whether each is right-aligned.
+fn table_columns<'a>(
+    args: &Arguments,
+    fields: &[String],
+    headers: &'a HashMap<String, String>,
+) -> (Vec<&'a String>, Vec<bool>) {
+    sql_columns(args, fields, headers)
+        .into_iter()
+        .map(|(header, t)| (header, t == ColumnType::Integer))
+        .unzip()
+}
+
+/**
+ * Creates a long-format ("tall") file from a stream of randomly generated
+ * `Person` objects: a row per field of each person, holding the person's
+ * ID, the field's name (its header) and the value, instead of a row per
+ * person. The ID is the key, so it isn't a row itself. Every field gets a
+ * row, even an empty one, so each person has the same number of rows.
+ *
+ * CSV output uses the CSV dialect options, JSON Lines output has an object
+ * per row, and JSON output is of the form
+ * `{"values": [{"person_id": "1", "field": "first_name", "value": "Moe"}, ...]}`.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the file to create or overwrite
+ * - `args`: The parsed command-line arguments. `args.output_format` is CSV,
+ *   JSON Lines, JSON or a table.
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the file; `msg` explains why.
+ */
+fn write_long(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let mut file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let columns = long_headers(args.header_format);
+    let fields: Vec<&String> = fields.iter().filter(|key| *key != HEADER_ID_KEY).collect();
+
+    // Calls `write_row` with each row's ID, field name and value.
+    let write_rows = |write_row: &mut dyn FnMut([&str; 3]) -> Result<(), String>| {
+        let mut total = 0;
+        for p in people {
+            let p = p?;
+            let id = p.id.to_string();
+            for key in &fields {
+                write_row([&id, headers.get(*key).unwrap(), &field_value(&p, p.id, args, key)])?;
+            }
+            total += 1;
         }
+        Ok::<u64, String>(total)
+    };
 
-        w.write_record(&rec).map_err(|e| format!("{}", e))?;
+    match args.output_format {
+        OutputFormat::JsonL | OutputFormat::JsonPretty => {
+            let pretty = args.output_format == OutputFormat::JsonPretty;
+            let mut w = BufWriter::new(file);
+            let mut rows = 0;
+            if pretty {
+                w.write_all(b"{\"values\":[").map_err(write_err)?;
+            }
+
+            let total = write_rows(&mut |row| {
+                let mut rec = JsonValue::new_object();
+                for (column, value) in columns.iter().zip(row) {
+                    rec.insert(column, value).map_err(|e| format!("{}", e))?;
+                }
+                if pretty && rows > 0 {
+                    w.write_all(b",").map_err(write_err)?;
+                }
+                rows += 1;
+                w.write_all(rec.dump().as_bytes()).map_err(write_err)?;
+                if !pretty {
+                    w.write_all(b"\n").map_err(write_err)?;
+                }
+                Ok(())
+            })?;
+
+            if pretty {
+                w.write_all(b"]}\n").map_err(write_err)?;
+            }
+            finish_output(w).map_err(write_err)?;
+            Ok(total)
+        },
+
+        OutputFormat::Table => {
+            let header_rec: Vec<&String> = columns.iter().collect();
+            let mut w = TableWriter::new(BufWriter::new(file), &header_rec, &[true, false, false]);
+            let total = write_rows(&mut |row| {
+                w.write_row(&row);
+                Ok(())
+            })?;
+            w.finish().and_then(finish_output).map_err(write_err)?;
+            Ok(total)
+        },
+
+        // Validation allows nothing else.
+        _ => {
+            if args.bom {
+                file.write_all("\u{feff}".as_bytes()).map_err(write_err)?;
+            }
+            let mut w = csv_builder(args).from_writer(file);
+            w.write_record(&columns).map_err(|e| format!("{}", e))?;
+            let total = write_rows(&mut |row| w.write_record(row).map_err(|e| format!("{}", e)))?;
+
+            w.flush().map_err(write_err)?;
+            let file = w.into_inner().map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))?;
+            file.finish().map_err(write_err)?;
+            Ok(total)
+        },
+    }
+}
+
+/// Get the column headers for long-format output: the person's ID, the
+/// field's name, and the value.
+fn long_headers(header_format: HeaderFormat) -> [String; 3] {
+    let [id, field, value] = match header_format {
+        HeaderFormat::SnakeCase => ["person_id", "field", "value"],
+        HeaderFormat::Pretty => ["Person ID", "Field", "Value"],
+        HeaderFormat::CamelCase | HeaderFormat::ActiveDirectory => ["personId", "field", "value"],
+    };
+    [id.to_string(), field.to_string(), value.to_string()]
+}
+
+/// Get the protobuf field names and types for the fields to be written.
+/// They're typed like the SQL columns, except that dates are strings.
+fn proto_fields<'a>(
+    args: &Arguments,
+    fields: &[String],
+    headers: &'a HashMap<String, String>,
+) -> Vec<(&'a String, FieldType)> {
+    sql_columns(args, fields, headers)
+        .into_iter()
+        .map(|(name, t)| match t {
+            ColumnType::Integer => (name, FieldType::Int64),
+            ColumnType::Date | ColumnType::Text => (name, FieldType::String),
+        })
+        .collect()
+}
+
+/**
+ * Get the SQL column names and types for the fields to be written. Formatted
+ * and encrypted fields are text, whatever they started out as.
+ */
+fn sql_columns<'a>(
+    args: &Arguments,
+    fields: &[String],
+    headers: &'a HashMap<String, String>,
+) -> Vec<(&'a String, ColumnType)> {
+    fields
+        .iter()
+        .map(|key| {
+            let transformed = args.field_formats.iter().any(|f| &f.field == key) ||
+                              args.field_ciphers.iter().any(|c| &c.field == key);
+            let t = match key.as_str() {
+                _ if transformed => ColumnType::Text,
+                HEADER_ID_KEY | HEADER_SALARY_KEY | HEADER_POINTS_BALANCE_KEY => ColumnType::Integer,
+                HEADER_BIRTH_DATE_KEY |
+                HEADER_SIGNUP_DATE_KEY |
+                HEADER_LAST_ACTIVE_DATE_KEY |
+                HEADER_MEMBER_SINCE_KEY => ColumnType::Date,
+                _ => ColumnType::Text,
+            };
+            (&headers[key], t)
+        })
+        .collect()
+}
+
+/**
+ * Creates an Excel workbook from a stream of randomly generated `Person`
+ * objects, with one worksheet, whose first row holds the column headers.
+ * Birth, signup, last-active and membership dates are written as real dates,
+ * and IDs, salaries and points balances as numbers,
+ * unless formatting or encryption has made them into something else;
+ * everything else (including SSNs) is written as text, so Excel leaves it
+ * alone.
+ *
+ * # Arguments
+ *
+ * - `path`: The path to the workbook to create or overwrite
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `people`: The randomly generated people to save
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The save was successful, and `total` people were written
+ * - `Err(msg)`: Unable to write the workbook; `msg` explains why.
+ */
+fn write_xlsx(
+    path: &Path,
+    args: &Arguments,
+    fields: &[String],
+    people: impl Iterator<Item = Result<Person, String>>,
+) -> Result<u64, String> {
+    let write_err = |e: io::Error| format!("Can't write to \"{}\": {}", path_str(path), e);
+    let file = OutputFile::create(path, args.compression).map_err(write_err)?;
+    let headers = headers_for(args);
+    let names: Vec<&String> = fields.iter().map(|key| &headers[key]).collect();
+    let mut w = XlsxWriter::new(BufWriter::new(file), &names).map_err(write_err)?;
+    let mut total = 0;
+
+    for p in people {
+        let p = p?;
+        let row: Vec<Cell> = fields
+            .iter()
+            .map(|key| {
+                let value = field_value(&p, p.id, args, key);
+                match key.as_str() {
+                    HEADER_BIRTH_DATE_KEY |
+                    HEADER_SIGNUP_DATE_KEY |
+                    HEADER_LAST_ACTIVE_DATE_KEY |
+                    HEADER_MEMBER_SINCE_KEY => match NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                        Ok(d) => Cell::Date(d),
+                        Err(_) => Cell::Text(value),
+                    },
+                    HEADER_ID_KEY | HEADER_SALARY_KEY | HEADER_POINTS_BALANCE_KEY => match value.parse::<u64>() {
+                        Ok(n) => Cell::Number(n as f64),
+                        Err(_) => Cell::Text(value),
+                    },
+                    _ => Cell::Text(value),
+                }
+            })
+            .collect();
+
+        w.write_row(&row).map_err(write_err)?;
+        total += 1;
     }
 
-    Ok(people.len())
+    w.finish().and_then(finish_output).map_err(write_err)?;
+    Ok(total)
 }
 
 /**
@@ -481,9 +2300,10 @@ fn write_csv(
  * # Arguments
  *
  * - `person`: The `Person` object
- * - `headers`: A map of the keys to use, from `get_headers()`
- * - `opt_id`: A `Some` with the generated ID for the user, or `None` for no ID
- * - `save_ssn`: Whether or not to save the Social Security number
+ * - `id`: The generated ID for the person
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ * - `headers`: A map of the keys to use, from `headers_for()`
  *
  * # Returns
  *
@@ -492,61 +2312,468 @@ fn write_csv(
  */
 fn person_to_json_object(
     person: &Person,
-    headers: &HashMap<&str, String>,
-    opt_id: Option<usize>,
-    save_ssn: bool,
-    save_salary: bool
+    id: u64,
+    args: &Arguments,
+    fields: &[String],
+    headers: &HashMap<String, String>,
 ) -> Result<JsonValue, String> {
-    let id_key = headers.get(HEADER_ID_KEY).unwrap();
-    let first_name_key = headers.get(HEADER_FIRST_NAME_KEY).unwrap();
-    let middle_name_key = headers.get(HEADER_MIDDLE_NAME_KEY).unwrap();
-    let last_name_key = headers.get(HEADER_LAST_NAME_KEY).unwrap();
-    let gender_key = headers.get(HEADER_GENDER_KEY).unwrap();
-    let birth_date_key = headers.get(HEADER_BIRTH_DATE_KEY).unwrap();
-    let ssn_key = headers.get(HEADER_SSN_KEY).unwrap();
-    let salary_key = headers.get(HEADER_SALARY_KEY).unwrap();
-
     let mut rec = JsonValue::new_object();
-    let s_id = opt_id.map(|i| i.to_string());
-    let s_gender = person.gender.to_string();
-    let s_date = date_str(&person.birth_date);
 
-    // Have to clone each of the people fields, because the JsonValue
-    // object wants to capture them (and doesn't support &String).
-    let first_name = person.first_name.to_string();
-    let middle_name = person.middle_name.to_string();
-    let last_name = person.last_name.to_string();
-    let ssn = person.ssn.to_string();
-    let salary = person.salary.to_string();
+    // The JsonValue object wants to capture each value (and doesn't support
+    // &String), so field_value() hands back owned strings.
+    for key in fields {
+        rec.insert(headers.get(key).unwrap(), field_value(person, id, args, key))
+            .map_err(|e| format!("{}", e))?;
+    }
+
+    Ok(rec)
+}
+
+/**
+ * Determine which fields are to be written, and in what order, based on
+ * the command-line settings.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * The keys of the fields to write. Each key is also a key in the map
+ * returned by `headers_for()`.
+ */
+pub fn output_fields(args: &Arguments) -> Vec<String> {
+    let mut fields: Vec<&str> = Vec::new();
+
+    if args.generate_ids {
+        fields.push(HEADER_ID_KEY);
+    }
 
-    if let Some(s) = s_id {
-        rec.insert(&id_key, s).map_err(|e| format!("{}", e))?;
+    if let HeaderFormat::ActiveDirectory = args.header_format {
+        fields.push(HEADER_ACCOUNT_NAME_KEY);
+        fields.push(HEADER_PRINCIPAL_NAME_KEY);
     }
 
-    rec.insert(&first_name_key, first_name)
-        .map_err(|e| format!("{}", e))?;
-    rec.insert(&middle_name_key, middle_name)
-        .map_err(|e| format!("{}", e))?;
-    rec.insert(&last_name_key, last_name)
-        .map_err(|e| format!("{}", e))?;
-    rec.insert(&gender_key, s_gender)
-        .map_err(|e| format!("{}", e))?;
-    rec.insert(&birth_date_key, s_date)
-        .map_err(|e| format!("{}", e))?;
+    fields.extend(REQUIRED_HEADERS.iter().filter(|f| !args.no_gender || **f != HEADER_GENDER_KEY));
 
-    if save_ssn {
-        rec.insert(&ssn_key, ssn).map_err(|e| format!("{}", e))?;
+    if args.pronouns.is_some() {
+        fields.push(HEADER_PRONOUNS_KEY);
     }
 
-    if save_salary {
-        rec.insert(&salary_key, salary).map_err(|e| format!("{}", e))?;
+    if args.generate_ssns {
+        fields.push(HEADER_SSN_KEY);
     }
 
-    Ok(rec)
+    if args.npi {
+        fields.push(HEADER_NPI_KEY);
+    }
+
+    if args.dea {
+        fields.push(HEADER_DEA_NUMBER_KEY);
+    }
+
+    if args.generate_salaries {
+        fields.push(HEADER_SALARY_KEY);
+    }
+
+    if args.vin.is_some() {
+        fields.push(HEADER_VIN_KEY);
+    }
+
+    if args.barcode_source.is_some() {
+        fields.push(HEADER_BARCODE_KEY);
+    }
+
+    if args.avatars_dir.is_some() {
+        fields.push(HEADER_AVATAR_PATH_KEY);
+    }
+
+    if args.avatar_base_url.is_some() {
+        fields.push(HEADER_AVATAR_URL_KEY);
+    }
+
+    if args.metadata.is_some() {
+        fields.extend(METADATA_HEADERS);
+    }
+
+    if args.device {
+        fields.extend(DEVICE_HEADERS);
+    }
+
+    if args.consent.is_some() {
+        fields.extend(CONSENT_HEADERS);
+    }
+
+    if args.lifecycle.is_some() {
+        fields.extend(LIFECYCLE_HEADERS);
+    }
+
+    if args.loyalty.is_some() {
+        fields.extend(LOYALTY_HEADERS);
+    }
+
+    let mut fields: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+
+    for h in &args.field_hashes {
+        fields.push(h.key());
+    }
+
+    fields
+}
+
+/**
+ * Determine the order of the keys in each JSON object. JSON objects are
+ * written with their keys in the order they're inserted, so by default, the
+ * keys come out in the same (stable) order as the CSV columns. With
+ * `--sort-keys`, they're sorted by key name, instead.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `fields`: The keys of the fields to write, from `output_fields()`
+ *
+ * # Returns
+ *
+ * The field keys, in the order in which they're to be written.
+ */
+fn json_key_order(args: &Arguments, fields: Vec<String>) -> Vec<String> {
+    let mut fields = fields;
+
+    if args.sort_keys {
+        let headers = headers_for(args);
+        fields.sort_by_key(|key| headers.get(key).unwrap().to_string());
+    }
+
+    fields
+}
+
+/**
+ * Get the value of a single field of a `Person`, as a string, ready to be
+ * written: formatted, and encrypted or tokenized, if requested.
+ *
+ * # Arguments
+ *
+ * - `person`: The `Person` object
+ * - `id`: The generated ID for the person
+ * - `args`: The parsed command-line arguments
+ * - `key`: The field key, as returned by `output_fields()`
+ *
+ * # Returns
+ *
+ * The string value of the field.
+ */
+fn field_value(person: &Person, id: u64, args: &Arguments, key: &str) -> String {
+    let value = match args.field_hashes.iter().find(|h| h.key() == key) {
+        Some(h) => h.algorithm.hash(&formatted_value(person, id, args, &h.field)),
+        None => formatted_value(person, id, args, key),
+    };
+
+    protect_field(&args.field_ciphers, key, id, args.seed, value)
 }
 
+/**
+ * Get the value of a single field of a `Person`, as a string, with any
+ * `--rules`, `--gender-values` and formatting applied, but before encryption
+ * or tokenization. Hash fields are computed from these values, so that
+ * hashes correspond to the values that would be written.
+ *
+ * # Arguments
+ *
+ * - `person`: The `Person` object
+ * - `id`: The generated ID for the person
+ * - `args`: The parsed command-line arguments
+ * - `key`: The field key. Hash fields aren't handled here.
+ *
+ * # Returns
+ *
+ * The string value of the field.
+ */
+fn formatted_value(person: &Person, id: u64, args: &Arguments, key: &str) -> String {
+    let value = match (&args.gender_values, key) {
+        (Some(values), HEADER_GENDER_KEY) => values.relabel(ruled_value(person, id, args, key)),
+        _ => ruled_value(person, id, args, key),
+    };
+    let value = format_field(&args.field_formats, key, value);
+
+    // Active Directory enforces length limits on its attributes.
+    match (args.header_format, key) {
+        (HeaderFormat::ActiveDirectory, HEADER_ID_KEY) => {
+            ad::truncate(&value, ad::MAX_EMPLOYEE_ID)
+        },
+        (HeaderFormat::ActiveDirectory, HEADER_FIRST_NAME_KEY) => {
+            ad::truncate(&value, ad::MAX_GIVEN_NAME)
+        },
+        (HeaderFormat::ActiveDirectory, HEADER_MIDDLE_NAME_KEY) => {
+            ad::truncate(&value, ad::MAX_MIDDLE_NAME)
+        },
+        (HeaderFormat::ActiveDirectory, HEADER_LAST_NAME_KEY) => {
+            ad::truncate(&value, ad::MAX_SURNAME)
+        },
+        _ => value,
+    }
+}
+
+/**
+ * Get the value of a single field of a `Person`, with any `--rules` applied,
+ * but before formatting. Assertions are checked against these values.
+ *
+ * # Arguments
+ *
+ * - `person`: The `Person` object
+ * - `id`: The generated ID for the person
+ * - `args`: The parsed command-line arguments
+ * - `key`: The field key. Hash fields aren't handled here.
+ *
+ * # Returns
+ *
+ * The string value of the field.
+ */
+pub fn ruled_value(person: &Person, id: u64, args: &Arguments, key: &str) -> String {
+    let generated = || generated_value(person, id, args, key);
+    match &args.rules {
+        Some(rules) => rules
+            .apply(key, |field| generated_value(person, id, args, field))
+            .unwrap_or_else(generated),
+        None => generated(),
+    }
+}
+
+/**
+ * Get the value of a single field of a `Person`, as generated: before any
+ * `--rules` change it, and before formatting. Rule conditions are checked
+ * against these values.
+ *
+ * # Arguments
+ *
+ * - `person`: The `Person` object
+ * - `id`: The generated ID for the person
+ * - `args`: The parsed command-line arguments
+ * - `key`: The field key. Hash fields aren't handled here.
+ *
+ * # Returns
+ *
+ * The string value of the field.
+ */
+fn generated_value(person: &Person, id: u64, args: &Arguments, key: &str) -> String {
+    match key {
+        HEADER_ID_KEY => id.to_string(),
+        HEADER_FIRST_NAME_KEY => person.first_name.to_string(),
+        HEADER_MIDDLE_NAME_KEY => person.middle_name.to_string(),
+        HEADER_LAST_NAME_KEY => person.last_name.to_string(),
+        // Templates can still refer to the gender, but it's blank with
+        // --no-gender.
+        HEADER_GENDER_KEY if args.no_gender => String::new(),
+        HEADER_GENDER_KEY => person.gender.to_string(),
+        HEADER_BIRTH_DATE_KEY => date_str(&person.birth_date),
+        HEADER_SSN_KEY => args.ssn_format.apply(&person.ssn),
+        HEADER_NPI_KEY => person.npi.to_string(),
+        HEADER_DEA_NUMBER_KEY => person.dea_number.to_string(),
+        HEADER_SALARY_KEY => person.salary.to_string(),
+        HEADER_VIN_KEY => person.vin.to_string(),
+        HEADER_ACCOUNT_NAME_KEY => person.account_name.to_string(),
+        HEADER_PRINCIPAL_NAME_KEY => {
+            ad::user_principal_name(&person.account_name, &args.ad_domain)
+        },
+        HEADER_BARCODE_KEY => {
+            // IDs and SSNs only contain characters Code 39 can encode.
+            barcode::code39_payload(&barcode_data(person, id, args))
+                .unwrap_or_default()
+        },
+        HEADER_AVATAR_PATH_KEY => {
+            let dir = args.avatars_dir.as_deref().unwrap_or(Path::new(""));
+            path_str(&dir.join(numbered_file(id, args.id_offset + args.total, AVATAR_EXTENSION)))
+                .to_string()
+        },
+        HEADER_AVATAR_URL_KEY => {
+            // URLs always use "/", regardless of the platform's separator.
+            let file = numbered_file(id, args.id_offset + args.total, AVATAR_EXTENSION);
+            let parts: Vec<&str> = file.iter().filter_map(|c| c.to_str()).collect();
+            let base = args.avatar_base_url.as_deref().unwrap_or("");
+            format!("{}/{}", base.trim_end_matches('/'), parts.join("/"))
+        },
+        HEADER_CREATED_AT_KEY => metadata_value(person, |m| timestamp_str(&m.created_at)),
+        HEADER_UPDATED_AT_KEY => metadata_value(person, |m| timestamp_str(&m.updated_at)),
+        HEADER_SOURCE_SYSTEM_KEY => metadata_value(person, |m| m.source_system.clone()),
+        HEADER_RECORD_UUID_KEY => metadata_value(person, |m| m.record_uuid.clone()),
+        HEADER_PRONOUNS_KEY => person.pronouns.to_string(),
+        HEADER_DEVICE_TYPE_KEY => device_value(person, |d| d.device_type.clone()),
+        HEADER_OS_KEY => device_value(person, |d| d.os.clone()),
+        HEADER_USER_AGENT_KEY => device_value(person, |d| d.user_agent.clone()),
+        HEADER_EMAIL_OPT_IN_KEY => consent_value(person, |c| c.email_opt_in.to_string()),
+        HEADER_SMS_OPT_IN_KEY => consent_value(person, |c| c.sms_opt_in.to_string()),
+        HEADER_CONSENT_TIMESTAMP_KEY => consent_value(person, |c| timestamp_str(&c.consent_timestamp)),
+        HEADER_CONSENT_VERSION_KEY => consent_value(person, |c| c.consent_version.clone()),
+        HEADER_SIGNUP_DATE_KEY => lifecycle_value(person, |l| date_str(&l.signup_date)),
+        HEADER_LAST_ACTIVE_DATE_KEY => lifecycle_value(person, |l| date_str(&l.last_active_date)),
+        HEADER_CHURNED_KEY => lifecycle_value(person, |l| l.churned.to_string()),
+        HEADER_LIFETIME_VALUE_KEY => lifecycle_value(person, |l| money_str(l.lifetime_value)),
+        HEADER_LOYALTY_TIER_KEY => loyalty_value(person, |l| l.loyalty_tier.clone()),
+        HEADER_POINTS_BALANCE_KEY => loyalty_value(person, |l| l.points_balance.to_string()),
+        HEADER_MEMBER_SINCE_KEY => loyalty_value(person, |l| date_str(&l.member_since)),
+        _ => String::new(),
+    }
+}
+
+/**
+ * Get the relative path of a generated per-person file (document, avatar,
+ * etc.): a numbered subdirectory holding at most `FILES_PER_DIRECTORY` files,
+ * and a file named after the ID, zero-padded so the names sort properly.
+ *
+ * # Arguments
+ *
+ * - `id`: The person's ID, starting at 1
+ * - `last_id`: The last person's ID
+ * - `extension`: The file extension
+ *
+ * # Returns
+ *
+ * The relative path, e.g., `0000/000001.png`.
+ */
+fn numbered_file(id: u64, last_id: u64, extension: &str) -> PathBuf {
+    let width = last_id.to_string().len().max(6);
+    let subdir = format!("{:04}", (id - 1) / FILES_PER_DIRECTORY);
+    let name = format!("{:0width$}.{}", id, extension, width = width);
+    [subdir, name].iter().collect()
+}
+
+/// Write a file returned by numbered_file(), creating its directory if
+/// necessary.
+fn write_numbered_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.is_dir() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Can't create \"{}\": {}", path_str(parent), e))?;
+        }
+    }
+
+    fs::write(path, contents)
+        .map_err(|e| format!("Can't write to \"{}\": {}", path_str(path), e))
+}
+
+/// Get the value to encode in a person's barcode.
+fn barcode_data(person: &Person, id: u64, args: &Arguments) -> String {
+    match args.barcode_source {
+        Some(BarcodeSource::Ssn) => person.ssn.to_string(),
+        Some(BarcodeSource::Id) | None => id.to_string(),
+    }
+}
+
+/// Get a metadata value for a person, or "" if there's no metadata.
+fn metadata_value<F>(person: &Person, get: F) -> String
+where
+    F: Fn(&RecordMetadata) -> String,
+{
+    person.metadata.as_ref().map(get).unwrap_or_default()
+}
+
+/// Get a device value for a person, or "" if there's no device.
+fn device_value<F>(person: &Person, get: F) -> String
+where
+    F: Fn(&Device) -> String,
+{
+    person.device.as_ref().map(get).unwrap_or_default()
+}
+
+/// Get a consent value for a person, or "" if there's no consent.
+fn consent_value<F>(person: &Person, get: F) -> String
+where
+    F: Fn(&Consent) -> String,
+{
+    person.consent.as_ref().map(get).unwrap_or_default()
+}
+
+/// Get a lifecycle value for a person, or "" if there are no lifecycle
+/// fields.
+fn lifecycle_value<F>(person: &Person, get: F) -> String
+where
+    F: Fn(&Lifecycle) -> String,
+{
+    person.lifecycle.as_ref().map(get).unwrap_or_default()
+}
+
+/// Get a loyalty value for a person, or "" if there are no loyalty fields.
+fn loyalty_value<F>(person: &Person, get: F) -> String
+where
+    F: Fn(&Loyalty) -> String,
+{
+    person.loyalty.as_ref().map(get).unwrap_or_default()
+}
+
+/// Format a date as "YYYY-MM-DD", as `date_bytes()` does when it can, which
+/// is much cheaper than chrono's formatting.
 fn date_str(d: &NaiveDate) -> String {
-    d.format("%Y-%m-%d").to_string()
+    let mut buf = [0; 10];
+    match date_bytes(d, &mut buf) {
+        Some(date) => date.iter().copied().map(char::from).collect(),
+        None => d.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/**
+ * Get the header names (CSV column names, JSON keys) to use for all the
+ * fields, including any hash fields.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ *
+ * # Returns
+ *
+ * A map from field keys to header names.
+ */
+fn headers_for(args: &Arguments) -> HashMap<String, String> {
+    let mut m: HashMap<String, String> = get_headers(args.header_format)
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+    for h in &args.field_hashes {
+        // The field was validated when the arguments were parsed.
+        let base = m.get(&h.field).cloned().unwrap_or_default();
+        let header = match args.header_format {
+            HeaderFormat::SnakeCase => format!("{base}_hash"),
+            HeaderFormat::Pretty => format!("{base} Hash"),
+            HeaderFormat::CamelCase | HeaderFormat::ActiveDirectory => format!("{base}Hash"),
+        };
+        m.insert(h.key(), header);
+    }
+
+    m
+}
+
+/**
+ * Get the field key for a header name (a CSV column name or JSON key), in
+ * any of the header formats. The reverse of `headers_for()`.
+ *
+ * # Arguments
+ *
+ * - `header`: The header name
+ *
+ * # Returns
+ *
+ * The field key, or `None` if no header format uses the name. A hash
+ * field's key is the hashed field's key with the hash suffix, as with
+ * `FieldHash::key()`.
+ */
+pub fn header_key(header: &str) -> Option<String> {
+    let formats = [
+        HeaderFormat::SnakeCase,
+        HeaderFormat::CamelCase,
+        HeaderFormat::Pretty,
+        HeaderFormat::ActiveDirectory,
+    ];
+
+    formats.iter().find_map(|format| {
+        let headers = get_headers(*format);
+        let key_of = |name: &str| headers.iter().find(|(_, h)| *h == name).map(|(k, _)| k.to_string());
+        let hashed = match format {
+            HeaderFormat::SnakeCase => header.strip_suffix("_hash"),
+            HeaderFormat::Pretty => header.strip_suffix(" Hash"),
+            HeaderFormat::CamelCase | HeaderFormat::ActiveDirectory => header.strip_suffix("Hash"),
+        };
+
+        key_of(header).or_else(|| hashed.and_then(key_of).map(|k| format!("{k}{HASH_FIELD_SUFFIX}")))
+    })
 }
 
 fn get_headers(header_format: HeaderFormat) -> HashMap<&'static str, String> {
@@ -561,7 +2788,34 @@ fn get_headers(header_format: HeaderFormat) -> HashMap<&'static str, String> {
             m.insert(HEADER_GENDER_KEY, String::from("gender"));
             m.insert(HEADER_BIRTH_DATE_KEY, String::from("birth_date"));
             m.insert(HEADER_SSN_KEY, String::from("ssn"));
+            m.insert(HEADER_NPI_KEY, String::from("npi"));
+            m.insert(HEADER_DEA_NUMBER_KEY, String::from("dea_number"));
             m.insert(HEADER_SALARY_KEY, String::from("salary"));
+            m.insert(HEADER_VIN_KEY, String::from("vin"));
+            m.insert(HEADER_ACCOUNT_NAME_KEY, String::from("account_name"));
+            m.insert(HEADER_PRINCIPAL_NAME_KEY, String::from("principal_name"));
+            m.insert(HEADER_AVATAR_PATH_KEY, String::from("avatar_path"));
+            m.insert(HEADER_AVATAR_URL_KEY, String::from("avatar_url"));
+            m.insert(HEADER_BARCODE_KEY, String::from("barcode"));
+            m.insert(HEADER_CREATED_AT_KEY, String::from("created_at"));
+            m.insert(HEADER_UPDATED_AT_KEY, String::from("updated_at"));
+            m.insert(HEADER_SOURCE_SYSTEM_KEY, String::from("source_system"));
+            m.insert(HEADER_RECORD_UUID_KEY, String::from("record_uuid"));
+            m.insert(HEADER_PRONOUNS_KEY, String::from("pronouns"));
+            m.insert(HEADER_DEVICE_TYPE_KEY, String::from("device_type"));
+            m.insert(HEADER_OS_KEY, String::from("os"));
+            m.insert(HEADER_USER_AGENT_KEY, String::from("user_agent"));
+            m.insert(HEADER_EMAIL_OPT_IN_KEY, String::from("email_opt_in"));
+            m.insert(HEADER_SMS_OPT_IN_KEY, String::from("sms_opt_in"));
+            m.insert(HEADER_CONSENT_TIMESTAMP_KEY, String::from("consent_timestamp"));
+            m.insert(HEADER_CONSENT_VERSION_KEY, String::from("consent_version"));
+            m.insert(HEADER_SIGNUP_DATE_KEY, String::from("signup_date"));
+            m.insert(HEADER_LAST_ACTIVE_DATE_KEY, String::from("last_active_date"));
+            m.insert(HEADER_CHURNED_KEY, String::from("churned"));
+            m.insert(HEADER_LIFETIME_VALUE_KEY, String::from("lifetime_value"));
+            m.insert(HEADER_LOYALTY_TIER_KEY, String::from("loyalty_tier"));
+            m.insert(HEADER_POINTS_BALANCE_KEY, String::from("points_balance"));
+            m.insert(HEADER_MEMBER_SINCE_KEY, String::from("member_since"));
         }
         HeaderFormat::CamelCase => {
             m.insert(HEADER_ID_KEY, String::from("id"));
@@ -571,7 +2825,34 @@ fn get_headers(header_format: HeaderFormat) -> HashMap<&'static str, String> {
             m.insert(HEADER_GENDER_KEY, String::from("gender"));
             m.insert(HEADER_BIRTH_DATE_KEY, String::from("birthDate"));
             m.insert(HEADER_SSN_KEY, String::from("ssn"));
+            m.insert(HEADER_NPI_KEY, String::from("npi"));
+            m.insert(HEADER_DEA_NUMBER_KEY, String::from("deaNumber"));
             m.insert(HEADER_SALARY_KEY, String::from("salary"));
+            m.insert(HEADER_VIN_KEY, String::from("vin"));
+            m.insert(HEADER_ACCOUNT_NAME_KEY, String::from("accountName"));
+            m.insert(HEADER_PRINCIPAL_NAME_KEY, String::from("principalName"));
+            m.insert(HEADER_AVATAR_PATH_KEY, String::from("avatarPath"));
+            m.insert(HEADER_AVATAR_URL_KEY, String::from("avatarUrl"));
+            m.insert(HEADER_BARCODE_KEY, String::from("barcode"));
+            m.insert(HEADER_CREATED_AT_KEY, String::from("createdAt"));
+            m.insert(HEADER_UPDATED_AT_KEY, String::from("updatedAt"));
+            m.insert(HEADER_SOURCE_SYSTEM_KEY, String::from("sourceSystem"));
+            m.insert(HEADER_RECORD_UUID_KEY, String::from("recordUuid"));
+            m.insert(HEADER_PRONOUNS_KEY, String::from("pronouns"));
+            m.insert(HEADER_DEVICE_TYPE_KEY, String::from("deviceType"));
+            m.insert(HEADER_OS_KEY, String::from("os"));
+            m.insert(HEADER_USER_AGENT_KEY, String::from("userAgent"));
+            m.insert(HEADER_EMAIL_OPT_IN_KEY, String::from("emailOptIn"));
+            m.insert(HEADER_SMS_OPT_IN_KEY, String::from("smsOptIn"));
+            m.insert(HEADER_CONSENT_TIMESTAMP_KEY, String::from("consentTimestamp"));
+            m.insert(HEADER_CONSENT_VERSION_KEY, String::from("consentVersion"));
+            m.insert(HEADER_SIGNUP_DATE_KEY, String::from("signupDate"));
+            m.insert(HEADER_LAST_ACTIVE_DATE_KEY, String::from("lastActiveDate"));
+            m.insert(HEADER_CHURNED_KEY, String::from("churned"));
+            m.insert(HEADER_LIFETIME_VALUE_KEY, String::from("lifetimeValue"));
+            m.insert(HEADER_LOYALTY_TIER_KEY, String::from("loyaltyTier"));
+            m.insert(HEADER_POINTS_BALANCE_KEY, String::from("pointsBalance"));
+            m.insert(HEADER_MEMBER_SINCE_KEY, String::from("memberSince"));
         }
         HeaderFormat::Pretty => {
             m.insert(HEADER_ID_KEY, String::from("ID"));
@@ -581,7 +2862,71 @@ fn get_headers(header_format: HeaderFormat) -> HashMap<&'static str, String> {
             m.insert(HEADER_GENDER_KEY, String::from("Gender"));
             m.insert(HEADER_BIRTH_DATE_KEY, String::from("Birth Date"));
             m.insert(HEADER_SSN_KEY, String::from("SSN"));
+            m.insert(HEADER_NPI_KEY, String::from("NPI"));
+            m.insert(HEADER_DEA_NUMBER_KEY, String::from("DEA Number"));
             m.insert(HEADER_SALARY_KEY, String::from("Salary"));
+            m.insert(HEADER_VIN_KEY, String::from("VIN"));
+            m.insert(HEADER_ACCOUNT_NAME_KEY, String::from("Account Name"));
+            m.insert(HEADER_PRINCIPAL_NAME_KEY, String::from("Principal Name"));
+            m.insert(HEADER_AVATAR_PATH_KEY, String::from("Avatar Path"));
+            m.insert(HEADER_AVATAR_URL_KEY, String::from("Avatar URL"));
+            m.insert(HEADER_BARCODE_KEY, String::from("Barcode"));
+            m.insert(HEADER_CREATED_AT_KEY, String::from("Created At"));
+            m.insert(HEADER_UPDATED_AT_KEY, String::from("Updated At"));
+            m.insert(HEADER_SOURCE_SYSTEM_KEY, String::from("Source System"));
+            m.insert(HEADER_RECORD_UUID_KEY, String::from("Record UUID"));
+            m.insert(HEADER_PRONOUNS_KEY, String::from("Pronouns"));
+            m.insert(HEADER_DEVICE_TYPE_KEY, String::from("Device Type"));
+            m.insert(HEADER_OS_KEY, String::from("OS"));
+            m.insert(HEADER_USER_AGENT_KEY, String::from("User Agent"));
+            m.insert(HEADER_EMAIL_OPT_IN_KEY, String::from("Email Opt In"));
+            m.insert(HEADER_SMS_OPT_IN_KEY, String::from("SMS Opt In"));
+            m.insert(HEADER_CONSENT_TIMESTAMP_KEY, String::from("Consent Timestamp"));
+            m.insert(HEADER_CONSENT_VERSION_KEY, String::from("Consent Version"));
+            m.insert(HEADER_SIGNUP_DATE_KEY, String::from("Signup Date"));
+            m.insert(HEADER_LAST_ACTIVE_DATE_KEY, String::from("Last Active Date"));
+            m.insert(HEADER_CHURNED_KEY, String::from("Churned"));
+            m.insert(HEADER_LIFETIME_VALUE_KEY, String::from("Lifetime Value"));
+            m.insert(HEADER_LOYALTY_TIER_KEY, String::from("Loyalty Tier"));
+            m.insert(HEADER_POINTS_BALANCE_KEY, String::from("Points Balance"));
+            m.insert(HEADER_MEMBER_SINCE_KEY, String::from("Member Since"));
+        }
+        HeaderFormat::ActiveDirectory => {
+            m.insert(HEADER_ID_KEY, String::from("employeeID"));
+            m.insert(HEADER_FIRST_NAME_KEY, String::from("givenName"));
+            m.insert(HEADER_MIDDLE_NAME_KEY, String::from("middleName"));
+            m.insert(HEADER_LAST_NAME_KEY, String::from("sn"));
+            m.insert(HEADER_GENDER_KEY, String::from("gender"));
+            m.insert(HEADER_BIRTH_DATE_KEY, String::from("birthDate"));
+            m.insert(HEADER_SSN_KEY, String::from("ssn"));
+            m.insert(HEADER_NPI_KEY, String::from("npi"));
+            m.insert(HEADER_DEA_NUMBER_KEY, String::from("deaNumber"));
+            m.insert(HEADER_SALARY_KEY, String::from("salary"));
+            m.insert(HEADER_VIN_KEY, String::from("vin"));
+            m.insert(HEADER_ACCOUNT_NAME_KEY, String::from("sAMAccountName"));
+            m.insert(HEADER_PRINCIPAL_NAME_KEY, String::from("userPrincipalName"));
+            m.insert(HEADER_AVATAR_PATH_KEY, String::from("avatarPath"));
+            m.insert(HEADER_AVATAR_URL_KEY, String::from("thumbnailPhotoURL"));
+            m.insert(HEADER_BARCODE_KEY, String::from("barcode"));
+            m.insert(HEADER_CREATED_AT_KEY, String::from("whenCreated"));
+            m.insert(HEADER_UPDATED_AT_KEY, String::from("whenChanged"));
+            m.insert(HEADER_SOURCE_SYSTEM_KEY, String::from("sourceSystem"));
+            m.insert(HEADER_RECORD_UUID_KEY, String::from("recordUUID"));
+            m.insert(HEADER_PRONOUNS_KEY, String::from("pronouns"));
+            m.insert(HEADER_DEVICE_TYPE_KEY, String::from("deviceType"));
+            m.insert(HEADER_OS_KEY, String::from("operatingSystem"));
+            m.insert(HEADER_USER_AGENT_KEY, String::from("userAgent"));
+            m.insert(HEADER_EMAIL_OPT_IN_KEY, String::from("emailOptIn"));
+            m.insert(HEADER_SMS_OPT_IN_KEY, String::from("smsOptIn"));
+            m.insert(HEADER_CONSENT_TIMESTAMP_KEY, String::from("consentTimestamp"));
+            m.insert(HEADER_CONSENT_VERSION_KEY, String::from("consentVersion"));
+            m.insert(HEADER_SIGNUP_DATE_KEY, String::from("signupDate"));
+            m.insert(HEADER_LAST_ACTIVE_DATE_KEY, String::from("lastActiveDate"));
+            m.insert(HEADER_CHURNED_KEY, String::from("churned"));
+            m.insert(HEADER_LIFETIME_VALUE_KEY, String::from("lifetimeValue"));
+            m.insert(HEADER_LOYALTY_TIER_KEY, String::from("loyaltyTier"));
+            m.insert(HEADER_POINTS_BALANCE_KEY, String::from("pointsBalance"));
+            m.insert(HEADER_MEMBER_SINCE_KEY, String::from("memberSince"));
         }
     };
 
@@ -593,40 +2938,600 @@ fn get_headers(header_format: HeaderFormat) -> HashMap<&'static str, String> {
  *
  * # Arguments
  *
+ * - `rng`: The random number generator to use
  * - `first_names`: The first names from which to choose a random first name
  * - `last_names`: The last names from which to choose a random last name
  * - `gender`: The assigned gender
- * - `epoch_start`: The starting year for birth dates, as a Unix timestamp
- * - `epoch_end`: The ending year for birth dates, as a Unix timestamp
- * - `ssn_prefixes`: The set of known unused Social Security prefixes (the
- *                   first three numbers of an SSN).
+ * - `salary`: The generated salary
+ * - `birth_range`: The range of birth dates, as Unix timestamps
+ * - `ssn`: The generated Social Security number
  *
  * # Returns
  *
  * The generated `Person`.
  */
 fn make_person(
-    first_names: &Vec<String>,
-    last_names: &Vec<String>,
+    rng: &mut ChaCha8Rng,
+    first_names: &WeightedValues,
+    last_names: &WeightedValues,
     gender: Gender,
     salary: u32,
-    epoch_start: i64,
-    epoch_end: i64,
+    birth_range: &RangeInclusive<i64>,
     ssn: String
 ) -> Person {
-    let first_index = rand::thread_rng().gen_range(0..first_names.len());
-    let mid_index = rand::thread_rng().gen_range(0..first_names.len());
-    let last_index = rand::thread_rng().gen_range(0..last_names.len());
-    let epoch_birth = rand::thread_rng().gen_range(epoch_start..=epoch_end);
+    let first_name = first_names.choose(rng).clone();
+    let middle_name = first_names.choose(rng).clone();
+    let last_name = last_names.choose(rng).clone();
+    let epoch_birth = rng.gen_range(birth_range.clone());
     let birth_date = NaiveDateTime::from_timestamp(epoch_birth, 0).date();
 
     Person {
-        first_name: String::from(&first_names[first_index]),
-        middle_name: String::from(&first_names[mid_index]),
-        last_name: String::from(&last_names[last_index]),
-        gender: gender,
-        birth_date: birth_date,
+        id: 0,
+        first_name,
+        middle_name,
+        last_name,
+        gender,
+        birth_date,
         ssn,
-        salary
+        npi: String::new(),
+        dea_number: String::new(),
+        vin: String::new(),
+        salary,
+        account_name: String::new(),
+        metadata: None,
+        pronouns: String::new(),
+        device: None,
+        consent: None,
+        lifecycle: None,
+        loyalty: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::Arguments;
+    use crate::format::FieldFormat;
+    use crate::hash::{FieldHash, HashAlgorithm};
+    use crate::people::*;
+    use crate::ssn::fake_prefixes;
+    use chrono::Datelike;
+    use std::collections::HashSet;
+    use proptest::prelude::*;
+
+    fn moe() -> Person {
+        Person {
+            id: 1,
+            first_name: Arc::from("Moe"),
+            middle_name: Arc::from("Harry"),
+            last_name: Arc::from("Howard"),
+            gender: Gender::Male,
+            birth_date: NaiveDate::from_ymd(1897, 6, 19),
+            ssn: String::from("900-01-0001"),
+            npi: String::new(),
+            dea_number: String::new(),
+            vin: String::new(),
+            salary: 58260,
+            account_name: String::new(),
+            metadata: None,
+            pronouns: String::new(),
+            device: None,
+            consent: None,
+            lifecycle: None,
+            loyalty: None,
+        }
+    }
+
+    fn json_for(args: &Arguments) -> String {
+        let fields = json_key_order(args, output_fields(args));
+        let headers = headers_for(args);
+        person_to_json_object(&moe(), 1, args, &fields, &headers)
+            .unwrap()
+            .dump()
+    }
+
+    #[test]
+    fn json_keys_in_field_order() {
+        let args = Arguments {
+            generate_ids: true,
+            generate_ssns: true,
+            generate_salaries: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json_for(&args),
+            "{\"id\":\"1\",\"first_name\":\"Moe\",\"middle_name\":\"Harry\",\
+             \"last_name\":\"Howard\",\"gender\":\"M\",\"birth_date\":\"1897-06-19\",\
+             \"ssn\":\"900-01-0001\",\"salary\":\"58260\"}"
+        );
+    }
+
+    #[test]
+    fn csv_fast_path() {
+        let p = Person {
+            last_name: Arc::from("Howard, Jr."),
+            npi: String::from("1234567893"),
+            pronouns: String::from("he/him"),
+            ..moe()
+        };
+        let write = |args: &Arguments, key: &String, fast: bool| {
+            let mut w = csv::Writer::from_writer(Vec::new());
+            if fast {
+                let fields = csv_fields(args, std::slice::from_ref(key)).unwrap();
+                write_csv_field(&mut w, &p, args, &fields[0], &mut CsvBuffers::default()).unwrap();
+            }
+            else {
+                w.write_field(field_value(&p, p.id, args, key)).unwrap();
+            }
+            w.write_record(None::<&[u8]>).unwrap();
+            String::from_utf8(w.into_inner().unwrap()).unwrap()
+        };
+
+        let all = || Arguments {
+            generate_ids: true,
+            generate_ssns: true,
+            generate_salaries: true,
+            npi: true,
+            ..Default::default()
+        };
+        let digits = Arguments { no_gender: true, ssn_format: SsnFormat::Digits, ..all() };
+        for args in [all(), digits] {
+            for key in output_fields(&args) {
+                assert_eq!(write(&args, &key, true), write(&args, &key, false), "{key}");
+            }
+        }
+
+        // Anything that changes the values takes the slow path.
+        let formatted = Arguments {
+            field_formats: vec![FieldFormat::parse("ssn=digits-only", &FIELD_NAMES).unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(csv_fields(&formatted, &[]), None);
+
+        let mut buf = [0; 10];
+        for (y, m, d) in [(1897, 6, 19), (5, 1, 2), (2000, 12, 31), (9999, 12, 31)] {
+            let date = NaiveDate::from_ymd(y, m, d);
+            assert_eq!(date_bytes(&date, &mut buf), Some(date_str(&date).as_bytes()));
+        }
+        assert_eq!(date_bytes(&NaiveDate::from_ymd(10000, 1, 1), &mut buf), None);
+        assert_eq!(date_bytes(&NaiveDate::from_ymd(-1, 1, 1), &mut buf), None);
+    }
+
+    #[test]
+    fn date_strings() {
+        for (y, m, d) in [(1897, 6, 19), (5, 1, 2), (-1, 1, 1), (10000, 1, 1)] {
+            let date = NaiveDate::from_ymd(y, m, d);
+            assert_eq!(date_str(&date), date.format("%Y-%m-%d").to_string());
+        }
+    }
+
+    #[test]
+    fn json_keys_sorted() {
+        let args = Arguments {
+            generate_ids: true,
+            generate_ssns: true,
+            header_format: HeaderFormat::CamelCase,
+            sort_keys: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json_for(&args),
+            "{\"birthDate\":\"1897-06-19\",\"firstName\":\"Moe\",\"gender\":\"M\",\
+             \"id\":\"1\",\"lastName\":\"Howard\",\"middleName\":\"Harry\",\
+             \"ssn\":\"900-01-0001\"}"
+        );
+    }
+
+    #[test]
+    fn header_keys() {
+        assert_eq!(header_key("birth_date").as_deref(), Some("birth_date"));
+        assert_eq!(header_key("birthDate").as_deref(), Some("birth_date"));
+        assert_eq!(header_key("Birth Date").as_deref(), Some("birth_date"));
+        assert_eq!(header_key("sn").as_deref(), Some("last_name"));
+        assert_eq!(header_key("ssn_hash").as_deref(), Some("ssn_hash"));
+        assert_eq!(header_key("SSN Hash").as_deref(), Some("ssn_hash"));
+        assert_eq!(header_key("birthDateHash").as_deref(), Some("birth_date_hash"));
+        assert_eq!(header_key("shoe_size"), None);
+    }
+
+    #[test]
+    fn serde() {
+        let json = serde_json::to_string(&moe()).unwrap();
+        assert_eq!(
+            json,
+            "{\"id\":1,\"first_name\":\"Moe\",\"middle_name\":\"Harry\",\"last_name\":\"Howard\",\
+             \"gender\":\"M\",\"birth_date\":\"1897-06-19\",\"ssn\":\"900-01-0001\",\"salary\":58260,\
+             \"account_name\":\"\",\"pronouns\":\"\"}"
+        );
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), moe());
+        let shemp = Person { gender: Gender::NonBinary, ..moe() };
+        let json = serde_json::to_string(&shemp).unwrap();
+        assert!(json.contains("\"gender\":\"X\""), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), shemp);
+
+        // The metadata is flattened into the record.
+        let metadata = RecordMetadata {
+            created_at: NaiveDate::from_ymd(2020, 1, 2).and_hms(3, 4, 5),
+            updated_at: NaiveDate::from_ymd(2021, 1, 2).and_hms(3, 4, 5),
+            source_system: String::from("crm"),
+            record_uuid: String::from("9e1c2b4a-0d4e-4c52-8a1f-3b7f7e0e6a11"),
+        };
+        let curly = Person { gender: Gender::Custom(String::from("unknown")), metadata: Some(metadata), ..moe() };
+        let json = serde_json::to_string(&curly).unwrap();
+        assert!(json.contains("\"gender\":\"unknown\""), "{json}");
+        assert!(json.contains("\"created_at\":\"2020-01-02T03:04:05Z\",\"updated_at\""), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), curly);
+
+        // So is the device.
+        let device = Device {
+            device_type: String::from("mobile"),
+            os: String::from("iOS"),
+            user_agent: String::from("Mozilla/5.0 (iPhone)"),
+        };
+        let larry = Person { device: Some(device), ..moe() };
+        let json = serde_json::to_string(&larry).unwrap();
+        assert!(json.ends_with("\"device_type\":\"mobile\",\"os\":\"iOS\",\"user_agent\":\"Mozilla/5.0 (iPhone)\"}"), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), larry);
+        let json = serde_json::to_string(&larry.with_headers(HeaderFormat::ActiveDirectory)).unwrap();
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), larry, "{json}");
+
+        // And the consent, whose opt-ins are booleans.
+        let consent = Consent {
+            email_opt_in: true,
+            sms_opt_in: false,
+            consent_timestamp: NaiveDate::from_ymd(2022, 5, 6).and_hms(7, 8, 9),
+            consent_version: String::from("v2"),
+        };
+        let shemp = Person { consent: Some(consent), ..moe() };
+        let json = serde_json::to_string(&shemp.with_headers(HeaderFormat::CamelCase)).unwrap();
+        assert!(json.contains("\"emailOptIn\":true,\"smsOptIn\":false,\"consentTimestamp\":\"2022-05-06T07:08:09Z\""), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), shemp);
+
+        // Provider IDs are only there when they've been generated.
+        let joe = Person { npi: String::from("1234567893"), dea_number: String::from("AB1234563"), ..moe() };
+        let json = serde_json::to_string(&joe.with_headers(HeaderFormat::Pretty)).unwrap();
+        assert!(json.contains("\"SSN\":\"900-01-0001\",\"NPI\":\"1234567893\",\"DEA Number\":\"AB1234563\""), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), joe);
+
+        let jim = Person { vin: String::from("1HGCM82633A004352"), ..moe() };
+        let json = serde_json::to_string(&jim.with_headers(HeaderFormat::Pretty)).unwrap();
+        assert!(json.contains("\"Salary\":58260,\"VIN\":\"1HGCM82633A004352\""), "{json}");
+        assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), jim);
+    }
+
+    #[test]
+    fn serde_with_headers() {
+        let json = serde_json::to_string(&moe().with_headers(HeaderFormat::Pretty)).unwrap();
+        assert_eq!(
+            json,
+            "{\"ID\":1,\"First Name\":\"Moe\",\"Middle Name\":\"Harry\",\"Last Name\":\"Howard\",\
+             \"Gender\":\"M\",\"Birth Date\":\"1897-06-19\",\"SSN\":\"900-01-0001\",\"Salary\":58260,\
+             \"Account Name\":\"\",\"Pronouns\":\"\"}"
+        );
+
+        // Any header format's names deserialize.
+        for header_format in [HeaderFormat::Pretty, HeaderFormat::CamelCase, HeaderFormat::ActiveDirectory] {
+            let json = serde_json::to_string(&moe().with_headers(header_format)).unwrap();
+            assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), moe(), "{json}");
+        }
+    }
+
+    #[test]
+    fn numbered_paths() {
+        // Paths are built from components, so they use the platform's
+        // separator, but URLs always use "/".
+        assert_eq!(numbered_file(1, 10, "png"), Path::new("0000").join("000001.png"));
+        assert_eq!(numbered_file(1001, 2_000_000, "png"), Path::new("0001").join("0001001.png"));
+
+        let args = Arguments {
+            avatars_dir: Some(PathBuf::from("avatars")),
+            avatar_base_url: Some(String::from("https://example.com/avatars/")),
+            total: 10,
+            ..Default::default()
+        };
+        let expected = Path::new("avatars").join("0000").join("000001.png");
+        assert_eq!(field_value(&moe(), 1, &args, HEADER_AVATAR_PATH_KEY), path_str(&expected));
+        assert_eq!(
+            field_value(&moe(), 1, &args, HEADER_AVATAR_URL_KEY),
+            "https://example.com/avatars/0000/000001.png"
+        );
+    }
+
+    #[test]
+    fn ddl_columns() {
+        let args = Arguments {
+            generate_ids: true,
+            generate_salaries: true,
+            no_gender: true,
+            field_formats: vec![
+                FieldFormat::parse("birth_date=date:%m/%d/%Y", &FIELD_NAMES).unwrap()
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            table_ddl(&args, SqlDialect::Postgres, "people"),
+            "CREATE TABLE \"people\" (\n  \"id\" BIGINT,\n  \"first_name\" TEXT,\n  \
+             \"middle_name\" TEXT,\n  \"last_name\" TEXT,\n  \"birth_date\" TEXT,\n  \
+             \"salary\" BIGINT\n);"
+        );
+    }
+
+    #[test]
+    fn hash_fields() {
+        let args = Arguments {
+            generate_ssns: true,
+            header_format: HeaderFormat::CamelCase,
+            field_formats: vec![
+                FieldFormat::parse("ssn=digits-only", &FIELD_NAMES).unwrap()
+            ],
+            field_hashes: vec![
+                FieldHash::parse("ssn=sha256", &FIELD_NAMES).unwrap()
+            ],
+            ..Default::default()
+        };
+
+        let expected = format!(
+            "{{\"firstName\":\"Moe\",\"middleName\":\"Harry\",\
+             \"lastName\":\"Howard\",\"gender\":\"M\",\
+             \"birthDate\":\"1897-06-19\",\"ssn\":\"900010001\",\
+             \"ssnHash\":\"{}\"}}",
+            HashAlgorithm::Sha256.hash("900010001")
+        );
+        assert_eq!(json_for(&args), expected);
+    }
+
+    #[test]
+    fn generator_yields_requested_genders() {
+        let names = WeightedValues::uniform(vec![String::from("Moe")]);
+        let categories = vec![
+            GenderCategory { gender: Gender::Male, percent: 30, first_names: names.clone() },
+            GenderCategory { gender: Gender::Female, percent: 70, first_names: names.clone() },
+        ];
+        let args = Arguments {
+            male_percent: 30,
+            female_percent: 70,
+            year_min: 1900,
+            year_max: 1950,
+            salary_mean: 50000,
+            salary_sigma: 1000,
+            seed: 42,
+            total: 1000,
+            ..Default::default()
+        };
+
+        let people: Vec<Person> = make_people(&args, &categories, &names).unwrap().collect();
+        let males = people.iter().filter(|p| p.gender == Gender::Male).count();
+
+        assert_eq!(people.len(), 1000);
+        assert_eq!(males, 300);
+        // The genders should be mixed, not generated in two blocks.
+        assert!(people[..500].iter().any(|p| p.gender == Gender::Female));
+        assert!(people[500..].iter().any(|p| p.gender == Gender::Male));
+
+        let again: Vec<NaiveDate> = make_people(&args, &categories, &names)
+            .unwrap()
+            .map(|p| p.birth_date)
+            .collect();
+        assert_eq!(again, people.iter().map(|p| p.birth_date).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn custom_genders() {
+        let names = WeightedValues::uniform(vec![String::from("Moe")]);
+        let category = |label: &str, percent| GenderCategory {
+            gender: Gender::Custom(String::from(label)),
+            percent,
+            first_names: names.clone(),
+        };
+        let categories = vec![category("a", 33), category("b", 0), category("c", 67)];
+        let args = Arguments { total: 10, year_min: 1900, year_max: 1950, ..Default::default() };
+
+        let labels: Vec<String> = make_people(&args, &categories, &names)
+            .unwrap()
+            .map(|p| p.gender.to_str().to_string())
+            .collect();
+        assert_eq!(labels.iter().filter(|l| *l == "a").count(), 3);
+        assert_eq!(labels.iter().filter(|l| *l == "b").count(), 0);
+        assert_eq!(labels.iter().filter(|l| *l == "c").count(), 7);
+    }
+
+    #[test]
+    fn huge_totals() {
+        let names = WeightedValues::uniform(vec![String::from("Moe")]);
+        let categories = vec![
+            GenderCategory { gender: Gender::Male, percent: 49, first_names: names.clone() },
+            GenderCategory { gender: Gender::Female, percent: 51, first_names: names.clone() },
+        ];
+
+        let counts = category_counts(u64::MAX, &categories);
+        assert_eq!(counts, [u64::MAX / 100 * 49 + 15 * 49 / 100, u64::MAX - counts[0]]);
+        assert_eq!(category_counts(10_000_000_000, &categories), [4_900_000_000, 5_100_000_000]);
+
+        // Billions of people stream like any other number.
+        let args = Arguments { total: 10_000_000_000, year_min: 1900, year_max: 1950, ..Default::default() };
+        let people: Vec<Person> = make_people(&args, &categories, &names).unwrap().take(3).collect();
+        assert_eq!(people.iter().map(|p| p.id).collect::<Vec<u64>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn generation_errors() {
+        let names = WeightedValues::uniform(vec![String::from("Moe")]);
+        let categories = vec![GenderCategory { gender: Gender::Male, percent: 100, first_names: names.clone() }];
+        // Salaries this spread out are soon negative.
+        let args = Arguments {
+            total: 1000,
+            year_min: 1900,
+            year_max: 1950,
+            salary_mean: 1000,
+            salary_sigma: 100_000,
+            ..Default::default()
+        };
+
+        let mut people = make_people(&args, &categories, &names).unwrap();
+        assert!(people.by_ref().count() < 1000);
+        assert!(people.error().unwrap().starts_with("Generated negative salary"));
+
+        let results: Vec<Result<Person, String>> = make_people(&args, &categories, &names).unwrap().results().collect();
+        assert!(results.last().unwrap().is_err());
+        assert!(results[..results.len() - 1].iter().all(|p| p.is_ok()));
+    }
+
+    #[test]
+    fn strict() {
+        let names = WeightedValues::uniform(vec![String::from("Moe")]);
+        let categories = vec![
+            GenderCategory { gender: Gender::Male, percent: 1, first_names: names.clone() },
+            GenderCategory { gender: Gender::Female, percent: 99, first_names: names.clone() },
+        ];
+        let mut args = Arguments { total: 50, year_min: 1900, year_max: 1950, ..Default::default() };
+
+        // 1% of 50 people is no one, which is a warning, unless it's strict.
+        assert_eq!(make_people(&args, &categories, &names).unwrap().count(), 50);
+        args.strict = true;
+        let e = make_people(&args, &categories, &names).err().unwrap();
+        assert!(matches!(&e, PeoplegenError::Validation(msg) if msg.contains("no \"M\" people")), "{e}");
+        args.total = 100;
+        assert!(make_people(&args, &categories, &names).is_ok());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("names.txt");
+        fs::write(&path, "Moe\nLarry\nMoe\n").unwrap();
+        assert_eq!(read_names_file(&path, false).unwrap().len(), 3);
+        assert!(matches!(read_names_file(&path, true), Err(PeoplegenError::Validation(_))));
+    }
+
+    #[test]
+    fn weighted_names() {
+        let weighted = |s: &str| WeightedValues::parse(s.as_bytes()).unwrap();
+        let categories = vec![
+            GenderCategory { gender: Gender::Male, percent: 50, first_names: weighted("Moe,1\nLarry,0\n") },
+            GenderCategory { gender: Gender::Female, percent: 50, first_names: weighted("Larry,0\nShemp,0\nMoe,1\n") },
+        ];
+        let last_names = weighted("Howard,1\nFine,0\n");
+        let args = Arguments { total: 20, year_min: 1900, year_max: 1950, ..Default::default() };
+
+        let people: Vec<Person> = make_people(&args, &categories, &last_names).unwrap().collect();
+        assert!(people.iter().all(|p| &*p.first_name == "Moe" && &*p.last_name == "Howard"));
+
+        // The union of the first names keeps the weights.
+        let args = Arguments { no_gender: true, ..args };
+        let people: Vec<Person> = make_people(&args, &categories, &last_names).unwrap().collect();
+        assert!(people.iter().all(|p| &*p.first_name == "Moe" && &*p.middle_name == "Moe"));
+    }
+
+    #[test]
+    fn no_gender() {
+        let args = Arguments { no_gender: true, ..Default::default() };
+
+        assert_eq!(
+            json_for(&args),
+            "{\"first_name\":\"Moe\",\"middle_name\":\"Harry\",\
+             \"last_name\":\"Howard\",\"birth_date\":\"1897-06-19\"}"
+        );
+        assert_eq!(field_value(&moe(), 1, &args, HEADER_GENDER_KEY), "");
+    }
+
+    /// Generate people for a property test, with the given gender split.
+    fn generate(args: &Arguments, percents: &[u32]) -> Vec<Result<Person, String>> {
+        let names = WeightedValues::uniform(["Moe", "Larry", "Curly"].iter().map(|s| s.to_string()).collect());
+        let categories: Vec<GenderCategory> = percents
+            .iter()
+            .enumerate()
+            .map(|(i, percent)| GenderCategory {
+                gender: Gender::Custom(i.to_string()),
+                percent: *percent,
+                first_names: names.clone(),
+            })
+            .collect();
+
+        make_people(args, &categories, &names).unwrap().results().collect()
+    }
+
+    /// Split 100% at the given points, e.g., [30, 80] into [30, 50, 20].
+    fn percents(mut cuts: Vec<u32>) -> Vec<u32> {
+        cuts.sort();
+        cuts.push(100);
+        cuts.iter().scan(0, |prev, c| { let p = c - *prev; *prev = *c; Some(p) }).collect()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn birth_dates_within_years(seed: u64, year_min in 1900u32..2020, span in 0u32..50) {
+            let args = Arguments {
+                seed,
+                year_min,
+                year_max: year_min + span,
+                total: 200,
+                ..Default::default()
+            };
+
+            for p in generate(&args, &[100]) {
+                let year = p.unwrap().birth_date.year() as u32;
+                prop_assert!((args.year_min..=args.year_max).contains(&year));
+            }
+        }
+
+        // Every category gets its percentage of the total, rounded down,
+        // and the last one gets the rest, however large the run.
+        #[test]
+        fn gender_percentages_respected(
+            seed: u64,
+            cuts in prop::collection::vec(0u32..=100, 0..4),
+            total in 0u64..2000,
+        ) {
+            let percents = percents(cuts);
+            let args = Arguments { seed, total, year_min: 1950, year_max: 2000, ..Default::default() };
+            let people = generate(&args, &percents);
+            let count = |i: usize| {
+                people.iter().filter(|p| p.as_ref().unwrap().gender.to_str() == i.to_string()).count() as u64
+            };
+
+            prop_assert_eq!(people.len() as u64, total);
+            let mut expected_total = 0;
+            for (i, percent) in percents.iter().enumerate().take(percents.len() - 1) {
+                let expected = total * *percent as u64 / 100;
+                prop_assert_eq!(count(i), expected);
+                expected_total += expected;
+            }
+            prop_assert_eq!(count(percents.len() - 1), total - expected_total);
+        }
+
+        // A salary is never clamped or wrapped: each one is either drawn
+        // from the distribution, or the person is an error.
+        #[test]
+        fn salaries_follow_distribution(seed: u64, mean in 20_000u32..200_000, sigma in 0u32..100_000) {
+            let args = Arguments {
+                seed,
+                salary_mean: mean,
+                salary_sigma: sigma,
+                total: 500,
+                year_min: 1950,
+                year_max: 2000,
+                ..Default::default()
+            };
+
+            for p in generate(&args, &[100]) {
+                match p {
+                    Ok(p) => prop_assert!((p.salary as f64 - mean as f64).abs() <= 10.0 * sigma as f64 + 1.0),
+                    Err(e) => prop_assert!(e.starts_with("Generated negative salary"), "{}", e),
+                }
+            }
+        }
+
+        #[test]
+        fn ssns_are_fake_and_unique(seed: u64) {
+            let args = Arguments { seed, total: 500, year_min: 1950, year_max: 2000, ..Default::default() };
+            let ssns: Vec<String> = generate(&args, &[100]).into_iter().map(|p| p.unwrap().ssn).collect();
+
+            prop_assert_eq!(ssns.iter().collect::<HashSet<_>>().len(), ssns.len());
+            for ssn in &ssns {
+                prop_assert_eq!(ssn.len(), 11);
+                prop_assert!(fake_prefixes().contains(&ssn[..3].parse().unwrap()), "{} isn't fake", ssn);
+            }
+        }
     }
 }
@@ -0,0 +1,112 @@
+//! The Postgres sink: instead of writing a file, load the people straight
+//! into an existing table, with `COPY ... FROM STDIN`.
+//!
+//! Rows are sent in batches, one `COPY` per batch, all inside a single
+//! transaction, so a failed load (or a failed generation) leaves the table as
+//! it was, even with `--truncate`.
+
+use crate::sql::SqlDialect;
+use postgres::{Client, NoTls};
+use std::io::Write;
+
+/// The default `--table`.
+pub const DEFAULT_TABLE: &str = "people";
+
+/// The settings for the Postgres sink, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostgresOptions {
+    /// The connection string, as a URL (`postgres://user@host/db`) or as
+    /// `key=value` pairs (`host=localhost dbname=test`)
+    pub url: String,
+    /// The table to load, optionally with a schema (`schema.table`)
+    pub table: String,
+    /// Whether to empty the table first
+    pub truncate: bool,
+    /// How many rows to send in each `COPY`
+    pub batch_size: usize,
+}
+
+/**
+ * Load rows into a table.
+ *
+ * # Arguments
+ *
+ * - `options`: The sink settings
+ * - `columns`: The names of the table columns the row values go in, in order
+ * - `rows`: The rows. Each value is sent as text, so Postgres converts it to
+ *   the column's type.
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: The load was committed, and `total` rows were loaded.
+ * - `Err(msg)`: The load failed, and was rolled back; `msg` explains why.
+ */
+pub fn copy_rows<I>(options: &PostgresOptions, columns: &[&String], rows: I) -> Result<u64, String>
+where
+    I: Iterator<Item = Result<Vec<String>, String>>,
+{
+    // Errors from the server only say "db error", unless you dig.
+    let pg_err = |e: postgres::Error| match e.as_db_error() {
+        Some(db) => format!("Postgres: {}", db.message()),
+        None => match std::error::Error::source(&e) {
+            Some(cause) => format!("Postgres: {e}: {cause}"),
+            None => format!("Postgres: {e}"),
+        },
+    };
+    let io_err = |e: std::io::Error| format!("Postgres: {e}");
+
+    let mut client = Client::connect(&options.url, NoTls).map_err(pg_err)?;
+    let mut tx = client.transaction().map_err(pg_err)?;
+    let table = quote_table(&options.table);
+
+    if options.truncate {
+        tx.batch_execute(&format!("TRUNCATE TABLE {table}")).map_err(pg_err)?;
+    }
+
+    let copy = format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+        table,
+        columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", ")
+    );
+    let mut rows = rows.peekable();
+    let mut total = 0;
+
+    while rows.peek().is_some() {
+        let mut batch = csv::Writer::from_writer(Vec::new());
+        for row in rows.by_ref().take(options.batch_size) {
+            batch.write_record(row?).map_err(|e| format!("Postgres: {e}"))?;
+            total += 1;
+        }
+
+        let data = batch.into_inner().map_err(|e| format!("Postgres: {e}"))?;
+        let mut w = tx.copy_in(copy.as_str()).map_err(pg_err)?;
+        w.write_all(&data).map_err(io_err)?;
+        w.finish().map_err(pg_err)?;
+    }
+
+    tx.commit().map_err(pg_err)?;
+    Ok(total)
+}
+
+/// Quote a (possibly schema-qualified) table name.
+pub fn quote_table(name: &str) -> String {
+    SqlDialect::Postgres.quote_table(name)
+}
+
+/// Quote an identifier, so that names with spaces or upper case letters
+/// (e.g., "pretty" headers) work.
+fn quote_ident(name: &str) -> String {
+    SqlDialect::Postgres.quote_ident(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pg::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote_table("people"), "\"people\"");
+        assert_eq!(quote_table("test.people"), "\"test\".\"people\"");
+        assert_eq!(quote_ident("First \"Name\""), "\"First \"\"Name\"\"\"");
+    }
+}
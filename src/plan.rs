@@ -0,0 +1,151 @@
+//! `--dry-run`: what a run would do, worked out from the settings, once
+//! they've been checked and the names files read, without generating or
+//! writing anyone: where the people would go, how many of each gender
+//! there'd be, how they'd be split into files, and the columns.
+
+use crate::args::Arguments;
+use crate::genders::GenderCategory;
+use crate::output::{is_null, is_stdout};
+use crate::people::{category_counts, column_settings};
+use crate::shards::{range_sizes, shard_path, shard_paths};
+use crate::weighted::WeightedValues;
+
+/**
+ * Describe what a run would do, as messages, one per line.
+ *
+ * # Arguments
+ *
+ * - `args`: The parsed command-line arguments
+ * - `categories`: The gender categories, with their first names
+ * - `last_names`: The last names
+ *
+ * # Returns
+ *
+ * The messages.
+ */
+pub fn plan(args: &Arguments, categories: &[GenderCategory], last_names: &WeightedValues) -> Vec<String> {
+    let format = args.output_format.to_str();
+    let mut messages = vec![match (&args.postgres, &args.shards, &args.rollover) {
+        (Some(pg), _, _) => format!("Would load {} record(s) into Postgres table \"{}\".", args.total, pg.table),
+        _ if is_stdout(&args.output_file) => format!("Would write {} record(s) to standard output, as {}.",
+                                                     args.total, format),
+        _ if is_null(&args.output_file) => format!("Would format {} record(s) as {}, and discard them.",
+                                                   args.total, format),
+        (None, Some(shards), _) => {
+            let files: Vec<String> = shard_paths(&args.output_file, shards.count)
+                .iter()
+                .zip(range_sizes(args.total, shards.count))
+                .map(|(path, n)| format!("\"{}\" ({})", path.display(), n))
+                .collect();
+            format!("Would write {} record(s) to {} {} files: {}.", args.total, shards.count, format, files.join(", "))
+        },
+        (None, None, Some(rollover)) => {
+            let limits: Vec<String> = [
+                rollover.max_rows.map(|n| format!("{} record(s)", n)),
+                rollover.max_bytes.map(|n| format!("{} byte(s)", n)),
+            ].into_iter().flatten().collect();
+            format!("Would write {} record(s) to {} files of at most {}, from \"{}\".",
+                    args.total, format, limits.join(" or "), shard_path(&args.output_file, 0).display())
+        },
+        (None, None, None) => format!("Would write {} record(s) to {} file \"{}\".",
+                                      args.total, format, args.output_file.display()),
+    }];
+
+    if !args.no_gender {
+        let genders: Vec<String> = categories
+            .iter()
+            .zip(category_counts(args.total, categories))
+            .map(|(c, n)| format!("{} {}", c.gender, n))
+            .collect();
+        messages.push(format!("Genders: {}.", genders.join(", ")));
+    }
+
+    if let Some(key) = args.cluster_by {
+        messages.push(format!("Clustered by {}.", key.name()));
+    }
+
+    let columns: Vec<String> = column_settings(args).members().map(|c| c["header"].to_string()).collect();
+    messages.push(format!("Columns ({}): {}.", columns.len(), columns.join(", ")));
+
+    let first_names: usize = categories.iter().map(|c| c.first_names.len()).sum();
+    messages.push(format!("Names: {} first name(s) and {} last name(s).", first_names, last_names.len()));
+
+    let extras = [
+        ("documents", args.documents_dir.as_deref().filter(|_| args.template_file.is_some())),
+        ("avatars", args.avatars_dir.as_deref()),
+        ("barcodes", args.barcodes_dir.as_deref()),
+        ("aggregate tables", args.aggregates_dir.as_deref()),
+        ("shifts", args.shifts_file.as_deref()),
+        ("benefits elections", args.benefits_dir.as_deref()),
+        ("W-2 statements", args.w2_file.as_deref()),
+        ("transactions", args.transactions_file.as_deref()),
+        ("immunizations", args.immunizations_file.as_deref()),
+        ("shared SSNs", args.ssn_collisions_file.as_deref()),
+        ("the protobuf definition", args.proto_file.as_deref()),
+    ];
+    for (what, path) in extras {
+        if let Some(path) = path {
+            messages.push(format!("Would also write {} to \"{}\".", what, path.display()));
+        }
+    }
+
+    messages.push(String::from("Dry run: nothing was generated or written."));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::OutputFormat;
+    use crate::people::Gender;
+    use crate::plan::*;
+    use crate::shards::{ShardBy, Shards};
+    use std::path::PathBuf;
+
+    fn categories() -> Vec<GenderCategory> {
+        let names = |names: &[&str]| WeightedValues::uniform(names.iter().map(|n| n.to_string()).collect());
+        vec![
+            GenderCategory { gender: Gender::Male, percent: 30, first_names: names(&["Moe", "Larry"]) },
+            GenderCategory { gender: Gender::Female, percent: 70, first_names: names(&["Shemp"]) },
+        ]
+    }
+
+    #[test]
+    fn file() {
+        let last_names = WeightedValues::uniform(vec![String::from("Howard")]);
+        let args = Arguments {
+            total: 10,
+            output_file: PathBuf::from("people.csv"),
+            generate_ids: true,
+            shifts_file: Some(PathBuf::from("shifts.csv")),
+            ..Default::default()
+        };
+
+        assert_eq!(plan(&args, &categories(), &last_names), [
+            "Would write 10 record(s) to CSV file \"people.csv\".",
+            "Genders: M 3, F 7.",
+            "Columns (6): id, first_name, middle_name, last_name, gender, birth_date.",
+            "Names: 3 first name(s) and 1 last name(s).",
+            "Would also write shifts to \"shifts.csv\".",
+            "Dry run: nothing was generated or written.",
+        ]);
+    }
+
+    #[test]
+    fn shards() {
+        let last_names = WeightedValues::uniform(vec![String::from("Howard")]);
+        let args = Arguments {
+            total: 10,
+            output_file: PathBuf::from("people.jsonl"),
+            output_format: OutputFormat::JsonL,
+            shards: Some(Shards { count: 3, by: ShardBy::Range }),
+            no_gender: true,
+            ..Default::default()
+        };
+
+        assert_eq!(plan(&args, &categories(), &last_names)[..2], [
+            "Would write 10 record(s) to 3 JSON Lines files: \"people-00000.jsonl\" (4), \
+             \"people-00001.jsonl\" (3), \"people-00002.jsonl\" (3).",
+            "Columns (4): first_name, middle_name, last_name, birth_date.",
+        ]);
+    }
+}
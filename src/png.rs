@@ -0,0 +1,155 @@
+//! A minimal PNG encoder, used to write generated images without pulling in
+//! an image library.
+//!
+//! Only palette-based images with a bit depth of 1 are supported (i.e.,
+//! two-color images), which is all the generated images need. Image data is
+//! stored with uncompressed deflate blocks; at a bit per pixel, the files
+//! are small anyway.
+
+// The largest payload an uncompressed ("stored") deflate block can hold.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/**
+ * Encode a two-color image as a PNG.
+ *
+ * # Arguments
+ *
+ * - `width`: The image width, in pixels
+ * - `height`: The image height, in pixels
+ * - `palette`: The two colors, as RGB triples. Pixels that are `false` use
+ *   the first color; pixels that are `true` use the second.
+ * - `pixel`: A function that returns the value of the pixel at (x, y)
+ *
+ * # Returns
+ *
+ * The bytes of the PNG file.
+ */
+pub fn encode_two_color<F>(width: u32,
+                           height: u32,
+                           palette: [[u8; 3]; 2],
+                           pixel: F) -> Vec<u8>
+    where F: Fn(u32, u32) -> bool
+{
+    let row_bytes = width.div_ceil(8) as usize;
+    let mut raw: Vec<u8> = Vec::with_capacity((row_bytes + 1) * height as usize);
+
+    for y in 0..height {
+        // Each scanline starts with a filter type byte. 0 is "no filter".
+        raw.push(0);
+        let mut row = vec![0u8; row_bytes];
+        for x in 0..width {
+            if pixel(x, y) {
+                row[(x / 8) as usize] |= 0x80 >> (x % 8);
+            }
+        }
+        raw.extend(row);
+    }
+
+    let mut header: Vec<u8> = Vec::new();
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    // Bit depth 1, color type 3 (palette), default compression, filtering
+    // and no interlacing.
+    header.extend([1, 3, 0, 0, 0]);
+
+    let mut png: Vec<u8> = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"PLTE", &palette.concat());
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/**
+ * Compute the CRC-32 (ISO 3309, as used by PNG and zip) of some bytes.
+ */
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0, bytes)
+}
+
+/**
+ * Continue a CRC-32 computation: `crc32_update(crc32(a), b)` is the CRC of
+ * `a` followed by `b`. Start with 0.
+ */
+pub fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc: u32 = !crc;
+
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Append a PNG chunk (length, type, data, CRC) to a buffer.
+fn write_chunk(buf: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    buf.extend((data.len() as u32).to_be_bytes());
+    let start = buf.len();
+    buf.extend(chunk_type);
+    buf.extend(data);
+    let crc = crc32(&buf[start..]);
+    buf.extend(crc.to_be_bytes());
+}
+
+/// Wrap bytes in a zlib stream, using uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // CMF/FLG: deflate, 32K window, no dictionary, check bits.
+    let mut buf: Vec<u8> = vec![0x78, 0x01];
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    if chunks.peek().is_none() {
+        buf.extend([1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let last = if chunks.peek().is_none() { 1 } else { 0 };
+        let len = chunk.len() as u16;
+        buf.push(last);
+        buf.extend(len.to_le_bytes());
+        buf.extend((!len).to_le_bytes());
+        buf.extend(chunk);
+    }
+
+    buf.extend(adler32(data).to_be_bytes());
+    buf
+}
+
+/// Compute the Adler-32 checksum of some bytes, as used by zlib.
+fn adler32(bytes: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::png::{adler32, crc32, crc32_update, encode_two_color};
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn png_layout() {
+        let png = encode_two_color(10, 2, [[0, 0, 0], [255, 255, 255]],
+                                   |x, _| x % 2 == 0);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &10u32.to_be_bytes());
+        assert_eq!(&png[20..24], &2u32.to_be_bytes());
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+}
@@ -0,0 +1,153 @@
+//! Previews: the first few people, printed to the terminal instead of
+//! written to a file, to see what a set of options produces. A preview is an
+//! aligned table, by default, with numeric columns right-aligned, or JSON or
+//! CSV, written just as the output file would be.
+
+use std::io::{self, Write};
+
+/// The names `--preview-format` accepts.
+pub const PREVIEW_FORMAT_NAMES: [&str; 3] = ["table", "json", "csv"];
+
+/// How to print a preview.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PreviewFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl PreviewFormat {
+    /**
+     * Look up a preview format by name.
+     *
+     * # Arguments
+     *
+     * - `name`: The name, one of `PREVIEW_FORMAT_NAMES`
+     *
+     * # Returns
+     *
+     * The format, or `None` if the name isn't one.
+     */
+    pub fn from_name(name: &str) -> Option<PreviewFormat> {
+        match name {
+            "table" => Some(PreviewFormat::Table),
+            "json" => Some(PreviewFormat::Json),
+            "csv" => Some(PreviewFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Writes an aligned table, for reading in a terminal. The columns can't be
+ * sized until every row is in, so the rows are kept until `finish()`; a
+ * preview is only a few people.
+ */
+pub struct TableWriter<W: Write> {
+    w: W,
+    numeric: Vec<bool>,
+    /// The header row, then the others
+    rows: Vec<Vec<String>>,
+}
+
+impl<W: Write> TableWriter<W> {
+    /**
+     * Create a new writer.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the table
+     * - `headers`: The column headers, in order
+     * - `numeric`: Whether each column is numeric, and so right-aligned
+     */
+    pub fn new(w: W, headers: &[&String], numeric: &[bool]) -> TableWriter<W> {
+        let header_row = headers.iter().map(|h| cell(h)).collect();
+        TableWriter { w, numeric: numeric.to_vec(), rows: vec![header_row] }
+    }
+
+    /**
+     * Add a row. The values must be in column order.
+     */
+    pub fn write_row<S: AsRef<str>>(&mut self, values: &[S]) {
+        self.rows.push(values.iter().map(|v| cell(v.as_ref())).collect());
+    }
+
+    /**
+     * Write the table, and flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The table couldn't be written.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut widths = vec![0; self.numeric.len()];
+        for row in &self.rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+
+        let rule: Vec<String> = widths.iter().map(|n| "-".repeat(*n)).collect();
+        self.rows.insert(1, rule);
+
+        let mut line = String::new();
+        for row in &self.rows {
+            line.clear();
+            for (i, value) in row.iter().enumerate() {
+                if i > 0 {
+                    line.push_str("  ");
+                }
+                let padding = " ".repeat(widths[i] - value.chars().count());
+                if self.numeric[i] {
+                    line.push_str(&padding);
+                    line.push_str(value);
+                }
+                else {
+                    line.push_str(value);
+                    line.push_str(&padding);
+                }
+            }
+            writeln!(self.w, "{}", line.trim_end())?;
+        }
+
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// A value as a table cell: each control character (e.g., a line break,
+/// which would split the row) becomes a space.
+fn cell(s: &str) -> String {
+    s.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preview::*;
+
+    #[test]
+    fn table() {
+        let headers = [String::from("ID"), String::from("Last Name"), String::from("City")];
+        let mut w = TableWriter::new(Vec::new(), &[&headers[0], &headers[1], &headers[2]], &[true, false, false]);
+        w.write_row(&["1", "Howard", "New York"]);
+        w.write_row(&["10", "Fine\nLarry", ""]);
+
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "ID  Last Name   City\n\
+             --  ----------  --------\n\
+             \x201  Howard      New York\n\
+             10  Fine Larry\n"
+        );
+    }
+
+    #[test]
+    fn names() {
+        for name in PREVIEW_FORMAT_NAMES {
+            assert!(PreviewFormat::from_name(name).is_some());
+        }
+        assert_eq!(PreviewFormat::from_name("xml"), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! The optional pronouns field, derived from each person's gender. By
+//! default, "M" maps to "he/him", "F" to "she/her", and any other gender
+//! ("X", or one from `--gender-config`) to "they/them"; `--pronoun` overrides the
+//! mapping for a gender. A percentage of people can be given "ask" instead,
+//! as HR systems often allow.
+//!
+//! The "ask" choices come from their own random number generator, derived
+//! from the run's seed, so turning pronouns on or off doesn't change any of
+//! the other generated values.
+
+use crate::people::Gender;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+/// Mixed into the run's seed to seed the pronouns random number generator.
+const PRONOUNS_SEED_SALT: u64 = 0x7072_6f6e_6f75_6e73;
+
+/// The pronouns value for people who'd rather be asked.
+pub const PRONOUNS_ASK: &str = "ask";
+
+/// The settings for pronoun generation, from the command line.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PronounOptions {
+    /// Gender labels, and the pronouns to use for them, overriding the
+    /// defaults
+    pub mappings: Vec<(String, String)>,
+    /// The percentage of people whose pronouns are "ask"
+    pub ask_percent: u32,
+}
+
+/// Generates a pronouns value for each person.
+pub struct PronounGenerator {
+    rng: ChaCha8Rng,
+    mappings: HashMap<String, String>,
+    ask_percent: u32,
+}
+
+impl PronounGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The pronoun settings
+     * - `seed`: The run's random number generator seed
+     */
+    pub fn new(options: &PronounOptions, seed: u64) -> PronounGenerator {
+        PronounGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ PRONOUNS_SEED_SALT),
+            mappings: options.mappings.iter().cloned().collect(),
+            ask_percent: options.ask_percent,
+        }
+    }
+
+    /// Generate the pronouns for the next person, who has the given gender.
+    pub fn next_pronouns(&mut self, gender: &Gender) -> String {
+        // Always draw a number, so the sequence doesn't depend on the
+        // genders.
+        if self.rng.gen_range(0..100) < self.ask_percent {
+            return String::from(PRONOUNS_ASK);
+        }
+
+        match self.mappings.get(gender.to_str()) {
+            Some(pronouns) => pronouns.clone(),
+            None => String::from(default_pronouns(gender)),
+        }
+    }
+}
+
+/// The pronouns used for a gender when there's no `--pronoun` mapping for it.
+pub fn default_pronouns(gender: &Gender) -> &'static str {
+    match gender {
+        Gender::Male => "he/him",
+        Gender::Female => "she/her",
+        Gender::NonBinary | Gender::Custom(_) => "they/them",
+    }
+}
+
+/**
+ * Parse a `--pronoun` mapping, of the form `GENDER=PRONOUNS`, e.g.,
+ * `nonbinary=xe/xem`.
+ *
+ * # Arguments
+ *
+ * - `s`: The mapping
+ *
+ * # Returns
+ *
+ * - `Ok((gender, pronouns))`: The parsed mapping
+ * - `Err(msg)`: The mapping is invalid; `msg` explains why.
+ */
+pub fn parse_pronoun_mapping(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((gender, pronouns)) if !gender.trim().is_empty() && !pronouns.trim().is_empty() => {
+            Ok((gender.trim().to_string(), pronouns.trim().to_string()))
+        },
+        _ => Err(format!("Bad pronoun mapping \"{s}\": expected GENDER=PRONOUNS")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pronouns::*;
+
+    #[test]
+    fn mappings() {
+        let options = PronounOptions {
+            mappings: vec![(String::from("F"), String::from("she/they"))],
+            ask_percent: 0,
+        };
+        let mut g = PronounGenerator::new(&options, 42);
+
+        assert_eq!(g.next_pronouns(&Gender::Male), "he/him");
+        assert_eq!(g.next_pronouns(&Gender::Female), "she/they");
+        assert_eq!(g.next_pronouns(&Gender::NonBinary), "they/them");
+        assert_eq!(g.next_pronouns(&Gender::Custom(String::from("x"))), "they/them");
+    }
+
+    #[test]
+    fn ask() {
+        let options = PronounOptions { ask_percent: 100, ..Default::default() };
+        let mut g = PronounGenerator::new(&options, 42);
+        assert_eq!(g.next_pronouns(&Gender::Male), PRONOUNS_ASK);
+
+        let options = PronounOptions { ask_percent: 30, ..Default::default() };
+        let mut g = PronounGenerator::new(&options, 42);
+        let asks = (0..1000)
+            .filter(|_| g.next_pronouns(&Gender::Female) == PRONOUNS_ASK)
+            .count();
+        assert!((200..400).contains(&asks));
+    }
+
+    #[test]
+    fn parse_mappings() {
+        assert_eq!(
+            parse_pronoun_mapping("nonbinary = xe/xem"),
+            Ok((String::from("nonbinary"), String::from("xe/xem")))
+        );
+        assert!(parse_pronoun_mapping("nonbinary").is_err());
+        assert!(parse_pronoun_mapping("=xe/xem").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! The optional healthcare-provider identifiers, for provider datasets: a
+//! National Provider Identifier (NPI) and a DEA registration number for each
+//! person.
+//!
+//! An NPI is ten digits: a leading 1 or 2, eight more digits, and a Luhn
+//! check digit, computed with the `80840` prefix in front, as CMS specifies.
+//! A DEA number is two letters, six digits and a check digit (see
+//! `checksum::dea_check_digit()`). The first letter is the registrant type,
+//! and the second is the first letter of the person's last name, as with
+//! real registrations.
+//!
+//! Both are drawn from an offset (chosen with the seed) and the person's ID,
+//! so that NPIs are unique for up to `NPI_BODIES` people, and DEA numbers
+//! for up to `DEA_BODIES` people with the same two letters.
+
+use crate::checksum::{dea_check_digit, luhn_check_digit};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Mixed into the run's seed to seed the provider ID random number
+/// generator.
+const PROVIDER_SEED_SALT: u64 = 0x7072_6f76_6964_6572;
+
+/// The prefix NPI check digits are computed with: the health industry
+/// number (80) and the United States (840).
+const NPI_PREFIX: &str = "80840";
+
+/// How many different NPIs there are: leading 1 or 2, and eight digits.
+const NPI_BODIES: u64 = 200_000_000;
+
+/// Steps through the NPIs. It has no factors in common with `NPI_BODIES`, so
+/// the first `NPI_BODIES` IDs all get different NPIs.
+const NPI_STRIDE: u64 = 123_606_797;
+
+/// How many different six-digit DEA numbers there are.
+const DEA_BODIES: u64 = 1_000_000;
+
+/// Steps through the DEA numbers, as `NPI_STRIDE` does the NPIs.
+const DEA_STRIDE: u64 = 618_033;
+
+/// DEA registrant types for practitioners, and how common each is: `A` and
+/// `B` are the older practitioner registrations, `F` and `G` the newer ones,
+/// and `M` mid-level practitioners (nurse practitioners, physician
+/// assistants).
+const DEA_REGISTRANT_TYPES: [(char, u32); 5] = [('A', 10), ('B', 30), ('F', 25), ('G', 5), ('M', 30)];
+
+/// Generates NPIs and DEA numbers.
+pub struct ProviderIdGenerator {
+    rng: ChaCha8Rng,
+    npi_offset: u64,
+    dea_offset: u64,
+}
+
+impl ProviderIdGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `seed`: The run's random number generator seed
+     */
+    pub fn new(seed: u64) -> ProviderIdGenerator {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed ^ PROVIDER_SEED_SALT);
+        let npi_offset = rng.gen_range(0..NPI_BODIES);
+        let dea_offset = rng.gen_range(0..DEA_BODIES);
+        ProviderIdGenerator { rng, npi_offset, dea_offset }
+    }
+
+    /**
+     * Get the NPI for a person.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     */
+    pub fn npi(&self, id: u64) -> String {
+        let body = 100_000_000 + (self.npi_offset + id % NPI_BODIES * NPI_STRIDE) % NPI_BODIES;
+        format!("{body}{}", luhn_check_digit(&format!("{NPI_PREFIX}{body}")))
+    }
+
+    /**
+     * Generate the DEA number for a person.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     * - `last_name`: The person's last name, whose first letter is the
+     *   number's second letter
+     */
+    pub fn dea_number(&mut self, id: u64, last_name: &str) -> String {
+        let total: u32 = DEA_REGISTRANT_TYPES.iter().map(|(_, w)| w).sum();
+        let mut r = self.rng.gen_range(0..total);
+        let mut registrant = DEA_REGISTRANT_TYPES[0].0;
+        for (t, weight) in DEA_REGISTRANT_TYPES {
+            if r < weight {
+                registrant = t;
+                break;
+            }
+            r -= weight;
+        }
+
+        // DEA uses 9 when there's no letter to use.
+        let initial = last_name
+            .chars()
+            .find(|c| c.is_alphabetic())
+            .filter(char::is_ascii_alphabetic)
+            .map_or('9', |c| c.to_ascii_uppercase());
+
+        let body = (self.dea_offset + id % DEA_BODIES * DEA_STRIDE) % DEA_BODIES;
+        let digits = format!("{body:06}");
+        let mut d = [0; 6];
+        for (i, c) in digits.chars().enumerate() {
+            d[i] = c.to_digit(10).unwrap();
+        }
+
+        format!("{registrant}{initial}{digits}{}", dea_check_digit(d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::luhn_valid;
+    use crate::provider::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn npis() {
+        let g = ProviderIdGenerator::new(42);
+        let npis: Vec<String> = (1..=10000).map(|id| g.npi(id)).collect();
+
+        for npi in &npis {
+            assert_eq!(npi.len(), 10, "{npi}");
+            assert!(npi.starts_with('1') || npi.starts_with('2'), "{npi}");
+            assert!(luhn_valid(&format!("{NPI_PREFIX}{npi}")), "{npi}");
+        }
+        assert_eq!(npis.iter().collect::<HashSet<_>>().len(), npis.len());
+        assert_eq!(ProviderIdGenerator::new(42).npi(7), npis[6]);
+    }
+
+    #[test]
+    fn dea_numbers() {
+        let mut g = ProviderIdGenerator::new(42);
+        let mut seen = HashSet::new();
+
+        for id in 1..=10000 {
+            let dea = g.dea_number(id, "o'Brien");
+            let (letters, digits) = dea.split_at(2);
+            assert!(DEA_REGISTRANT_TYPES.iter().any(|(t, _)| letters.starts_with(*t)), "{dea}");
+            assert!(letters.ends_with('O'), "{dea}");
+            let d: Vec<u32> = digits.chars().map(|c| c.to_digit(10).unwrap()).collect();
+            assert_eq!(d.len(), 7, "{dea}");
+            assert_eq!(d[6], dea_check_digit([d[0], d[1], d[2], d[3], d[4], d[5]]), "{dea}");
+            assert!(seen.insert(digits.to_string()), "{dea}");
+        }
+
+        assert!(g.dea_number(1, "Ørsted").chars().nth(1) == Some('9'));
+    }
+}
@@ -0,0 +1,202 @@
+//! File rollover: one run's people written to as many numbered files as it
+//! takes (`people-00000.csv`, `people-00001.csv`, ...) for each file to stay
+//! under a row count, a size, or both, for loaders with a limit on either.
+//! Each file is a complete file in the output format, with its own header.
+//!
+//! Writers buffer what they write, so the size of a file isn't known
+//! exactly until it's finished. A new file is started once a file is within
+//! `SIZE_HEADROOM` of the size limit, which leaves more than enough room for
+//! what's still buffered, one more record, and the end of the file.
+
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+use crate::output::bytes_written;
+use crate::shards::shard_path;
+
+/// How close to the size limit a file gets before the next one starts.
+pub const SIZE_HEADROOM: u64 = 64 * 1024;
+
+/// The smallest size limit, so every file has room for some people.
+pub const MIN_FILE_SIZE: u64 = 2 * SIZE_HEADROOM;
+
+/// The `--max-rows-per-file` and `--max-bytes-per-file` limits.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Rollover {
+    pub max_rows: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+/**
+ * Parse a file size: a whole number of bytes, optionally followed by K, M
+ * or G (powers of 1024), with an optional "B" or "iB" (e.g., "1G", "500MB",
+ * "64KiB").
+ *
+ * # Arguments
+ *
+ * - `s`: The size
+ *
+ * # Returns
+ *
+ * - `Ok(bytes)`: The size in bytes
+ * - `Err(msg)`: The size is invalid; `msg` explains why.
+ */
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let err = || format!("Bad size \"{s}\": expected a number of bytes, optionally followed by K, M or G.");
+    let upper = s.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, multiplier) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 1 << 10),
+        Some('M') => (&number[..number.len() - 1], 1 << 20),
+        Some('G') => (&number[..number.len() - 1], 1 << 30),
+        _ => (number, 1),
+    };
+
+    digits.trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(err)
+}
+
+/**
+ * Write a stream of people to numbered files, each within the limits.
+ *
+ * # Arguments
+ *
+ * - `rollover`: The limits
+ * - `path`: The output file, which the numbered files are named after
+ * - `people`: The people
+ * - `write`: Writes a stream of people to a file, returning how many were
+ *   written. It has to write on the calling thread, through an
+ *   `OutputFile`, for the size limit to work.
+ *
+ * # Returns
+ *
+ * - `Ok((total, paths))`: How many people were written, altogether, and the
+ *   files they were written to. There's always at least one file.
+ * - `Err(msg)`: Generating or writing failed; `msg` explains why.
+ */
+pub fn write_rolling<T, I, W>(
+    rollover: &Rollover,
+    path: &Path,
+    people: I,
+    mut write: W,
+) -> Result<(u64, Vec<PathBuf>), String>
+where
+    I: Iterator<Item = Result<T, String>>,
+    W: FnMut(&Path, &mut dyn Iterator<Item = Result<T, String>>) -> Result<u64, String>,
+{
+    let mut people = people.peekable();
+    let mut paths = Vec::new();
+    let mut written = 0;
+
+    loop {
+        let file = shard_path(path, paths.len());
+        let mut limited = Limited { people: &mut people, rollover, rows: 0, start: bytes_written() };
+        written += write(&file, &mut limited)?;
+        paths.push(file);
+
+        if people.peek().is_none() {
+            return Ok((written, paths));
+        }
+    }
+}
+
+/// The people for one file: a stream that ends when the file is full.
+struct Limited<'a, I: Iterator> {
+    people: &'a mut Peekable<I>,
+    rollover: &'a Rollover,
+    /// How many people have been written to the file
+    rows: u64,
+    /// What `bytes_written()` was when the file was started
+    start: u64,
+}
+
+impl<I: Iterator> Iterator for Limited<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let full_of_rows = self.rollover.max_rows.is_some_and(|max| self.rows >= max);
+        let full_of_bytes = self.rollover.max_bytes.is_some_and(|max| {
+            self.rows > 0 && bytes_written() - self.start + SIZE_HEADROOM >= max
+        });
+        if full_of_rows || full_of_bytes {
+            return None;
+        }
+
+        self.rows += 1;
+        self.people.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rollover::*;
+    use crate::output::OutputFile;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Write the numbers 1 to `total`, a line each, to rolled-over files, and
+    /// get the files' contents.
+    fn roll(rollover: Rollover, total: u64) -> Vec<String> {
+        let dir = TempDir::new().unwrap();
+        let people = (1..=total).map(Ok::<u64, String>);
+
+        let (written, paths) = write_rolling(&rollover, &dir.path().join("n.txt"), people, |path, people| {
+            let mut file = OutputFile::create(path, None).map_err(|e| e.to_string())?;
+            let mut n = 0;
+            for number in people {
+                writeln!(file, "{:099}", number?).map_err(|e| e.to_string())?;
+                n += 1;
+            }
+            file.finish().map_err(|e| e.to_string())?;
+            Ok(n)
+        }).unwrap();
+
+        assert_eq!(written, total);
+        paths.iter().map(|p| std::fs::read_to_string(p).unwrap()).collect()
+    }
+
+    #[test]
+    fn rows() {
+        let files = roll(Rollover { max_rows: Some(3), max_bytes: None }, 7);
+        let lines: Vec<usize> = files.iter().map(|f| f.lines().count()).collect();
+        assert_eq!(lines, [3, 3, 1]);
+
+        // An empty run still has a file.
+        assert_eq!(roll(Rollover { max_rows: Some(3), max_bytes: None }, 0), [""]);
+    }
+
+    #[test]
+    fn bytes() {
+        // 100-byte lines, in files of up to 128 KiB.
+        let files = roll(Rollover { max_rows: None, max_bytes: Some(MIN_FILE_SIZE) }, 5000);
+        assert_eq!(files.len(), 8);
+        assert!(files.iter().all(|f| f.len() as u64 <= MIN_FILE_SIZE));
+        assert!(files[0].len() as u64 >= MIN_FILE_SIZE - SIZE_HEADROOM);
+        assert!(files[7].ends_with(&format!("{:099}\n", 5000)));
+
+        // Whichever limit comes first starts a new file.
+        let lines = |max_rows| {
+            roll(Rollover { max_rows: Some(max_rows), max_bytes: Some(MIN_FILE_SIZE) }, 1000)
+                .iter()
+                .map(|f| f.lines().count())
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(lines(500), [500, 500]);
+        assert_eq!(lines(1000), [656, 344]);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1000"), Ok(1000));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("500MB"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert_eq!(parse_size(" 2g "), Ok(2 << 30));
+        assert!(parse_size("1T").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("-1K").is_err());
+    }
+}
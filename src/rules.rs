@@ -0,0 +1,409 @@
+//! Conditional rules, which override field values based on other fields,
+//! for simple business logic (minors have no salary, a gender gets a
+//! particular pronoun, and so on).
+//!
+//! Rules are read from a file given with `--rules`, one rule per line:
+//!
+//! ```text
+//! # Blank lines and lines starting with "#" are ignored.
+//! if age < 18 then salary = 0
+//! if gender == "F" and salary >= 100000 then source_system = "exec-hr"
+//! if age < 16 then ssn = null
+//! ```
+//!
+//! A condition compares a field with a number, a quoted string or `null`
+//! (an empty value), using `==`, `!=`, `<`, `<=`, `>` or `>=`. Numbers are
+//! compared numerically, and strings alphabetically (so ISO dates compare
+//! correctly, too). Besides the fields, conditions can use `age`: the
+//! person's age in whole years, today.
+//!
+//! Conditions see the values as generated, before formatting, and before any
+//! rule changes them. When more than one rule sets a field, the last one
+//! whose conditions hold wins.
+
+use chrono::{Datelike, NaiveDate};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+/// The derived field that holds a person's age, for conditions.
+pub const AGE_FIELD: &str = "age";
+
+/// Fields that hold whole numbers, and so can only be set to one.
+const NUMERIC_FIELDS: [&str; 2] = ["id", "salary"];
+
+/// A literal value in a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// An empty value
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A condition: a field compared with a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub field: String,
+    pub op: Operator,
+    pub value: Value,
+}
+
+/// A rule: when all of its conditions hold, the field gets the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub conditions: Vec<Condition>,
+    pub field: String,
+    pub value: Value,
+}
+
+/// The rules for a run, and the date that ages are computed on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub rules: Vec<Rule>,
+    pub today: NaiveDate,
+}
+
+impl Value {
+    /// The value as it's written to a field.
+    fn to_field(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Number(n) => n.to_string(),
+            Value::Text(s) => s.clone(),
+        }
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds for a field value.
+    pub fn holds(&self, actual: &str) -> bool {
+        let ordering = match &self.value {
+            Value::Null => Some(actual.cmp("")),
+            Value::Number(n) => actual.parse::<f64>().ok().and_then(|a| a.partial_cmp(n)),
+            Value::Text(s) => Some(actual.cmp(s.as_str())),
+        };
+
+        // A number can't be compared with a non-numeric value, so only
+        // "!=" holds.
+        match ordering {
+            None => self.op == Operator::Ne,
+            Some(o) => match self.op {
+                Operator::Eq => o == Ordering::Equal,
+                Operator::Ne => o != Ordering::Equal,
+                Operator::Lt => o == Ordering::Less,
+                Operator::Le => o != Ordering::Greater,
+                Operator::Gt => o == Ordering::Greater,
+                Operator::Ge => o != Ordering::Less,
+            },
+        }
+    }
+}
+
+impl Rules {
+    /**
+     * Get the value of a field, as the rules set it.
+     *
+     * # Arguments
+     *
+     * - `field`: The field
+     * - `value_of`: Gets the generated value of any field, for the
+     *   conditions
+     *
+     * # Returns
+     *
+     * - `Some(value)`: The value set by the last rule for `field` whose
+     *   conditions hold
+     * - `None`: No rule applies, so the field keeps its generated value.
+     */
+    pub fn apply<F: Fn(&str) -> String>(&self, field: &str, value_of: F) -> Option<String> {
+        self.rules
+            .iter()
+            .rev()
+            .filter(|r| r.field == field)
+            .find(|r| r.conditions.iter().all(|c| {
+                let actual = if c.field == AGE_FIELD {
+                    age(&value_of("birth_date"), self.today)
+                }
+                else {
+                    value_of(&c.field)
+                };
+                c.holds(&actual)
+            }))
+            .map(|r| r.value.to_field())
+    }
+}
+
+/// A person's age in whole years on a date, given their ISO birth date.
+fn age(birth_date: &str, today: NaiveDate) -> String {
+    match NaiveDate::parse_from_str(birth_date, "%Y-%m-%d") {
+        Ok(born) => {
+            let birthday_passed = (today.month(), today.day()) >= (born.month(), born.day());
+            let years = today.year() - born.year() - if birthday_passed { 0 } else { 1 };
+            years.to_string()
+        },
+        Err(_) => String::new(),
+    }
+}
+
+/**
+ * Read a rules file.
+ *
+ * # Arguments
+ *
+ * - `path`: The rules file
+ * - `fields`: The valid field names (besides `AGE_FIELD`)
+ *
+ * # Returns
+ *
+ * - `Ok(rules)`: The rules, in order
+ * - `Err(msg)`: The file can't be read or is invalid; `msg` explains why.
+ */
+pub fn read_rules_file(path: &Path, fields: &[&str]) -> Result<Vec<Rule>, String> {
+    let err = |msg: String| format!("\"{}\": {}", path.display(), msg);
+    let text = fs::read_to_string(path).map_err(|e| err(e.to_string()))?;
+    parse_rules(&text, fields).map_err(err)
+}
+
+/**
+ * Parse the contents of a rules file. See `read_rules_file()`.
+ *
+ * # Arguments
+ *
+ * - `text`: The contents of the file
+ * - `fields`: The valid field names (besides `AGE_FIELD`)
+ *
+ * # Returns
+ *
+ * - `Ok(rules)`: The rules, in order
+ * - `Err(msg)`: A rule is invalid; `msg` explains why, with its line number.
+ */
+pub fn parse_rules(text: &str, fields: &[&str]) -> Result<Vec<Rule>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_rule(line, fields).map_err(|e| format!("Line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// A token in a rule.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Text(String),
+    Op(Operator),
+    Assign,
+}
+
+/// Parse a single rule: `if CONDITION [and CONDITION ...] then FIELD = VALUE`.
+fn parse_rule(line: &str, fields: &[&str]) -> Result<Rule, String> {
+    let mut tokens = tokenize(line)?.into_iter();
+    let mut next = || tokens.next();
+
+    if next() != Some(Token::Word(String::from("if"))) {
+        return Err(String::from("A rule must start with \"if\"."));
+    }
+
+    let mut conditions = Vec::new();
+    loop {
+        let field = match next() {
+            Some(Token::Word(w)) if w == AGE_FIELD || fields.contains(&w.as_str()) => w,
+            Some(Token::Word(w)) => return Err(format!("Unknown field \"{w}\".")),
+            _ => return Err(String::from("Expected a field name after \"if\" or \"and\".")),
+        };
+        let op = match next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("Expected ==, !=, <, <=, > or >= after \"{field}\".")),
+        };
+        let value = literal(next())?;
+        if value == Value::Null && !matches!(op, Operator::Eq | Operator::Ne) {
+            return Err(String::from("null can only be compared with == or !=."));
+        }
+        conditions.push(Condition { field, op, value });
+
+        match next() {
+            Some(Token::Word(w)) if w == "and" => continue,
+            Some(Token::Word(w)) if w == "then" => break,
+            _ => return Err(String::from("Expected \"and\" or \"then\" after a condition.")),
+        }
+    }
+
+    let field = match next() {
+        Some(Token::Word(w)) if fields.contains(&w.as_str()) => w,
+        Some(Token::Word(w)) => return Err(format!("Can't set \"{w}\": not a field.")),
+        _ => return Err(String::from("Expected a field name after \"then\".")),
+    };
+    if next() != Some(Token::Assign) {
+        return Err(format!("Expected \"=\" after \"{field}\"."));
+    }
+    let value = literal(next())?;
+    if next().is_some() {
+        return Err(String::from("Unexpected text after the value."));
+    }
+
+    let whole_number = matches!(value, Value::Number(n) if n >= 0.0 && n.fract() == 0.0);
+    if NUMERIC_FIELDS.contains(&field.as_str()) && !whole_number {
+        return Err(format!("{field} can only be set to a whole number."));
+    }
+
+    Ok(Rule { conditions, field, value })
+}
+
+/// Turn a token into a literal value.
+fn literal(token: Option<Token>) -> Result<Value, String> {
+    match token {
+        Some(Token::Number(n)) => Ok(Value::Number(n)),
+        Some(Token::Text(s)) => Ok(Value::Text(s)),
+        Some(Token::Word(w)) if w == "null" => Ok(Value::Null),
+        _ => Err(String::from("Expected a number, a quoted string or null.")),
+    }
+}
+
+/// Split a rule into tokens.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        }
+        else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+        else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '-' || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            let n = number.parse::<f64>().map_err(|_| format!("Bad number \"{number}\"."))?;
+            tokens.push(Token::Number(n));
+        }
+        else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c @ ('"' | '\\')) => text.push(c),
+                        _ => return Err(String::from("Only \\\" and \\\\ can be escaped in a string.")),
+                    },
+                    Some(c) => text.push(c),
+                    None => return Err(String::from("Unterminated string.")),
+                }
+            }
+            tokens.push(Token::Text(text));
+        }
+        else {
+            chars.next();
+            let two = chars.peek() == Some(&'=');
+            let token = match (c, two) {
+                ('=', true) => Token::Op(Operator::Eq),
+                ('!', true) => Token::Op(Operator::Ne),
+                ('<', true) => Token::Op(Operator::Le),
+                ('>', true) => Token::Op(Operator::Ge),
+                ('<', false) => Token::Op(Operator::Lt),
+                ('>', false) => Token::Op(Operator::Gt),
+                ('=', false) => Token::Assign,
+                _ => return Err(format!("Unexpected \"{c}\".")),
+            };
+            if two {
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::*;
+
+    const FIELDS: [&str; 4] = ["id", "gender", "salary", "ssn"];
+
+    #[test]
+    fn parsing() {
+        let rules = parse_rules(
+            "# Comment\n\nif gender == \"F\" and salary >= 100000 then ssn = null\nif age<18 then salary=0\n",
+            &FIELDS,
+        ).unwrap();
+
+        assert_eq!(rules, vec![
+            Rule {
+                conditions: vec![
+                    Condition { field: String::from("gender"), op: Operator::Eq, value: Value::Text(String::from("F")) },
+                    Condition { field: String::from("salary"), op: Operator::Ge, value: Value::Number(100000.0) },
+                ],
+                field: String::from("ssn"),
+                value: Value::Null,
+            },
+            Rule {
+                conditions: vec![Condition { field: String::from("age"), op: Operator::Lt, value: Value::Number(18.0) }],
+                field: String::from("salary"),
+                value: Value::Number(0.0),
+            },
+        ]);
+
+        let bad = |s: &str| parse_rules(s, &FIELDS).unwrap_err();
+        assert_eq!(bad("\nwhen gender == \"F\" then ssn = null"), "Line 2: A rule must start with \"if\".");
+        assert!(bad("if country == \"CA\" then ssn = null").contains("Unknown field \"country\""));
+        assert!(bad("if gender is \"F\" then ssn = null").contains("Expected =="));
+        assert!(bad("if gender == \"F then ssn = null").contains("Unterminated"));
+        assert!(bad("if gender < null then ssn = null").contains("null can only"));
+        assert!(bad("if gender == \"F\" then age = 1").contains("Can't set \"age\""));
+        assert!(bad("if gender == \"F\" then salary = null").contains("whole number"));
+        assert!(bad("if gender == \"F\" then ssn = \"x\" y").contains("Unexpected text"));
+    }
+
+    #[test]
+    fn applying() {
+        let rules = Rules {
+            rules: parse_rules(
+                "if age < 18 then salary = 0\n\
+                 if gender == \"F\" then ssn = \"redacted\"\n\
+                 if gender == \"F\" and salary > 50000 then ssn = null\n",
+                &FIELDS,
+            ).unwrap(),
+            today: NaiveDate::from_ymd(2024, 6, 1),
+        };
+        let person = |gender: &'static str, salary: &'static str, born: &'static str| {
+            move |field: &str| String::from(match field {
+                "gender" => gender,
+                "salary" => salary,
+                "birth_date" => born,
+                _ => "",
+            })
+        };
+
+        // Turns 18 the day after "today".
+        assert_eq!(rules.apply("salary", person("M", "40000", "2006-06-02")), Some(String::from("0")));
+        assert_eq!(rules.apply("salary", person("M", "40000", "2006-06-01")), None);
+
+        // The last rule that applies wins.
+        assert_eq!(rules.apply("ssn", person("F", "40000", "1980-01-01")), Some(String::from("redacted")));
+        assert_eq!(rules.apply("ssn", person("F", "60000", "1980-01-01")), Some(String::new()));
+        assert_eq!(rules.apply("ssn", person("M", "60000", "1980-01-01")), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! Salary distributions. By default, salaries are normally distributed,
+//! with `--salary-mean` and `--salary-sigma`. Since compensation is usually
+//! described in percentiles instead, `--salary-p50` and `--salary-p90` give
+//! the median and the 90th percentile, and salaries follow the log-normal
+//! distribution that has them. Log-normal salaries are skewed to the right,
+//! like real ones, and never negative.
+//!
+//! For multi-modal salaries (e.g., for testing histogram and binning code),
+//! `--salary-mix` draws each salary from one of several distributions,
+//! chosen at random with the given percentages:
+//!
+//! ```text
+//! 70% normal(50k, 8k) + 30% lognormal(120k, 250k)
+//! ```
+//!
+//! The components are `normal(MEAN, SIGMA)`, `lognormal(P50, P90)` (fit to
+//! percentiles, as with `--salary-p50` and `--salary-p90`) and
+//! `uniform(MIN, MAX)`. Amounts can end in `k` (thousands) or `m` (millions),
+//! and the percentages have to add up to 100.
+//!
+//! Real payrolls cluster at round numbers, so salaries can be rounded, too:
+//! to the nearest multiple of an amount (`--salary-round 1000`), or to a
+//! number of significant digits (`--salary-precision 2`).
+
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal, Uniform};
+
+/// The z-score of the 90th percentile of the standard normal distribution.
+const Z_90: f64 = 1.281_551_565_545;
+
+/// Salary percentile targets.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SalaryPercentiles {
+    pub p50: u32,
+    pub p90: u32,
+}
+
+/// How salaries are rounded.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum SalaryRounding {
+    /// Only to whole numbers, by dropping the fraction
+    #[default]
+    None,
+    /// To the nearest multiple of an amount
+    Nearest(u32),
+    /// To a number of significant digits
+    Significant(u32),
+}
+
+impl SalaryRounding {
+    /// Round a salary.
+    pub fn apply(&self, salary: f64) -> f64 {
+        match self {
+            SalaryRounding::None => salary.trunc(),
+            SalaryRounding::Nearest(n) => (salary / *n as f64).round() * *n as f64,
+            SalaryRounding::Significant(_) if salary <= 0.0 => salary.round(),
+            SalaryRounding::Significant(digits) => {
+                let magnitude = salary.log10().floor() as i32 + 1;
+                let unit = 10f64.powi(magnitude - *digits as i32);
+                ((salary / unit).round() * unit).round()
+            },
+        }
+    }
+}
+
+/// A component of a salary mixture.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MixtureComponent {
+    Normal { mean: u32, sigma: u32 },
+    LogNormal(SalaryPercentiles),
+    Uniform { min: u32, max: u32 },
+}
+
+/// A salary mixture: components, with the percentage of salaries drawn from
+/// each.
+pub type SalaryMix = Vec<(u32, MixtureComponent)>;
+
+/// The distribution salaries are drawn from.
+#[derive(Debug, Clone)]
+pub enum SalaryDistribution {
+    Normal(Normal<f32>),
+    LogNormal(LogNormal<f64>),
+    Uniform(Uniform<f64>),
+    /// Each distribution, with its cumulative percentage
+    Mixture(Vec<(u32, SalaryDistribution)>),
+}
+
+impl SalaryDistribution {
+    /**
+     * Create the distribution for a run.
+     *
+     * # Arguments
+     *
+     * - `mean`: The mean, for a normal distribution
+     * - `sigma`: The standard deviation, for a normal distribution
+     * - `percentiles`: If set, the percentiles to fit a log-normal
+     *   distribution to, instead
+     *
+     * # Returns
+     *
+     * - `Ok(distribution)`: The distribution
+     * - `Err(msg)`: The parameters are invalid; `msg` explains why.
+     */
+    pub fn new(
+        mean: u32,
+        sigma: u32,
+        percentiles: Option<&SalaryPercentiles>,
+    ) -> Result<SalaryDistribution, String> {
+        match percentiles {
+            Some(p) => fit_log_normal(p).map(SalaryDistribution::LogNormal),
+            None => Normal::new(mean as f32, sigma as f32)
+                .map(SalaryDistribution::Normal)
+                .map_err(|e| format!("{}", e)),
+        }
+    }
+
+    /**
+     * Create a mixture distribution.
+     *
+     * # Arguments
+     *
+     * - `mix`: The components, e.g., from `parse_salary_mix()`
+     *
+     * # Returns
+     *
+     * - `Ok(distribution)`: The distribution
+     * - `Err(msg)`: A component is invalid; `msg` explains why.
+     */
+    pub fn mixture(mix: &SalaryMix) -> Result<SalaryDistribution, String> {
+        let mut cumulative = 0;
+        let components = mix
+            .iter()
+            .map(|(percent, c)| {
+                cumulative += percent;
+                let d = match c {
+                    MixtureComponent::Normal { mean, sigma } => {
+                        SalaryDistribution::new(*mean, *sigma, None)?
+                    },
+                    MixtureComponent::LogNormal(p) => {
+                        SalaryDistribution::LogNormal(fit_log_normal(p)?)
+                    },
+                    MixtureComponent::Uniform { min, max } => {
+                        SalaryDistribution::Uniform(Uniform::new_inclusive(*min as f64, *max as f64))
+                    },
+                };
+                Ok((cumulative, d))
+            })
+            .collect::<Result<Vec<(u32, SalaryDistribution)>, String>>()?;
+
+        Ok(SalaryDistribution::Mixture(components))
+    }
+
+    /// Draw a salary. Only a normal distribution can produce negative ones.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match self {
+            SalaryDistribution::Normal(d) => d.sample(rng) as f64,
+            SalaryDistribution::LogNormal(d) => d.sample(rng),
+            SalaryDistribution::Uniform(d) => d.sample(rng),
+            SalaryDistribution::Mixture(components) => {
+                let r = rng.gen_range(0..100);
+                components
+                    .iter()
+                    .find(|(cumulative, _)| r < *cumulative)
+                    .map_or(0.0, |(_, d)| d.sample(rng))
+            },
+        }
+    }
+}
+
+/**
+ * Parse a salary mixture, e.g., "70% normal(50k, 8k) + 30% lognormal(120k,
+ * 250k)".
+ *
+ * # Arguments
+ *
+ * - `s`: The mixture
+ *
+ * # Returns
+ *
+ * - `Ok(mix)`: The components, in order, with their percentages
+ * - `Err(msg)`: The mixture is invalid; `msg` explains why.
+ */
+pub fn parse_salary_mix(s: &str) -> Result<SalaryMix, String> {
+    let mix = s
+        .split('+')
+        .map(|c| parse_component(c.trim()))
+        .collect::<Result<SalaryMix, String>>()?;
+
+    let total: u64 = mix.iter().map(|(p, _)| *p as u64).sum();
+    if total != 100 {
+        return Err(format!("Salary mixture percentages must add up to 100, not {total}."));
+    }
+
+    // Catch bad percentiles now, instead of when generating.
+    for (_, c) in &mix {
+        if let MixtureComponent::LogNormal(p) = c {
+            fit_log_normal(p)?;
+        }
+    }
+
+    Ok(mix)
+}
+
+fn parse_component(s: &str) -> Result<(u32, MixtureComponent), String> {
+    let bad = || format!(
+        "Bad salary mixture component \"{s}\": expected PERCENT% DISTRIBUTION(A, B), e.g., \"70% normal(50k, 8k)\"."
+    );
+    let (percent, rest) = s.split_once('%').ok_or_else(bad)?;
+    let percent = percent.trim().parse::<u32>().map_err(|_| bad())?;
+    let (name, params) = rest.trim().strip_suffix(')').and_then(|r| r.split_once('(')).ok_or_else(bad)?;
+    let params: Vec<u32> = params
+        .split(',')
+        .map(|p| parse_amount(p.trim()))
+        .collect::<Result<Vec<u32>, String>>()?;
+    let [a, b] = params[..] else {
+        return Err(format!("\"{name}\" needs two parameters, in \"{s}\"."));
+    };
+
+    let component = match name.trim() {
+        "normal" => MixtureComponent::Normal { mean: a, sigma: b },
+        "lognormal" => MixtureComponent::LogNormal(SalaryPercentiles { p50: a, p90: b }),
+        "uniform" if a <= b => MixtureComponent::Uniform { min: a, max: b },
+        "uniform" => return Err(format!("The minimum exceeds the maximum, in \"{s}\".")),
+        other => return Err(format!(
+            "Unknown distribution \"{other}\". Valid distributions: normal, lognormal, uniform"
+        )),
+    };
+
+    Ok((percent, component))
+}
+
+/// Parse an amount, which can end in "k" (thousands) or "m" (millions).
+fn parse_amount(s: &str) -> Result<u32, String> {
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1_000.0),
+        Some((i, 'm' | 'M')) => (&s[..i], 1_000_000.0),
+        _ => (s, 1.0),
+    };
+
+    match digits.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n * multiplier <= u32::MAX as f64 => Ok((n * multiplier).round() as u32),
+        _ => Err(format!("Bad amount \"{s}\" in salary mixture.")),
+    }
+}
+
+/**
+ * Fit a log-normal distribution to salary percentiles. The median of a
+ * log-normal distribution is `e^mu`, and its 90th percentile is
+ * `e^(mu + Z_90 * sigma)`, so both parameters follow from the two targets.
+ *
+ * # Arguments
+ *
+ * - `p`: The percentile targets
+ *
+ * # Returns
+ *
+ * - `Ok(distribution)`: The distribution
+ * - `Err(msg)`: No distribution fits; `msg` explains why.
+ */
+pub fn fit_log_normal(p: &SalaryPercentiles) -> Result<LogNormal<f64>, String> {
+    if p.p50 == 0 {
+        return Err(String::from("--salary-p50 must be greater than 0."));
+    }
+    if p.p90 <= p.p50 {
+        return Err(format!(
+            "--salary-p90 ({}) must be greater than --salary-p50 ({}).", p.p90, p.p50
+        ));
+    }
+
+    let mu = (p.p50 as f64).ln();
+    let sigma = ((p.p90 as f64).ln() - mu) / Z_90;
+    LogNormal::new(mu, sigma).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::salary::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn percentiles_are_hit() {
+        let p = SalaryPercentiles { p50: 60_000, p90: 150_000 };
+        let d = SalaryDistribution::new(0, 0, Some(&p)).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut salaries: Vec<f64> = (0..20_000).map(|_| d.sample(&mut rng)).collect();
+        salaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let percentile = |q: usize| salaries[salaries.len() * q / 100];
+        assert!((percentile(50) / 60_000.0 - 1.0).abs() < 0.02, "p50 = {}", percentile(50));
+        assert!((percentile(90) / 150_000.0 - 1.0).abs() < 0.03, "p90 = {}", percentile(90));
+        assert!(salaries[0] > 0.0);
+    }
+
+    #[test]
+    fn mixtures() {
+        let mix = parse_salary_mix("70% normal(50k, 8k) + 20% lognormal(300k,500k) + 10%uniform(1.5m, 2m)")
+            .unwrap();
+        assert_eq!(mix, vec![
+            (70, MixtureComponent::Normal { mean: 50_000, sigma: 8_000 }),
+            (20, MixtureComponent::LogNormal(SalaryPercentiles { p50: 300_000, p90: 500_000 })),
+            (10, MixtureComponent::Uniform { min: 1_500_000, max: 2_000_000 }),
+        ]);
+
+        // Each salary comes from one of the components, in proportion.
+        let d = SalaryDistribution::mixture(&mix).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let salaries: Vec<f64> = (0..10_000).map(|_| d.sample(&mut rng)).collect();
+        let share = |f: fn(&f64) -> bool| salaries.iter().filter(|s| f(s)).count() as f64 / 10_000.0;
+        assert!((share(|s| *s >= 1_500_000.0) - 0.1).abs() < 0.02);
+        assert!((share(|s| *s < 80_000.0) - 0.7).abs() < 0.03);
+
+        let bad = |s: &str| parse_salary_mix(s).unwrap_err();
+        assert!(bad("70% normal(50k, 8k)").contains("not 70"));
+        assert!(bad("100% gamma(1, 2)").contains("Unknown distribution"));
+        assert!(bad("100% normal(50k)").contains("two parameters"));
+        assert!(bad("100% normal(50x, 1)").contains("Bad amount"));
+        assert!(bad("normal(50k, 1)").contains("expected PERCENT%"));
+        assert!(bad("100% uniform(2, 1)").contains("minimum"));
+        assert!(bad("100% lognormal(2, 1)").contains("greater than"));
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(SalaryRounding::None.apply(58_260.6), 58_260.0);
+        assert_eq!(SalaryRounding::Nearest(1000).apply(58_260.6), 58_000.0);
+        assert_eq!(SalaryRounding::Nearest(5000).apply(57_500.0), 60_000.0);
+        assert_eq!(SalaryRounding::Significant(2).apply(58_260.6), 58_000.0);
+        assert_eq!(SalaryRounding::Significant(3).apply(158_760.0), 159_000.0);
+        assert_eq!(SalaryRounding::Significant(1).apply(0.0), 0.0);
+    }
+
+    #[test]
+    fn bad_percentiles() {
+        assert!(fit_log_normal(&SalaryPercentiles { p50: 60_000, p90: 60_000 }).is_err());
+        assert!(fit_log_normal(&SalaryPercentiles { p50: 0, p90: 1 }).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+//! Scenario files: `peoplegen scenario FILE` runs several generation steps as
+//! one command, from a TOML file, instead of a shell script that runs
+//! `peoplegen` once for each file:
+//!
+//! ```toml
+//! seed = 42
+//! id = true
+//!
+//! [[step]]
+//! name = "employees"
+//! output = "employees.csv"
+//! total = 500
+//! salary = true
+//! shifts = "shifts.csv"
+//! w2 = "w2.csv"
+//!
+//! [[step]]
+//! name = "patients"
+//! output = "patients.csv"
+//! total = 2000
+//! immunizations = "immunizations.csv"
+//! ```
+//!
+//! Each `[[step]]` holds a run's options, as a configuration file does (see
+//! `config_file`), and `name`, which error messages go by. The options at
+//! the top are every step's, unless the step gives its own. The steps run in
+//! order, and share:
+//!
+//! - the seed: each step's is derived from the scenario's, so one seed
+//!   reproduces every file, and no two steps generate the same people.
+//! - the ID space: each step's people's IDs carry on from the step before's,
+//!   so they're unique across the scenario, and so are the IDs in the
+//!   records that refer to them (shifts, W-2s and so on).
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use clap::Command;
+use toml::{Table, Value};
+
+use crate::args::Arguments;
+use crate::config_file::table_args;
+use crate::output::{is_null, is_stdout};
+use crate::path::path_str;
+use crate::shards::{shard_path, shard_paths};
+
+/// The key of a scenario file's steps.
+pub const STEPS_KEY: &str = "step";
+
+/// The key of a step's name.
+const NAME_KEY: &str = "name";
+
+/// The key of the scenario's seed.
+const SEED_KEY: &str = "seed";
+
+/// Mixed into the scenario's seed, once for each step before, to seed a step.
+const STEP_SEED_SALT: u64 = 0x7363_656e_6172_696f;
+
+/// A scenario, as its file gives it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// The seed, if the file gives one
+    pub seed: Option<u64>,
+    pub steps: Vec<Step>,
+}
+
+/// A step of a scenario, before its options are parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// What messages about the step call it: its name, or its number
+    pub label: String,
+    /// Its options, the shared ones included, as command-line arguments
+    pub args: Vec<String>,
+}
+
+/**
+ * Read a scenario file.
+ *
+ * # Arguments
+ *
+ * - `path`: The file
+ * - `command`: The command-line parser the steps' options are for
+ *
+ * # Returns
+ *
+ * - `Ok(scenario)`: The scenario
+ * - `Err(msg)`: The file couldn't be read or is invalid; `msg` explains why.
+ */
+pub fn read_scenario(path: &Path, command: &Command) -> Result<Scenario, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
+    parse_scenario(&contents, command).map_err(|e| format!("\"{}\": {}", path_str(path), e))
+}
+
+/**
+ * Parse the contents of a scenario file.
+ *
+ * # Arguments
+ *
+ * - `contents`: The file's contents
+ * - `command`: The command-line parser the steps' options are for
+ *
+ * # Returns
+ *
+ * - `Ok(scenario)`: The scenario
+ * - `Err(msg)`: The scenario is invalid; `msg` explains why.
+ */
+pub fn parse_scenario(contents: &str, command: &Command) -> Result<Scenario, String> {
+    let mut shared: Table = contents.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+    let steps = match shared.remove(STEPS_KEY) {
+        Some(Value::Array(steps)) if !steps.is_empty() => steps,
+        None | Some(Value::Array(_)) => return Err(String::from("No steps: each one is a [[step]] table.")),
+        Some(_) => return Err(format!("\"{STEPS_KEY}\" must be an array of tables: [[step]].")),
+    };
+    let seed = match shared.remove(SEED_KEY) {
+        Some(Value::Integer(n)) if n >= 0 => Some(n as u64),
+        Some(_) => return Err(format!("\"{SEED_KEY}\" must be a number from 0 up.")),
+        None => None,
+    };
+    // A step's options replace the shared ones, however their keys are
+    // written.
+    let shared: Table = shared.into_iter().map(|(key, value)| (key.replace('_', "-"), value)).collect();
+
+    let steps = steps
+        .into_iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let Value::Table(mut step) = step else {
+                return Err(format!("\"{STEPS_KEY}\" must be an array of tables: [[step]]."));
+            };
+            let label = match step.remove(NAME_KEY) {
+                Some(Value::String(name)) => format!("Step \"{name}\""),
+                Some(_) => return Err(format!("Step {}: \"{NAME_KEY}\" must be a string.", i + 1)),
+                None => format!("Step {}", i + 1),
+            };
+            if step.contains_key(SEED_KEY) {
+                return Err(format!("{label}: \"{SEED_KEY}\" goes at the top, where every step shares it."));
+            }
+
+            let mut options = shared.clone();
+            options.extend(step.into_iter().map(|(key, value)| (key.replace('_', "-"), value)));
+            let args = table_args(command, &options).map_err(|e| format!("{label}: {e}"))?;
+            Ok(Step { label, args })
+        })
+        .collect::<Result<Vec<Step>, String>>()?;
+
+    Ok(Scenario { seed, steps })
+}
+
+/**
+ * Get a step's seed.
+ *
+ * # Arguments
+ *
+ * - `seed`: The scenario's seed
+ * - `step`: The step's index, from 0. The first step's seed is the
+ *   scenario's.
+ */
+pub fn step_seed(seed: u64, step: usize) -> u64 {
+    seed ^ STEP_SEED_SALT.wrapping_mul(step as u64)
+}
+
+/// Something a step writes.
+#[derive(Debug, Clone, PartialEq)]
+enum Output {
+    /// A file, or a directory of files
+    Path(PathBuf),
+    /// The numbered files an output file rolls over into (see
+    /// `shards::shard_path()`), as many as it takes
+    Series(PathBuf),
+}
+
+/**
+ * Find a file that two steps would both write, the second replacing the
+ * first's: any of their output files, shards, reports or directories, or a
+ * directory one of them writes into.
+ *
+ * # Arguments
+ *
+ * - `steps`: The steps' parsed arguments
+ *
+ * # Returns
+ *
+ * The file (as the later step names it), or `None` if every step writes its
+ * own.
+ */
+pub fn written_twice(steps: &[Arguments]) -> Option<PathBuf> {
+    let mut written: Vec<Output> = Vec::new();
+    for args in steps {
+        let outputs = outputs(args);
+        for output in &outputs {
+            if written.iter().any(|w| overlap(w, output)) {
+                return Some(match output {
+                    Output::Path(path) => path.clone(),
+                    Output::Series(path) => shard_path(path, 0),
+                });
+            }
+        }
+        written.extend(outputs);
+    }
+    None
+}
+
+/// Get everything a step writes.
+fn outputs(args: &Arguments) -> Vec<Output> {
+    let mut outputs = Vec::new();
+    let output = plain(&args.output_file);
+    match (&args.postgres, &args.shards, &args.rollover) {
+        (Some(_), _, _) => (),
+        _ if is_stdout(&output) || is_null(&output) => (),
+        (None, Some(shards), _) => {
+            outputs.extend(shard_paths(&output, shards.count).into_iter().map(Output::Path));
+        },
+        (None, None, Some(_)) => outputs.push(Output::Series(output)),
+        (None, None, None) => outputs.push(Output::Path(output)),
+    }
+
+    let documents_dir = args.documents_dir.as_ref().filter(|_| args.template_file.is_some());
+    let others = [
+        args.config_dump.as_ref().map(|dump| &dump.path),
+        args.summary_json_file.as_ref(),
+        args.ssn_collisions_file.as_ref(),
+        args.proto_file.as_ref(),
+        args.aggregates_dir.as_ref(),
+        documents_dir,
+        args.avatars_dir.as_ref(),
+        args.barcodes_dir.as_ref(),
+        args.shifts_file.as_ref(),
+        args.benefits_dir.as_ref(),
+        args.w2_file.as_ref(),
+        args.transactions_file.as_ref(),
+        args.immunizations_file.as_ref(),
+    ];
+    outputs.extend(others.into_iter().flatten().map(|path| Output::Path(plain(path))));
+    outputs
+}
+
+/// Get a path without any `.` in it, so `./people.csv` is `people.csv`.
+fn plain(path: &Path) -> PathBuf {
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+/// Whether two outputs would write the same file.
+fn overlap(a: &Output, b: &Output) -> bool {
+    match (a, b) {
+        (Output::Path(a), Output::Path(b)) => a.starts_with(b) || b.starts_with(a),
+        (Output::Series(series), Output::Path(path)) | (Output::Path(path), Output::Series(series)) => {
+            in_series(series, path) || shard_path(series, 0).starts_with(path)
+        },
+        (Output::Series(a), Output::Series(b)) => {
+            in_series(a, &shard_path(b, 0)) || in_series(b, &shard_path(a, 0))
+        },
+    }
+}
+
+/// Whether a file is one of the numbered files an output file rolls over
+/// into.
+fn in_series(series: &Path, path: &Path) -> bool {
+    let first = shard_path(series, 0);
+    let (Some(first_name), Some(name)) = (first.file_name().and_then(|n| n.to_str()),
+                                          path.file_name().and_then(|n| n.to_str())) else {
+        return false;
+    };
+    // The number is the only part that changes.
+    let (before, after) = first_name.rsplit_once("00000").unwrap();
+    first.parent() == path.parent()
+        && name.len() >= first_name.len()
+        && name.starts_with(before)
+        && name.ends_with(after)
+        && name[before.len()..name.len() - after.len()].bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenario::*;
+    use clap::{Arg, ArgAction};
+    use std::path::PathBuf;
+
+    fn command() -> Command {
+        Command::new("generate")
+            .arg(Arg::new("female").short('f').long("female-pct"))
+            .arg(Arg::new("ssn").long("ssn").action(ArgAction::SetTrue))
+            .arg(Arg::new("seed").long("seed"))
+            .arg(Arg::new("shifts").long("shifts"))
+            .arg(Arg::new("output"))
+            .arg(Arg::new("total"))
+    }
+
+    #[test]
+    fn scenarios() {
+        let scenario = parse_scenario(
+            "seed = 42\n\
+             female_pct = 60\n\
+             [[step]]\n\
+             name = \"employees\"\n\
+             output = \"employees.csv\"\n\
+             total = 10\n\
+             shifts = \"shifts.csv\"\n\
+             [[step]]\n\
+             female-pct = 40\n\
+             ssn = true\n\
+             output = \"patients.csv\"\n\
+             total = 20\n",
+            &command(),
+        ).unwrap();
+
+        assert_eq!(scenario.seed, Some(42));
+        assert_eq!(scenario.steps, [
+            Step {
+                label: String::from("Step \"employees\""),
+                args: ["--female-pct", "60", "--shifts", "shifts.csv", "employees.csv", "10"].map(String::from).to_vec(),
+            },
+            Step {
+                label: String::from("Step 2"),
+                args: ["--female-pct", "40", "--ssn", "patients.csv", "20"].map(String::from).to_vec(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn bad_scenarios() {
+        let bad = |s: &str| parse_scenario(s, &command()).unwrap_err();
+
+        assert!(bad("seed = 42\n").contains("No steps"));
+        assert!(bad("step = 1\n").contains("array of tables"));
+        assert!(bad("seed = -1\n[[step]]\ntotal = 1\n").contains("from 0 up"));
+        assert!(bad("[[step]]\nname = 2\n").contains("Step 1: \"name\" must be a string"));
+        assert!(bad("[[step]]\nname = \"x\"\nseed = 1\n").contains("Step \"x\": \"seed\" goes at the top"));
+        assert!(bad("[[step]]\n[[step]]\nmale-pct = 1\n").contains("Step 2: Unknown option \"male-pct\""));
+        assert!(bad("[[step]\n").contains("expected"));
+    }
+
+    #[test]
+    fn seeds() {
+        assert_eq!(step_seed(42, 0), 42);
+        assert_ne!(step_seed(42, 1), 42);
+        assert_ne!(step_seed(42, 1), step_seed(42, 2));
+    }
+
+    #[test]
+    fn files_written_twice() {
+        use crate::rollover::Rollover;
+        use crate::shards::{ShardBy, Shards};
+
+        let step = |output: &str, shifts: Option<&str>| Arguments {
+            output_file: PathBuf::from(output),
+            shifts_file: shifts.map(PathBuf::from),
+            ..Default::default()
+        };
+        let twice = |steps: &[Arguments]| written_twice(steps).map(|path| path.to_string_lossy().into_owned());
+
+        assert_eq!(twice(&[step("a.csv", Some("shifts.csv")), step("b.csv", None)]), None);
+        assert_eq!(twice(&[step("/dev/null", None), step("/dev/null", None)]), None);
+        assert_eq!(twice(&[step("-", None), step("-", None)]), None);
+        assert_eq!(
+            twice(&[step("a.csv", Some("shifts.csv")), step("b.csv", Some("./shifts.csv"))]).as_deref(),
+            Some("shifts.csv")
+        );
+        assert_eq!(twice(&[step("a.csv", None), step("a.csv", None)]).as_deref(), Some("a.csv"));
+
+        // Reports, and directories that other files are in.
+        let mut reports = step("b.csv", None);
+        reports.ssn_collisions_file = Some(PathBuf::from("a.csv"));
+        assert_eq!(twice(&[step("a.csv", None), reports]).as_deref(), Some("a.csv"));
+        let mut aggregates = step("b.csv", None);
+        aggregates.aggregates_dir = Some(PathBuf::from("out"));
+        assert_eq!(twice(&[step("out/a.csv", None), aggregates]).as_deref(), Some("out"));
+
+        // Shards and rolled-over files.
+        let sharded = || Arguments {
+            shards: Some(Shards { count: 3, by: ShardBy::Range }),
+            ..step("a.csv", None)
+        };
+        let rolled = |output: &str| Arguments {
+            rollover: Some(Rollover { max_rows: Some(10), max_bytes: None }),
+            ..step(output, None)
+        };
+        assert_eq!(twice(&[sharded(), step("a.csv", None)]), None);
+        assert_eq!(twice(&[sharded(), step("a-00002.csv", None)]).as_deref(), Some("a-00002.csv"));
+        assert_eq!(twice(&[step("a-00017.csv", None), rolled("a.csv")]).as_deref(), Some("a-00000.csv"));
+        assert_eq!(twice(&[rolled("a.csv"), sharded()]).as_deref(), Some("a-00000.csv"));
+        assert_eq!(twice(&[rolled("a.csv"), step("a-0001.csv", None), step("a-x0001.csv", None)]), None);
+        assert_eq!(twice(&[rolled("a.csv"), rolled("a.csv.gz")]), None);
+    }
+}
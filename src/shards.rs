@@ -0,0 +1,237 @@
+//! Sharded output: the people split across several numbered files, e.g.,
+//! `people-00000.csv`, `people-00001.csv`, ..., for loaders that read files
+//! in parallel. Each shard is a complete file in the output format, with its
+//! own header. The shards are either contiguous ranges of people, or dealt
+//! out round-robin, one person per shard in turn.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::output::split_compression;
+
+/// The names `--shard-by` accepts.
+pub const SHARD_BY_NAMES: [&str; 2] = ["range", "round-robin"];
+
+/// How many people can wait for a round-robin shard's writer.
+const QUEUE_SIZE: usize = 1024;
+
+/// How to split the people among the shards.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ShardBy {
+    /// The first shard gets the first people, and so on. Shard sizes differ
+    /// by at most one.
+    #[default]
+    Range,
+    /// Person 1 goes to the first shard, person 2 to the second, and so on,
+    /// around again.
+    RoundRobin,
+}
+
+impl ShardBy {
+    /**
+     * Look up a way of sharding by name.
+     *
+     * # Arguments
+     *
+     * - `name`: The name, one of `SHARD_BY_NAMES`
+     *
+     * # Returns
+     *
+     * The way of sharding, or `None` if the name isn't one.
+     */
+    pub fn from_name(name: &str) -> Option<ShardBy> {
+        match name {
+            "range" => Some(ShardBy::Range),
+            "round-robin" => Some(ShardBy::RoundRobin),
+            _ => None,
+        }
+    }
+}
+
+/// The `--shards` options.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Shards {
+    pub count: usize,
+    pub by: ShardBy,
+}
+
+/**
+ * Get the names of the shards of an output file. See `shard_path()`.
+ *
+ * # Arguments
+ *
+ * - `path`: The output file, e.g., "people.csv.gz"
+ * - `count`: How many shards there are
+ *
+ * # Returns
+ *
+ * The shards' paths, e.g., "people-00000.csv.gz", "people-00001.csv.gz".
+ */
+pub fn shard_paths(path: &Path, count: usize) -> Vec<PathBuf> {
+    (0..count).map(|i| shard_path(path, i)).collect()
+}
+
+/**
+ * Get the name of one of the numbered files an output file is split into:
+ * the file's name, with the number before the extension (and before any
+ * compression extension).
+ *
+ * # Arguments
+ *
+ * - `path`: The output file, e.g., "people.csv.gz"
+ * - `index`: The file's number, from 0
+ *
+ * # Returns
+ *
+ * The file's path, e.g., "people-00002.csv.gz".
+ */
+pub fn shard_path(path: &Path, index: usize) -> PathBuf {
+    let (format_file, compression) = split_compression(path);
+    let stem = format_file.file_stem().and_then(|s| s.to_str()).unwrap_or("people");
+    let mut extensions = String::new();
+    for extension in [format_file.extension(), compression.and(path.extension())].into_iter().flatten() {
+        extensions.push('.');
+        extensions.push_str(&extension.to_string_lossy());
+    }
+
+    path.with_file_name(format!("{stem}-{index:05}{extensions}"))
+}
+
+/**
+ * Get the sizes of contiguous shards. The first `total % count` shards get
+ * one more person than the rest.
+ */
+pub fn range_sizes(total: u64, count: usize) -> Vec<u64> {
+    let count = count as u64;
+    (0..count).map(|i| total / count + u64::from(i < total % count)).collect()
+}
+
+/**
+ * Write a stream of people to shards. The people are generated on the
+ * calling thread, in order, so a seed always gives the same shards.
+ *
+ * # Arguments
+ *
+ * - `shards`: How to shard
+ * - `paths`: Where to write each shard, from `shard_paths()`
+ * - `total`: How many people there are
+ * - `people`: The people
+ * - `write`: Writes a stream of people to a file, returning how many were
+ *   written. For round-robin shards, it's called on a thread per shard, so
+ *   that every shard is written at once.
+ *
+ * # Returns
+ *
+ * - `Ok(total)`: How many people were written, altogether
+ * - `Err(msg)`: Generating or writing failed; `msg` is the first shard's
+ *   error. The shards are incomplete.
+ */
+pub fn write_shards<T, I, W>(
+    shards: &Shards,
+    paths: &[PathBuf],
+    total: u64,
+    mut people: I,
+    write: W,
+) -> Result<u64, String>
+where
+    T: Send,
+    I: Iterator<Item = Result<T, String>>,
+    W: Fn(&Path, &mut dyn Iterator<Item = Result<T, String>>) -> Result<u64, String> + Sync,
+{
+    match shards.by {
+        ShardBy::Range => {
+            let mut written = 0;
+            for (path, size) in paths.iter().zip(range_sizes(total, shards.count)) {
+                written += write(path, &mut people.by_ref().take(size as usize))?;
+            }
+            Ok(written)
+        },
+
+        ShardBy::RoundRobin => {
+            // The writers' spans belong to the caller's.
+            let span = tracing::Span::current();
+            thread::scope(|scope| {
+                let (senders, writers): (Vec<_>, Vec<_>) = paths
+                    .iter()
+                    .map(|path| {
+                        let (tx, rx) = mpsc::sync_channel::<Result<T, String>>(QUEUE_SIZE);
+                        let (write, span) = (&write, &span);
+                        (tx, scope.spawn(move || {
+                            let _entered = span.enter();
+                            write(path, &mut rx.into_iter())
+                        }))
+                    })
+                    .unzip();
+
+                // A writer that fails stops listening, so the people stop
+                // there. An error from the generator ends its shard, and the
+                // rest.
+                for (p, tx) in people.by_ref().zip(senders.iter().cycle()) {
+                    let failed = p.is_err();
+                    if tx.send(p).is_err() || failed {
+                        break;
+                    }
+                }
+                drop(senders);
+
+                let mut written = 0;
+                for writer in writers {
+                    written += writer.join().map_err(|_| String::from("A shard writer panicked."))??;
+                }
+                Ok(written)
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shards::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Shard the numbers 1 to `total`, and get each shard's numbers.
+    fn shard(by: ShardBy, count: usize, total: u64) -> (Result<u64, String>, Vec<Vec<u64>>) {
+        let shards = Shards { count, by };
+        let paths = shard_paths(Path::new("n.txt"), count);
+        let written: Mutex<HashMap<PathBuf, Vec<u64>>> = Mutex::new(HashMap::new());
+        let people = (1..=total).map(|n| if n == 100 { Err(String::from("100!")) } else { Ok(n) });
+
+        let result = write_shards(&shards, &paths, total, people, |path, people| {
+            let numbers: Vec<u64> = people.collect::<Result<_, _>>()?;
+            let n = numbers.len() as u64;
+            written.lock().unwrap().insert(path.to_path_buf(), numbers);
+            Ok(n)
+        });
+
+        let mut written = written.into_inner().unwrap();
+        (result, paths.iter().map(|p| written.remove(p).unwrap_or_default()).collect())
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            shard_paths(Path::new("out/people.csv"), 2),
+            vec![PathBuf::from("out/people-00000.csv"), PathBuf::from("out/people-00001.csv")]
+        );
+        assert_eq!(shard_paths(Path::new("people.jsonl.zst"), 1), vec![PathBuf::from("people-00000.jsonl.zst")]);
+        assert_eq!(shard_paths(Path::new("part"), 1), vec![PathBuf::from("part-00000")]);
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range_sizes(10, 3), vec![4, 3, 3]);
+        assert_eq!(range_sizes(2, 3), vec![1, 1, 0]);
+        assert_eq!(shard(ShardBy::Range, 3, 7), (Ok(7), vec![vec![1, 2, 3], vec![4, 5], vec![6, 7]]));
+    }
+
+    #[test]
+    fn round_robin() {
+        assert_eq!(shard(ShardBy::RoundRobin, 3, 7), (Ok(7), vec![vec![1, 4, 7], vec![2, 5], vec![3, 6]]));
+
+        // One shard's error is the run's error.
+        let (result, _) = shard(ShardBy::RoundRobin, 3, 200);
+        assert_eq!(result, Err(String::from("100!")));
+    }
+}
@@ -0,0 +1,384 @@
+//! Weekly shift schedules, for testing workforce-management systems. Each
+//! person is put in a department, and each department works its own way:
+//!
+//! - `office`: Monday to Friday, 8:00-16:00 or 9:00-17:00
+//! - `retail`: five days out of seven, each an opening (7:00-15:00), middle
+//!   (11:00-19:00) or closing (14:00-22:00) shift
+//! - `warehouse`: Monday to Friday, on a crew that rotates weekly from days
+//!   (6:00-14:00) to swing (14:00-22:00) to nights (22:00-6:00)
+//! - `nursing`: three 12-hour shifts a week, days (7:00-19:00) or nights
+//!   (19:00-7:00)
+//!
+//! A person's shifts never overlap, and there are always at least
+//! `MIN_REST_HOURS` hours between them. The times are local, with no time
+//! zone, and a night shift ends the day after it starts.
+//!
+//! The schedules come from their own random number generator, derived from
+//! the run's seed, so turning them on or off doesn't change any of the
+//! other generated values.
+
+use std::fs::File;
+use std::path::Path;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use csv::{Writer, WriterBuilder};
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::path::path_str;
+use crate::weighted::WeightedValues;
+
+/// Mixed into the run's seed to seed the schedules' random number
+/// generator.
+const SHIFTS_SEED_SALT: u64 = 0x7368_6966_7473_2121;
+
+/// The fewest hours between the end of one of a person's shifts and the
+/// start of their next.
+pub const MIN_REST_HOURS: i64 = 8;
+
+/// The default `--departments`: the departments, with relative weights.
+pub const DEFAULT_DEPARTMENTS: &str = "office:40,retail:25,warehouse:20,nursing:15";
+
+/// The department names, for help and error messages.
+pub const DEPARTMENT_NAMES: [&str; 4] = ["office", "retail", "warehouse", "nursing"];
+
+/// The columns of the schedule file.
+const HEADER: [&str; 5] = ["id", "department", "start", "end", "hours"];
+
+/// A department, which decides how its people's shifts are scheduled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Department {
+    Office,
+    Retail,
+    Warehouse,
+    Nursing,
+}
+
+impl Department {
+    /**
+     * Parse a department name.
+     *
+     * # Arguments
+     *
+     * - `s`: The name, one of `DEPARTMENT_NAMES`
+     *
+     * # Returns
+     *
+     * - `Ok(department)`: The department
+     * - `Err(msg)`: The name isn't a department's.
+     */
+    pub fn parse(s: &str) -> Result<Department, String> {
+        match s {
+            "office" => Ok(Department::Office),
+            "retail" => Ok(Department::Retail),
+            "warehouse" => Ok(Department::Warehouse),
+            "nursing" => Ok(Department::Nursing),
+            _ => Err(format!(
+                "Unknown department \"{}\": expected one of {}.", s, DEPARTMENT_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// The department's name, as it is in the schedule file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Department::Office => "office",
+            Department::Retail => "retail",
+            Department::Warehouse => "warehouse",
+            Department::Nursing => "nursing",
+        }
+    }
+
+    /// The department's shifts, as (start hour, length in hours).
+    fn templates(&self) -> &'static [(u32, u32)] {
+        match self {
+            Department::Office => &[(8, 8), (9, 8)],
+            Department::Retail => &[(7, 8), (11, 8), (14, 8)],
+            Department::Warehouse => &[(6, 8), (14, 8), (22, 8)],
+            Department::Nursing => &[(7, 12), (19, 12)],
+        }
+    }
+}
+
+/// The settings for shift schedules, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftOptions {
+    /// The first day of the schedules, a Monday
+    pub week_start: NaiveDate,
+    /// How many weeks the schedules cover
+    pub weeks: u32,
+    /// The departments' names, with their relative weights
+    pub departments: WeightedValues,
+}
+
+/// One shift.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shift {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Shift {
+    /// How long the shift is, in hours.
+    pub fn hours(&self) -> i64 {
+        (self.end - self.start).num_hours()
+    }
+}
+
+/// Generates people's shift schedules.
+pub struct ShiftGenerator {
+    rng: ChaCha8Rng,
+    week_start: NaiveDate,
+    weeks: u32,
+    departments: WeightedValues,
+    /// The department each of `departments` names
+    kinds: Vec<Department>,
+}
+
+impl ShiftGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The schedule settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &ShiftOptions, seed: u64) -> Result<ShiftGenerator, String> {
+        if options.week_start.weekday() != Weekday::Mon {
+            return Err(format!(
+                "Schedules start on a Monday, and {} is a {}.",
+                options.week_start, options.week_start.weekday()
+            ));
+        }
+
+        if options.weeks == 0 {
+            return Err(String::from("Schedules must cover at least one week."));
+        }
+
+        let kinds = options.departments
+            .values()
+            .iter()
+            .map(|name| Department::parse(name))
+            .collect::<Result<Vec<Department>, String>>()?;
+
+        Ok(ShiftGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ SHIFTS_SEED_SALT),
+            week_start: options.week_start,
+            weeks: options.weeks,
+            departments: options.departments.clone(),
+            kinds,
+        })
+    }
+
+    /**
+     * Generate the next person's schedule.
+     *
+     * # Returns
+     *
+     * The person's department, and their shifts, in order.
+     */
+    pub fn next_schedule(&mut self) -> (Department, Vec<Shift>) {
+        let department = self.kinds[self.departments.choose_index(&mut self.rng)];
+        let templates = department.templates();
+        // Office workers and nurses keep the same hours, and warehouse crews
+        // start their rotation somewhere.
+        let usual = self.rng.gen_range(0..templates.len());
+        let mut shifts: Vec<Shift> = Vec::new();
+
+        for week in 0..self.weeks {
+            let days: Vec<usize> = match department {
+                Department::Office | Department::Warehouse => (0..5).collect(),
+                Department::Retail => self.days_of_week(5),
+                Department::Nursing => self.days_of_week(3),
+            };
+            for day in days {
+                let date = self.week_start + Duration::days(i64::from(week) * 7 + day as i64);
+                let choices: Vec<(u32, u32)> = match department {
+                    Department::Retail => templates.to_vec(),
+                    Department::Warehouse => vec![templates[(usual + week as usize) % templates.len()]],
+                    Department::Office | Department::Nursing => vec![templates[usual]],
+                };
+                let rested = shifts.last().map(|s| s.end + Duration::hours(MIN_REST_HOURS));
+                let choices: Vec<Shift> = choices
+                    .iter()
+                    .map(|(hour, hours)| {
+                        let start = date.and_hms(*hour, 0, 0);
+                        Shift { start, end: start + Duration::hours(i64::from(*hours)) }
+                    })
+                    .filter(|s| rested.is_none_or(|r| s.start >= r))
+                    .collect();
+                // There's always one for the built-in departments, but a day
+                // without one is skipped.
+                if !choices.is_empty() {
+                    let i = self.rng.gen_range(0..choices.len());
+                    shifts.push(choices[i]);
+                }
+            }
+        }
+
+        (department, shifts)
+    }
+
+    /// Pick `n` days of the week at random, as offsets from Monday, in order.
+    fn days_of_week(&mut self, n: usize) -> Vec<usize> {
+        let mut days = sample(&mut self.rng, 7, n).into_vec();
+        days.sort_unstable();
+        days
+    }
+}
+
+/// Writes people's shifts to a CSV file, one row per shift.
+pub struct ShiftWriter {
+    generator: ShiftGenerator,
+    writer: Writer<File>,
+    path: String,
+    /// How many shifts have been written
+    pub total: u64,
+}
+
+impl ShiftWriter {
+    /**
+     * Start a schedule file.
+     *
+     * # Arguments
+     *
+     * - `options`: The schedule settings
+     * - `path`: Where to write the schedules
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, with the header written
+     * - `Err(msg)`: The settings are invalid, or the file can't be written.
+     */
+    pub fn new(options: &ShiftOptions, path: &Path, seed: u64) -> Result<ShiftWriter, String> {
+        let generator = ShiftGenerator::new(options, seed)?;
+        let path = path_str(path).to_string();
+        let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path, e);
+        let mut writer = WriterBuilder::new().from_path(&path).map_err(write_err)?;
+        writer.write_record(HEADER).map_err(write_err)?;
+
+        Ok(ShiftWriter { generator, writer, path, total: 0 })
+    }
+
+    /**
+     * Generate and write a person's shifts.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     */
+    pub fn write(&mut self, id: u64) -> Result<(), String> {
+        let (department, shifts) = self.generator.next_schedule();
+
+        for shift in &shifts {
+            self.writer
+                .write_record([
+                    id.to_string(),
+                    department.name().to_string(),
+                    shift.start.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                    shift.end.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                    shift.hours().to_string(),
+                ])
+                .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        }
+
+        self.total += shifts.len() as u64;
+        Ok(())
+    }
+
+    /// Finish the file, and return how many shifts are in it.
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        Ok(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shifts::*;
+    use chrono::Timelike;
+
+    fn options(weeks: u32, departments: &str) -> ShiftOptions {
+        ShiftOptions {
+            week_start: NaiveDate::from_ymd(2024, 3, 4),
+            weeks,
+            departments: WeightedValues::parse_list(departments, "department").unwrap(),
+        }
+    }
+
+    fn all_departments() -> String {
+        DEPARTMENT_NAMES.join(",")
+    }
+
+    #[test]
+    fn no_overlaps() {
+        let opts = options(4, &all_departments());
+        let mut g = ShiftGenerator::new(&opts, 42).unwrap();
+        let first = opts.week_start.and_hms(0, 0, 0);
+        let last = first + Duration::weeks(4);
+
+        for _ in 0..2000 {
+            let (department, shifts) = g.next_schedule();
+            let per_week = if department == Department::Nursing { 3 } else { 5 };
+            assert_eq!(shifts.len(), per_week * 4, "{department:?}");
+
+            for s in &shifts {
+                assert!(s.start >= first && s.start < last, "{s:?}");
+                let hours = if department == Department::Nursing { 12 } else { 8 };
+                assert_eq!(s.hours(), hours);
+            }
+            for pair in shifts.windows(2) {
+                assert!(pair[1].start - pair[0].end >= Duration::hours(MIN_REST_HOURS), "{pair:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn departments() {
+        let mut g = ShiftGenerator::new(&options(3, "warehouse"), 7).unwrap();
+        let (department, shifts) = g.next_schedule();
+        assert_eq!(department, Department::Warehouse);
+        // The crew rotates every week.
+        let hours: Vec<u32> = shifts.iter().step_by(5).map(|s| s.start.hour()).collect();
+        assert!(hours.windows(2).all(|w| w[0] != w[1]), "{hours:?}");
+        assert!(shifts.iter().all(|s| s.start.weekday().number_from_monday() <= 5));
+
+        let mut g = ShiftGenerator::new(&options(1, "office"), 7).unwrap();
+        let (_, shifts) = g.next_schedule();
+        assert!(shifts.iter().all(|s| s.start.hour() == shifts[0].start.hour()));
+        assert_eq!(shifts[4].start.date(), NaiveDate::from_ymd(2024, 3, 8));
+
+        assert_eq!(Department::parse("nursing"), Ok(Department::Nursing));
+        assert!(Department::parse("sales").is_err());
+    }
+
+    #[test]
+    fn seeds() {
+        let opts = options(2, &all_departments());
+        let schedules = |seed| {
+            let mut g = ShiftGenerator::new(&opts, seed).unwrap();
+            (0..20).map(|_| g.next_schedule()).collect::<Vec<_>>()
+        };
+        assert_eq!(schedules(42), schedules(42));
+        assert_ne!(schedules(42), schedules(43));
+    }
+
+    #[test]
+    fn bad_options() {
+        let mut opts = options(1, &all_departments());
+        opts.week_start = NaiveDate::from_ymd(2024, 3, 5);
+        assert!(ShiftGenerator::new(&opts, 42).is_err());
+        assert!(ShiftGenerator::new(&options(0, &all_departments()), 42).is_err());
+        assert!(ShiftGenerator::new(&options(1, "office,sales"), 42).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! SQL script output: a `CREATE TABLE` statement, followed by batched
+//! `INSERT` statements, all in one transaction, so the script can be loaded
+//! with `psql -f`, `mysql < people.sql` or `sqlite3 db < people.sql`. The
+//! `CREATE TABLE` statement is also available on its own, from `peoplegen
+//! schema ddl`, for loading other output formats into a pre-created table.
+//!
+//! The dialect controls identifier quoting, string escaping and column types.
+
+use std::io::{self, Write};
+
+/// How many rows go into each `INSERT` statement.
+const ROWS_PER_INSERT: usize = 500;
+
+/// The supported SQL dialects.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum SqlDialect {
+    #[default]
+    Postgres,
+    MySql,
+    Sqlite,
+    Snowflake,
+}
+
+/// The names of the SQL dialects, for the command line.
+pub const SQL_DIALECT_NAMES: [&str; 4] = ["postgres", "mysql", "sqlite", "snowflake"];
+
+/// The type of a column.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColumnType {
+    Integer,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    /// The type's name, for reports: "integer", "date" or "text".
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnType::Integer => "integer",
+            ColumnType::Date => "date",
+            ColumnType::Text => "text",
+        }
+    }
+}
+
+impl SqlDialect {
+    /**
+     * Parse a dialect name.
+     *
+     * # Arguments
+     *
+     * - `s`: The name, one of `SQL_DIALECT_NAMES`
+     *
+     * # Returns
+     *
+     * - `Ok(dialect)`: The dialect
+     * - `Err(msg)`: Not a valid dialect name; `msg` explains why.
+     */
+    pub fn parse(s: &str) -> Result<SqlDialect, String> {
+        match s {
+            "postgres" => Ok(SqlDialect::Postgres),
+            "mysql" => Ok(SqlDialect::MySql),
+            "sqlite" => Ok(SqlDialect::Sqlite),
+            "snowflake" => Ok(SqlDialect::Snowflake),
+            _ => Err(format!(
+                "Unknown SQL dialect \"{}\". Valid dialects: {}",
+                s, SQL_DIALECT_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Quote an identifier (a table or column name).
+    pub fn quote_ident(&self, name: &str) -> String {
+        match self {
+            SqlDialect::MySql => format!("`{}`", name.replace('`', "``")),
+            SqlDialect::Postgres | SqlDialect::Sqlite | SqlDialect::Snowflake => {
+                format!("\"{}\"", name.replace('"', "\"\""))
+            },
+        }
+    }
+
+    /// Quote a (possibly schema-qualified) table name.
+    pub fn quote_table(&self, name: &str) -> String {
+        name.split('.').map(|n| self.quote_ident(n)).collect::<Vec<_>>().join(".")
+    }
+
+    /// Quote a string literal.
+    pub fn quote_str(&self, s: &str) -> String {
+        let s = s.replace('\'', "''");
+        match self {
+            // MySQL (by default) and Snowflake treat backslashes in strings
+            // as escapes.
+            SqlDialect::MySql | SqlDialect::Snowflake => {
+                format!("'{}'", s.replace('\\', "\\\\"))
+            },
+            SqlDialect::Postgres | SqlDialect::Sqlite => format!("'{s}'"),
+        }
+    }
+
+    /// The name of a column type.
+    pub fn type_name(&self, t: ColumnType) -> &'static str {
+        match (self, t) {
+            (SqlDialect::Postgres, ColumnType::Integer) => "BIGINT",
+            (SqlDialect::MySql, ColumnType::Integer) => "BIGINT",
+            (SqlDialect::Sqlite, ColumnType::Integer) => "INTEGER",
+            (SqlDialect::Snowflake, ColumnType::Integer) => "NUMBER(38, 0)",
+            // SQLite has no date type; ISO 8601 text is the convention.
+            (SqlDialect::Sqlite, ColumnType::Date) => "TEXT",
+            (_, ColumnType::Date) => "DATE",
+            (SqlDialect::MySql, ColumnType::Text) => "VARCHAR(255)",
+            (SqlDialect::Snowflake, ColumnType::Text) => "VARCHAR",
+            (_, ColumnType::Text) => "TEXT",
+        }
+    }
+}
+
+/**
+ * Build a `CREATE TABLE` statement.
+ *
+ * # Arguments
+ *
+ * - `dialect`: The SQL dialect
+ * - `table`: The table name, optionally with a schema (`schema.table`)
+ * - `columns`: The column names and types, in order
+ *
+ * # Returns
+ *
+ * The statement, with a trailing semicolon, but no newline.
+ */
+pub fn create_table(dialect: SqlDialect, table: &str, columns: &[(&String, ColumnType)]) -> String {
+    let defs: Vec<String> = columns
+        .iter()
+        .map(|(name, t)| format!("  {} {}", dialect.quote_ident(name), dialect.type_name(*t)))
+        .collect();
+    format!("CREATE TABLE {} (\n{}\n);", dialect.quote_table(table), defs.join(",\n"))
+}
+
+/**
+ * Writes rows to a SQL script, as batched `INSERT` statements.
+ */
+pub struct SqlWriter<W: Write> {
+    w: W,
+    dialect: SqlDialect,
+    insert: String,
+    types: Vec<ColumnType>,
+    rows: usize,
+}
+
+impl<W: Write> SqlWriter<W> {
+    /**
+     * Create a new writer, writing the start of the transaction and the
+     * `CREATE TABLE` statement.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the script
+     * - `dialect`: The SQL dialect
+     * - `table`: The table name
+     * - `columns`: The column names and types, in order
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The header was written.
+     * - `Err(e)`: The write failed.
+     */
+    pub fn new(mut w: W,
+               dialect: SqlDialect,
+               table: &str,
+               columns: &[(&String, ColumnType)]) -> io::Result<SqlWriter<W>> {
+        let names: Vec<String> = columns.iter().map(|(name, _)| dialect.quote_ident(name)).collect();
+
+        writeln!(w, "BEGIN;")?;
+        writeln!(w, "{}", create_table(dialect, table, columns))?;
+
+        Ok(SqlWriter {
+            w,
+            dialect,
+            insert: format!("INSERT INTO {} ({}) VALUES",
+                            dialect.quote_ident(table), names.join(", ")),
+            types: columns.iter().map(|(_, t)| *t).collect(),
+            rows: 0,
+        })
+    }
+
+    /**
+     * Write a row. The values must be in column order. Values in integer
+     * columns are written as they are, so they have to be valid integers.
+     */
+    pub fn write_row(&mut self, values: &[String]) -> io::Result<()> {
+        if self.rows.is_multiple_of(ROWS_PER_INSERT) {
+            if self.rows > 0 {
+                writeln!(self.w, ";")?;
+            }
+            writeln!(self.w, "{}", self.insert)?;
+        }
+        else {
+            writeln!(self.w, ",")?;
+        }
+
+        let literals: Vec<String> = values
+            .iter()
+            .zip(&self.types)
+            .map(|(v, t)| match t {
+                ColumnType::Integer => v.to_string(),
+                ColumnType::Date | ColumnType::Text => self.dialect.quote_str(v),
+            })
+            .collect();
+        write!(self.w, "  ({})", literals.join(", "))?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /**
+     * Finish the last `INSERT` statement, commit, and flush the underlying
+     * writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The write failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows > 0 {
+            writeln!(self.w, ";")?;
+        }
+        writeln!(self.w, "COMMIT;")?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sql::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(SqlDialect::Postgres.quote_ident("First Name"), "\"First Name\"");
+        assert_eq!(SqlDialect::MySql.quote_ident("id"), "`id`");
+        assert_eq!(SqlDialect::Postgres.quote_str("O'Neill\\"), "'O''Neill\\'");
+        assert_eq!(SqlDialect::MySql.quote_str("O'Neill\\"), "'O''Neill\\\\'");
+        assert_eq!(SqlDialect::Snowflake.quote_str("a\\b"), "'a\\\\b'");
+    }
+
+    #[test]
+    fn ddl() {
+        let (id, born) = (String::from("id"), String::from("Birth Date"));
+        let columns = [(&id, ColumnType::Integer), (&born, ColumnType::Date)];
+
+        assert_eq!(
+            create_table(SqlDialect::Snowflake, "people", &columns),
+            "CREATE TABLE \"people\" (\n  \"id\" NUMBER(38, 0),\n  \"Birth Date\" DATE\n);"
+        );
+        assert_eq!(
+            create_table(SqlDialect::MySql, "hr.people", &columns),
+            "CREATE TABLE `hr`.`people` (\n  `id` BIGINT,\n  `Birth Date` DATE\n);"
+        );
+    }
+
+    #[test]
+    fn script() {
+        let (id, name) = (String::from("id"), String::from("name"));
+        let columns = [(&id, ColumnType::Integer), (&name, ColumnType::Text)];
+        let mut w = SqlWriter::new(Vec::new(), SqlDialect::Sqlite, "people", &columns).unwrap();
+        w.write_row(&[String::from("1"), String::from("Moe")]).unwrap();
+        w.write_row(&[String::from("2"), String::from("O'Neill")]).unwrap();
+        let script = String::from_utf8(w.finish().unwrap()).unwrap();
+
+        assert_eq!(
+            script,
+            "BEGIN;\n\
+             CREATE TABLE \"people\" (\n  \"id\" INTEGER,\n  \"name\" TEXT\n);\n\
+             INSERT INTO \"people\" (\"id\", \"name\") VALUES\n  (1, 'Moe'),\n  (2, 'O''Neill');\n\
+             COMMIT;\n"
+        );
+    }
+}
@@ -1,14 +1,106 @@
+//! Fake U.S. Social Security numbers.
+//!
+//! `SsnGenerator` is an iterator that hands out Social Security numbers in
+//! sequence. By default, it only uses the prefixes (the first group of three
+//! digits) 900-999 and 666, which the Social Security Administration never
+//! assigns, so the generated numbers are guaranteed to be fake, per the
+//! approach outlined at <https://stackoverflow.com/a/2313726/53495>
+//!
+//! Use `SsnGenerator::builder()` for more control:
+//!
+//! ```
+//! use peoplegen::ssn::{SsnFormat, SsnGenerator};
+//!
+//! let mut ssns = SsnGenerator::builder()
+//!     .prefixes([987])
+//!     .group_range(10..=19)
+//!     .format(SsnFormat::Digits)
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(ssns.next(), Some(String::from("987100001")));
+//! assert_eq!(ssns.next(), Some(String::from("987100002")));
+//! assert_eq!(ssns.total(), 10 * 9999);
+//! ```
+
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
+
+/// How Social Security numbers are rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum SsnFormat {
+    /// The usual form, e.g., "900-01-6789"
+    #[default]
+    Dashed,
+    /// Just the nine digits, e.g., "900016789"
+    Digits,
+    /// Dashed, with all but the last four digits masked, e.g., "***-**-6789"
+    Masked,
+}
+
+/// The names of the SSN formats, for the command line.
+pub const SSN_FORMAT_NAMES: [&str; 3] = ["dashed", "digits", "masked"];
+
+impl SsnFormat {
+    /**
+     * Parse an SSN format name.
+     *
+     * # Arguments
+     *
+     * - `s`: The name, one of `SSN_FORMAT_NAMES`
+     *
+     * # Returns
+     *
+     * - `Ok(format)`: The format
+     * - `Err(msg)`: Not a valid format name; `msg` explains why.
+     */
+    pub fn parse(s: &str) -> Result<SsnFormat, String> {
+        match s {
+            "dashed" => Ok(SsnFormat::Dashed),
+            "digits" => Ok(SsnFormat::Digits),
+            "masked" => Ok(SsnFormat::Masked),
+            _ => Err(format!(
+                "Unknown SSN format \"{}\". Valid formats: {}",
+                s, SSN_FORMAT_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /**
+     * Render a dashed Social Security number (e.g., "900-01-6789") in this
+     * format.
+     */
+    pub fn apply(&self, ssn: &str) -> String {
+        match self {
+            SsnFormat::Dashed => ssn.to_string(),
+            SsnFormat::Digits => ssn.chars().filter(|c| *c != '-').collect(),
+            SsnFormat::Masked => {
+                let last4: String = ssn.chars().skip(ssn.len().saturating_sub(4)).collect();
+                format!("***-**-{last4}")
+            },
+        }
+    }
+}
+
+/**
+ * The prefixes that are never assigned to real people: 900-999 and 666.
+ */
+pub fn fake_prefixes() -> Vec<u32> {
+    (900..=999).chain([666]).collect()
+}
 
 /**
- * This struct can be used to generate Social Security numbers, as an iterator.
- * If you use `SsnGenerator::new()` to create the object, it will prepopulate
- * the fields with appropriate values to generate guaranteed-fake SSNs. These
- * defaults will generate a maximum of 99,980,001 fake Social Security numbers,
- * before the iterator runs out. If you then reset the iterator, it will start
- * again. You can also use the `SsnGenerator::new_auto_reset()`
- * constructor to have the generator auto-reset (and, therefore, cycle)
- * infinitely.
+ * An iterator over Social Security numbers. Numbers are generated in order:
+ * by prefix, then by group number (the middle two digits), then by serial
+ * number (the last four digits). With the default settings, that's
+ * "900-01-0001", "900-01-0002", ..., "900-01-9999", "900-02-0001", ...,
+ * "666-99-9999", for a maximum of 99,980,001 fake Social Security numbers.
+ *
+ * When the numbers run out, the iterator either ends or, if it was built to
+ * auto-reset, starts over. Either way, `reset()` starts it over manually.
  */
 pub struct SsnGenerator {
     prefixes: Vec<u32>,
@@ -19,15 +111,146 @@ pub struct SsnGenerator {
     last_min: u32,
     last_cur: u32,
     last_max: u32,
+    format: SsnFormat,
     auto_reset: bool,
 }
 
+/**
+ * Builds an `SsnGenerator`. Start with `SsnGenerator::builder()`, which
+ * uses the same settings as `SsnGenerator::new()`, then override whatever
+ * you need.
+ */
+#[derive(Debug, Clone)]
+pub struct SsnGeneratorBuilder {
+    prefixes: Vec<u32>,
+    group_range: RangeInclusive<u32>,
+    serial_range: RangeInclusive<u32>,
+    format: SsnFormat,
+    auto_reset: bool,
+    shuffle_seed: Option<u64>,
+    max_prefixes: Option<usize>,
+}
+
+impl SsnGeneratorBuilder {
+    /**
+     * Set the prefixes to use, in order. The default is `fake_prefixes()`.
+     * Note that using prefixes outside the fake ranges can generate real
+     * Social Security numbers.
+     */
+    pub fn prefixes<I: IntoIterator<Item = u32>>(mut self, prefixes: I) -> Self {
+        self.prefixes = prefixes.into_iter().collect();
+        self
+    }
+
+    /**
+     * Set the range of group numbers (the middle two digits). Must be
+     * within 1 to 99, which is the default.
+     */
+    pub fn group_range(mut self, range: RangeInclusive<u32>) -> Self {
+        self.group_range = range;
+        self
+    }
+
+    /**
+     * Set the range of serial numbers (the last four digits). Must be
+     * within 1 to 9999, which is the default.
+     */
+    pub fn serial_range(mut self, range: RangeInclusive<u32>) -> Self {
+        self.serial_range = range;
+        self
+    }
+
+    /**
+     * Set how the generated numbers are rendered. The default is
+     * `SsnFormat::Dashed`.
+     */
+    pub fn format(mut self, format: SsnFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /**
+     * Set whether the generator starts over (`true`) or ends (`false`, the
+     * default) when it runs out of numbers.
+     */
+    pub fn auto_reset(mut self, auto_reset: bool) -> Self {
+        self.auto_reset = auto_reset;
+        self
+    }
+
+    /**
+     * Use the prefixes in a random order, derived from `seed`, instead of in
+     * the order given. Independently generated data sets are then unlikely
+     * to share numbers.
+     */
+    pub fn shuffle_prefixes(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /**
+     * Use at most `max` of the prefixes (after shuffling, if
+     * `shuffle_prefixes()` was called).
+     */
+    pub fn max_prefixes(mut self, max: usize) -> Self {
+        self.max_prefixes = Some(max);
+        self
+    }
+
+    /**
+     * Build the generator.
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn build(self) -> Result<SsnGenerator, String> {
+        let (mid_min, mid_max) = self.group_range.into_inner();
+        let (last_min, last_max) = self.serial_range.into_inner();
+        let mut prefixes = self.prefixes;
+
+        if let Some(seed) = self.shuffle_seed {
+            prefixes.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        }
+
+        if let Some(max) = self.max_prefixes {
+            prefixes.truncate(max);
+        }
+
+        if prefixes.is_empty() {
+            Err(String::from("At least one SSN prefix is required."))
+        }
+        else if let Some(p) = prefixes.iter().find(|p| **p > 999) {
+            Err(format!("SSN prefix {p} has more than three digits."))
+        }
+        else if mid_min < 1 || mid_max > 99 || mid_min > mid_max {
+            Err(format!("Bad SSN group number range {mid_min}-{mid_max}: must be within 1-99."))
+        }
+        else if last_min < 1 || last_max > 9999 || last_min > last_max {
+            Err(format!("Bad SSN serial number range {last_min}-{last_max}: must be within 1-9999."))
+        }
+        else {
+            Ok(SsnGenerator {
+                prefixes,
+                prefix_index: 0,
+                mid_min,
+                mid_cur: 0,
+                mid_max,
+                last_min,
+                last_cur: 0,
+                last_max,
+                format: self.format,
+                auto_reset: self.auto_reset,
+            })
+        }
+    }
+}
+
 impl SsnGenerator {
     /**
-     * Create a new `SsnGenerator` with default values, including a set of
-     * Social Security prefixes (the first group of three numbers) that are
-     * guaranteed to be fake, per the approach outlined at
-     * <https://stackoverflow.com/a/2313726/53495>
+     * Create a new `SsnGenerator` with the default settings: fake prefixes,
+     * in order, dashed numbers.
      *
      * This generator will _not_ auto-reset when it gets to the end of the
      * sequence. Use `SsnGenerator::new_auto_reset()` to create a generator
@@ -35,65 +258,42 @@ impl SsnGenerator {
      * reset the generator yourself by calling the `reset()` function.
      */
     pub fn new() -> Self {
-        let mut prefixes: Vec<u32> = (900..=999).collect();
-        prefixes.push(666);
-        Self {
-            prefixes,
-            prefix_index: 0,
-            mid_min: 1,
-            mid_cur: 0,
-            mid_max: 99,
-            last_min: 1,
-            last_cur: 0,
-            last_max: 9999,
-            auto_reset: false,
-        }
+        // The default settings are always valid.
+        SsnGenerator::builder().build().unwrap()
     }
 
     /**
-     * Create a new `SsnGenerator` with default values, including a set of
-     * Social Security prefixes (the first group of three numbers) that are
-     * guaranteed to be fake, per the approach outlined at
-     * <https://stackoverflow.com/a/2313726/53495>
-     *
-     * This generator will auto-reset when it gets to the end of the
-     * sequence. Use `SsnGenerator::new())` to create a generator will not
-     * cycle back to the beginning automatically.
+     * Create a new `SsnGenerator` with the default settings, except that it
+     * will auto-reset when it gets to the end of the sequence. Use
+     * `SsnGenerator::new()` to create a generator that will not cycle back
+     * to the beginning automatically.
      */
     pub fn new_auto_reset() -> Self {
-        let mut s = SsnGenerator::new();
-        s.auto_reset = true;
-        s
+        SsnGenerator::builder().auto_reset(true).build().unwrap()
     }
 
     /**
-     * Create a new `SsnGenerator`, with complete control over the fields.
-     * This constructor is intended primarily for testing.
+     * Get a builder, initialized with the default settings.
      */
-    pub fn new_for_tests(prefixes: Vec<u32>,
-                         mid_min: u32,
-                         mid_max: u32,
-                         last_min: u32,
-                         last_max: u32,
-                         auto_reset: bool) -> Self {
-        assert!(mid_min > 0);
-        assert!(mid_max <= 99);
-        assert!(last_min > 0);
-        assert!(last_max <= 9999);
-
-        Self {
-            prefixes,
-            prefix_index: 0,
-            mid_min,
-            mid_cur: 0,
-            mid_max,
-            last_min,
-            last_cur: 0,
-            last_max,
-            auto_reset
+    pub fn builder() -> SsnGeneratorBuilder {
+        SsnGeneratorBuilder {
+            prefixes: fake_prefixes(),
+            group_range: 1..=99,
+            serial_range: 1..=9999,
+            format: SsnFormat::Dashed,
+            auto_reset: false,
+            shuffle_seed: None,
+            max_prefixes: None,
         }
     }
 
+    /**
+     * The number of prefixes the generator uses.
+     */
+    pub fn prefix_count(&self) -> usize {
+        self.prefixes.len()
+    }
+
     /**
      * Reset the generator to its initial values.
      */
@@ -103,6 +303,39 @@ impl SsnGenerator {
         self.prefix_index = 0;
     }
 
+    /**
+     * Skip the next `n` numbers, as if `next()` had been called `n` times,
+     * without generating them.
+     */
+    pub fn advance(&mut self, n: u64) {
+        let lasts = (self.last_max - self.last_min + 1) as u64;
+        let mids = (self.mid_max - self.mid_min + 1) as u64;
+        // How many numbers have been handed out since the start.
+        let done = match self.mid_cur {
+            0 => 0,
+            _ => self.prefix_index as u64 * mids * lasts
+                + (self.mid_cur - self.mid_min) as u64 * lasts
+                + (self.last_cur - self.last_min) as u64
+                + 1,
+        };
+        let done = match done + n {
+            d if self.auto_reset => d % self.total(),
+            d => d.min(self.total()),
+        };
+
+        self.reset();
+        if done > 0 {
+            let last = done - 1;
+            self.prefix_index = (last / (mids * lasts)) as usize;
+            self.mid_cur = self.mid_min + (last / lasts % mids) as u32;
+            self.last_cur = self.last_min + (last % lasts) as u32;
+        }
+    }
+
+    /**
+     * The number of distinct Social Security numbers the generator can
+     * produce before it runs out (or starts over).
+     */
     pub fn total(&self) -> u64 {
         let total_firsts: u64 = self.prefixes.len() as u64;
         let total_lasts: u64 = (self.last_max - self.last_min + 1) as u64;
@@ -112,6 +345,12 @@ impl SsnGenerator {
     }
 }
 
+impl Default for SsnGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Iterator for SsnGenerator {
     type Item = String;
 
@@ -134,8 +373,8 @@ impl Iterator for SsnGenerator {
         }
 
         if (self.prefix_index == 0) && (self.mid_cur == 0) && (self.last_cur == 0) {
-            self.mid_cur += 1;
-            self.last_cur += 1;
+            self.mid_cur = self.mid_min;
+            self.last_cur = self.last_min;
         }
         else if (self.mid_cur == self.mid_max) &&
                 (self.last_cur == self.last_max) {
@@ -153,13 +392,58 @@ impl Iterator for SsnGenerator {
 
         let first = self.prefixes[self.prefix_index];
         let ssn = format!("{:03}-{:02}-{:04}", first, self.mid_cur, self.last_cur);
-        Some(ssn)
+        Some(self.format.apply(&ssn))
     }
 }
 
+/**
+ * Determine which Social Security numbers an auto-resetting generator hands
+ * out more than once, and to whom. Since the generator cycles through the
+ * same sequence, the people at positions `n`, `n + total()`,
+ * `n + 2 * total()`, ... all share an SSN, so this doesn't require keeping
+ * track of the SSNs as they're generated.
+ *
+ * # Arguments
+ *
+ * - `ssns`: A new generator, configured the same way as the one used to
+ *   generate the people
+ * - `offset`: How many people the generator handed out SSNs to before these
+ *   (in a scenario, the earlier steps' people)
+ * - `total`: The number of people generated
+ *
+ * # Returns
+ *
+ * An iterator over the SSNs these people share, with anyone, each paired with
+ * the positions (starting at 1, with the earlier people's first) of all of
+ * the people who share it. It's empty if there are no shared SSNs.
+ */
+pub fn collisions(ssns: SsnGenerator, offset: u64, total: u64) -> impl Iterator<Item = (String, Vec<u64>)> {
+    let space = ssns.total();
+    let end = offset + total;
+    let shared = if end > space { (end - space).min(space) } else { 0 };
+
+    ssns.take(shared as usize).enumerate().filter_map(move |(i, ssn)| {
+        let positions: Vec<u64> = (i as u64 + 1..=end).step_by(space as usize).collect();
+        positions.iter().any(|p| *p > offset).then_some((ssn, positions))
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::ssn::SsnGenerator;
+    use crate::ssn::{collisions, fake_prefixes, SsnFormat, SsnGenerator};
+    use proptest::prelude::*;
+    use proptest::sample::subsequence;
+    use std::collections::HashSet;
+
+    fn small(prefixes: Vec<u32>, auto_reset: bool) -> SsnGenerator {
+        SsnGenerator::builder()
+            .prefixes(prefixes)
+            .group_range(1..=2)
+            .serial_range(1..=2)
+            .auto_reset(auto_reset)
+            .build()
+            .unwrap()
+    }
 
     #[test]
     fn gen_several() {
@@ -176,9 +460,7 @@ mod tests {
     #[test]
     fn gen_two_prefixes() {
         let prefixes: Vec<u32> = (900..=901).collect();
-        let mut ssns = SsnGenerator::new_for_tests(
-            prefixes, 1, 2, 1, 2, false
-        );
+        let mut ssns = small(prefixes, false);
 
         assert_eq!(ssns.total(), 8);
 
@@ -209,11 +491,8 @@ mod tests {
 
     #[test]
     fn test_reset() {
-        let mut prefixes: Vec<u32> = Vec::new();
-        prefixes.push(900);
-        let mut ssns = SsnGenerator::new_for_tests(
-            prefixes, 1, 2, 1, 2, false
-        );
+        let prefixes: Vec<u32> = vec![900];
+        let mut ssns = small(prefixes, false);
         let ssn1 = ssns.next();
         let ssn2 = ssns.next();
         let ssn3 = ssns.next();
@@ -234,11 +513,8 @@ mod tests {
 
     #[test]
     fn test_auto_reset() {
-        let mut prefixes: Vec<u32> = Vec::new();
-        prefixes.push(900);
-        let mut ssns = SsnGenerator::new_for_tests(
-            prefixes, 1, 2, 1, 2, true
-        );
+        let prefixes: Vec<u32> = vec![900];
+        let mut ssns = small(prefixes, true);
         let ssn1 = ssns.next();
         let ssn2 = ssns.next();
         let ssn3 = ssns.next();
@@ -252,4 +528,141 @@ mod tests {
         let ssn5 = ssns.next();
         assert_eq!(ssn5, Some(String::from("900-01-0001")));
     }
+
+    #[test]
+    fn test_collisions() {
+        let ssns = || small(vec![900], true);
+
+        assert_eq!(collisions(ssns(), 0, 4).count(), 0);
+        assert_eq!(
+            collisions(ssns(), 0, 10).collect::<Vec<_>>(),
+            vec![
+                (String::from("900-01-0001"), vec![1, 5, 9]),
+                (String::from("900-01-0002"), vec![2, 6, 10]),
+                (String::from("900-02-0001"), vec![3, 7]),
+                (String::from("900-02-0002"), vec![4, 8]),
+            ]
+        );
+        assert_eq!(
+            collisions(ssns(), 0, 5).collect::<Vec<_>>(),
+            vec![(String::from("900-01-0001"), vec![1, 5])]
+        );
+
+        // After 3 others, 2 people share with the others, but not with
+        // each other.
+        assert_eq!(
+            collisions(ssns(), 3, 2).collect::<Vec<_>>(),
+            vec![(String::from("900-01-0001"), vec![1, 5])]
+        );
+        assert_eq!(collisions(ssns(), 2, 2).count(), 0);
+        assert_eq!(
+            collisions(ssns(), 5, 2).collect::<Vec<_>>(),
+            vec![
+                (String::from("900-01-0002"), vec![2, 6]),
+                (String::from("900-02-0001"), vec![3, 7]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_advance() {
+        for auto_reset in [true, false] {
+            for start in 0..6 {
+                for n in 0..12 {
+                    let mut skipped = small(vec![900, 901], auto_reset);
+                    let mut stepped = small(vec![900, 901], auto_reset);
+                    skipped.by_ref().take(start).for_each(drop);
+                    stepped.by_ref().take(start).for_each(drop);
+                    skipped.advance(n);
+                    stepped.by_ref().take(n as usize).for_each(drop);
+                    assert_eq!(skipped.next(), stepped.next(), "{auto_reset} {start} {n}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(SsnFormat::Dashed.apply("900-01-6789"), "900-01-6789");
+        assert_eq!(SsnFormat::Digits.apply("900-01-6789"), "900016789");
+        assert_eq!(SsnFormat::Masked.apply("900-01-6789"), "***-**-6789");
+        assert_eq!(SsnFormat::parse("masked"), Ok(SsnFormat::Masked));
+        assert!(SsnFormat::parse("hidden").is_err());
+    }
+
+    #[test]
+    fn test_random_prefixes() {
+        let ssns = SsnGenerator::builder()
+            .shuffle_prefixes(42)
+            .max_prefixes(3)
+            .build()
+            .unwrap();
+        assert_eq!(ssns.prefix_count(), 3);
+        assert_eq!(ssns.total(), 3 * 99 * 9999);
+
+        let first = SsnGenerator::builder()
+            .shuffle_prefixes(42)
+            .build()
+            .unwrap()
+            .next()
+            .unwrap();
+        let prefix: u32 = first[..3].parse().unwrap();
+        assert!(fake_prefixes().contains(&prefix));
+        assert_eq!(&first[3..], "-01-0001");
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut ssns = SsnGenerator::builder()
+            .prefixes([901])
+            .group_range(5..=5)
+            .serial_range(10..=11)
+            .format(SsnFormat::Masked)
+            .build()
+            .unwrap();
+        assert_eq!(ssns.next(), Some(String::from("***-**-0010")));
+        assert_eq!(ssns.next(), Some(String::from("***-**-0011")));
+        assert_eq!(ssns.next(), None);
+
+        assert!(SsnGenerator::builder().prefixes([]).build().is_err());
+        assert!(SsnGenerator::builder().prefixes([1000]).build().is_err());
+        assert!(SsnGenerator::builder().group_range(0..=99).build().is_err());
+        assert!(SsnGenerator::builder().serial_range(1..=10000).build().is_err());
+    }
+
+    proptest! {
+        // Whatever the settings, every number uses one of the prefixes and
+        // stays within the ranges, and no number repeats until the
+        // generator runs out, after exactly `total()` numbers.
+        #[test]
+        fn numbers_stay_within_settings(
+            prefixes in subsequence(fake_prefixes(), 1..5),
+            group_min in 1u32..=99,
+            group_len in 0u32..5,
+            serial_min in 1u32..=9999,
+            serial_len in 0u32..20,
+            seed: u64,
+        ) {
+            let group_max = (group_min + group_len).min(99);
+            let serial_max = (serial_min + serial_len).min(9999);
+            let ssns = SsnGenerator::builder()
+                .prefixes(prefixes.clone())
+                .group_range(group_min..=group_max)
+                .serial_range(serial_min..=serial_max)
+                .shuffle_prefixes(seed)
+                .build()
+                .unwrap();
+            let total = ssns.total();
+            let numbers: Vec<String> = ssns.collect();
+
+            prop_assert_eq!(numbers.len() as u64, total);
+            prop_assert_eq!(numbers.iter().collect::<HashSet<_>>().len(), numbers.len());
+            for n in &numbers {
+                let parts: Vec<u32> = n.split('-').map(|p| p.parse().unwrap()).collect();
+                prop_assert!(prefixes.contains(&parts[0]), "{} has the wrong prefix", n);
+                prop_assert!((group_min..=group_max).contains(&parts[1]));
+                prop_assert!((serial_min..=serial_max).contains(&parts[2]));
+            }
+        }
+    }
 }
@@ -0,0 +1,312 @@
+//! Staged output files. A run's files are written under temporary names, in
+//! the same directories as the real ones, and only moved into place once
+//! everything's been written. A run that fails, or is interrupted, removes
+//! its temporary files and leaves the previous files (if any) alone.
+//!
+//! Moving the files into place takes a rename per file, so it isn't atomic:
+//! something reading the files during the commit can see some new files and
+//! some old ones. But the files being replaced are kept until every rename
+//! has worked, and if one fails, the files already moved are taken out again
+//! and the old ones put back, so a commit that fails leaves the files as they
+//! were, unless putting them back fails, too.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/**
+ * The files of a run that are being staged. Dropping a `Staging` without
+ * calling `commit()` removes whatever was staged.
+ */
+#[derive(Debug, Default)]
+pub struct Staging {
+    /// The temporary and final paths of the staged files, in staging order
+    files: Vec<(PathBuf, PathBuf)>,
+    /// The temporary and final paths of the staged directories, in staging
+    /// order
+    dirs: Vec<(PathBuf, PathBuf)>,
+}
+
+/// A file `commit()` moved into place.
+struct Moved {
+    path: PathBuf,
+    /// Where the file it replaced was moved, if it replaced one
+    backup: Option<PathBuf>,
+}
+
+impl Staging {
+    /// Create an empty staging area.
+    pub fn new() -> Staging {
+        Staging::default()
+    }
+
+    /**
+     * Stage a file.
+     *
+     * # Arguments
+     *
+     * - `path`: Where the file belongs, once the run succeeds
+     *
+     * # Returns
+     *
+     * The temporary path to write the file to, instead: a hidden file in the
+     * same directory (so renaming it into place is atomic), named after the
+     * process, so concurrent runs don't collide.
+     */
+    pub fn stage(&mut self, path: &Path) -> PathBuf {
+        let temporary = hidden(path, "tmp");
+        self.files.push((temporary.clone(), path.to_path_buf()));
+        temporary
+    }
+
+    /**
+     * Stage a directory of files. The files are moved into the directory on
+     * commit, replacing any with the same names, and the directory's other
+     * files are left alone.
+     *
+     * # Arguments
+     *
+     * - `dir`: Where the files belong, once the run succeeds
+     *
+     * # Returns
+     *
+     * The temporary directory to write the files to, instead, which is
+     * hidden next to `dir`, as with `stage()`. It isn't created until
+     * something's written to it.
+     */
+    pub fn stage_dir(&mut self, dir: &Path) -> PathBuf {
+        let temporary = hidden(dir, "tmp");
+        self.dirs.push((temporary.clone(), dir.to_path_buf()));
+        temporary
+    }
+
+    /**
+     * Move the staged files into place, in the order they were staged, and
+     * then the files of the staged directories.
+     *
+     * # Returns
+     *
+     * - `Ok(())`: Every file is in place.
+     * - `Err(msg)`: A file couldn't be moved; `msg` explains why. The files
+     *   already moved are taken out again, the files they replaced are put
+     *   back, and the staged files are removed.
+     */
+    pub fn commit(mut self) -> Result<(), String> {
+        let mut files = self.files.clone();
+        for (temporary, dir) in &self.dirs {
+            staged_files(temporary, dir, &mut files)?;
+        }
+        self.files.clear();
+
+        let mut moved = Vec::new();
+        let mut created = Vec::new();
+        for (i, (temporary, path)) in files.iter().enumerate() {
+            if let Err(msg) = move_into_place(temporary, path, &mut moved, &mut created) {
+                roll_back(&moved, &created);
+                self.files = files[i..].to_vec();
+                return Err(msg);
+            }
+        }
+
+        for backup in moved.into_iter().filter_map(|m| m.backup) {
+            let _ = fs::remove_file(backup);
+        }
+        // Dropping `self` removes the staged directories, which are empty now.
+        Ok(())
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        for (temporary, _) in &self.files {
+            // Some of the files may never have been created.
+            let _ = fs::remove_file(temporary);
+        }
+        for (temporary, _) in &self.dirs {
+            let _ = fs::remove_dir_all(temporary);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Private Functions
+// ----------------------------------------------------------------------------
+
+/// Get a hidden path next to `path`, named after it and the process.
+fn hidden(path: &Path, extension: &str) -> PathBuf {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("output");
+    path.with_file_name(format!(".{}.{}.{}", name, process::id(), extension))
+}
+
+/// Add the files written to the staged directory `temporary` to `files`,
+/// along with where they belong in `dir`, in name order.
+fn staged_files(temporary: &Path, dir: &Path, files: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), String> {
+    // Nothing was written to it.
+    if !temporary.is_dir() {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(temporary)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Can't read \"{}\": {}", temporary.display(), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let (from, to) = (entry.path(), dir.join(entry.file_name()));
+        if from.is_dir() {
+            staged_files(&from, &to, files)?;
+        }
+        else {
+            files.push((from, to));
+        }
+    }
+
+    Ok(())
+}
+
+/// Move a staged file into place, first moving the file it replaces, if
+/// any, aside, and creating the directories it goes in, if need be.
+fn move_into_place(
+    temporary: &Path,
+    path: &Path,
+    moved: &mut Vec<Moved>,
+    created: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        let missing: Vec<&Path> = parent
+            .ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && !dir.is_dir())
+            .collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(dir).map_err(|e| format!("Can't create \"{}\": {}", dir.display(), e))?;
+            created.push(dir.to_path_buf());
+        }
+    }
+
+    let backup = if path.is_file() {
+        let backup = hidden(path, "bak");
+        fs::rename(path, &backup)
+            .map_err(|e| format!("Can't move \"{}\" to \"{}\": {}", path.display(), backup.display(), e))?;
+        Some(backup)
+    }
+    else {
+        None
+    };
+
+    if let Err(e) = fs::rename(temporary, path) {
+        if let Some(backup) = &backup {
+            let _ = fs::rename(backup, path);
+        }
+        return Err(format!("Can't move \"{}\" to \"{}\": {}", temporary.display(), path.display(), e));
+    }
+
+    moved.push(Moved { path: path.to_path_buf(), backup });
+    Ok(())
+}
+
+/// Undo the moves of a failed commit, latest first, and remove the
+/// directories it created.
+fn roll_back(moved: &[Moved], created: &[PathBuf]) {
+    for m in moved.iter().rev() {
+        let _ = fs::remove_file(&m.path);
+        if let Some(backup) = &m.backup {
+            let _ = fs::rename(backup, &m.path);
+        }
+    }
+    for dir in created.iter().rev() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::staging::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn commit() {
+        let dir = TempDir::new().unwrap();
+        let people = dir.path().join("people.csv");
+        let report = dir.path().join("report.csv");
+        let avatars = dir.path().join("avatars");
+        fs::write(&people, "old").unwrap();
+        fs::create_dir_all(avatars.join("0000")).unwrap();
+        fs::write(avatars.join("0000").join("000001.png"), "old").unwrap();
+        fs::write(avatars.join("notes.txt"), "kept").unwrap();
+
+        let mut staging = Staging::new();
+        let staged = staging.stage(&people);
+        assert_eq!(staged.parent(), people.parent());
+        fs::write(&staged, "new").unwrap();
+        fs::write(staging.stage(&report), "report").unwrap();
+        let staged_avatars = staging.stage_dir(&avatars);
+        assert_eq!(staged_avatars.parent(), avatars.parent());
+        fs::create_dir_all(staged_avatars.join("0000")).unwrap();
+        fs::create_dir_all(staged_avatars.join("0001")).unwrap();
+        fs::write(staged_avatars.join("0000").join("000001.png"), "new").unwrap();
+        fs::write(staged_avatars.join("0001").join("001001.png"), "new").unwrap();
+        // A directory nothing was written to.
+        staging.stage_dir(&dir.path().join("barcodes"));
+
+        // Nothing's in place until the commit.
+        assert_eq!(fs::read_to_string(&people).unwrap(), "old");
+        assert!(!report.exists());
+        assert_eq!(fs::read_to_string(avatars.join("0000").join("000001.png")).unwrap(), "old");
+
+        staging.commit().unwrap();
+        assert_eq!(fs::read_to_string(&people).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&report).unwrap(), "report");
+        assert_eq!(fs::read_to_string(avatars.join("0000").join("000001.png")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(avatars.join("0001").join("001001.png")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(avatars.join("notes.txt")).unwrap(), "kept");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+        assert_eq!(fs::read_dir(avatars.join("0000")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failed_commit() {
+        let dir = TempDir::new().unwrap();
+        let people = dir.path().join("people.csv");
+        let documents = dir.path().join("documents");
+        let avatars = dir.path().join("avatars");
+        fs::write(&people, "old").unwrap();
+        // A directory where a file belongs can't be replaced.
+        let blocked = avatars.join("0000").join("000002.png");
+        fs::create_dir_all(&blocked).unwrap();
+        fs::write(blocked.join("file"), "").unwrap();
+
+        let mut staging = Staging::new();
+        fs::write(staging.stage(&people), "new").unwrap();
+        let staged_documents = staging.stage_dir(&documents);
+        fs::create_dir_all(staged_documents.join("0000")).unwrap();
+        fs::write(staged_documents.join("0000").join("000001.txt"), "new").unwrap();
+        let staged_avatars = staging.stage_dir(&avatars);
+        fs::create_dir_all(staged_avatars.join("0000")).unwrap();
+        fs::write(staged_avatars.join("0000").join("000001.png"), "new").unwrap();
+        fs::write(staged_avatars.join("0000").join("000002.png"), "new").unwrap();
+
+        assert!(staging.commit().is_err());
+        assert_eq!(fs::read_to_string(&people).unwrap(), "old");
+        assert!(!documents.exists());
+        assert_eq!(fs::read_dir(avatars.join("0000")).unwrap().count(), 1);
+        assert!(blocked.join("file").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn abandon() {
+        let dir = TempDir::new().unwrap();
+        let people = dir.path().join("people.csv");
+        fs::write(&people, "old").unwrap();
+
+        let mut staging = Staging::new();
+        fs::write(staging.stage(&people), "half").unwrap();
+        staging.stage(&dir.path().join("never-written.csv"));
+        let staged_avatars = staging.stage_dir(&dir.path().join("avatars"));
+        fs::create_dir_all(staged_avatars.join("0000")).unwrap();
+        fs::write(staged_avatars.join("0000").join("000001.png"), "half").unwrap();
+        drop(staging);
+
+        assert_eq!(fs::read_to_string(&people).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+//! Statistics about a run's people, for sanity-checking the data without a
+//! separate script: how many of each gender, a histogram of birth years,
+//! the salaries' mean, median and standard deviation, and how many people
+//! share a name.
+//!
+//! Like the aggregate tables, the statistics are computed as the people are
+//! generated, and see the values as the rules leave them, before formatting,
+//! hashing or encryption. The salaries are counted per distinct value, as
+//! for the aggregate tables, so the median is exact; the names are kept,
+//! since any of them could turn up again.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use thousands::Separable;
+
+use crate::aggregates::quantile;
+
+/// The bucket widths, in years, the birth year histogram can use: the
+/// narrowest that needs no more than `MAX_BUCKETS` buckets.
+const BUCKET_WIDTHS: [i32; 6] = [1, 2, 5, 10, 20, 50];
+const MAX_BUCKETS: i32 = 20;
+
+/// The length of the histogram bar for the biggest bucket.
+const BAR_WIDTH: u64 = 40;
+
+/// The statistics of the people generated so far.
+#[derive(Debug, Default)]
+pub struct Stats {
+    total: u64,
+    genders: BTreeMap<String, u64>,
+    birth_years: BTreeMap<i32, u64>,
+    /// How many people have each salary, if salaries are being written
+    salaries: Option<BTreeMap<u64, u64>>,
+    /// How many people have each full name
+    names: HashMap<String, u64>,
+}
+
+impl Stats {
+    /**
+     * Start the statistics, with no people yet.
+     *
+     * # Arguments
+     *
+     * - `salaries`: Whether salaries are being written
+     */
+    pub fn new(salaries: bool) -> Stats {
+        Stats { salaries: salaries.then(BTreeMap::new), ..Default::default() }
+    }
+
+    /**
+     * Add a person to the statistics.
+     *
+     * # Arguments
+     *
+     * - `value_of`: Gets the value of any of the person's fields
+     */
+    pub fn observe<F: Fn(&str) -> String>(&mut self, value_of: F) {
+        self.total += 1;
+        *self.genders.entry(value_of("gender")).or_default() += 1;
+
+        // A date a rule replaced with something that isn't one is left out,
+        // and so is a salary.
+        if let Some(year) = value_of("birth_date").get(..4).and_then(|y| y.parse::<i32>().ok()) {
+            *self.birth_years.entry(year).or_default() += 1;
+        }
+        if let (Some(salaries), Ok(salary)) = (&mut self.salaries, value_of("salary").parse::<u64>()) {
+            *salaries.entry(salary).or_default() += 1;
+        }
+
+        let name = format!("{} {}", value_of("first_name"), value_of("last_name"));
+        *self.names.entry(name).or_default() += 1;
+    }
+
+    /// Get the report, as lines of text.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        // Writing to a String can't fail.
+        let _ = writeln!(report, "Statistics for {} people:", self.total.separate_with_commas());
+
+        let genders: Vec<String> = self.genders
+            .iter()
+            .map(|(gender, count)| format!("{} {} ({})", gender, count.separate_with_commas(),
+                                           percent(*count, self.total)))
+            .collect();
+        let _ = writeln!(report, "Gender: {}", genders.join(", "));
+
+        let _ = writeln!(report, "Birth years:");
+        let histogram = self.histogram();
+        let most = histogram.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+        for (label, count) in histogram {
+            let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most) as usize);
+            let line = format!("  {label:<9} {:>10}  {bar}", count.separate_with_commas());
+            let _ = writeln!(report, "{}", line.trim_end());
+        }
+
+        if let Some(salaries) = &self.salaries {
+            let _ = writeln!(report, "Salary: {}", salary_stats(salaries));
+        }
+
+        let shared: Vec<(&String, &u64)> = self.names.iter().filter(|(_, n)| **n > 1).collect();
+        let people: u64 = shared.iter().map(|(_, n)| **n).sum();
+        let mut names = format!(
+            "Names: {} distinct, {} shared by {} people",
+            self.names.len().separate_with_commas(),
+            shared.len().separate_with_commas(),
+            people.separate_with_commas()
+        );
+        // The ties are broken by name, so the report is the same every time.
+        if let Some((name, n)) = shared.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))) {
+            let _ = write!(names, "; the most common is \"{name}\" ({n})");
+        }
+        let _ = writeln!(report, "{names}");
+
+        report
+    }
+
+    /// Get the birth year histogram, as bucket labels and counts, in order.
+    fn histogram(&self) -> Vec<(String, u64)> {
+        let (Some(first), Some(last)) = (self.birth_years.keys().next(), self.birth_years.keys().next_back()) else {
+            return Vec::new();
+        };
+        let width = *BUCKET_WIDTHS
+            .iter()
+            .find(|w| last.div_euclid(**w) - first.div_euclid(**w) < MAX_BUCKETS)
+            .unwrap_or(&BUCKET_WIDTHS[BUCKET_WIDTHS.len() - 1]);
+
+        let mut buckets: BTreeMap<i32, u64> = BTreeMap::new();
+        for (year, count) in &self.birth_years {
+            *buckets.entry(year - year.rem_euclid(width)).or_default() += count;
+        }
+
+        // Empty buckets in the middle are shown, so the gaps are visible.
+        let (start, end) = (first - first.rem_euclid(width), last - last.rem_euclid(width));
+        (start..=end)
+            .step_by(width as usize)
+            .map(|b| {
+                let label = if width == 1 { b.to_string() } else { format!("{}-{}", b, b + width - 1) };
+                (label, buckets.get(&b).copied().unwrap_or(0))
+            })
+            .collect()
+    }
+}
+
+/// Format a count as a percentage of a total, to a tenth of a percent.
+fn percent(count: u64, total: u64) -> String {
+    format!("{:.1}%", count as f64 * 100.0 / total.max(1) as f64)
+}
+
+/// Describe the salaries: their mean, median, standard deviation and range.
+fn salary_stats(counts: &BTreeMap<u64, u64>) -> String {
+    let total: u64 = counts.values().sum();
+    if total == 0 {
+        return String::from("none");
+    }
+
+    let n = total as f64;
+    let mean = counts.iter().map(|(s, c)| *s as f64 * *c as f64).sum::<f64>() / n;
+    let variance = counts.iter().map(|(s, c)| (*s as f64 - mean).powi(2) * *c as f64).sum::<f64>() / n;
+    format!(
+        "mean {}, median {}, standard deviation {}, range {} to {}",
+        (mean.round() as u64).separate_with_commas(),
+        quantile(counts, total, 50).separate_with_commas(),
+        (variance.sqrt().round() as u64).separate_with_commas(),
+        quantile(counts, total, 0).separate_with_commas(),
+        quantile(counts, total, 100).separate_with_commas()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::*;
+
+    fn stats(people: &[(&str, &str, &str, &str)], salaries: bool) -> Stats {
+        let mut stats = Stats::new(salaries);
+        for (gender, birth_date, salary, first_name) in people {
+            let person = HashMap::from([
+                ("gender", *gender),
+                ("birth_date", *birth_date),
+                ("salary", *salary),
+                ("first_name", *first_name),
+                ("last_name", "Howard"),
+            ]);
+            stats.observe(|key| person[key].to_string());
+        }
+        stats
+    }
+
+    #[test]
+    fn report() {
+        let people = [
+            ("M", "1950-01-02", "40000", "Moe"),
+            ("M", "1953-05-06", "50000", "Curly"),
+            ("F", "1961-07-08", "60000", "Moe"),
+            ("M", "1957-03-04", "90000", "Shemp"),
+        ];
+        assert_eq!(stats(&people, true).report(), "\
+Statistics for 4 people:
+Gender: F 1 (25.0%), M 3 (75.0%)
+Birth years:
+  1950               1  ########################################
+  1951               0
+  1952               0
+  1953               1  ########################################
+  1954               0
+  1955               0
+  1956               0
+  1957               1  ########################################
+  1958               0
+  1959               0
+  1960               0
+  1961               1  ########################################
+Salary: mean 60,000, median 50,000, standard deviation 18,708, range 40,000 to 90,000
+Names: 3 distinct, 1 shared by 2 people; the most common is \"Moe Howard\" (2)
+");
+        let report = stats(&people, false).report();
+        assert!(!report.contains("Salary"), "{report}");
+    }
+
+    #[test]
+    fn histogram() {
+        let people = [("M", "1940-01-01", "", "Moe"), ("M", "1985-01-01", "", "Moe"), ("F", "1999-12-31", "", "Moe")];
+        assert_eq!(
+            stats(&people, false).histogram(),
+            [
+                (String::from("1940-1944"), 1), (String::from("1945-1949"), 0), (String::from("1950-1954"), 0),
+                (String::from("1955-1959"), 0), (String::from("1960-1964"), 0), (String::from("1965-1969"), 0),
+                (String::from("1970-1974"), 0), (String::from("1975-1979"), 0), (String::from("1980-1984"), 0),
+                (String::from("1985-1989"), 1), (String::from("1990-1994"), 0), (String::from("1995-1999"), 1),
+            ]
+        );
+        assert!(Stats::new(false).histogram().is_empty());
+        assert!(Stats::new(false).report().contains("Names: 0 distinct, 0 shared by 0 people\n"));
+    }
+}
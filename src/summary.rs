@@ -0,0 +1,195 @@
+//! The summary of a run: what was written, and where. It's printed at the end
+//! of a run, either as messages or, with `--json-summary`, as a single JSON
+//! object, for scripts and orchestration tools.
+
+use json::JsonValue;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where the people went: a file, shards of one, standard output, nowhere
+/// (the null sink), or a Postgres table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    File(PathBuf),
+    Shards(Vec<PathBuf>),
+    Stdout,
+    Null,
+    Table(String),
+}
+
+/// An extra output of a run (e.g., a directory of avatars), and how many
+/// things were written to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraOutput {
+    /// What was written, e.g., "avatars"
+    pub kind: &'static str,
+    pub path: PathBuf,
+    pub count: u64,
+}
+
+/// The summary of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// How many people were written
+    pub records: u64,
+    /// The output format's name, e.g., "CSV"
+    pub format: String,
+    pub destination: Destination,
+    pub extras: Vec<ExtraOutput>,
+    pub seed: u64,
+    /// Whether the seed was chosen at random
+    pub random_seed: bool,
+    pub duration: Duration,
+}
+
+impl RunSummary {
+    /**
+     * Get the summary as messages, one per line, as printed at the end of a
+     * run without `--json-summary`.
+     */
+    pub fn to_messages(&self) -> Vec<String> {
+        let mut messages: Vec<String> = self.extras
+            .iter()
+            .map(|e| match e.kind {
+                "ssn_collisions" => format!("Wrote {} shared SSN(s) to \"{}\".",
+                                            e.count, e.path.display()),
+                "immunizations" => format!("Wrote {} immunization(s) to \"{}\".", e.count, e.path.display()),
+                "w2" => format!("Wrote {} W-2 statement(s) to \"{}\".", e.count, e.path.display()),
+                "transactions" => format!("Wrote {} transaction(s) to \"{}\".", e.count, e.path.display()),
+                "shifts" => format!("Wrote {} shift(s) to \"{}\".", e.count, e.path.display()),
+                "proto" => format!("Wrote protobuf definition to \"{}\".", e.path.display()),
+                kind => format!("Wrote {} {} to directory \"{}\".",
+                                e.count, what(kind), e.path.display()),
+            })
+            .collect();
+
+        messages.push(match &self.destination {
+            Destination::Table(table) => format!("Loaded {} record(s) into Postgres table \"{}\".",
+                                                 self.records, table),
+            Destination::File(path) => format!("Wrote {} records(s) to {} file \"{}\".",
+                                               self.records, self.format, path.display()),
+            Destination::Shards(paths) => format!("Wrote {} records(s) to {} {} files, \"{}\" to \"{}\".",
+                                                  self.records, paths.len(), self.format,
+                                                  paths[0].display(), paths[paths.len() - 1].display()),
+            Destination::Stdout => format!("Wrote {} records(s) to standard output, as {}.",
+                                           self.records, self.format),
+            Destination::Null => format!("Formatted {} records(s) as {}, and discarded them.",
+                                         self.records, self.format),
+        });
+        messages
+    }
+
+    /**
+     * Get the summary as a JSON object, e.g.:
+     *
+     * ```text
+     * {"records":100,"format":"CSV","output":"people.csv","seed":"42",
+     *  "random_seed":false,"duration_secs":0.012,
+     *  "files":[{"kind":"avatars","path":"avatars","count":100}]}
+     * ```
+     *
+     * A Postgres load has a "table" key in place of "output", the null
+     * sink's "output" is null, and sharded output's is an array of the
+     * shards.
+     */
+    pub fn to_json(&self) -> JsonValue {
+        let mut files = JsonValue::new_array();
+        for e in &self.extras {
+            let mut file = JsonValue::new_object();
+            file["kind"] = e.kind.into();
+            file["path"] = e.path.display().to_string().into();
+            file["count"] = e.count.into();
+            // Pushing onto an array can't fail.
+            let _ = files.push(file);
+        }
+
+        let mut summary = JsonValue::new_object();
+        summary["records"] = self.records.into();
+        summary["format"] = self.format.as_str().into();
+        match &self.destination {
+            Destination::File(path) => summary["output"] = path.display().to_string().into(),
+            Destination::Shards(paths) => {
+                summary["output"] = paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().into()
+            },
+            Destination::Stdout => summary["output"] = "-".into(),
+            Destination::Null => summary["output"] = JsonValue::Null,
+            Destination::Table(table) => summary["table"] = table.as_str().into(),
+        }
+        // JSON numbers are doubles, which can't hold every u64 seed.
+        summary["seed"] = self.seed.to_string().into();
+        summary["random_seed"] = self.random_seed.into();
+        summary["duration_secs"] = self.duration.as_secs_f64().into();
+        summary["files"] = files;
+        summary
+    }
+}
+
+/// The "thing(s)" in a message about an extra output.
+fn what(kind: &str) -> &str {
+    match kind {
+        "documents" => "document(s)",
+        "avatars" => "avatar(s)",
+        "barcodes" => "barcode(s)",
+        "aggregates" => "aggregate table(s)",
+        "benefits" => "benefits election(s)",
+        _ => "file(s)",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::summary::*;
+
+    fn summary() -> RunSummary {
+        RunSummary {
+            records: 10,
+            format: String::from("CSV"),
+            destination: Destination::File(PathBuf::from("people.csv")),
+            extras: vec![
+                ExtraOutput { kind: "avatars", path: PathBuf::from("av"), count: 10 },
+                ExtraOutput { kind: "ssn_collisions", path: PathBuf::from("c.csv"), count: 2 },
+            ],
+            seed: 42,
+            random_seed: false,
+            duration: Duration::from_millis(1500),
+        }
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(summary().to_messages(), vec![
+            String::from("Wrote 10 avatar(s) to directory \"av\"."),
+            String::from("Wrote 2 shared SSN(s) to \"c.csv\"."),
+            String::from("Wrote 10 records(s) to CSV file \"people.csv\"."),
+        ]);
+
+        let to_stdout = RunSummary { destination: Destination::Stdout, extras: Vec::new(), ..summary() };
+        assert_eq!(to_stdout.to_messages(), vec![String::from("Wrote 10 records(s) to standard output, as CSV.")]);
+        assert_eq!(to_stdout.to_json()["output"], "-");
+
+        let sharded = RunSummary {
+            destination: Destination::Shards(vec![PathBuf::from("p-00000.csv"), PathBuf::from("p-00001.csv")]),
+            extras: Vec::new(),
+            ..summary()
+        };
+        assert_eq!(sharded.to_messages(),
+                   vec![String::from("Wrote 10 records(s) to 2 CSV files, \"p-00000.csv\" to \"p-00001.csv\".")]);
+        assert_eq!(sharded.to_json()["output"][1], "p-00001.csv");
+
+        let to_null = RunSummary { destination: Destination::Null, extras: Vec::new(), ..summary() };
+        assert_eq!(to_null.to_messages(), vec![String::from("Formatted 10 records(s) as CSV, and discarded them.")]);
+        assert!(to_null.to_json()["output"].is_null());
+    }
+
+    #[test]
+    fn json() {
+        let j = summary().to_json();
+        assert_eq!(j["records"], 10);
+        assert_eq!(j["output"], "people.csv");
+        assert_eq!(j["seed"], "42");
+        assert_eq!(j["duration_secs"], 1.5);
+        assert_eq!(j["files"][1]["kind"], "ssn_collisions");
+        assert_eq!(j["files"][1]["count"], 2);
+        assert!(j["table"].is_null());
+    }
+}
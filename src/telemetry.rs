@@ -0,0 +1,406 @@
+//! Trace spans for a run, for platform teams running `peoplegen` inside data
+//! pipelines, who want to see where the time goes without profiling it
+//! themselves: reading the names, writing each output file (which is where
+//! the people are generated, too), finishing the extra files, and moving the
+//! output into place. The library marks these with `tracing` spans, so a
+//! program using it can collect them with a subscriber of its own.
+//!
+//! `--otlp-endpoint` installs `SpanCollector`, which keeps the spans, and,
+//! once the run is over, sends them to an OpenTelemetry collector. This is a
+//! minimal OTLP exporter: only what `peoplegen` needs. The spans are sent in
+//! a single request, as OTLP/HTTP with the JSON encoding, to the endpoint's
+//! `/v1/traces`. See <https://opentelemetry.io/docs/specs/otlp/#otlphttp>.
+
+use json::JsonValue;
+use rand::Rng;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// The environment variable that sets the OTLP endpoint, when the command
+/// line doesn't. It's the one OpenTelemetry's own exporters use.
+pub const ENV_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// How long to wait for the collector to take the spans.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The installed collector, and the endpoint its spans go to.
+static INSTALLED: OnceLock<(SpanCollector, String)> = OnceLock::new();
+
+thread_local! {
+    /// The spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A span attribute's value.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+/// A span, and what happened in it.
+#[derive(Debug, Clone)]
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+}
+
+/// The spans still open, and those that have ended.
+#[derive(Debug, Default)]
+struct Spans {
+    /// The open spans, by `Id`, with how many handles to each there are
+    open: HashMap<u64, (SpanData, usize)>,
+    finished: Vec<SpanData>,
+    last_id: u64,
+}
+
+/// A `tracing` subscriber that keeps a run's spans, for export.
+#[derive(Debug, Clone)]
+pub struct SpanCollector {
+    trace_id: u128,
+    spans: Arc<Mutex<Spans>>,
+}
+
+impl Default for SpanCollector {
+    fn default() -> Self {
+        SpanCollector::new()
+    }
+}
+
+impl SpanCollector {
+    /// Create a collector, for a new trace.
+    pub fn new() -> SpanCollector {
+        SpanCollector {
+            trace_id: rand::thread_rng().gen_range(1..=u128::MAX),
+            spans: Arc::new(Mutex::new(Spans::default())),
+        }
+    }
+
+    /// How many spans have ended.
+    pub fn finished(&self) -> usize {
+        self.spans().finished.len()
+    }
+
+    /**
+     * Get the spans that have ended as an OTLP/JSON export request, with
+     * the program as the resource, and the library as the scope.
+     */
+    pub fn to_json(&self) -> JsonValue {
+        let attribute = |key: &str, value: &Value| {
+            let mut v = JsonValue::new_object();
+            match value {
+                Value::String(s) => v["stringValue"] = s.as_str().into(),
+                // 64-bit integers are strings in JSON-encoded protobuf.
+                Value::Int(n) => v["intValue"] = n.to_string().into(),
+                Value::Double(x) => v["doubleValue"] = (*x).into(),
+                Value::Bool(b) => v["boolValue"] = (*b).into(),
+            }
+            json::object! { key: key, value: v }
+        };
+        let nanos = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string();
+        let version = Value::String(env!("CARGO_PKG_VERSION").to_string());
+
+        let mut spans = JsonValue::new_array();
+        for span in &self.spans().finished {
+            let mut s = JsonValue::new_object();
+            s["traceId"] = format!("{:032x}", self.trace_id).into();
+            s["spanId"] = format!("{:016x}", span.span_id).into();
+            if let Some(parent) = span.parent_span_id {
+                s["parentSpanId"] = format!("{parent:016x}").into();
+            }
+            s["name"] = span.metadata.name().into();
+            // SPAN_KIND_INTERNAL
+            s["kind"] = 1.into();
+            s["startTimeUnixNano"] = nanos(span.start).into();
+            s["endTimeUnixNano"] = nanos(span.end).into();
+            s["attributes"] = span.attributes.iter().map(|(k, v)| attribute(k, v)).collect::<Vec<_>>().into();
+            let _ = spans.push(s);
+        }
+
+        let mut request = json::object! {
+            resourceSpans: [{
+                resource: {
+                    attributes: [
+                        attribute("service.name", &Value::String(String::from("peoplegen"))),
+                        attribute("service.version", &version),
+                    ],
+                },
+                scopeSpans: [{
+                    scope: { name: "peoplegen", version: env!("CARGO_PKG_VERSION") },
+                }],
+            }],
+        };
+        request["resourceSpans"][0]["scopeSpans"][0]["spans"] = spans;
+        request
+    }
+
+    fn spans(&self) -> MutexGuard<'_, Spans> {
+        // A panic elsewhere doesn't make the spans any less worth having.
+        self.spans.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Subscriber for SpanCollector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && metadata.target().starts_with("peoplegen")
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let parent = match attrs.parent() {
+            Some(parent) => Some(parent.clone()),
+            None if attrs.is_contextual() => current(),
+            None => None,
+        };
+        let mut spans = self.spans();
+        let now = SystemTime::now();
+        let mut span = SpanData {
+            metadata: attrs.metadata(),
+            span_id: rand::thread_rng().gen_range(1..=u64::MAX),
+            parent_span_id: parent.and_then(|p| spans.open.get(&p.into_u64()).map(|(s, _)| s.span_id)),
+            start: now,
+            end: now,
+            attributes: Vec::new(),
+        };
+        attrs.record(&mut FieldValues(&mut span.attributes));
+
+        spans.last_id += 1;
+        let id = spans.last_id;
+        spans.open.insert(id, (span, 1));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some((span, _)) = self.spans().open.get_mut(&span.into_u64()) {
+            values.record(&mut FieldValues(&mut span.attributes));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|id| id == span) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some((_, handles)) = self.spans().open.get_mut(&id.into_u64()) {
+            *handles += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let mut spans = self.spans();
+        let id = id.into_u64();
+        match spans.open.get_mut(&id) {
+            Some((_, handles)) if *handles > 1 => {
+                *handles -= 1;
+                false
+            },
+            Some(_) => {
+                if let Some((mut span, _)) = spans.open.remove(&id) {
+                    span.end = SystemTime::now();
+                    spans.finished.push(span);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn current_span(&self) -> Current {
+        let spans = self.spans();
+        match current().and_then(|id| spans.open.get(&id.into_u64()).map(|(s, _)| (id, s.metadata))) {
+            Some((id, metadata)) => Current::new(id, metadata),
+            None => Current::none(),
+        }
+    }
+}
+
+/// The innermost span entered on this thread, if there is one.
+fn current() -> Option<Id> {
+    ENTERED.with(|entered| entered.borrow().last().cloned())
+}
+
+/// Keeps a span's fields, as attributes.
+struct FieldValues<'a>(&'a mut Vec<(&'static str, Value)>);
+
+impl FieldValues<'_> {
+    fn set(&mut self, field: &Field, value: Value) {
+        match self.0.iter_mut().find(|(key, _)| *key == field.name()) {
+            Some(attribute) => attribute.1 = value,
+            None => self.0.push((field.name(), value)),
+        }
+    }
+}
+
+impl Visit for FieldValues<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, Value::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, i64::try_from(value).map_or(Value::String(value.to_string()), Value::Int));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, Value::Double(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, Value::Bool(value));
+    }
+}
+
+/**
+ * Install a `SpanCollector` for the rest of the run, if no subscriber is
+ * installed already.
+ *
+ * # Arguments
+ *
+ * - `endpoint`: The OTLP/HTTP endpoint to send the spans to, e.g.,
+ *   "http://localhost:4318"
+ */
+pub fn init(endpoint: &str) {
+    let collector = SpanCollector::new();
+    if tracing::subscriber::set_global_default(collector.clone()).is_ok() {
+        let _ = INSTALLED.set((collector, endpoint.to_string()));
+    }
+}
+
+/**
+ * Send the spans that have ended to the collector `init()` installed, if it
+ * installed one.
+ *
+ * # Returns
+ *
+ * - `Ok(n)`: `n` spans were sent (none, without `init()`).
+ * - `Err(msg)`: The spans couldn't be sent; `msg` explains why.
+ */
+pub fn export() -> Result<usize, String> {
+    let Some((collector, endpoint)) = INSTALLED.get() else {
+        return Ok(0);
+    };
+
+    // ureq's errors already include the URL.
+    ureq::post(&traces_url(endpoint))
+        .timeout(EXPORT_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&collector.to_json().dump())
+        .map_err(|e| format!("Can't export the trace spans: {e}"))?;
+    Ok(collector.finished())
+}
+
+/// The URL traces go to: the endpoint's `/v1/traces`, as OpenTelemetry's own
+/// exporters do with `OTEL_EXPORTER_OTLP_ENDPOINT`.
+fn traces_url(endpoint: &str) -> String {
+    format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::telemetry::*;
+    use std::thread;
+    use tracing::info_span;
+
+    /// Get an exported span's attributes, as `key=value` strings.
+    fn attributes(span: &JsonValue) -> Vec<String> {
+        span["attributes"]
+            .members()
+            .map(|a| {
+                let value = a["value"].entries().next().unwrap().1;
+                format!("{}={}", a["key"], value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spans() {
+        let collector = SpanCollector::new();
+        tracing::subscriber::with_default(collector.clone(), || {
+            let run = info_span!("run", total = 25_u64, format = "CSV", records = tracing::field::Empty).entered();
+            info_span!("read_names").in_scope(|| {});
+            // Spans on other threads belong to the span they're given.
+            // (The collector is only this thread's default, so it's given,
+            // too.)
+            let parent = tracing::Span::current();
+            thread::scope(|scope| {
+                scope.spawn(|| {
+                    tracing::subscriber::with_default(collector.clone(), || {
+                        parent.in_scope(|| info_span!("write_people", path = "p-00000.csv").in_scope(|| {}))
+                    })
+                });
+            });
+            run.record("records", 24_u64);
+        });
+
+        let json = collector.to_json();
+        let scope = &json["resourceSpans"][0]["scopeSpans"][0];
+        let spans: Vec<&JsonValue> = scope["spans"].members().collect();
+        let names: Vec<&str> = spans.iter().map(|s| s["name"].as_str().unwrap()).collect();
+        assert_eq!(collector.finished(), 3);
+        assert_eq!(names, ["read_names", "write_people", "run"]);
+        assert_eq!(scope["scope"]["name"], "peoplegen");
+
+        let run = spans[2];
+        assert!(run["parentSpanId"].is_null());
+        assert_eq!(attributes(run), ["total=25", "format=CSV", "records=24"]);
+        for span in &spans[..2] {
+            assert_eq!(span["traceId"], run["traceId"]);
+            assert_eq!(span["parentSpanId"], run["spanId"]);
+            assert_ne!(span["spanId"], run["spanId"]);
+            assert!(span["startTimeUnixNano"].as_str().unwrap() <= span["endTimeUnixNano"].as_str().unwrap());
+        }
+        assert_eq!(attributes(spans[1]), ["path=p-00000.csv"]);
+        assert_eq!(run["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(run["spanId"].as_str().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn other_spans() {
+        // Only the library's own spans are kept.
+        let collector = SpanCollector::new();
+        tracing::subscriber::with_default(collector.clone(), || {
+            info_span!(target: "somebody_else", "theirs").in_scope(|| {});
+        });
+        assert_eq!(collector.finished(), 0);
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(traces_url("http://localhost:4318"), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("http://localhost:4318/"), "http://localhost:4318/v1/traces");
+        assert_eq!(export(), Ok(0));
+    }
+}
@@ -0,0 +1,187 @@
+//! Simple document templates, used to render one document per person.
+//!
+//! A template is a text file (plain text, HTML, etc.) containing
+//! placeholders of the form `{{field}}`, where `field` is the snake case
+//! name of a generated field (e.g., `{{first_name}}`, `{{birth_date}}`).
+//! Whitespace inside the braces is ignored, so `{{ first_name }}` works, too.
+
+use crate::path::path_str;
+use std::fs;
+use std::path::Path;
+
+/// One piece of a parsed template.
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Field(String),
+}
+
+/// A parsed template.
+#[derive(Debug)]
+pub struct Template {
+    segments: Vec<Segment>,
+    escape_html: bool,
+}
+
+impl Template {
+    /**
+     * Read and parse a template file. If the file has an `.html` or `.htm`
+     * extension, field values are HTML-escaped when the template is
+     * rendered.
+     *
+     * # Arguments
+     *
+     * - `path`: The path to the template file
+     * - `fields`: The field names the template is allowed to reference
+     *
+     * # Returns
+     *
+     * - `Ok(template)`: The parsed template
+     * - `Err(msg)`: The file couldn't be read or parsed; `msg` explains why.
+     */
+    pub fn from_file(path: &Path, fields: &[&str]) -> Result<Template, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("\"{}\": {}", path_str(path), e))?;
+        let escape_html = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("html") | Some("htm")
+        );
+
+        Template::parse(&text, fields, escape_html)
+            .map_err(|e| format!("\"{}\": {}", path_str(path), e))
+    }
+
+    /**
+     * Parse a template from a string.
+     *
+     * # Arguments
+     *
+     * - `text`: The template text
+     * - `fields`: The field names the template is allowed to reference
+     * - `escape_html`: Whether to HTML-escape field values on rendering
+     *
+     * # Returns
+     *
+     * - `Ok(template)`: The parsed template
+     * - `Err(msg)`: The template is malformed, or it references an unknown
+     *   field; `msg` explains why.
+     */
+    pub fn parse(text: &str,
+                 fields: &[&str],
+                 escape_html: bool) -> Result<Template, String> {
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or_else(|| {
+                String::from("Unterminated \"{{\" in template.")
+            })?;
+            let name = after[..end].trim();
+
+            if !fields.contains(&name) {
+                return Err(format!(
+                    "Unknown template field \"{}\". Valid fields: {}",
+                    name, fields.join(", ")
+                ));
+            }
+
+            segments.push(Segment::Field(name.to_string()));
+            rest = &after[end + 2..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(Template { segments, escape_html })
+    }
+
+    /**
+     * Render the template.
+     *
+     * # Arguments
+     *
+     * - `value`: A function that returns the value of a named field
+     *
+     * # Returns
+     *
+     * The rendered document.
+     */
+    pub fn render<F>(&self, value: F) -> String
+        where F: Fn(&str) -> String
+    {
+        let mut buf = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(s) => buf.push_str(s),
+                Segment::Field(name) if self.escape_html => {
+                    buf.push_str(&html_escape(&value(name)))
+                },
+                Segment::Field(name) => buf.push_str(&value(name)),
+            }
+        }
+
+        buf
+    }
+}
+
+/**
+ * Escape the characters that are special in HTML.
+ */
+pub fn html_escape(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&#39;"),
+            _ => buf.push(c),
+        }
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::Template;
+
+    #[test]
+    fn render_fields() {
+        let t = Template::parse("Dear {{first_name}} {{ last_name }},",
+                                &["first_name", "last_name"],
+                                false).unwrap();
+        let s = t.render(|name| {
+            if name == "first_name" { String::from("Moe") }
+            else { String::from("Howard") }
+        });
+        assert_eq!(s, "Dear Moe Howard,");
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert!(Template::parse("{{nope}}", &["first_name"], false).is_err());
+    }
+
+    #[test]
+    fn unterminated_field() {
+        assert!(Template::parse("{{first_name", &["first_name"], false).is_err());
+    }
+
+    #[test]
+    fn escape_html() {
+        let t = Template::parse("<b>{{last_name}}</b>", &["last_name"], true)
+            .unwrap();
+        assert_eq!(t.render(|_| String::from("O'Neill & Sons")),
+                   "<b>O&#39;Neill &amp; Sons</b>");
+    }
+}
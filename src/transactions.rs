@@ -0,0 +1,263 @@
+//! Transactions: line items for each person, so a finance or claims
+//! pipeline gets fact data to go with the people, which are the dimension.
+//! Each person gets the same number of transactions, each on a date in the
+//! window, with a merchant category code (MCC) and an amount that's typical
+//! for the category.
+//!
+//! The transactions come from their own random number generator, derived
+//! from the run's seed, so turning them on or off doesn't change any of the
+//! other generated values.
+
+use std::fs::File;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+use csv::{Writer, WriterBuilder};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::path::path_str;
+
+/// Mixed into the run's seed to seed the transactions' random number
+/// generator.
+const TRANSACTIONS_SEED_SALT: u64 = 0x7478_6e73_6163_7473;
+
+/// The default number of transactions per person.
+pub const DEFAULT_TRANSACTIONS_PER_PERSON: u32 = 5;
+
+/// The categories: the merchant category code, its description, how often
+/// it comes up (relative to the others), and the range of amounts, in cents.
+const CATEGORIES: [(&str, &str, u32, u64, u64); 12] = [
+    ("5411", "Grocery Stores, Supermarkets", 20, 1_500, 25_000),
+    ("5812", "Eating Places, Restaurants", 16, 800, 12_000),
+    ("5814", "Fast Food Restaurants", 14, 400, 3_000),
+    ("5541", "Service Stations", 10, 2_000, 9_000),
+    ("5912", "Drug Stores, Pharmacies", 8, 500, 15_000),
+    ("5311", "Department Stores", 7, 1_500, 40_000),
+    ("4900", "Utilities", 6, 4_000, 30_000),
+    ("5732", "Electronics Stores", 4, 2_000, 150_000),
+    ("4121", "Taxicabs, Limousines", 5, 800, 8_000),
+    ("8011", "Doctors", 4, 5_000, 60_000),
+    ("7011", "Hotels, Motels, Resorts", 3, 9_000, 80_000),
+    ("4511", "Airlines", 3, 9_000, 120_000),
+];
+
+/// The columns of the transactions file.
+const HEADER: [&str; 6] = ["transaction_id", "id", "date", "category_code", "category", "amount"];
+
+/// The settings for transactions, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionOptions {
+    /// How many transactions each person has
+    pub per_person: u32,
+    /// The earliest transaction date
+    pub min_date: NaiveDate,
+    /// The latest transaction date
+    pub max_date: NaiveDate,
+}
+
+/// One transaction. The amount is in cents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub date: NaiveDate,
+    pub category_code: &'static str,
+    pub category: &'static str,
+    pub amount: u64,
+}
+
+/// Generates transactions.
+pub struct TransactionGenerator {
+    rng: ChaCha8Rng,
+    weights: WeightedIndex<u32>,
+    per_person: u32,
+    min_date: NaiveDate,
+    max_date: NaiveDate,
+}
+
+impl TransactionGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The transaction settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &TransactionOptions, seed: u64) -> Result<TransactionGenerator, String> {
+        if options.min_date > options.max_date {
+            return Err(format!(
+                "The earliest transaction date, {}, is after the latest, {}.",
+                options.min_date, options.max_date
+            ));
+        }
+
+        Ok(TransactionGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ TRANSACTIONS_SEED_SALT),
+            // The weights are constants, and none of them is zero.
+            weights: WeightedIndex::new(CATEGORIES.iter().map(|c| c.2)).unwrap(),
+            per_person: options.per_person,
+            min_date: options.min_date,
+            max_date: options.max_date,
+        })
+    }
+
+    /**
+     * Generate the next person's transactions.
+     *
+     * # Returns
+     *
+     * The transactions, in date order.
+     */
+    pub fn next_transactions(&mut self) -> Vec<Transaction> {
+        let days = (self.max_date - self.min_date).num_days();
+        let mut transactions: Vec<Transaction> = (0..self.per_person)
+            .map(|_| {
+                let (category_code, category, _, min, max) = CATEGORIES[self.weights.sample(&mut self.rng)];
+                Transaction {
+                    date: self.min_date + Duration::days(self.rng.gen_range(0..=days)),
+                    category_code,
+                    category,
+                    amount: self.rng.gen_range(min..=max),
+                }
+            })
+            .collect();
+
+        transactions.sort_by_key(|t| t.date);
+        transactions
+    }
+}
+
+/// Format an amount of cents as dollars and cents, e.g., "1234.50".
+fn dollars(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+/// Writes people's transactions to a CSV file, one row per transaction.
+pub struct TransactionWriter {
+    generator: TransactionGenerator,
+    writer: Writer<File>,
+    path: String,
+    /// How many transactions have been written
+    pub total: u64,
+}
+
+impl TransactionWriter {
+    /**
+     * Start a transactions file.
+     *
+     * # Arguments
+     *
+     * - `options`: The transaction settings
+     * - `path`: Where to write the transactions
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, with the header written
+     * - `Err(msg)`: The settings are invalid, or the file can't be written.
+     */
+    pub fn new(options: &TransactionOptions, path: &Path, seed: u64) -> Result<TransactionWriter, String> {
+        let generator = TransactionGenerator::new(options, seed)?;
+        let path = path_str(path).to_string();
+        let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path, e);
+        let mut writer = WriterBuilder::new().from_path(&path).map_err(write_err)?;
+        writer.write_record(HEADER).map_err(write_err)?;
+
+        Ok(TransactionWriter { generator, writer, path, total: 0 })
+    }
+
+    /**
+     * Generate and write a person's transactions. They're numbered across
+     * the file, from 1.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     */
+    pub fn write(&mut self, id: u64) -> Result<(), String> {
+        for t in self.generator.next_transactions() {
+            self.total += 1;
+            self.writer
+                .write_record([
+                    self.total.to_string(),
+                    id.to_string(),
+                    t.date.to_string(),
+                    t.category_code.to_string(),
+                    t.category.to_string(),
+                    dollars(t.amount),
+                ])
+                .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Finish the file, and return how many transactions are in it.
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        Ok(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transactions::*;
+
+    fn options(per_person: u32) -> TransactionOptions {
+        TransactionOptions {
+            per_person,
+            min_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            max_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        }
+    }
+
+    #[test]
+    fn transactions() {
+        let mut g = TransactionGenerator::new(&options(20), 42).unwrap();
+
+        for _ in 0..50 {
+            let transactions = g.next_transactions();
+            assert_eq!(transactions.len(), 20);
+            assert!(transactions.windows(2).all(|t| t[0].date <= t[1].date));
+
+            for t in &transactions {
+                assert_eq!(t.date.format("%Y").to_string(), "2024");
+                let (_, category, _, min, max) = CATEGORIES.iter().find(|c| c.0 == t.category_code).unwrap();
+                assert_eq!(t.category, *category);
+                assert!(t.amount >= *min && t.amount <= *max, "{t:?}");
+            }
+        }
+
+        assert!(TransactionGenerator::new(&options(0), 42).unwrap().next_transactions().is_empty());
+    }
+
+    #[test]
+    fn seeds() {
+        let transactions = |seed| {
+            let mut g = TransactionGenerator::new(&options(3), seed).unwrap();
+            (0..10).map(|_| g.next_transactions()).collect::<Vec<_>>()
+        };
+        assert_eq!(transactions(42), transactions(42));
+        assert_ne!(transactions(42), transactions(43));
+    }
+
+    #[test]
+    fn bad_options() {
+        let backwards = TransactionOptions { min_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), ..options(1) };
+        assert!(TransactionGenerator::new(&backwards, 42).is_err());
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(dollars(123_450), "1234.50");
+        assert_eq!(dollars(7), "0.07");
+    }
+}
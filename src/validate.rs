@@ -0,0 +1,429 @@
+//! `peoplegen validate`: re-read a file of people, and check that it still
+//! looks like something `peoplegen` wrote, e.g., after it's been through a
+//! spreadsheet or an ETL job. The checks are of the schema (the columns are
+//! ones `peoplegen` writes, in any header format, and the required ones are
+//! there), the dates and timestamps, the SSNs, and the salaries.
+//!
+//! CSV, TSV, JSON and JSON Lines files can be checked, compressed or not.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::ReaderBuilder;
+use json::JsonValue;
+
+use crate::error::PeoplegenError;
+use crate::output::{split_compression, Compression};
+use crate::path::{file_extension, path_str};
+use crate::people::header_key;
+
+/// The fields every file has. (Gender can be left out, with `--no-gender`.)
+const REQUIRED_FIELDS: [&str; 4] = ["first_name", "middle_name", "last_name", "birth_date"];
+
+/// The fields holding dates, which are checked against the date format.
+const DATE_FIELDS: [&str; 4] = ["birth_date", "signup_date", "last_active_date", "member_since"];
+
+/// The fields holding timestamps, which are always written the same way.
+const TIMESTAMP_FIELDS: [&str; 3] = ["created_at", "updated_at", "consent_timestamp"];
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// The date format `peoplegen` writes, unless `--format-field` changes it.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// How many violations are reported, by default. The rest are counted.
+pub const DEFAULT_MAX_VIOLATIONS: usize = 100;
+
+/// What to check a file against.
+#[derive(Debug, Clone)]
+pub struct ValidateOptions {
+    /// The `strftime`-style format of the dates
+    pub date_format: String,
+    /// The lowest salary allowed
+    pub salary_min: u64,
+    /// The highest salary allowed, if there's a limit
+    pub salary_max: Option<u64>,
+    /// How many violations to report
+    pub max_violations: usize,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        ValidateOptions {
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            salary_min: 0,
+            salary_max: None,
+            max_violations: DEFAULT_MAX_VIOLATIONS,
+        }
+    }
+}
+
+/// Something wrong with a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The record's number, from 1, or 0 for the file's header
+    pub record: u64,
+    /// The column's name, as it is in the file
+    pub column: String,
+    /// What's wrong
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.record {
+            0 => write!(f, "header: {}: {}", self.column, self.message),
+            n => write!(f, "record {}: {}: {}", n, self.column, self.message),
+        }
+    }
+}
+
+/// The result of checking a file.
+#[derive(Debug, Default)]
+pub struct Validation {
+    /// How many records were checked
+    pub records: u64,
+    /// The first violations, up to `ValidateOptions::max_violations`
+    pub violations: Vec<Violation>,
+    /// How many violations there were, in all
+    pub total_violations: u64,
+}
+
+impl Validation {
+    /// Whether the file passed.
+    pub fn is_valid(&self) -> bool {
+        self.total_violations == 0
+    }
+
+    fn report(&mut self, options: &ValidateOptions, violation: Violation) {
+        self.total_violations += 1;
+        if self.violations.len() < options.max_violations {
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// The kinds of file that can be checked.
+enum FileFormat {
+    Delimited(u8),
+    Json,
+    JsonLines,
+}
+
+/**
+ * Check a file of people.
+ *
+ * # Arguments
+ *
+ * - `path`: The file. Its extension (".csv", ".tsv", ".json" or ".jsonl",
+ *   optionally followed by ".gz", ".zst" or ".bz2") says what's in it.
+ * - `options`: What to check it against
+ *
+ * # Returns
+ *
+ * - `Ok(validation)`: The file was read, and `validation` says what's wrong
+ *   with it, if anything.
+ * - `Err(e)`: The file couldn't be read (`PeoplegenError::Io`), or isn't a
+ *   CSV, TSV, JSON or JSON Lines file (`PeoplegenError::Parse`).
+ */
+pub fn validate_file(path: &Path, options: &ValidateOptions) -> Result<Validation, PeoplegenError> {
+    let (inner, compression) = split_compression(path);
+    let format = match file_extension(&inner) {
+        Some("csv") => FileFormat::Delimited(b','),
+        Some("tsv") => FileFormat::Delimited(b'\t'),
+        Some("json") => FileFormat::Json,
+        Some("jsonl") | Some("ndjson") => FileFormat::JsonLines,
+        _ => return Err(PeoplegenError::Parse(format!(
+            "Can't validate \"{}\": only CSV, TSV, JSON and JSON Lines files can be validated.",
+            path_str(path)
+        ))),
+    };
+    let io_error = |source| PeoplegenError::Io { path: path.to_path_buf(), source };
+    let file = File::open(path).map_err(io_error)?;
+    let reader: Box<dyn Read> = match compression {
+        None => Box::new(file),
+        Some(Compression::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some(Compression::Zstd) => Box::new(zstd::stream::read::Decoder::new(file).map_err(io_error)?),
+        Some(Compression::Bzip2) => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+    };
+    let parse_error = |msg: String| PeoplegenError::Parse(format!("\"{}\": {}", path_str(path), msg));
+    let mut validator = Validator::new(options);
+
+    match format {
+        FileFormat::Delimited(delimiter) => {
+            let mut csv = ReaderBuilder::new().delimiter(delimiter).flexible(true).from_reader(reader);
+            let headers: Vec<String> = csv
+                .headers()
+                .map_err(|e| parse_error(e.to_string()))?
+                .iter()
+                .map(|h| h.to_string())
+                .collect();
+            validator.check_columns(&headers);
+            for row in csv.records() {
+                let row = row.map_err(|e| parse_error(e.to_string()))?;
+                let values: Vec<&str> = row.iter().collect();
+                validator.check_row(&headers, &values);
+            }
+        },
+        FileFormat::Json => {
+            let mut text = String::new();
+            BufReader::new(reader).read_to_string(&mut text).map_err(io_error)?;
+            let json = json::parse(&text).map_err(|e| parse_error(e.to_string()))?;
+            // peoplegen writes {"people": [...]}, but a bare array will do.
+            let people = if json.is_array() { &json } else { &json["people"] };
+            if !people.is_array() {
+                return Err(parse_error(String::from("Not a JSON array of people.")));
+            }
+            for person in people.members() {
+                validator.check_object(person);
+            }
+        },
+        FileFormat::JsonLines => {
+            for line in BufReader::new(reader).lines() {
+                let line = line.map_err(io_error)?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let person = json::parse(&line).map_err(|e| parse_error(e.to_string()))?;
+                validator.check_object(&person);
+            }
+        },
+    }
+
+    Ok(validator.validation)
+}
+
+/// Checks the records of a file, one by one.
+struct Validator<'a> {
+    options: &'a ValidateOptions,
+    validation: Validation,
+}
+
+impl<'a> Validator<'a> {
+    fn new(options: &'a ValidateOptions) -> Validator<'a> {
+        Validator { options, validation: Validation::default() }
+    }
+
+    fn violation(&mut self, record: u64, column: &str, message: String) {
+        let violation = Violation { record, column: column.to_string(), message };
+        self.validation.report(self.options, violation);
+    }
+
+    /**
+     * Check a file's columns (or a JSON object's keys): that each is a
+     * field `peoplegen` writes, and that the required fields are all there.
+     * A CSV file's header is checked before any of its records, as record 0.
+     */
+    fn check_columns(&mut self, columns: &[String]) {
+        let record = self.validation.records;
+        let keys: Vec<Option<String>> = columns.iter().map(|c| header_key(c)).collect();
+
+        for (column, key) in columns.iter().zip(&keys) {
+            if key.is_none() {
+                self.violation(record, column, String::from("not a peoplegen column"));
+            }
+        }
+        for field in REQUIRED_FIELDS {
+            if !keys.iter().any(|k| k.as_deref() == Some(field)) {
+                self.violation(record, field, String::from("missing"));
+            }
+        }
+    }
+
+    /// Check a CSV or TSV row, whose columns are `headers`.
+    fn check_row(&mut self, headers: &[String], values: &[&str]) {
+        self.validation.records += 1;
+        let record = self.validation.records;
+
+        if values.len() != headers.len() {
+            self.violation(record, "*", format!("{} field(s), not {}", values.len(), headers.len()));
+        }
+        for (column, value) in headers.iter().zip(values) {
+            self.check_value(record, column, value);
+        }
+    }
+
+    /// Check a JSON object, whose keys are its columns.
+    fn check_object(&mut self, person: &JsonValue) {
+        self.validation.records += 1;
+        let record = self.validation.records;
+
+        if !person.is_object() {
+            self.violation(record, "*", String::from("not a JSON object"));
+            return;
+        }
+        let columns: Vec<String> = person.entries().map(|(k, _)| k.to_string()).collect();
+        self.check_columns(&columns);
+        for (column, value) in person.entries() {
+            // Numbers (IDs, salaries) can be written as numbers or strings.
+            let value = match value.as_str() {
+                Some(s) => s.to_string(),
+                None => value.dump(),
+            };
+            self.check_value(record, column, &value);
+        }
+    }
+
+    /// Check a single value, if its column is one whose values are checked.
+    fn check_value(&mut self, record: u64, column: &str, value: &str) {
+        let Some(key) = header_key(column) else { return };
+        let problem = match key.as_str() {
+            "id" => value.parse::<u64>().ok().filter(|id| *id > 0).is_none()
+                .then(|| format!("\"{}\" isn't a positive whole number", value)),
+            "ssn" => (!is_ssn(value)).then(|| format!("\"{}\" isn't an SSN", value)),
+            "salary" => self.check_salary(value),
+            k if DATE_FIELDS.contains(&k) => NaiveDate::parse_from_str(value, &self.options.date_format)
+                .is_err()
+                .then(|| format!("\"{}\" isn't a date in the format \"{}\"", value, self.options.date_format)),
+            k if TIMESTAMP_FIELDS.contains(&k) => NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT)
+                .is_err()
+                .then(|| format!("\"{}\" isn't a timestamp in the format \"{}\"", value, TIMESTAMP_FORMAT)),
+            _ => None,
+        };
+
+        if let Some(message) = problem {
+            self.violation(record, column, message);
+        }
+    }
+
+    fn check_salary(&self, value: &str) -> Option<String> {
+        let salary = match value.parse::<u64>() {
+            Ok(salary) => salary,
+            Err(_) => return Some(format!("\"{}\" isn't a whole number", value)),
+        };
+        if salary < self.options.salary_min {
+            return Some(format!("{} is less than {}", salary, self.options.salary_min));
+        }
+        match self.options.salary_max {
+            Some(max) if salary > max => Some(format!("{} is more than {}", salary, max)),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Whether a value is an SSN, in any of the formats `peoplegen` writes:
+ * dashed ("900-01-6789"), digits ("900016789") or masked ("***-**-6789").
+ * The area, group and serial number can't be all zeros.
+ */
+pub fn is_ssn(value: &str) -> bool {
+    let digits = |s: &str, n: usize| s.len() == n && s.bytes().all(|b| b.is_ascii_digit());
+    let nonzero = |s: &str| s.bytes().any(|b| b != b'0');
+    let parts: Vec<&str> = match value.split('-').collect::<Vec<&str>>()[..] {
+        [area, group, serial] => vec![area, group, serial],
+        [all] if digits(all, 9) => vec![&all[..3], &all[3..5], &all[5..]],
+        _ => return false,
+    };
+
+    match parts[..] {
+        ["***", "**", serial] => digits(serial, 4) && nonzero(serial),
+        [area, group, serial] => {
+            digits(area, 3) && digits(group, 2) && digits(serial, 4)
+                && nonzero(area) && nonzero(group) && nonzero(serial)
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validate::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn check(name: &str, contents: &str, options: &ValidateOptions) -> Validation {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        validate_file(&path, options).unwrap()
+    }
+
+    fn messages(validation: &Validation) -> Vec<String> {
+        validation.violations.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn ssns() {
+        for ssn in ["900-01-6789", "900016789", "***-**-6789"] {
+            assert!(is_ssn(ssn), "{ssn}");
+        }
+        for ssn in ["900-00-6789", "000-01-6789", "900-01-0000", "90-001-6789", "9000167890", "***-**-67a9", ""] {
+            assert!(!is_ssn(ssn), "{ssn}");
+        }
+    }
+
+    #[test]
+    fn valid_csv() {
+        let csv = "id,first_name,middle_name,last_name,gender,birth_date,ssn,salary\n\
+                   1,Moe,Harry,Howard,M,1897-06-19,900-01-0001,58260\n\
+                   2,Larry,,Fine,M,1902-10-05,900-01-0002,61000\n";
+        let validation = check("people.csv", csv, &ValidateOptions::default());
+        assert_eq!(validation.records, 2);
+        assert!(validation.is_valid(), "{:?}", validation.violations);
+    }
+
+    #[test]
+    fn invalid_csv() {
+        let csv = "ID,First Name,Last Name,Birth Date,SSN,Salary,Shoe Size\n\
+                   1,Moe,Howard,06/19/1897,900-01-0001,58260,11\n\
+                   x,Larry,Fine,1902-10-05,900-1-0002,-5,9\n\
+                   3,Curly,Howard\n";
+        let options = ValidateOptions { salary_max: Some(60000), ..Default::default() };
+        let validation = check("people.csv", csv, &options);
+        assert_eq!(validation.records, 3);
+        assert_eq!(messages(&validation), [
+            "header: Shoe Size: not a peoplegen column",
+            "header: middle_name: missing",
+            "record 1: Birth Date: \"06/19/1897\" isn't a date in the format \"%Y-%m-%d\"",
+            "record 2: ID: \"x\" isn't a positive whole number",
+            "record 2: SSN: \"900-1-0002\" isn't an SSN",
+            "record 2: Salary: \"-5\" isn't a whole number",
+            "record 3: *: 3 field(s), not 7",
+        ]);
+    }
+
+    #[test]
+    fn date_format_and_salary_bounds() {
+        let tsv = "firstName\tmiddleName\tlastName\tbirthDate\tsalary\n\
+                   Moe\tHarry\tHoward\t06/19/1897\t58260\n";
+        let options = ValidateOptions {
+            date_format: String::from("%m/%d/%Y"),
+            salary_min: 60000,
+            ..Default::default()
+        };
+        let validation = check("people.tsv", tsv, &options);
+        assert_eq!(messages(&validation), ["record 1: salary: 58260 is less than 60000"]);
+    }
+
+    #[test]
+    fn json_lines() {
+        let jsonl = "{\"first_name\":\"Moe\",\"middle_name\":\"Harry\",\"last_name\":\"Howard\",\
+                     \"birth_date\":\"1897-06-19\",\"salary\":58260,\"created_at\":\"2020-01-02T03:04:05Z\"}\n\
+                     {\"first_name\":\"Larry\",\"last_name\":\"Fine\",\"birth_date\":\"1902-10-05\",\
+                     \"created_at\":\"2020-01-02\"}\n";
+        let validation = check("people.jsonl", jsonl, &ValidateOptions::default());
+        assert_eq!(validation.records, 2);
+        assert_eq!(messages(&validation), [
+            "record 2: middle_name: missing",
+            "record 2: created_at: \"2020-01-02\" isn't a timestamp in the format \"%Y-%m-%dT%H:%M:%SZ\"",
+        ]);
+    }
+
+    #[test]
+    fn max_violations() {
+        let json = "{\"people\": [{\"salary\": \"a\"}, {\"salary\": \"b\"}]}";
+        let options = ValidateOptions { max_violations: 2, ..Default::default() };
+        let validation = check("people.json", json, &options);
+        assert_eq!(validation.total_violations, 10);
+        assert_eq!(validation.violations.len(), 2);
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("people.xlsx");
+        assert!(matches!(validate_file(&path, &ValidateOptions::default()), Err(PeoplegenError::Parse(_))));
+    }
+}
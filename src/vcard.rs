@@ -0,0 +1,147 @@
+//! vCard output: one vCard 3.0 card per person, for exercising contact-sync
+//! software with bulk address books. Version 3.0 is the one that address
+//! book software (Apple Contacts, Google Contacts, Outlook) imports most
+//! reliably. See RFC 2426.
+
+use std::io::{self, Write};
+
+/// Lines longer than this many bytes are folded.
+const MAX_LINE_BYTES: usize = 75;
+
+/// What goes into a card.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Contact {
+    pub first_name: String,
+    pub middle_name: String,
+    pub last_name: String,
+    /// The birth date, as YYYY-MM-DD, if it's known
+    pub birthday: Option<String>,
+    /// A unique ID for the card, if there is one
+    pub uid: Option<String>,
+}
+
+/**
+ * Writes vCards.
+ */
+pub struct VCardWriter<W: Write> {
+    w: W,
+}
+
+impl<W: Write> VCardWriter<W> {
+    /**
+     * Create a new writer.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the cards
+     */
+    pub fn new(w: W) -> VCardWriter<W> {
+        VCardWriter { w }
+    }
+
+    /**
+     * Write a card.
+     */
+    pub fn write_contact(&mut self, c: &Contact) -> io::Result<()> {
+        let full_name: Vec<&str> = [&c.first_name, &c.middle_name, &c.last_name]
+            .iter()
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        self.write_line("BEGIN:VCARD")?;
+        self.write_line("VERSION:3.0")?;
+        // Family; given; additional names; prefixes; suffixes
+        self.write_line(&format!(
+            "N:{};{};{};;",
+            escape(&c.last_name), escape(&c.first_name), escape(&c.middle_name)
+        ))?;
+        self.write_line(&format!("FN:{}", escape(&full_name.join(" "))))?;
+        if let Some(birthday) = &c.birthday {
+            self.write_line(&format!("BDAY:{birthday}"))?;
+        }
+        if let Some(uid) = &c.uid {
+            self.write_line(&format!("UID:{}", escape(uid)))?;
+        }
+        self.write_line("END:VCARD")
+    }
+
+    /**
+     * Flush the underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The flush failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    /// Write a content line, folded as necessary, with a CRLF.
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.w.write_all(fold(line).as_bytes())?;
+        self.w.write_all(b"\r\n")
+    }
+}
+
+/// Escape a text value: backslashes, commas, semicolons and newlines.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+     .replace(',', "\\,")
+     .replace(';', "\\;")
+     .replace('\n', "\\n")
+}
+
+/// Fold a content line, so no line is longer than `MAX_LINE_BYTES`:
+/// continuation lines start with a space. Lines are only broken between
+/// characters, never inside one.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_BYTES {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vcard::*;
+
+    #[test]
+    fn card() {
+        let mut w = VCardWriter::new(Vec::new());
+        w.write_contact(&Contact {
+            first_name: String::from("Moe"),
+            middle_name: String::new(),
+            last_name: String::from("Howard, Jr."),
+            birthday: Some(String::from("1897-06-19")),
+            uid: Some(String::from("1")),
+        }).unwrap();
+
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Howard\\, Jr.;Moe;;;\r\nFN:Moe Howard\\, Jr.\r\n\
+             BDAY:1897-06-19\r\nUID:1\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn folding() {
+        let line = format!("FN:{}", "é".repeat(40));
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|l| l.len() <= MAX_LINE_BYTES));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("FN:Moe"), "FN:Moe");
+    }
+}
@@ -0,0 +1,246 @@
+//! Vehicle identification numbers (VINs), for fleet-management and insurance
+//! datasets. A VIN is 17 characters, laid out as in North America:
+//!
+//! - 1-3: the World Manufacturer Identifier (WMI), from a weighted pool
+//! - 4-8: the vehicle descriptor section (model, body, engine and so on).
+//!   For North American WMIs, the seventh character is a letter for model
+//!   years from 2010 and a digit before, which is how the model year code
+//!   tells 1985 from 2015.
+//! - 9: the check digit (see `checksum::vin_check_char()`)
+//! - 10: the model year code, which comes round every 30 years
+//! - 11: the assembly plant
+//! - 12-17: the serial number
+//!
+//! The serial numbers are drawn from an offset (chosen with the seed) and the
+//! person's ID, so VINs are unique for up to `SERIALS` people. The rest comes
+//! from the generator's own random number generator, derived from the run's
+//! seed, so turning VINs on or off doesn't change any of the other generated
+//! values.
+
+use crate::checksum::{vin_check_char, VIN_CHARS};
+use crate::weighted::WeightedValues;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Mixed into the run's seed to seed the VIN random number generator.
+const VIN_SEED_SALT: u64 = 0x7665_6869_636c_6573;
+
+/// The default `--vin-wmis` pool: common manufacturers' WMIs, weighted
+/// roughly by how many of their vehicles are on American roads.
+pub const DEFAULT_VIN_WMIS: &str =
+    "1FT:10,1GC:9,1FA:7,1G1:7,4T1:6,1HG:5,1N4:5,JTD:5,KMH:5,2T1:4,JHM:4,5YJ:3,3VW:3,WBA:2,WVW:2";
+
+/// The first model year with 17-character VINs.
+pub const FIRST_MODEL_YEAR: u32 = 1981;
+
+/// The model year codes, from 1980 on: letters, then digits, then round
+/// again. `I`, `O`, `Q`, `U`, `Z` and `0` aren't used.
+const MODEL_YEAR_CODES: &[u8; 30] = b"ABCDEFGHJKLMNPRSTVWXY123456789";
+
+/// The year `MODEL_YEAR_CODES` starts at.
+const MODEL_YEAR_BASE: u32 = 1980;
+
+/// How many different serial numbers there are.
+const SERIALS: u64 = 1_000_000;
+
+/// Steps through the serial numbers. It has no factors in common with
+/// `SERIALS`, so the first `SERIALS` IDs all get different ones.
+const SERIAL_STRIDE: u64 = 381_967;
+
+/// The settings for VIN generation, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VinOptions {
+    /// The WMIs, with their relative weights
+    pub wmis: WeightedValues,
+    /// The earliest model year
+    pub model_year_min: u32,
+    /// The latest model year
+    pub model_year_max: u32,
+}
+
+/// Generates VINs.
+pub struct VinGenerator {
+    rng: ChaCha8Rng,
+    wmis: WeightedValues,
+    model_year_min: u32,
+    model_year_max: u32,
+    serial_offset: u64,
+}
+
+impl VinGenerator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The VIN settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &VinOptions, seed: u64) -> Result<VinGenerator, String> {
+        if options.model_year_min < FIRST_MODEL_YEAR {
+            return Err(format!("Model years start at {}, not {}.", FIRST_MODEL_YEAR, options.model_year_min));
+        }
+
+        if options.model_year_min > options.model_year_max {
+            return Err(format!(
+                "Minimum model year {} is after maximum model year {}.",
+                options.model_year_min, options.model_year_max
+            ));
+        }
+
+        if let Some(wmi) = options.wmis.values().iter().find(|wmi| !is_wmi(wmi)) {
+            return Err(format!(
+                "Bad WMI \"{wmi}\": it must be 3 digits and capital letters, other than I, O and Q."
+            ));
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed ^ VIN_SEED_SALT);
+        let serial_offset = rng.gen_range(0..SERIALS);
+
+        Ok(VinGenerator {
+            rng,
+            wmis: options.wmis.clone(),
+            model_year_min: options.model_year_min,
+            model_year_max: options.model_year_max,
+            serial_offset,
+        })
+    }
+
+    /**
+     * Create a generator with the default WMI pool, for a range of model
+     * years.
+     *
+     * # Arguments
+     *
+     * - `model_year_min`: The earliest model year
+     * - `model_year_max`: The latest model year
+     * - `seed`: The random number generator seed
+     */
+    pub fn with_model_years(model_year_min: u32, model_year_max: u32, seed: u64) -> Result<VinGenerator, String> {
+        let options = VinOptions {
+            wmis: WeightedValues::parse_list(DEFAULT_VIN_WMIS, "WMI")?,
+            model_year_min,
+            model_year_max,
+        };
+        VinGenerator::new(&options, seed)
+    }
+
+    /**
+     * Generate the VIN for a person (or anything else with an ID).
+     *
+     * # Arguments
+     *
+     * - `id`: The ID, which picks the serial number
+     */
+    pub fn vin(&mut self, id: u64) -> String {
+        let wmi = self.wmis.choose(&mut self.rng);
+        let model_year = self.rng.gen_range(self.model_year_min..=self.model_year_max);
+        let north_american = matches!(wmi.as_bytes()[0], b'1'..=b'5');
+        let mut vin = String::with_capacity(17);
+        vin.push_str(wmi);
+
+        for position in 4..=8 {
+            let chars = match position {
+                7 if north_american && model_year >= 2010 => &VIN_CHARS[10..],
+                7 if north_american => &VIN_CHARS[..10],
+                _ => VIN_CHARS,
+            };
+            vin.push(self.pick(chars));
+        }
+
+        // A placeholder, until the rest is there to check.
+        vin.push('0');
+        vin.push(char::from(MODEL_YEAR_CODES[((model_year - MODEL_YEAR_BASE) % 30) as usize]));
+        vin.push(self.pick(VIN_CHARS));
+        let serial = (self.serial_offset + id % SERIALS * SERIAL_STRIDE) % SERIALS;
+        vin.push_str(&format!("{serial:06}"));
+
+        // Every character is a VIN character, so there is a check digit.
+        let check = vin_check_char(&vin).unwrap();
+        vin.replace_range(8..9, &check.to_string());
+        vin
+    }
+
+    /// Pick one of some (ASCII) characters at random.
+    fn pick(&mut self, chars: &str) -> char {
+        char::from(chars.as_bytes()[self.rng.gen_range(0..chars.len())])
+    }
+}
+
+/// Get the model year a VIN's model year code stands for, in the 30 years
+/// from `first_year` (which is 1980 or later), or `None` if it doesn't have
+/// one.
+pub fn model_year(vin: &str, first_year: u32) -> Option<u32> {
+    let code = *vin.as_bytes().get(9)?;
+    let index = MODEL_YEAR_CODES.iter().position(|c| *c == code)? as u32;
+    let offset = (index + 30 - first_year.checked_sub(MODEL_YEAR_BASE)? % 30) % 30;
+    Some(first_year + offset)
+}
+
+/// Whether a string is a possible WMI: three VIN characters.
+fn is_wmi(s: &str) -> bool {
+    s.len() == 3 && s.chars().all(|c| VIN_CHARS.contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::vin_valid;
+    use crate::vin::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn vins() {
+        let mut g = VinGenerator::with_model_years(2005, 2024, 42).unwrap();
+        let mut seen = HashSet::new();
+        let mut years = HashSet::new();
+
+        for id in 1..=10000 {
+            let vin = g.vin(id);
+            assert_eq!(vin.len(), 17, "{vin}");
+            assert!(vin_valid(&vin), "{vin}");
+            assert!(g.wmis.values().iter().any(|wmi| vin.starts_with(&**wmi)), "{vin}");
+            let year = model_year(&vin, 2005).unwrap();
+            assert!((2005..=2024).contains(&year), "{vin}");
+            years.insert(year);
+            if vin.starts_with(['1', '2', '3', '4', '5']) {
+                let seventh = vin.as_bytes()[6];
+                assert_eq!(seventh.is_ascii_alphabetic(), year >= 2010, "{vin}");
+            }
+            assert!(seen.insert(vin[11..].to_string()), "{vin}");
+        }
+        assert_eq!(years.len(), 20);
+
+        let mut again = VinGenerator::with_model_years(2005, 2024, 42).unwrap();
+        let mut other = VinGenerator::with_model_years(2005, 2024, 43).unwrap();
+        assert_eq!(again.vin(1), VinGenerator::with_model_years(2005, 2024, 42).unwrap().vin(1));
+        assert_ne!(again.vin(2), other.vin(2));
+    }
+
+    #[test]
+    fn model_years() {
+        assert_eq!(model_year("1M8GDM9AXKP042788", 1981), Some(1989));
+        assert_eq!(model_year("1M8GDM9AXKP042788", 2000), Some(2019));
+        assert_eq!(model_year("1HGCM82633A004352", 1990), Some(2003));
+        assert_eq!(model_year("1HGCM8263", 1990), None);
+    }
+
+    #[test]
+    fn bad_options() {
+        assert!(VinGenerator::with_model_years(1975, 2000, 42).is_err());
+        assert!(VinGenerator::with_model_years(2020, 2010, 42).is_err());
+
+        let options = |wmis: &str| VinOptions {
+            wmis: WeightedValues::parse_list(wmis, "WMI").unwrap(),
+            model_year_min: 2000,
+            model_year_max: 2020,
+        };
+        assert!(VinGenerator::new(&options("1FT,WBA:2"), 42).is_ok());
+        assert!(VinGenerator::new(&options("1FTX"), 42).is_err());
+        assert!(VinGenerator::new(&options("1OT"), 42).is_err());
+    }
+}
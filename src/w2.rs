@@ -0,0 +1,337 @@
+//! W-2 style wage and tax statements, for testing tax software. Each person
+//! gets one statement per tax year, from one employer, with the federal
+//! boxes filled in:
+//!
+//! - Box 1, wages: the salary, less the 401(k) deferral in box 12 (code D)
+//! - Box 2, federal income tax withheld: a withholding rate, from 8% to 22%,
+//!   of the wages
+//! - Boxes 3 and 4, Social Security wages and tax: the salary, up to the
+//!   year's wage base, and 6.2% of it
+//! - Boxes 5 and 6, Medicare wages and tax: the salary, and 1.45% of it,
+//!   plus the additional 0.9% on anything over $200,000
+//!
+//! The latest tax year's salary is the person's salary; earlier years' are
+//! smaller, by a raise of 0% to 5% a year. The employers' EINs start with
+//! prefixes the IRS doesn't assign, so they can't be anyone's real number.
+//!
+//! The statements come from their own random number generator, derived
+//! from the run's seed, so turning them on or off doesn't change any of the
+//! other generated values.
+
+use std::fs::File;
+use std::path::Path;
+
+use csv::{Writer, WriterBuilder};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::path::path_str;
+
+/// Mixed into the run's seed to seed the statements' random number
+/// generator.
+const W2_SEED_SALT: u64 = 0x7732_2d66_6f72_6d73;
+
+/// The Social Security wage base for each tax year: the most wages that
+/// year that are taxed.
+const WAGE_BASES: [(i32, u64); 12] = [
+    (2015, 118_500),
+    (2016, 118_500),
+    (2017, 127_200),
+    (2018, 128_400),
+    (2019, 132_900),
+    (2020, 137_700),
+    (2021, 142_800),
+    (2022, 147_000),
+    (2023, 160_200),
+    (2024, 168_600),
+    (2025, 176_100),
+    (2026, 184_500),
+];
+
+/// The first and last tax years there are wage bases for.
+pub const FIRST_TAX_YEAR: i32 = WAGE_BASES[0].0;
+pub const LAST_TAX_YEAR: i32 = WAGE_BASES[WAGE_BASES.len() - 1].0;
+
+/// EIN prefixes the IRS doesn't assign.
+const FAKE_EIN_PREFIXES: [u32; 14] = [7, 8, 9, 17, 18, 19, 28, 29, 49, 69, 70, 78, 79, 89];
+
+/// How many employers the people work for.
+const EMPLOYERS: usize = 25;
+
+/// The tax rates, in hundredths of a percent.
+const SOCIAL_SECURITY_RATE: u64 = 620;
+const MEDICARE_RATE: u64 = 145;
+const ADDITIONAL_MEDICARE_RATE: u64 = 90;
+const ADDITIONAL_MEDICARE_THRESHOLD: u64 = 200_000;
+
+const HEADER: [&str; 10] = [
+    "id",
+    "tax_year",
+    "employer_ein",
+    "wages",
+    "federal_income_tax",
+    "social_security_wages",
+    "social_security_tax",
+    "medicare_wages",
+    "medicare_tax",
+    "box_12d_401k",
+];
+
+/// The settings for W-2 statements, from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct W2Options {
+    /// The latest tax year
+    pub tax_year: i32,
+    /// How many tax years, back from `tax_year`, there are statements for
+    pub years: u32,
+}
+
+/// One statement. The amounts are in cents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct W2 {
+    pub tax_year: i32,
+    pub employer_ein: String,
+    pub wages: u64,
+    pub federal_income_tax: u64,
+    pub social_security_wages: u64,
+    pub social_security_tax: u64,
+    pub medicare_wages: u64,
+    pub medicare_tax: u64,
+    pub deferral_401k: u64,
+}
+
+/// Generates W-2 statements.
+pub struct W2Generator {
+    rng: ChaCha8Rng,
+    tax_year: i32,
+    years: u32,
+    employers: Vec<String>,
+}
+
+impl W2Generator {
+    /**
+     * Create a new generator.
+     *
+     * # Arguments
+     *
+     * - `options`: The statement settings
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(generator)`: The generator
+     * - `Err(msg)`: The settings are invalid; `msg` explains why.
+     */
+    pub fn new(options: &W2Options, seed: u64) -> Result<W2Generator, String> {
+        let first_year = options.tax_year - options.years as i32 + 1;
+        if options.years == 0 || first_year < FIRST_TAX_YEAR || options.tax_year > LAST_TAX_YEAR {
+            return Err(format!(
+                "W-2 tax years must be from {} to {}, not {} to {}.",
+                FIRST_TAX_YEAR, LAST_TAX_YEAR, first_year, options.tax_year
+            ));
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed ^ W2_SEED_SALT);
+        let employers = (0..EMPLOYERS)
+            .map(|_| {
+                let prefix = FAKE_EIN_PREFIXES[rng.gen_range(0..FAKE_EIN_PREFIXES.len())];
+                format!("{:02}-{:07}", prefix, rng.gen_range(0..10_000_000))
+            })
+            .collect();
+
+        Ok(W2Generator { rng, tax_year: options.tax_year, years: options.years, employers })
+    }
+
+    /**
+     * Generate the next person's statements.
+     *
+     * # Arguments
+     *
+     * - `salary`: The person's salary, in dollars, in the latest tax year
+     *
+     * # Returns
+     *
+     * The statements, from the earliest tax year to the latest.
+     */
+    pub fn next_statements(&mut self, salary: u64) -> Vec<W2> {
+        let employer = self.employers[self.rng.gen_range(0..self.employers.len())].clone();
+        // 401(k) deferrals and withholding are per person, in tenths of a
+        // percent.
+        let deferral_rate = if self.rng.gen_bool(0.6) { self.rng.gen_range(10..=100) } else { 0 };
+        let withholding_rate = self.rng.gen_range(80..=220);
+        let mut salary = salary * 100;
+        let mut statements = Vec::new();
+
+        for tax_year in (self.tax_year - self.years as i32 + 1..=self.tax_year).rev() {
+            let deferral = salary * deferral_rate / 1000;
+            let wages = salary - deferral;
+            let social_security_wages = salary.min(wage_base(tax_year) * 100);
+            let additional = salary.saturating_sub(ADDITIONAL_MEDICARE_THRESHOLD * 100);
+
+            statements.push(W2 {
+                tax_year,
+                employer_ein: employer.clone(),
+                wages,
+                federal_income_tax: wages * withholding_rate / 1000,
+                social_security_wages,
+                social_security_tax: social_security_wages * SOCIAL_SECURITY_RATE / 10_000,
+                medicare_wages: salary,
+                medicare_tax: (salary * MEDICARE_RATE + additional * ADDITIONAL_MEDICARE_RATE) / 10_000,
+                deferral_401k: deferral,
+            });
+
+            // Last year's salary, before this year's raise (in hundredths of
+            // a percent).
+            let raise = self.rng.gen_range(0..=500);
+            salary = salary * 10_000 / (10_000 + raise);
+        }
+
+        statements.reverse();
+        statements
+    }
+}
+
+/// Get the Social Security wage base for a tax year (which has one).
+fn wage_base(tax_year: i32) -> u64 {
+    WAGE_BASES.iter().find(|(year, _)| *year == tax_year).map_or(0, |(_, base)| *base)
+}
+
+/// Format an amount of cents as dollars and cents, e.g., "1234.50".
+fn dollars(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+/// Writes people's W-2 statements to a CSV file, one row per person per tax
+/// year.
+pub struct W2Writer {
+    generator: W2Generator,
+    writer: Writer<File>,
+    path: String,
+    /// How many statements have been written
+    pub total: u64,
+}
+
+impl W2Writer {
+    /**
+     * Start a statements file.
+     *
+     * # Arguments
+     *
+     * - `options`: The statement settings
+     * - `path`: Where to write the statements
+     * - `seed`: The run's random number generator seed
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The writer, with the header written
+     * - `Err(msg)`: The settings are invalid, or the file can't be written.
+     */
+    pub fn new(options: &W2Options, path: &Path, seed: u64) -> Result<W2Writer, String> {
+        let generator = W2Generator::new(options, seed)?;
+        let path = path_str(path).to_string();
+        let write_err = |e: csv::Error| format!("Can't write to \"{}\": {}", path, e);
+        let mut writer = WriterBuilder::new().from_path(&path).map_err(write_err)?;
+        writer.write_record(HEADER).map_err(write_err)?;
+
+        Ok(W2Writer { generator, writer, path, total: 0 })
+    }
+
+    /**
+     * Generate and write a person's statements.
+     *
+     * # Arguments
+     *
+     * - `id`: The person's ID
+     * - `salary`: The person's salary, in dollars
+     */
+    pub fn write(&mut self, id: u64, salary: u64) -> Result<(), String> {
+        let statements = self.generator.next_statements(salary);
+
+        for w2 in &statements {
+            self.writer
+                .write_record([
+                    id.to_string(),
+                    w2.tax_year.to_string(),
+                    w2.employer_ein.clone(),
+                    dollars(w2.wages),
+                    dollars(w2.federal_income_tax),
+                    dollars(w2.social_security_wages),
+                    dollars(w2.social_security_tax),
+                    dollars(w2.medicare_wages),
+                    dollars(w2.medicare_tax),
+                    dollars(w2.deferral_401k),
+                ])
+                .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        }
+
+        self.total += statements.len() as u64;
+        Ok(())
+    }
+
+    /// Finish the file, and return how many statements are in it.
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Can't write to \"{}\": {}", self.path, e))?;
+        Ok(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::w2::*;
+
+    fn generator(tax_year: i32, years: u32) -> W2Generator {
+        W2Generator::new(&W2Options { tax_year, years }, 42).unwrap()
+    }
+
+    #[test]
+    fn statements() {
+        let mut g = generator(2024, 3);
+
+        for salary in [30_000, 58_260, 150_000, 250_000] {
+            let statements = g.next_statements(salary);
+            assert_eq!(statements.iter().map(|w| w.tax_year).collect::<Vec<_>>(), [2022, 2023, 2024]);
+            assert_eq!(statements[2].medicare_wages, salary * 100);
+            assert!(statements.windows(2).all(|w| w[0].medicare_wages <= w[1].medicare_wages));
+
+            for w in &statements {
+                assert_eq!(w.employer_ein, statements[0].employer_ein);
+                let prefix: u32 = w.employer_ein[..2].parse().unwrap();
+                assert!(FAKE_EIN_PREFIXES.contains(&prefix), "{w:?}");
+                assert_eq!(w.wages + w.deferral_401k, w.medicare_wages);
+                assert!(w.social_security_wages <= wage_base(w.tax_year) * 100);
+                assert_eq!(w.social_security_tax, w.social_security_wages * 62 / 1000);
+                assert!(w.federal_income_tax >= w.wages * 8 / 100 && w.federal_income_tax <= w.wages * 22 / 100);
+            }
+        }
+
+        assert_eq!(g.next_statements(250_000)[2].social_security_wages, 16_860_000);
+        // 1.45% of $250,000, and 0.9% of $50,000.
+        assert_eq!(generator(2024, 1).next_statements(250_000)[0].medicare_tax, 362_500 + 45_000);
+    }
+
+    #[test]
+    fn seeds() {
+        let statements = |seed| {
+            let mut g = W2Generator::new(&W2Options { tax_year: 2024, years: 2 }, seed).unwrap();
+            (0..10).map(|_| g.next_statements(60_000)).collect::<Vec<_>>()
+        };
+        assert_eq!(statements(42), statements(42));
+        assert_ne!(statements(42), statements(43));
+    }
+
+    #[test]
+    fn bad_options() {
+        assert!(W2Generator::new(&W2Options { tax_year: 2016, years: 3 }, 42).is_err());
+        assert!(W2Generator::new(&W2Options { tax_year: 2030, years: 1 }, 42).is_err());
+        assert!(W2Generator::new(&W2Options { tax_year: 2024, years: 0 }, 42).is_err());
+        assert!(W2Generator::new(&W2Options { tax_year: 2024, years: 10 }, 42).is_ok());
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(dollars(123_450), "1234.50");
+        assert_eq!(dollars(7), "0.07");
+    }
+}
@@ -0,0 +1,347 @@
+//! Weighted value files: lists of values to choose from at random, each
+//! with an optional weight. This is the format of the names files, and of
+//! any other file of choices.
+//!
+//! Each line is a value, optionally followed by a comma and a weight:
+//!
+//! ```text
+//! # Blank lines and lines starting with "#" are ignored.
+//! Smith,2442977
+//! Johnson,1932812
+//! Williams
+//! ```
+//!
+//! A value without a weight has a weight of 1, so a plain list of values is
+//! a valid file, in which every value is equally likely. Otherwise, a value
+//! is chosen in proportion to its weight. Weights can be any non-negative
+//! number (counts, percentages, fractions), since they're normalized; a
+//! value that appears more than once gets the total of its weights.
+//!
+//! ```
+//! use peoplegen::weighted::WeightedValues;
+//! use rand::SeedableRng;
+//! use rand::rngs::StdRng;
+//!
+//! let values = WeightedValues::parse("heads,3\ntails,1\n".as_bytes()).unwrap();
+//! let mut rng = StdRng::seed_from_u64(42);
+//!
+//! assert_eq!(values.len(), 2);
+//! assert_eq!(values.probability(0), 0.75);
+//! let value: &str = values.choose(&mut rng);
+//! assert!(["heads", "tails"].contains(&value));
+//! ```
+//!
+//! Options that take their choices on the command line (`--loyalty-tiers`,
+//! say) give them as a list instead, `value:weight,value:weight,...`, which
+//! `WeightedValues::parse_list()` reads, with the same rules for weights.
+//!
+//! The values are shared (`Arc<str>`), so a value that's chosen a million
+//! times is still only stored once: cloning a chosen value just counts
+//! another reference to it.
+
+use rand::Rng;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A list of values, with the probability of choosing each one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedValues {
+    values: Vec<Arc<str>>,
+    weights: Vec<f64>,
+    /// The cumulative probabilities, or `None` if every value is equally
+    /// likely
+    cumulative: Option<Vec<f64>>,
+}
+
+impl WeightedValues {
+    /**
+     * Create a list in which every value is equally likely.
+     *
+     * # Arguments
+     *
+     * - `values`: The values. There has to be at least one.
+     */
+    pub fn uniform(values: Vec<String>) -> WeightedValues {
+        let weights = vec![1.0; values.len()];
+        WeightedValues { values: values.into_iter().map(Arc::from).collect(), weights, cumulative: None }
+    }
+
+    /**
+     * Create a list from values and their weights.
+     *
+     * # Arguments
+     *
+     * - `weighted`: The values and their weights
+     *
+     * # Returns
+     *
+     * - `Ok(values)`: The list
+     * - `Err(msg)`: There are no values, a weight is negative or not a
+     *   number, or the weights are all zero; `msg` explains why.
+     */
+    pub fn new(weighted: Vec<(String, f64)>) -> Result<WeightedValues, String> {
+        if weighted.is_empty() {
+            return Err(String::from("No values."));
+        }
+        if let Some((v, w)) = weighted.iter().find(|(_, w)| !w.is_finite() || *w < 0.0) {
+            return Err(format!("Bad weight {w} for \"{v}\": weights must be non-negative numbers."));
+        }
+
+        let total: f64 = weighted.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            return Err(String::from("The weights add up to zero."));
+        }
+
+        // The same weight for every value needs no arithmetic when choosing.
+        let uniform = weighted.iter().all(|(_, w)| *w == weighted[0].1);
+        let mut sum = 0.0;
+        let cumulative: Vec<f64> = weighted.iter().map(|(_, w)| { sum += w / total; sum }).collect();
+        let (values, weights) = weighted.into_iter().map(|(v, w)| (Arc::from(v), w)).unzip();
+
+        Ok(WeightedValues { values, weights, cumulative: if uniform { None } else { Some(cumulative) } })
+    }
+
+    /**
+     * Parse a weighted value file.
+     *
+     * # Arguments
+     *
+     * - `r`: Where to read the file from
+     *
+     * # Returns
+     *
+     * - `Ok(values)`: The values
+     * - `Err(msg)`: The file couldn't be read or is invalid; `msg` explains
+     *   why.
+     */
+    pub fn parse<R: BufRead>(r: R) -> Result<WeightedValues, String> {
+        let mut weighted: Vec<(String, f64)> = Vec::new();
+
+        for (i, line) in r.lines().enumerate() {
+            let line = line.map_err(|e| format!("{}", e))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = match line.rsplit_once(',') {
+                None => (line.to_string(), 1.0),
+                Some((value, weight)) => {
+                    let weight = weight.trim().parse::<f64>()
+                        .map_err(|_| format!("Line {}: bad weight \"{}\".", i + 1, weight.trim()))?;
+                    (value.trim().to_string(), weight)
+                },
+            };
+
+            if entry.0.is_empty() {
+                return Err(format!("Line {}: empty value.", i + 1));
+            }
+            weighted.push(entry);
+        }
+
+        WeightedValues::new(weighted)
+    }
+
+    /**
+     * Parse a list of values given on the command line, of the form
+     * `value:weight,value:weight,...`. A value without a weight has a
+     * weight of 1.
+     *
+     * # Arguments
+     *
+     * - `s`: The list
+     * - `what`: What the values are, for error messages (e.g., "loyalty
+     *   tier")
+     *
+     * # Returns
+     *
+     * - `Ok(values)`: The values
+     * - `Err(msg)`: The list is invalid; `msg` explains why.
+     */
+    pub fn parse_list(s: &str, what: &str) -> Result<WeightedValues, String> {
+        let weighted = s.split(',')
+            .map(|entry| {
+                let (value, weight) = match entry.split_once(':') {
+                    None => (entry.trim(), 1.0),
+                    Some((value, weight)) => {
+                        let weight = weight.trim().parse::<f64>()
+                            .map_err(|_| format!("Bad weight \"{}\" for {} \"{}\".", weight.trim(), what, value.trim()))?;
+                        (value.trim(), weight)
+                    },
+                };
+
+                if value.is_empty() {
+                    Err(format!("Empty {what} in \"{s}\"."))
+                }
+                else {
+                    Ok((value.to_string(), weight))
+                }
+            })
+            .collect::<Result<Vec<(String, f64)>, String>>()?;
+
+        WeightedValues::new(weighted).map_err(|e| format!("Bad {what} weights in \"{s}\": {e}"))
+    }
+
+    /**
+     * Read a weighted value file.
+     *
+     * # Arguments
+     *
+     * - `path`: The file
+     *
+     * # Returns
+     *
+     * - `Ok(values)`: The values
+     * - `Err(msg)`: The file couldn't be read or is invalid; `msg` explains
+     *   why, and names the file.
+     */
+    pub fn read(path: &Path) -> Result<WeightedValues, String> {
+        let err = |msg: String| format!("\"{}\": {}", path.display(), msg);
+        let file = File::open(path).map_err(|e| err(e.to_string()))?;
+        WeightedValues::parse(BufReader::new(file)).map_err(err)
+    }
+
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether there are no values. (Lists built with `new()` or `parse()`
+    /// always have some.)
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The values, in order.
+    pub fn values(&self) -> &[Arc<str>] {
+        &self.values
+    }
+
+    /// The values that appear more than once, each once, in the order they
+    /// first appear.
+    pub fn duplicates(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for value in &self.values {
+            if !seen.insert(&**value) && !duplicates.contains(&&**value) {
+                duplicates.push(&**value);
+            }
+        }
+        duplicates
+    }
+
+    /// The weight of the value at an index, as given.
+    pub fn weight(&self, index: usize) -> f64 {
+        self.weights[index]
+    }
+
+    /// The probability of choosing the value at an index.
+    pub fn probability(&self, index: usize) -> f64 {
+        match &self.cumulative {
+            None => 1.0 / self.values.len() as f64,
+            Some(c) if index == 0 => c[0],
+            Some(c) => c[index] - c[index - 1],
+        }
+    }
+
+    /**
+     * Choose a value at random. When every value is equally likely, this
+     * draws exactly one number from `rng`, in `0..len()`.
+     */
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> &Arc<str> {
+        &self.values[self.choose_index(rng)]
+    }
+
+    /**
+     * Choose a value at random, as `choose()` does, but get its index, for
+     * lists whose order means something (tiers, lowest first, say).
+     */
+    pub fn choose_index<R: Rng>(&self, rng: &mut R) -> usize {
+        match &self.cumulative {
+            None => rng.gen_range(0..self.values.len()),
+            Some(c) => {
+                let r: f64 = rng.gen();
+                c.partition_point(|p| *p <= r).min(self.values.len() - 1)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::weighted::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn parsing() {
+        let values = WeightedValues::parse("# Comment\nSmith, 3\n\nJones,1\nBrown,0\n".as_bytes()).unwrap();
+        assert_eq!(values.values(), [Arc::from("Smith"), Arc::from("Jones"), Arc::from("Brown")]);
+        assert_eq!(values.probability(0), 0.75);
+        assert_eq!(values.probability(2), 0.0);
+
+        let plain = WeightedValues::parse("Moe\nLarry\n".as_bytes()).unwrap();
+        assert_eq!(plain, WeightedValues::uniform(vec![String::from("Moe"), String::from("Larry")]));
+
+        let bad = |s: &str| WeightedValues::parse(s.as_bytes()).unwrap_err();
+        assert!(bad("").contains("No values"));
+        assert!(bad("a,x\n").contains("Line 1: bad weight \"x\""));
+        assert!(bad("a,\n").contains("bad weight"));
+        assert!(bad("a,-1\n").contains("non-negative"));
+        assert!(bad("a,0\nb,0\n").contains("zero"));
+        assert!(bad(",2\n").contains("empty value"));
+    }
+
+    #[test]
+    fn lists() {
+        let values = WeightedValues::parse_list("Bronze:3, Silver,Gold:0", "tier").unwrap();
+        assert_eq!(values.values(), [Arc::from("Bronze"), Arc::from("Silver"), Arc::from("Gold")]);
+        assert_eq!(values.probability(0), 0.75);
+        assert_eq!(values.probability(2), 0.0);
+
+        let bad = |s: &str| WeightedValues::parse_list(s, "tier").unwrap_err();
+        assert_eq!(bad("Bronze:x"), "Bad weight \"x\" for tier \"Bronze\".");
+        assert_eq!(bad("Bronze,"), "Empty tier in \"Bronze,\".");
+        assert!(bad("Bronze:-1").contains("non-negative"));
+        assert!(bad("Bronze:0").contains("zero"));
+    }
+
+    #[test]
+    fn duplicates() {
+        let values = WeightedValues::parse("Moe\nLarry\nMoe,2\nCurly\nMoe\nLarry\n".as_bytes()).unwrap();
+        assert_eq!(values.duplicates(), ["Moe", "Larry"]);
+        assert!(WeightedValues::parse("Moe\nLarry\n".as_bytes()).unwrap().duplicates().is_empty());
+    }
+
+    #[test]
+    fn choosing() {
+        let values = WeightedValues::parse("a,9\nb,1\nc,0\n".as_bytes()).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let chosen: Vec<&Arc<str>> = (0..10_000).map(|_| values.choose(&mut rng)).collect();
+        let share = |v: &str| chosen.iter().filter(|c| &c[..] == v).count() as f64 / 10_000.0;
+
+        assert!((share("a") - 0.9).abs() < 0.02);
+        assert_eq!(share("c"), 0.0);
+
+        // Equally likely values are chosen with gen_range(), as a plain
+        // list would be.
+        let uniform = WeightedValues::uniform(vec![String::from("x"), String::from("y")]);
+        let (mut r1, mut r2) = (StdRng::seed_from_u64(7), StdRng::seed_from_u64(7));
+        for _ in 0..10 {
+            assert_eq!(uniform.choose(&mut r1), &uniform.values()[r2.gen_range(0..2)]);
+        }
+    }
+
+    #[test]
+    fn shared() {
+        let values = WeightedValues::uniform(vec![String::from("Moe")]);
+        let mut rng = StdRng::seed_from_u64(42);
+        let chosen: Vec<Arc<str>> = (0..100).map(|_| values.choose(&mut rng).clone()).collect();
+
+        assert!(chosen.iter().all(|c| Arc::ptr_eq(c, &values.values()[0])));
+        assert_eq!(Arc::strong_count(&values.values()[0]), 101);
+    }
+}
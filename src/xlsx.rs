@@ -0,0 +1,408 @@
+//! A minimal writer for Excel (XLSX) workbooks.
+//!
+//! Only what `peoplegen` needs is supported: a single worksheet with a bold,
+//! shaded, frozen header row, followed by rows of text, number and date
+//! cells. Text is written as inline strings (so rows can be streamed without
+//! building a shared strings table), which also keeps Excel from "helpfully"
+//! converting values like SSNs into numbers. The workbook's parts are stored
+//! uncompressed in the zip container. See ECMA-376, Part 1.
+
+use chrono::NaiveDate;
+use crate::png::crc32_update;
+use std::io::{self, Write};
+
+/// The most rows an Excel worksheet can hold, including the header row.
+pub const MAX_ROWS: u64 = 1_048_576;
+
+// Zip signatures and settings. Everything is "stored" (method 0), and every
+// entry gets the same timestamp (1980-01-01 00:00), so output is
+// reproducible.
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIR_SIG: u32 = 0x0605_4b50;
+const ZIP_VERSION: u16 = 20;
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const DOS_DATE: u16 = 0x0021;
+
+// Cell style indexes, from STYLES.
+const STYLE_HEADER: u8 = 1;
+const STYLE_DATE: u8 = 2;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="People" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+// Style 0 is the default, 1 is the header (bold, shaded) and 2 is a date
+// (yyyy-mm-dd, like the other output formats).
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="yyyy\-mm\-dd"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="3"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill><fill><patternFill patternType="solid"><fgColor rgb="FFD9D9D9"/><bgColor indexed="64"/></patternFill></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="3"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="2" borderId="0" xfId="0" applyFont="1" applyFill="1"/><xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+const SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#;
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+const SHEET_NAME: &str = "xl/worksheets/sheet1.xml";
+
+/// A worksheet cell value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+}
+
+/**
+ * Writes rows to a single-sheet Excel workbook. The worksheet is streamed
+ * into the zip container as rows arrive.
+ */
+pub struct XlsxWriter<W: Write> {
+    w: W,
+    // The number of bytes written so far.
+    pos: u64,
+    entries: Vec<ZipEntry>,
+    // The worksheet entry, while it's being written.
+    sheet: ZipEntry,
+    rows: u64,
+}
+
+/// What the zip central directory needs to know about an entry.
+#[derive(Clone, Copy)]
+struct ZipEntry {
+    name: &'static str,
+    flags: u16,
+    crc: u32,
+    size: u64,
+    offset: u64,
+}
+
+impl<W: Write> XlsxWriter<W> {
+    /**
+     * Create a new writer, writing everything but the worksheet rows, and
+     * then the header row.
+     *
+     * # Arguments
+     *
+     * - `w`: Where to write the workbook
+     * - `names`: The column names, in order
+     *
+     * # Returns
+     *
+     * - `Ok(writer)`: The header was written.
+     * - `Err(e)`: The write failed.
+     */
+    pub fn new(w: W, names: &[&String]) -> io::Result<XlsxWriter<W>> {
+        let mut x = XlsxWriter {
+            w,
+            pos: 0,
+            entries: Vec::new(),
+            sheet: ZipEntry {
+                name: SHEET_NAME,
+                flags: FLAG_DATA_DESCRIPTOR,
+                crc: 0,
+                size: 0,
+                offset: 0,
+            },
+            rows: 0,
+        };
+
+        x.write_entry("[Content_Types].xml", CONTENT_TYPES.as_bytes())?;
+        x.write_entry("_rels/.rels", ROOT_RELS.as_bytes())?;
+        x.write_entry("xl/workbook.xml", WORKBOOK.as_bytes())?;
+        x.write_entry("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes())?;
+        x.write_entry("xl/styles.xml", STYLES.as_bytes())?;
+
+        // The worksheet's size and CRC aren't known until it's finished, so
+        // they go in a data descriptor after the data.
+        x.sheet.offset = x.pos;
+        let header = local_header(SHEET_NAME, FLAG_DATA_DESCRIPTOR, 0, 0);
+        x.write(&header)?;
+        x.write_sheet(SHEET_START.as_bytes())?;
+
+        let header_row: Vec<Cell> = names.iter().map(|n| Cell::Text(n.to_string())).collect();
+        x.write_cells(&header_row, true)?;
+        Ok(x)
+    }
+
+    /**
+     * Write a row. The values must be in column order.
+     */
+    pub fn write_row(&mut self, cells: &[Cell]) -> io::Result<()> {
+        self.write_cells(cells, false)
+    }
+
+    /**
+     * Finish the worksheet, write the zip central directory, and flush the
+     * underlying writer.
+     *
+     * # Returns
+     *
+     * - `Ok(w)`: The underlying writer
+     * - `Err(e)`: The write failed.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_sheet(SHEET_END.as_bytes())?;
+        if self.sheet.size > u32::MAX as u64 {
+            return Err(io::Error::other("worksheet is too large for a zip file"));
+        }
+
+        let mut descriptor: Vec<u8> = Vec::new();
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIG);
+        put_u32(&mut descriptor, self.sheet.crc);
+        put_u32(&mut descriptor, self.sheet.size as u32);
+        put_u32(&mut descriptor, self.sheet.size as u32);
+        self.write(&descriptor)?;
+
+        self.entries.push(self.sheet);
+
+        let directory_start = self.pos;
+        let mut directory: Vec<u8> = Vec::new();
+        for e in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER_SIG);
+            put_u16(&mut directory, ZIP_VERSION);
+            put_u16(&mut directory, ZIP_VERSION);
+            put_u16(&mut directory, e.flags);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, DOS_DATE);
+            put_u32(&mut directory, e.crc);
+            put_u32(&mut directory, e.size as u32);
+            put_u32(&mut directory, e.size as u32);
+            put_u16(&mut directory, e.name.len() as u16);
+            // Extra field length, comment length, disk number, and internal
+            // and external attributes.
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u32(&mut directory, 0);
+            put_u32(&mut directory, e.offset as u32);
+            directory.extend(e.name.as_bytes());
+        }
+
+        let count = self.entries.len() as u16;
+        let directory_size = directory.len() as u32;
+        put_u32(&mut directory, END_OF_CENTRAL_DIR_SIG);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, count);
+        put_u16(&mut directory, count);
+        put_u32(&mut directory, directory_size);
+        put_u32(&mut directory, directory_start as u32);
+        put_u16(&mut directory, 0);
+        self.write(&directory)?;
+
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn write_cells(&mut self, cells: &[Cell], header: bool) -> io::Result<()> {
+        if self.rows == MAX_ROWS {
+            return Err(io::Error::other(format!(
+                "Excel worksheets can't have more than {MAX_ROWS} rows"
+            )));
+        }
+
+        self.rows += 1;
+        let row = self.rows;
+        let mut xml = format!("<row r=\"{row}\">");
+
+        for (i, cell) in cells.iter().enumerate() {
+            let r = format!("{}{}", column_name(i), row);
+            let s = match cell {
+                _ if header => format!(" s=\"{STYLE_HEADER}\""),
+                Cell::Date(_) => format!(" s=\"{STYLE_DATE}\""),
+                _ => String::new(),
+            };
+            match cell {
+                Cell::Text(t) => xml.push_str(&format!(
+                    "<c r=\"{}\"{} t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                    r, s, escape(t)
+                )),
+                Cell::Number(n) => {
+                    xml.push_str(&format!("<c r=\"{}\"{}><v>{}</v></c>", r, s, n))
+                },
+                Cell::Date(d) => match date_serial(d) {
+                    Some(n) => {
+                        xml.push_str(&format!("<c r=\"{}\"{}><v>{}</v></c>", r, s, n))
+                    },
+                    // Excel can't represent dates before 1900, so they're
+                    // written as text.
+                    None => xml.push_str(&format!(
+                        "<c r=\"{}\" t=\"inlineStr\"><is><t>{}</t></is></c>",
+                        r, d.format("%Y-%m-%d")
+                    )),
+                },
+            }
+        }
+
+        xml.push_str("</row>");
+        self.write_sheet(xml.as_bytes())
+    }
+
+    /// Write a complete (non-streamed) zip entry.
+    fn write_entry(&mut self, name: &'static str, data: &[u8]) -> io::Result<()> {
+        let crc = crc32_update(0, data);
+        let entry = ZipEntry { name, flags: 0, crc, size: data.len() as u64, offset: self.pos };
+        let header = local_header(name, 0, crc, data.len() as u32);
+        self.write(&header)?;
+        self.write(data)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write part of the worksheet entry.
+    fn write_sheet(&mut self, data: &[u8]) -> io::Result<()> {
+        self.sheet.crc = crc32_update(self.sheet.crc, data);
+        self.sheet.size += data.len() as u64;
+        self.write(data)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.w.write_all(data)?;
+        self.pos += data.len() as u64;
+        Ok(())
+    }
+}
+
+/**
+ * Convert a date to an Excel date serial number (days since 1899-12-30,
+ * thanks to Lotus 1-2-3's belief that 1900 was a leap year).
+ *
+ * # Returns
+ *
+ * - `Some(n)`: The serial number
+ * - `None`: The date is before 1900, which Excel can't represent.
+ */
+pub fn date_serial(d: &NaiveDate) -> Option<i64> {
+    let days = d.signed_duration_since(NaiveDate::from_ymd(1899, 12, 30)).num_days();
+
+    match days {
+        // 1900-03-01 and later
+        61.. => Some(days),
+        // 1900-01-01 to 1900-02-28, before the phantom 1900-02-29
+        2..=60 => Some(days - 1),
+        _ => None,
+    }
+}
+
+/// Convert a zero-based column index into an Excel column name (A, B, ...,
+/// Z, AA, AB, ...).
+fn column_name(mut i: usize) -> String {
+    let mut name: Vec<u8> = Vec::new();
+
+    loop {
+        name.push(b'A' + (i % 26) as u8);
+        if i < 26 {
+            break;
+        }
+        i = i / 26 - 1;
+    }
+
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// Escape text for an XML element, dropping characters XML doesn't allow.
+fn escape(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '\t' | '\n' | '\r' => buf.push(c),
+            c if c < ' ' => (),
+            c => buf.push(c),
+        }
+    }
+
+    buf
+}
+
+/// Build a zip local file header.
+fn local_header(name: &str, flags: u16, crc: u32, size: u32) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    put_u32(&mut buf, LOCAL_HEADER_SIG);
+    put_u16(&mut buf, ZIP_VERSION);
+    put_u16(&mut buf, flags);
+    // Method (stored), time and date.
+    put_u16(&mut buf, 0);
+    put_u16(&mut buf, 0);
+    put_u16(&mut buf, DOS_DATE);
+    put_u32(&mut buf, crc);
+    put_u32(&mut buf, size);
+    put_u32(&mut buf, size);
+    put_u16(&mut buf, name.len() as u16);
+    put_u16(&mut buf, 0);
+    buf.extend(name.as_bytes());
+    buf
+}
+
+fn put_u16(buf: &mut Vec<u8>, n: u16) {
+    buf.extend(n.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, n: u32) {
+    buf.extend(n.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xlsx::*;
+
+    #[test]
+    fn columns() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn serials() {
+        assert_eq!(date_serial(&NaiveDate::from_ymd(1900, 1, 1)), Some(1));
+        assert_eq!(date_serial(&NaiveDate::from_ymd(1900, 2, 28)), Some(59));
+        assert_eq!(date_serial(&NaiveDate::from_ymd(1900, 3, 1)), Some(61));
+        assert_eq!(date_serial(&NaiveDate::from_ymd(2000, 1, 1)), Some(36526));
+        assert_eq!(date_serial(&NaiveDate::from_ymd(1899, 12, 31)), None);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("O'Neill & <Sons>\u{1}"), "O'Neill &amp; &lt;Sons&gt;");
+    }
+
+    #[test]
+    fn workbook() {
+        let names = [String::from("name"), String::from("birth_date")];
+        let mut w = XlsxWriter::new(Vec::new(), &names.iter().collect::<Vec<_>>()).unwrap();
+        w.write_row(&[
+            Cell::Text(String::from("Moe")),
+            Cell::Date(NaiveDate::from_ymd(2000, 1, 1)),
+        ]).unwrap();
+        let bytes = w.finish().unwrap();
+
+        assert_eq!(&bytes[..4], &LOCAL_HEADER_SIG.to_le_bytes());
+        let sheet = String::from_utf8_lossy(&bytes);
+        assert!(sheet.contains(
+            "<row r=\"2\"><c r=\"A2\" t=\"inlineStr\"><is><t xml:space=\"preserve\">Moe</t>\
+             </is></c><c r=\"B2\" s=\"2\"><v>36526</v></c></row>"
+        ));
+        // Six entries in the central directory.
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(&end[..4], &END_OF_CENTRAL_DIR_SIG.to_le_bytes());
+        assert_eq!(&end[10..12], &6u16.to_le_bytes());
+    }
+}
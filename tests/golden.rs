@@ -0,0 +1,1079 @@
+//! End-to-end tests: run the binary with a fixed seed, across output formats
+//! and option combinations, and compare what it writes with the golden files
+//! in `tests/golden`, so that changes to the output can't go unnoticed.
+//!
+//! The runs use the small names files in `tests/fixtures`, fixed birth year
+//! and metadata date ranges (the defaults move with the current date), and
+//! an empty home directory, so nothing from the environment leaks in.
+//!
+//! When a change to the output is intended, regenerate the golden files with
+//!
+//! ```text
+//! PEOPLEGEN_UPDATE_GOLDEN=1 cargo test --test golden
+//! ```
+//!
+//! and review the diff.
+
+use assert_cmd::Command;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// The options every run gets, so the output is reproducible.
+const COMMON: [&str; 6] = ["--seed", "42", "--year-min", "1950", "--year-max", "2000"];
+
+/// How many people each run generates.
+const TOTAL: &str = "25";
+
+fn source_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+/**
+ * Run `peoplegen` in a temporary directory, and compare the output file and
+ * standard output with the golden files `<name>.<extension>` and
+ * `<name>.stdout`.
+ *
+ * # Arguments
+ *
+ * - `name`: The name of the case
+ * - `output`: The output file name, which picks the format
+ * - `args`: The options, besides `COMMON`
+ */
+fn check(name: &str, output: &str, args: &[&str]) {
+    let (written, stdout) = run(output, args);
+    let extension = Path::new(output).extension().unwrap().to_str().unwrap();
+    compare(&format!("{name}.{extension}"), &written);
+    compare(&format!("{name}.stdout"), &stdout);
+}
+
+/**
+ * Run `peoplegen` in a temporary directory.
+ *
+ * # Arguments
+ *
+ * - `output`: The output file name, which picks the format
+ * - `args`: The options, besides `COMMON`
+ *
+ * # Returns
+ *
+ * The contents of the output file, and the standard output.
+ */
+fn run(output: &str, args: &[&str]) -> (Vec<u8>, Vec<u8>) {
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(args)
+        .args([output, TOTAL])
+        .assert()
+        .success();
+
+    (fs::read(dir.path().join(output)).unwrap(), assert.get_output().stdout.clone())
+}
+
+/// A `peoplegen` command that runs in a directory, with the fixtures' names
+/// files, and nothing else from the environment.
+fn peoplegen(dir: &Path) -> Command {
+    let fixtures = source_dir("fixtures");
+    let fixture = |file: &str| fixtures.join(file);
+    let mut command = Command::cargo_bin("peoplegen").unwrap();
+
+    command
+        .current_dir(dir)
+        .env_clear()
+        .env("HOME", dir)
+        .env("PEOPLEGEN_MALE_FIRST_NAMES", fixture("male_first_names.txt"))
+        .env("PEOPLEGEN_FEMALE_FIRST_NAMES", fixture("female_first_names.txt"))
+        .env("PEOPLEGEN_LAST_NAMES", fixture("last_names.txt"));
+    command
+}
+
+/// Compare output with a golden file, or replace the golden file, if
+/// `PEOPLEGEN_UPDATE_GOLDEN` is set.
+fn compare(golden: &str, actual: &[u8]) {
+    let path = source_dir("golden").join(golden);
+
+    if std::env::var_os("PEOPLEGEN_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read(&path).unwrap_or_else(|e| {
+        panic!("Can't read {}: {}. Set PEOPLEGEN_UPDATE_GOLDEN=1 to create it.", path.display(), e)
+    });
+
+    // Text is compared as text, so a failure shows a readable diff.
+    match (std::str::from_utf8(&expected), std::str::from_utf8(actual)) {
+        (Ok(e), Ok(a)) => assert_eq!(a, e, "output differs from {}", path.display()),
+        _ => assert!(actual == expected, "output differs from {}", path.display()),
+    }
+}
+
+#[test]
+fn csv_defaults() {
+    check("csv_defaults", "people.csv", &[]);
+}
+
+/// Compressed output has to decompress to the same file as uncompressed
+/// output. (The compressed bytes depend on the compression libraries'
+/// versions, so they aren't compared.)
+#[test]
+fn compressed() {
+    let decompress = |extension: &str, compressed: &[u8]| {
+        let mut text = Vec::new();
+        match extension {
+            "gz" => flate2::read::GzDecoder::new(compressed).read_to_end(&mut text),
+            "zst" => zstd::Decoder::new(compressed).unwrap().read_to_end(&mut text),
+            _ => bzip2::read::BzDecoder::new(compressed).read_to_end(&mut text),
+        }.unwrap();
+        text
+    };
+
+    for extension in ["gz", "zst", "bz2"] {
+        let (written, _) = run(&format!("people.csv.{extension}"), &[]);
+        compare("csv_defaults.csv", &decompress(extension, &written));
+    }
+}
+
+/// Shards hold the same people as a single file, split by range or dealt
+/// out round-robin.
+#[test]
+fn shards() {
+    let golden = fs::read_to_string(source_dir("golden").join("csv_defaults.csv")).unwrap();
+    let (header, rows) = golden.split_once('\n').unwrap();
+    let rows: Vec<&str> = rows.lines().collect();
+
+    for by in ["range", "round-robin"] {
+        let dir = TempDir::new().unwrap();
+        peoplegen(dir.path())
+            .args(COMMON)
+            .args(["--shards", "3", "--shard-by", by, "people.csv", TOTAL])
+            .assert()
+            .success();
+
+        // Each row, with where it came from in a single file.
+        let mut sharded: Vec<(usize, String)> = Vec::new();
+        for shard in 0..3 {
+            let text = fs::read_to_string(dir.path().join(format!("people-0000{shard}.csv"))).unwrap();
+            let (shard_header, shard_rows) = text.split_once('\n').unwrap();
+            let start = sharded.len();
+            assert_eq!(shard_header, header);
+
+            for (i, row) in shard_rows.lines().enumerate() {
+                let n = if by == "range" { start + i } else { i * 3 + shard };
+                sharded.push((n, row.to_string()));
+            }
+        }
+        sharded.sort();
+
+        assert_eq!(sharded.iter().map(|(_, row)| row.as_str()).collect::<Vec<_>>(), rows);
+        assert_eq!(sharded.iter().map(|(n, _)| *n).collect::<Vec<_>>(), (0..25).collect::<Vec<_>>());
+    }
+}
+
+/**
+ * Stand in for an OpenTelemetry collector: take one OTLP/HTTP request on a
+ * local port, and answer it.
+ *
+ * # Returns
+ *
+ * The collector's endpoint, and a thread that returns the request line and
+ * the body.
+ */
+fn collector() -> (String, std::thread::JoinHandle<(String, String)>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let thread = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request).unwrap();
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            match header.trim_end().split_once(':') {
+                Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                    length = value.trim().parse().unwrap();
+                },
+                Some(_) => (),
+                None => break,
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        (request.trim_end().to_string(), String::from_utf8(body).unwrap())
+    });
+    (endpoint, thread)
+}
+
+/// --otlp-endpoint sends the run's spans to a collector, and one that isn't
+/// there is only a warning.
+#[test]
+fn otlp_endpoint() {
+    let (endpoint, collector) = collector();
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--otlp-endpoint", &endpoint, "--shards", "2", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    let (request, body) = collector.join().unwrap();
+    assert_eq!(request, "POST /v1/traces HTTP/1.1");
+    let traces: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let spans = traces["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+    let span = |name: &str| spans.iter().find(|s| s["name"] == name).unwrap();
+    let mut names: Vec<&str> = spans.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    names.sort();
+    assert_eq!(names, ["commit", "finish_extras", "generate", "peoplegen", "read_names", "write_people",
+                       "write_people"]);
+    assert!(span("peoplegen")["parentSpanId"].is_null());
+    assert_eq!(span("generate")["parentSpanId"], span("peoplegen")["spanId"]);
+    assert_eq!(span("write_people")["parentSpanId"], span("generate")["spanId"]);
+    // The shards' writers finish in no particular order.
+    let mut records: Vec<&str> = spans
+        .iter()
+        .filter(|s| s["name"] == "write_people")
+        .flat_map(|s| s["attributes"].as_array().unwrap())
+        .filter(|a| a["key"] == "records")
+        .map(|a| a["value"]["intValue"].as_str().unwrap())
+        .collect();
+    records.sort();
+    assert_eq!(records, ["12", "13"]);
+
+    // Nothing's listening on a port that was just given up.
+    let endpoint = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .env("OTEL_EXPORTER_OTLP_ENDPOINT", endpoint)
+        .args(COMMON)
+        .args(["people.csv", TOTAL])
+        .assert()
+        .success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("Can't export the trace spans"), "{stderr}");
+    assert!(dir.path().join("people.csv").exists());
+}
+
+/// Rolled-over files hold the same people as a single file, in order.
+#[test]
+fn rollover() {
+    let golden = fs::read_to_string(source_dir("golden").join("csv_defaults.csv")).unwrap();
+    let (header, rows) = golden.split_once('\n').unwrap();
+
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--max-rows-per-file", "10", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    let mut rolled = String::new();
+    for (n, file) in [10, 10, 5].iter().enumerate() {
+        let text = fs::read_to_string(dir.path().join(format!("people-0000{n}.csv"))).unwrap();
+        let (file_header, file_rows) = text.split_once('\n').unwrap();
+        assert_eq!(file_header, header);
+        assert_eq!(file_rows.lines().count(), *file);
+        rolled.push_str(file_rows);
+    }
+    assert!(!dir.path().join("people-00003.csv").exists());
+    assert_eq!(rolled, rows);
+}
+
+/// "-" writes the people to standard output, as CSV, and the messages to
+/// standard error.
+#[test]
+fn stdout() {
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path()).args(COMMON).args(["-", TOTAL]).assert().success();
+    let output = assert.get_output();
+
+    compare("csv_defaults.csv", &output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Wrote 25 records(s) to standard output, as CSV.\n");
+}
+
+/// `--output null` and "/dev/null" format the people and write nothing.
+#[test]
+fn null_sink() {
+    for args in [&["--output", "null", TOTAL][..], &["/dev/null", TOTAL]] {
+        let dir = TempDir::new().unwrap();
+        let assert = peoplegen(dir.path()).args(COMMON).args(args).assert().success();
+
+        assert_eq!(String::from_utf8_lossy(&assert.get_output().stdout),
+                   "Formatted 25 records(s) as CSV, and discarded them.\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
+
+/// --preview prints the people to standard output, and writes nothing.
+#[test]
+fn preview() {
+    for (format, extension) in [("table", "txt"), ("json", "json"), ("csv", "csv")] {
+        let dir = TempDir::new().unwrap();
+        let assert = peoplegen(dir.path())
+            .args(COMMON)
+            .args(["--id", "--salary", "--preview", "5", "--preview-format", format])
+            .assert()
+            .success();
+
+        compare(&format!("preview.{extension}"), &assert.get_output().stdout);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
+
+/// With an output file, --preview prints the first people as a table, and
+/// the file is written as usual.
+#[test]
+fn preview_and_file() {
+    let (written, stdout) = run("people.csv", &["--preview", "3"]);
+    compare("csv_defaults.csv", &written);
+    compare("preview_and_file.stdout", &stdout);
+
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path()).args(["--preview", "3", "people.csv"]).assert().code(2);
+    peoplegen(dir.path()).args(["--preview", "3", "--preview-format", "csv", "people.csv", TOTAL]).assert().code(2);
+}
+
+/// --dry-run says what would be generated, and writes nothing. Bad settings
+/// still fail it.
+#[test]
+fn dry_run() {
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--dry-run", "--id", "--salary", "--shards", "2", "--w2", "w2.csv", "people.csv.gz", TOTAL])
+        .assert()
+        .success();
+    compare("dry_run.stdout", &assert.get_output().stdout);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    peoplegen(dir.path()).args(["--dry-run", "--year-min", "2000", "--year-max", "1950", "people.csv", TOTAL])
+        .assert()
+        .code(2);
+    peoplegen(dir.path()).args(["--dry-run", "--last-names", "nope.txt", "people.csv", TOTAL]).assert().code(3);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+/// TOTAL and the salary options can be written with underscores and k/M/B
+/// suffixes.
+#[test]
+fn readable_counts() {
+    let people = |args: &[&str]| {
+        let dir = TempDir::new().unwrap();
+        peoplegen(dir.path()).args(COMMON).args(["--salary"]).args(args).assert().success();
+        fs::read(dir.path().join("people.csv")).unwrap()
+    };
+
+    assert_eq!(
+        people(&["--salary-mean", "58.26k", "--salary-sigma", "5_000", "people.csv", "0.025k"]),
+        people(&["--salary-mean", "58260", "--salary-sigma", "5000", "people.csv", TOTAL]),
+    );
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path()).args(["people.csv", "2.5"]).assert().code(2);
+    peoplegen(dir.path()).args(["--salary", "--salary-mean", "5B", "people.csv", TOTAL]).assert().code(2);
+}
+
+/// --cluster-by birth_year writes the people in birth date order.
+#[test]
+fn cluster_by() {
+    let (written, _) = run("people.csv", &["--id", "--cluster-by", "birth_year"]);
+    compare("cluster_by_birth_year.csv", &written);
+
+    let text = String::from_utf8(written).unwrap();
+    let dates: Vec<&str> = text.lines().skip(1).map(|line| line.rsplit(',').next().unwrap()).collect();
+    assert_eq!(dates.len(), 25);
+    assert!(dates.windows(2).all(|d| d[0] <= d[1]));
+}
+
+/// --format picks the format when the output file's name doesn't, and wins
+/// when it does.
+#[test]
+fn format_override() {
+    let (written, _) = run("part-00000", &["--id", "--salary", "--sort-keys", "--format", "jsonl"]);
+    compare("jsonl_sorted.jsonl", &written);
+
+    let (written, _) = run("people.csv", &["--id", "--format", "tsv"]);
+    compare("tsv.tsv", &written);
+
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--salary", "--sort-keys", "--format", "jsonl", "-", TOTAL])
+        .assert()
+        .success();
+    compare("jsonl_sorted.jsonl", &assert.get_output().stdout);
+}
+
+/// Assertions that hold don't change the output; ones that don't fail the
+/// run, and leave no output.
+#[test]
+fn assertions() {
+    let (written, _) = run("people.csv", &["--assert", "count(gender = 'F') / count(*) ~ 0.5 ± 0.1"]);
+    compare("csv_defaults.csv", &written);
+
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--salary", "--assert", "min(salary) > 1000000", "--avatars", "faces", "--summary-json", "s.json",
+               "people.csv", TOTAL])
+        .assert()
+        .code(6);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+
+    assert!(stderr.starts_with("Assertion \"min(salary) > 1000000\" failed: the value is "), "{stderr}");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+/// Aggregate tables are written alongside the output, which they don't
+/// change.
+#[test]
+fn aggregates() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--salary", "--aggregates", "summary", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("aggregates_gender_by_decade.csv", &fs::read(dir.path().join("summary/gender_by_decade.csv")).unwrap());
+    compare("aggregates_salary_quantiles.csv", &fs::read(dir.path().join("summary/salary_quantiles.csv")).unwrap());
+}
+
+/// Statistics are printed after the run summary, and don't change the
+/// output.
+#[test]
+fn stats() {
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--salary", "--stats", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("stats.stdout", &assert.get_output().stdout);
+}
+
+/// Shift schedules are written alongside the output, which they don't
+/// change.
+#[test]
+fn shifts() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--shifts", "shifts.csv", "--shift-weeks", "2", "--shift-start", "2024-03-04",
+               "people.csv", TOTAL])
+        .assert()
+        .success();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "plain.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("shifts.csv", &fs::read(dir.path().join("shifts.csv")).unwrap());
+    assert_eq!(fs::read(dir.path().join("people.csv")).unwrap(), fs::read(dir.path().join("plain.csv")).unwrap());
+}
+
+/// W-2 statements are written alongside the output.
+#[test]
+fn w2() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--salary", "--w2", "w2.csv", "--tax-year", "2024", "--w2-years", "2", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("w2.csv", &fs::read(dir.path().join("w2.csv")).unwrap());
+}
+
+/// Transactions are written alongside the output, and don't change it.
+#[test]
+fn transactions() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--transactions", "transactions.csv", "--transactions-per-person", "3",
+               "--transaction-min", "2024-01-01", "--transaction-max", "2024-12-31", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("transactions.csv", &fs::read(dir.path().join("transactions.csv")).unwrap());
+    let (written, _) = run("people.csv", &["--id"]);
+    assert_eq!(fs::read(dir.path().join("people.csv")).unwrap(), written);
+}
+
+/// Immunization histories are written as CSV, or as FHIR resources, from
+/// the built-in value set or one from a file.
+#[test]
+fn immunizations() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("medications.csv"),
+        "# Some RxNorm codes\nsystem,code,display,weight\n\
+         http://www.nlm.nih.gov/research/umls/rxnorm,197361,amlodipine 5 MG Oral Tablet,3\n\
+         http://www.nlm.nih.gov/research/umls/rxnorm,860975,metformin 500 MG Oral Tablet,1\n",
+    )
+    .unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--immunizations", "immunizations.csv", "--immunization-max", "2025-06-30", "people.csv", TOTAL])
+        .assert()
+        .success();
+    compare("immunizations.csv", &fs::read(dir.path().join("immunizations.csv")).unwrap());
+
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--immunizations", "medications.ndjson", "--immunization-codes", "medications.csv",
+               "--max-immunizations", "2", "--immunization-max", "2025-06-30", "people.csv", TOTAL])
+        .assert()
+        .success();
+    compare("medications.ndjson", &fs::read(dir.path().join("medications.ndjson")).unwrap());
+}
+
+/// Benefits enrollments are written to a directory alongside the output.
+#[test]
+fn benefits() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--benefits", "benefits", "--plan-year", "2025", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    compare("benefits_elections.csv", &fs::read(dir.path().join("benefits/elections.csv")).unwrap());
+    compare("benefits_dependents.csv", &fs::read(dir.path().join("benefits/dependents.csv")).unwrap());
+}
+
+/// A run that fails part way through leaves the old output file alone, and
+/// no temporary files behind.
+#[test]
+fn failed_run() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("people.csv"), "old").unwrap();
+
+    // A wide enough salary distribution soon produces a negative salary.
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--salary", "--salary-mean", "100", "--salary-sigma", "100000", "people.csv", TOTAL])
+        .assert()
+        .code(4);
+
+    assert_eq!(fs::read_to_string(dir.path().join("people.csv")).unwrap(), "old");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn csv_all_fields_pretty() {
+    check("csv_all_fields_pretty", "people.csv", &[
+        "--id", "--ssn", "--salary", "--pronouns", "--header-format", "pretty",
+    ]);
+}
+
+#[test]
+fn csv_formatted_fields() {
+    check("csv_formatted_fields", "people.csv", &[
+        "--ssn", "--format-field", "ssn=digits-only", "--format-field", "birth_date=date:%m/%d/%Y",
+        "--hash-field", "ssn=sha256",
+    ]);
+}
+
+/// A field can only have one hash field, since they'd have the same name.
+#[test]
+fn hash_field_twice() {
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--ssn", "--hash-field", "ssn=sha256", "--hash-field", "ssn=sha512", "people.csv", TOTAL])
+        .assert()
+        .code(2);
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr)
+                .contains("--hash-field is given more than once for \"ssn\"."));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn csv_no_gender() {
+    check("csv_no_gender", "people.csv", &["--no-gender"]);
+}
+
+#[test]
+fn csv_nonbinary() {
+    check("csv_nonbinary", "people.csv", &["--female-pct", "40", "--male-pct", "40", "--other-pct", "20", "--pronouns"]);
+
+    // The percentages must still add up to 100.
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path()).args(["--other-pct", "20", "people.csv", TOTAL]).assert().code(2);
+}
+
+#[test]
+fn csv_gender_values() {
+    let genders = ["--female-pct", "40", "--male-pct", "40", "--other-pct", "20", "--pronouns"];
+    check("csv_gender_values", "people.csv", &[&genders[..], &["--gender-values", "0,1,2"]].concat());
+
+    // The values are formatted like any others.
+    let (formatted, _) = run("people.csv", &[
+        &genders[..],
+        &["--gender-values", "Male,Female,Other", "--format-field", "gender=upper"],
+    ].concat());
+    let (upper, _) = run("people.csv", &[&genders[..], &["--gender-values", "MALE,FEMALE,OTHER"]].concat());
+    assert_eq!(formatted, upper);
+
+    let dir = TempDir::new().unwrap();
+    for args in [&[&genders[..], &["--gender-values", "Male,Female"]].concat(),
+                 &vec!["--gender-values", "M,M"],
+                 &vec!["--no-gender", "--gender-values", "Male,Female"]] {
+        peoplegen(dir.path()).args(args).args(["people.csv", TOTAL]).assert().code(2);
+    }
+
+    // Nor can a gender configuration file in the config directory, which
+    // would otherwise be used.
+    let config_dir = dir.path().join(".config").join("peoplegen");
+    fs::create_dir_all(&config_dir).unwrap();
+    let names = source_dir("fixtures").join("female_first_names.txt");
+    fs::write(config_dir.join("genders.csv"), format!("label,percent,names\nW,100,{}\n", names.display())).unwrap();
+    peoplegen(dir.path()).args(["people.csv", TOTAL]).assert().success();
+    peoplegen(dir.path()).args(["--gender-values", "Male,Female", "people.csv", TOTAL]).assert().code(2);
+}
+
+#[test]
+fn csv_weighted_names() {
+    let names = source_dir("fixtures").join("weighted_last_names.txt");
+    check("csv_weighted_names", "people.csv", &["--last-names", names.to_str().unwrap()]);
+}
+
+#[test]
+fn csv_rules() {
+    let rules = source_dir("fixtures").join("rules.txt");
+    check("csv_rules", "people.csv", &["-s", "--salary", "--pronouns", "--rules", rules.to_str().unwrap()]);
+}
+
+/// The --summary-json report, with the duration (which varies) zeroed.
+#[test]
+fn summary_json() {
+    let dir = TempDir::new().unwrap();
+    let rules = source_dir("fixtures").join("rules.txt");
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "-s", "--salary", "--pronouns", "--rules", rules.to_str().unwrap(),
+               "--format-field", "ssn=digits-only", "--hash-field", "ssn=sha256",
+               "--summary-json", "summary.json", "people.csv", TOTAL])
+        .assert()
+        .success();
+
+    let text = fs::read_to_string(dir.path().join("summary.json")).unwrap();
+    let mut report: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert!(report["duration_secs"].as_f64().unwrap() > 0.0, "{text}");
+    report["duration_secs"] = 0.into();
+    compare("summary_json.json", serde_json::to_string_pretty(&report).unwrap().as_bytes());
+}
+
+#[test]
+fn csv_config() {
+    // The command line's --header-format replaces the file's.
+    let config = source_dir("fixtures").join("peoplegen.toml");
+    check("csv_config", "people.csv", &["--config", config.to_str().unwrap(), "--header-format", "camel"]);
+}
+
+/// The effective configuration from --dump-config reproduces the run, even
+/// with a random seed.
+#[test]
+fn dump_config() {
+    let dir = TempDir::new().unwrap();
+    peoplegen(dir.path())
+        .args(["--ssn", "--salary", "--metadata", "--format-field", "ssn=digits-only", "--dump-config", "run.toml"])
+        .args(["first.csv", TOTAL])
+        .assert()
+        .success();
+    peoplegen(dir.path())
+        .args(["--config", "run.toml", "again.csv"])
+        .assert()
+        .success();
+
+    let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(read("again.csv"), read("first.csv"));
+    assert!(read("run.toml").contains("ssn = true\n"), "{}", read("run.toml"));
+
+    // With -, it's printed instead.
+    let assert = peoplegen(dir.path()).args(COMMON).args(["--dump-config", "-", "never.csv", TOTAL]).assert().success();
+    let printed = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(printed.contains("seed = 42\n"), "{printed}");
+    assert!(!dir.path().join("never.csv").exists());
+}
+
+#[test]
+fn subcommands() {
+    let dir = TempDir::new().unwrap();
+    let stdout = |args: &[&str]| {
+        let assert = peoplegen(dir.path()).args(args).assert().success();
+        String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+    };
+
+    // "generate" is what peoplegen does without a subcommand.
+    let mut generate = vec!["generate"];
+    generate.extend(COMMON);
+    generate.extend(["--ssn", "-", TOTAL]);
+    assert_eq!(stdout(&generate), stdout(&generate[1..]));
+
+    // Its options can go before it, too, as scripts that added "generate" to
+    // a working command line have them.
+    let mut before: Vec<&str> = COMMON.to_vec();
+    before.extend(["--ssn", "generate", "-", TOTAL]);
+    assert_eq!(stdout(&generate), stdout(&before));
+
+    // Schema options can go after the subcommand, or before it.
+    let ddl = stdout(&["schema", "ddl", "--id", "--ssn", "--dialect", "sqlite"]);
+    assert!(ddl.contains("\"ssn\" TEXT"), "{ddl}");
+    assert_eq!(ddl, stdout(&["--id", "--ssn", "schema", "ddl", "--dialect", "sqlite"]));
+
+    peoplegen(dir.path()).args(["--id", "schema", "ddl", "--ssn"]).assert().code(2);
+}
+
+/// The golden files pass "validate", and a file that's been mangled doesn't.
+#[test]
+fn validate() {
+    let dir = TempDir::new().unwrap();
+    let validate = |args: &[&str]| peoplegen(dir.path()).arg("validate").args(args).assert();
+    let golden = |name: &str| source_dir("golden").join(name).to_str().unwrap().to_string();
+
+    for name in ["csv_all_fields_pretty.csv", "csv_metadata.csv", "jsonl_sorted.jsonl", "json_camel.json", "tsv.tsv"] {
+        validate(&[&golden(name)]).success();
+    }
+    validate(&["--date-format", "%m/%d/%Y", &golden("csv_formatted_fields.csv")]).success();
+    validate(&["--salary-max", "60000", &golden("csv_all_fields_pretty.csv")]).code(7);
+
+    let mangled = fs::read_to_string(source_dir("golden").join("csv_defaults.csv")).unwrap()
+        .replacen("1957-08-25", "8/25/1957", 1);
+    fs::write(dir.path().join("people.csv"), mangled).unwrap();
+    let assert = validate(&["people.csv"]).code(7);
+    assert_eq!(String::from_utf8_lossy(&assert.get_output().stdout),
+               "record 1: birth_date: \"8/25/1957\" isn't a date in the format \"%Y-%m-%d\"\n");
+    assert_eq!(String::from_utf8_lossy(&assert.get_output().stderr),
+               "\"people.csv\" has 1 violation(s) in 25 record(s).\n");
+
+    validate(&["missing.csv"]).code(3);
+    validate(&["--ssn"]).code(2);
+}
+
+/// A scenario's steps share the seed, and their people share the IDs.
+#[test]
+fn scenario() {
+    let dir = TempDir::new().unwrap();
+    let write_scenario = |steps: &str| {
+        let shared = "seed = 42\nyear-min = 1950\nyear-max = 2000\nid = true\n";
+        fs::write(dir.path().join("scenario.toml"), format!("{shared}{steps}")).unwrap();
+    };
+    write_scenario(
+        "[[step]]\n\
+         name = \"employees\"\n\
+         output = \"employees.csv\"\n\
+         total = 25\n\
+         shifts = \"shifts.csv\"\n\
+         shift-start = \"2024-03-04\"\n\
+         [[step]]\n\
+         name = \"contractors\"\n\
+         output = \"contractors.csv\"\n\
+         total = 25\n\
+         salary = true\n\
+         w2 = \"w2.csv\"\n\
+         tax-year = 2024\n"
+    );
+    peoplegen(dir.path()).args(["scenario", "scenario.toml"]).assert().success();
+
+    for file in ["employees.csv", "shifts.csv", "contractors.csv", "w2.csv"] {
+        compare(&format!("scenario_{file}"), &fs::read(dir.path().join(file)).unwrap());
+    }
+    // The first step has the scenario's seed; the second carries on from its
+    // IDs.
+    peoplegen(dir.path())
+        .args(COMMON)
+        .args(["--id", "--shifts", "plain_shifts.csv", "--shift-start", "2024-03-04", "plain.csv", TOTAL])
+        .assert()
+        .success();
+    assert_eq!(fs::read(dir.path().join("employees.csv")).unwrap(), fs::read(dir.path().join("plain.csv")).unwrap());
+    let contractors = fs::read_to_string(dir.path().join("contractors.csv")).unwrap();
+    assert!(contractors.lines().nth(1).unwrap().starts_with("26,"), "{contractors}");
+
+    // --seed replaces the file's.
+    peoplegen(dir.path()).args(["scenario", "--seed", "43", "scenario.toml"]).assert().success();
+    assert_ne!(fs::read_to_string(dir.path().join("contractors.csv")).unwrap(), contractors);
+
+    // The SSNs carry on from step to step, like the IDs.
+    write_scenario(
+        "ssn = true\n\
+         [[step]]\noutput = \"first.csv\"\ntotal = 3\n\
+         [[step]]\noutput = \"second.csv\"\ntotal = 3\n"
+    );
+    peoplegen(dir.path()).args(["scenario", "scenario.toml"]).assert().success();
+    let ssns: Vec<String> = ["first.csv", "second.csv"]
+        .iter()
+        .flat_map(|file| {
+            let people = fs::read_to_string(dir.path().join(file)).unwrap();
+            let column = people.lines().next().unwrap().split(',').position(|h| h == "ssn").unwrap();
+            people.lines().skip(1).map(|line| line.split(',').nth(column).unwrap().to_string()).collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(ssns.iter().collect::<HashSet<_>>().len(), 6, "{ssns:?}");
+
+    // Two steps can't write the same file.
+    write_scenario("[[step]]\noutput = \"a.csv\"\ntotal = 1\n[[step]]\noutput = \"a.csv\"\ntotal = 1\n");
+    peoplegen(dir.path()).args(["scenario", "scenario.toml"]).assert().code(2);
+    write_scenario("[[step]]\noutput = \"a.csv\"\ntotal = 1\nseed = 1\n");
+    peoplegen(dir.path()).args(["scenario", "scenario.toml"]).assert().code(2);
+    peoplegen(dir.path()).args(["scenario", "missing.toml"]).assert().code(2);
+    assert!(!dir.path().join("a.csv").exists());
+
+    // A step that fails leaves the files of the steps before it unwritten,
+    // too, including their per-person directories.
+    write_scenario(
+        "[[step]]\noutput = \"a.csv\"\ntotal = 3\navatars = \"faces\"\n\
+         [[step]]\noutput = \"b.csv\"\ntotal = 3\nassert = [\"count(*) > 3\"]\n"
+    );
+    peoplegen(dir.path()).args(["scenario", "scenario.toml"]).assert().code(6);
+    assert!(!dir.path().join("a.csv").exists());
+    assert!(!dir.path().join("faces").exists());
+    assert!(!dir.path().join("b.csv").exists());
+    let hidden: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with('.'))
+        .collect();
+    assert!(hidden.is_empty(), "{hidden:?}");
+}
+
+/// bench reports the rates, which vary, and writes nothing.
+#[test]
+fn bench() {
+    let dir = TempDir::new().unwrap();
+    let bench = |args: &[&str]| peoplegen(dir.path()).arg("bench").args(args).assert();
+
+    let assert = bench(&["--seed", "42", "--ssn", "--format", "jsonl", "1000"]).success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{stdout}");
+    assert!(lines[0].ends_with(": 1,000 people, as JSON Lines, with seed 42."), "{stdout}");
+    for (line, what) in lines[1..].iter().zip(["Generation", "Formatting", "Total"]) {
+        assert!(line.starts_with(what) && line.ends_with(" rows/s"), "{stdout}");
+    }
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    bench(&["--shards", "2", "1000"]).code(2);
+    bench(&["people.csv", "1000"]).code(2);
+    peoplegen(dir.path()).args(["--ssn", "bench", "1000"]).assert().success();
+    peoplegen(dir.path()).args(["--shards", "2", "bench", "1000"]).assert().code(2);
+}
+
+#[test]
+fn doctor() {
+    let dir = TempDir::new().unwrap();
+    let doctor = |args: &[&str]| peoplegen(dir.path()).arg("doctor").args(args).assert();
+
+    let assert = doctor(&["--ssn", "people.csv.gz", "10M"]).success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 6, "{stdout}");
+    assert!(lines[..5].iter().all(|line| line.starts_with("OK: ")), "{stdout}");
+    assert!(lines[2].ends_with("(last names): 40 name(s)."), "{stdout}");
+    assert!(lines[4].starts_with("OK: The output will take about "), "{stdout}");
+    assert_eq!(lines[5], "Ready to run.");
+    // The checks leave nothing behind, and don't start the run.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let assert = peoplegen(dir.path())
+        .env("PEOPLEGEN_LAST_NAMES", dir.path().join("missing.txt"))
+        .args(["doctor", "missing/people.csv", TOTAL])
+        .assert()
+        .code(7);
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.contains("(last names) doesn't exist."), "{stdout}");
+    assert!(stdout.contains("\"missing\", the output file's directory, doesn't exist."), "{stdout}");
+    assert_eq!(String::from_utf8_lossy(&assert.get_output().stderr), "Found 2 problem(s). Fix them before the run.\n");
+}
+
+/// Logging doesn't change the output, and goes to standard error, where the
+/// timings (which vary) can't end up in the golden files.
+#[test]
+fn logging() {
+    let (written, stdout) = run("people.csv", &["--quiet"]);
+    compare("csv_defaults.csv", &written);
+    assert!(stdout.is_empty(), "{}", String::from_utf8_lossy(&stdout));
+
+    let dir = TempDir::new().unwrap();
+    let assert = peoplegen(dir.path()).args(COMMON).args(["-v", "people.csv", TOTAL]).assert().success();
+    compare("csv_defaults.stdout", &assert.get_output().stdout);
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    for phase in ["Read 50 first name(s) and 40 last name(s) in ", "Generated 25 people in ", "Wrote 25 records(s) in "] {
+        assert!(stderr.contains(phase), "{stderr}");
+    }
+    assert!(!stderr.contains("DEBUG"), "{stderr}");
+
+    let assert = peoplegen(dir.path()).env("PEOPLEGEN_LOG", "debug").args(COMMON).args(["p.csv", TOTAL]).assert();
+    let stderr = String::from_utf8(assert.success().get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("DEBUG] Seed 42."), "{stderr}");
+
+    peoplegen(dir.path()).args(["-v", "--quiet", "people.csv", TOTAL]).assert().code(2);
+}
+
+/// Warnings go to standard error, and --strict makes them errors.
+#[test]
+fn strict() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("people.csv"), "old").unwrap();
+    let args = ["--female-pct", "99", "--male-pct", "1", "people.csv", TOTAL];
+
+    let assert = peoplegen(dir.path()).args(COMMON).args(["--strict"]).args(args).assert().code(4);
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert_eq!(stderr, "1% of 25 people rounds down to none, so there will be no \"M\" people.\n");
+    assert_eq!(fs::read_to_string(dir.path().join("people.csv")).unwrap(), "old");
+
+    let assert = peoplegen(dir.path()).args(COMMON).args(args).assert().success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.starts_with("Warning: 1% of 25 people"), "{stderr}");
+    assert_eq!(String::from_utf8_lossy(&assert.get_output().stdout), "Wrote 25 records(s) to CSV file \"people.csv\".\n");
+
+    // With no more people than genders, there's no room for them all.
+    for total in ["0", "1", "2"] {
+        let assert = peoplegen(dir.path()).args(COMMON).args(["--strict", "people.csv", total]).assert().success();
+        assert_eq!(String::from_utf8_lossy(&assert.get_output().stderr), "");
+    }
+}
+
+#[test]
+fn csv_active_directory() {
+    check("csv_active_directory", "people.csv", &["--header-format", "ad", "--ad-domain", "example.com"]);
+}
+
+#[test]
+fn csv_metadata() {
+    check("csv_metadata", "people.csv", &[
+        "--id", "--metadata", "--created-min", "2020-01-01", "--created-max", "2020-12-31",
+    ]);
+}
+
+#[test]
+fn csv_provider_ids() {
+    check("csv_provider_ids", "people.csv", &["--id", "--npi", "--dea"]);
+}
+
+#[test]
+fn csv_vin() {
+    check("csv_vin", "people.csv", &["--id", "--vin", "--model-year-min", "2005", "--model-year-max", "2024"]);
+}
+
+#[test]
+fn csv_device() {
+    check("csv_device", "people.csv", &["--id", "--device"]);
+}
+
+#[test]
+fn csv_consent() {
+    check("csv_consent", "people.csv", &[
+        "--id", "--consent", "--sms-opt-in", "40", "--consent-min", "2022-01-01", "--consent-max", "2022-12-31",
+    ]);
+}
+
+#[test]
+fn csv_lifecycle() {
+    check("csv_lifecycle", "people.csv", &[
+        "--id", "--salary", "--lifecycle", "--churn-rate", "40", "--signup-min", "2018-01-01", "--signup-max", "2023-12-31",
+    ]);
+}
+
+#[test]
+fn csv_loyalty() {
+    check("csv_loyalty", "people.csv", &[
+        "--id", "--loyalty", "--loyalty-tiers", "Blue:3,Silver:2,Gold", "--member-min", "2010-01-01", "--member-max", "2023-12-31",
+    ]);
+}
+
+#[test]
+fn json_camel() {
+    check("json_camel", "people.json", &["--id", "--ssn", "--header-format", "camel"]);
+}
+
+#[test]
+fn jsonl_sorted() {
+    check("jsonl_sorted", "people.jsonl", &["--id", "--salary", "--sort-keys"]);
+}
+
+#[test]
+fn long() {
+    check("long_csv", "people.csv", &["--id", "--ssn", "--long"]);
+    check("long_jsonl", "people.jsonl", &["--id", "--ssn", "--long", "-H", "camel"]);
+}
+
+#[test]
+fn avro() {
+    check("avro", "people.avro", &["--id", "--ssn", "--salary"]);
+}
+
+#[test]
+fn arrow() {
+    check("arrow", "people.arrow", &["--id", "--ssn", "--salary"]);
+}
+
+#[test]
+fn xlsx() {
+    check("xlsx", "people.xlsx", &["--id", "--ssn", "--salary"]);
+}
+
+#[test]
+fn sql_sqlite() {
+    check("sql_sqlite", "people.sql", &["--id", "--salary", "--sql-dialect", "sqlite"]);
+}
+
+#[test]
+fn protobuf() {
+    check("protobuf", "people.pb", &["--id", "--ssn", "--salary"]);
+}
+
+#[test]
+fn fixed_width() {
+    check("fixed_width", "people.dat", &[
+        "--id", "--salary", "--layout", "id:6,last_name:12,first_name:10,filler:1,birth_date:10,salary:7",
+    ]);
+}
+
+#[test]
+fn vcard() {
+    check("vcard", "people.vcf", &["--id", "--ssn"]);
+}
+
+#[test]
+fn ldif() {
+    check("ldif", "people.ldif", &["--id", "--base-dn", "ou=users,dc=test,dc=local"]);
+}
+
+#[test]
+fn html() {
+    check("html", "people.html", &["--id", "--salary"]);
+}
+
+#[test]
+fn markdown() {
+    check("markdown", "people.md", &["--id", "--salary", "--ssn"]);
+}
+
+#[test]
+fn tsv() {
+    check("tsv", "people.tsv", &["--id"]);
+}
+
+#[test]
+fn csv_pipe_delimited() {
+    check("csv_pipe_delimited", "people.csv", &["--id", "--delimiter", "|"]);
+}
+
+#[test]
+fn csv_excel_dialect() {
+    check("csv_excel_dialect", "people.csv", &["--id", "--quote-all", "--crlf", "--bom"]);
+}
@@ -0,0 +1,28 @@
+;JenetteLoraine"Freyn*F2
+1957-08-25:900-01-0001@9OmerGarry"Treagust*M2
+1998-10-22:900-01-0002@<CaronLissette"Brasner*F2
+1957-11-19:900-01-0003@=CedrickNeal"
+Dimberline*M2
+1961-03-07:900-01-0004@@Jenette	Annamaria"Spykings*F2
+1983-06-19:900-01-0005@?	AnnamariaValeri"Redmille*F2
+1994-02-11:900-01-0006@:CecileLoraine"Cains*F2
+1957-01-08:900-01-0007@?LouanneLavonna"	Blacksell*F2
+1958-12-04:900-01-0008@9	MelvinTheodore"Kay*M2
+1980-11-03:900-01-0009@@
+	JacqulineLavonna"Rossetti*F2
+1950-05-31:900-01-0010@=	SebastianGarry"Sillito*M2
+1986-01-17:900-01-0011@ӘDTheodoreFranklyn"MacGillivray*M2
+1950-07-23:900-01-0012@>WaltonFranklyn"Spykings*M2
+1961-11-18:900-01-0013@?Fallon	Annamaria"Kindread*F2
+1992-03-07:900-01-0014@;GregoryNoah"Treagust*M2
+1986-11-27:900-01-0015@ܼ8BuckChas"Kindread*M2
+1981-03-30:900-01-0016@9ChrisBuck"Kindread*M2
+1980-06-21:900-01-0017@:ValeriLoraine"Roast*F2
+1980-09-09:900-01-0018@<TheodoreWoodrow"Cains*M2
+1960-04-29:900-01-0019@<MarhtaAnita"	Norsister*F2
+1969-06-25:900-01-0020@:ArturoDirk"Kindread*M2
+1993-05-14:900-01-0021@>MigdaliaArlean"Spykings*F2
+1974-02-07:900-01-0022@9LianneLorna"Gresty*F2
+1952-11-19:900-01-0023@=LavonnaLouanne"Meagher*F2
+1993-05-18:900-01-0024@?WoodrowCedrick"	Ridgedell*M2
+1958-07-29:900-01-0025@
\ No newline at end of file
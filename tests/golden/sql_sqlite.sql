@@ -0,0 +1,37 @@
+BEGIN;
+CREATE TABLE "people" (
+  "id" INTEGER,
+  "first_name" TEXT,
+  "middle_name" TEXT,
+  "last_name" TEXT,
+  "gender" TEXT,
+  "birth_date" TEXT,
+  "salary" INTEGER
+);
+INSERT INTO "people" ("id", "first_name", "middle_name", "last_name", "gender", "birth_date", "salary") VALUES
+  (1, 'Jenette', 'Loraine', 'Freyn', 'F', '1957-08-25', 64930),
+  (2, 'Omer', 'Garry', 'Treagust', 'M', '1998-10-22', 62843),
+  (3, 'Caron', 'Lissette', 'Brasner', 'F', '1957-11-19', 61126),
+  (4, 'Cedrick', 'Neal', 'Dimberline', 'M', '1961-03-07', 62440),
+  (5, 'Jenette', 'Annamaria', 'Spykings', 'F', '1983-06-19', 65025),
+  (6, 'Annamaria', 'Valeri', 'Redmille', 'F', '1994-02-11', 62146),
+  (7, 'Cecile', 'Loraine', 'Cains', 'F', '1957-01-08', 64580),
+  (8, 'Louanne', 'Lavonna', 'Blacksell', 'F', '1958-12-04', 59455),
+  (9, 'Melvin', 'Theodore', 'Kay', 'M', '1980-11-03', 58214),
+  (10, 'Jacquline', 'Lavonna', 'Rossetti', 'F', '1950-05-31', 67837),
+  (11, 'Sebastian', 'Garry', 'Sillito', 'M', '1986-01-17', 52307),
+  (12, 'Theodore', 'Franklyn', 'MacGillivray', 'M', '1950-07-23', 59884),
+  (13, 'Walton', 'Franklyn', 'Spykings', 'M', '1961-11-18', 56087),
+  (14, 'Fallon', 'Annamaria', 'Kindread', 'F', '1992-03-07', 47286),
+  (15, 'Gregory', 'Noah', 'Treagust', 'M', '1986-11-27', 56924),
+  (16, 'Buck', 'Chas', 'Kindread', 'M', '1981-03-30', 55173),
+  (17, 'Chris', 'Buck', 'Kindread', 'M', '1980-06-21', 63277),
+  (18, 'Valeri', 'Loraine', 'Roast', 'F', '1980-09-09', 59364),
+  (19, 'Theodore', 'Woodrow', 'Cains', 'M', '1960-04-29', 57406),
+  (20, 'Marhta', 'Anita', 'Norsister', 'F', '1969-06-25', 55544),
+  (21, 'Arturo', 'Dirk', 'Kindread', 'M', '1993-05-14', 57767),
+  (22, 'Migdalia', 'Arlean', 'Spykings', 'F', '1974-02-07', 54160),
+  (23, 'Lianne', 'Lorna', 'Gresty', 'F', '1952-11-19', 57963),
+  (24, 'Lavonna', 'Louanne', 'Meagher', 'F', '1993-05-18', 62056),
+  (25, 'Woodrow', 'Cedrick', 'Ridgedell', 'M', '1958-07-29', 64900);
+COMMIT;